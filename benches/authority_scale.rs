@@ -0,0 +1,120 @@
+//! Costs of the authority list operations that grow with the authority size. Run it by
+//! `cargo bench --bench authority_scale`, it prints the average time of each operation and the
+//! encoded size of a proof at each authority size.
+
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use bit_vec::BitVec;
+use bytes::Bytes;
+use overlord::types::{
+    Address, AggregatedSignature, Hash, Height, Node, Proof, QcKind, Round, Signature,
+};
+use overlord::{extract_voters, get_leader, Crypto};
+use rand::random;
+
+const AUTHORITY_SIZES: [usize; 5] = [4, 64, 256, 1024, 4096];
+const ITERATIONS: u32 = 100;
+
+struct NoopCrypto;
+
+impl Crypto for NoopCrypto {
+    fn hash(&self, msg: Bytes) -> Hash {
+        Hash::from(msg)
+    }
+
+    fn sign(&self, hash: Hash) -> Result<Signature, Box<dyn Error + Send>> {
+        Ok(Signature::from(hash.into_bytes()))
+    }
+
+    fn aggregate_signatures(
+        &self,
+        _signatures: Vec<Signature>,
+        _voters: Vec<Address>,
+    ) -> Result<Signature, Box<dyn Error + Send>> {
+        Ok(Signature::new())
+    }
+
+    fn verify_signature(
+        &self,
+        _signature: Signature,
+        _hash: Hash,
+        _voter: Address,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        Ok(())
+    }
+
+    fn verify_aggregated_signature(
+        &self,
+        _aggregate_signature: Signature,
+        _msg_hash: Hash,
+        _voters: Vec<Address>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        Ok(())
+    }
+}
+
+fn gen_authority_list(size: usize) -> Vec<Node> {
+    (0..size)
+        .map(|_| {
+            Node::new(Address::from(
+                (0..32).map(|_| random::<u8>()).collect::<Vec<_>>(),
+            ))
+        })
+        .collect()
+}
+
+fn average<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    println!(
+        "{:>8} {:>14} {:>14} {:>14} {:>14} {:>12}",
+        "size", "get_leader", "extract_voters", "vote_weight", "verify_proof", "proof_bytes"
+    );
+
+    for size in AUTHORITY_SIZES {
+        let authority_list = gen_authority_list(size);
+        let bitmap = Bytes::from(BitVec::from_elem(size, true).to_bytes());
+        let proof = Proof {
+            height: Height(1),
+            round: Round(0),
+            block_hash: Hash::from(vec![0u8; 32]),
+            signature: AggregatedSignature {
+                signature: Signature::from(vec![0u8; 96]),
+                address_bitmap: bitmap.clone(),
+                kind: QcKind::Aggregated,
+            },
+            extensions: None,
+            vrf: None,
+        };
+
+        let leader = average(|| {
+            get_leader(Height(1), Round(0), authority_list.clone()).unwrap();
+        });
+        let voters = average(|| {
+            extract_voters(&mut authority_list.clone(), &bitmap).unwrap();
+        });
+        let weight = average(|| {
+            proof.signature.vote_weight(&authority_list).unwrap();
+        });
+        let verify = average(|| {
+            proof.verify(&authority_list, &NoopCrypto).unwrap();
+        });
+
+        println!(
+            "{:>8} {:>14?} {:>14?} {:>14?} {:>14?} {:>12}",
+            size,
+            leader,
+            voters,
+            weight,
+            verify,
+            alloy_rlp::encode(&proof).len()
+        );
+    }
+}
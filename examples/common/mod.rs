@@ -0,0 +1,305 @@
+//! The pieces shared by the examples: a trivial block type, a file wal, and an in-process network
+//! which runs a chain of overlord nodes until every node commits a target height.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use creep::Context;
+use futures::channel::mpsc::{unbounded, UnboundedSender};
+use futures::StreamExt;
+use hummer::coding::hex_encode;
+use serde::{Deserialize, Serialize};
+
+use overlord::error::{ConsensusError, ErrorReport, Severity};
+use overlord::types::{
+    Address, Commit, Hash, Height, Node, OverlordMsg, Round, Status, ViewChangeReason,
+};
+use overlord::{Consensus, Crypto, FileAuxStore, Overlord, OverlordHandler, Wal};
+
+/// The height interval as millisecond.
+pub const INTERVAL: u64 = 500;
+
+/// A trivial block, of which the payload is a counter of the proposer.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub height: Height,
+    pub proposer: Bytes,
+    pub payload: u64,
+}
+
+/// A wal which keeps the latest entry in a file. An entry is written to a temporary file which is
+/// renamed over the wal, so that a crash never leaves a torn entry.
+pub struct FileWal {
+    path: PathBuf,
+}
+
+impl FileWal {
+    pub fn new(path: PathBuf) -> Self {
+        FileWal { path }
+    }
+}
+
+#[async_trait]
+impl Wal for FileWal {
+    async fn save(&self, info: Bytes) -> Result<(), Box<dyn Error + Send>> {
+        let tmp = self.path.with_extension("tmp");
+        let write = || -> io::Result<()> {
+            let mut file = fs::File::create(&tmp)?;
+            file.write_all(&info)?;
+            file.sync_all()?;
+            fs::rename(&tmp, &self.path)
+        };
+        write().map_err(|e| Box::new(e) as Box<dyn Error + Send>)
+    }
+
+    async fn load(&self) -> Result<Option<Bytes>, Box<dyn Error + Send>> {
+        match fs::read(&self.path) {
+            Ok(info) => Ok(Some(Bytes::from(info))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+}
+
+/// The handlers of the nodes by address, which deliver the messages in process.
+#[derive(Default)]
+pub struct LocalNetwork {
+    handlers: RwLock<HashMap<Address, OverlordHandler<Block>>>,
+}
+
+impl LocalNetwork {
+    fn deliver(&self, to: &Address, msg: OverlordMsg<Block>) {
+        if let Some(handler) = self.handlers.read().unwrap().get(to) {
+            let _ = handler.send_msg(Context::new(), msg);
+        }
+    }
+}
+
+/// The application of a node, which produces the blocks, sends the messages through the local
+/// network and reports the commits.
+pub struct Adapter<C: Crypto> {
+    address: Address,
+    authority_list: Vec<Node>,
+    crypto: Arc<C>,
+    counter: AtomicU64,
+    network: Arc<LocalNetwork>,
+    commits: UnboundedSender<(Address, Height, Hash)>,
+}
+
+#[async_trait]
+impl<C: Crypto + Send + Sync + 'static> Consensus<Block> for Adapter<C> {
+    async fn get_block(
+        &self,
+        _ctx: Context,
+        height: Height,
+    ) -> Result<(Block, Hash), Box<dyn Error + Send>> {
+        let block = Block {
+            height,
+            proposer: self.address.clone().into(),
+            payload: self.counter.fetch_add(1, Ordering::Relaxed),
+        };
+        let encode = bcs::to_bytes(&block)
+            .map_err(|e| Box::new(ConsensusError::Other(e.to_string())) as Box<dyn Error + Send>)?;
+        Ok((block, self.crypto.hash(Bytes::from(encode))))
+    }
+
+    async fn check_block(
+        &self,
+        _ctx: Context,
+        height: Height,
+        _hash: Hash,
+        block: Block,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        if block.height != height {
+            return Err(Box::new(ConsensusError::Other(format!(
+                "block of height {} proposed at height {}",
+                block.height, height
+            ))));
+        }
+        Ok(())
+    }
+
+    async fn commit(
+        &self,
+        _ctx: Context,
+        height: Height,
+        commit: Commit<Block>,
+    ) -> Result<Status, Box<dyn Error + Send>> {
+        let _ = self.commits.unbounded_send((
+            self.address.clone(),
+            commit.height,
+            commit.proof.block_hash.clone(),
+        ));
+        Ok(Status {
+            height: height + 1,
+            interval: Some(INTERVAL),
+            timer_config: None,
+            vrf_seed: None,
+            next_authority_list: None,
+            proof: None,
+            authority_list: self.authority_list.clone(),
+        })
+    }
+
+    async fn get_authority_list(
+        &self,
+        _ctx: Context,
+        _height: Height,
+    ) -> Result<Vec<Node>, Box<dyn Error + Send>> {
+        Ok(self.authority_list.clone())
+    }
+
+    async fn broadcast_to_other(
+        &self,
+        _ctx: Context,
+        msg: OverlordMsg<Block>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        for node in self.authority_list.iter() {
+            if node.address != self.address {
+                self.network.deliver(&node.address, msg.clone());
+            }
+        }
+        Ok(())
+    }
+
+    async fn transmit_to_relayer(
+        &self,
+        _ctx: Context,
+        addr: Address,
+        msg: OverlordMsg<Block>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.network.deliver(&addr, msg);
+        Ok(())
+    }
+
+    fn report_error(&self, _ctx: Context, err: ConsensusError) {
+        eprintln!("node {} error: {}", hex_encode(&self.address), err);
+    }
+
+    fn report_error_detail(&self, _ctx: Context, err: ConsensusError, report: ErrorReport) {
+        if report.severity == Severity::Ignorable {
+            return;
+        }
+        eprintln!(
+            "node {} {} error at height {}, round {}, step {}: {}",
+            hex_encode(&self.address),
+            report.severity,
+            report.height,
+            report.round,
+            report.step,
+            err
+        );
+    }
+
+    fn report_view_change(
+        &self,
+        _ctx: Context,
+        height: Height,
+        round: Round,
+        reason: ViewChangeReason,
+    ) {
+        println!(
+            "node {} view change at height {}, round {}: {}",
+            hex_encode(&self.address),
+            height,
+            round,
+            reason
+        );
+    }
+}
+
+/// Run a chain of the nodes with the given addresses and cryptos until every node commits the
+/// target height, then shut the nodes down. The wal and the auxiliary store of each node are kept
+/// in the given directory.
+pub async fn run_chain<C: Crypto + Send + Sync + 'static>(
+    nodes: Vec<(Address, C)>,
+    target: Height,
+    dir: &Path,
+) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(dir)?;
+    let authority_list = nodes
+        .iter()
+        .map(|(address, _)| Node::new(address.clone()))
+        .collect::<Vec<_>>();
+    let network = Arc::new(LocalNetwork::default());
+    let (commit_tx, mut commit_rx) = unbounded();
+
+    let mut handlers = Vec::new();
+    let mut tasks = Vec::new();
+    for (index, (address, crypto)) in nodes.into_iter().enumerate() {
+        let crypto = Arc::new(crypto);
+        let adapter = Arc::new(Adapter {
+            address: address.clone(),
+            authority_list: authority_list.clone(),
+            crypto: Arc::clone(&crypto),
+            counter: AtomicU64::new(0),
+            network: Arc::clone(&network),
+            commits: commit_tx.clone(),
+        });
+        let wal = Arc::new(FileWal::new(dir.join(format!("node-{}.wal", index))));
+        let aux_store = Arc::new(FileAuxStore::open(dir.join(format!("node-{}.aux", index)))?);
+        let overlord =
+            Overlord::new(address.clone(), adapter, crypto, wal).with_aux_store(aux_store);
+
+        let handler = overlord.get_handler();
+        network
+            .handlers
+            .write()
+            .unwrap()
+            .insert(address, handler.clone());
+        handlers.push(handler);
+
+        let list = authority_list.clone();
+        tasks.push(tokio::spawn(async move {
+            overlord.run(Height(1), INTERVAL, list, None).await
+        }));
+    }
+    drop(commit_tx);
+
+    // Start the first height once every node is reachable.
+    for handler in handlers.iter() {
+        handler.send_msg(
+            Context::new(),
+            OverlordMsg::RichStatus(Status {
+                height: Height(1),
+                interval: Some(INTERVAL),
+                timer_config: None,
+                vrf_seed: None,
+                next_authority_list: None,
+                proof: None,
+                authority_list: authority_list.clone(),
+            }),
+        )?;
+    }
+
+    let mut committed: HashMap<Height, (Hash, usize)> = HashMap::new();
+    while let Some((address, height, hash)) = commit_rx.next().await {
+        let (first, count) = committed.entry(height).or_insert((hash.clone(), 0));
+        assert_eq!(*first, hash, "conflicting commits at height {}", height);
+        *count += 1;
+        println!(
+            "node {} commits height {}, block {}",
+            hex_encode(&address),
+            height,
+            hex_encode(&hash)
+        );
+        if height >= target && *count == handlers.len() {
+            break;
+        }
+    }
+
+    for handler in handlers.iter() {
+        handler.shutdown().await?;
+    }
+    for task in tasks.into_iter() {
+        task.await??;
+    }
+    Ok(())
+}
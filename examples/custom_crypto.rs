@@ -0,0 +1,181 @@
+//! Plug a custom `Crypto` into overlord. The signature of a node is a keyed blake2b MAC over the
+//! message hash with the secret of the node, and the verifier looks the secret up in a registry of
+//! the validators. It suits a permissioned chain whose validators share their keys with a trusted
+//! verifier, and shows the contract every `Crypto` must keep:
+//!
+//! * `sign` binds the signature to the signer, so a signature of another voter is rejected;
+//! * `aggregate_signatures` is independent of the order of the votes, since overlord collects them
+//!   in arrival order;
+//! * `verify_aggregated_signature` accepts exactly the voters of the aggregation.
+//!
+//! ```text
+//! cargo run --example custom_crypto
+//! ```
+
+mod common;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+
+use blake2b_simd::{blake2b, Params};
+use bytes::Bytes;
+
+use overlord::error::ConsensusError;
+use overlord::types::{Address, Hash, Height, Signature};
+use overlord::Crypto;
+
+const SIGNATURE_LEN: usize = 32;
+
+/// The secrets of the validators by address.
+type Registry = Arc<HashMap<Address, [u8; 32]>>;
+
+struct MacCrypto {
+    address: Address,
+    registry: Registry,
+}
+
+impl MacCrypto {
+    fn mac(&self, voter: &Address, hash: &Hash) -> Result<Signature, Box<dyn Error + Send>> {
+        let secret = self.registry.get(voter).ok_or_else(|| {
+            Box::new(ConsensusError::CryptoErr(format!(
+                "unknown voter {:?}",
+                voter
+            ))) as Box<dyn Error + Send>
+        })?;
+        let mac = Params::new()
+            .hash_length(SIGNATURE_LEN)
+            .key(secret)
+            .hash(hash);
+        Ok(Signature::from(mac.as_bytes().to_vec()))
+    }
+}
+
+fn crypto_err(msg: &str) -> Box<dyn Error + Send> {
+    Box::new(ConsensusError::CryptoErr(msg.to_string()))
+}
+
+impl Crypto for MacCrypto {
+    fn hash(&self, msg: Bytes) -> Hash {
+        Hash::from(blake2b(&msg).as_bytes().to_vec())
+    }
+
+    fn sign(&self, hash: Hash) -> Result<Signature, Box<dyn Error + Send>> {
+        self.mac(&self.address, &hash)
+    }
+
+    /// The aggregated signature is the concatenation of the signatures sorted by the voters.
+    fn aggregate_signatures(
+        &self,
+        signatures: Vec<Signature>,
+        voters: Vec<Address>,
+    ) -> Result<Signature, Box<dyn Error + Send>> {
+        if signatures.len() != voters.len() {
+            return Err(crypto_err("signatures and voters mismatch"));
+        }
+        let mut pairs = voters.into_iter().zip(signatures).collect::<Vec<_>>();
+        pairs.sort();
+        let signatures = pairs
+            .into_iter()
+            .flat_map(|(_, sig)| sig.to_vec())
+            .collect::<Vec<_>>();
+        Ok(Signature::from(signatures))
+    }
+
+    fn verify_signature(
+        &self,
+        signature: Signature,
+        hash: Hash,
+        voter: Address,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        if signature != self.mac(&voter, &hash)? {
+            return Err(crypto_err("invalid signature"));
+        }
+        Ok(())
+    }
+
+    fn verify_aggregated_signature(
+        &self,
+        aggregated_signature: Signature,
+        hash: Hash,
+        mut voters: Vec<Address>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        if aggregated_signature.len() != voters.len() * SIGNATURE_LEN {
+            return Err(crypto_err("invalid aggregated signature length"));
+        }
+        voters.sort();
+        for (voter, signature) in voters
+            .iter()
+            .zip(aggregated_signature.chunks(SIGNATURE_LEN))
+        {
+            if signature != self.mac(voter, &hash)?.as_ref() {
+                return Err(crypto_err("invalid aggregated signature"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let addresses = (0..4u8)
+        .map(|i| Address::from(vec![i; 20]))
+        .collect::<Vec<_>>();
+    let registry: Registry = Arc::new(
+        addresses
+            .iter()
+            .enumerate()
+            .map(|(i, address)| (address.clone(), [i as u8 + 0x10; 32]))
+            .collect(),
+    );
+    let crypto_of = |address: &Address| MacCrypto {
+        address: address.clone(),
+        registry: Arc::clone(&registry),
+    };
+
+    // A signature verifies as its signer only.
+    let (alice, bob) = (crypto_of(&addresses[0]), crypto_of(&addresses[1]));
+    let hash = alice.hash(Bytes::from_static(b"block"));
+    let signature = alice.sign(hash.clone()).map_err(|e| e.to_string())?;
+    assert!(bob
+        .verify_signature(signature.clone(), hash.clone(), addresses[0].clone())
+        .is_ok());
+    assert!(bob
+        .verify_signature(signature.clone(), hash.clone(), addresses[1].clone())
+        .is_err());
+
+    // An aggregation does not depend on the order of the votes, and carries its voters only.
+    let signatures = addresses
+        .iter()
+        .map(|address| crypto_of(address).sign(hash.clone()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let aggregated = alice
+        .aggregate_signatures(signatures.clone(), addresses.clone())
+        .map_err(|e| e.to_string())?;
+    let reversed = alice
+        .aggregate_signatures(
+            signatures.into_iter().rev().collect(),
+            addresses.iter().rev().cloned().collect(),
+        )
+        .map_err(|e| e.to_string())?;
+    assert_eq!(aggregated, reversed);
+    assert!(bob
+        .verify_aggregated_signature(aggregated.clone(), hash.clone(), addresses.clone())
+        .is_ok());
+    assert!(bob
+        .verify_aggregated_signature(aggregated, hash, addresses[..3].to_vec())
+        .is_err());
+    println!("the custom crypto rejects the forged signatures");
+
+    let nodes = addresses
+        .iter()
+        .map(|address| (address.clone(), crypto_of(address)))
+        .collect::<Vec<_>>();
+    let dir = std::env::temp_dir().join(format!("overlord-custom-crypto-{}", std::process::id()));
+
+    common::run_chain(nodes, Height(3), &dir).await?;
+    std::fs::remove_dir_all(&dir)?;
+    println!("every node committed height 3 with the custom crypto");
+    Ok(())
+}
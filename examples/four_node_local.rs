@@ -0,0 +1,104 @@
+//! Run a chain of four overlord nodes in one process, which exchange the messages through
+//! in-process channels and keep their wal in files, until every node commits height 5.
+//!
+//! ```text
+//! cargo run --example four_node_local
+//! ```
+
+mod common;
+
+use std::error::Error;
+
+use blake2b_simd::blake2b;
+use bytes::Bytes;
+
+use overlord::error::ConsensusError;
+use overlord::types::{Address, Hash, Height, Signature};
+use overlord::Crypto;
+
+/// A crypto of which the signature is the hash of the signer and the message. It shows the shape
+/// of the `Crypto` trait only: anyone can forge such a signature, see the `custom_crypto` example
+/// for signatures bound to a secret.
+struct PlainCrypto {
+    address: Address,
+}
+
+fn sign_as(address: &Address, hash: &Hash) -> Signature {
+    let mut msg = address.to_vec();
+    msg.extend_from_slice(hash);
+    Signature::from(blake2b(&msg).as_bytes().to_vec())
+}
+
+impl Crypto for PlainCrypto {
+    fn hash(&self, msg: Bytes) -> Hash {
+        Hash::from(blake2b(&msg).as_bytes().to_vec())
+    }
+
+    fn sign(&self, hash: Hash) -> Result<Signature, Box<dyn Error + Send>> {
+        Ok(sign_as(&self.address, &hash))
+    }
+
+    /// The aggregated signature is the concatenation of the signatures in the order of the voters.
+    fn aggregate_signatures(
+        &self,
+        signatures: Vec<Signature>,
+        voters: Vec<Address>,
+    ) -> Result<Signature, Box<dyn Error + Send>> {
+        let mut pairs = voters.into_iter().zip(signatures).collect::<Vec<_>>();
+        pairs.sort();
+        let signatures = pairs
+            .into_iter()
+            .flat_map(|(_, sig)| sig.to_vec())
+            .collect::<Vec<_>>();
+        Ok(Signature::from(signatures))
+    }
+
+    fn verify_signature(
+        &self,
+        signature: Signature,
+        hash: Hash,
+        voter: Address,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        if signature != sign_as(&voter, &hash) {
+            return Err(Box::new(ConsensusError::CryptoErr(
+                "invalid signature".to_string(),
+            )));
+        }
+        Ok(())
+    }
+
+    fn verify_aggregated_signature(
+        &self,
+        aggregated_signature: Signature,
+        hash: Hash,
+        mut voters: Vec<Address>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        voters.sort();
+        let expect = voters
+            .iter()
+            .flat_map(|voter| sign_as(voter, &hash).to_vec())
+            .collect::<Vec<_>>();
+        if aggregated_signature != Signature::from(expect) {
+            return Err(Box::new(ConsensusError::CryptoErr(
+                "invalid aggregated signature".to_string(),
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let nodes = (0..4u8)
+        .map(|i| {
+            let address = Address::from(vec![i; 20]);
+            (address.clone(), PlainCrypto { address })
+        })
+        .collect::<Vec<_>>();
+    let dir = std::env::temp_dir().join(format!("overlord-four-node-{}", std::process::id()));
+
+    common::run_chain(nodes, Height(5), &dir).await?;
+    std::fs::remove_dir_all(&dir)?;
+    println!("every node committed height 5");
+    Ok(())
+}
@@ -0,0 +1,58 @@
+//! Inspect the wal of a node, such as one that fails to start with `LoadWalErr`. It prints the
+//! height, round, step and lock of each record of the wal file in JSON, and with `--strip` it
+//! truncates the file before the first corrupted record, which is usually a record torn by a crash.
+//!
+//! ```text
+//! cargo run --example overlord-wal -- <wal file> [--strip]
+//! ```
+
+use std::env;
+use std::fs;
+use std::process;
+
+use overlord::wal::repair;
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let strip = args.iter().any(|arg| arg == "--strip");
+    let path = match args.iter().find(|arg| !arg.starts_with("--")) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: overlord-wal <wal file> [--strip]");
+            process::exit(2);
+        }
+    };
+
+    let data = fs::read(path).unwrap_or_else(|e| {
+        eprintln!("read {} error {}", path, e);
+        process::exit(1);
+    });
+    let repaired = repair(&data);
+    for report in repaired.reports.iter() {
+        println!("{}", serde_json::to_string(report).unwrap());
+    }
+
+    let error = match repaired.error {
+        Some(error) => error,
+        None => return,
+    };
+    eprintln!(
+        "corrupted record at offset {} of {} bytes: {}",
+        repaired.valid_len,
+        data.len(),
+        error
+    );
+    if !strip {
+        process::exit(1);
+    }
+
+    fs::write(path, &data[..repaired.valid_len]).unwrap_or_else(|e| {
+        eprintln!("write {} error {}", path, e);
+        process::exit(1);
+    });
+    eprintln!(
+        "stripped {} bytes, {} records are kept",
+        data.len() - repaired.valid_len,
+        repaired.reports.len()
+    );
+}
@@ -17,7 +17,9 @@ use rand::random;
 use serde::{Deserialize, Serialize};
 
 use overlord::error::ConsensusError;
-use overlord::types::{Commit, Hash, Node, OverlordMsg, Status, ViewChangeReason};
+use overlord::types::{
+    Address, Commit, Hash, Height, Node, OverlordMsg, Round, Signature, Status, ViewChangeReason,
+};
 use overlord::{Consensus, Crypto, DurationConfig, Overlord, OverlordHandler, Wal};
 
 lazy_static! {
@@ -71,46 +73,46 @@ impl Wal for MockWal {
 }
 
 struct MockCrypto {
-    name: Bytes,
+    name: Address,
 }
 
 impl MockCrypto {
-    fn new(name: Bytes) -> Self {
+    fn new(name: Address) -> Self {
         MockCrypto { name }
     }
 }
 
 impl Crypto for MockCrypto {
-    fn hash(&self, speech: Bytes) -> Bytes {
+    fn hash(&self, speech: Bytes) -> Hash {
         hash(&speech)
     }
 
-    fn sign(&self, _hash: Bytes) -> Result<Bytes, Box<dyn Error + Send>> {
-        Ok(self.name.clone())
+    fn sign(&self, _hash: Hash) -> Result<Signature, Box<dyn Error + Send>> {
+        Ok(Signature::from(self.name.clone().into_bytes()))
     }
 
     fn aggregate_signatures(
         &self,
-        _signatures: Vec<Bytes>,
-        _speaker: Vec<Bytes>,
-    ) -> Result<Bytes, Box<dyn Error + Send>> {
-        Ok(Bytes::new())
+        _signatures: Vec<Signature>,
+        _speaker: Vec<Address>,
+    ) -> Result<Signature, Box<dyn Error + Send>> {
+        Ok(Signature::new())
     }
 
     fn verify_signature(
         &self,
-        _signature: Bytes,
-        _hash: Bytes,
-        _voter: Bytes,
+        _signature: Signature,
+        _hash: Hash,
+        _voter: Address,
     ) -> Result<(), Box<dyn Error + Send>> {
         Ok(())
     }
 
     fn verify_aggregated_signature(
         &self,
-        _aggregated_signature: Bytes,
-        _hash: Bytes,
-        _voters: Vec<Bytes>,
+        _aggregated_signature: Signature,
+        _hash: Hash,
+        _voters: Vec<Address>,
     ) -> Result<(), Box<dyn Error + Send>> {
         Ok(())
     }
@@ -118,17 +120,17 @@ impl Crypto for MockCrypto {
 
 struct Brain {
     speaker_list: Vec<Node>,
-    talk_to: HashMap<Bytes, Sender<OverlordMsg<Speech>>>,
+    talk_to: HashMap<Address, Sender<OverlordMsg<Speech>>>,
     hearing: Receiver<OverlordMsg<Speech>>,
-    consensus_speech: Arc<Mutex<HashMap<u64, Bytes>>>,
+    consensus_speech: Arc<Mutex<HashMap<Height, Bytes>>>,
 }
 
 impl Brain {
     fn new(
         speaker_list: Vec<Node>,
-        talk_to: HashMap<Bytes, Sender<OverlordMsg<Speech>>>,
+        talk_to: HashMap<Address, Sender<OverlordMsg<Speech>>>,
         hearing: Receiver<OverlordMsg<Speech>>,
-        consensus_speech: Arc<Mutex<HashMap<u64, Bytes>>>,
+        consensus_speech: Arc<Mutex<HashMap<Height, Bytes>>>,
     ) -> Brain {
         Brain {
             speaker_list,
@@ -144,7 +146,7 @@ impl Consensus<Speech> for Brain {
     async fn get_block(
         &self,
         _ctx: Context,
-        _height: u64,
+        _height: Height,
     ) -> Result<(Speech, Hash), Box<dyn Error + Send>> {
         let thought = gen_random_bytes();
         Ok((Speech::from(thought.clone()), hash(&thought)))
@@ -153,7 +155,7 @@ impl Consensus<Speech> for Brain {
     async fn check_block(
         &self,
         _ctx: Context,
-        _height: u64,
+        _height: Height,
         _hash: Hash,
         _speech: Speech,
     ) -> Result<(), Box<dyn Error + Send>> {
@@ -163,7 +165,7 @@ impl Consensus<Speech> for Brain {
     async fn commit(
         &self,
         _ctx: Context,
-        height: u64,
+        height: Height,
         commit: Commit<Speech>,
     ) -> Result<Status, Box<dyn Error + Send>> {
         let mut speeches = self.consensus_speech.lock().unwrap();
@@ -182,6 +184,9 @@ impl Consensus<Speech> for Brain {
             height: height + 1,
             interval: Some(SPEECH_INTERVAL),
             timer_config: None,
+            vrf_seed: None,
+            next_authority_list: None,
+            proof: None,
             authority_list: self.speaker_list.clone(),
         })
     }
@@ -189,7 +194,7 @@ impl Consensus<Speech> for Brain {
     async fn get_authority_list(
         &self,
         _ctx: Context,
-        _height: u64,
+        _height: Height,
     ) -> Result<Vec<Node>, Box<dyn Error + Send>> {
         Ok(self.speaker_list.clone())
     }
@@ -208,7 +213,7 @@ impl Consensus<Speech> for Brain {
     async fn transmit_to_relayer(
         &self,
         _ctx: Context,
-        name: Bytes,
+        name: Address,
         words: OverlordMsg<Speech>,
     ) -> Result<(), Box<dyn Error + Send>> {
         self.talk_to.get(&name).unwrap().send(words).unwrap();
@@ -220,8 +225,8 @@ impl Consensus<Speech> for Brain {
     fn report_view_change(
         &self,
         _ctx: Context,
-        _height: u64,
-        _round: u64,
+        _height: Height,
+        _round: Round,
         _reason: ViewChangeReason,
     ) {
     }
@@ -235,11 +240,11 @@ struct Speaker {
 
 impl Speaker {
     fn new(
-        name: Bytes,
+        name: Address,
         speaker_list: Vec<Node>,
-        talk_to: HashMap<Bytes, Sender<OverlordMsg<Speech>>>,
+        talk_to: HashMap<Address, Sender<OverlordMsg<Speech>>>,
         hearing: Receiver<OverlordMsg<Speech>>,
-        consensus_speech: Arc<Mutex<HashMap<u64, Bytes>>>,
+        consensus_speech: Arc<Mutex<HashMap<Height, Bytes>>>,
     ) -> Self {
         let crypto = MockCrypto::new(name.clone());
         let brain = Arc::new(Brain::new(
@@ -260,9 +265,12 @@ impl Speaker {
             .send_msg(
                 Context::new(),
                 OverlordMsg::RichStatus(Status {
-                    height: 1,
+                    height: Height(1),
                     interval: Some(SPEECH_INTERVAL),
                     timer_config: None,
+                    vrf_seed: None,
+                    next_authority_list: None,
+                    proof: None,
                     authority_list: speaker_list,
                 }),
             )
@@ -307,13 +315,33 @@ impl Speaker {
                             .send_msg(Context::new(), OverlordMsg::SignedChoke(choke))
                             .unwrap();
                     }
+                    OverlordMsg::SignedCompactProposal(proposal) => {
+                        handler
+                            .send_msg(Context::new(), OverlordMsg::SignedCompactProposal(proposal))
+                            .unwrap();
+                    }
+                    OverlordMsg::BlockPart(part) => {
+                        handler
+                            .send_msg(Context::new(), OverlordMsg::BlockPart(part))
+                            .unwrap();
+                    }
+                    OverlordMsg::PullQC(pull) => {
+                        handler
+                            .send_msg(Context::new(), OverlordMsg::PullQC(pull))
+                            .unwrap();
+                    }
+                    OverlordMsg::RoundSummary(summary) => {
+                        handler
+                            .send_msg(Context::new(), OverlordMsg::RoundSummary(summary))
+                            .unwrap();
+                    }
                     _ => {}
                 }
             }
         });
 
         self.overlord
-            .run(0, interval, speaker_list, timer_config)
+            .run(Height(0), interval, speaker_list, timer_config)
             .await
             .unwrap();
 
@@ -324,10 +352,10 @@ impl Speaker {
 #[tokio::main]
 async fn main() {
     let speaker_list: Vec<Node> = (0..SPEAKER_NUM)
-        .map(|_| Node::new(gen_random_bytes()))
+        .map(|_| Node::new(gen_random_bytes().into()))
         .collect();
     let channels: Vec<Channel> = (0..SPEAKER_NUM).map(|_| unbounded()).collect();
-    let hearings: HashMap<Bytes, Receiver<OverlordMsg<Speech>>> = speaker_list
+    let hearings: HashMap<Address, Receiver<OverlordMsg<Speech>>> = speaker_list
         .iter()
         .map(|node| node.address.clone())
         .zip(channels.iter().map(|(_, receiver)| receiver.clone()))
@@ -339,7 +367,7 @@ async fn main() {
 
     for speaker in speaker_list.iter() {
         let name = speaker.address.clone();
-        let mut talk_to: HashMap<Bytes, Sender<OverlordMsg<Speech>>> = speaker_list_clone
+        let mut talk_to: HashMap<Address, Sender<OverlordMsg<Speech>>> = speaker_list_clone
             .iter()
             .map(|speaker| speaker.address.clone())
             .zip(channels.iter().map(|(sender, _)| sender.clone()))
@@ -351,7 +379,7 @@ async fn main() {
             speaker_list_clone.clone(),
             talk_to,
             hearings.get(&name).unwrap().clone(),
-            Arc::<Mutex<HashMap<u64, Bytes>>>::clone(&consensus_speech),
+            Arc::<Mutex<HashMap<Height, Bytes>>>::clone(&consensus_speech),
         ));
 
         let list = auth_list.clone();
@@ -371,10 +399,10 @@ fn gen_random_bytes() -> Bytes {
     Bytes::from(vec)
 }
 
-fn hash(bytes: &Bytes) -> Bytes {
+fn hash(bytes: &Bytes) -> Hash {
     let mut out = [0u8; 32];
     out.copy_from_slice(&HASHER_INST.digest(bytes));
-    BytesMut::from(&out[..]).freeze()
+    Hash::from(BytesMut::from(&out[..]).freeze())
 }
 
 fn timer_config() -> Option<DurationConfig> {
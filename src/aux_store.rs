@@ -0,0 +1,311 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use parking_lot::{Mutex, RwLock};
+
+use crate::error::ConsensusError;
+use crate::types::Height;
+use crate::ConsensusResult;
+
+/// An operation of an atomic batch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuxOp {
+    /// Put the value of the key.
+    Put(Bytes, Bytes),
+    /// Delete the key.
+    Delete(Bytes),
+}
+
+/// A small key-value store for the auxiliary persistence beyond the single record wal, such as
+/// the votes signed by self and the proofs of the recent heights. The records are small and few,
+/// so the store is accessed synchronously.
+pub trait AuxStore: Send + Sync {
+    /// Get the value of the key.
+    fn get(&self, key: &[u8]) -> Result<Option<Bytes>, Box<dyn Error + Send>>;
+
+    /// Put the value of the key.
+    fn put(&self, key: Bytes, value: Bytes) -> Result<(), Box<dyn Error + Send>> {
+        self.write_batch(vec![AuxOp::Put(key, value)])
+    }
+
+    /// Delete the key.
+    fn delete(&self, key: Bytes) -> Result<(), Box<dyn Error + Send>> {
+        self.write_batch(vec![AuxOp::Delete(key)])
+    }
+
+    /// Apply the operations atomically, either all or none of them are persisted.
+    fn write_batch(&self, batch: Vec<AuxOp>) -> Result<(), Box<dyn Error + Send>>;
+
+    /// Whether the items survive a restart. The double sign guard relies on it, and a warning is
+    /// logged on start if it is not. Return `true` by default.
+    fn is_durable(&self) -> bool {
+        true
+    }
+}
+
+/// An in-memory auxiliary store, which is the default. Nothing survives a restart.
+#[derive(Debug, Default)]
+pub struct MemoryAuxStore {
+    items: RwLock<BTreeMap<Bytes, Bytes>>,
+}
+
+impl MemoryAuxStore {
+    /// Create an empty in-memory store.
+    pub fn new() -> Self {
+        MemoryAuxStore::default()
+    }
+}
+
+impl AuxStore for MemoryAuxStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Bytes>, Box<dyn Error + Send>> {
+        Ok(self.items.read().get(key).cloned())
+    }
+
+    fn write_batch(&self, batch: Vec<AuxOp>) -> Result<(), Box<dyn Error + Send>> {
+        apply_batch(&mut self.items.write(), batch);
+        Ok(())
+    }
+
+    fn is_durable(&self) -> bool {
+        false
+    }
+}
+
+/// A file-backed auxiliary store. All the items are kept in memory and the whole file is
+/// rewritten on each batch, through a temporary file which is renamed over the store file, so a
+/// batch is atomic across crashes.
+#[derive(Debug)]
+pub struct FileAuxStore {
+    path: PathBuf,
+    items: Mutex<BTreeMap<Bytes, Bytes>>,
+}
+
+impl FileAuxStore {
+    /// Open the store of the file path, which is created on the first write if it does not exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let items = match fs::read(&path) {
+            Ok(raw) => bcs::from_bytes::<Vec<(Vec<u8>, Vec<u8>)>>(&raw)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+                .into_iter()
+                .map(|(key, value)| (Bytes::from(key), Bytes::from(value)))
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e),
+        };
+
+        Ok(FileAuxStore {
+            path,
+            items: Mutex::new(items),
+        })
+    }
+
+    fn persist(&self, items: &BTreeMap<Bytes, Bytes>) -> io::Result<()> {
+        let encode = bcs::to_bytes(
+            &items
+                .iter()
+                .map(|(key, value)| (key.to_vec(), value.to_vec()))
+                .collect::<Vec<_>>(),
+        )
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&encode)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)
+    }
+}
+
+impl AuxStore for FileAuxStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Bytes>, Box<dyn Error + Send>> {
+        Ok(self.items.lock().get(key).cloned())
+    }
+
+    fn write_batch(&self, batch: Vec<AuxOp>) -> Result<(), Box<dyn Error + Send>> {
+        let mut items = self.items.lock();
+        let mut updated = items.clone();
+        apply_batch(&mut updated, batch);
+        self.persist(&updated)
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send>)?;
+        *items = updated;
+        Ok(())
+    }
+}
+
+fn apply_batch(items: &mut BTreeMap<Bytes, Bytes>, batch: Vec<AuxOp>) {
+    for op in batch.into_iter() {
+        match op {
+            AuxOp::Put(key, value) => {
+                items.insert(key, value);
+            }
+            AuxOp::Delete(key) => {
+                items.remove(&key);
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for HeightRecords {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HeightRecords")
+            .field("prefix", &self.prefix)
+            .field("heights", &self.heights)
+            .finish()
+    }
+}
+
+/// The records of a subsystem keyed by height in an auxiliary store. An index record lists the
+/// heights, which is updated in the same batch as the records, so that the records are found on
+/// restart.
+pub(crate) struct HeightRecords {
+    prefix: &'static str,
+    store: Arc<dyn AuxStore>,
+    heights: BTreeSet<Height>,
+}
+
+impl HeightRecords {
+    /// Open the records of the prefix, and load all of them.
+    pub(crate) fn open(
+        store: Arc<dyn AuxStore>,
+        prefix: &'static str,
+    ) -> ConsensusResult<(Self, Vec<(Height, Bytes)>)> {
+        let mut records = HeightRecords {
+            prefix,
+            store,
+            heights: BTreeSet::new(),
+        };
+        let heights = match records
+            .store
+            .get(&records.index_key())
+            .map_err(storage_err)?
+        {
+            Some(raw) => bcs::from_bytes::<BTreeSet<Height>>(&raw)
+                .map_err(|e| ConsensusError::StorageErr(format!("{} index {:?}", prefix, e)))?,
+            None => BTreeSet::new(),
+        };
+
+        let mut loaded = Vec::with_capacity(heights.len());
+        for height in heights.iter() {
+            if let Some(value) = records
+                .store
+                .get(&records.record_key(*height))
+                .map_err(storage_err)?
+            {
+                loaded.push((*height, value));
+            }
+        }
+        records.heights = heights;
+        Ok((records, loaded))
+    }
+
+    /// Put the records of the heights and delete the records of the others atomically.
+    pub(crate) fn update(
+        &mut self,
+        puts: Vec<(Height, Bytes)>,
+        deletes: Vec<Height>,
+    ) -> ConsensusResult<()> {
+        let mut heights = self.heights.clone();
+        let mut batch = Vec::with_capacity(puts.len() + deletes.len() + 1);
+        for (height, value) in puts.into_iter() {
+            heights.insert(height);
+            batch.push(AuxOp::Put(self.record_key(height), value));
+        }
+        for height in deletes.into_iter() {
+            if heights.remove(&height) {
+                batch.push(AuxOp::Delete(self.record_key(height)));
+            }
+        }
+        let index = bcs::to_bytes(&heights)
+            .map_err(|e| ConsensusError::StorageErr(format!("{} index {:?}", self.prefix, e)))?;
+        batch.push(AuxOp::Put(self.index_key(), Bytes::from(index)));
+
+        self.store.write_batch(batch).map_err(storage_err)?;
+        self.heights = heights;
+        Ok(())
+    }
+
+    /// The heights of the records lower than the given one.
+    pub(crate) fn heights_below(&self, till: Height) -> Vec<Height> {
+        self.heights.range(..till).copied().collect()
+    }
+
+    fn index_key(&self) -> Bytes {
+        Bytes::from(format!("overlord/{}/index", self.prefix))
+    }
+
+    fn record_key(&self, height: Height) -> Bytes {
+        let mut key = format!("overlord/{}/", self.prefix).into_bytes();
+        key.extend_from_slice(&height.0.to_be_bytes());
+        Bytes::from(key)
+    }
+}
+
+fn storage_err(e: Box<dyn Error + Send>) -> ConsensusError {
+    ConsensusError::AuxStoreErr {
+        context: "aux store",
+        source: e.into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+
+    use super::{AuxOp, AuxStore, FileAuxStore, HeightRecords, MemoryAuxStore};
+    use crate::types::Height;
+
+    #[test]
+    fn test_file_aux_store() {
+        let path = std::env::temp_dir().join(format!("overlord_aux_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let store = FileAuxStore::open(&path).unwrap();
+        store
+            .write_batch(vec![
+                AuxOp::Put(Bytes::from("a"), Bytes::from("1")),
+                AuxOp::Put(Bytes::from("b"), Bytes::from("2")),
+            ])
+            .unwrap();
+        store.delete(Bytes::from("a")).unwrap();
+
+        let store = FileAuxStore::open(&path).unwrap();
+        assert!(store.get(b"a").unwrap().is_none());
+        assert_eq!(store.get(b"b").unwrap(), Some(Bytes::from("2")));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_height_records() {
+        let store: Arc<dyn AuxStore> = Arc::new(MemoryAuxStore::new());
+        let (mut records, loaded) = HeightRecords::open(Arc::clone(&store), "test").unwrap();
+        assert!(loaded.is_empty());
+
+        records
+            .update(
+                vec![(Height(1), Bytes::from("1")), (Height(2), Bytes::from("2"))],
+                Vec::new(),
+            )
+            .unwrap();
+        records
+            .update(
+                vec![(Height(3), Bytes::from("3"))],
+                records.heights_below(Height(2)),
+            )
+            .unwrap();
+
+        let (_, loaded) = HeightRecords::open(store, "test").unwrap();
+        assert_eq!(
+            loaded,
+            vec![(Height(2), Bytes::from("2")), (Height(3), Bytes::from("3"))]
+        );
+    }
+}
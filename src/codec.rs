@@ -1,15 +1,129 @@
+use std::borrow::Cow;
+use std::cell::Cell;
 use std::convert::TryFrom;
 
-use alloy_rlp::{encode_list, Decodable, Encodable, Header};
-use bytes::BufMut;
+use alloy_rlp::{encode_list, Decodable, Encodable, Header, RlpEncodable};
+use bytes::{BufMut, Bytes};
+use serde::de::DeserializeOwned;
 
+use crate::error::{ConsensusError, DecodeLimit};
 use crate::smr::smr_types::Step;
 use crate::types::{
-    Address, AggregatedChoke, AggregatedVote, Commit, Hash, PoLC, Proof, Proposal, Signature,
-    SignedProposal, UpdateFrom, VoteType,
+    Address, AggregatedChoke, AggregatedSignature, AggregatedVote, Commit, Hash, Height, PoLC,
+    Proof, Proposal, QcKind, Round, Signature, SignedProposal, UpdateFrom, VoteType,
 };
 use crate::wal::{WalInfo, WalLock};
-use crate::Codec;
+use crate::{Codec, ConsensusResult};
+
+thread_local! {
+    /// The limits of the decoding in progress on this thread.
+    static LIMITS: Cell<DecodeScope> = const {
+        Cell::new(DecodeScope {
+            config: None,
+            exceeded: None,
+        })
+    };
+}
+
+/// The limits of a decoding, and the first limit it exceeds with the decoded length.
+#[derive(Clone, Copy)]
+struct DecodeScope {
+    config: Option<CodecConfig>,
+    exceeded: Option<(DecodeLimit, usize)>,
+}
+
+/// The size limits enforced while decoding the messages from the network, so that a malicious
+/// peer can not make a node allocate for arbitrarily long contents and voter lists. The limits
+/// only apply to the decoding through `CodecConfig::decode`, a plain `Decodable::decode` is not
+/// limited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CodecConfig {
+    /// The max bytes of the encoded content of a proposal or a commit.
+    pub max_content_bytes: usize,
+    /// The max count of the voters of an aggregated choke.
+    pub max_voters: usize,
+    /// The max bytes of the address bitmap of an aggregated signature.
+    pub max_bitmap_len: usize,
+}
+
+impl Default for CodecConfig {
+    fn default() -> Self {
+        CodecConfig {
+            max_content_bytes: 64 << 20,
+            max_voters: 1024,
+            max_bitmap_len: 128,
+        }
+    }
+}
+
+impl CodecConfig {
+    /// Decode an item with the limits enforced. Return `DecodeLimitErr` if the item exceeds a
+    /// limit, or `DecodeErr` if it is malformed.
+    pub fn decode<D: Decodable>(&self, mut buf: &[u8]) -> ConsensusResult<D> {
+        let scope = DecodeScope {
+            config: Some(*self),
+            exceeded: None,
+        };
+        let previous = LIMITS.with(|limits| limits.replace(scope));
+        let res = D::decode(&mut buf);
+        let scope = LIMITS.with(|limits| limits.replace(previous));
+
+        match (res, scope.exceeded) {
+            (_, Some((limit, len))) => Err(ConsensusError::DecodeLimitErr {
+                limit,
+                len,
+                max: self.max(limit),
+            }),
+            (Ok(item), None) => Ok(item),
+            (Err(e), None) => Err(ConsensusError::RlpErr(e)),
+        }
+    }
+
+    fn max(&self, limit: DecodeLimit) -> usize {
+        match limit {
+            DecodeLimit::ContentBytes => self.max_content_bytes,
+            DecodeLimit::Voters => self.max_voters,
+            DecodeLimit::BitmapLen => self.max_bitmap_len,
+        }
+    }
+}
+
+/// Check the length against the limit of the decoding in progress if any.
+fn check_limit(limit: DecodeLimit, len: usize) -> alloy_rlp::Result<()> {
+    LIMITS.with(|limits| {
+        let mut scope = limits.get();
+        match scope.config {
+            Some(config) if len > config.max(limit) => {
+                if scope.exceeded.is_none() {
+                    scope.exceeded = Some((limit, len));
+                    limits.set(scope);
+                }
+                Err(alloy_rlp::Error::Custom("Decode limit exceeded."))
+            }
+            _ => Ok(()),
+        }
+    })
+}
+
+/// Bind a payload to sign to the chain id, so that a signature of one deployment can not be
+/// replayed in another deployment sharing the keys. With an empty chain id the payload is signed
+/// as it is, which keeps the signatures of the nodes configured without a chain id.
+pub fn domain_payload(chain_id: &[u8], payload: Bytes) -> Bytes {
+    if chain_id.is_empty() {
+        return payload;
+    }
+    alloy_rlp::encode(DomainPayload {
+        chain_id: Bytes::copy_from_slice(chain_id),
+        payload,
+    })
+    .into()
+}
+
+#[derive(RlpEncodable)]
+struct DomainPayload {
+    chain_id: Bytes,
+    payload: Bytes,
+}
 
 impl Encodable for VoteType {
     fn encode(&self, out: &mut dyn BufMut) {
@@ -44,33 +158,107 @@ impl Decodable for VoteType {
     }
 }
 
+/// The flag byte of the snappy compressed frame.
+const SNAPPY_FLAG: u8 = 1;
+/// The frame shorter than this is never compressed.
+#[cfg(feature = "compression")]
+const COMPRESS_THRESHOLD: usize = 1024;
+
+/// Compress an encoded frame, which is a network message or the payload of a wal record, with
+/// snappy under the `compression` feature if it is large enough and the compression pays off. A
+/// plain frame is an RLP list, while the compressed frame is encoded as a byte string that starts
+/// with the flag byte of the compression, so that the decoder tells them apart by the header. The
+/// items themselves are always encoded plain, so that the signatures never depend on the feature.
+pub(crate) fn compress_frame(frame: Vec<u8>) -> Vec<u8> {
+    #[cfg(feature = "compression")]
+    if frame.len() >= COMPRESS_THRESHOLD {
+        if let Ok(compressed) = snap::raw::Encoder::new().compress_vec(&frame) {
+            if compressed.len() + 1 < frame.len() {
+                let mut flagged = Vec::with_capacity(compressed.len() + 1);
+                flagged.push(SNAPPY_FLAG);
+                flagged.extend_from_slice(&compressed);
+                return alloy_rlp::encode(Bytes::from(flagged));
+            }
+        }
+    }
+    frame
+}
+
+/// Decompress a frame of `compress_frame`, of which the decompressed length is checked against
+/// the given maximum before it is decompressed. A plain frame is returned as it is.
+#[cfg_attr(not(feature = "compression"), allow(unused_variables))]
+pub(crate) fn decompress_frame(frame: &[u8], max: Option<usize>) -> ConsensusResult<Cow<'_, [u8]>> {
+    let err = |msg: &'static str| ConsensusError::RlpErr(alloy_rlp::Error::Custom(msg));
+    let mut buf = frame;
+    if Header::decode(&mut &buf[..])
+        .map_err(ConsensusError::RlpErr)?
+        .list
+    {
+        return Ok(Cow::Borrowed(frame));
+    }
+
+    let flagged = Header::decode_bytes(&mut buf, false).map_err(ConsensusError::RlpErr)?;
+    match flagged.split_first() {
+        #[cfg(feature = "compression")]
+        Some((&SNAPPY_FLAG, compressed)) => {
+            let len = snap::raw::decompress_len(compressed)
+                .map_err(|_| err("Decompress frame error."))?;
+            if let Some(max) = max.filter(|max| len > *max) {
+                return Err(ConsensusError::DecodeLimitErr {
+                    limit: DecodeLimit::ContentBytes,
+                    len,
+                    max,
+                });
+            }
+            let plain = snap::raw::Decoder::new()
+                .decompress_vec(compressed)
+                .map_err(|_| err("Decompress frame error."))?;
+            Ok(Cow::Owned(plain))
+        }
+        #[cfg(not(feature = "compression"))]
+        Some((&SNAPPY_FLAG, _)) => Err(err("Compressed frame needs the compression feature.")),
+        _ => Err(err("Unknown frame compression.")),
+    }
+}
+
 impl<T: Codec> Encodable for Proposal<T> {
     fn encode(&self, out: &mut dyn BufMut) {
         let content = bcs::to_bytes(&self.content).unwrap();
 
+        // The VRF is a trailing item which is omitted when it is absent, so that the proposals
+        // without VRF keep the same encoding.
+        let has_locked = self.lock.is_some();
+        let mut enc: Vec<&dyn Encodable> = vec![
+            &has_locked,
+            &self.height,
+            &self.round,
+            &content,
+            &self.block_hash,
+        ];
         if let Some(polc) = &self.lock {
-            let enc: [&dyn Encodable; 7] = [
-                &true,
-                &self.height,
-                &self.round,
-                &content,
-                &self.block_hash,
-                polc,
-                &self.proposer,
-            ];
-            encode_list::<_, dyn Encodable>(&enc, out);
-        } else {
-            let enc: [&dyn Encodable; 6] = [
-                &false,
-                &self.height,
-                &self.round,
-                &content,
-                &self.block_hash,
-                &self.proposer,
-            ];
-            encode_list::<_, dyn Encodable>(&enc, out);
+            enc.push(polc);
+        }
+        enc.push(&self.proposer);
+        if let Some(vrf) = &self.vrf {
+            enc.push(vrf);
         }
+        encode_list::<_, dyn Encodable>(&enc, out);
+    }
+}
+
+/// Decode an optional item at the end of a list, which is omitted when it is absent.
+fn decode_trailing<D: Decodable>(payload: &mut &[u8]) -> alloy_rlp::Result<Option<D>> {
+    if payload.is_empty() {
+        return Ok(None);
     }
+    Ok(Some(D::decode(payload)?))
+}
+
+/// Decode the bcs encoded content of a proposal, a commit or a wal lock.
+fn decode_content<D: DeserializeOwned>(payload: &mut &[u8]) -> alloy_rlp::Result<D> {
+    let buf = <Vec<u8>>::decode(payload)?;
+    check_limit(DecodeLimit::ContentBytes, buf.len())?;
+    bcs::from_bytes(&buf).map_err(|_| alloy_rlp::Error::Custom("Decode content error."))
 }
 
 impl<T: Codec> Decodable for Proposal<T> {
@@ -80,30 +268,89 @@ impl<T: Codec> Decodable for Proposal<T> {
 
         if has_locked {
             return Ok(Proposal {
-                height: u64::decode(&mut payload)?,
-                round: u64::decode(&mut payload)?,
-                content: {
-                    let buf = <Vec<u8>>::decode(&mut payload)?;
-                    bcs::from_bytes(&buf)
-                        .map_err(|_| alloy_rlp::Error::Custom("Decode content error."))?
-                },
+                height: Height::decode(&mut payload)?,
+                round: Round::decode(&mut payload)?,
+                content: decode_content(&mut payload)?,
                 block_hash: Hash::decode(&mut payload)?,
                 lock: Some(PoLC::decode(&mut payload)?),
                 proposer: Address::decode(&mut payload)?,
+                vrf: decode_trailing(&mut payload)?,
             });
         }
 
         Ok(Proposal {
-            height: u64::decode(&mut payload)?,
-            round: u64::decode(&mut payload)?,
-            content: {
-                let buf = <Vec<u8>>::decode(&mut payload)?;
-                bcs::from_bytes(&buf)
-                    .map_err(|_| alloy_rlp::Error::Custom("Decode content error."))?
-            },
+            height: Height::decode(&mut payload)?,
+            round: Round::decode(&mut payload)?,
+            content: decode_content(&mut payload)?,
             block_hash: Hash::decode(&mut payload)?,
             lock: None,
             proposer: Address::decode(&mut payload)?,
+            vrf: decode_trailing(&mut payload)?,
+        })
+    }
+}
+
+/// The trailing item of the signature of a threshold QC.
+const THRESHOLD_QC: u8 = 1;
+
+impl Encodable for AggregatedSignature {
+    fn encode(&self, out: &mut dyn BufMut) {
+        // The kind is a trailing item which is omitted for an aggregated signature, so that it
+        // keeps the same encoding.
+        let kind = THRESHOLD_QC;
+        let mut enc: Vec<&dyn Encodable> = vec![&self.signature, &self.address_bitmap];
+        if self.kind == QcKind::Threshold {
+            enc.push(&kind);
+        }
+        encode_list::<_, dyn Encodable>(&enc, out);
+    }
+}
+
+impl Decodable for AggregatedSignature {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let mut payload = Header::decode_bytes(buf, true)?;
+        let signature = Signature::decode(&mut payload)?;
+        let address_bitmap = Bytes::decode(&mut payload)?;
+        check_limit(DecodeLimit::BitmapLen, address_bitmap.len())?;
+        let kind = match decode_trailing::<u8>(&mut payload)? {
+            None => QcKind::Aggregated,
+            Some(THRESHOLD_QC) => QcKind::Threshold,
+            Some(_) => return Err(alloy_rlp::Error::Custom("Invalid QC kind.")),
+        };
+        Ok(AggregatedSignature {
+            signature,
+            address_bitmap,
+            kind,
+        })
+    }
+}
+
+impl Decodable for AggregatedChoke {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let mut payload = Header::decode_bytes(buf, true)?;
+        let height = Height::decode(&mut payload)?;
+        let round = Round::decode(&mut payload)?;
+        let signature = Signature::decode(&mut payload)?;
+
+        // Count the voters before they are decoded.
+        let mut list = Header::decode_bytes(&mut payload, true)?;
+        let mut count = 0;
+        let mut rest = list;
+        while !rest.is_empty() {
+            Header::decode_bytes(&mut rest, false)?;
+            count += 1;
+        }
+        check_limit(DecodeLimit::Voters, count)?;
+
+        let mut voters = Vec::with_capacity(count);
+        while !list.is_empty() {
+            voters.push(Address::decode(&mut list)?);
+        }
+        Ok(AggregatedChoke {
+            height,
+            round,
+            signature,
+            voters,
         })
     }
 }
@@ -120,12 +367,8 @@ impl<T: Codec> Decodable for Commit<T> {
     fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
         let mut payload = Header::decode_bytes(buf, true)?;
         Ok(Commit {
-            height: u64::decode(&mut payload)?,
-            content: {
-                let buf = <Vec<u8>>::decode(&mut payload)?;
-                bcs::from_bytes(&buf)
-                    .map_err(|_| alloy_rlp::Error::Custom("Decode content error."))?
-            },
+            height: Height::decode(&mut payload)?,
+            content: decode_content(&mut payload)?,
             proof: Proof::decode(&mut payload)?,
         })
     }
@@ -184,13 +427,9 @@ impl<T: Codec> Decodable for WalLock<T> {
     fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
         let mut payload = Header::decode_bytes(buf, true)?;
         Ok(WalLock {
-            lock_round: u64::decode(&mut payload)?,
+            lock_round: Round::decode(&mut payload)?,
             lock_votes: AggregatedVote::decode(&mut payload)?,
-            content: {
-                let buf = <Vec<u8>>::decode(&mut payload)?;
-                bcs::from_bytes(&buf)
-                    .map_err(|_| alloy_rlp::Error::Custom("Decode content error."))?
-            },
+            content: decode_content(&mut payload)?,
         })
     }
 }
@@ -207,27 +446,32 @@ impl Decodable for Step {
     fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
         let mut payload = Header::decode_bytes(buf, true)?;
         let value = u8::decode(&mut payload)?;
+        if value > 4 {
+            return Err(alloy_rlp::Error::Custom("Invalid step."));
+        }
         Ok(Step::from(value))
     }
 }
 
 impl<T: Codec> Encodable for WalInfo<T> {
     fn encode(&self, out: &mut dyn BufMut) {
+        let has_locked = self.lock.is_some();
+        let mut enc: Vec<&dyn Encodable> = vec![&has_locked, &self.height, &self.round, &self.step];
         if let Some(lock) = &self.lock {
-            let enc: [&dyn Encodable; 6] = [
-                &true,
-                &self.height,
-                &self.round,
-                &self.step,
-                &lock,
-                &self.from,
-            ];
-            encode_list::<_, dyn Encodable>(&enc, out);
-        } else {
-            let enc: [&dyn Encodable; 5] =
-                [&false, &self.height, &self.round, &self.step, &self.from];
-            encode_list::<_, dyn Encodable>(&enc, out);
+            enc.push(lock);
         }
+        enc.push(&self.from);
+        // The VRF seed is a trailing item as the VRF of `Proposal`. The votes and the QCs trail
+        // it, of which the seed is encoded empty if there is none.
+        let empty_seed = Hash::new();
+        if !self.votes.is_empty() || !self.qcs.is_empty() {
+            enc.push(self.vrf_seed.as_ref().unwrap_or(&empty_seed));
+            enc.push(&self.votes);
+            enc.push(&self.qcs);
+        } else if let Some(seed) = &self.vrf_seed {
+            enc.push(seed);
+        }
+        encode_list::<_, dyn Encodable>(&enc, out);
     }
 }
 
@@ -236,34 +480,37 @@ impl<T: Codec> Decodable for WalInfo<T> {
         let mut payload = Header::decode_bytes(buf, true)?;
         let has_locked = bool::decode(&mut payload)?;
 
-        if has_locked {
-            return Ok(WalInfo {
-                height: u64::decode(&mut payload)?,
-                round: u64::decode(&mut payload)?,
-                step: Step::decode(&mut payload)?,
-                lock: Some(WalLock::decode(&mut payload)?),
-                from: UpdateFrom::decode(&mut payload)?,
-            });
-        }
-
         Ok(WalInfo {
-            height: u64::decode(&mut payload)?,
-            round: u64::decode(&mut payload)?,
+            height: Height::decode(&mut payload)?,
+            round: Round::decode(&mut payload)?,
             step: Step::decode(&mut payload)?,
+            lock: if has_locked {
+                Some(WalLock::decode(&mut payload)?)
+            } else {
+                None
+            },
             from: UpdateFrom::decode(&mut payload)?,
-            lock: None,
+            vrf_seed: decode_trailing::<Hash>(&mut payload)?.filter(|seed| !seed.is_empty()),
+            votes: decode_trailing(&mut payload)?.unwrap_or_default(),
+            qcs: decode_trailing(&mut payload)?.unwrap_or_default(),
         })
     }
 }
 
 #[cfg(test)]
 mod test {
+    use std::sync::Arc;
+
     use bytes::Bytes;
     use rand::random;
     use serde::{Deserialize, Serialize};
 
     use super::*;
-    use crate::types::{AggregatedSignature, Choke, Node, SignedChoke, SignedVote, Status, Vote};
+    use crate::types::{
+        AggregatedSignature, BlockPart, Choke, Node, PartSetHeader, PullProposal, PullQC,
+        PullVotes, QcKind, RoundSummary, SignedChoke, SignedCompactProposal, SignedVote, Status,
+        Vote, VoteExtension, VoterExtension, Vrf,
+    };
     use crate::DurationConfig;
 
     #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -296,12 +543,22 @@ mod test {
             let block_hash = gen_hash();
             let proposer = gen_address();
             Proposal {
-                height,
-                round,
+                height: Height(height),
+                round: Round(round),
                 content,
                 block_hash,
                 lock,
                 proposer,
+                vrf: None,
+            }
+        }
+    }
+
+    impl Vrf {
+        fn new() -> Self {
+            Vrf {
+                output: gen_hash(),
+                proof: gen_signature().into(),
             }
         }
     }
@@ -309,7 +566,7 @@ mod test {
     impl PoLC {
         fn new() -> Self {
             PoLC {
-                lock_round: random::<u64>(),
+                lock_round: Round(random::<u64>()),
                 lock_votes: AggregatedVote::new(1u8),
             }
         }
@@ -321,6 +578,7 @@ mod test {
                 signature: gen_signature(),
                 vote: Vote::new(vote_type),
                 voter: gen_address(),
+                extension: (vote_type == 2u8).then(VoteExtension::new),
             }
         }
     }
@@ -330,10 +588,25 @@ mod test {
             AggregatedVote {
                 signature: gen_aggr_signature(),
                 vote_type: VoteType::try_from(vote_type).unwrap(),
-                height: random::<u64>(),
-                round: random::<u64>(),
+                height: Height(random::<u64>()),
+                round: Round(random::<u64>()),
                 block_hash: gen_hash(),
                 leader: gen_address(),
+                extensions: (vote_type == 2u8).then(|| {
+                    vec![VoterExtension {
+                        voter: gen_address(),
+                        extension: VoteExtension::new(),
+                    }]
+                }),
+            }
+        }
+    }
+
+    impl VoteExtension {
+        fn new() -> Self {
+            VoteExtension {
+                data: gen_hash().into(),
+                signature: gen_signature(),
             }
         }
     }
@@ -341,8 +614,8 @@ mod test {
     impl Vote {
         fn new(vote_type: u8) -> Self {
             Vote {
-                height: random::<u64>(),
-                round: random::<u64>(),
+                height: Height(random::<u64>()),
+                round: Round(random::<u64>()),
                 vote_type: VoteType::try_from(vote_type).unwrap(),
                 block_hash: gen_hash(),
             }
@@ -354,7 +627,7 @@ mod test {
             let height = random::<u64>();
             let proof = Proof::new();
             Commit {
-                height,
+                height: Height(height),
                 content,
                 proof,
             }
@@ -364,10 +637,12 @@ mod test {
     impl Proof {
         fn new() -> Self {
             Proof {
-                height: random::<u64>(),
-                round: random::<u64>(),
+                height: Height(random::<u64>()),
+                round: Round(random::<u64>()),
                 block_hash: gen_hash(),
                 signature: gen_aggr_signature(),
+                extensions: None,
+                vrf: None,
             }
         }
     }
@@ -375,8 +650,8 @@ mod test {
     impl AggregatedChoke {
         fn new() -> Self {
             AggregatedChoke {
-                height: random::<u64>(),
-                round: random::<u64>(),
+                height: Height(random::<u64>()),
+                round: Round(random::<u64>()),
                 signature: gen_signature(),
                 voters: vec![gen_address(), gen_address()],
             }
@@ -386,8 +661,8 @@ mod test {
     impl Choke {
         fn new(from: UpdateFrom) -> Self {
             Choke {
-                height: random::<u64>(),
-                round: random::<u64>(),
+                height: Height(random::<u64>()),
+                round: Round(random::<u64>()),
                 from,
             }
         }
@@ -417,9 +692,12 @@ mod test {
             };
 
             Status {
-                height: random::<u64>(),
+                height: Height(random::<u64>()),
                 interval: time,
                 timer_config: config,
+                vrf_seed: None,
+                next_authority_list: random::<bool>().then(|| vec![Node::new(gen_address())]),
+                proof: None,
                 authority_list: vec![Node::new(gen_address())],
             }
         }
@@ -432,7 +710,7 @@ mod test {
                 Some(WalLock {
                     lock_round: polc.lock_round,
                     lock_votes: polc.lock_votes,
-                    content: tmp,
+                    content: Arc::new(tmp),
                 })
             } else {
                 None
@@ -443,11 +721,14 @@ mod test {
             let step = Step::Precommit;
             let from = UpdateFrom::ChokeQC(AggregatedChoke::new());
             WalInfo {
-                height,
-                round,
+                height: Height(height),
+                round: Round(round),
                 step,
                 lock,
                 from,
+                vrf_seed: random::<bool>().then(gen_hash),
+                votes: Vec::new(),
+                qcs: Vec::new(),
             }
         }
     }
@@ -468,9 +749,41 @@ mod test {
         AggregatedSignature {
             signature: gen_signature(),
             address_bitmap: Bytes::from((0..8).map(|_| random::<u8>()).collect::<Vec<_>>()),
+            kind: QcKind::Aggregated,
         }
     }
 
+    #[test]
+    fn test_qc_kind_codec() {
+        // An aggregated signature keeps the encoding of the signature and the bitmap.
+        let aggregated = gen_aggr_signature();
+        let mut legacy = Vec::new();
+        let enc: [&dyn Encodable; 2] = [&aggregated.signature, &aggregated.address_bitmap];
+        alloy_rlp::encode_list::<_, dyn Encodable>(&enc, &mut legacy);
+        assert_eq!(alloy_rlp::encode(&aggregated), legacy);
+        let decoded: AggregatedSignature =
+            Decodable::decode(&mut alloy_rlp::encode(&aggregated).as_ref()).unwrap();
+        assert_eq!(decoded, aggregated);
+
+        // The kind of a threshold signature is carried, and not inferred from the empty bitmap.
+        let threshold = AggregatedSignature::threshold(gen_signature());
+        let decoded: AggregatedSignature =
+            Decodable::decode(&mut alloy_rlp::encode(&threshold).as_ref()).unwrap();
+        assert_eq!(decoded, threshold);
+        let empty = AggregatedSignature {
+            kind: QcKind::Aggregated,
+            ..threshold.clone()
+        };
+        let decoded: AggregatedSignature =
+            Decodable::decode(&mut alloy_rlp::encode(&empty).as_ref()).unwrap();
+        assert!(!decoded.is_threshold());
+
+        let mut invalid = Vec::new();
+        let enc: [&dyn Encodable; 3] = [&threshold.signature, &threshold.address_bitmap, &2u8];
+        alloy_rlp::encode_list::<_, dyn Encodable>(&enc, &mut invalid);
+        assert!(<AggregatedSignature as Decodable>::decode(&mut invalid.as_ref()).is_err());
+    }
+
     #[test]
     fn test_pill_codec() {
         for _ in 0..100 {
@@ -493,6 +806,58 @@ mod test {
             Decodable::decode(&mut alloy_rlp::encode(&signed_proposal).as_ref()).unwrap();
         assert_eq!(signed_proposal, res);
 
+        let mut signed_proposal = SignedProposal::new(Pill::new(), Some(PoLC::new()));
+        signed_proposal.proposal.vrf = Some(Vrf::new());
+        let res: SignedProposal<Pill> =
+            Decodable::decode(&mut alloy_rlp::encode(&signed_proposal).as_ref()).unwrap();
+        assert_eq!(signed_proposal, res);
+
+        let mut signed_proposal = SignedProposal::new(Pill::new(), None);
+        signed_proposal.proposal.vrf = Some(Vrf::new());
+        let res: SignedProposal<Pill> =
+            Decodable::decode(&mut alloy_rlp::encode(&signed_proposal).as_ref()).unwrap();
+        assert_eq!(signed_proposal, res);
+
+        // Test SignedCompactProposal, with a lock but no VRF and with a VRF but no lock.
+        for (lock, vrf) in [(Some(PoLC::new()), None), (None, Some(Vrf::new()))] {
+            let mut proposal = SignedProposal::new(Pill::new(), lock).proposal;
+            proposal.vrf = vrf;
+            let compact = SignedCompactProposal {
+                signature: gen_signature(),
+                proposal: proposal.to_compact(),
+            };
+            let res: SignedCompactProposal =
+                Decodable::decode(&mut alloy_rlp::encode(&compact).as_ref()).unwrap();
+            assert_eq!(compact, res);
+            assert_eq!(
+                res.proposal.into_proposal(proposal.content.clone()),
+                proposal
+            );
+        }
+
+        let mut compact = SignedCompactProposal {
+            signature: gen_signature(),
+            proposal: SignedProposal::new(Pill::new(), None).proposal.to_compact(),
+        };
+        compact.proposal.parts = Some(PartSetHeader {
+            total: 3,
+            root: gen_hash(),
+        });
+        let res: SignedCompactProposal =
+            Decodable::decode(&mut alloy_rlp::encode(&compact).as_ref()).unwrap();
+        assert_eq!(compact, res);
+
+        // Test BlockPart
+        let part = BlockPart {
+            height: Height(random::<u64>()),
+            round: Round(random::<u64>()),
+            index: 2,
+            data: gen_signature().into(),
+            proof: vec![gen_hash(), gen_hash()],
+        };
+        let res: BlockPart = Decodable::decode(&mut alloy_rlp::encode(&part).as_ref()).unwrap();
+        assert_eq!(part, res);
+
         // Test SignedVote
         let signed_vote = SignedVote::new(2u8);
         let res: SignedVote =
@@ -553,6 +918,46 @@ mod test {
             Decodable::decode(&mut alloy_rlp::encode(&signed_choke).as_ref()).unwrap();
         assert_eq!(signed_choke, res);
 
+        // Test Pull QC
+        let pull = PullQC {
+            height: Height(random::<u64>()),
+            round: Round(random::<u64>()),
+            vote_type: VoteType::Precommit,
+            requester: gen_address(),
+        };
+        let res: PullQC = Decodable::decode(&mut alloy_rlp::encode(&pull).as_ref()).unwrap();
+        assert_eq!(pull, res);
+
+        // Test Pull Proposal
+        let pull = PullProposal {
+            height: Height(random::<u64>()),
+            round: Round(random::<u64>()),
+            requester: gen_address(),
+        };
+        let res: PullProposal = Decodable::decode(&mut alloy_rlp::encode(&pull).as_ref()).unwrap();
+        assert_eq!(pull, res);
+
+        // Test Pull Votes
+        let pull = PullVotes {
+            height: Height(random::<u64>()),
+            round: Round(random::<u64>()),
+            vote_type: VoteType::Prevote,
+            have_bitmap: Bytes::from((0..8).map(|_| random::<u8>()).collect::<Vec<_>>()),
+            requester: gen_address(),
+        };
+        let res: PullVotes = Decodable::decode(&mut alloy_rlp::encode(&pull).as_ref()).unwrap();
+        assert_eq!(pull, res);
+
+        // Test Round Summary
+        let summary = RoundSummary {
+            height: Height(random::<u64>()),
+            new_round: Round(3),
+            choke_qc: AggregatedChoke::new(),
+        };
+        let res: RoundSummary =
+            Decodable::decode(&mut alloy_rlp::encode(&summary).as_ref()).unwrap();
+        assert_eq!(summary, res);
+
         // Test Wal Info
         let pill = Pill::new();
         let wal_info = WalInfo::new(Some(pill));
@@ -564,5 +969,112 @@ mod test {
         let res: WalInfo<Pill> =
             Decodable::decode(&mut alloy_rlp::encode(&wal_info).as_ref()).unwrap();
         assert_eq!(wal_info, res);
+
+        // The votes and the QCs trail the seed, which is encoded empty without one.
+        let mut wal_info = WalInfo::<Pill>::new(None);
+        wal_info.vrf_seed = None;
+        wal_info.votes = vec![SignedVote::new(1), SignedVote::new(2)];
+        wal_info.qcs = vec![AggregatedVote::new(1)];
+        let res: WalInfo<Pill> =
+            Decodable::decode(&mut alloy_rlp::encode(&wal_info).as_ref()).unwrap();
+        assert_eq!(wal_info, res);
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_compressed_frame() {
+        #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+        struct Block(Vec<u8>);
+
+        // The item is encoded plain, so that its signature does not depend on the feature.
+        let proposal = Proposal::new(Block(vec![7u8; 4096]), Some(PoLC::new()));
+        let encode = alloy_rlp::encode(&proposal);
+        assert!(encode.len() > 4096);
+        let frame = compress_frame(encode.clone());
+        assert!(frame.len() < 1024);
+        let plain = decompress_frame(&frame, None).unwrap();
+        assert_eq!(plain.as_ref(), encode.as_slice());
+        let res: Proposal<Block> = Decodable::decode(&mut plain.as_ref()).unwrap();
+        assert_eq!(proposal, res);
+
+        // A small frame is kept plain.
+        let small = alloy_rlp::encode(Proposal::new(Block(vec![7u8; 16]), None));
+        assert_eq!(compress_frame(small.clone()), small);
+        assert!(matches!(
+            decompress_frame(&small, None).unwrap(),
+            Cow::Borrowed(_)
+        ));
+
+        // The decompressed length is limited.
+        assert!(matches!(
+            decompress_frame(&frame, Some(1024)),
+            Err(ConsensusError::DecodeLimitErr {
+                limit: DecodeLimit::ContentBytes,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        let config = CodecConfig {
+            max_content_bytes: 512,
+            max_voters: 2,
+            max_bitmap_len: 8,
+        };
+
+        // The pill is encoded in more than 1024 bytes.
+        let signed_proposal = SignedProposal::new(Pill::new(), None);
+        let encode = alloy_rlp::encode(&signed_proposal);
+        assert!(matches!(
+            config.decode::<SignedProposal<Pill>>(&encode),
+            Err(ConsensusError::DecodeLimitErr {
+                limit: DecodeLimit::ContentBytes,
+                max: 512,
+                ..
+            })
+        ));
+        let unlimited: SignedProposal<Pill> = Decodable::decode(&mut encode.as_ref()).unwrap();
+        assert_eq!(signed_proposal, unlimited);
+        let relaxed = CodecConfig {
+            max_content_bytes: 4096,
+            ..config
+        };
+        assert_eq!(relaxed.decode(&encode), Ok(signed_proposal));
+
+        let mut choke_qc = AggregatedChoke::new();
+        assert_eq!(
+            config.decode::<AggregatedChoke>(&alloy_rlp::encode(&choke_qc)),
+            Ok(choke_qc.clone())
+        );
+        choke_qc.voters.push(gen_address());
+        assert!(matches!(
+            config.decode::<AggregatedChoke>(&alloy_rlp::encode(&choke_qc)),
+            Err(ConsensusError::DecodeLimitErr {
+                limit: DecodeLimit::Voters,
+                len: 3,
+                max: 2,
+            })
+        ));
+
+        // The bitmap is checked in a nested QC.
+        let mut qc = AggregatedVote::new(1u8);
+        assert_eq!(
+            config.decode::<AggregatedVote>(&alloy_rlp::encode(&qc)),
+            Ok(qc.clone())
+        );
+        qc.signature.address_bitmap = Bytes::from(vec![0u8; 9]);
+        assert!(matches!(
+            config.decode::<AggregatedVote>(&alloy_rlp::encode(&qc)),
+            Err(ConsensusError::DecodeLimitErr {
+                limit: DecodeLimit::BitmapLen,
+                ..
+            })
+        ));
+
+        assert!(matches!(
+            config.decode::<AggregatedVote>(&[0xc1, 0x80]),
+            Err(ConsensusError::RlpErr(_))
+        ));
     }
 }
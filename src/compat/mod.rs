@@ -0,0 +1,279 @@
+use std::marker::PhantomData;
+
+use alloy_rlp::Decodable;
+use bytes::Bytes;
+use derive_more::Display;
+
+use crate::error::ConsensusError;
+use crate::types::Proof;
+use crate::wal::{decode_wal_record, encode_wal_record, WalInfo, WAL_VERSION_0};
+use crate::{Codec, ConsensusResult};
+
+/// The golden blobs encoded by the previous overlord releases. The blobs of the wal info carry no
+/// lock, so that they decode with any content type.
+const GOLDEN_BLOBS: [(&str, BlobKind, &[u8]); 6] = [
+    (
+        "v0.5.0/wal_info_propose",
+        BlobKind::WalInfo,
+        include_bytes!("golden/v0.5.0/wal_info_propose.bin"),
+    ),
+    (
+        "v0.5.0/wal_info_prevote",
+        BlobKind::WalInfo,
+        include_bytes!("golden/v0.5.0/wal_info_prevote.bin"),
+    ),
+    (
+        "v0.5.0/wal_info_precommit",
+        BlobKind::WalInfo,
+        include_bytes!("golden/v0.5.0/wal_info_precommit.bin"),
+    ),
+    (
+        "v0.5.0/wal_info_brake",
+        BlobKind::WalInfo,
+        include_bytes!("golden/v0.5.0/wal_info_brake.bin"),
+    ),
+    (
+        "v0.5.0/wal_info_commit",
+        BlobKind::WalInfo,
+        include_bytes!("golden/v0.5.0/wal_info_commit.bin"),
+    ),
+    (
+        "v0.5.0/proof",
+        BlobKind::Proof,
+        include_bytes!("golden/v0.5.0/proof.bin"),
+    ),
+];
+
+/// The kind of an encoded blob.
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq)]
+pub enum BlobKind {
+    /// A wal record which is saved by `Wal::save`, either framed or a bare encoded `WalInfo` of
+    /// version 0.
+    #[display("wal info")]
+    WalInfo,
+    /// An encoded `Proof`.
+    #[display("proof")]
+    Proof,
+}
+
+/// An encoded blob produced by a previous overlord release.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompatBlob {
+    /// The name to report when the blob fails to decode.
+    pub name: String,
+    /// The kind of the blob.
+    pub kind: BlobKind,
+    /// The encoded bytes.
+    pub data: Bytes,
+}
+
+/// A compatibility suite of the wal and proof formats. It contains the golden blobs of the
+/// previous overlord releases, and the application can register the blobs saved by its own nodes,
+/// such as the wal info with a lock of its content type. Run `check` after upgrading overlord to
+/// make sure that every blob still decodes, and encodes back to the same bytes.
+#[derive(Clone, Debug)]
+pub struct CompatSuite<T: Codec> {
+    blobs: Vec<CompatBlob>,
+    phantom: PhantomData<T>,
+}
+
+impl<T: Codec> Default for CompatSuite<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Codec> CompatSuite<T> {
+    /// Create a compatibility suite with the golden blobs.
+    pub fn new() -> Self {
+        let blobs = GOLDEN_BLOBS
+            .iter()
+            .map(|(name, kind, data)| CompatBlob {
+                name: name.to_string(),
+                kind: *kind,
+                data: Bytes::from_static(data),
+            })
+            .collect::<Vec<_>>();
+
+        CompatSuite {
+            blobs,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Register an application provided blob.
+    pub fn register(&mut self, blob: CompatBlob) -> &mut Self {
+        self.blobs.push(blob);
+        self
+    }
+
+    /// Register an application provided wal info blob.
+    pub fn register_wal_info(&mut self, name: &str, data: Bytes) -> &mut Self {
+        self.register(CompatBlob {
+            name: name.to_string(),
+            kind: BlobKind::WalInfo,
+            data,
+        })
+    }
+
+    /// Register an application provided proof blob.
+    pub fn register_proof(&mut self, name: &str, data: Bytes) -> &mut Self {
+        self.register(CompatBlob {
+            name: name.to_string(),
+            kind: BlobKind::Proof,
+            data,
+        })
+    }
+
+    /// Get all blobs of the suite.
+    pub fn blobs(&self) -> &[CompatBlob] {
+        &self.blobs
+    }
+
+    /// Check every blob of the suite. Return `Err()` with the name of the first blob that fails
+    /// to decode or encodes to different bytes.
+    pub fn check(&self) -> ConsensusResult<()> {
+        for blob in self.blobs.iter() {
+            let encoded = match blob.kind {
+                BlobKind::WalInfo => {
+                    let encoded = alloy_rlp::encode(decode_wal_info::<T>(&blob.data)?);
+                    if wal_record_version(&blob.data)? == WAL_VERSION_0 {
+                        encoded
+                    } else {
+                        encode_wal_record(&encoded).to_vec()
+                    }
+                }
+                BlobKind::Proof => alloy_rlp::encode(decode_proof(&blob.data)?),
+            };
+
+            if encoded != blob.data {
+                return Err(ConsensusError::CompatErr(format!(
+                    "{} {} encodes to different bytes",
+                    blob.kind, blob.name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decode a wal info which is saved by `Wal::save`. The records of every format version are
+/// decoded.
+pub fn decode_wal_info<T: Codec>(data: &[u8]) -> ConsensusResult<WalInfo<T>> {
+    let (_, buf) = decode_wal_record(data)
+        .map_err(|e| ConsensusError::CompatErr(format!("wal info {}", e)))?;
+    WalInfo::decode(&mut buf.as_ref())
+        .map_err(|e| ConsensusError::CompatErr(format!("wal info {}", e)))
+}
+
+fn wal_record_version(data: &[u8]) -> ConsensusResult<u8> {
+    decode_wal_record(data)
+        .map(|(version, _)| version)
+        .map_err(|e| ConsensusError::CompatErr(format!("wal info {}", e)))
+}
+
+/// Decode an encoded proof.
+pub fn decode_proof(data: &[u8]) -> ConsensusResult<Proof> {
+    let mut buf = data;
+    Proof::decode(&mut buf).map_err(|e| ConsensusError::CompatErr(format!("proof {}", e)))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+    use serde::{Deserialize, Serialize};
+
+    use super::{decode_proof, decode_wal_info, CompatSuite};
+    use crate::error::ConsensusError;
+    use crate::types::{
+        Address, AggregatedSignature, AggregatedVote, Hash, Height, QcKind, Round, Signature,
+        UpdateFrom, VoteType,
+    };
+    use crate::wal::{encode_wal_record, WalInfo, WalLock};
+    use crate::Step;
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+    struct Pill {
+        inner: Vec<u8>,
+    }
+
+    fn mock_qc() -> AggregatedVote {
+        AggregatedVote {
+            signature: AggregatedSignature {
+                signature: Signature::from(vec![0x11; 96]),
+                address_bitmap: Bytes::from(vec![0b1011_0000]),
+                kind: QcKind::Aggregated,
+            },
+            vote_type: VoteType::Prevote,
+            height: Height(10),
+            round: Round(2),
+            block_hash: Hash::from(vec![0xab; 32]),
+            leader: Address::from(vec![0x01; 20]),
+            extensions: None,
+        }
+    }
+
+    #[test]
+    fn test_golden_blobs() {
+        let suite = CompatSuite::<Pill>::new();
+        assert_eq!(suite.blobs().len(), 6);
+        assert_eq!(suite.check(), Ok(()));
+
+        let info = decode_wal_info::<Pill>(include_bytes!("golden/v0.5.0/wal_info_brake.bin"));
+        let info = info.unwrap();
+        assert_eq!(info.height, Height(10));
+        assert_eq!(info.round, Round(2));
+        assert_eq!(info.step, Step::Brake);
+        assert!(info.lock.is_none());
+
+        let proof = decode_proof(include_bytes!("golden/v0.5.0/proof.bin")).unwrap();
+        assert_eq!(proof.height, Height(10));
+        assert_eq!(proof.block_hash, Hash::from(vec![0xab; 32]));
+    }
+
+    #[test]
+    fn test_register_blobs() {
+        let info = WalInfo {
+            height: Height(10),
+            round: Round(2),
+            step: Step::Precommit,
+            lock: Some(WalLock {
+                lock_round: Round(2),
+                lock_votes: mock_qc(),
+                content: Arc::new(Pill {
+                    inner: vec![1, 2, 3],
+                }),
+            }),
+            from: UpdateFrom::PrevoteQC(mock_qc()),
+            vrf_seed: Some(Hash::from(vec![0xcd; 32])),
+            votes: Vec::new(),
+            qcs: Vec::new(),
+        };
+        let data = Bytes::from(alloy_rlp::encode(&info));
+
+        let mut suite = CompatSuite::<Pill>::new();
+        suite.register_wal_info("locked", data.clone());
+        assert_eq!(suite.check(), Ok(()));
+        assert_eq!(decode_wal_info::<Pill>(&data).unwrap(), info);
+
+        // A framed record decodes to the same wal info, and encodes back into a framed record.
+        let record = encode_wal_record(&data);
+        suite.register_wal_info("locked record", record.clone());
+        assert_eq!(suite.check(), Ok(()));
+        assert_eq!(decode_wal_info::<Pill>(&record).unwrap(), info);
+
+        // A blob with an unknown step should fail instead of panic.
+        let mut raw = alloy_rlp::encode(&WalInfo::<Pill> { lock: None, ..info });
+        let step = alloy_rlp::encode(Step::Precommit);
+        let pos = raw.windows(step.len()).position(|w| w == step).unwrap();
+        raw[pos + 1] = 0x09;
+        suite.register_wal_info("unknown step", Bytes::from(raw));
+        assert!(matches!(suite.check(), Err(ConsensusError::CompatErr(_))));
+
+        let mut suite = CompatSuite::<Pill>::new();
+        suite.register_proof("truncated", data.slice(..8));
+        assert!(suite.check().is_err());
+    }
+}
@@ -0,0 +1,236 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::panic::{self, PanicHookInfo};
+use std::pin::Pin;
+use std::sync::{Arc, Once};
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::aux_store::AuxStore;
+use crate::error::ConsensusError;
+use crate::smr::smr_types::Step;
+use crate::types::{Height, Round};
+use crate::ConsensusResult;
+
+/// The key of the crash snapshot in the auxiliary store.
+pub const CRASH_SNAPSHOT_KEY: &[u8] = b"crash_snapshot";
+
+/// The count of the recent SMR triggers kept in a crash snapshot.
+const TRIGGER_LOG_LEN: usize = 64;
+
+static INSTALL_HOOK: Once = Once::new();
+
+thread_local! {
+    /// The recorder of the consensus task which is being polled on this thread.
+    static CURRENT: RefCell<Option<Arc<CrashRecorder>>> = const { RefCell::new(None) };
+}
+
+/// A snapshot of the consensus state, which is saved to the auxiliary store by the panic hook when
+/// the state or the SMR task panics. It is enough to reproduce the crash, and the node restarts
+/// safely from its wal as usual, since the wal record of the snapshot is only newer than the saved
+/// one by the entries coalesced under `WalPolicy::Batched`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CrashSnapshot {
+    /// The panic message with its location.
+    pub panic: String,
+    /// The height of the state.
+    pub height: Height,
+    /// The round of the state.
+    pub round: Round,
+    /// The step of the state.
+    pub step: Step,
+    /// The latest wal record built by the state, see `compat::decode_wal_info`.
+    pub wal_record: Option<Bytes>,
+    /// The recent triggers handled by the SMR, the oldest first.
+    pub triggers: Vec<String>,
+}
+
+/// Load the crash snapshot saved in the auxiliary store, if any.
+pub fn load_crash_snapshot(store: &dyn AuxStore) -> ConsensusResult<Option<CrashSnapshot>> {
+    let raw = store
+        .get(CRASH_SNAPSHOT_KEY)
+        .map_err(|e| ConsensusError::AuxStoreErr {
+            context: "load crash snapshot",
+            source: e.into(),
+        })?;
+    raw.map(|raw| {
+        bcs::from_bytes(&raw).map_err(|e| ConsensusError::StorageCodecErr {
+            context: "decode crash snapshot",
+            source: e,
+        })
+    })
+    .transpose()
+}
+
+/// Install the panic hook once per process. The hook saves the crash snapshot of the consensus
+/// task which panics, then calls the previous hook.
+pub(crate) fn install_panic_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let recorder = CURRENT
+                .try_with(|current| current.try_borrow().ok().and_then(|r| r.clone()))
+                .ok()
+                .flatten();
+            if let Some(recorder) = recorder {
+                recorder.save(info);
+            }
+            previous(info);
+        }));
+    });
+}
+
+#[derive(Default)]
+struct Recorded {
+    height: Height,
+    round: Round,
+    step: Step,
+    wal_record: Option<Bytes>,
+    triggers: VecDeque<String>,
+}
+
+/// The recorder of the consensus state of an overlord instance for the crash snapshot.
+pub(crate) struct CrashRecorder {
+    store: Arc<dyn AuxStore>,
+    recorded: Mutex<Recorded>,
+}
+
+impl fmt::Debug for CrashRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CrashRecorder").finish_non_exhaustive()
+    }
+}
+
+impl CrashRecorder {
+    pub(crate) fn new(store: Arc<dyn AuxStore>) -> Self {
+        CrashRecorder {
+            store,
+            recorded: Mutex::new(Recorded::default()),
+        }
+    }
+
+    /// Record the latest wal record of the state.
+    pub(crate) fn record_wal(&self, height: Height, round: Round, step: Step, record: Bytes) {
+        let mut recorded = self.recorded.lock();
+        recorded.height = height;
+        recorded.round = round;
+        recorded.step = step;
+        recorded.wal_record = Some(record);
+    }
+
+    /// Record a trigger handled by the SMR.
+    pub(crate) fn record_trigger(&self, trigger: String) {
+        let mut recorded = self.recorded.lock();
+        if recorded.triggers.len() == TRIGGER_LOG_LEN {
+            recorded.triggers.pop_front();
+        }
+        recorded.triggers.push_back(trigger);
+    }
+
+    /// Run the future as a consensus task of which a panic saves the crash snapshot.
+    pub(crate) fn scope<F: Future>(self: &Arc<Self>, fut: F) -> CrashScope<F> {
+        CrashScope {
+            inner: Box::pin(fut),
+            recorder: Arc::clone(self),
+        }
+    }
+
+    fn save(&self, info: &PanicHookInfo) {
+        // The panic may happen with the recorded state locked.
+        let snapshot = match self.recorded.try_lock() {
+            Some(recorded) => CrashSnapshot {
+                panic: info.to_string(),
+                height: recorded.height,
+                round: recorded.round,
+                step: recorded.step.clone(),
+                wal_record: recorded.wal_record.clone(),
+                triggers: recorded.triggers.iter().cloned().collect(),
+            },
+            None => return,
+        };
+
+        match bcs::to_bytes(&snapshot) {
+            Ok(encode) => {
+                if let Err(e) = self
+                    .store
+                    .put(Bytes::from(CRASH_SNAPSHOT_KEY), Bytes::from(encode))
+                {
+                    log::error!("Overlord: save crash snapshot error {:?}", e);
+                }
+            }
+            Err(e) => log::error!("Overlord: encode crash snapshot error {:?}", e),
+        }
+    }
+}
+
+/// A consensus task which marks its recorder as current on the thread while it is polled.
+pub(crate) struct CrashScope<F> {
+    inner: Pin<Box<F>>,
+    recorder: Arc<CrashRecorder>,
+}
+
+impl<F: Future> Future for CrashScope<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        /// Restore the previous recorder, also when the poll unwinds.
+        struct Restore(Option<Arc<CrashRecorder>>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                let previous = self.0.take();
+                let _ = CURRENT.try_with(|current| *current.borrow_mut() = previous);
+            }
+        }
+
+        let previous =
+            CURRENT.with(|current| current.borrow_mut().replace(Arc::clone(&self.recorder)));
+        let _restore = Restore(previous);
+        self.inner.as_mut().poll(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+
+    use super::{install_panic_hook, load_crash_snapshot, CrashRecorder};
+    use crate::aux_store::{AuxStore, MemoryAuxStore};
+    use crate::smr::smr_types::Step;
+    use crate::types::{Height, Round};
+
+    #[tokio::test]
+    async fn test_crash_snapshot() {
+        install_panic_hook();
+        let store: Arc<dyn AuxStore> = Arc::new(MemoryAuxStore::new());
+        let recorder = Arc::new(CrashRecorder::new(Arc::clone(&store)));
+        recorder.record_wal(Height(5), Round(1), Step::Prevote, Bytes::from(vec![1, 2]));
+        for i in 0..100 {
+            recorder.record_trigger(format!("trigger {}", i));
+        }
+
+        // A panic outside of the consensus tasks saves nothing.
+        let res = tokio::spawn(async { panic!("not consensus") }).await;
+        assert!(res.is_err());
+        assert_eq!(load_crash_snapshot(store.as_ref()).unwrap(), None);
+
+        let task = recorder.scope(async { panic!("consensus") });
+        assert!(tokio::spawn(task).await.is_err());
+        let snapshot = load_crash_snapshot(store.as_ref()).unwrap().unwrap();
+        assert!(snapshot.panic.contains("consensus"));
+        assert_eq!(
+            (snapshot.height, snapshot.round, snapshot.step),
+            (Height(5), Round(1), Step::Prevote)
+        );
+        assert_eq!(snapshot.wal_record, Some(Bytes::from(vec![1, 2])));
+        assert_eq!(snapshot.triggers.len(), 64);
+        assert_eq!(snapshot.triggers.last().unwrap(), "trigger 99");
+    }
+}
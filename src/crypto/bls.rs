@@ -0,0 +1,448 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use blst::min_pk::{AggregateSignature, PublicKey, SecretKey, Signature as BlsSignature};
+use blst::BLST_ERROR;
+use bytes::Bytes;
+use parking_lot::RwLock;
+use sha2::{Digest, Sha256};
+
+use crate::error::ConsensusError;
+use crate::types::{Address, Hash, Height, KeyRotation, Signature};
+use crate::{ConsensusResult, Crypto};
+
+/// The domain separation tag of the signatures, which is the ciphersuite of the proof of
+/// possession scheme over the minimal public key size variant, so that the signatures of a vote
+/// can be aggregated and verified against the aggregated public keys of the voters.
+pub const BLS_SIG_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+/// The domain separation tag of the proofs of possession.
+pub const BLS_POP_DST: &[u8] = b"BLS_POP_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+fn crypto_err(msg: String) -> Box<dyn Error + Send> {
+    Box::new(ConsensusError::CryptoErr(msg))
+}
+
+fn check(res: BLST_ERROR, msg: &str) -> Result<(), Box<dyn Error + Send>> {
+    if res != BLST_ERROR::BLST_SUCCESS {
+        return Err(crypto_err(format!("{} {:?}", msg, res)));
+    }
+    Ok(())
+}
+
+/// The BLS public keys of the validators keyed by the validator address. A public key is only
+/// registered with its proof of possession, which rules out the rogue key attacks on the
+/// aggregated signatures. The registry is shared by its clones, so that the keys of the joining
+/// validators can be registered while the overlord is running. A validator may register a new key
+/// from a height on, which verifies its signatures of the height and above.
+#[derive(Clone, Default)]
+pub struct BlsKeyRegistry {
+    keys: Arc<RwLock<HashMap<Address, BTreeMap<Height, PublicKey>>>>,
+}
+
+impl fmt::Debug for BlsKeyRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlsKeyRegistry")
+            .field("len", &self.keys.read().len())
+            .finish()
+    }
+}
+
+impl BlsKeyRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        BlsKeyRegistry::default()
+    }
+
+    /// Register the compressed public key of a validator with its proof of possession, see
+    /// `BlsCrypto::proof_of_possession`. The previous keys of the address are replaced.
+    pub fn insert(
+        &self,
+        address: Address,
+        public_key: &[u8],
+        proof_of_possession: &[u8],
+    ) -> ConsensusResult<()> {
+        let key = Self::validate(&address, public_key, proof_of_possession)?;
+        self.keys
+            .write()
+            .insert(address, BTreeMap::from([(Height(0), key)]));
+        Ok(())
+    }
+
+    /// Register a new key of a validator effective from the given height, while its signatures
+    /// below the height are still verified by the previous key.
+    pub fn insert_at(
+        &self,
+        address: Address,
+        height: Height,
+        public_key: &[u8],
+        proof_of_possession: &[u8],
+    ) -> ConsensusResult<()> {
+        let key = Self::validate(&address, public_key, proof_of_possession)?;
+        self.keys
+            .write()
+            .entry(address)
+            .or_default()
+            .insert(height, key);
+        Ok(())
+    }
+
+    fn validate(
+        address: &Address,
+        public_key: &[u8],
+        proof_of_possession: &[u8],
+    ) -> ConsensusResult<PublicKey> {
+        let key = PublicKey::key_validate(public_key).map_err(|e| {
+            ConsensusError::CryptoErr(format!("invalid public key of {:?}, {:?}", address, e))
+        })?;
+        let pop = BlsSignature::from_bytes(proof_of_possession).map_err(|e| {
+            ConsensusError::CryptoErr(format!("invalid proof of {:?}, {:?}", address, e))
+        })?;
+        let res = pop.verify(true, public_key, BLS_POP_DST, &[], &key, false);
+        if res != BLST_ERROR::BLST_SUCCESS {
+            return Err(ConsensusError::CryptoErr(format!(
+                "proof of possession of {:?} mismatch, {:?}",
+                address, res
+            )));
+        }
+        Ok(key)
+    }
+
+    /// Remove the public key of a validator.
+    pub fn remove(&self, address: &Address) {
+        self.keys.write().remove(address);
+    }
+
+    /// Whether the public key of the address is registered.
+    pub fn contains(&self, address: &Address) -> bool {
+        self.keys.read().contains_key(address)
+    }
+
+    /// The number of the registered public keys.
+    pub fn len(&self) -> usize {
+        self.keys.read().len()
+    }
+
+    /// Whether no public key is registered.
+    pub fn is_empty(&self) -> bool {
+        self.keys.read().is_empty()
+    }
+
+    /// The key of the address effective at the height, or the latest one without a height.
+    fn get(
+        &self,
+        address: &Address,
+        height: Option<Height>,
+    ) -> Result<PublicKey, Box<dyn Error + Send>> {
+        let keys = self.keys.read();
+        let key = keys.get(address).and_then(|keys| match height {
+            Some(height) => keys.range(..=height).next_back(),
+            None => keys.iter().next_back(),
+        });
+        key.map(|(_, key)| *key).ok_or_else(|| {
+            crypto_err(format!(
+                "unknown public key of {:?} at height {:?}",
+                address, height
+            ))
+        })
+    }
+}
+
+/// The `Crypto` of the BLS12-381 signatures, with the public keys on G1 and the signatures on G2.
+/// The hash is SHA-256. The signatures of the same hash are aggregated into one, which is verified
+/// against the public keys of the voters resolved from the bitmap of a QC.
+#[derive(Clone)]
+pub struct BlsCrypto {
+    private_key: SecretKey,
+    public_key: PublicKey,
+    registry: BlsKeyRegistry,
+}
+
+impl fmt::Debug for BlsCrypto {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlsCrypto")
+            .field("public_key", &self.public_key())
+            .field("registry", &self.registry)
+            .finish()
+    }
+}
+
+impl BlsCrypto {
+    /// Create a BLS crypto from the 32 bytes private key, which verifies the signatures by the
+    /// public keys of the registry.
+    pub fn new(private_key: &[u8], registry: BlsKeyRegistry) -> ConsensusResult<Self> {
+        let private_key = SecretKey::from_bytes(private_key)
+            .map_err(|e| ConsensusError::CryptoErr(format!("invalid private key {:?}", e)))?;
+        let public_key = private_key.sk_to_pk();
+        Ok(BlsCrypto {
+            private_key,
+            public_key,
+            registry,
+        })
+    }
+
+    /// Derive a private key from the key material of at least 32 bytes.
+    pub fn derive_private_key(ikm: &[u8]) -> ConsensusResult<Bytes> {
+        let key = SecretKey::key_gen(ikm, &[])
+            .map_err(|e| ConsensusError::CryptoErr(format!("derive private key {:?}", e)))?;
+        Ok(Bytes::copy_from_slice(&key.to_bytes()))
+    }
+
+    /// The compressed public key of self.
+    pub fn public_key(&self) -> Bytes {
+        Bytes::copy_from_slice(&self.public_key.to_bytes())
+    }
+
+    /// The proof of possession of the private key, which is registered with the public key.
+    pub fn proof_of_possession(&self) -> Bytes {
+        let pop = self
+            .private_key
+            .sign(&self.public_key.to_bytes(), BLS_POP_DST, &[]);
+        Bytes::copy_from_slice(&pop.to_bytes())
+    }
+
+    /// The registry of the public keys.
+    pub fn registry(&self) -> &BlsKeyRegistry {
+        &self.registry
+    }
+
+    fn verify(
+        &self,
+        height: Option<Height>,
+        signature: Signature,
+        hash: Hash,
+        voter: Address,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        let key = self.registry.get(&voter, height)?;
+        let signature = BlsSignature::from_bytes(signature.as_bytes())
+            .map_err(|e| crypto_err(format!("invalid signature of {:?}, {:?}", voter, e)))?;
+        let res = signature.verify(true, hash.as_bytes(), BLS_SIG_DST, &[], &key, false);
+        check(res, "verify signature")
+    }
+
+    fn verify_aggregated(
+        &self,
+        height: Option<Height>,
+        aggregate_signature: Signature,
+        msg_hash: Hash,
+        voters: Vec<Address>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        if voters.is_empty() {
+            return Err(crypto_err(
+                "verify aggregated signature of no voter".to_string(),
+            ));
+        }
+        // The same key aggregated twice verifies against the signature signed twice, so a voter
+        // must not be counted twice.
+        let mut set = HashSet::with_capacity(voters.len());
+        if !voters.iter().all(|voter| set.insert(voter)) {
+            return Err(crypto_err(
+                "verify aggregated signature of duplicate voters".to_string(),
+            ));
+        }
+
+        let keys = voters
+            .iter()
+            .map(|voter| self.registry.get(voter, height))
+            .collect::<Result<Vec<_>, _>>()?;
+        let refs = keys.iter().collect::<Vec<_>>();
+        let signature = BlsSignature::from_bytes(aggregate_signature.as_bytes())
+            .map_err(|e| crypto_err(format!("invalid aggregated signature {:?}", e)))?;
+        let res = signature.fast_aggregate_verify(true, msg_hash.as_bytes(), BLS_SIG_DST, &refs);
+        check(res, "verify aggregated signature")
+    }
+}
+
+impl Crypto for BlsCrypto {
+    fn hash(&self, msg: Bytes) -> Hash {
+        Hash::from(Sha256::digest(&msg).to_vec())
+    }
+
+    fn sign(&self, hash: Hash) -> Result<Signature, Box<dyn Error + Send>> {
+        let signature = self.private_key.sign(hash.as_bytes(), BLS_SIG_DST, &[]);
+        Ok(Signature::from(signature.to_bytes().to_vec()))
+    }
+
+    fn aggregate_signatures(
+        &self,
+        signatures: Vec<Signature>,
+        voters: Vec<Address>,
+    ) -> Result<Signature, Box<dyn Error + Send>> {
+        if signatures.is_empty() || signatures.len() != voters.len() {
+            return Err(crypto_err(format!(
+                "aggregate {} signatures of {} voters",
+                signatures.len(),
+                voters.len()
+            )));
+        }
+
+        let signatures = signatures
+            .iter()
+            .map(|signature| BlsSignature::from_bytes(signature.as_bytes()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| crypto_err(format!("invalid signature {:?}", e)))?;
+        let refs = signatures.iter().collect::<Vec<_>>();
+        let aggregated = AggregateSignature::aggregate(&refs, true)
+            .map_err(|e| crypto_err(format!("aggregate signatures {:?}", e)))?;
+        Ok(Signature::from(
+            aggregated.to_signature().to_bytes().to_vec(),
+        ))
+    }
+
+    fn verify_signature(
+        &self,
+        signature: Signature,
+        hash: Hash,
+        voter: Address,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.verify(None, signature, hash, voter)
+    }
+
+    fn verify_aggregated_signature(
+        &self,
+        aggregate_signature: Signature,
+        msg_hash: Hash,
+        voters: Vec<Address>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.verify_aggregated(None, aggregate_signature, msg_hash, voters)
+    }
+
+    fn rotate_key(
+        &self,
+        address: &Address,
+        rotation: &KeyRotation,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.registry
+            .insert_at(
+                address.clone(),
+                rotation.height,
+                &rotation.public_key,
+                &rotation.proof,
+            )
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send>)
+    }
+
+    fn verify_signature_at(
+        &self,
+        height: Height,
+        signature: Signature,
+        hash: Hash,
+        voter: Address,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.verify(Some(height), signature, hash, voter)
+    }
+
+    fn verify_aggregated_signature_at(
+        &self,
+        height: Height,
+        aggregate_signature: Signature,
+        msg_hash: Hash,
+        voters: Vec<Address>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.verify_aggregated(Some(height), aggregate_signature, msg_hash, voters)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::{BlsCrypto, BlsKeyRegistry};
+    use crate::types::{Address, Hash, Height, KeyRotation};
+    use crate::Crypto;
+
+    fn crypto(seed: u8, registry: &BlsKeyRegistry) -> BlsCrypto {
+        let key = BlsCrypto::derive_private_key(&[seed; 32]).unwrap();
+        BlsCrypto::new(&key, registry.clone()).unwrap()
+    }
+
+    #[test]
+    fn test_bls_crypto() {
+        let registry = BlsKeyRegistry::new();
+        let cryptos = (0..4u8)
+            .map(|seed| crypto(seed, &registry))
+            .collect::<Vec<_>>();
+        let voters = (0..4u8)
+            .map(|i| Address::from(vec![i; 20]))
+            .collect::<Vec<_>>();
+
+        // A public key is only registered with its own proof of possession.
+        assert!(registry
+            .insert(
+                voters[0].clone(),
+                &cryptos[0].public_key(),
+                &cryptos[1].proof_of_possession()
+            )
+            .is_err());
+        for (crypto, voter) in cryptos.iter().zip(voters.iter()) {
+            registry
+                .insert(
+                    voter.clone(),
+                    &crypto.public_key(),
+                    &crypto.proof_of_possession(),
+                )
+                .unwrap();
+        }
+        assert_eq!(registry.len(), 4);
+
+        let hash = cryptos[0].hash(Bytes::from_static(b"vote"));
+        let signatures = cryptos[..3]
+            .iter()
+            .map(|crypto| crypto.sign(hash.clone()).unwrap())
+            .collect::<Vec<_>>();
+        cryptos[3]
+            .verify_signature(signatures[0].clone(), hash.clone(), voters[0].clone())
+            .unwrap();
+
+        // The signature of a voter aggregated twice does not count as two voters.
+        let twice = vec![voters[0].clone(), voters[0].clone()];
+        let aggregated = cryptos[3]
+            .aggregate_signatures(vec![signatures[0].clone(); 2], twice.clone())
+            .unwrap();
+        assert!(cryptos[3]
+            .verify_aggregated_signature(aggregated, hash.clone(), twice)
+            .is_err());
+        assert!(cryptos[3]
+            .verify_signature(signatures[0].clone(), hash.clone(), voters[1].clone())
+            .is_err());
+
+        let aggregated = cryptos[3]
+            .aggregate_signatures(signatures, voters[..3].to_vec())
+            .unwrap();
+        cryptos[3]
+            .verify_aggregated_signature(aggregated.clone(), hash.clone(), voters[..3].to_vec())
+            .unwrap();
+        assert!(cryptos[3]
+            .verify_aggregated_signature(aggregated.clone(), hash, voters[1..].to_vec())
+            .is_err());
+        assert!(cryptos[3]
+            .verify_aggregated_signature(aggregated, Hash::new(), voters[..3].to_vec())
+            .is_err());
+
+        // The key of voter 0 rotates to the key of voter 3 from height 10.
+        let rotation = KeyRotation {
+            height: Height(10),
+            public_key: cryptos[3].public_key(),
+            proof: cryptos[3].proof_of_possession(),
+        };
+        cryptos[0].rotate_key(&voters[0], &rotation).unwrap();
+        let hash = cryptos[0].hash(Bytes::from_static(b"rotate"));
+        let signature = cryptos[3].sign(hash.clone()).unwrap();
+        assert!(cryptos[1]
+            .verify_signature_at(
+                Height(9),
+                signature.clone(),
+                hash.clone(),
+                voters[0].clone()
+            )
+            .is_err());
+        cryptos[1]
+            .verify_signature_at(Height(10), signature, hash, voters[0].clone())
+            .unwrap();
+
+        registry.remove(&voters[3]);
+        assert!(!registry.contains(&voters[3]));
+    }
+}
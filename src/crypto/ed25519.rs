@@ -0,0 +1,351 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use bytes::{Bytes, BytesMut};
+use ed25519_dalek::{
+    Signature as EdSignature, Signer, SigningKey, VerifyingKey, PUBLIC_KEY_LENGTH,
+    SECRET_KEY_LENGTH, SIGNATURE_LENGTH,
+};
+use parking_lot::RwLock;
+use sha2::{Digest, Sha256};
+
+use crate::error::ConsensusError;
+use crate::types::{Address, Hash, Height, KeyRotation, Signature};
+use crate::{ConsensusResult, Crypto};
+
+fn crypto_err(msg: String) -> Box<dyn Error + Send> {
+    Box::new(ConsensusError::CryptoErr(msg))
+}
+
+/// The ed25519 public keys of the validators keyed by the validator address. The registry is
+/// shared by its clones, so that the keys of the joining validators can be registered while the
+/// overlord is running. A validator may register a new key from a height on, which verifies its
+/// signatures of the height and above.
+#[derive(Clone, Default)]
+pub struct Ed25519KeyRegistry {
+    keys: Arc<RwLock<HashMap<Address, BTreeMap<Height, VerifyingKey>>>>,
+}
+
+impl fmt::Debug for Ed25519KeyRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ed25519KeyRegistry")
+            .field("len", &self.keys.read().len())
+            .finish()
+    }
+}
+
+impl Ed25519KeyRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Ed25519KeyRegistry::default()
+    }
+
+    /// Register the 32 bytes public key of a validator. The previous keys of the address are
+    /// replaced.
+    pub fn insert(&self, address: Address, public_key: &[u8]) -> ConsensusResult<()> {
+        let key = Self::validate(&address, public_key)?;
+        self.keys
+            .write()
+            .insert(address, BTreeMap::from([(Height(0), key)]));
+        Ok(())
+    }
+
+    /// Register a new key of a validator effective from the given height, while its signatures
+    /// below the height are still verified by the previous key.
+    pub fn insert_at(
+        &self,
+        address: Address,
+        height: Height,
+        public_key: &[u8],
+    ) -> ConsensusResult<()> {
+        let key = Self::validate(&address, public_key)?;
+        self.keys
+            .write()
+            .entry(address)
+            .or_default()
+            .insert(height, key);
+        Ok(())
+    }
+
+    fn validate(address: &Address, public_key: &[u8]) -> ConsensusResult<VerifyingKey> {
+        <[u8; PUBLIC_KEY_LENGTH]>::try_from(public_key)
+            .ok()
+            .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+            .ok_or_else(|| {
+                ConsensusError::CryptoErr(format!("invalid public key of {:?}", address))
+            })
+    }
+
+    /// Remove the public key of a validator.
+    pub fn remove(&self, address: &Address) {
+        self.keys.write().remove(address);
+    }
+
+    /// Whether the public key of the address is registered.
+    pub fn contains(&self, address: &Address) -> bool {
+        self.keys.read().contains_key(address)
+    }
+
+    /// The number of the registered public keys.
+    pub fn len(&self) -> usize {
+        self.keys.read().len()
+    }
+
+    /// Whether no public key is registered.
+    pub fn is_empty(&self) -> bool {
+        self.keys.read().is_empty()
+    }
+
+    /// The key of the address effective at the height, or the latest one without a height.
+    fn get(
+        &self,
+        address: &Address,
+        height: Option<Height>,
+    ) -> Result<VerifyingKey, Box<dyn Error + Send>> {
+        let keys = self.keys.read();
+        let key = keys.get(address).and_then(|keys| match height {
+            Some(height) => keys.range(..=height).next_back(),
+            None => keys.iter().next_back(),
+        });
+        key.map(|(_, key)| *key).ok_or_else(|| {
+            crypto_err(format!(
+                "unknown public key of {:?} at height {:?}",
+                address, height
+            ))
+        })
+    }
+}
+
+/// The `Crypto` of the ed25519 signatures for the chains that do without BLS. The hash is SHA-256.
+/// An aggregated signature is the concatenation of the signatures in the order of the voter
+/// addresses, which is the order of the bitmap of a QC, so it grows with the voters and suits the
+/// small networks.
+#[derive(Clone)]
+pub struct Ed25519Crypto {
+    private_key: SigningKey,
+    registry: Ed25519KeyRegistry,
+}
+
+impl fmt::Debug for Ed25519Crypto {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ed25519Crypto")
+            .field("public_key", &self.public_key())
+            .field("registry", &self.registry)
+            .finish()
+    }
+}
+
+impl Ed25519Crypto {
+    /// Create an ed25519 crypto from the 32 bytes private key, which verifies the signatures by
+    /// the public keys of the registry.
+    pub fn new(private_key: &[u8], registry: Ed25519KeyRegistry) -> ConsensusResult<Self> {
+        let private_key = <[u8; SECRET_KEY_LENGTH]>::try_from(private_key).map_err(|_| {
+            ConsensusError::CryptoErr(format!("invalid private key length {}", private_key.len()))
+        })?;
+        Ok(Ed25519Crypto {
+            private_key: SigningKey::from_bytes(&private_key),
+            registry,
+        })
+    }
+
+    /// The public key of self.
+    pub fn public_key(&self) -> Bytes {
+        Bytes::copy_from_slice(&self.private_key.verifying_key().to_bytes())
+    }
+
+    /// The registry of the public keys.
+    pub fn registry(&self) -> &Ed25519KeyRegistry {
+        &self.registry
+    }
+
+    fn verify(
+        &self,
+        height: Option<Height>,
+        signature: &[u8],
+        hash: &Hash,
+        voter: &Address,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        let key = self.registry.get(voter, height)?;
+        let signature = EdSignature::from_slice(signature)
+            .map_err(|e| crypto_err(format!("invalid signature of {:?}, {}", voter, e)))?;
+        key.verify_strict(hash.as_bytes(), &signature)
+            .map_err(|e| crypto_err(format!("verify signature of {:?}, {}", voter, e)))
+    }
+
+    fn verify_aggregated(
+        &self,
+        height: Option<Height>,
+        aggregate_signature: Signature,
+        msg_hash: Hash,
+        mut voters: Vec<Address>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        voters.sort();
+        voters.dedup();
+        if voters.is_empty()
+            || aggregate_signature.as_bytes().len() != voters.len() * SIGNATURE_LENGTH
+        {
+            return Err(crypto_err(format!(
+                "aggregated signature of {} bytes for {} voters",
+                aggregate_signature.as_bytes().len(),
+                voters.len()
+            )));
+        }
+
+        aggregate_signature
+            .as_bytes()
+            .chunks(SIGNATURE_LENGTH)
+            .zip(voters.iter())
+            .try_for_each(|(signature, voter)| self.verify(height, signature, &msg_hash, voter))
+    }
+}
+
+impl Crypto for Ed25519Crypto {
+    fn hash(&self, msg: Bytes) -> Hash {
+        Hash::from(Sha256::digest(&msg).to_vec())
+    }
+
+    fn sign(&self, hash: Hash) -> Result<Signature, Box<dyn Error + Send>> {
+        let signature = self.private_key.sign(hash.as_bytes());
+        Ok(Signature::from(signature.to_bytes().to_vec()))
+    }
+
+    fn aggregate_signatures(
+        &self,
+        signatures: Vec<Signature>,
+        voters: Vec<Address>,
+    ) -> Result<Signature, Box<dyn Error + Send>> {
+        if signatures.is_empty() || signatures.len() != voters.len() {
+            return Err(crypto_err(format!(
+                "aggregate {} signatures of {} voters",
+                signatures.len(),
+                voters.len()
+            )));
+        }
+
+        let mut pairs = voters.into_iter().zip(signatures).collect::<Vec<_>>();
+        pairs.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut aggregated = BytesMut::with_capacity(pairs.len() * SIGNATURE_LENGTH);
+        for (voter, signature) in pairs.iter() {
+            if signature.as_bytes().len() != SIGNATURE_LENGTH {
+                return Err(crypto_err(format!("invalid signature of {:?}", voter)));
+            }
+            aggregated.extend_from_slice(signature.as_bytes());
+        }
+        Ok(Signature::from(aggregated.freeze()))
+    }
+
+    fn verify_signature(
+        &self,
+        signature: Signature,
+        hash: Hash,
+        voter: Address,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.verify(None, signature.as_bytes(), &hash, &voter)
+    }
+
+    fn verify_aggregated_signature(
+        &self,
+        aggregate_signature: Signature,
+        msg_hash: Hash,
+        voters: Vec<Address>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.verify_aggregated(None, aggregate_signature, msg_hash, voters)
+    }
+
+    fn rotate_key(
+        &self,
+        address: &Address,
+        rotation: &KeyRotation,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.registry
+            .insert_at(address.clone(), rotation.height, &rotation.public_key)
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send>)
+    }
+
+    fn verify_signature_at(
+        &self,
+        height: Height,
+        signature: Signature,
+        hash: Hash,
+        voter: Address,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.verify(Some(height), signature.as_bytes(), &hash, &voter)
+    }
+
+    fn verify_aggregated_signature_at(
+        &self,
+        height: Height,
+        aggregate_signature: Signature,
+        msg_hash: Hash,
+        voters: Vec<Address>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.verify_aggregated(Some(height), aggregate_signature, msg_hash, voters)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::{Ed25519Crypto, Ed25519KeyRegistry};
+    use crate::types::{Address, Hash, Signature};
+    use crate::Crypto;
+
+    #[test]
+    fn test_ed25519_crypto() {
+        let registry = Ed25519KeyRegistry::new();
+        let cryptos = (0..4u8)
+            .map(|seed| Ed25519Crypto::new(&[seed; 32], registry.clone()).unwrap())
+            .collect::<Vec<_>>();
+        let voters = (0..4u8)
+            .map(|i| Address::from(vec![i; 20]))
+            .collect::<Vec<_>>();
+        assert!(registry.insert(voters[0].clone(), &[0u8; 31]).is_err());
+        for (crypto, voter) in cryptos.iter().zip(voters.iter()) {
+            registry
+                .insert(voter.clone(), &crypto.public_key())
+                .unwrap();
+        }
+
+        let hash = cryptos[0].hash(Bytes::from_static(b"vote"));
+        let signatures = cryptos[..3]
+            .iter()
+            .map(|crypto| crypto.sign(hash.clone()).unwrap())
+            .collect::<Vec<_>>();
+        cryptos[3]
+            .verify_signature(signatures[1].clone(), hash.clone(), voters[1].clone())
+            .unwrap();
+        assert!(cryptos[3]
+            .verify_signature(signatures[1].clone(), hash.clone(), voters[0].clone())
+            .is_err());
+
+        // The signatures are concatenated in the order of the voters whatever the given order.
+        let mut reversed = signatures.clone();
+        reversed.reverse();
+        let mut reversed_voters = voters[..3].to_vec();
+        reversed_voters.reverse();
+        let aggregated = cryptos[3]
+            .aggregate_signatures(reversed, reversed_voters)
+            .unwrap();
+        assert_eq!(
+            aggregated,
+            cryptos[3]
+                .aggregate_signatures(signatures, voters[..3].to_vec())
+                .unwrap()
+        );
+        cryptos[3]
+            .verify_aggregated_signature(aggregated.clone(), hash.clone(), voters[..3].to_vec())
+            .unwrap();
+        assert!(cryptos[3]
+            .verify_aggregated_signature(aggregated.clone(), hash.clone(), voters[1..].to_vec())
+            .is_err());
+        assert!(cryptos[3]
+            .verify_aggregated_signature(aggregated, Hash::new(), voters[..3].to_vec())
+            .is_err());
+        assert!(cryptos[3]
+            .verify_aggregated_signature(Signature::new(), hash, Vec::new())
+            .is_err());
+    }
+}
@@ -0,0 +1,8 @@
+/// The BLS12-381 aggregate signatures.
+#[cfg(feature = "bls")]
+pub mod bls;
+/// The ed25519 signatures, aggregated by concatenation.
+#[cfg(feature = "ed25519")]
+pub mod ed25519;
+/// The signing by a signer in a separate process.
+pub mod remote;
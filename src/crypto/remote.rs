@@ -0,0 +1,940 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use hummer::coding::{hex_decode, hex_encode};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ConsensusError;
+use crate::types::{
+    Address, Choke, CompactProposal, Hash, Height, KeyRotation, Round, Signature, Vote, VoteType,
+};
+use crate::Crypto;
+
+/// The default timeout of a request to the remote signer.
+pub const DEFAULT_SIGN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The largest response of `HttpSigner` read, as byte.
+pub const MAX_RESPONSE_SIZE: usize = 64 * 1024;
+
+fn crypto_err(msg: String) -> Box<dyn Error + Send> {
+    Box::new(ConsensusError::CryptoErr(msg))
+}
+
+/// A request to the remote signer. Every request is typed, so that the signer checks what it signs,
+/// and there is no request of a bare hash. The hash of each request is given for convenience, and
+/// the signer derives it from the request by itself, as `Crypto::hash` of the payload bound to the
+/// chain id by `domain_payload`, which is the payload itself for an empty chain id, or else the
+/// RLP list of the chain id and the payload. The payload is the RLP encoding of
+///
+/// - a vote, the list of the height, the round, the vote type as `1` for a prevote or `2` for a
+///   precommit, and the block hash;
+/// - a proposal, the `CompactProposal` in the compact proposal mode or with the block parts, while
+///   the full proposal with its block is signed otherwise, of which the signer only checks the
+///   height and the round;
+/// - a choke, the list of the height and the round;
+/// - a vote extension, the list of the height, the round and the block hash of the vote, and the
+///   extension data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignRequest {
+    /// Sign the hash of a vote. The vote is given, so that the signer can guard against double
+    /// signing by itself.
+    Vote {
+        /// The vote to sign.
+        vote: Vote,
+        /// The hash of the vote bound to the chain id.
+        hash: Hash,
+    },
+    /// Sign the hash of a proposal. The proposal is given without its block, so that the signer
+    /// can guard against double proposing by itself.
+    Proposal {
+        /// The proposal to sign.
+        proposal: Box<CompactProposal>,
+        /// The hash of the proposal bound to the chain id.
+        hash: Hash,
+    },
+    /// Sign the hash of a choke of the height and the round.
+    Choke {
+        /// The height of the choke.
+        height: Height,
+        /// The round of the choke.
+        round: Round,
+        /// The hash of the choke bound to the chain id.
+        hash: Hash,
+    },
+    /// Sign the hash of the extension data of a precommit vote.
+    Extension {
+        /// The precommit vote which the data extends.
+        vote: Vote,
+        /// The extension data.
+        data: Bytes,
+        /// The hash of the extension bound to the chain id.
+        hash: Hash,
+    },
+}
+
+/// The JSON body of a request of `HttpSigner`.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum HttpRequest {
+    Vote {
+        height: Height,
+        round: Round,
+        vote_type: String,
+        block_hash: String,
+        hash: String,
+    },
+    Proposal {
+        height: Height,
+        round: Round,
+        block_hash: String,
+        proposer: String,
+        hash: String,
+    },
+    Choke {
+        height: Height,
+        round: Round,
+        hash: String,
+    },
+    Extension {
+        height: Height,
+        round: Round,
+        block_hash: String,
+        data: String,
+        hash: String,
+    },
+}
+
+impl From<SignRequest> for HttpRequest {
+    fn from(request: SignRequest) -> Self {
+        match request {
+            SignRequest::Vote { vote, hash } => HttpRequest::Vote {
+                height: vote.height,
+                round: vote.round,
+                vote_type: vote.vote_type.to_string(),
+                block_hash: hex_encode(vote.block_hash),
+                hash: hex_encode(hash),
+            },
+            SignRequest::Proposal { proposal, hash } => HttpRequest::Proposal {
+                height: proposal.height,
+                round: proposal.round,
+                block_hash: hex_encode(proposal.block_hash),
+                proposer: hex_encode(proposal.proposer),
+                hash: hex_encode(hash),
+            },
+            SignRequest::Choke {
+                height,
+                round,
+                hash,
+            } => HttpRequest::Choke {
+                height,
+                round,
+                hash: hex_encode(hash),
+            },
+            SignRequest::Extension { vote, data, hash } => HttpRequest::Extension {
+                height: vote.height,
+                round: vote.round,
+                block_hash: hex_encode(vote.block_hash),
+                data: hex_encode(data),
+                hash: hex_encode(hash),
+            },
+        }
+    }
+}
+
+/// The JSON body of a response of `HttpSigner`.
+#[derive(Deserialize)]
+struct HttpResponse {
+    signature: String,
+}
+
+/// The client of a signer which keeps the private key in a separate process, such as a key
+/// management service behind gRPC or HTTP. The transport is up to the implementation, and
+/// `HttpSigner` is a client over HTTP.
+#[async_trait]
+pub trait RemoteSigner: Send + Sync {
+    /// Send the request to the signer and return the signature.
+    async fn sign(&self, request: SignRequest) -> Result<Signature, Box<dyn Error + Send>>;
+}
+
+/// A `RemoteSigner` over HTTP/1.1, which posts each request as a JSON object to the path of the
+/// signer on a new connection, and reads the signature from the JSON object of the response. A
+/// vote is requested by
+/// `{"type":"vote","height":5,"round":1,"vote_type":"Prevote","block_hash":"<hex>","hash":"<hex>"}`,
+/// a proposal by
+/// `{"type":"proposal","height":5,"round":1,"block_hash":"<hex>","proposer":"<hex>","hash":"<hex>"}`,
+/// a choke by `{"type":"choke","height":5,"round":1,"hash":"<hex>"}` and a vote extension by
+/// `{"type":"extension","height":5,"round":1,"block_hash":"<hex>","data":"<hex>","hash":"<hex>"}`,
+/// to which the signer responds `200 OK` with `{"signature":"<hex>"}`. See `SignRequest` for the
+/// payload of each hash. The blocking connection is served by the blocking pool of the runtime,
+/// and the whole request, from connecting to reading at most `MAX_RESPONSE_SIZE` bytes of the
+/// response, is bounded by the timeout.
+///
+/// The connection is plain TCP without TLS, so the client only talks to a signer on a loopback
+/// address, such as a TLS tunnel or a sidecar which forwards the requests to the key management
+/// service over an authenticated channel. A signer on another host is refused unless
+/// `allow_insecure` is set. The requests carry the bearer token of `with_auth_token` if any, which
+/// the signer or the sidecar checks.
+#[derive(Clone)]
+pub struct HttpSigner {
+    addr: SocketAddr,
+    path: String,
+    timeout: Duration,
+    auth_token: Option<String>,
+    insecure: bool,
+}
+
+impl fmt::Debug for HttpSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpSigner")
+            .field("addr", &self.addr)
+            .field("path", &self.path)
+            .field("timeout", &self.timeout)
+            .field("insecure", &self.insecure)
+            .finish_non_exhaustive()
+    }
+}
+
+impl HttpSigner {
+    /// Create a client of the signer at the address, which serves the requests on the path.
+    pub fn new(addr: SocketAddr, path: impl Into<String>) -> Self {
+        HttpSigner {
+            addr,
+            path: path.into(),
+            timeout: DEFAULT_SIGN_TIMEOUT,
+            auth_token: None,
+            insecure: false,
+        }
+    }
+
+    /// Set the timeout of a whole request, which is `DEFAULT_SIGN_TIMEOUT` by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Send the token as `Authorization: Bearer <token>` with every request.
+    pub fn with_auth_token(mut self, token: impl Into<String>) -> Self {
+        self.auth_token = Some(token.into());
+        self
+    }
+
+    /// Allow a signer on a non-loopback address, to which the requests and the signatures go in
+    /// plaintext. It is only for a network which is trusted as a whole.
+    pub fn allow_insecure(mut self) -> Self {
+        self.insecure = true;
+        self
+    }
+
+    fn post(&self, body: String) -> Result<Signature, Box<dyn Error + Send>> {
+        if !self.insecure && !self.addr.ip().is_loopback() {
+            return Err(crypto_err(format!(
+                "remote signer {} is not on a loopback address, which needs an authenticated \
+                 transport",
+                self.addr
+            )));
+        }
+        let io_err = |e: std::io::Error| crypto_err(format!("remote signer {:?}", e));
+        // The socket timeouts are of each operation, so each is set to what is left of the
+        // deadline, and a signer which responds byte by byte does not hold the thread.
+        let deadline = Instant::now() + self.timeout;
+        let remaining = || {
+            deadline
+                .checked_duration_since(Instant::now())
+                .filter(|left| !left.is_zero())
+                .ok_or_else(|| crypto_err(format!("remote sign timeout {:?}", self.timeout)))
+        };
+        let mut stream = TcpStream::connect_timeout(&self.addr, remaining()?).map_err(io_err)?;
+        stream
+            .set_write_timeout(Some(remaining()?))
+            .map_err(io_err)?;
+
+        let auth = self
+            .auth_token
+            .as_ref()
+            .map(|token| format!("Authorization: Bearer {}\r\n", token))
+            .unwrap_or_default();
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\n{}Content-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path,
+            self.addr,
+            auth,
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).map_err(io_err)?;
+        let mut response = Vec::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            stream
+                .set_read_timeout(Some(remaining()?))
+                .map_err(io_err)?;
+            let len = stream.read(&mut buf).map_err(io_err)?;
+            if len == 0 {
+                break;
+            }
+            if response.len() + len > MAX_RESPONSE_SIZE {
+                return Err(crypto_err(format!(
+                    "response of the remote signer exceeds {} bytes",
+                    MAX_RESPONSE_SIZE
+                )));
+            }
+            response.extend_from_slice(&buf[..len]);
+        }
+        parse_response(&response)
+    }
+}
+
+#[async_trait]
+impl RemoteSigner for HttpSigner {
+    async fn sign(&self, request: SignRequest) -> Result<Signature, Box<dyn Error + Send>> {
+        let body = serde_json::to_string(&HttpRequest::from(request))
+            .map_err(|e| crypto_err(format!("encode remote sign request {:?}", e)))?;
+        let signer = self.clone();
+        tokio::task::spawn_blocking(move || signer.post(body))
+            .await
+            .map_err(|e| crypto_err(format!("remote signer task {:?}", e)))?
+    }
+}
+
+/// Parse the signature out of an HTTP/1.1 response of the signer, of which the body is delimited
+/// by the `Content-Length`, the chunked encoding or the end of the connection.
+fn parse_response(response: &[u8]) -> Result<Signature, Box<dyn Error + Send>> {
+    let malformed = || crypto_err("malformed response of the remote signer".to_string());
+    let split = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(malformed)?;
+    let head = std::str::from_utf8(&response[..split]).map_err(|_| malformed())?;
+    let mut body = response[split + 4..].to_vec();
+
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(malformed)?;
+    for line in lines {
+        let (name, value) = line.split_once(':').ok_or_else(malformed)?;
+        let (name, value) = (name.trim().to_ascii_lowercase(), value.trim());
+        if name == "content-length" {
+            let len = value.parse::<usize>().map_err(|_| malformed())?;
+            body.truncate(len);
+        } else if name == "transfer-encoding" && value.eq_ignore_ascii_case("chunked") {
+            body = decode_chunked(&body).ok_or_else(malformed)?;
+        }
+    }
+
+    let body = String::from_utf8(body).map_err(|_| malformed())?;
+    if status != 200 {
+        return Err(crypto_err(format!(
+            "remote signer responds {} {}",
+            status,
+            body.trim()
+        )));
+    }
+    let response = serde_json::from_str::<HttpResponse>(&body).map_err(|_| malformed())?;
+    let signature = response.signature;
+    let signature = hex_decode(signature.trim_start_matches("0x")).map_err(|_| malformed())?;
+    Ok(Signature::from(signature))
+}
+
+fn decode_chunked(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    loop {
+        let end = body.windows(2).position(|window| window == b"\r\n")?;
+        let size = std::str::from_utf8(&body[..end]).ok()?;
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        body = &body[end + 2..];
+        if size == 0 {
+            return Some(decoded);
+        }
+        decoded.extend_from_slice(body.get(..size)?);
+        body = body.get(size + 2..)?;
+    }
+}
+
+type SignJob = (
+    SignRequest,
+    Duration,
+    mpsc::SyncSender<Result<Signature, Box<dyn Error + Send>>>,
+);
+
+/// Start the thread which sends the requests to the signer on a runtime of its own, so that the
+/// signing neither depends on the runtime of the caller nor blocks it in place.
+fn spawn_worker<S: RemoteSigner + 'static>(
+    signer: Arc<S>,
+) -> Result<mpsc::Sender<SignJob>, Box<dyn Error + Send>> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| crypto_err(format!("remote sign runtime {:?}", e)))?;
+    let (tx, rx) = mpsc::channel::<SignJob>();
+    thread::Builder::new()
+        .name("overlord-remote-signer".to_string())
+        .spawn(move || {
+            for (request, timeout, reply) in rx.iter() {
+                let res = runtime.block_on(async {
+                    tokio::time::timeout(timeout, signer.sign(request))
+                        .await
+                        .map_err(|_| crypto_err(format!("remote sign timeout {:?}", timeout)))?
+                });
+                let _ = reply.send(res);
+            }
+        })
+        .map_err(|e| crypto_err(format!("spawn remote signer worker {:?}", e)))?;
+    Ok(tx)
+}
+
+/// A `Crypto` which signs by a remote signer, and hashes, aggregates and verifies by a local
+/// `Crypto` which needs no private key. Every request waits for the signer up to the timeout.
+/// The votes pass a local double sign guard before they are sent, which keeps the last signed
+/// vote of each vote type, and refuses a vote of a lower height and round, or of another block
+/// hash at the same height and round.
+///
+/// The requests are sent by a dedicated thread with a runtime of its own, which is started on the
+/// first request, and the signing waits for it up to the timeout. The signing blocks the calling
+/// thread, so it reports `is_sign_blocking`, and the overlord signs on the blocking thread pool
+/// rather than on a worker of the runtime.
+pub struct RemoteCrypto<C: Crypto, S: RemoteSigner> {
+    local: C,
+    signer: Arc<S>,
+    timeout: Duration,
+    signed: Mutex<HashMap<VoteType, (Height, Round, Hash)>>,
+    worker: Mutex<Option<mpsc::Sender<SignJob>>>,
+}
+
+impl<C: Crypto, S: RemoteSigner> fmt::Debug for RemoteCrypto<C, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteCrypto")
+            .field("timeout", &self.timeout)
+            .field("signed", &self.signed.lock())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C: Crypto, S: RemoteSigner + 'static> RemoteCrypto<C, S> {
+    /// Create a remote crypto with the default timeout.
+    pub fn new(local: C, signer: Arc<S>) -> Self {
+        RemoteCrypto {
+            local,
+            signer,
+            timeout: DEFAULT_SIGN_TIMEOUT,
+            signed: Mutex::new(HashMap::new()),
+            worker: Mutex::new(None),
+        }
+    }
+
+    /// Set the timeout of a request to the remote signer.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the last signed vote of each vote type, such as the ones persisted before a restart.
+    pub fn with_signed_votes(self, votes: Vec<Vote>) -> Self {
+        {
+            let mut signed = self.signed.lock();
+            for vote in votes.into_iter() {
+                signed.insert(vote.vote_type, (vote.height, vote.round, vote.block_hash));
+            }
+        }
+        self
+    }
+
+    /// The last signed vote of the vote type.
+    pub fn last_signed(&self, vote_type: &VoteType) -> Option<(Height, Round, Hash)> {
+        self.signed.lock().get(vote_type).cloned()
+    }
+
+    /// Check the vote against the last signed one of its type, and record it if it is signable.
+    fn guard(&self, vote: &Vote) -> Result<(), Box<dyn Error + Send>> {
+        let mut signed = self.signed.lock();
+        if let Some((height, round, hash)) = signed.get(&vote.vote_type) {
+            let conflict = match (vote.height, vote.round).cmp(&(*height, *round)) {
+                std::cmp::Ordering::Less => true,
+                std::cmp::Ordering::Equal => hash != &vote.block_hash,
+                std::cmp::Ordering::Greater => false,
+            };
+            if conflict {
+                return Err(Box::new(ConsensusError::RefuseSignErr {
+                    vote: vote.clone(),
+                    height: *height,
+                    round: *round,
+                }));
+            }
+        }
+        signed.insert(
+            vote.vote_type.clone(),
+            (vote.height, vote.round, vote.block_hash.clone()),
+        );
+        Ok(())
+    }
+
+    fn request(&self, request: SignRequest) -> Result<Signature, Box<dyn Error + Send>> {
+        let (reply_tx, reply_rx) = mpsc::sync_channel(1);
+        {
+            let mut worker = self.worker.lock();
+            if worker.is_none() {
+                *worker = Some(spawn_worker(Arc::clone(&self.signer))?);
+            }
+            let job = (request, self.timeout, reply_tx);
+            if worker.as_ref().is_some_and(|tx| tx.send(job).is_err()) {
+                *worker = None;
+                return Err(crypto_err("remote signer worker is gone".to_string()));
+            }
+        }
+
+        // The worker times the request out, and a request queued behind a hanging one is timed
+        // out here.
+        reply_rx
+            .recv_timeout(self.timeout)
+            .map_err(|_| crypto_err(format!("remote sign timeout {:?}", self.timeout)))?
+    }
+}
+
+impl<C: Crypto, S: RemoteSigner + 'static> Crypto for RemoteCrypto<C, S> {
+    fn hash(&self, msg: Bytes) -> Hash {
+        self.local.hash(msg)
+    }
+
+    /// A bare hash is never sent to the signer, which could not tell what it signs.
+    fn sign(&self, _hash: Hash) -> Result<Signature, Box<dyn Error + Send>> {
+        Err(crypto_err(
+            "remote signer signs the typed requests only".to_string(),
+        ))
+    }
+
+    fn sign_vote(&self, vote: &Vote, hash: Hash) -> Result<Signature, Box<dyn Error + Send>> {
+        self.guard(vote)?;
+        self.request(SignRequest::Vote {
+            vote: vote.clone(),
+            hash,
+        })
+    }
+
+    fn sign_proposal(
+        &self,
+        proposal: &CompactProposal,
+        hash: Hash,
+    ) -> Result<Signature, Box<dyn Error + Send>> {
+        self.request(SignRequest::Proposal {
+            proposal: Box::new(proposal.clone()),
+            hash,
+        })
+    }
+
+    fn sign_choke(&self, choke: &Choke, hash: Hash) -> Result<Signature, Box<dyn Error + Send>> {
+        self.request(SignRequest::Choke {
+            height: choke.height,
+            round: choke.round,
+            hash,
+        })
+    }
+
+    fn sign_extension(
+        &self,
+        vote: &Vote,
+        data: &Bytes,
+        hash: Hash,
+    ) -> Result<Signature, Box<dyn Error + Send>> {
+        self.request(SignRequest::Extension {
+            vote: vote.clone(),
+            data: data.clone(),
+            hash,
+        })
+    }
+
+    fn is_sign_blocking(&self) -> bool {
+        true
+    }
+
+    fn aggregate_signatures(
+        &self,
+        signatures: Vec<Signature>,
+        voters: Vec<Address>,
+    ) -> Result<Signature, Box<dyn Error + Send>> {
+        self.local.aggregate_signatures(signatures, voters)
+    }
+
+    fn verify_signature(
+        &self,
+        signature: Signature,
+        hash: Hash,
+        voter: Address,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.local.verify_signature(signature, hash, voter)
+    }
+
+    fn verify_aggregated_signature(
+        &self,
+        aggregate_signature: Signature,
+        msg_hash: Hash,
+        voters: Vec<Address>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.local
+            .verify_aggregated_signature(aggregate_signature, msg_hash, voters)
+    }
+
+    fn combine_threshold_signature(
+        &self,
+        signatures: Vec<Signature>,
+        voters: Vec<Address>,
+    ) -> Result<Signature, Box<dyn Error + Send>> {
+        self.local.combine_threshold_signature(signatures, voters)
+    }
+
+    fn verify_threshold_signature(
+        &self,
+        height: Height,
+        signature: Signature,
+        msg_hash: Hash,
+        authority: Vec<Address>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.local
+            .verify_threshold_signature(height, signature, msg_hash, authority)
+    }
+
+    fn rotate_key(
+        &self,
+        address: &Address,
+        rotation: &KeyRotation,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.local.rotate_key(address, rotation)
+    }
+
+    fn verify_signature_at(
+        &self,
+        height: Height,
+        signature: Signature,
+        hash: Hash,
+        voter: Address,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.local
+            .verify_signature_at(height, signature, hash, voter)
+    }
+
+    fn verify_aggregated_signature_at(
+        &self,
+        height: Height,
+        aggregate_signature: Signature,
+        msg_hash: Hash,
+        voters: Vec<Address>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.local
+            .verify_aggregated_signature_at(height, aggregate_signature, msg_hash, voters)
+    }
+
+    fn vrf_prove(&self, msg: Bytes) -> Result<(Hash, Bytes), Box<dyn Error + Send>> {
+        self.local.vrf_prove(msg)
+    }
+
+    fn vrf_verify(
+        &self,
+        proof: Bytes,
+        msg: Bytes,
+        signer: Address,
+    ) -> Result<Hash, Box<dyn Error + Send>> {
+        self.local.vrf_verify(proof, msg, signer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use bytes::Bytes;
+
+    use super::{HttpSigner, RemoteCrypto, RemoteSigner, SignRequest, MAX_RESPONSE_SIZE};
+    use crate::types::{
+        Address, AggregatedChoke, Choke, CompactProposal, Hash, Height, Round, Signature,
+        UpdateFrom, Vote, VoteType,
+    };
+    use crate::Crypto;
+
+    struct LocalCrypto;
+
+    impl Crypto for LocalCrypto {
+        fn hash(&self, msg: Bytes) -> Hash {
+            Hash::from(msg)
+        }
+
+        fn sign(&self, _hash: Hash) -> Result<Signature, Box<dyn Error + Send>> {
+            unreachable!("the private key is remote")
+        }
+
+        fn aggregate_signatures(
+            &self,
+            _signatures: Vec<Signature>,
+            _voters: Vec<Address>,
+        ) -> Result<Signature, Box<dyn Error + Send>> {
+            Ok(Signature::new())
+        }
+
+        fn verify_signature(
+            &self,
+            _signature: Signature,
+            _hash: Hash,
+            _voter: Address,
+        ) -> Result<(), Box<dyn Error + Send>> {
+            Ok(())
+        }
+
+        fn verify_aggregated_signature(
+            &self,
+            _aggregate_signature: Signature,
+            _hash: Hash,
+            _voters: Vec<Address>,
+        ) -> Result<(), Box<dyn Error + Send>> {
+            Ok(())
+        }
+    }
+
+    /// Sign by echoing the hash, and hang on the empty hash.
+    struct EchoSigner;
+
+    #[async_trait]
+    impl RemoteSigner for EchoSigner {
+        async fn sign(&self, request: SignRequest) -> Result<Signature, Box<dyn Error + Send>> {
+            let hash = match request {
+                SignRequest::Vote { hash, .. }
+                | SignRequest::Proposal { hash, .. }
+                | SignRequest::Choke { hash, .. }
+                | SignRequest::Extension { hash, .. } => hash,
+            };
+            if hash.as_bytes().is_empty() {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+            Ok(Signature::from(hash.into_bytes()))
+        }
+    }
+
+    fn vote(round: u64, hash: u8) -> Vote {
+        Vote {
+            height: Height(5),
+            round: Round(round),
+            vote_type: VoteType::Prevote,
+            block_hash: Hash::from(vec![hash]),
+        }
+    }
+
+    fn choke(round: u64) -> Choke {
+        Choke {
+            height: Height(5),
+            round: Round(round),
+            from: UpdateFrom::ChokeQC(AggregatedChoke {
+                height: Height(5),
+                round: Round(round - 1),
+                signature: Signature::new(),
+                voters: Vec::new(),
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remote_crypto() {
+        let crypto = RemoteCrypto::new(LocalCrypto, Arc::new(EchoSigner))
+            .with_timeout(Duration::from_millis(100));
+        let hash = Hash::from(vec![1u8]);
+        assert_eq!(
+            crypto.sign_choke(&choke(1), hash.clone()).unwrap(),
+            Signature::from(hash.clone().into_bytes())
+        );
+        assert!(crypto.sign_choke(&choke(1), Hash::new()).is_err());
+        // A bare hash is never sent.
+        assert!(crypto.sign(hash.clone()).is_err());
+
+        // A vote conflicting with the last signed one is refused before it is sent.
+        crypto.sign_vote(&vote(1, 1), hash.clone()).unwrap();
+        crypto.sign_vote(&vote(1, 1), hash.clone()).unwrap();
+        assert!(crypto.sign_vote(&vote(1, 2), hash.clone()).is_err());
+        assert!(crypto.sign_vote(&vote(0, 1), hash.clone()).is_err());
+        crypto.sign_vote(&vote(2, 2), hash).unwrap();
+        assert_eq!(
+            crypto.last_signed(&VoteType::Prevote),
+            Some((Height(5), Round(2), Hash::from(vec![2u8])))
+        );
+        assert_eq!(crypto.last_signed(&VoteType::Precommit), None);
+    }
+
+    /// Serve the requests of the given count of connections, responding the signature of the
+    /// hash of a request as the hash itself, or an error to an empty hash or a request without
+    /// the bearer token.
+    fn serve_http(listener: TcpListener, connections: usize) -> thread::JoinHandle<Vec<String>> {
+        thread::spawn(move || {
+            let mut bodies = Vec::new();
+            for stream in listener.incoming().take(connections) {
+                let mut stream = stream.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                let (head, body) = loop {
+                    let len = stream.read(&mut buf).unwrap();
+                    request.extend_from_slice(&buf[..len]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let len = head
+                            .lines()
+                            .find_map(|line| line.strip_prefix("Content-Length: "))
+                            .unwrap()
+                            .parse::<usize>()
+                            .unwrap();
+                        if body.len() >= len {
+                            break (head.to_string(), body.to_string());
+                        }
+                    }
+                };
+                let authorized = head
+                    .lines()
+                    .any(|line| line == "Authorization: Bearer secret");
+                let json = serde_json::from_str::<serde_json::Value>(&body).unwrap();
+                let hash = json["hash"].as_str().unwrap().to_string();
+                let response = if hash.is_empty() || !authorized {
+                    "HTTP/1.1 403 Forbidden\r\nContent-Length: 7\r\n\r\nrefused".to_string()
+                } else {
+                    let json = format!(r#"{{"signature": "{}"}}"#, hash);
+                    format!(
+                        "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+                        json.len(),
+                        json
+                    )
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+                bodies.push(body);
+            }
+            bodies
+        })
+    }
+
+    #[tokio::test]
+    async fn test_http_signer() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = serve_http(listener, 5);
+
+        let signer = HttpSigner::new(addr, "/sign")
+            .with_timeout(Duration::from_secs(5))
+            .with_auth_token("secret");
+        let crypto = RemoteCrypto::new(LocalCrypto, Arc::new(signer));
+        assert!(crypto.is_sign_blocking());
+        let hash = Hash::from(vec![1u8, 2]);
+        assert_eq!(
+            crypto.sign_choke(&choke(1), hash.clone()).unwrap(),
+            Signature::from(vec![1u8, 2])
+        );
+        assert_eq!(
+            crypto.sign_vote(&vote(1, 3), hash.clone()).unwrap(),
+            Signature::from(vec![1u8, 2])
+        );
+        let proposal = CompactProposal {
+            height: Height(5),
+            round: Round(1),
+            block_hash: Hash::from(vec![3u8]),
+            proposer: Address::from(vec![4u8]),
+            lock: None,
+            vrf: None,
+            parts: None,
+        };
+        assert_eq!(
+            crypto.sign_proposal(&proposal, hash.clone()).unwrap(),
+            Signature::from(vec![1u8, 2])
+        );
+        let mut precommit = vote(1, 3);
+        precommit.vote_type = VoteType::Precommit;
+        let data = Bytes::from(vec![5u8]);
+        assert_eq!(
+            crypto.sign_extension(&precommit, &data, hash).unwrap(),
+            Signature::from(vec![1u8, 2])
+        );
+        assert!(crypto
+            .sign_extension(&precommit, &data, Hash::new())
+            .is_err());
+
+        let bodies = server.join().unwrap();
+        assert_eq!(
+            bodies[0],
+            r#"{"type":"choke","height":5,"round":1,"hash":"0102"}"#
+        );
+        assert_eq!(
+            bodies[1],
+            r#"{"type":"vote","height":5,"round":1,"vote_type":"Prevote","block_hash":"03","hash":"0102"}"#
+        );
+        assert_eq!(
+            bodies[2],
+            r#"{"type":"proposal","height":5,"round":1,"block_hash":"03","proposer":"04","hash":"0102"}"#
+        );
+        assert_eq!(
+            bodies[3],
+            r#"{"type":"extension","height":5,"round":1,"block_hash":"03","data":"05","hash":"0102"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_http_signer_deadline() {
+        // One signer trickles a byte at a time, and the other responds without end.
+        for trickle in [true, false] {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            let server = thread::spawn(move || {
+                let (mut stream, _) = listener.accept().unwrap();
+                let _ = stream.read(&mut [0u8; 1024]);
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n");
+                let chunk = if trickle {
+                    vec![b' ']
+                } else {
+                    vec![b' '; 4096]
+                };
+                for _ in 0..MAX_RESPONSE_SIZE {
+                    if stream.write_all(&chunk).is_err() {
+                        break;
+                    }
+                    if trickle {
+                        thread::sleep(Duration::from_millis(20));
+                    }
+                }
+            });
+
+            let signer = HttpSigner::new(addr, "/sign").with_timeout(Duration::from_millis(300));
+            let crypto = RemoteCrypto::new(LocalCrypto, Arc::new(signer));
+            let start = std::time::Instant::now();
+            assert!(crypto.sign_choke(&choke(1), Hash::from(vec![1u8])).is_err());
+            assert!(start.elapsed() < Duration::from_secs(2));
+            drop(crypto);
+            server.join().unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_http_signer_auth() {
+        // A signer on another host is refused before connecting, as the transport is plaintext.
+        let addr = "192.0.2.1:80".parse().unwrap();
+        let signer = HttpSigner::new(addr, "/sign").with_timeout(Duration::from_millis(100));
+        let crypto = RemoteCrypto::new(LocalCrypto, Arc::new(signer));
+        let err = crypto
+            .sign_choke(&choke(1), Hash::from(vec![1u8]))
+            .unwrap_err();
+        assert!(format!("{}", err).contains("loopback"), "{}", err);
+
+        // A request without the bearer token is refused by the signer.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = serve_http(listener, 1);
+        let crypto = RemoteCrypto::new(LocalCrypto, Arc::new(HttpSigner::new(addr, "/sign")));
+        assert!(crypto.sign_choke(&choke(1), Hash::from(vec![1u8])).is_err());
+        server.join().unwrap();
+    }
+}
@@ -1,15 +1,24 @@
 #[cfg(test)]
 use std::cmp::{Eq, PartialEq};
 use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
 
 use derive_more::Display;
+use hummer::coding::hex_encode;
+use parking_lot::Mutex;
 
-/// Overlord consensus error.
+use crate::smr::smr_types::Step;
+use crate::transport::PeerId;
+use crate::types::{Address, Hash, Height, Round, Vote, VoteType};
+
+/// Overlord consensus error. The variants of the same category share the prefix of the message,
+/// and `kind` tells the category without matching on the variants.
 #[derive(Clone, Debug, Display)]
 pub enum ConsensusError {
-    ///
+    /// The address is not in the authority list.
     #[display("Invalid address")]
-    InvalidAddress,
+    InvalidAddress(Address),
     ///
     #[display("Channel error {:?}", _0)]
     ChannelErr(String),
@@ -38,9 +47,9 @@ pub enum ConsensusError {
     #[display("Self round is {}, vote round is {}", local, vote)]
     RoundDiff {
         ///
-        local: u64,
+        local: Round,
         ///
-        vote: u64,
+        vote: Round,
     },
     ///
     #[display("Self check not pass {}", _0)]
@@ -56,7 +65,7 @@ pub enum ConsensusError {
     StateErr(String),
     ///
     #[display("Multiple proposal in height {}, round {}", _0, _1)]
-    MultiProposal(u64, u64),
+    MultiProposal(Height, Round),
     ///
     #[display("Storage error {}", _0)]
     StorageErr(String),
@@ -64,27 +73,478 @@ pub enum ConsensusError {
     #[display("Save Wal error {}, {}, {} step", height, round, step)]
     SaveWalErr {
         ///
-        height: u64,
+        height: Height,
         ///
-        round: u64,
+        round: Round,
         ///
         step: String,
     },
     ///
     #[display("Load Wal error {}", _0)]
     LoadWalErr(String),
+    /// A wal record is torn or corrupted, or of an unknown format version.
+    #[display("Corrupted Wal error {}", _0)]
+    CorruptedWalErr(String),
     ///
     #[display("Crypto error {}", _0)]
     CryptoErr(String),
     ///
     #[display("Aggregated signature error {}", _0)]
     AggregatedSignatureErr(String),
+    /// A fatal safety error, such as self has signed conflicting votes.
+    #[display("Safety error {}", _0)]
+    SafetyErr(String),
+    ///
+    #[display("Compatibility error {}", _0)]
+    CompatErr(String),
+    /// A message is malformed.
+    #[display("Decode error {}", _0)]
+    DecodeErr(String),
+    /// A message exceeds a limit of the `CodecConfig`.
+    #[display("Decode limit error {} is {}, above the limit {}", limit, len, max)]
+    DecodeLimitErr {
+        /// The exceeded limit.
+        limit: DecodeLimit,
+        /// The decoded length.
+        len: usize,
+        /// The limit.
+        max: usize,
+    },
+    /// A call of the `Crypto` fails.
+    #[display("Crypto error {} error {:?}", op, source)]
+    CryptoCallErr {
+        /// The called operation, such as `sign vote`.
+        op: &'static str,
+        /// The error of the `Crypto`.
+        source: ExternalError,
+    },
+    /// A call of the `Consensus` or the `BlockProvider` fails.
+    #[display("Other error {} error {:?}", op, source)]
+    ConsensusCallErr {
+        /// The called operation, such as `commit`.
+        op: &'static str,
+        /// The height of the call.
+        height: Height,
+        /// The error of the implementation.
+        source: ExternalError,
+    },
+    /// The signature of a QC, a proof or a vote extension fails the verification.
+    #[display("Aggregated signature error {:?}", source)]
+    InvalidSignatureErr {
+        /// The height of the signed vote.
+        height: Height,
+        /// The round of the signed vote.
+        round: Round,
+        /// The error of the `Crypto`.
+        source: ExternalError,
+    },
+    /// Self has signed another block hash with the same height, round and vote type.
+    #[display(
+        "Safety error double sign {:?} vote height {}, round {}, signed hash {:?}, new hash {:?}",
+        vote_type,
+        height,
+        round,
+        hex_encode(signed),
+        hex_encode(hash)
+    )]
+    DoubleSignErr {
+        /// The vote type.
+        vote_type: VoteType,
+        /// The height of the votes.
+        height: Height,
+        /// The round of the votes.
+        round: Round,
+        /// The signed block hash.
+        signed: Hash,
+        /// The block hash to sign.
+        hash: Hash,
+    },
+    /// The signer refuses a vote below or conflicting with the last signed one of its type.
+    #[display(
+        "Safety error refuse to sign {}, last signed height {}, round {}",
+        vote,
+        height,
+        round
+    )]
+    RefuseSignErr {
+        /// The refused vote.
+        vote: Vote,
+        /// The height of the last signed vote.
+        height: Height,
+        /// The round of the last signed vote.
+        round: Round,
+    },
+    /// Self refuses a proposal below or conflicting with the last proposal it signed.
+    #[display(
+        "Safety error refuse to sign proposal height {}, round {}, last signed height {}, round {}",
+        height,
+        round,
+        signed_height,
+        signed_round
+    )]
+    RefuseProposalErr {
+        /// The height of the refused proposal.
+        height: Height,
+        /// The round of the refused proposal.
+        round: Round,
+        /// The height of the last signed proposal.
+        signed_height: Height,
+        /// The round of the last signed proposal.
+        signed_round: Round,
+    },
+    /// Two QCs of different block hashes are found with the same height, round and vote type.
+    #[display(
+        "Safety error fork detected {:?} QC height {}, round {}, kept hash {:?}, received hash {:?}",
+        vote_type,
+        height,
+        round,
+        hex_encode(kept),
+        hex_encode(received)
+    )]
+    ForkDetected {
+        /// The type of the QCs.
+        vote_type: VoteType,
+        /// The height of the QCs.
+        height: Height,
+        /// The round of the QCs.
+        round: Round,
+        /// The block hash of the QC kept before.
+        kept: Hash,
+        /// The block hash of the received QC.
+        received: Hash,
+    },
+    /// The signing is halted after a double sign, see `OverlordConfig::halt_on_double_sign`.
+    #[display("Safety error signing is halted after a double sign")]
+    SignHaltedErr,
+    /// The authority list of the height is not kept by the state.
+    #[display("State error authority list of height {} is not kept", _0)]
+    AuthorityNotKeptErr(Height),
+    /// A QC to inject is not of the height of the state.
+    #[display(
+        "State error inject a {:?} QC of height {}, the state is at height {}",
+        vote_type,
+        height,
+        current
+    )]
+    InjectHeightErr {
+        /// The type of the QC.
+        vote_type: VoteType,
+        /// The height of the QC.
+        height: Height,
+        /// The height of the state.
+        current: Height,
+    },
+    /// A snapshot to import is behind the state.
+    #[display(
+        "State error stale snapshot of height {}, round {}, the state is at height {}, round {}",
+        height,
+        round,
+        current_height,
+        current_round
+    )]
+    StaleSnapshotErr {
+        /// The height of the snapshot.
+        height: Height,
+        /// The round of the snapshot.
+        round: Round,
+        /// The height of the state.
+        current_height: Height,
+        /// The round of the state.
+        current_round: Round,
+    },
+    /// A status jumps over heights without a valid proof of the height before it, see
+    /// `OverlordConfig::verify_height_jump`.
+    #[display(
+        "State error jump to height {} from height {}, {}",
+        height,
+        current,
+        reason
+    )]
+    HeightJumpErr {
+        /// The height of the status.
+        height: Height,
+        /// The height of the state.
+        current: Height,
+        /// Why the proof is refused.
+        reason: String,
+    },
+    /// A message or a status is not of the expected height.
+    #[display(
+        "Correctness error {} of height {}, expect height {}",
+        what,
+        height,
+        expect
+    )]
+    UnexpectedHeightErr {
+        /// What is of the height, such as `commit`.
+        what: &'static str,
+        /// The height of it.
+        height: Height,
+        /// The expected height.
+        expect: Height,
+    },
+    /// The proposal, the votes or the QC of the height and the round is not collected.
+    #[display("Storage error {} height {}, round {}", item, height, round)]
+    MissingErr {
+        /// The missing item.
+        item: Missing,
+        /// The height of the item.
+        height: Height,
+        /// The round of the item.
+        round: Round,
+    },
+    /// A call of the `AuxStore` fails.
+    #[display("Storage error {} {:?}", context, source)]
+    AuxStoreErr {
+        /// What is read or written.
+        context: &'static str,
+        /// The error of the `AuxStore`.
+        source: ExternalError,
+    },
+    /// A call of the `ProofStore` fails.
+    #[display("Storage error {} {:?}", context, source)]
+    ProofStoreErr {
+        /// What is read or written.
+        context: &'static str,
+        /// The error of the `ProofStore`.
+        source: ExternalError,
+    },
+    /// A record of the `AuxStore` can not be encoded or decoded.
+    #[display("Storage error {} {:?}", context, source)]
+    StorageCodecErr {
+        /// What is encoded or decoded.
+        context: &'static str,
+        /// The codec error.
+        source: bcs::Error,
+    },
+    /// A message is malformed RLP.
+    #[display("Decode error {}", _0)]
+    RlpErr(alloy_rlp::Error),
+    /// A field of the `OverlordConfig` is invalid, see `OverlordConfig::validate`.
+    #[display("Config error {} {}", field, reason)]
+    ConfigErr {
+        /// The name of the field.
+        field: &'static str,
+        /// Why the value is invalid.
+        reason: &'static str,
+    },
     /// Other error.
     #[display("Other error {}", _0)]
     Other(String),
 }
 
-impl Error for ConsensusError {}
+impl ConsensusError {
+    /// The category of the error.
+    pub fn kind(&self) -> ErrorKind {
+        use self::ConsensusError::*;
+        match self {
+            InvalidAddress(_) => ErrorKind::Authority,
+            ChannelErr(_) => ErrorKind::Channel,
+            TriggerSMRErr(_) | MonitorEventErr(_) | ThrowEventErr(_) => ErrorKind::Smr,
+            ProposalErr(_) | MultiProposal(..) => ErrorKind::Proposal,
+            PrevoteErr(_) | PrecommitErr(_) => ErrorKind::Vote,
+            BrakeErr(_) => ErrorKind::Brake,
+            RoundDiff { .. } | SelfCheckErr(_) | CorrectnessErr(_) | UnexpectedHeightErr { .. } => {
+                ErrorKind::Correctness
+            }
+            TimerErr(_) => ErrorKind::Timer,
+            StateErr(_)
+            | AuthorityNotKeptErr(_)
+            | InjectHeightErr { .. }
+            | StaleSnapshotErr { .. }
+            | HeightJumpErr { .. } => ErrorKind::State,
+            StorageErr(_)
+            | MissingErr { .. }
+            | AuxStoreErr { .. }
+            | ProofStoreErr { .. }
+            | StorageCodecErr { .. } => ErrorKind::Storage,
+            SaveWalErr { .. } | LoadWalErr(_) | CorruptedWalErr(_) => ErrorKind::Wal,
+            CryptoErr(_) | CryptoCallErr { .. } => ErrorKind::Crypto,
+            AggregatedSignatureErr(_) | InvalidSignatureErr { .. } => ErrorKind::Signature,
+            SafetyErr(_)
+            | DoubleSignErr { .. }
+            | RefuseSignErr { .. }
+            | RefuseProposalErr { .. }
+            | ForkDetected { .. }
+            | SignHaltedErr => ErrorKind::Safety,
+            CompatErr(_) => ErrorKind::Compat,
+            DecodeErr(_) | DecodeLimitErr { .. } | RlpErr(_) => ErrorKind::Decode,
+            ConfigErr { .. } => ErrorKind::Config,
+            ConsensusCallErr { .. } | Other(_) => ErrorKind::Other,
+        }
+    }
+
+    /// How much the error matters to the application. A safety error or a failure of the wal or
+    /// the `AuxStore` is fatal, a message that is stale or ahead of the collected ones is
+    /// ignorable, and the rest is recoverable.
+    pub fn severity(&self) -> Severity {
+        use self::ConsensusError::*;
+        match self {
+            AuxStoreErr { .. } | StorageCodecErr { .. } => Severity::Fatal,
+            RoundDiff { .. }
+            | MissingErr { .. }
+            | AuthorityNotKeptErr(_)
+            | StaleSnapshotErr { .. }
+            | UnexpectedHeightErr { .. } => Severity::Ignorable,
+            _ => match self.kind() {
+                ErrorKind::Safety | ErrorKind::Wal | ErrorKind::Config => Severity::Fatal,
+                _ => Severity::Recoverable,
+            },
+        }
+    }
+}
+
+impl Error for ConsensusError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConsensusError::CryptoCallErr { source, .. }
+            | ConsensusError::ConsensusCallErr { source, .. }
+            | ConsensusError::InvalidSignatureErr { source, .. }
+            | ConsensusError::AuxStoreErr { source, .. }
+            | ConsensusError::ProofStoreErr { source, .. } => Some(source),
+            ConsensusError::StorageCodecErr { source, .. } => Some(source),
+            ConsensusError::RlpErr(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// The category of a `ConsensusError`, see `ConsensusError::kind`.
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// A node is not in the authority list.
+    Authority,
+    /// A channel between the components is closed or full.
+    Channel,
+    /// The state machine fails to take a trigger or to throw an event.
+    Smr,
+    /// A proposal is invalid.
+    Proposal,
+    /// A vote is invalid.
+    Vote,
+    /// A choke or a choke QC is invalid.
+    Brake,
+    /// The state and the state machine disagree, or a message is not of the expected height.
+    Correctness,
+    /// The timer fails.
+    Timer,
+    /// The state refuses a request.
+    State,
+    /// A message or a record is missing or can not be stored.
+    Storage,
+    /// The wal can not be saved or loaded.
+    Wal,
+    /// The `Crypto` fails.
+    Crypto,
+    /// A signature fails the verification.
+    Signature,
+    /// Self would sign conflicting votes.
+    Safety,
+    /// The encoding is incompatible.
+    Compat,
+    /// A message is malformed.
+    Decode,
+    /// The configuration is invalid.
+    Config,
+    /// Other error.
+    Other,
+}
+
+/// The severity of a `ConsensusError`, see `ConsensusError::severity`.
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The error needs no action, such as a stale message.
+    Ignorable,
+    /// The overlord goes on, but the error may point to a faulty peer or application.
+    Recoverable,
+    /// The node can not go on safely, such as a double sign or a failure to save the wal.
+    Fatal,
+}
+
+/// The classification and the context of a reported error, see `Consensus::report_error_detail`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorReport {
+    /// The severity of the error.
+    pub severity: Severity,
+    /// The height of the state as the error occurs.
+    pub height: Height,
+    /// The round of the state as the error occurs.
+    pub round: Round,
+    /// The step of the state as the error occurs.
+    pub step: Step,
+    /// The transport identity of the peer that the offending message is received from, if known.
+    pub peer: Option<PeerId>,
+    /// The signer of the offending message, if the error is caused by a message.
+    pub sender: Option<Address>,
+}
+
+/// What is missing of a `ConsensusError::MissingErr`.
+#[derive(Clone, Debug, Display, PartialEq, Eq)]
+pub enum Missing {
+    /// The proposal.
+    #[display("No proposal")]
+    Proposal,
+    /// The vote map of the vote type.
+    #[display("Can not get {:?} vote map", _0)]
+    VoteMap(VoteType),
+    /// The votes of the vote type.
+    #[display("Can not get {:?} votes", _0)]
+    Votes(VoteType),
+    /// The QC of the vote type.
+    #[display("Can not get {:?} qc", _0)]
+    QC(VoteType),
+}
+
+/// An error of an implementation of the traits, such as a `Crypto` or a `Consensus`, kept as the
+/// source of a `ConsensusError`. The error is shared, so that the consensus error is cloned.
+#[derive(Clone)]
+pub struct ExternalError(Arc<Mutex<Box<dyn Error + Send>>>);
+
+impl ExternalError {
+    /// Wrap the error of an implementation.
+    pub fn new(err: Box<dyn Error + Send>) -> Self {
+        ExternalError(Arc::new(Mutex::new(err)))
+    }
+
+    /// Call the function with the wrapped error, such as to downcast it.
+    pub fn with<R>(&self, f: impl FnOnce(&(dyn Error + Send + 'static)) -> R) -> R {
+        f(self.0.lock().as_ref())
+    }
+}
+
+impl From<Box<dyn Error + Send>> for ExternalError {
+    fn from(err: Box<dyn Error + Send>) -> Self {
+        ExternalError::new(err)
+    }
+}
+
+impl fmt::Debug for ExternalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.0.lock().as_ref(), f)
+    }
+}
+
+impl fmt::Display for ExternalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.0.lock().as_ref(), f)
+    }
+}
+
+impl Error for ExternalError {}
+
+/// A size limit enforced while decoding, see `CodecConfig`.
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq)]
+pub enum DecodeLimit {
+    /// The bytes of the encoded content.
+    #[display("content bytes")]
+    ContentBytes,
+    /// The count of the voters.
+    #[display("voters")]
+    Voters,
+    /// The bytes of the address bitmap.
+    #[display("bitmap length")]
+    BitmapLen,
+}
 
 #[cfg(test)]
 impl PartialEq for ConsensusError {
@@ -96,7 +556,7 @@ impl PartialEq for ConsensusError {
         match (self, other) {
             // If compare objects are the following types of error, as long as the error type need
             // the same, the details are ignored.
-            (InvalidAddress, InvalidAddress)
+            (InvalidAddress(_), InvalidAddress(_))
             | (TriggerSMRErr(_), TriggerSMRErr(_))
             | (MonitorEventErr(_), MonitorEventErr(_))
             | (ThrowEventErr(_), ThrowEventErr(_))
@@ -115,3 +575,79 @@ impl PartialEq for ConsensusError {
 
 #[cfg(test)]
 impl Eq for ConsensusError {}
+
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+
+    use super::{ConsensusError, ErrorKind, Missing, Severity};
+    use crate::types::{Hash, Height, Round, VoteType};
+
+    #[test]
+    fn test_typed_error() {
+        let err = ConsensusError::MissingErr {
+            item: Missing::QC(VoteType::Prevote),
+            height: Height(3),
+            round: Round(1),
+        };
+        assert_eq!(err.kind(), ErrorKind::Storage);
+        assert_eq!(
+            err.to_string(),
+            "Storage error Can not get Prevote qc height 3, round 1"
+        );
+        assert!(err.source().is_none());
+
+        // The error of an implementation is kept as the source, and can be downcast.
+        let inner: Box<dyn Error + Send> = Box::new(ConsensusError::Other("io".to_string()));
+        let err = ConsensusError::CryptoCallErr {
+            op: "sign vote",
+            source: inner.into(),
+        };
+        assert_eq!(err.kind(), ErrorKind::Crypto);
+        assert_eq!(
+            err.to_string(),
+            "Crypto error sign vote error Other(\"io\")"
+        );
+        assert_eq!(err.source().unwrap().to_string(), "Other error io");
+        match err.clone() {
+            ConsensusError::CryptoCallErr { source, .. } => assert_eq!(
+                source.with(|e| e.downcast_ref::<ConsensusError>().cloned()),
+                Some(ConsensusError::Other("io".to_string()))
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_error_severity() {
+        let double_sign = ConsensusError::DoubleSignErr {
+            vote_type: VoteType::Precommit,
+            height: Height(3),
+            round: Round(1),
+            signed: Hash::from(vec![1]),
+            hash: Hash::from(vec![2]),
+        };
+        assert_eq!(double_sign.severity(), Severity::Fatal);
+        assert_eq!(ConsensusError::SignHaltedErr.severity(), Severity::Fatal);
+        assert_eq!(
+            ConsensusError::LoadWalErr("torn".to_string()).severity(),
+            Severity::Fatal
+        );
+
+        let missing = ConsensusError::MissingErr {
+            item: Missing::Proposal,
+            height: Height(3),
+            round: Round(1),
+        };
+        assert_eq!(missing.severity(), Severity::Ignorable);
+        assert_eq!(
+            ConsensusError::AuthorityNotKeptErr(Height(1)).severity(),
+            Severity::Ignorable
+        );
+        assert_eq!(
+            ConsensusError::PrevoteErr("invalid".to_string()).severity(),
+            Severity::Recoverable
+        );
+        assert!(Severity::Fatal > Severity::Recoverable);
+    }
+}
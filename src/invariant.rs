@@ -0,0 +1,34 @@
+//! The safety invariants of the implementation, which are asserted on the critical code paths
+//! when the `verify` feature is enabled, and compiled out otherwise. Each assertion names the
+//! invariant it checks:
+//!
+//! - `LOCK-MONOTONIC`: within a height, a lock is only replaced by a lock of the same or a higher
+//!   round. A lock is only released by a proposal with a higher PoLC round, or by a nil prevote QC
+//!   of a round not lower than the lock round.
+//! - `LOCK-ROUND`: the lock round is never higher than the current round.
+//! - `LOCK-HASH`: while locked, the proposal hash to vote is the locked hash.
+//! - `STEP-MONOTONIC`: within a round, the step never goes back. Only a wal restores a lower step.
+//! - `PRECOMMIT-QC`: the precommit step of a round is entered only by a prevote QC of the height
+//!   and of a round not lower than the last round, or by the full prevotes of the round, so that
+//!   a node never precommits without the prevotes behind it.
+//! - `QC-THRESHOLD`: a QC triggers the state machine only if the voting weight of its voters is
+//!   above two thirds of the total.
+//! - `COMMIT-RULE`: a block is committed only with a non-empty precommit QC of the current height
+//!   from the state, of which the hash is the committed hash and the weight is above the threshold.
+
+/// Assert a safety invariant of the given name under the `verify` feature. A violation panics with
+/// the name and the message, so that it is never ignored by a test.
+macro_rules! invariant {
+    ($name:expr, $cond:expr, $($arg:tt)+) => {
+        #[cfg(feature = "verify")]
+        {
+            if !$cond {
+                panic!(
+                    "Overlord: invariant {} violated: {}",
+                    $name,
+                    format_args!($($arg)+)
+                );
+            }
+        }
+    };
+}
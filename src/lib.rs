@@ -7,12 +7,31 @@
 #![recursion_limit = "512"]
 #![allow(clippy::mutable_key_type)]
 
+/// The safety invariant assertions compiled under the `verify` feature.
+#[macro_use]
+mod invariant;
+/// The key-value store for the auxiliary persistence.
+pub mod aux_store;
 /// A module that impl rlp encodable and decodable trait for types that need to save wal.
 mod codec;
+/// Compatibility checks of the wal and proof formats across overlord releases.
+pub mod compat;
+/// The crash snapshot saved by the panic hook.
+pub mod crash;
+/// The built-in implementations of the `Crypto` trait, each under the feature of its name.
+pub mod crypto;
 /// Overlord error module.
 pub mod error;
+/// Light-client verification of the commits.
+pub mod light;
+/// Latency metrics of the state loop.
+pub mod metrics;
 /// Create and run the overlord consensus process.
 pub mod overlord;
+/// The store of the commit proofs.
+pub mod proof_store;
+/// Vote relayer for sentry topologies.
+pub mod relayer;
 /// serialize Bytes in hex format
 pub mod serde_hex;
 /// serialize Vec<Bytes> in hex format
@@ -21,55 +40,78 @@ mod serde_multi_hex;
 mod smr;
 /// The state module to storage proposals and votes.
 mod state;
+/// The mocks of the adapters to unit test an application against overlord.
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+/// A seeded multi-node simulation in virtual time.
+#[cfg(any(test, feature = "testkit"))]
+pub mod testkit;
 /// The timer module to ensure the protocol liveness.
 mod timer;
+/// The recording and the replay of the triggers and the events of the SMR.
+pub mod trace;
+/// The wire format and the peer identity of the messages between the nodes. The transport itself
+/// is not built in, but is the `Network` of the application.
+pub mod transport;
 /// Message types using in the overlord consensus protocol.
 pub mod types;
 /// Some utility functions.
 mod utils;
-/// Write ahead log module.
-mod wal;
+/// Write ahead log module, with the tools to inspect and repair the wal records.
+pub mod wal;
 
+pub use self::aux_store::{AuxOp, AuxStore, FileAuxStore, MemoryAuxStore};
+pub use self::codec::{domain_payload, CodecConfig};
 pub use self::overlord::Overlord;
 pub use self::overlord::OverlordHandler;
-pub use self::utils::auth_manage::{extract_voters, get_leader};
+pub use self::proof_store::{AuxProofStore, ProofStore};
+pub use self::relayer::{Relayer, RelayerConfig, RelayerHandler};
+pub use self::smr::smr_types::Step;
+pub use self::utils::auth_manage::{extract_voters, get_leader, select_proposer, ProposerConfig};
 pub use creep::Context;
 use serde::de::DeserializeOwned;
 pub use wal::WalInfo;
 
 use std::error::Error;
 use std::fmt::Debug;
+use std::time::{Duration, Instant};
 
 use alloy_rlp::{RlpDecodable, RlpEncodable};
 use async_trait::async_trait;
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 
-use crate::error::ConsensusError;
-use crate::types::{Address, Commit, Hash, Node, OverlordMsg, Signature, Status, ViewChangeReason};
+use crate::error::{ConsensusError, ErrorReport};
+use crate::types::{
+    AbandonedWork, Address, AggregatedChoke, AggregatedVote, Choke, Commit, CompactProposal,
+    Decision, Hash, Height, KeyRotation, MessageDrop, Node, OverlordMsg, Round, Signature,
+    SignedProposal, StallDiagnostics, Status, ViewChangeReason, Vote, VoteParticipation, VoteType,
+};
 
 /// Overlord consensus result.
 pub type ConsensusResult<T> = std::result::Result<T, ConsensusError>;
 
-const INIT_HEIGHT: u64 = 0;
-const INIT_ROUND: u64 = 0;
+const INIT_HEIGHT: Height = Height(0);
+const INIT_ROUND: Round = Round(0);
+const MAX_BACKOFF_ROUNDS: u64 = 64;
 
 /// Trait for some functions that consensus needs.
 #[async_trait]
 pub trait Consensus<T: Codec>: Send + Sync {
-    /// Get a block of the given height and return the block with its hash.
+    /// Get a block of the given height and return the block with its hash. It is the default
+    /// `BlockProvider::get_block`.
     async fn get_block(
         &self,
         ctx: Context,
-        height: u64,
+        height: Height,
     ) -> Result<(T, Hash), Box<dyn Error + Send>>;
 
     /// Check the correctness of a block. If is passed, return the integrated transcations to do
-    /// data persistence.
+    /// data persistence. It is the default `BlockProvider::check_block`.
     async fn check_block(
         &self,
         ctx: Context,
-        height: u64,
+        height: Height,
         hash: Hash,
         block: T,
     ) -> Result<(), Box<dyn Error + Send>>;
@@ -78,7 +120,7 @@ pub trait Consensus<T: Codec>: Send + Sync {
     async fn commit(
         &self,
         ctx: Context,
-        height: u64,
+        height: Height,
         commit: Commit<T>,
     ) -> Result<Status, Box<dyn Error + Send>>;
 
@@ -86,17 +128,18 @@ pub trait Consensus<T: Codec>: Send + Sync {
     async fn get_authority_list(
         &self,
         ctx: Context,
-        height: u64,
+        height: Height,
     ) -> Result<Vec<Node>, Box<dyn Error + Send>>;
 
-    /// Broadcast a message to other replicas.
+    /// Broadcast a message to other replicas. It is the default `Network::broadcast`.
     async fn broadcast_to_other(
         &self,
         ctx: Context,
         msg: OverlordMsg<T>,
     ) -> Result<(), Box<dyn Error + Send>>;
 
-    /// Transmit a message to the Relayer, the third argument is the relayer's address.
+    /// Transmit a message to the Relayer, the third argument is the relayer's address. It is the
+    /// default `Network::transmit`.
     async fn transmit_to_relayer(
         &self,
         ctx: Context,
@@ -104,26 +147,340 @@ pub trait Consensus<T: Codec>: Send + Sync {
         msg: OverlordMsg<T>,
     ) -> Result<(), Box<dyn Error + Send>>;
 
+    /// Get the block of the given hash, which is disseminated apart from the consensus messages.
+    /// It is called for the compact proposals, of which the signed proposal carries the block hash
+    /// only. The returned block is checked by `check_block` against the hash before it is used.
+    /// Not supported by default.
+    async fn get_block_by_hash(
+        &self,
+        _ctx: Context,
+        height: Height,
+        _hash: Hash,
+    ) -> Result<T, Box<dyn Error + Send>> {
+        Err(Box::new(ConsensusError::Other(format!(
+            "get block by hash of height {} is not supported",
+            height
+        ))))
+    }
+
+    /// A hint that self is the proposer of the given height, which is called as the previous
+    /// height commits, so that the application assembles the block during the interval between
+    /// the heights and `get_block` returns it at once. It should return quickly, for example by
+    /// spawning the assembly. Ignore the hint by default.
+    fn prepare_block(&self, _ctx: Context, _height: Height) {}
+
+    /// Decide whether to propose the block of the given hash, which is called after `get_block`
+    /// and before the proposal is signed and broadcast. Return `Decision::Abort` to give up
+    /// proposing in this round, for example when the application finds its state stale. Always
+    /// propose by default.
+    async fn before_propose(
+        &self,
+        _ctx: Context,
+        _height: Height,
+        _round: Round,
+        _hash: Hash,
+    ) -> Decision {
+        Decision::Propose
+    }
+
+    /// Extend the precommit vote of the given block with an application defined payload, such as
+    /// oracle data. The payload is signed apart from the vote and is carried by the precommit QC.
+    /// Return an empty payload to extend nothing, which is the default.
+    async fn extend_vote(
+        &self,
+        _ctx: Context,
+        _height: Height,
+        _round: Round,
+        _block_hash: Hash,
+    ) -> Result<Bytes, Box<dyn Error + Send>> {
+        Ok(Bytes::new())
+    }
+
+    /// Verify the vote extension of a precommit vote from the given voter. The vote is dropped if
+    /// the extension does not pass. Accept every extension by default.
+    async fn verify_vote_extension(
+        &self,
+        _ctx: Context,
+        _height: Height,
+        _voter: Address,
+        _block_hash: Hash,
+        _extension: Bytes,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        Ok(())
+    }
+
+    /// Return the instant to start proposing the given height, which is called after a commit when
+    /// self is the next proposer. It is used for the chains that produce blocks in external slot
+    /// schedules, such as fixed wall clock slots. Return `None` to wait for the fixed interval of
+    /// the status since the last height started, which is the default. An instant that is passed
+    /// proposes at once.
+    fn next_propose_at(&self, _height: Height) -> Option<Instant> {
+        None
+    }
+
+    /// Return the unix timestamp of a block as millisecond, if the block carries one. The
+    /// timestamp of a proposed block is compared with the local clock, and the block is not
+    /// followed if the skew is beyond `OverlordConfig::max_clock_skew`. Return `None` by default,
+    /// which skips the comparison.
+    fn block_timestamp(&self, _block: &T) -> Option<u64> {
+        None
+    }
+
+    /// Report a message that is dropped by the state, such as an outdated vote, so that the drops
+    /// can be investigated without the debug logs. Ignore the drops by default.
+    fn report_message_drop(&self, _ctx: Context, _drop: MessageDrop) {}
+
+    /// Report the work of a height which is cancelled as a status removes self from the authority
+    /// list. The results of the cancelled `check_block` calls are never used. Ignore it by default.
+    fn report_abandoned_height(&self, _ctx: Context, _work: AbandonedWork) {}
+
+    /// Report a height which passes the thresholds of `OverlordConfig::stall_watchdog` without a
+    /// commit, with the diagnostics of the round. It is reported once per height. Ignore it by
+    /// default.
+    fn report_stalled(
+        &self,
+        _ctx: Context,
+        _height: Height,
+        _round: Round,
+        _diagnostics: StallDiagnostics,
+    ) {
+    }
+
+    /// Report a fork, which is the kept QC and a received one of different block hashes with the
+    /// same height, round and vote type, as the evidence. It takes more than a third of the vote
+    /// weight signing conflicting votes, so the overlord halts signing once it is found, and
+    /// reports `ConsensusError::ForkDetected` as well. Ignore it by default.
+    fn report_fork(&self, _ctx: Context, _kept: AggregatedVote, _received: AggregatedVote) {}
+
     /// Report the overlord error with the corresponding context.
     fn report_error(&self, ctx: Context, error: ConsensusError);
 
+    /// Report the overlord error with its severity and the height, round, step and offending peer
+    /// as it occurs, so that the application can alert only on the fatal errors. It calls
+    /// `report_error` by default.
+    fn report_error_detail(&self, ctx: Context, error: ConsensusError, _report: ErrorReport) {
+        self.report_error(ctx, error)
+    }
+
     /// Report the overlord view change reason.
-    fn report_view_change(&self, ctx: Context, height: u64, round: u64, reason: ViewChangeReason);
+    fn report_view_change(
+        &self,
+        ctx: Context,
+        height: Height,
+        round: Round,
+        reason: ViewChangeReason,
+    );
 }
 
+/// Trait for providing and checking the blocks. Every `Consensus` is a block provider through its
+/// `get_block` and `check_block`, which the overlord uses unless a separate provider is given by
+/// `Overlord::with_block_provider`, so that the mempool and the execution can live apart from the
+/// commit.
+#[async_trait]
+pub trait BlockProvider<T: Codec>: Send + Sync {
+    /// Get a block of the given height to propose and return the block with its hash.
+    async fn get_block(
+        &self,
+        ctx: Context,
+        height: Height,
+    ) -> Result<(T, Hash), Box<dyn Error + Send>>;
+
+    /// Check the correctness of a proposed block of the given hash.
+    async fn check_block(
+        &self,
+        ctx: Context,
+        height: Height,
+        hash: Hash,
+        block: T,
+    ) -> Result<(), Box<dyn Error + Send>>;
+
+    /// A hint that self is the proposer of the given height, see `Consensus::prepare_block`.
+    /// Ignore the hint by default.
+    fn prepare_block(&self, _ctx: Context, _height: Height) {}
+}
+
+impl<T: Codec> Debug for dyn BlockProvider<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BlockProvider")
+    }
+}
+
+#[async_trait]
+impl<T: Codec + 'static, F: Consensus<T> + ?Sized> BlockProvider<T> for F {
+    async fn get_block(
+        &self,
+        ctx: Context,
+        height: Height,
+    ) -> Result<(T, Hash), Box<dyn Error + Send>> {
+        Consensus::get_block(self, ctx, height).await
+    }
+
+    async fn check_block(
+        &self,
+        ctx: Context,
+        height: Height,
+        hash: Hash,
+        block: T,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        Consensus::check_block(self, ctx, height, hash, block).await
+    }
+
+    fn prepare_block(&self, ctx: Context, height: Height) {
+        Consensus::prepare_block(self, ctx, height)
+    }
+}
+
+/// Trait for sending the consensus messages to the other nodes. Every `Consensus` is a network
+/// through its `broadcast_to_other` and `transmit_to_relayer`, which the overlord uses unless a
+/// separate network is given by `Overlord::with_network`, so that the transport can be swapped
+/// apart from the application adapter.
+#[async_trait]
+pub trait Network<T: Codec>: Send + Sync {
+    /// Broadcast a message to the other nodes.
+    async fn broadcast(
+        &self,
+        ctx: Context,
+        msg: OverlordMsg<T>,
+    ) -> Result<(), Box<dyn Error + Send>>;
+
+    /// Send a message to the node of the given address.
+    async fn transmit(
+        &self,
+        ctx: Context,
+        addr: Address,
+        msg: OverlordMsg<T>,
+    ) -> Result<(), Box<dyn Error + Send>>;
+}
+
+impl<T: Codec> Debug for dyn Network<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Network")
+    }
+}
+
+#[async_trait]
+impl<T: Codec + 'static, F: Consensus<T> + ?Sized> Network<T> for F {
+    async fn broadcast(
+        &self,
+        ctx: Context,
+        msg: OverlordMsg<T>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.broadcast_to_other(ctx, msg).await
+    }
+
+    async fn transmit(
+        &self,
+        ctx: Context,
+        addr: Address,
+        msg: OverlordMsg<T>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.transmit_to_relayer(ctx, addr, msg).await
+    }
+}
+
+/// Trait for archiving the consensus artifacts beyond the commits, see
+/// `Overlord::with_artifact_sink`. It receives every verified proposal of the current height,
+/// including the proposals of self, and every QC and choke QC that self aggregates or verifies,
+/// so that an operator can keep a full audit archive of the consensus traffic. The same artifact
+/// is archived once, while a conflicting QC of the same round is archived as well. Every method
+/// archives nothing by default. The methods are called by the state loop, so they should return
+/// quickly, for example by queueing the artifacts to a writer task.
+pub trait ArtifactSink<T: Codec>: Send + Sync {
+    /// Archive a verified signed proposal.
+    fn archive_proposal(&self, _proposal: &SignedProposal<T>) {}
+
+    /// Archive a verified prevote or precommit QC.
+    fn archive_qc(&self, _qc: &AggregatedVote) {}
+
+    /// Archive a verified choke QC.
+    fn archive_choke_qc(&self, _qc: &AggregatedChoke) {}
+}
+
+impl<T: Codec> Debug for dyn ArtifactSink<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ArtifactSink")
+    }
+}
+
+/// Trait for reporting the consensus metrics, such as to a monitoring system. Every method
+/// reports nothing by default, see `Overlord::with_reporter`. The methods are called by the state
+/// loop, so they should return quickly.
+pub trait Reporter: Debug + Send + Sync {
+    /// Report the duration of a round, from the start of the round to the view change or to the
+    /// commit.
+    fn report_round_latency(&self, _height: Height, _round: Round, _elapsed: Duration) {}
+
+    /// Report the authorities that vote in a QC of the current height and those that do not, once
+    /// self gets the QC.
+    fn report_vote_participation(&self, _participation: VoteParticipation) {}
+
+    /// Report the duration from the start of the round to the time self gets a QC of the round.
+    fn report_qc_formation(
+        &self,
+        _height: Height,
+        _round: Round,
+        _vote_type: VoteType,
+        _elapsed: Duration,
+    ) {
+    }
+
+    /// Report the duration of saving a wal entry of the step.
+    fn report_wal_duration(&self, _height: Height, _round: Round, _step: Step, _elapsed: Duration) {
+    }
+
+    /// Report a step transition of the state machine, at the height and the round it transits
+    /// in, such as from the commit step of the previous height to the propose step of round 0. It
+    /// is called by the state machine task, and also when the step is recovered from the wal.
+    fn on_step_change(&self, _height: Height, _round: Round, _old_step: Step, _new_step: Step) {}
+}
+
+/// A reporter which reports nothing, which is the default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopReporter;
+
+impl Reporter for NoopReporter {}
+
 /// Trait for doing serialize and deserialize.
 pub trait Codec: Serialize + DeserializeOwned + Clone + Debug + Send + PartialEq + Eq {}
 
 impl<T> Codec for T where T: Serialize + DeserializeOwned + Clone + Debug + Send + PartialEq + Eq {}
 
-/// Trait for save and load wal information.
+/// Trait for save and load wal information. The wal is a log of the step transitions of the
+/// current height. A wal which only implements `save` and `load` keeps the latest entry of the
+/// log, while a wal which also implements `append`, `load_all` and `compact` keeps every entry of
+/// the height for postmortem debugging.
 #[async_trait]
 pub trait Wal {
-    /// Save wal information.
+    /// Save wal information. The information is an opaque checksummed record, which is decoded
+    /// by `compat::decode_wal_info`.
     async fn save(&self, info: Bytes) -> Result<(), Box<dyn Error + Send>>;
 
-    /// Load wal information.
+    /// Load the latest wal information.
     async fn load(&self) -> Result<Option<Bytes>, Box<dyn Error + Send>>;
+
+    /// Append wal information to the log of the current height. It saves over the latest entry by
+    /// default.
+    async fn append(&self, info: Bytes) -> Result<(), Box<dyn Error + Send>> {
+        self.save(info).await
+    }
+
+    /// Load all wal information of the log in the order they are appended. It loads the latest
+    /// entry by default.
+    async fn load_all(&self) -> Result<Vec<Bytes>, Box<dyn Error + Send>> {
+        Ok(self.load().await?.into_iter().collect())
+    }
+
+    /// Drop the log of the committed heights and start a new log with the given wal information,
+    /// which is the first entry of a new height. It saves over the latest entry by default.
+    async fn compact(&self, info: Bytes) -> Result<(), Box<dyn Error + Send>> {
+        self.save(info).await
+    }
+
+    /// Make the saved wal information durable. It is only called under a `WalPolicy` other than
+    /// `WalPolicy::Always`, where `save` may return before the information is durable.
+    async fn sync(&self) -> Result<(), Box<dyn Error + Send>> {
+        Ok(())
+    }
 }
 
 /// Trait for some crypto methods.
@@ -134,6 +491,49 @@ pub trait Crypto: Send {
     /// Sign to the given hash by private key and return the signature if success.
     fn sign(&self, hash: Hash) -> Result<Signature, Box<dyn Error + Send>>;
 
+    /// Sign to the hash of a vote of self. It is `sign` by default, while a signer which guards
+    /// against double signing by the vote itself overrides it, such as
+    /// `crypto::remote::RemoteCrypto`.
+    fn sign_vote(&self, _vote: &Vote, hash: Hash) -> Result<Signature, Box<dyn Error + Send>> {
+        self.sign(hash)
+    }
+
+    /// Sign to the hash of a proposal of self, which is given in the compact form without the
+    /// block. It is `sign` by default, while a signer which guards against double proposing by the
+    /// proposal itself overrides it, such as `crypto::remote::RemoteCrypto`.
+    fn sign_proposal(
+        &self,
+        _proposal: &CompactProposal,
+        hash: Hash,
+    ) -> Result<Signature, Box<dyn Error + Send>> {
+        self.sign(hash)
+    }
+
+    /// Sign to the hash of a choke of self. It is `sign` by default, while a signer which checks
+    /// what it signs overrides it, such as `crypto::remote::RemoteCrypto`.
+    fn sign_choke(&self, _choke: &Choke, hash: Hash) -> Result<Signature, Box<dyn Error + Send>> {
+        self.sign(hash)
+    }
+
+    /// Sign to the hash of the extension data of a precommit vote of self. It is `sign` by
+    /// default, while a signer which checks what it signs overrides it, such as
+    /// `crypto::remote::RemoteCrypto`.
+    fn sign_extension(
+        &self,
+        _vote: &Vote,
+        _data: &Bytes,
+        hash: Hash,
+    ) -> Result<Signature, Box<dyn Error + Send>> {
+        self.sign(hash)
+    }
+
+    /// Whether the signing blocks the thread, such as on the response of a remote signer. Overlord
+    /// then signs on the blocking thread pool of tokio rather than on the async runtime, and the
+    /// state waits for the signature. It is `false` by default.
+    fn is_sign_blocking(&self) -> bool {
+        false
+    }
+
     /// Aggregate the given signatures into an aggregated signature according to the given bitmap.
     fn aggregate_signatures(
         &self,
@@ -156,6 +556,90 @@ pub trait Crypto: Send {
         msg_hash: Hash,
         voters: Vec<Address>,
     ) -> Result<(), Box<dyn Error + Send>>;
+
+    /// Combine the signature shares of the voters above the threshold into a single threshold
+    /// signature, which is verified by the group public key of the authority list without the
+    /// voters. Only the threshold QC mode calls it, see `OverlordConfig::threshold_qc`, and it is
+    /// unsupported by default.
+    fn combine_threshold_signature(
+        &self,
+        _signatures: Vec<Signature>,
+        _voters: Vec<Address>,
+    ) -> Result<Signature, Box<dyn Error + Send>> {
+        Err(Box::new(ConsensusError::CryptoErr(
+            "threshold signature is unsupported".to_string(),
+        )))
+    }
+
+    /// Verify a threshold signature of a QC of the given height by the group public key of the
+    /// given authority list, which is the list of the height that the QC is verified against. It
+    /// is unsupported by default, so that the QCs of the threshold signatures are refused.
+    fn verify_threshold_signature(
+        &self,
+        _height: Height,
+        _signature: Signature,
+        _msg_hash: Hash,
+        _authority: Vec<Address>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        Err(Box::new(ConsensusError::CryptoErr(
+            "threshold signature is unsupported".to_string(),
+        )))
+    }
+
+    /// Learn a key rotation of a node of the authority list, see `Node::key_rotation`. It is
+    /// called whenever the authority list is updated, so the same rotation may be given again.
+    /// A crypto which does not rotate keys ignores it by default.
+    fn rotate_key(
+        &self,
+        _address: &Address,
+        _rotation: &KeyRotation,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        Ok(())
+    }
+
+    /// Verify a signature of a message of the given height, by the key of the voter effective at
+    /// the height. It is `verify_signature` by default.
+    fn verify_signature_at(
+        &self,
+        _height: Height,
+        signature: Signature,
+        hash: Hash,
+        voter: Address,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.verify_signature(signature, hash, voter)
+    }
+
+    /// Verify an aggregated signature of a QC of the given height, by the keys of the voters
+    /// effective at the height. It is `verify_aggregated_signature` by default.
+    fn verify_aggregated_signature_at(
+        &self,
+        _height: Height,
+        aggregate_signature: Signature,
+        msg_hash: Hash,
+        voters: Vec<Address>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.verify_aggregated_signature(aggregate_signature, msg_hash, voters)
+    }
+
+    /// Evaluate the verifiable random function on the message by private key and return the
+    /// output with its proof. Only the VRF proposer mode calls it, it is unsupported by default.
+    fn vrf_prove(&self, _msg: Bytes) -> Result<(Hash, Bytes), Box<dyn Error + Send>> {
+        Err(Box::new(ConsensusError::CryptoErr(
+            "VRF is unsupported".to_string(),
+        )))
+    }
+
+    /// Verify a VRF proof of the message from the signer and return the output.
+    fn vrf_verify(
+        &self,
+        _proof: Bytes,
+        _msg: Bytes,
+        _signer: Address,
+    ) -> Result<Hash, Box<dyn Error + Send>> {
+        Err(Box::new(ConsensusError::CryptoErr(
+            "VRF is unsupported".to_string(),
+        )))
+    }
 }
 
 /// The setting of the timeout interval of each step.
@@ -206,9 +690,347 @@ impl DurationConfig {
     }
 }
 
+/// The exponential backoff of the round timeouts, as percents of the step timeout of the height
+/// interval. The timeout of round `r` is scaled by `base * (multiplier / 100)^r`, bounded by
+/// `cap`, so that a network under stress converges instead of thrashing through the rounds at a
+/// constant rate. The brake timeout is never scaled. The default doubles the timeout per round up
+/// to 32 times.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimeoutBackoff {
+    /// The scale of the timeouts of round 0.
+    pub base: u64,
+    /// The growth of the scale per round. A multiplier of 100 keeps the timeouts constant.
+    pub multiplier: u64,
+    /// The maximum scale of the timeouts.
+    pub cap: u64,
+}
+
+impl Default for TimeoutBackoff {
+    fn default() -> Self {
+        TimeoutBackoff {
+            base: 100,
+            multiplier: 200,
+            cap: 3200,
+        }
+    }
+}
+
+impl TimeoutBackoff {
+    /// Create a timeout backoff.
+    pub fn new(base: u64, multiplier: u64, cap: u64) -> Self {
+        TimeoutBackoff {
+            base,
+            multiplier,
+            cap,
+        }
+    }
+
+    /// Scale the step timeout for the given round.
+    pub fn timeout(&self, timeout: Duration, round: Round) -> Duration {
+        let mut scale = self.base.min(self.cap);
+        // The scale reaches the cap or stays after a few rounds, so the rounds are bounded.
+        for _ in 0..round.0.min(MAX_BACKOFF_ROUNDS) {
+            if scale >= self.cap {
+                break;
+            }
+            scale = (scale.saturating_mul(self.multiplier) / 100).min(self.cap);
+        }
+        let millis = u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX);
+        Duration::from_millis(millis.saturating_mul(scale) / 100)
+    }
+}
+
+/// The durability policy of the wal. An entry is safety critical if it carries a lock, or if
+/// self signs a vote or a choke after saving it. The safety critical entries are always saved and
+/// synced before self signs anything, under any policy. The other entries are the new height and
+/// new round entries without a lock, and the entries saved while paused, of which a loss only
+/// makes self restart from an earlier step.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum WalPolicy {
+    /// Await `Wal::save` on every step change, which must be durable when it returns. `Wal::sync`
+    /// is never called.
+    #[default]
+    Always,
+    /// Save every entry, and sync the entries that are not safety critical at most once per
+    /// interval, as millisecond.
+    Interval(u64),
+    /// Coalesce the consecutive entries of the same height, round and step that are not safety
+    /// critical into the latest one, which is saved along with the next step change. Only the
+    /// safety critical entries are synced.
+    Batched,
+}
+
+/// The thresholds of the stuck height watchdog. A height is stalled once it reaches the round
+/// `max_rounds` or lasts `max_duration` milliseconds without a commit, then
+/// `Consensus::report_stalled` is called. A zero threshold is disabled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StallWatchdog {
+    /// The count of the rounds of a height without a commit.
+    pub max_rounds: u64,
+    /// The duration of a height without a commit, as millisecond.
+    pub max_duration: u64,
+}
+
+impl StallWatchdog {
+    /// Create a stall watchdog.
+    pub fn new(max_rounds: u64, max_duration: u64) -> Self {
+        StallWatchdog {
+            max_rounds,
+            max_duration,
+        }
+    }
+}
+
+/// The setting of an overlord instance.
+///
+/// The messages from the network pass through bounded channels from the handler to the state and
+/// from the signature verification to the state. When the message channel is full,
+/// `OverlordHandler::send_msg` drops the message and returns a `ChannelErr`, the network layer is
+/// expected to retry or let the message go. Rich status and stop messages never pass through the
+/// bounded channel so that they are never dropped. The signature verification tasks wait for the
+/// state when the verified channel is full, while the messages of future heights, which are cached
+/// before verification, are dropped. The channels between the state, the SMR and the timer are
+/// driven by the consensus steps rather than by the network, so they are left unbounded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OverlordConfig {
+    /// The capacity of the channel from the overlord handler to the state.
+    pub msg_channel_capacity: usize,
+    /// The capacity of the channels of the verified messages and the check block responses, and
+    /// of the queue of the verify workers.
+    pub verify_channel_capacity: usize,
+    /// Refuse to sign any further message once self is found to have signed conflicting votes,
+    /// for example after the node is restored from an old backup. A conflicting vote is never
+    /// signed and the safety error is always reported, whether halting or not.
+    pub halt_on_double_sign: bool,
+    /// Elect the proposer of each height by the VRF seed of the height, instead of the rotation or
+    /// random schedule of the authority list. Every authority must agree on the mode, and `Crypto`
+    /// must implement `vrf_prove` and `vrf_verify`. The proposer of a height proves a VRF over the
+    /// seed of its height and carries it in the proposal. The seed of the next height is derived
+    /// from the seed and the committed VRF, see `Vrf::next_seed`. The seed of the first height is
+    /// empty and the default schedule is used, and every status above it must carry the seed,
+    /// see `Status::vrf_seed`.
+    pub vrf_proposer: bool,
+    /// The count of the recent committed heights of which the consensus artifacts are retained
+    /// for `export_height_artifacts`. Zero retains nothing but the current height.
+    pub artifact_retention: usize,
+    /// The count of the previous authority lists kept after the validator set changes, so that
+    /// the messages and QCs signed under them can still be verified.
+    pub authority_retention: usize,
+    /// The maximum size of the authority list. The bitmaps, the linear scans over the authority
+    /// list and the sizes of the QCs grow with it, see the `authority_scale` benchmark for the
+    /// costs at each size. An initial authority list or a rich status beyond the maximum is
+    /// refused with an error.
+    pub max_authority_size: usize,
+    /// Enable the trusted intake of `OverlordHandler::send_trusted_msg`, of which the messages
+    /// skip the signature verification. Only enable it if the messages that pass the trusted
+    /// intake are verified by the caller, such as at the network edge.
+    pub trusted_intake: bool,
+    /// The count of the blocks, and of the check block results, that are cached by hash. The
+    /// least recently used one is evicted beyond it.
+    pub block_cache_capacity: usize,
+    /// The count of the signed proposals cached over all heights and rounds. The least recently
+    /// used one is evicted beyond it.
+    pub proposal_cache_capacity: usize,
+    /// The count of the rounds of which the votes and QCs are cached over all heights. The votes of
+    /// the least recently used round are evicted beyond it.
+    pub vote_round_capacity: usize,
+    /// The count of the heights above the current one of which the messages are cached for later.
+    /// The messages of the higher heights are dropped. It must be positive, and a larger gap
+    /// suits a shorter block interval, where a lagging node falls more heights behind.
+    pub future_height_gap: u64,
+    /// The count of the rounds above the current one of which the messages are cached for later,
+    /// which also bounds the rounds of the messages of the future heights. The messages of the
+    /// higher rounds are dropped. It must be positive.
+    pub future_round_gap: u64,
+    /// The maximum count of the running check block tasks. The further checks are queued, and the
+    /// checks of the passed heights and rounds are cancelled.
+    pub max_check_tasks: usize,
+    /// The maximum skew between the timestamp of a proposed block, which is given by
+    /// `Consensus::block_timestamp`, and the local clock, as millisecond. A fresh block beyond it
+    /// is prevoted nil, and the skews are reported per proposer in the metrics. Zero accepts any
+    /// skew, which is the default.
+    pub max_clock_skew: u64,
+    /// The timeout of a check block task, as millisecond. A check which times out is aborted and
+    /// counts as not passed for the block. Zero never times out, which is the default.
+    pub check_block_timeout: u64,
+    /// Broadcast the proposals of self in the compact form, which carries the block hash instead
+    /// of the block. The receivers fetch the block by `Consensus::get_block_by_hash`, so enable
+    /// it only if the application disseminates the blocks itself. The proposals are signed in the
+    /// compact form in this mode, and the signatures of both forms are accepted.
+    pub compact_proposal: bool,
+    /// The maximum size of a block part. The encoded blocks of self larger than it are split into
+    /// parts, which are broadcast after the compact proposal with the merkle root of the parts,
+    /// so that a block larger than the message limit of the network is disseminated. Zero never
+    /// splits the blocks, which is the default. The parts are reassembled for the current height
+    /// only.
+    pub block_part_size: usize,
+    /// The exponential backoff of the round timeouts.
+    pub timeout_backoff: TimeoutBackoff,
+    /// The bound of a random delay added to the propose, prevote and precommit timeouts, as a
+    /// percent of the timeout, so that the validators do not time out and brake at the same
+    /// instant. The random source of each node is seeded by its address. Zero adds no delay, which
+    /// is the default.
+    pub timeout_jitter: u64,
+    /// The durability policy of the wal.
+    pub wal_policy: WalPolicy,
+    /// Install a panic hook which saves a `crash::CrashSnapshot` of the state to the auxiliary
+    /// store when the state or the SMR task panics, before the panic unwinds. The hook is global
+    /// to the process and calls the previous hook after saving.
+    pub panic_snapshot: bool,
+    /// The stuck height watchdog, which is disabled by default.
+    pub stall_watchdog: Option<StallWatchdog>,
+    /// End the prevote or the precommit step of the leader once the whole vote weight has voted
+    /// without a QC, such as on split votes, instead of waiting out the step timeout.
+    pub optimistic_step: bool,
+    /// The percent of the propose timeout after which a replica that has not received the
+    /// proposal of the round pulls it from the proposer and the backups, which are the proposers
+    /// of the next rounds. Zero never pulls, which is the default.
+    pub proposal_pull: u64,
+    /// The percent of the prevote and precommit timeouts after which the leader that has not
+    /// formed the QC of the step pulls the votes it misses from the peers, which might have been
+    /// relayed to a wrong leader. Zero never pulls, which is the default.
+    pub vote_pull: u64,
+    /// The percent of the prevote timeout after which the leader that is still in the prevote step
+    /// broadcasts its signed proposal of the round once more, so that the replicas which missed
+    /// the first broadcast can still vote for it. The replicas holding the proposal answer the
+    /// `PullProposal` requests as well. Zero never rebroadcasts, which is the default.
+    pub proposal_rebroadcast: u64,
+    /// Relay a prevote or precommit QC of the current round to the peers the first time it is
+    /// received, so that a QC reaches every replica even if some of the broadcasts of the leader
+    /// are lost. Each replica relays a QC at most once, which costs a broadcast of each QC from
+    /// every replica. It is off by default.
+    pub qc_relay: bool,
+    /// The chain id mixed into every payload that overlord signs and verifies, so that the
+    /// signatures can not be replayed across the deployments sharing the keys, see
+    /// `domain_payload`. Every node of a chain must use the same chain id. It is empty by default,
+    /// which signs the payloads as they are.
+    pub chain_id: Bytes,
+    /// Build the QCs of a single threshold signature combined by
+    /// `Crypto::combine_threshold_signature` instead of an address bitmap and an aggregated
+    /// signature, so that a QC is of a constant size however large the authority list is. The
+    /// shares of a t-of-n scheme weigh the same, so every node of the authority list must have the
+    /// same vote weight, and the threshold of the scheme must be above 2/3 of the nodes. It is off
+    /// by default.
+    pub threshold_qc: bool,
+    /// The count of the threads that verify the signatures of the received messages, apart from
+    /// the async runtime, of which the queue is drained by the threads in order and the results
+    /// are sent over the verified message channel. The messages beyond the capacity of the queue
+    /// are dropped. Zero verifies each message on a task of the
+    /// async runtime, which is the default.
+    pub verify_workers: usize,
+    /// The maximum count of the verified votes counted together. The votes queued on the verified
+    /// message channel behind a vote are drained into a batch, which is counted once for each
+    /// vote type instead of once for each vote. Zero or one counts each vote apart, which is the
+    /// default.
+    pub vote_batch_size: usize,
+    /// Save the signed votes of the current round and the QCs of the height in every wal entry,
+    /// so that a leader restarted mid-round resumes the aggregation from the votes it collected
+    /// instead of waiting for the round to fail. The votes collected after the last entry are
+    /// still lost, and the entries grow with the authority list. It is off by default.
+    pub wal_votes: bool,
+    /// Refuse a status which jumps more than one height ahead unless it carries the proof of the
+    /// height before it, verified against the authority list known to self, so that a faulty
+    /// syncer can not move the consensus onto a forked height. The application must then fill
+    /// `Status::proof` when it syncs over heights. Self knows the authority list of the next
+    /// height only, so a status can jump over one height at most, and the application syncs over
+    /// more heights by the statuses of each height in order. It is off by default.
+    pub verify_height_jump: bool,
+    /// Aggregate the signatures of a QC on a task of the async runtime instead of the blocking
+    /// thread pool, so that a single thread runtime does all the work itself, as the simulation
+    /// does to run in order. An aggregation then holds the runtime while it runs. It is off by
+    /// default.
+    pub inline_aggregation: bool,
+}
+
+impl Default for OverlordConfig {
+    fn default() -> Self {
+        OverlordConfig {
+            msg_channel_capacity: 1024,
+            verify_channel_capacity: 1024,
+            halt_on_double_sign: false,
+            vrf_proposer: false,
+            artifact_retention: 16,
+            authority_retention: 4,
+            max_authority_size: 1024,
+            trusted_intake: false,
+            block_cache_capacity: 64,
+            proposal_cache_capacity: 256,
+            vote_round_capacity: 256,
+            future_height_gap: 5,
+            future_round_gap: 10,
+            max_check_tasks: 8,
+            max_clock_skew: 0,
+            check_block_timeout: 0,
+            compact_proposal: false,
+            block_part_size: 0,
+            timeout_backoff: TimeoutBackoff::default(),
+            timeout_jitter: 0,
+            wal_policy: WalPolicy::default(),
+            panic_snapshot: false,
+            stall_watchdog: None,
+            optimistic_step: false,
+            proposal_pull: 0,
+            vote_pull: 0,
+            proposal_rebroadcast: 0,
+            qc_relay: false,
+            chain_id: Bytes::new(),
+            threshold_qc: false,
+            verify_workers: 0,
+            vote_batch_size: 0,
+            wal_votes: false,
+            verify_height_jump: false,
+            inline_aggregation: false,
+        }
+    }
+}
+
+impl OverlordConfig {
+    /// Create an overlord configuration.
+    pub fn new(msg_channel_capacity: usize, verify_channel_capacity: usize) -> Self {
+        OverlordConfig {
+            msg_channel_capacity,
+            verify_channel_capacity,
+            ..Default::default()
+        }
+    }
+
+    /// Check the configuration, which `Overlord::run` does before it starts. The gaps, the channel
+    /// capacities and the cache capacities must be positive, and the percents of the timeouts
+    /// must be at most 100.
+    pub fn validate(&self) -> ConsensusResult<()> {
+        let invalid = |field, reason| Err(ConsensusError::ConfigErr { field, reason });
+        let positive = [
+            ("future_height_gap", self.future_height_gap as usize),
+            ("future_round_gap", self.future_round_gap as usize),
+            ("msg_channel_capacity", self.msg_channel_capacity),
+            ("verify_channel_capacity", self.verify_channel_capacity),
+            ("block_cache_capacity", self.block_cache_capacity),
+            ("proposal_cache_capacity", self.proposal_cache_capacity),
+            ("vote_round_capacity", self.vote_round_capacity),
+        ];
+        if let Some((field, _)) = positive.iter().find(|(_, value)| *value == 0) {
+            return invalid(field, "must be positive");
+        }
+        let percents = [
+            ("timeout_jitter", self.timeout_jitter),
+            ("proposal_pull", self.proposal_pull),
+            ("vote_pull", self.vote_pull),
+            ("proposal_rebroadcast", self.proposal_rebroadcast),
+        ];
+        if let Some((field, _)) = percents.iter().find(|(_, value)| *value > 100) {
+            return invalid(field, "must be at most 100 percent");
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::DurationConfig;
+    use std::time::Duration;
+
+    use super::{DurationConfig, OverlordConfig, TimeoutBackoff};
+    use crate::error::{ConsensusError, ErrorKind};
+    use crate::types::Round;
 
     #[test]
     fn test_duration_config() {
@@ -218,4 +1040,75 @@ mod test {
         assert_eq!(config.get_precommit_config(), (3, 10));
         assert_eq!(config.get_brake_config(), (4, 10));
     }
+
+    #[test]
+    fn test_timeout_backoff() {
+        let timeout = Duration::from_millis(1000);
+        let backoff = TimeoutBackoff::default();
+        assert_eq!(backoff.timeout(timeout, Round(0)), timeout);
+        assert_eq!(backoff.timeout(timeout, Round(3)), timeout * 8);
+        assert_eq!(backoff.timeout(timeout, Round(5)), timeout * 32);
+        assert_eq!(backoff.timeout(timeout, Round(u64::MAX)), timeout * 32);
+
+        let backoff = TimeoutBackoff::new(50, 150, 200);
+        assert_eq!(backoff.timeout(timeout, Round(0)), timeout / 2);
+        assert_eq!(backoff.timeout(timeout, Round(1)), timeout * 3 / 4);
+        assert_eq!(backoff.timeout(timeout, Round(10)), timeout * 2);
+
+        let constant = TimeoutBackoff::new(100, 100, 100);
+        assert_eq!(constant.timeout(timeout, Round(9)), timeout);
+    }
+
+    #[test]
+    fn test_validate_config() {
+        assert!(OverlordConfig::default().validate().is_ok());
+        let invalid = [
+            OverlordConfig {
+                future_height_gap: 0,
+                ..Default::default()
+            },
+            OverlordConfig {
+                future_round_gap: 0,
+                ..Default::default()
+            },
+            OverlordConfig {
+                vote_round_capacity: 0,
+                ..Default::default()
+            },
+            OverlordConfig {
+                block_cache_capacity: 0,
+                ..Default::default()
+            },
+            OverlordConfig {
+                timeout_jitter: 101,
+                ..Default::default()
+            },
+            OverlordConfig {
+                vote_pull: 150,
+                ..Default::default()
+            },
+        ];
+        for (config, field) in invalid.iter().zip([
+            "future_height_gap",
+            "future_round_gap",
+            "vote_round_capacity",
+            "block_cache_capacity",
+            "timeout_jitter",
+            "vote_pull",
+        ]) {
+            let err = config.validate().unwrap_err();
+            assert!(
+                matches!(err, ConsensusError::ConfigErr { field: f, .. } if f == field),
+                "{}",
+                err
+            );
+            assert_eq!(err.kind(), ErrorKind::Config);
+        }
+        let config = OverlordConfig {
+            timeout_jitter: 100,
+            proposal_pull: 100,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+    }
 }
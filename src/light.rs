@@ -0,0 +1,243 @@
+use bytes::Bytes;
+
+use crate::error::ConsensusError;
+use crate::types::{Commit, Height, Node, Status};
+use crate::{Codec, ConsensusResult, Crypto};
+
+/// Verify a commit against the trusted authority list of its height, by the same quorum and
+/// bitmap rules as overlord verifies a precommit QC, see `Proof::verify_with_chain_id`.
+pub fn verify_commit<T: Codec>(
+    authority: &[Node],
+    crypto: &impl Crypto,
+    chain_id: &[u8],
+    commit: &Commit<T>,
+) -> ConsensusResult<()> {
+    if commit.proof.height != commit.height {
+        return Err(ConsensusError::CorrectnessErr(format!(
+            "commit of height {} carries the proof of height {}",
+            commit.height, commit.proof.height
+        )));
+    }
+    commit
+        .proof
+        .verify_with_chain_id(authority, crypto, chain_id)
+}
+
+/// A light client which follows a chain by its commits, starting from a trusted authority list.
+/// It checks the proof of every commit, and takes the authority list of the next height from the
+/// status committed with the block.
+///
+/// The status is not signed by the voters, so the application must bind it to the committed
+/// content, for example by a hash of the authority list in the block header, and check the binding
+/// before it updates the client.
+#[derive(Clone, Debug)]
+pub struct LightClient<C: Crypto> {
+    crypto: C,
+    chain_id: Bytes,
+    height: Height,
+    authority: Vec<Node>,
+    next_authority: Option<Vec<Node>>,
+}
+
+impl<C: Crypto> LightClient<C> {
+    /// Create a light client which trusts the authority list of the given height.
+    pub fn new(crypto: C, height: Height, authority: Vec<Node>) -> Self {
+        LightClient {
+            crypto,
+            chain_id: Bytes::new(),
+            height,
+            authority,
+            next_authority: None,
+        }
+    }
+
+    /// Verify the commits of a chain whose nodes sign with the given chain id, see
+    /// `OverlordConfig::chain_id`.
+    pub fn with_chain_id(mut self, chain_id: Bytes) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    /// The height of the next commit to verify.
+    pub fn height(&self) -> Height {
+        self.height
+    }
+
+    /// The trusted authority list of the current height.
+    pub fn authority(&self) -> &[Node] {
+        &self.authority
+    }
+
+    /// Verify a commit of the current height without updating the client.
+    pub fn verify<T: Codec>(&self, commit: &Commit<T>) -> ConsensusResult<()> {
+        if commit.height != self.height {
+            return Err(ConsensusError::UnexpectedHeightErr {
+                what: "commit",
+                height: commit.height,
+                expect: self.height,
+            });
+        }
+        verify_commit(&self.authority, &self.crypto, &self.chain_id, commit)
+    }
+
+    /// Verify a commit of the current height, then move the client to the next height. The
+    /// authority list of the next height is taken from the given status. Without a status, it is
+    /// the one announced by `Status::next_authority_list` of the previous status if any, or else
+    /// the current one. A status must be of the next height, and must follow the announced
+    /// authority list if any.
+    pub fn update<T: Codec>(
+        &mut self,
+        commit: &Commit<T>,
+        status: Option<&Status>,
+    ) -> ConsensusResult<()> {
+        self.verify(commit)?;
+        let next_height = self.height + 1;
+        let authority = match status {
+            Some(status) => {
+                self.verify_transition(next_height, status)?;
+                status.authority_list.clone()
+            }
+            None => match self.next_authority.take() {
+                Some(announced) => announced,
+                None => self.authority.clone(),
+            },
+        };
+
+        self.next_authority = status.and_then(|s| s.next_authority_list.clone());
+        self.authority = authority;
+        self.height = next_height;
+        Ok(())
+    }
+
+    fn verify_transition(&self, next_height: Height, status: &Status) -> ConsensusResult<()> {
+        if status.height != next_height {
+            return Err(ConsensusError::UnexpectedHeightErr {
+                what: "status",
+                height: status.height,
+                expect: next_height,
+            });
+        }
+        if status.authority_list.is_empty() {
+            return Err(ConsensusError::CorrectnessErr(
+                "status of an empty authority list".to_string(),
+            ));
+        }
+        if let Some(announced) = self.next_authority.as_ref() {
+            if !same_authority(announced, &status.authority_list) {
+                return Err(ConsensusError::CorrectnessErr(format!(
+                    "authority list of height {} differs from the announced one",
+                    next_height
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The authority lists are the same regardless of the order.
+fn same_authority(a: &[Node], b: &[Node]) -> bool {
+    let (mut a, mut b) = (a.to_vec(), b.to_vec());
+    a.sort();
+    b.sort();
+    a == b
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::LightClient;
+    use crate::test_utils::EchoCrypto;
+    use crate::types::{
+        Address, AggregatedSignature, Commit, Hash, Height, Node, Proof, QcKind, Round, Signature,
+        Status, Vote, VoteType,
+    };
+
+    fn authority(seed: u8) -> Vec<Node> {
+        (0..4u8)
+            .map(|i| Node::new(Address::from(vec![seed, i])))
+            .collect()
+    }
+
+    fn commit(height: u64, bitmap: u8) -> Commit<Vec<u8>> {
+        let vote = Vote {
+            height: Height(height),
+            round: Round(0),
+            vote_type: VoteType::Precommit,
+            block_hash: Hash::from(vec![height as u8]),
+        };
+        Commit {
+            height: Height(height),
+            content: vec![height as u8],
+            proof: Proof {
+                height: Height(height),
+                round: Round(0),
+                block_hash: vote.block_hash.clone(),
+                signature: AggregatedSignature {
+                    signature: Signature::from(Bytes::from(alloy_rlp::encode(&vote))),
+                    address_bitmap: Bytes::from(vec![bitmap]),
+                    kind: QcKind::Aggregated,
+                },
+                extensions: None,
+                vrf: None,
+            },
+        }
+    }
+
+    fn status(height: u64, authority_list: Vec<Node>, next: Option<Vec<Node>>) -> Status {
+        Status {
+            height: Height(height),
+            authority_list,
+            interval: None,
+            timer_config: None,
+            vrf_seed: None,
+            next_authority_list: next,
+            proof: None,
+        }
+    }
+
+    #[test]
+    fn test_light_client() {
+        let mut client = LightClient::new(EchoCrypto, Height(1), authority(0));
+
+        // A commit below the quorum or of another height is rejected.
+        assert!(client.verify(&commit(1, 0b1100_0000)).is_err());
+        assert!(client.verify(&commit(2, 0b1110_0000)).is_err());
+        let mut forged = commit(1, 0b1110_0000);
+        forged.proof.height = Height(2);
+        assert!(client.verify(&forged).is_err());
+
+        client.update(&commit(1, 0b1110_0000), None).unwrap();
+        assert_eq!(client.height(), Height(2));
+        assert_eq!(client.authority(), authority(0).as_slice());
+
+        // The status announces the authority list of height 4.
+        let status_3 = status(3, authority(0), Some(authority(1)));
+        assert!(client
+            .update(
+                &commit(2, 0b1111_0000),
+                Some(&status(2, authority(0), None))
+            )
+            .is_err());
+        client
+            .update(&commit(2, 0b1111_0000), Some(&status_3))
+            .unwrap();
+
+        // A status which breaks the announcement is rejected, and the client is not updated.
+        let commit_3 = commit(3, 0b0111_0000);
+        assert!(client
+            .update(&commit_3, Some(&status(4, authority(2), None)))
+            .is_err());
+        assert_eq!(client.height(), Height(3));
+        client
+            .update(&commit_3, Some(&status(4, authority(1), None)))
+            .unwrap();
+        assert_eq!(client.authority(), authority(1).as_slice());
+
+        // The commits of height 4 are verified against the new authority list, of which the
+        // bitmap is in the order of the sorted addresses.
+        assert!(client.verify(&commit(4, 0b1110_0000)).is_ok());
+        let mut client = client.with_chain_id(Bytes::from_static(b"chain"));
+        assert!(client.update(&commit(4, 0b1110_0000), None).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Address, DropReason};
+
+/// The count of the recent samples to calculate the percentiles from.
+const SAMPLE_CAPACITY: usize = 1024;
+
+/// The branches of the state loop.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Display, PartialEq, Eq, Hash, PartialOrd, Ord,
+)]
+pub enum StateBranch {
+    /// Handle a message from the overlord handler.
+    #[display("raw message")]
+    RawMsg,
+    /// Handle a rich status or stop message.
+    #[display("priority message")]
+    PriorityMsg,
+    /// Handle a control message.
+    #[display("control")]
+    Control,
+    /// Handle a SMR event.
+    #[display("event")]
+    Event,
+    /// Handle a check block response.
+    #[display("verify response")]
+    VerifyResp,
+    /// Handle an aggregated QC.
+    #[display("aggregate response")]
+    AggregateResp,
+    /// Handle a message which signature is verified.
+    #[display("verified message")]
+    VerifiedMsg,
+    /// Handle a message from the trusted intake.
+    #[display("trusted message")]
+    TrustedMsg,
+    /// Handle a block fetched for a signed compact proposal.
+    #[display("fetch response")]
+    FetchResp,
+}
+
+/// The latency of a branch of the state loop.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BranchLatency {
+    /// The branch of the state loop.
+    pub branch: StateBranch,
+    /// Count of the handled items since the overlord starts.
+    pub count: u64,
+    /// Mean latency since the overlord starts, as microsecond.
+    pub mean: u64,
+    /// Median latency of the recent items, as microsecond.
+    pub p50: u64,
+    /// 99th percentile latency of the recent items, as microsecond.
+    pub p99: u64,
+    /// Max latency since the overlord starts, as microsecond.
+    pub max: u64,
+}
+
+/// The metrics of the state loop. Every branch of the state loop blocks the others while it is
+/// handling, so a slow commit or wal saving shows up here.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateMetrics {
+    /// The latencies of the branches which have handled any item, ordered by the branch.
+    pub branches: Vec<BranchLatency>,
+    /// The evictions of the bounded caches.
+    #[serde(default)]
+    pub evictions: CacheEvictions,
+    /// The counts of the check block tasks.
+    #[serde(default)]
+    pub check_tasks: CheckTasks,
+    /// The counts of the statuses that are not applied.
+    #[serde(default)]
+    pub statuses: StatusCounts,
+    /// The counts of the dropped messages by type and reason, ordered by type and reason.
+    #[serde(default)]
+    pub drops: Vec<DropCount>,
+    /// The clock skews of the proposers, ordered by the proposer.
+    #[serde(default)]
+    pub clock_skews: Vec<ClockSkew>,
+    /// The count of the wal entries superseded before they are saved under `WalPolicy::Batched`.
+    #[serde(default)]
+    pub wal_coalesced: u64,
+}
+
+impl StateMetrics {
+    /// Get the latency of the given branch.
+    pub fn get(&self, branch: StateBranch) -> Option<&BranchLatency> {
+        self.branches.iter().find(|item| item.branch == branch)
+    }
+}
+
+/// The count of the items evicted from each bounded cache since the overlord starts. A growing
+/// count within a height means the cache is full of messages of spam rounds.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheEvictions {
+    /// Evicted blocks.
+    pub blocks: u64,
+    /// Evicted check block results.
+    pub full_transactions: u64,
+    /// Evicted signed proposals.
+    pub proposals: u64,
+    /// Evicted rounds of votes.
+    pub vote_rounds: u64,
+}
+
+/// The counts of the check block tasks. The running tasks are no more than the configured maximum
+/// and the others are queued.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CheckTasks {
+    /// The running tasks.
+    pub running: u64,
+    /// The queued tasks.
+    pub queued: u64,
+    /// The spawned tasks since the overlord starts.
+    pub spawned: u64,
+    /// The tasks cancelled for their stale heights or rounds since the overlord starts.
+    pub cancelled: u64,
+}
+
+/// The count of the dropped messages of a type for a reason since the overlord starts.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DropCount {
+    /// The type of the messages, as the display of `OverlordMsg`.
+    pub msg_type: String,
+    /// The reason of the drops.
+    pub reason: DropReason,
+    /// The count of the drops.
+    pub count: u64,
+}
+
+/// The counts of the statuses that are not applied since the overlord starts.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StatusCounts {
+    /// The duplicates of the last applied status, which are absorbed silently.
+    pub duplicate: u64,
+    /// The statuses lower than the current height, which are rejected with a warning.
+    pub stale: u64,
+}
+
+/// The clock skews of a proposer measured from the timestamps of its blocks since the overlord
+/// starts. A positive skew is of a clock ahead of self.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ClockSkew {
+    /// The address of the proposer.
+    pub proposer: Address,
+    /// Count of the measured timestamps.
+    pub samples: u64,
+    /// Count of the timestamps beyond the maximum clock skew.
+    pub outliers: u64,
+    /// The last skew, as millisecond.
+    pub last: i64,
+    /// The skew of the largest magnitude, as millisecond.
+    pub max: i64,
+}
+
+impl ClockSkew {
+    pub(crate) fn new(proposer: Address) -> Self {
+        ClockSkew {
+            proposer,
+            samples: 0,
+            outliers: 0,
+            last: 0,
+            max: 0,
+        }
+    }
+
+    pub(crate) fn record(&mut self, skew: i64, is_outlier: bool) {
+        self.samples += 1;
+        self.last = skew;
+        if skew.unsigned_abs() > self.max.unsigned_abs() {
+            self.max = skew;
+        }
+        if is_outlier {
+            self.outliers += 1;
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Samples {
+    recent: VecDeque<Duration>,
+    count: u64,
+    total: Duration,
+    max: Duration,
+}
+
+impl Samples {
+    fn record(&mut self, cost: Duration) {
+        if self.recent.len() == SAMPLE_CAPACITY {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(cost);
+        self.count += 1;
+        self.total += cost;
+        self.max = self.max.max(cost);
+    }
+
+    fn latency(&self, branch: StateBranch) -> BranchLatency {
+        let mut sorted = self.recent.iter().cloned().collect::<Vec<_>>();
+        sorted.sort();
+
+        BranchLatency {
+            branch,
+            count: self.count,
+            mean: (self.total.as_micros() / u128::from(self.count.max(1))) as u64,
+            p50: percentile(&sorted, 50).as_micros() as u64,
+            p99: percentile(&sorted, 99).as_micros() as u64,
+            max: self.max.as_micros() as u64,
+        }
+    }
+}
+
+/// The nearest-rank percentile of the sorted samples.
+fn percentile(sorted: &[Duration], pct: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::default();
+    }
+    let rank = (sorted.len() * pct).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+/// A recorder of the latencies of the state loop branches.
+#[derive(Debug, Default)]
+pub(crate) struct LatencyRecorder {
+    samples: HashMap<StateBranch, Samples>,
+}
+
+impl LatencyRecorder {
+    pub(crate) fn new() -> Self {
+        LatencyRecorder::default()
+    }
+
+    pub(crate) fn record(&mut self, branch: StateBranch, cost: Duration) {
+        self.samples.entry(branch).or_default().record(cost);
+    }
+
+    pub(crate) fn metrics(&self) -> StateMetrics {
+        let mut branches = self
+            .samples
+            .iter()
+            .map(|(branch, samples)| samples.latency(*branch))
+            .collect::<Vec<_>>();
+        branches.sort_by_key(|item| item.branch);
+        StateMetrics {
+            branches,
+            evictions: CacheEvictions::default(),
+            check_tasks: CheckTasks::default(),
+            statuses: StatusCounts::default(),
+            drops: Vec::new(),
+            clock_skews: Vec::new(),
+            wal_coalesced: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{ClockSkew, LatencyRecorder, StateBranch, SAMPLE_CAPACITY};
+    use crate::types::Address;
+
+    #[test]
+    fn test_latency_recorder() {
+        let mut recorder = LatencyRecorder::new();
+        assert!(recorder.metrics().branches.is_empty());
+
+        for i in 1..=100u64 {
+            recorder.record(StateBranch::Event, Duration::from_micros(i));
+        }
+        recorder.record(StateBranch::RawMsg, Duration::from_micros(7));
+
+        let metrics = recorder.metrics();
+        assert_eq!(metrics.branches.len(), 2);
+        assert_eq!(metrics.branches[0].branch, StateBranch::RawMsg);
+
+        let event = metrics.get(StateBranch::Event).unwrap();
+        assert_eq!(event.count, 100);
+        assert_eq!(event.mean, 50);
+        assert_eq!(event.p50, 50);
+        assert_eq!(event.p99, 99);
+        assert_eq!(event.max, 100);
+        assert_eq!(metrics.get(StateBranch::RawMsg).unwrap().p99, 7);
+
+        // The percentiles come from the recent samples only.
+        for _ in 0..SAMPLE_CAPACITY {
+            recorder.record(StateBranch::Event, Duration::from_micros(1));
+        }
+        let event = recorder.metrics().get(StateBranch::Event).cloned().unwrap();
+        assert_eq!(event.count, 100 + SAMPLE_CAPACITY as u64);
+        assert_eq!(event.p99, 1);
+        assert_eq!(event.max, 100);
+    }
+
+    #[test]
+    fn test_clock_skew() {
+        let mut skew = ClockSkew::new(Address::from(vec![1u8; 20]));
+        skew.record(30, false);
+        skew.record(-500, true);
+        skew.record(20, false);
+        assert_eq!(skew.samples, 3);
+        assert_eq!(skew.outliers, 1);
+        assert_eq!(skew.last, 20);
+        assert_eq!(skew.max, -500);
+    }
+}
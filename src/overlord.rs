@@ -1,26 +1,58 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use creep::Context;
-use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
-use parking_lot::RwLock;
+use futures::channel::mpsc::{
+    channel, unbounded, Receiver, Sender, UnboundedReceiver, UnboundedSender,
+};
+use futures::channel::oneshot;
+use parking_lot::{Mutex, RwLock};
 
+use crate::aux_store::{AuxStore, MemoryAuxStore};
+use crate::crash::install_panic_hook;
 use crate::error::ConsensusError;
+use crate::metrics::StateMetrics;
 use crate::state::process::State;
-use crate::types::{Address, Node, OverlordMsg};
-use crate::DurationConfig;
+use crate::trace::TraceRecorder;
+use crate::types::{
+    Address, AggregatedVote, Commit, ConsensusStatus, ControlMsg, DebugTarget, Height,
+    HeightArtifacts, Node, OverlordMsg, Proof, Round, StateSnapshot,
+};
+use crate::utils::auth_manage::{
+    check_authority_size, check_authority_weights, check_threshold_weights,
+};
 use crate::{smr::SMR, timer::Timer};
-use crate::{Codec, Consensus, ConsensusResult, Crypto, Wal};
+use crate::{
+    ArtifactSink, BlockProvider, Codec, Consensus, ConsensusResult, Crypto, Network, NoopReporter,
+    ProofStore, Reporter, Wal,
+};
+use crate::{DurationConfig, OverlordConfig};
 
 type Pile<T> = RwLock<Option<T>>;
+type MsgSender<T> = Sender<(Context, OverlordMsg<T>)>;
 
 /// An overlord consensus instance.
 pub struct Overlord<T: Codec, F: Consensus<T>, C: Crypto, W: Wal> {
-    sender: Pile<UnboundedSender<(Context, OverlordMsg<T>)>>,
-    state_rx: Pile<UnboundedReceiver<(Context, OverlordMsg<T>)>>,
+    sender: Pile<MsgSender<T>>,
+    state_rx: Pile<Receiver<(Context, OverlordMsg<T>)>>,
+    priority_sender: Pile<UnboundedSender<(Context, OverlordMsg<T>)>>,
+    priority_rx: Pile<UnboundedReceiver<(Context, OverlordMsg<T>)>>,
+    ctrl_sender: Pile<UnboundedSender<ControlMsg<T>>>,
+    ctrl_rx: Pile<UnboundedReceiver<ControlMsg<T>>>,
+    trusted_sender: Pile<MsgSender<T>>,
+    trusted_rx: Pile<Receiver<(Context, OverlordMsg<T>)>>,
     address: Pile<Address>,
     consensus: Pile<Arc<F>>,
     crypto: Pile<Arc<C>>,
     wal: Pile<Arc<W>>,
+    aux_store: Arc<dyn AuxStore>,
+    reporter: Arc<dyn Reporter>,
+    network: Option<Arc<dyn Network<T>>>,
+    block_provider: Option<Arc<dyn BlockProvider<T>>>,
+    trace_recorder: Option<Arc<TraceRecorder>>,
+    artifact_sink: Option<Arc<dyn ArtifactSink<T>>>,
+    proof_store: Option<Arc<dyn ProofStore>>,
+    config: OverlordConfig,
 }
 
 impl<T, F, C, W> Overlord<T, F, C, W>
@@ -28,44 +60,153 @@ where
     T: Codec + Send + Sync + 'static,
     F: Consensus<T> + 'static,
     C: Crypto + Send + Sync + 'static,
-    W: Wal + 'static,
+    W: Wal + Sync + 'static,
 {
-    /// Create a new overlord and return an overlord instance with an unbounded receiver.
+    /// Create a new overlord with the default configuration.
     pub fn new(address: Address, consensus: Arc<F>, crypto: Arc<C>, wal: Arc<W>) -> Self {
-        let (tx, rx) = unbounded();
+        Self::new_with_config(address, consensus, crypto, wal, OverlordConfig::default())
+    }
+
+    /// Create a new overlord with the given configuration.
+    pub fn new_with_config(
+        address: Address,
+        consensus: Arc<F>,
+        crypto: Arc<C>,
+        wal: Arc<W>,
+        config: OverlordConfig,
+    ) -> Self {
+        let (tx, rx) = channel(config.msg_channel_capacity);
+        let (priority_tx, priority_rx) = unbounded();
+        let (ctrl_tx, ctrl_rx) = unbounded();
+        // The trusted sender is dropped if the trusted intake is not enabled, then the receiver
+        // is terminated at once.
+        let (trusted_tx, trusted_rx) = channel(config.msg_channel_capacity);
         Overlord {
             sender: RwLock::new(Some(tx)),
             state_rx: RwLock::new(Some(rx)),
+            priority_sender: RwLock::new(Some(priority_tx)),
+            priority_rx: RwLock::new(Some(priority_rx)),
+            ctrl_sender: RwLock::new(Some(ctrl_tx)),
+            ctrl_rx: RwLock::new(Some(ctrl_rx)),
+            trusted_sender: RwLock::new(config.trusted_intake.then_some(trusted_tx)),
+            trusted_rx: RwLock::new(Some(trusted_rx)),
             address: RwLock::new(Some(address)),
             consensus: RwLock::new(Some(consensus)),
             crypto: RwLock::new(Some(crypto)),
             wal: RwLock::new(Some(wal)),
+            aux_store: Arc::new(MemoryAuxStore::new()),
+            reporter: Arc::new(NoopReporter),
+            network: None,
+            block_provider: None,
+            trace_recorder: None,
+            artifact_sink: None,
+            proof_store: None,
+            config,
         }
     }
 
+    /// Persist the votes signed by self and the artifacts of the recent heights in the given
+    /// auxiliary store, which are restored on restart. They are kept in memory by default.
+    ///
+    /// **NOTICE**: A validator must set a durable store, such as a `FileAuxStore`. The double sign
+    /// guard persists the signed votes and the high-water marks of the signed payloads in the
+    /// store, and with the default in-memory store they are lost on restart, so a restarted
+    /// validator may sign a conflicting vote of the round it crashed in.
+    pub fn with_aux_store(mut self, aux_store: Arc<dyn AuxStore>) -> Self {
+        self.aux_store = aux_store;
+        self
+    }
+
+    /// Report the consensus metrics to the given reporter. Nothing is reported by default.
+    pub fn with_reporter(mut self, reporter: Arc<dyn Reporter>) -> Self {
+        self.reporter = reporter;
+        self
+    }
+
+    /// Send the messages through the given network rather than `Consensus::broadcast_to_other`
+    /// and `Consensus::transmit_to_relayer`.
+    pub fn with_network(mut self, network: Arc<dyn Network<T>>) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    /// Get and check the blocks by the given provider rather than `Consensus::get_block` and
+    /// `Consensus::check_block`.
+    pub fn with_block_provider(mut self, block_provider: Arc<dyn BlockProvider<T>>) -> Self {
+        self.block_provider = Some(block_provider);
+        self
+    }
+
+    /// Record the triggers handled by the SMR and the events thrown by it to the given recorder,
+    /// of which the trace can be replayed, see `SmrTrace::replay`. Nothing is recorded by default.
+    pub fn with_trace_recorder(mut self, recorder: Arc<TraceRecorder>) -> Self {
+        self.trace_recorder = Some(recorder);
+        self
+    }
+
+    /// Archive the verified proposals, QCs and choke QCs to the given sink, besides the commits.
+    /// Nothing is archived by default.
+    pub fn with_artifact_sink(mut self, sink: Arc<dyn ArtifactSink<T>>) -> Self {
+        self.artifact_sink = Some(sink);
+        self
+    }
+
+    /// Save the proof of every committed height to the given store, which is queried by
+    /// `OverlordHandler::get_proof` and `OverlordHandler::latest_proof`. Without a store, the
+    /// proofs of the recent `artifact_retention` heights are queried.
+    pub fn with_proof_store(mut self, proof_store: Arc<dyn ProofStore>) -> Self {
+        self.proof_store = Some(proof_store);
+        self
+    }
+
     /// Get the overlord handler from the overlord instance.
     pub fn get_handler(&self) -> OverlordHandler<T> {
         let sender = self.sender.write();
         assert!(sender.is_some());
         let tx = sender.clone().unwrap();
-        OverlordHandler::new(tx)
+        let priority_tx = self.priority_sender.read().clone().unwrap();
+        let ctrl_tx = self.ctrl_sender.read().clone().unwrap();
+        let trusted_tx = self.trusted_sender.read().clone();
+        OverlordHandler::new(tx, priority_tx, ctrl_tx, trusted_tx)
     }
 
     /// Run overlord consensus process. The `interval` is the height interval as millisecond.
     pub async fn run(
         &self,
-        init_height: u64,
+        init_height: Height,
         interval: u64,
         authority_list: Vec<Node>,
         timer_config: Option<DurationConfig>,
     ) -> ConsensusResult<()> {
+        self.config.validate()?;
+        check_authority_size(&authority_list, self.config.max_authority_size)?;
+        check_authority_weights(&authority_list)?;
+        if self.config.threshold_qc {
+            check_threshold_weights(&authority_list)?;
+        }
         let (mut smr_provider, evt_state, evt_timer) = SMR::new();
         let smr_handler = smr_provider.take_smr();
-        let timer = Timer::new(evt_timer, smr_handler.clone(), interval, timer_config);
-        let (verify_sig_tx, verify_sig_rx) = unbounded();
+        let timer = Timer::new(
+            evt_timer,
+            smr_handler.clone(),
+            interval,
+            timer_config,
+            self.config.timeout_backoff.clone(),
+        )
+        .with_proposal_pull(self.config.proposal_pull)
+        .with_vote_pull(self.config.vote_pull)
+        .with_proposal_rebroadcast(self.config.proposal_rebroadcast)
+        .with_jitter(
+            self.config.timeout_jitter,
+            self.address.read().as_ref().map_or(0, jitter_seed),
+        );
+        let (verify_sig_tx, verify_sig_rx) = channel(self.config.verify_channel_capacity);
 
-        let (rx, mut state, resp) = {
+        let (rx, priority_rx, ctrl_rx, trusted_rx, mut state, resp, aggregate_rx, fetch_rx) = {
             let mut state_rx = self.state_rx.write();
+            let mut priority_rx = self.priority_rx.write();
+            let mut ctrl_rx = self.ctrl_rx.write();
+            let mut trusted_rx = self.trusted_rx.write();
             let mut address = self.address.write();
             let mut consensus = self.consensus.write();
             let mut crypto = self.crypto.write();
@@ -73,7 +214,10 @@ where
             // let sender = self.sender.read();
 
             let tmp_rx = state_rx.take().unwrap();
-            let (tmp_state, tmp_resp) = State::new(
+            let tmp_priority_rx = priority_rx.take().unwrap();
+            let tmp_ctrl_rx = ctrl_rx.take().unwrap();
+            let tmp_trusted_rx = trusted_rx.take().unwrap();
+            let (tmp_state, tmp_resp, tmp_aggregate_rx, tmp_fetch_rx) = State::new(
                 smr_handler,
                 address.take().unwrap(),
                 init_height,
@@ -83,44 +227,137 @@ where
                 consensus.take().unwrap(),
                 crypto.take().unwrap(),
                 wal.take().unwrap(),
-            );
+                Arc::clone(&self.aux_store),
+                &self.config,
+            )?;
 
             // assert!(sender.is_none());
             assert!(address.is_none());
             assert!(consensus.is_none());
             assert!(crypto.is_none());
             assert!(state_rx.is_none());
+            assert!(priority_rx.is_none());
+            assert!(ctrl_rx.is_none());
+            assert!(trusted_rx.is_none());
             assert!(wal.is_none());
 
-            (tmp_rx, tmp_state, tmp_resp)
+            (
+                tmp_rx,
+                tmp_priority_rx,
+                tmp_ctrl_rx,
+                tmp_trusted_rx,
+                tmp_state,
+                tmp_resp,
+                tmp_aggregate_rx,
+                tmp_fetch_rx,
+            )
         };
 
         log::info!("Overlord start running");
+        state.set_reporter(Arc::clone(&self.reporter));
+        if let Some(network) = self.network.as_ref() {
+            state.set_network(Arc::clone(network));
+        }
+        if let Some(block_provider) = self.block_provider.as_ref() {
+            state.set_block_provider(Arc::clone(block_provider));
+        }
+        if let Some(sink) = self.artifact_sink.as_ref() {
+            state.set_artifact_sink(Arc::clone(sink));
+        }
+        if let Some(proof_store) = self.proof_store.as_ref() {
+            state.set_proof_store(Arc::clone(proof_store));
+        }
+
+        let recorder = state.crash_recorder();
+        if let Some(recorder) = recorder.as_ref() {
+            install_panic_hook();
+            smr_provider.set_recorder(Arc::clone(recorder));
+        }
 
         // Run SMR.
+        smr_provider.set_reporter(Arc::clone(&self.reporter));
+        if let Some(trace_recorder) = self.trace_recorder.as_ref() {
+            smr_provider.set_trace_recorder(Arc::clone(trace_recorder));
+        }
         smr_provider.run();
 
         // Run timer.
         timer.run();
 
         // Run state.
-        state.run(rx, evt_state, resp, verify_sig_rx).await;
+        let run = state.run(
+            rx,
+            priority_rx,
+            ctrl_rx,
+            trusted_rx,
+            evt_state,
+            resp,
+            verify_sig_rx,
+            aggregate_rx,
+            fetch_rx,
+        );
+        match recorder {
+            Some(recorder) => recorder.scope(run).await,
+            None => run.await,
+        }
 
         Ok(())
     }
+
+    /// Export the proposals, QCs, chokes and the proof that self saw in the given height, see
+    /// `OverlordHandler::export_height_artifacts`.
+    pub async fn export_height_artifacts(
+        &self,
+        height: Height,
+    ) -> ConsensusResult<Option<HeightArtifacts<T>>> {
+        self.get_handler().export_height_artifacts(height).await
+    }
+}
+
+/// The seed of the timeout jitter of a node, which differs by the address.
+fn jitter_seed(address: &Address) -> u64 {
+    address
+        .iter()
+        .fold(0u64, |seed, byte| seed.rotate_left(8) ^ u64::from(*byte))
 }
 
 /// An overlord handler to send messages to an overlord instance.
 #[derive(Clone, Debug)]
-pub struct OverlordHandler<T: Codec>(UnboundedSender<(Context, OverlordMsg<T>)>);
+pub struct OverlordHandler<T: Codec> {
+    msg_tx: Arc<Mutex<MsgSender<T>>>,
+    priority_tx: UnboundedSender<(Context, OverlordMsg<T>)>,
+    ctrl_tx: UnboundedSender<ControlMsg<T>>,
+    trusted_tx: Option<Arc<Mutex<MsgSender<T>>>>,
+}
 
 impl<T: Codec> OverlordHandler<T> {
-    fn new(tx: UnboundedSender<(Context, OverlordMsg<T>)>) -> Self {
-        OverlordHandler(tx)
+    fn new(
+        msg_tx: MsgSender<T>,
+        priority_tx: UnboundedSender<(Context, OverlordMsg<T>)>,
+        ctrl_tx: UnboundedSender<ControlMsg<T>>,
+        trusted_tx: Option<MsgSender<T>>,
+    ) -> Self {
+        OverlordHandler {
+            msg_tx: Arc::new(Mutex::new(msg_tx)),
+            priority_tx,
+            ctrl_tx,
+            trusted_tx: trusted_tx.map(|tx| Arc::new(Mutex::new(tx))),
+        }
     }
 
-    /// Send overlord message to the instance. Return `Err()` when the message channel is closed.
+    /// Send overlord message to the instance. Return `Err()` when the message channel is closed,
+    /// or when the message channel is full and the message is dropped. Rich status and stop
+    /// messages are never dropped.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "overlord.send_msg_to_inner",
+            skip_all,
+            fields(kind = "overlord")
+        )
+    )]
     pub fn send_msg(&self, ctx: Context, msg: OverlordMsg<T>) -> ConsensusResult<()> {
+        #[cfg(feature = "apm")]
         let ctx = match muta_apm::MUTA_TRACER.span(
             "overlord.send_msg_to_inner",
             vec![muta_apm::rustracing::tag::Tag::new("kind", "overlord")],
@@ -134,14 +371,280 @@ impl<T: Codec> OverlordHandler<T> {
             None => ctx,
         };
 
-        if self.0.is_closed() {
-            Err(ConsensusError::ChannelErr(
+        if self.priority_tx.is_closed() {
+            return Err(ConsensusError::ChannelErr(
                 "[OverlordHandler]: channel closed".to_string(),
-            ))
-        } else {
-            self.0
+            ));
+        }
+
+        if msg.is_priority() {
+            return self
+                .priority_tx
                 .unbounded_send((ctx, msg))
-                .map_err(|e| ConsensusError::Other(format!("Send message error {:?}", e)))
+                .map_err(|e| ConsensusError::Other(format!("Send message error {:?}", e)));
         }
+
+        self.msg_tx.lock().try_send((ctx, msg)).map_err(|e| {
+            if e.is_full() {
+                log::warn!("Overlord: handler message channel is full, drop a message");
+                ConsensusError::ChannelErr("[OverlordHandler]: channel full".to_string())
+            } else {
+                ConsensusError::ChannelErr("[OverlordHandler]: channel closed".to_string())
+            }
+        })
+    }
+
+    /// Send an overlord message that has been verified by the caller, such as a QC that the sync
+    /// module of the node has verified. The message skips the signature verification of the
+    /// overlord. Return `Err()` when the trusted intake is not enabled by
+    /// `OverlordConfig::trusted_intake`, when the channel is closed, or when the channel is full
+    /// and the message is dropped.
+    pub fn send_trusted_msg(&self, ctx: Context, msg: OverlordMsg<T>) -> ConsensusResult<()> {
+        let trusted_tx = self.trusted_tx.as_ref().ok_or_else(|| {
+            ConsensusError::ChannelErr("[OverlordHandler]: trusted intake disabled".to_string())
+        })?;
+
+        trusted_tx.lock().try_send((ctx, msg)).map_err(|e| {
+            if e.is_full() {
+                log::warn!("Overlord: handler trusted channel is full, drop a message");
+                ConsensusError::ChannelErr("[OverlordHandler]: trusted channel full".to_string())
+            } else {
+                ConsensusError::ChannelErr("[OverlordHandler]: trusted channel closed".to_string())
+            }
+        })
+    }
+
+    /// Query a snapshot of the live consensus status, includes the current height, round, step,
+    /// leader, lock round, authority list and vote tallies. Return `Err()` when the overlord
+    /// instance is not running.
+    pub async fn get_consensus_status(&self) -> ConsensusResult<ConsensusStatus> {
+        let (tx, rx) = oneshot::channel();
+        self.send_control(ControlMsg::QueryStatus(tx))?;
+        rx.await.map_err(|_| {
+            ConsensusError::ChannelErr("[OverlordHandler]: status query dropped".to_string())
+        })
+    }
+
+    /// Query the latency metrics of each branch of the state loop, such as the p99 latency of
+    /// handling SMR events. Return `Err()` when the overlord instance is not running.
+    pub async fn get_state_metrics(&self) -> ConsensusResult<StateMetrics> {
+        let (tx, rx) = oneshot::channel();
+        self.send_control(ControlMsg::QueryMetrics(tx))?;
+        rx.await.map_err(|_| {
+            ConsensusError::ChannelErr("[OverlordHandler]: metrics query dropped".to_string())
+        })
+    }
+
+    /// Export the proposals, QCs, chokes and the proof that self saw in the given height as a
+    /// serializable bundle. The current height is exported without a proof. A committed height is
+    /// exported if it is one of the recent `artifact_retention` heights, otherwise return
+    /// `Ok(None)`. Return `Err()` when the overlord instance is not running.
+    pub async fn export_height_artifacts(
+        &self,
+        height: Height,
+    ) -> ConsensusResult<Option<HeightArtifacts<T>>> {
+        let (tx, rx) = oneshot::channel();
+        self.send_control(ControlMsg::ExportArtifacts(height, tx))?;
+        rx.await.map_err(|_| {
+            ConsensusError::ChannelErr("[OverlordHandler]: artifacts export dropped".to_string())
+        })
+    }
+
+    /// Get the commit proof of the given height from the proof store, see
+    /// `Overlord::with_proof_store`. Return `Ok(None)` if the proof is not kept, and `Err()` if the
+    /// store fails or when the overlord instance is not running.
+    pub async fn get_proof(&self, height: Height) -> ConsensusResult<Option<Proof>> {
+        self.query_proof(Some(height)).await
+    }
+
+    /// Get the commit proof of the highest height kept by the proof store, see `get_proof`.
+    pub async fn latest_proof(&self) -> ConsensusResult<Option<Proof>> {
+        self.query_proof(None).await
+    }
+
+    async fn query_proof(&self, height: Option<Height>) -> ConsensusResult<Option<Proof>> {
+        let (tx, rx) = oneshot::channel();
+        self.send_control(ControlMsg::QueryProof(height, tx))?;
+        rx.await.map_err(|_| {
+            ConsensusError::ChannelErr("[OverlordHandler]: proof query dropped".to_string())
+        })?
+    }
+
+    /// Export the state snapshot of the current height, which carries the height, round, step,
+    /// lock, QCs and chokes of the state. Import it by `import_snapshot` into a node of the same
+    /// address, such as when the validator is migrated to new hardware mid-height. Stop the
+    /// exporting node before starting the importing one, or the two may sign conflicting votes.
+    /// Return `Err()` when the overlord instance is not running.
+    pub async fn export_snapshot(&self) -> ConsensusResult<StateSnapshot<T>> {
+        let (tx, rx) = oneshot::channel();
+        self.send_control(ControlMsg::ExportSnapshot(tx))?;
+        rx.await.map_err(|_| {
+            ConsensusError::ChannelErr("[OverlordHandler]: snapshot export dropped".to_string())
+        })?
+    }
+
+    /// Continue the height of a state snapshot exported by `export_snapshot`. The QCs, the chokes
+    /// and the choke QCs of the snapshot are verified, and it is saved to the wal before the state
+    /// recovers from it. Return
+    /// `Err()` if the snapshot is invalid or older than the state, or when the overlord instance is
+    /// not running.
+    pub async fn import_snapshot(&self, snapshot: StateSnapshot<T>) -> ConsensusResult<()> {
+        let (tx, rx) = oneshot::channel();
+        self.send_control(ControlMsg::ImportSnapshot(Box::new(snapshot), tx))?;
+        rx.await.map_err(|_| {
+            ConsensusError::ChannelErr("[OverlordHandler]: snapshot import dropped".to_string())
+        })?
+    }
+
+    /// Query the authority list that applies to the given height, which is used to verify the
+    /// messages and proofs of the height. A previous height is served if its authority list is
+    /// one of the recent `authority_retention` ones, otherwise return `Ok(None)`. A future height
+    /// gets the announced authority list if any, otherwise the current one. Return `Err()` when
+    /// the overlord instance is not running.
+    pub async fn get_authority_list(&self, height: Height) -> ConsensusResult<Option<Vec<Node>>> {
+        let (tx, rx) = oneshot::channel();
+        self.send_control(ControlMsg::QueryAuthority(height, tx))?;
+        rx.await.map_err(|_| {
+            ConsensusError::ChannelErr("[OverlordHandler]: authority query dropped".to_string())
+        })
+    }
+
+    /// Hand a precommit QC of the current height that the sync of the node has obtained, so that
+    /// the node commits the height at once instead of waiting for the QC to be gossiped again. The
+    /// QC is verified against the authority list of the height, and the height is committed once
+    /// the block of the QC is received and checked. Return `Err()` if the QC is of another height
+    /// or type, or is invalid, or when the overlord instance is not running.
+    pub async fn send_verified_qc(&self, qc: AggregatedVote) -> ConsensusResult<()> {
+        let (tx, rx) = oneshot::channel();
+        self.send_control(ControlMsg::InjectQC(qc, tx))?;
+        rx.await.map_err(|_| {
+            ConsensusError::ChannelErr("[OverlordHandler]: QC injection dropped".to_string())
+        })?
+    }
+
+    /// Subscribe to the commits of the instance, such as for an indexer or an archiver that lives
+    /// outside of the `Consensus` implementation. The stream yields every commit from the next
+    /// one, after `Consensus::commit` of it has returned, and ends when the instance stops. Up to
+    /// `capacity` commits are buffered until the stream is polled. A subscriber which lags behind
+    /// by more than that is dropped with a warning, so its stream ends without missing a commit in
+    /// the middle, and it should subscribe again after catching up from the storage. Return
+    /// `Err()` when the overlord instance is not running.
+    pub fn subscribe_commits(&self, capacity: usize) -> ConsensusResult<Receiver<Commit<T>>> {
+        let (tx, rx) = channel(capacity);
+        self.send_control(ControlMsg::SubscribeCommits(tx))?;
+        Ok(rx)
+    }
+
+    /// Force the current round to end as if it had timed out, such as to move past a proposer that
+    /// is known to be down. The node chokes for the round and goes to the next round once the
+    /// chokes are above the threshold, and the view change is reported as `Forced`. The lock is
+    /// kept, so safety is unaffected. Return `Err()` if the node is not a consensus node, or when
+    /// the overlord instance is not running.
+    pub async fn force_view_change(&self) -> ConsensusResult<()> {
+        let (tx, rx) = oneshot::channel();
+        self.send_control(ControlMsg::ForceViewChange(tx))?;
+        rx.await.map_err(|_| {
+            ConsensusError::ChannelErr("[OverlordHandler]: force view change dropped".to_string())
+        })?
+    }
+
+    /// Query the proposer of the given height and round as scheduled by the authority list of the
+    /// height, so that the expected leader can be displayed and the schedules of the nodes can be
+    /// compared. Return `Ok(None)` if the authority list of the height is not kept, see
+    /// `get_authority_list`, or if the height is not the current one in the VRF proposer mode, of
+    /// which the VRF seed is unknown. Return `Err()` when the overlord instance is not running.
+    pub async fn get_proposer(
+        &self,
+        height: Height,
+        round: Round,
+    ) -> ConsensusResult<Option<Address>> {
+        let (tx, rx) = oneshot::channel();
+        self.send_control(ControlMsg::QueryProposer(height, round, tx))?;
+        rx.await.map_err(|_| {
+            ConsensusError::ChannelErr("[OverlordHandler]: proposer query dropped".to_string())
+        })
+    }
+
+    /// Query the proposers of the rounds of the given height, from round zero until the proposers
+    /// repeat, see `get_proposer`.
+    pub async fn get_proposer_rotation(
+        &self,
+        height: Height,
+    ) -> ConsensusResult<Option<Vec<Address>>> {
+        let (tx, rx) = oneshot::channel();
+        self.send_control(ControlMsg::QueryRotation(height, tx))?;
+        rx.await.map_err(|_| {
+            ConsensusError::ChannelErr("[OverlordHandler]: rotation query dropped".to_string())
+        })
+    }
+
+    /// Pause the overlord instance for maintenance. A paused instance neither proposes, votes nor
+    /// chokes, while it keeps collecting messages, answering QC requests and following the QCs
+    /// from the peers. The wal and the lock are kept. Return `Err()` when the overlord instance is
+    /// not running.
+    pub async fn pause(&self) -> ConsensusResult<()> {
+        self.set_paused(true).await
+    }
+
+    /// Resume a paused overlord instance. It proposes and votes again from the next step.
+    /// Return `Err()` when the overlord instance is not running.
+    pub async fn resume(&self) -> ConsensusResult<()> {
+        self.set_paused(false).await
+    }
+
+    async fn set_paused(&self, paused: bool) -> ConsensusResult<()> {
+        let (tx, rx) = oneshot::channel();
+        self.send_control(ControlMsg::SetPaused(paused, tx))?;
+        rx.await
+            .map_err(|_| ConsensusError::ChannelErr("[OverlordHandler]: pause dropped".to_string()))
+    }
+
+    /// Shut down the overlord instance gracefully. The state saves a final wal entry, cancels the
+    /// outstanding `check_block` tasks, stops the SMR and the timer, and closes its channels from
+    /// the handler side to the internal side. Resolve when the state loop has fully exited, then
+    /// `Overlord::run` returns. Return `Err()` when the overlord instance is not running.
+    pub async fn shutdown(&self) -> ConsensusResult<()> {
+        let (tx, rx) = oneshot::channel();
+        self.send_control(ControlMsg::Shutdown(tx))?;
+        rx.await.map_err(|_| {
+            ConsensusError::ChannelErr("[OverlordHandler]: shutdown dropped".to_string())
+        })
+    }
+
+    /// Trace the messages and the events of the target at the info level with the
+    /// `overlord::debug` log target, until the time to live expires. It is used to debug an
+    /// incident on a running validator without the global debug logging. Setting a target again
+    /// renews its time to live. Return `Err()` when the overlord instance is not running.
+    pub async fn debug(&self, target: DebugTarget, ttl: Duration) -> ConsensusResult<()> {
+        let (tx, rx) = oneshot::channel();
+        self.send_control(ControlMsg::SetDebugTarget(target, ttl, tx))?;
+        rx.await
+            .map_err(|_| ConsensusError::ChannelErr("[OverlordHandler]: debug dropped".to_string()))
+    }
+
+    /// Clear all the debug targets. Return `Err()` when the overlord instance is not running.
+    pub async fn clear_debug(&self) -> ConsensusResult<()> {
+        let (tx, rx) = oneshot::channel();
+        self.send_control(ControlMsg::ClearDebugTargets(tx))?;
+        rx.await
+            .map_err(|_| ConsensusError::ChannelErr("[OverlordHandler]: debug dropped".to_string()))
+    }
+
+    /// Update the ratios of the propose, prevote, precommit and brake timeouts to the height
+    /// interval without a committed `Status`. The new configuration is applied from the next round
+    /// boundary, and a later `Status` with a `timer_config` overrides it. Return `Err()` when any
+    /// ratio is zero or the overlord instance is not running.
+    pub async fn update_timer_config(&self, config: DurationConfig) -> ConsensusResult<()> {
+        let (tx, rx) = oneshot::channel();
+        self.send_control(ControlMsg::UpdateTimerConfig(config, tx))?;
+        rx.await.map_err(|_| {
+            ConsensusError::ChannelErr("[OverlordHandler]: timer config dropped".to_string())
+        })?
+    }
+
+    fn send_control(&self, msg: ControlMsg<T>) -> ConsensusResult<()> {
+        self.ctrl_tx.unbounded_send(msg).map_err(|_| {
+            ConsensusError::ChannelErr("[OverlordHandler]: control channel closed".to_string())
+        })
     }
 }
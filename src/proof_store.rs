@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use parking_lot::Mutex;
+
+use crate::aux_store::{AuxStore, HeightRecords};
+use crate::error::ConsensusError;
+use crate::types::{Height, Proof};
+use crate::ConsensusResult;
+
+const PROOFS_PREFIX: &str = "proofs";
+
+/// A store of the commit proofs, see `Overlord::with_proof_store`. The overlord saves the proof of
+/// every height it commits, and `OverlordHandler::get_proof` and `OverlordHandler::latest_proof`
+/// query it, so that the application serves the proofs to the syncing peers without keeping them
+/// in its own database. The store is called by the state loop, so it should return quickly.
+pub trait ProofStore: Debug + Send + Sync {
+    /// Save the proof of a committed height.
+    fn save(&self, proof: Proof) -> Result<(), Box<dyn Error + Send>>;
+
+    /// Get the proof of the height, if it is kept.
+    fn get(&self, height: Height) -> Result<Option<Proof>, Box<dyn Error + Send>>;
+
+    /// Get the proof of the highest kept height.
+    fn latest(&self) -> Result<Option<Proof>, Box<dyn Error + Send>>;
+}
+
+#[derive(Debug)]
+struct Proofs {
+    proofs: BTreeMap<Height, Proof>,
+    records: HeightRecords,
+}
+
+/// A proof store in an auxiliary store. It keeps the latest proof and the given count of the
+/// historical ones before it, and evicts the lowest height first. Over a `MemoryAuxStore` nothing
+/// survives a restart, and over a `FileAuxStore` the proofs are restored.
+#[derive(Debug)]
+pub struct AuxProofStore {
+    history: usize,
+    inner: Mutex<Proofs>,
+}
+
+impl AuxProofStore {
+    /// Create a store with the proofs restored from the auxiliary store, which keeps the given
+    /// count of the historical proofs besides the latest one.
+    pub fn new(store: Arc<dyn AuxStore>, history: usize) -> ConsensusResult<Self> {
+        let (records, loaded) = HeightRecords::open(store, PROOFS_PREFIX)?;
+        let mut proofs = BTreeMap::new();
+        for (height, raw) in loaded.into_iter() {
+            let proof = bcs::from_bytes(&raw).map_err(|e| ConsensusError::StorageCodecErr {
+                context: "decode proof",
+                source: e,
+            })?;
+            proofs.insert(height, proof);
+        }
+
+        let mut inner = Proofs { proofs, records };
+        let evicted = evict(&mut inner.proofs, history);
+        if !evicted.is_empty() {
+            inner.records.update(Vec::new(), evicted)?;
+        }
+        Ok(AuxProofStore {
+            history,
+            inner: Mutex::new(inner),
+        })
+    }
+}
+
+impl ProofStore for AuxProofStore {
+    fn save(&self, proof: Proof) -> Result<(), Box<dyn Error + Send>> {
+        let height = proof.height;
+        let encode = bcs::to_bytes(&proof).map_err(|e| {
+            Box::new(ConsensusError::StorageCodecErr {
+                context: "encode proof",
+                source: e,
+            }) as Box<dyn Error + Send>
+        })?;
+
+        let mut inner = self.inner.lock();
+        inner.proofs.insert(height, proof);
+        let evicted = evict(&mut inner.proofs, self.history);
+        inner
+            .records
+            .update(vec![(height, Bytes::from(encode))], evicted)
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send>)
+    }
+
+    fn get(&self, height: Height) -> Result<Option<Proof>, Box<dyn Error + Send>> {
+        Ok(self.inner.lock().proofs.get(&height).cloned())
+    }
+
+    fn latest(&self) -> Result<Option<Proof>, Box<dyn Error + Send>> {
+        Ok(self
+            .inner
+            .lock()
+            .proofs
+            .last_key_value()
+            .map(|(_, proof)| proof.clone()))
+    }
+}
+
+fn evict(proofs: &mut BTreeMap<Height, Proof>, history: usize) -> Vec<Height> {
+    let mut evicted = Vec::new();
+    while proofs.len() > history.saturating_add(1) {
+        if let Some((height, _)) = proofs.pop_first() {
+            evicted.push(height);
+        }
+    }
+    evicted
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+
+    use super::{AuxProofStore, ProofStore};
+    use crate::aux_store::{AuxStore, MemoryAuxStore};
+    use crate::types::{AggregatedSignature, Hash, Height, Proof, QcKind, Round, Signature};
+
+    fn gen_proof(height: u64) -> Proof {
+        Proof {
+            height: Height(height),
+            round: Round(0),
+            block_hash: Hash::from(vec![height as u8]),
+            signature: AggregatedSignature {
+                signature: Signature::default(),
+                address_bitmap: Bytes::from(vec![0b1110_0000]),
+                kind: QcKind::Aggregated,
+            },
+            extensions: None,
+            vrf: None,
+        }
+    }
+
+    #[test]
+    fn test_aux_proof_store() {
+        let aux_store: Arc<dyn AuxStore> = Arc::new(MemoryAuxStore::new());
+        let store = AuxProofStore::new(Arc::clone(&aux_store), 2).unwrap();
+        assert!(store.latest().unwrap().is_none());
+        for height in 1..5 {
+            store.save(gen_proof(height)).unwrap();
+        }
+        assert_eq!(store.latest().unwrap(), Some(gen_proof(4)));
+        assert_eq!(store.get(Height(2)).unwrap(), Some(gen_proof(2)));
+        assert!(store.get(Height(1)).unwrap().is_none());
+
+        // The proofs are restored, and trimmed to a shorter history.
+        let store = AuxProofStore::new(aux_store, 0).unwrap();
+        assert!(store.get(Height(3)).unwrap().is_none());
+        assert_eq!(store.latest().unwrap(), Some(gen_proof(4)));
+    }
+}
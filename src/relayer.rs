@@ -0,0 +1,576 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use creep::Context;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures::{select, FutureExt, StreamExt};
+use hummer::coding::hex_encode;
+use parking_lot::RwLock;
+use tokio::time::MissedTickBehavior;
+
+use crate::error::ConsensusError;
+use crate::types::{
+    Address, Hash, Height, OverlordMsg, Round, Signature, SignedVote, Status, VoteType,
+};
+use crate::utils::auth_manage::AuthorityManage;
+use crate::{Codec, ConsensusResult, Network};
+
+type Pile<T> = RwLock<Option<T>>;
+
+/// The setting of a vote relayer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RelayerConfig {
+    /// Forward the buffered votes once the buffer reaches this size.
+    pub batch_size: usize,
+    /// Forward the buffered votes at least once per interval, as millisecond.
+    pub batch_interval: u64,
+    /// The count of the heights above the current one of which the votes are relayed, as
+    /// `OverlordConfig::future_height_gap`. The votes of the higher heights are dropped.
+    pub future_height_gap: u64,
+    /// The count of the rounds of which the votes of a height above the current one are relayed,
+    /// as `OverlordConfig::future_round_gap`. The votes of the higher rounds are dropped.
+    pub future_round_gap: u64,
+    /// The most votes buffered of a height and a round, the others are dropped. A replica sends
+    /// two votes a round, the rest are duplicate or forged ones.
+    pub max_round_votes: usize,
+    /// The most votes buffered of a height over all its rounds, the others are dropped. The rounds
+    /// of the current height are not bounded by the gap, so this bounds the buffer of a height of
+    /// which the votes of many rounds are forged.
+    pub max_height_votes: usize,
+    /// Whether the proposers are elected by VRF, as `OverlordConfig::vrf_proposer`, so that the
+    /// votes are forwarded to the leaders the engine elects.
+    pub vrf_proposer: bool,
+}
+
+impl Default for RelayerConfig {
+    fn default() -> Self {
+        RelayerConfig {
+            batch_size: 64,
+            batch_interval: 50,
+            future_height_gap: 5,
+            future_round_gap: 10,
+            max_round_votes: 1024,
+            max_height_votes: 16384,
+            vrf_proposer: false,
+        }
+    }
+}
+
+impl RelayerConfig {
+    /// Create a relayer configuration with the default future height gap.
+    pub fn new(batch_size: usize, batch_interval: u64) -> Self {
+        RelayerConfig {
+            batch_size,
+            batch_interval,
+            ..RelayerConfig::default()
+        }
+    }
+}
+
+/// A vote relayer for sentry topologies. Replicas transmit their signed votes to a relayer which
+/// is not the leader, the relayer collects the votes, drops the duplicate ones and forwards them
+/// in batches toward the leader of the vote round by `transmit_to_relayer`. The relayer never
+/// aggregates, signs or votes, so an operator can run it apart from the consensus node.
+pub struct Relayer<T: Codec, F: Network<T>> {
+    sender: UnboundedSender<(Context, OverlordMsg<T>)>,
+    receiver: Pile<UnboundedReceiver<(Context, OverlordMsg<T>)>>,
+    network: Arc<F>,
+    config: RelayerConfig,
+}
+
+impl<T, F> Relayer<T, F>
+where
+    T: Codec + 'static,
+    F: Network<T> + 'static,
+{
+    /// Create a new vote relayer which forwards the votes through the given network, which may be
+    /// a `Consensus`.
+    pub fn new(network: Arc<F>, config: RelayerConfig) -> Self {
+        let (tx, rx) = unbounded();
+        Relayer {
+            sender: tx,
+            receiver: RwLock::new(Some(rx)),
+            network,
+            config,
+        }
+    }
+
+    /// Get the relayer handler to feed messages to the relayer.
+    pub fn get_handler(&self) -> RelayerHandler<T> {
+        RelayerHandler(self.sender.clone())
+    }
+
+    /// Run the relayer from the status of the initial height, which carries the VRF seed of the
+    /// height in the VRF proposer mode. The relayer follows the heights, authority lists and VRF
+    /// seeds from the `RichStatus` messages and stops on `Stop`.
+    pub async fn run(&self, init_status: Status) -> ConsensusResult<()> {
+        let mut rx = self
+            .receiver
+            .write()
+            .take()
+            .ok_or_else(|| ConsensusError::Other("Relayer is running".to_string()))?;
+        let mut leaders = Leaders::new(self.config.vrf_proposer, &init_status)?;
+        let mut buffer = VoteBuffer::new(self.config.max_round_votes, self.config.max_height_votes);
+        let mut ticker =
+            tokio::time::interval(Duration::from_millis(self.config.batch_interval.max(1)));
+        // The ticks missed under load do not burst, which would forward the buffer vote by vote.
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        log::info!("Overlord: relayer start running");
+
+        loop {
+            select! {
+                raw = rx.next() => {
+                    let (_ctx, msg) = match raw {
+                        Some(raw) => raw,
+                        None => break,
+                    };
+
+                    let height = leaders.height;
+                    let max_height = height.saturating_add(self.config.future_height_gap);
+                    let max_round = self.config.future_round_gap;
+                    // The voters of a height of which the authority list is unknown yet are
+                    // checked by the leader.
+                    let is_relayed = |sv: &SignedVote| {
+                        (height..=max_height).contains(&sv.get_height())
+                            && (sv.get_height() == height || sv.get_round().0 <= max_round)
+                            && leaders
+                                .authority(sv.get_height())
+                                .is_none_or(|authority| authority.contains(&sv.voter))
+                    };
+                    match msg {
+                        OverlordMsg::SignedVote(sv) => {
+                            if !is_relayed(&sv) {
+                                continue;
+                            }
+                            buffer.insert(sv);
+                        }
+                        OverlordMsg::VoteBatch(votes) => {
+                            for sv in votes.into_iter() {
+                                if is_relayed(&sv) {
+                                    buffer.insert(sv);
+                                }
+                            }
+                        }
+                        OverlordMsg::RichStatus(status) => {
+                            if status.height <= leaders.height {
+                                continue;
+                            }
+                            match Leaders::new(self.config.vrf_proposer, &status) {
+                                Ok(new_leaders) => leaders = new_leaders,
+                                Err(e) => {
+                                    log::error!("Overlord: relayer update authority error {:?}", e);
+                                    continue;
+                                }
+                            }
+                            buffer.flush(leaders.height);
+                        }
+                        OverlordMsg::Stop => break,
+                        _ => (),
+                    }
+
+                    if buffer.len() < self.config.batch_size {
+                        continue;
+                    }
+                }
+
+                _ = Box::pin(ticker.tick()).fuse() => (),
+            }
+
+            let held = self.forward(buffer.take(), &leaders).await;
+            buffer.hold(held);
+        }
+
+        // The votes buffered before the stop are still forwarded.
+        self.forward(buffer.take(), &leaders).await;
+        log::info!("Overlord: relayer stopped");
+        Ok(())
+    }
+
+    /// Forward the votes to their leaders. The votes toward the same leader are forwarded in vote
+    /// batches of at most the batch size. Return the votes of which the leaders are unknown yet,
+    /// which are held until the status of their height.
+    async fn forward(&self, votes: Vec<SignedVote>, leaders: &Leaders) -> Vec<SignedVote> {
+        let mut batches: BTreeMap<Address, Vec<SignedVote>> = BTreeMap::new();
+        let mut held = Vec::new();
+        for vote in votes.into_iter() {
+            match leaders.get(vote.get_height(), vote.get_round()) {
+                Some(Ok(leader)) => batches.entry(leader).or_default().push(vote),
+                Some(Err(e)) => log::error!("Overlord: relayer get leader failed {:?}", e),
+                None => held.push(vote),
+            }
+        }
+
+        let batch_size = self.config.batch_size.max(1);
+        for (leader, votes) in batches.into_iter() {
+            log::debug!(
+                "Overlord: relayer forward {} signed votes to {:?}",
+                votes.len(),
+                hex_encode(leader.clone())
+            );
+
+            for batch in votes.chunks(batch_size) {
+                let msg = if batch.len() == 1 {
+                    OverlordMsg::SignedVote(batch[0].clone())
+                } else {
+                    OverlordMsg::VoteBatch(batch.to_vec())
+                };
+                let _ = self
+                    .network
+                    .transmit(Context::new(), leader.clone(), msg)
+                    .await
+                    .map_err(|err| log::error!("Overlord: relayer forward vote failed {:?}", err));
+            }
+        }
+        held
+    }
+}
+
+/// The proposer election of the relayer, which elects the leaders as the engine does for the
+/// heights of which the relayer knows the authority list and the VRF seed.
+struct Leaders {
+    vrf_proposer: bool,
+    height: Height,
+    authority: AuthorityManage,
+    vrf_seed: Option<Hash>,
+    next_authority: Option<AuthorityManage>,
+}
+
+impl Leaders {
+    fn new(vrf_proposer: bool, status: &Status) -> ConsensusResult<Self> {
+        let mut authority = AuthorityManage::new();
+        authority.update(&mut status.authority_list.clone())?;
+        let next_authority = match status.next_authority_list.as_ref() {
+            Some(list) => {
+                let mut next = AuthorityManage::new();
+                next.update(&mut list.clone())?;
+                Some(next)
+            }
+            None => None,
+        };
+        if vrf_proposer && status.vrf_seed.is_none() {
+            log::warn!(
+                "Overlord: relayer status of height {} has no VRF seed, hold its votes",
+                status.height
+            );
+        }
+        Ok(Leaders {
+            vrf_proposer,
+            height: status.height,
+            authority,
+            vrf_seed: status.vrf_seed.clone(),
+            next_authority,
+        })
+    }
+
+    /// Get the authority list of the height, or `None` if it is unknown yet.
+    fn authority(&self, height: Height) -> Option<&AuthorityManage> {
+        if height == self.height {
+            return Some(&self.authority);
+        }
+        if height == self.height.saturating_add(1) {
+            return self.next_authority.as_ref();
+        }
+        None
+    }
+
+    /// Get the leader of the height and the round, or `None` if the authority list or the VRF
+    /// seed of the height is unknown yet. The VRF seed of the next height is only known from its
+    /// status.
+    fn get(&self, height: Height, round: Round) -> Option<ConsensusResult<Address>> {
+        let authority = self.authority(height)?;
+        if !self.vrf_proposer {
+            return Some(authority.get_proposer(height, round));
+        }
+        match self.vrf_seed.as_ref() {
+            Some(seed) if height == self.height => {
+                Some(authority.get_vrf_proposer(seed, height, round))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A relayer handler to send messages to a relayer instance.
+#[derive(Clone, Debug)]
+pub struct RelayerHandler<T: Codec>(UnboundedSender<(Context, OverlordMsg<T>)>);
+
+impl<T: Codec> RelayerHandler<T> {
+    /// Send overlord message to the relayer. Return `Err()` when the message channel is closed.
+    pub fn send_msg(&self, ctx: Context, msg: OverlordMsg<T>) -> ConsensusResult<()> {
+        self.0
+            .unbounded_send((ctx, msg))
+            .map_err(|e| ConsensusError::ChannelErr(format!("[RelayerHandler]: {:?}", e)))
+    }
+}
+
+type VoteId = (Height, Round, VoteType, Address, Signature);
+
+/// A buffer of the votes waiting to be forwarded, which drops the votes that have been seen. The
+/// relayer does not verify the signatures, which the leader does, so a vote is keyed on its
+/// signature as well, and a forged vote which arrives first does not censor the genuine one. The
+/// votes of a height and a round, and of a height over all its rounds, are capped, so that the
+/// forged votes do not grow the buffer without bound.
+struct VoteBuffer {
+    seen: BTreeMap<(Height, Round), HashSet<VoteId>>,
+    height_votes: BTreeMap<Height, usize>,
+    pending: Vec<SignedVote>,
+    held: Vec<SignedVote>,
+    max_round_votes: usize,
+    max_height_votes: usize,
+}
+
+impl VoteBuffer {
+    fn new(max_round_votes: usize, max_height_votes: usize) -> Self {
+        VoteBuffer {
+            seen: BTreeMap::new(),
+            height_votes: BTreeMap::new(),
+            pending: Vec::new(),
+            held: Vec::new(),
+            max_round_votes,
+            max_height_votes,
+        }
+    }
+
+    /// Insert a signed vote. Return `false` if the vote is a duplicate one, or if the votes of
+    /// its height and round, or of its height, reach the cap.
+    fn insert(&mut self, vote: SignedVote) -> bool {
+        let height_votes = self.height_votes.entry(vote.get_height()).or_default();
+        if *height_votes >= self.max_height_votes {
+            return false;
+        }
+        let id = (
+            vote.get_height(),
+            vote.get_round(),
+            vote.vote.vote_type.clone(),
+            vote.voter.clone(),
+            vote.signature.clone(),
+        );
+        let seen = self
+            .seen
+            .entry((vote.get_height(), vote.get_round()))
+            .or_default();
+        if seen.len() >= self.max_round_votes || !seen.insert(id) {
+            return false;
+        }
+        *height_votes += 1;
+        self.pending.push(vote);
+        true
+    }
+
+    fn take(&mut self) -> Vec<SignedVote> {
+        std::mem::take(&mut self.pending)
+    }
+
+    /// Hold the votes of which the leaders are unknown yet, until the next flush.
+    fn hold(&mut self, votes: Vec<SignedVote>) {
+        self.held.extend(votes);
+    }
+
+    fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Remove items that height is less than `till`, and pend the held votes again.
+    fn flush(&mut self, till: Height) {
+        self.seen = self.seen.split_off(&(till, Round(0)));
+        self.height_votes = self.height_votes.split_off(&till);
+        self.pending.append(&mut self.held);
+        self.pending.retain(|vote| vote.get_height() >= till);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use creep::Context;
+
+    use super::{Leaders, Relayer, RelayerConfig, VoteBuffer};
+    use crate::test_utils::MockConsensus;
+    use crate::types::{
+        Address, Hash, Height, Node, OverlordMsg, Round, Signature, SignedVote, Status, Vote,
+        VoteType,
+    };
+    use crate::utils::auth_manage::{select_proposer, ProposerConfig};
+
+    fn gen_signed_vote(height: u64, round: u64, vote_type: VoteType, voter: u8) -> SignedVote {
+        SignedVote {
+            signature: Signature::from(vec![voter]),
+            vote: Vote {
+                height: Height(height),
+                round: Round(round),
+                vote_type,
+                block_hash: Hash::from(vec![1u8]),
+            },
+            voter: Address::from(vec![voter]),
+            extension: None,
+        }
+    }
+
+    #[test]
+    fn test_vote_buffer() {
+        let mut buffer = VoteBuffer::new(1024, 1024);
+        assert!(buffer.insert(gen_signed_vote(1, 0, VoteType::Prevote, 1)));
+        assert!(!buffer.insert(gen_signed_vote(1, 0, VoteType::Prevote, 1)));
+        // A vote of another signature is kept, which may be the genuine one of a forged vote.
+        let mut forged = gen_signed_vote(1, 0, VoteType::Prevote, 1);
+        forged.signature = Signature::from(vec![9u8]);
+        assert!(buffer.insert(forged));
+        assert!(buffer.insert(gen_signed_vote(1, 0, VoteType::Precommit, 1)));
+        assert!(buffer.insert(gen_signed_vote(1, 1, VoteType::Prevote, 1)));
+        assert!(buffer.insert(gen_signed_vote(2, 0, VoteType::Prevote, 2)));
+        assert_eq!(buffer.len(), 5);
+
+        assert_eq!(buffer.take().len(), 5);
+        assert_eq!(buffer.len(), 0);
+        assert!(!buffer.insert(gen_signed_vote(1, 0, VoteType::Prevote, 1)));
+
+        buffer.insert(gen_signed_vote(2, 1, VoteType::Prevote, 2));
+        buffer.flush(Height(3));
+        assert_eq!(buffer.len(), 0);
+        assert!(buffer.insert(gen_signed_vote(3, 0, VoteType::Prevote, 1)));
+    }
+
+    #[test]
+    fn test_vote_buffer_cap() {
+        let mut buffer = VoteBuffer::new(2, 1024);
+        assert!(buffer.insert(gen_signed_vote(1, 0, VoteType::Prevote, 1)));
+        assert!(buffer.insert(gen_signed_vote(1, 0, VoteType::Prevote, 2)));
+        assert!(!buffer.insert(gen_signed_vote(1, 0, VoteType::Precommit, 3)));
+        // The cap is of a height and a round.
+        assert!(buffer.insert(gen_signed_vote(1, 1, VoteType::Prevote, 3)));
+        assert!(buffer.insert(gen_signed_vote(2, 0, VoteType::Prevote, 3)));
+
+        // The held votes are pending again on a flush.
+        let votes = buffer.take();
+        buffer.hold(votes);
+        assert_eq!(buffer.len(), 0);
+        buffer.flush(Height(2));
+        assert_eq!(buffer.len(), 1);
+    }
+
+    #[test]
+    fn test_vote_buffer_height_cap() {
+        let mut buffer = VoteBuffer::new(2, 100);
+        // The forged votes of many distinct rounds stop at the cap of the height.
+        let relayed = (0..1000)
+            .filter(|round| buffer.insert(gen_signed_vote(1, *round, VoteType::Prevote, 1)))
+            .count();
+        assert_eq!(relayed, 100);
+        assert_eq!(buffer.seen.len(), 100);
+        assert!(buffer.insert(gen_signed_vote(2, 0, VoteType::Prevote, 1)));
+
+        buffer.flush(Height(2));
+        assert!(buffer.insert(gen_signed_vote(2, 1, VoteType::Prevote, 1)));
+        assert_eq!(buffer.height_votes.len(), 1);
+    }
+
+    #[test]
+    fn test_leaders() {
+        let authority_list = (1..=4u8)
+            .map(|i| Node::new(Address::from(vec![i])))
+            .collect::<Vec<_>>();
+        let seed = Hash::from(vec![7u8; 32]);
+        let status = Status {
+            height: Height(5),
+            authority_list: authority_list.clone(),
+            interval: None,
+            timer_config: None,
+            vrf_seed: Some(seed.clone()),
+            next_authority_list: None,
+            proof: None,
+        };
+
+        for vrf_proposer in [false, true] {
+            let leaders = Leaders::new(vrf_proposer, &status).unwrap();
+            let config = ProposerConfig {
+                vrf_proposer,
+                vrf_seed: Some(seed.clone()),
+            };
+            for round in 0..8 {
+                assert_eq!(
+                    leaders.get(Height(5), Round(round)).unwrap().unwrap(),
+                    select_proposer(&authority_list, Height(5), Round(round), &config).unwrap()
+                );
+            }
+            // The authority list of the next height is not announced.
+            assert!(leaders.get(Height(6), Round(0)).is_none());
+        }
+
+        // The VRF seed of the next height is unknown, though its authority list is announced.
+        let status = Status {
+            next_authority_list: Some(authority_list[..3].to_vec()),
+            ..status
+        };
+        let leaders = Leaders::new(false, &status).unwrap();
+        assert_eq!(
+            leaders.get(Height(6), Round(1)).unwrap().unwrap(),
+            select_proposer(
+                &authority_list[..3],
+                Height(6),
+                Round(1),
+                &ProposerConfig::default()
+            )
+            .unwrap()
+        );
+        assert!(Leaders::new(true, &status)
+            .unwrap()
+            .get(Height(6), Round(1))
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_relayer_round_gap() {
+        let authority_list = (1..=4u8)
+            .map(|i| Node::new(Address::from(vec![i])))
+            .collect::<Vec<_>>();
+        let network = Arc::new(MockConsensus::<Vec<u8>>::new(authority_list.clone()));
+        let config = RelayerConfig {
+            future_round_gap: 10,
+            ..RelayerConfig::default()
+        };
+        let relayer = Relayer::new(Arc::clone(&network), config);
+        let handler = relayer.get_handler();
+
+        // The rounds of the current height are relayed, and the ones of the next height up to
+        // the gap.
+        let votes = (1..=2)
+            .flat_map(|height| {
+                (0..50).map(move |round| gen_signed_vote(height, round, VoteType::Prevote, 1))
+            })
+            .collect::<Vec<_>>();
+        handler
+            .send_msg(Context::new(), OverlordMsg::VoteBatch(votes))
+            .unwrap();
+        handler.send_msg(Context::new(), OverlordMsg::Stop).unwrap();
+        relayer
+            .run(Status {
+                height: Height(1),
+                authority_list: authority_list.clone(),
+                interval: None,
+                timer_config: None,
+                vrf_seed: None,
+                next_authority_list: Some(authority_list),
+                proof: None,
+            })
+            .await
+            .unwrap();
+
+        let relayed = network
+            .transmits()
+            .into_iter()
+            .flat_map(|(_, msg)| match msg {
+                OverlordMsg::SignedVote(sv) => vec![sv],
+                OverlordMsg::VoteBatch(votes) => votes,
+                _ => Vec::new(),
+            })
+            .map(|sv| (sv.get_height(), sv.get_round()))
+            .collect::<Vec<_>>();
+        assert_eq!(relayed.len(), 50 + 11);
+        assert!(relayed
+            .iter()
+            .all(|(height, round)| *height == Height(1) || round.0 <= 10));
+    }
+}
@@ -5,9 +5,10 @@ use hummer::coding::{hex_decode, hex_encode};
 use serde::{de, Deserializer, Serializer};
 
 /// serialize Bytes with hex
-pub fn serialize<S>(val: &Bytes, s: S) -> Result<S::Ok, S::Error>
+pub fn serialize<S, T>(val: &T, s: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
+    T: AsRef<[u8]>,
 {
     s.serialize_str(&hex_encode(val))
 }
@@ -15,11 +16,12 @@ where
 struct StringVisit;
 
 /// deserialize Bytes with hex
-pub fn deserialize<'de, D>(deserializer: D) -> Result<Bytes, D::Error>
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
 where
     D: Deserializer<'de>,
+    T: From<Bytes>,
 {
-    deserializer.deserialize_str(StringVisit)
+    deserializer.deserialize_str(StringVisit).map(T::from)
 }
 
 impl<'de> de::Visitor<'de> for StringVisit {
@@ -15,14 +15,15 @@ struct VecT {
     inner: Vec<TWrapper>,
 }
 
-pub fn serialize<S>(val: &[Bytes], s: S) -> Result<S::Ok, S::Error>
+pub fn serialize<S, T>(val: &[T], s: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
+    T: AsRef<[u8]>,
 {
     let inner = val
         .iter()
         .map(|t| TWrapper {
-            inner: t.to_owned(),
+            inner: Bytes::copy_from_slice(t.as_ref()),
         })
         .collect::<Vec<TWrapper>>();
 
@@ -33,9 +34,10 @@ where
     state.end()
 }
 
-pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Bytes>, D::Error>
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
 where
     D: Deserializer<'de>,
+    T: From<Bytes>,
 {
     #[derive(Deserialize)]
     #[serde(field_identifier, rename_all = "lowercase")]
@@ -89,6 +91,6 @@ where
     let vec_t = deserializer.deserialize_struct("VecT", FIELDS, VecTVisitor)?;
 
     Ok(Vec::from_iter(
-        vec_t.inner.into_iter().map(|wrap_t| wrap_t.inner),
+        vec_t.inner.into_iter().map(|wrap_t| T::from(wrap_t.inner)),
     ))
 }
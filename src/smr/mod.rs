@@ -4,16 +4,19 @@ pub mod smr_types;
 mod state_machine;
 
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
 use futures::stream::{FusedStream, Stream, StreamExt};
 use log::error;
 
+use crate::crash::CrashRecorder;
 use crate::smr::smr_types::{SMREvent, SMRStatus, SMRTrigger, TriggerSource, TriggerType};
 use crate::smr::state_machine::StateMachine;
-use crate::types::Hash;
-use crate::{error::ConsensusError, ConsensusResult, INIT_ROUND};
+use crate::trace::TraceRecorder;
+use crate::types::{Hash, Height, Round, VoteType};
+use crate::{error::ConsensusError, ConsensusResult, DurationConfig, Reporter, INIT_ROUND};
 
 ///
 #[allow(clippy::upper_case_acronyms)]
@@ -21,6 +24,7 @@ use crate::{error::ConsensusError, ConsensusResult, INIT_ROUND};
 pub struct SMR {
     smr_handler: Option<SMRHandler>,
     state_machine: StateMachine,
+    recorder: Option<Arc<CrashRecorder>>,
 }
 
 impl SMR {
@@ -32,6 +36,7 @@ impl SMR {
         let provider = SMR {
             smr_handler: Some(smr),
             state_machine,
+            recorder: None,
         };
 
         (provider, evt_state, evt_timer)
@@ -43,9 +48,27 @@ impl SMR {
         self.smr_handler.take().unwrap()
     }
 
+    /// Record the handled triggers for the crash snapshot, and save the snapshot if the SMR task
+    /// panics.
+    pub(crate) fn set_recorder(&mut self, recorder: Arc<CrashRecorder>) {
+        self.state_machine.set_recorder(Arc::clone(&recorder));
+        self.recorder = Some(recorder);
+    }
+
+    /// Record the handled triggers and the thrown events to the given trace recorder.
+    pub(crate) fn set_trace_recorder(&mut self, recorder: Arc<TraceRecorder>) {
+        self.state_machine.set_trace_recorder(recorder);
+    }
+
+    /// Report the step transitions to the given reporter.
+    pub(crate) fn set_reporter(&mut self, reporter: Arc<dyn Reporter>) {
+        self.state_machine.set_reporter(reporter);
+    }
+
     /// Run SMR module in tokio environment.
     pub fn run(mut self) {
-        tokio::spawn(async move {
+        let recorder = self.recorder.take();
+        let task = async move {
             loop {
                 let res = self.state_machine.next().await;
                 if let Some(Err(err)) = res {
@@ -54,7 +77,27 @@ impl SMR {
                     break;
                 }
             }
-        });
+        };
+
+        match recorder {
+            Some(recorder) => tokio::spawn(recorder.scope(task)),
+            None => tokio::spawn(task),
+        };
+    }
+}
+
+/// Drive a fresh state machine by the triggers in order until a stop trigger, recording to the
+/// given trace recorder.
+pub(crate) fn replay(triggers: Vec<SMRTrigger>, recorder: Arc<TraceRecorder>) {
+    let (_tx, rx) = unbounded();
+    let (mut state_machine, _state_event, _timer_event) = StateMachine::new(rx);
+    state_machine.set_trace_recorder(recorder);
+    for trigger in triggers.into_iter() {
+        match state_machine.handle_trigger(trigger) {
+            Some(Err(err)) => log::debug!("Overlord: SMR replay error {:?}", err),
+            Some(Ok(())) => (),
+            None => break,
+        }
     }
 }
 
@@ -94,6 +137,64 @@ impl SMRHandler {
             })
             .map_err(|_| ConsensusError::TriggerSMRErr(trigger.to_string()))
     }
+
+    /// Trigger SMR to apply the timer configuration from the next round.
+    pub fn update_timer_config(
+        &mut self,
+        height: Height,
+        round: Round,
+        config: DurationConfig,
+    ) -> ConsensusResult<()> {
+        let trigger = TriggerType::TimerConfig(config);
+        self.tx
+            .unbounded_send(SMRTrigger {
+                trigger_type: trigger.clone(),
+                source: TriggerSource::State,
+                hash: Hash::new(),
+                lock_round: None,
+                round,
+                height,
+                wal_info: None,
+            })
+            .map_err(|_| ConsensusError::TriggerSMRErr(trigger.to_string()))
+    }
+
+    /// Trigger SMR to end the step of the vote type, since the whole vote weight has voted.
+    pub fn full_vote(
+        &mut self,
+        height: Height,
+        round: Round,
+        vote_type: VoteType,
+    ) -> ConsensusResult<()> {
+        let trigger = TriggerType::FullVote(vote_type);
+        self.tx
+            .unbounded_send(SMRTrigger {
+                trigger_type: trigger.clone(),
+                source: TriggerSource::State,
+                hash: Hash::new(),
+                lock_round: None,
+                round,
+                height,
+                wal_info: None,
+            })
+            .map_err(|_| ConsensusError::TriggerSMRErr(trigger.to_string()))
+    }
+
+    /// Trigger SMR to end the round as on the precommit timeout, whatever the step is.
+    pub fn force_brake(&mut self, height: Height, round: Round) -> ConsensusResult<()> {
+        let trigger = TriggerType::ForceBrake;
+        self.tx
+            .unbounded_send(SMRTrigger {
+                trigger_type: trigger.clone(),
+                source: TriggerSource::State,
+                hash: Hash::new(),
+                lock_round: None,
+                round,
+                height,
+                wal_info: None,
+            })
+            .map_err(|_| ConsensusError::TriggerSMRErr(trigger.to_string()))
+    }
 }
 
 ///
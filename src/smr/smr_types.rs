@@ -1,7 +1,7 @@
 use derive_more::Display;
 use serde::{Deserialize, Serialize};
 
-use crate::types::{Hash, ViewChangeReason};
+use crate::types::{Hash, Height, Round, ViewChangeReason, VoteType};
 use crate::wal::SMRBase;
 use crate::DurationConfig;
 
@@ -85,18 +85,18 @@ impl From<u8> for Step {
 }
 
 ///
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
 pub enum FromWhere {
     ///
-    PrevoteQC(u64),
+    PrevoteQC(Round),
     ///
-    PrecommitQC(u64),
+    PrecommitQC(Round),
     ///
-    ChokeQC(u64),
+    ChokeQC(Round),
 }
 
 impl FromWhere {
-    pub fn get_round(&self) -> u64 {
+    pub fn get_round(&self) -> Round {
         match self {
             FromWhere::PrevoteQC(round) => *round,
             FromWhere::PrecommitQC(round) => *round,
@@ -104,7 +104,7 @@ impl FromWhere {
         }
     }
 
-    pub fn to_reason(&self, old_round: u64) -> ViewChangeReason {
+    pub fn to_reason(&self, old_round: Round) -> ViewChangeReason {
         match self {
             FromWhere::PrevoteQC(round) => {
                 ViewChangeReason::UpdateFromHigherPrevoteQC(old_round, *round)
@@ -122,7 +122,7 @@ impl FromWhere {
 /// SMR event that state and timer monitor this.
 /// **NOTICE**: The `height` field is just for the timer. Timer will take this to signal the timer
 /// height. State will ignore this field on handling event.
-#[derive(Clone, Debug, Display, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Display, PartialEq, Eq)]
 pub enum SMREvent {
     /// New round event,
     /// for state: update round,
@@ -134,9 +134,9 @@ pub enum SMREvent {
         lock_proposal
     )]
     NewRoundInfo {
-        height: u64,
-        round: u64,
-        lock_round: Option<u64>,
+        height: Height,
+        round: Round,
+        lock_round: Option<Round>,
         lock_proposal: Option<Hash>,
         from_where: FromWhere,
         new_interval: Option<u64>,
@@ -154,10 +154,10 @@ pub enum SMREvent {
         lock_round
     )]
     PrevoteVote {
-        height: u64,
-        round: u64,
+        height: Height,
+        round: Round,
         block_hash: Hash,
-        lock_round: Option<u64>,
+        lock_round: Option<Round>,
     },
 
     /// Precommit event,
@@ -171,10 +171,10 @@ pub enum SMREvent {
         lock_round
     )]
     PrecommitVote {
-        height: u64,
-        round: u64,
+        height: Height,
+        round: Round,
         block_hash: Hash,
-        lock_round: Option<u64>,
+        lock_round: Option<Round>,
     },
     /// Commit event,
     /// for state: do commit,
@@ -192,11 +192,33 @@ pub enum SMREvent {
         lock_round
     )]
     Brake {
-        height: u64,
-        round: u64,
-        lock_round: Option<u64>,
+        height: Height,
+        round: Round,
+        lock_round: Option<Round>,
+    },
+
+    /// Pull proposal event,
+    /// for state: pull the proposal of the round if it is missing,
+    /// for timer: do nothing.
+    #[display("Pull proposal event height {}, round {}", height, round)]
+    PullProposal { height: Height, round: Round },
+
+    /// Pull votes event,
+    /// for state: pull the missing votes of the round if self is the leader,
+    /// for timer: do nothing.
+    #[display("Pull {:?} votes event height {}, round {}", vote_type, height, round)]
+    PullVotes {
+        height: Height,
+        round: Round,
+        vote_type: VoteType,
     },
 
+    /// Rebroadcast proposal event,
+    /// for state: rebroadcast the signed proposal of the round if self is the leader,
+    /// for timer: do nothing.
+    #[display("Rebroadcast proposal event height {}, round {}", height, round)]
+    RebroadcastProposal { height: Height, round: Round },
+
     /// Stop event,
     /// for state: stop process,
     /// for timer: stop process.
@@ -205,7 +227,7 @@ pub enum SMREvent {
 }
 
 /// SMR trigger types.
-#[derive(Clone, Debug, Display, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Display, PartialEq, Eq)]
 pub enum TriggerType {
     /// Proposal trigger.
     #[display("Proposal")]
@@ -228,6 +250,25 @@ pub enum TriggerType {
     /// Continue new round trigger.
     #[display("Continue Round")]
     ContinueRound,
+    /// Timer configuration update, which is applied at the next round boundary.
+    #[display("Timer Config")]
+    TimerConfig(DurationConfig),
+    /// The whole vote weight has voted in the step of the vote type without a QC, so the step
+    /// ends without waiting for the timeout.
+    #[display("Full Vote")]
+    FullVote(VoteType),
+    /// The round is forced to end as on the precommit timeout.
+    #[display("Force Brake")]
+    ForceBrake,
+    /// The propose step is about to time out, so the missing proposal is pulled.
+    #[display("Pull Proposal")]
+    PullProposal,
+    /// The vote step is about to time out, so the leader pulls the missing votes.
+    #[display("Pull Votes")]
+    PullVotes(VoteType),
+    /// The prevote step is about to time out, so the leader rebroadcasts its proposal.
+    #[display("Rebroadcast Proposal")]
+    RebroadcastProposal,
     /// Stop process.
     #[display("Stop Process")]
     Stop,
@@ -281,7 +322,7 @@ impl From<u8> for TriggerType {
 ///     * `round`: This must be `None`.
 /// For each sources, while filling the `SMRTrigger`, the `height` field take the current height
 /// directly.
-#[derive(Clone, Debug, Display, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Display, PartialEq, Eq)]
 #[display("{:?} trigger from {:?}, height {}", trigger_type, source, height)]
 pub struct SMRTrigger {
     /// SMR trigger type.
@@ -291,30 +332,30 @@ pub struct SMRTrigger {
     /// SMR trigger hash, the meaning shown above.
     pub hash: Hash,
     /// SMR trigger round, the meaning shown above.
-    pub lock_round: Option<u64>,
+    pub lock_round: Option<Round>,
     ///
-    pub round: u64,
+    pub round: Round,
     /// **NOTICE**: This field is only for timer to signed timer's height. Therefore, the SMR can
     /// filter out the outdated timers.
-    pub height: u64,
+    pub height: Height,
     ///
     pub wal_info: Option<SMRBase>,
 }
 
 /// An inner lock struct.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct Lock {
     /// Lock round.
-    pub round: u64,
+    pub round: Round,
     /// Lock hash.
     pub hash: Hash,
 }
 
 /// SMR new status.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct SMRStatus {
     /// New height.
-    pub height: u64,
+    pub height: Height,
     /// New height interval.
     pub new_interval: Option<u64>,
     /// New timeout configuration.
@@ -323,7 +364,7 @@ pub struct SMRStatus {
 
 #[cfg(test)]
 impl SMRStatus {
-    pub fn new(height: u64) -> Self {
+    pub fn new(height: Height) -> Self {
         SMRStatus {
             height,
             new_interval: None,
@@ -1,4 +1,5 @@
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use derive_more::Display;
@@ -6,23 +7,30 @@ use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
 use futures::stream::Stream;
 use hummer::coding::hex_encode;
 
+use crate::crash::CrashRecorder;
 use crate::smr::smr_types::{
     FromWhere, Lock, SMREvent, SMRStatus, SMRTrigger, Step, TriggerSource, TriggerType,
 };
+use crate::trace::{TraceEntry, TraceRecorder};
+use crate::types::{Hash, Height, Round, VoteType};
 use crate::wal::SMRBase;
-use crate::{error::ConsensusError, smr::Event, types::Hash};
-use crate::{ConsensusResult, INIT_HEIGHT, INIT_ROUND};
+use crate::{error::ConsensusError, smr::Event};
+use crate::{ConsensusResult, DurationConfig, NoopReporter, Reporter, INIT_HEIGHT, INIT_ROUND};
 
 /// A smallest implementation of an atomic overlord state machine. It
 #[derive(Debug, Display)]
 #[rustfmt::skip]
 #[display("State machine height {}, round {}, step {:?}", height, round, step)]
 pub struct StateMachine {
-    height:      u64,
-    round:         u64,
+    height:      Height,
+    round:         Round,
     step:          Step,
     block_hash:    Hash,
     lock:          Option<Lock>,
+    timer_config:  Option<DurationConfig>,
+    recorder:      Option<Arc<CrashRecorder>>,
+    trace:         Option<Arc<TraceRecorder>>,
+    reporter:      Arc<dyn Reporter>,
 
     event:   (UnboundedSender<SMREvent>, UnboundedSender<SMREvent>),
     trigger: UnboundedReceiver<SMRTrigger>,
@@ -42,41 +50,7 @@ impl Stream for StateMachine {
                     ))));
                 }
 
-                let msg = msg.unwrap();
-                let trigger_type = msg.trigger_type.clone();
-                let res = match trigger_type {
-                    TriggerType::NewHeight(status) => {
-                        Some(self.handle_new_height(status, msg.source))
-                    }
-                    TriggerType::Proposal => Some(self.handle_proposal(
-                        msg.hash,
-                        msg.round,
-                        msg.lock_round,
-                        msg.source,
-                        msg.height,
-                    )),
-                    TriggerType::PrevoteQC => {
-                        Some(self.handle_prevote(msg.hash, msg.round, msg.source, msg.height))
-                    }
-                    TriggerType::PrecommitQC => {
-                        Some(self.handle_precommit(msg.hash, msg.round, msg.source, msg.height))
-                    }
-                    TriggerType::BrakeTimeout => {
-                        assert!(msg.source == TriggerSource::Timer);
-                        Some(self.handle_brake_timeout(msg.height, msg.round))
-                    }
-                    TriggerType::ContinueRound => {
-                        assert!(msg.source == TriggerSource::State);
-                        Some(self.handle_continue_round(msg.height, msg.round))
-                    }
-                    TriggerType::WalInfo => Some(self.handle_wal(msg.wal_info.unwrap())),
-                    TriggerType::Stop => {
-                        let _ = self.throw_event(SMREvent::Stop);
-                        None
-                    }
-                };
-
-                Poll::Ready(res)
+                Poll::Ready(self.handle_trigger(msg.unwrap()))
             }
         }
     }
@@ -94,6 +68,10 @@ impl StateMachine {
             step: Step::default(),
             block_hash: Hash::new(),
             lock: None,
+            timer_config: None,
+            recorder: None,
+            trace: None,
+            reporter: Arc::new(NoopReporter),
             trigger: trigger_receiver,
             event: (tx_state, tx_timer),
         };
@@ -101,7 +79,122 @@ impl StateMachine {
         (state_machine, Event::new(rx_state), Event::new(rx_timer))
     }
 
-    fn handle_brake_timeout(&mut self, height: u64, round: u64) -> ConsensusResult<()> {
+    /// Record the handled triggers for the crash snapshot.
+    pub fn set_recorder(&mut self, recorder: Arc<CrashRecorder>) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Record the handled triggers and the thrown events to the given trace recorder.
+    pub fn set_trace_recorder(&mut self, recorder: Arc<TraceRecorder>) {
+        self.trace = Some(recorder);
+    }
+
+    /// Report the step transitions to the given reporter.
+    pub fn set_reporter(&mut self, reporter: Arc<dyn Reporter>) {
+        self.reporter = reporter;
+    }
+
+    /// Handle a trigger, return `None` if the trigger is to stop. The lock and step invariants are
+    /// checked after each transition under the `verify` feature.
+    pub(super) fn handle_trigger(&mut self, msg: SMRTrigger) -> Option<ConsensusResult<()>> {
+        if let Some(trace) = self.trace.as_ref() {
+            trace.record(TraceEntry::Trigger(msg.clone()));
+        }
+        if let Some(recorder) = self.recorder.as_ref() {
+            recorder.record_trigger(format!(
+                "{} trigger from {:?}, height {}, round {}, hash {}",
+                msg.trigger_type,
+                msg.source,
+                msg.height,
+                msg.round,
+                hex_encode(&msg.hash)
+            ));
+        }
+        #[cfg(feature = "verify")]
+        let (last_height, last_round, last_step, last_lock, trigger) = (
+            self.height,
+            self.round,
+            self.step.clone(),
+            self.lock.clone(),
+            msg.clone(),
+        );
+
+        let res = match msg.trigger_type {
+            TriggerType::NewHeight(status) => Some(self.handle_new_height(status, msg.source)),
+            TriggerType::Proposal => Some(self.handle_proposal(
+                msg.hash,
+                msg.round,
+                msg.lock_round,
+                msg.source,
+                msg.height,
+            )),
+            TriggerType::PrevoteQC => {
+                Some(self.handle_prevote(msg.hash, msg.round, msg.source, msg.height))
+            }
+            TriggerType::PrecommitQC => {
+                Some(self.handle_precommit(msg.hash, msg.round, msg.source, msg.height))
+            }
+            TriggerType::BrakeTimeout => {
+                assert!(msg.source == TriggerSource::Timer);
+                Some(self.handle_brake_timeout(msg.height, msg.round))
+            }
+            TriggerType::ContinueRound => {
+                assert!(msg.source == TriggerSource::State);
+                Some(self.handle_continue_round(msg.height, msg.round))
+            }
+            TriggerType::WalInfo => Some(self.handle_wal(msg.wal_info.unwrap())),
+            TriggerType::PullProposal => {
+                assert!(msg.source == TriggerSource::Timer);
+                Some(self.handle_pull_proposal(msg.height, msg.round))
+            }
+            TriggerType::PullVotes(vote_type) => {
+                assert!(msg.source == TriggerSource::Timer);
+                Some(self.handle_pull_votes(msg.height, msg.round, vote_type))
+            }
+            TriggerType::RebroadcastProposal => {
+                assert!(msg.source == TriggerSource::Timer);
+                Some(self.handle_rebroadcast_proposal(msg.height, msg.round))
+            }
+            TriggerType::FullVote(vote_type) => {
+                assert!(msg.source == TriggerSource::State);
+                Some(self.handle_full_vote(vote_type, msg.height, msg.round))
+            }
+            TriggerType::ForceBrake => {
+                assert!(msg.source == TriggerSource::State);
+                Some(self.handle_force_brake(msg.height, msg.round))
+            }
+            TriggerType::TimerConfig(config) => {
+                assert!(msg.source == TriggerSource::State);
+                log::debug!("Overlord: SMR timer config {:?} pending", config);
+                self.timer_config = Some(config);
+                Some(Ok(()))
+            }
+            TriggerType::Stop => {
+                // The state may have dropped its event receiver on shutdown, the timer is stopped
+                // first so that it does not outlive the SMR.
+                let _ = self.throw_timer_event(SMREvent::Stop);
+                let _ = self.event.0.unbounded_send(SMREvent::Stop);
+                None
+            }
+        };
+
+        #[cfg(feature = "verify")]
+        if trigger.trigger_type != TriggerType::WalInfo {
+            let violation = self
+                .lock_violation(last_height, last_lock.as_ref(), &trigger)
+                .or_else(|| self.step_violation(last_height, last_round, &last_step, &trigger));
+            invariant!(
+                violation.as_ref().map_or("SMR", |(name, _)| *name),
+                violation.is_none(),
+                "{}, {}",
+                violation.as_ref().map_or("", |(_, msg)| msg.as_str()),
+                self
+            );
+        }
+        res
+    }
+
+    fn handle_brake_timeout(&mut self, height: Height, round: Round) -> ConsensusResult<()> {
         if height != self.height || round != self.round {
             Ok(())
         } else {
@@ -118,7 +211,99 @@ impl StateMachine {
         }
     }
 
-    fn handle_continue_round(&mut self, height: u64, round: u64) -> ConsensusResult<()> {
+    /// Notify the state to pull the proposal if the propose step is not over yet.
+    fn handle_pull_proposal(&mut self, height: Height, round: Round) -> ConsensusResult<()> {
+        if height != self.height || round != self.round || self.step != Step::Propose {
+            return Ok(());
+        }
+        self.event
+            .0
+            .unbounded_send(SMREvent::PullProposal { height, round })
+            .map_err(|err| ConsensusError::ThrowEventErr(format!("{:?}", err)))
+    }
+
+    /// Notify the state to pull the votes if the vote step of the given type is not over yet.
+    fn handle_pull_votes(
+        &mut self,
+        height: Height,
+        round: Round,
+        vote_type: VoteType,
+    ) -> ConsensusResult<()> {
+        if height != self.height || round != self.round || self.step != vote_type.clone().into() {
+            return Ok(());
+        }
+        self.event
+            .0
+            .unbounded_send(SMREvent::PullVotes {
+                height,
+                round,
+                vote_type,
+            })
+            .map_err(|err| ConsensusError::ThrowEventErr(format!("{:?}", err)))
+    }
+
+    /// Notify the state to rebroadcast the proposal if the prevote step is not over yet.
+    fn handle_rebroadcast_proposal(&mut self, height: Height, round: Round) -> ConsensusResult<()> {
+        if height != self.height || round != self.round || self.step != Step::Prevote {
+            return Ok(());
+        }
+        self.event
+            .0
+            .unbounded_send(SMREvent::RebroadcastProposal { height, round })
+            .map_err(|err| ConsensusError::ThrowEventErr(format!("{:?}", err)))
+    }
+
+    /// End the prevote or precommit step as its timeout does, once the whole vote weight has voted
+    /// in the step without a QC.
+    fn handle_full_vote(
+        &mut self,
+        vote_type: VoteType,
+        height: Height,
+        round: Round,
+    ) -> ConsensusResult<()> {
+        if height != self.height || round != self.round {
+            return Ok(());
+        }
+
+        log::debug!(
+            "Overlord: SMR full {:?} vote height {}, round {}",
+            vote_type,
+            height,
+            round
+        );
+        match vote_type {
+            VoteType::Prevote if self.step == Step::Prevote => {
+                self.handle_prevote(Hash::new(), round, TriggerSource::Timer, height)
+            }
+            VoteType::Precommit if self.step == Step::Precommit => {
+                self.handle_precommit(Hash::new(), round, TriggerSource::Timer, height)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Go to the brake step of the round from any step before it, as the round times out. The
+    /// votes that self has not signed in the round are left out, and the lock is kept.
+    fn handle_force_brake(&mut self, height: Height, round: Round) -> ConsensusResult<()> {
+        if height != self.height || round != self.round || self.step >= Step::Brake {
+            return Ok(());
+        }
+
+        log::info!(
+            "Overlord: SMR force brake from {:?} step, height {}, round {}",
+            self.step,
+            height,
+            round
+        );
+        self.goto_step(Step::Brake);
+        self.throw_event(SMREvent::Brake {
+            height,
+            round,
+            lock_round: self.lock.as_ref().map(|lock| lock.round),
+        })
+    }
+
+    fn handle_continue_round(&mut self, height: Height, round: Round) -> ConsensusResult<()> {
         if height != self.height || round <= self.round {
             return Ok(());
         }
@@ -130,13 +315,14 @@ impl StateMachine {
             .lock
             .clone()
             .map_or_else(|| (None, None), |lock| (Some(lock.round), Some(lock.hash)));
+        let new_config = self.timer_config.take();
         self.throw_event(SMREvent::NewRoundInfo {
             height: self.height,
             round: self.round + 1,
             lock_round,
             lock_proposal,
             new_interval: None,
-            new_config: None,
+            new_config,
             from_where: FromWhere::ChokeQC(round - 1),
         })?;
         self.goto_next_round();
@@ -146,7 +332,7 @@ impl StateMachine {
     fn handle_wal(&mut self, info: SMRBase) -> ConsensusResult<()> {
         self.height = info.height;
         self.round = info.round;
-        self.step = info.step;
+        self.goto_step(info.step);
         if let Some(polc) = &info.polc {
             self.set_proposal(polc.hash.clone());
         }
@@ -172,6 +358,8 @@ impl StateMachine {
             return Err(ConsensusError::Other("Delayed status".to_string()));
         }
 
+        // The timer configuration of the committed status overrides the pending one.
+        let pending_config = self.timer_config.take();
         self.goto_new_height(height);
         self.throw_event(SMREvent::NewRoundInfo {
             height: self.height,
@@ -179,8 +367,8 @@ impl StateMachine {
             lock_round: None,
             lock_proposal: None,
             new_interval: status.new_interval,
-            new_config: status.new_config,
-            from_where: FromWhere::PrecommitQC(u64::MAX),
+            new_config: status.new_config.or(pending_config),
+            from_where: FromWhere::PrecommitQC(Round(u64::MAX)),
         })?;
         Ok(())
     }
@@ -192,10 +380,10 @@ impl StateMachine {
     fn handle_proposal(
         &mut self,
         proposal_hash: Hash,
-        round: u64,
-        lock_round: Option<u64>,
+        round: Round,
+        lock_round: Option<Round>,
         source: TriggerSource,
-        height: u64,
+        height: Height,
     ) -> ConsensusResult<()> {
         if self.height != height || self.round != round {
             return Ok(());
@@ -273,9 +461,9 @@ impl StateMachine {
     fn handle_prevote(
         &mut self,
         prevote_hash: Hash,
-        prevote_round: u64,
+        prevote_round: Round,
         source: TriggerSource,
-        height: u64,
+        height: Height,
     ) -> ConsensusResult<()> {
         if self.height != height {
             return Ok(());
@@ -335,13 +523,14 @@ impl StateMachine {
                 .map_or_else(|| (None, None), |lock| (Some(lock.round), Some(lock.hash)));
 
             self.round = prevote_round;
+            let new_config = self.timer_config.take();
             self.throw_event(SMREvent::NewRoundInfo {
                 height: self.height,
                 round: self.round + 1,
                 lock_round,
                 lock_proposal,
                 new_interval: None,
-                new_config: None,
+                new_config,
                 from_where: FromWhere::PrevoteQC(prevote_round),
             })?;
             self.goto_next_round();
@@ -366,9 +555,9 @@ impl StateMachine {
     fn handle_precommit(
         &mut self,
         precommit_hash: Hash,
-        precommit_round: u64,
+        precommit_round: Round,
         source: TriggerSource,
-        height: u64,
+        height: Height,
     ) -> ConsensusResult<()> {
         if self.height != height {
             return Ok(());
@@ -393,7 +582,8 @@ impl StateMachine {
             .map_or_else(|| (None, None), |lock| (Some(lock.round), Some(lock.hash)));
 
         if source == TriggerSource::Timer {
-            if precommit_round != self.round {
+            // The step may have ended before its timeout on a full vote.
+            if precommit_round != self.round || self.step > Step::Precommit {
                 return Ok(());
             }
 
@@ -415,13 +605,14 @@ impl StateMachine {
             }
 
             self.round = precommit_round;
+            let new_config = self.timer_config.take();
             self.throw_event(SMREvent::NewRoundInfo {
                 height: self.height,
                 round: self.round + 1,
                 lock_round,
                 lock_proposal,
                 new_interval: None,
-                new_config: None,
+                new_config,
                 from_where: FromWhere::PrecommitQC(precommit_round),
             })?;
 
@@ -430,6 +621,13 @@ impl StateMachine {
         }
 
         self.check()?;
+        invariant!(
+            "COMMIT-RULE",
+            source == TriggerSource::State && !precommit_hash.is_empty(),
+            "commit hash {:?} from {:?}",
+            hex_encode(precommit_hash.clone()),
+            source
+        );
         self.throw_event(SMREvent::Commit(precommit_hash))?;
         self.goto_step(Step::Commit);
         Ok(())
@@ -437,6 +635,9 @@ impl StateMachine {
 
     fn throw_event(&mut self, event: SMREvent) -> ConsensusResult<()> {
         log::debug!("Overlord: SMR throw {} event", event);
+        if let Some(trace) = self.trace.as_ref() {
+            trace.record(TraceEntry::Event(event.clone()));
+        }
         self.event.0.unbounded_send(event.clone()).map_err(|err| {
             ConsensusError::ThrowEventErr(format!("event: {}, error: {:?}", event.clone(), err))
         })?;
@@ -447,6 +648,9 @@ impl StateMachine {
     }
 
     fn throw_timer_event(&mut self, event: SMREvent) -> ConsensusResult<()> {
+        if let Some(trace) = self.trace.as_ref() {
+            trace.record(TraceEntry::TimerEvent(event.clone()));
+        }
         self.event.1.unbounded_send(event.clone()).map_err(|err| {
             ConsensusError::ThrowEventErr(format!("event: {}, error: {:?}", event.clone(), err))
         })?;
@@ -454,7 +658,7 @@ impl StateMachine {
     }
 
     /// Goto new height and clear everything.
-    fn goto_new_height(&mut self, height: u64) {
+    fn goto_new_height(&mut self, height: Height) {
         log::debug!("Overlord: SMR goto new height: {}", height);
         self.height = height;
         self.round = INIT_ROUND;
@@ -484,8 +688,8 @@ impl StateMachine {
                 lock_round,
                 lock_proposal,
                 new_interval: None,
-                new_config: None,
-                from_where: FromWhere::PrecommitQC(u64::MAX),
+                new_config: self.timer_config.take(),
+                from_where: FromWhere::PrecommitQC(Round(u64::MAX)),
             },
             Step::Prevote => SMREvent::PrevoteVote {
                 height: self.height,
@@ -513,13 +717,15 @@ impl StateMachine {
     #[inline]
     fn goto_step(&mut self, step: Step) {
         log::debug!("Overlord: SMR goto step {:?}", step);
-        self.step = step;
+        let old_step = std::mem::replace(&mut self.step, step);
+        self.reporter
+            .on_step_change(self.height, self.round, old_step, self.step.clone());
     }
 
     /// Update the PoLC. Firstly set self proposal as the given hash. Secondly update the PoLC. If
     /// the hash is empty, remove it. Otherwise, set lock round and hash as the given round and
     /// hash.
-    fn update_polc(&mut self, hash: Hash, round: u64) {
+    fn update_polc(&mut self, hash: Hash, round: Round) {
         log::debug!("Overlord: SMR update PoLC at round {}", round);
         self.set_proposal(hash.clone());
 
@@ -541,6 +747,103 @@ impl StateMachine {
         self.block_hash = proposal_hash;
     }
 
+    /// Check the lock invariants after a transition of the trigger, see the `invariant` module.
+    /// Return the name and the description of the violated one.
+    #[cfg(any(test, feature = "verify"))]
+    fn lock_violation(
+        &self,
+        last_height: Height,
+        last_lock: Option<&Lock>,
+        trigger: &SMRTrigger,
+    ) -> Option<(&'static str, String)> {
+        let last_lock = last_lock.filter(|_| last_height == self.height);
+        match (last_lock, self.lock.as_ref()) {
+            (Some(last), Some(lock)) if lock.round < last.round => {
+                return Some((
+                    "LOCK-MONOTONIC",
+                    format!("relock at round {} below round {}", lock.round, last.round),
+                ));
+            }
+            (Some(last), None) => {
+                let released = match trigger.trigger_type {
+                    TriggerType::Proposal => trigger
+                        .lock_round
+                        .is_some_and(|lock_round| lock_round > last.round),
+                    TriggerType::PrevoteQC => {
+                        trigger.source == TriggerSource::State
+                            && trigger.hash.is_empty()
+                            && trigger.round >= last.round
+                    }
+                    _ => false,
+                };
+                if !released {
+                    return Some((
+                        "LOCK-MONOTONIC",
+                        format!("release the lock of round {} by {}", last.round, trigger),
+                    ));
+                }
+            }
+            _ => (),
+        }
+
+        if let Some(lock) = self.lock.as_ref() {
+            if lock.round > self.round {
+                return Some((
+                    "LOCK-ROUND",
+                    format!("lock round {} above round {}", lock.round, self.round),
+                ));
+            }
+            if lock.hash != self.block_hash {
+                return Some((
+                    "LOCK-HASH",
+                    format!(
+                        "lock hash {:?} differs from proposal hash {:?}",
+                        hex_encode(lock.hash.clone()),
+                        hex_encode(self.block_hash.clone())
+                    ),
+                ));
+            }
+        }
+        None
+    }
+
+    /// Check the step invariants after a transition of the trigger, see the `invariant` module.
+    /// Return the name and the description of the violated one.
+    #[cfg(any(test, feature = "verify"))]
+    fn step_violation(
+        &self,
+        last_height: Height,
+        last_round: Round,
+        last_step: &Step,
+        trigger: &SMRTrigger,
+    ) -> Option<(&'static str, String)> {
+        let same_round = last_height == self.height && last_round == self.round;
+        if same_round && self.step < *last_step {
+            return Some((
+                "STEP-MONOTONIC",
+                format!(
+                    "step back from {:?} to {:?} by {}",
+                    last_step, self.step, trigger
+                ),
+            ));
+        }
+
+        if self.step == Step::Precommit && !(same_round && *last_step == Step::Precommit) {
+            let by_prevotes = match trigger.trigger_type {
+                TriggerType::PrevoteQC => trigger.round >= last_round,
+                TriggerType::FullVote(VoteType::Prevote) => trigger.round == self.round,
+                _ => false,
+            };
+            if last_height != self.height || trigger.height != self.height || !by_prevotes {
+                return Some((
+                    "PRECOMMIT-QC",
+                    format!("precommit at round {} by {}", self.round, trigger),
+                ));
+            }
+        }
+        None
+    }
+
     /// Do below self checks before each message is processed:
     /// 1. Whenever the lock is some and the proposal hash is empty, is impossible.
     /// 2. As long as there is a lock, the lock and proposal hash must be consistent.
@@ -581,12 +884,363 @@ impl StateMachine {
 #[cfg(test)]
 mod test {
     use bytes::Bytes;
+    use futures::channel::mpsc::unbounded;
+    use parking_lot::Mutex;
     use std::ops::BitXor;
+    use std::sync::Arc;
+
+    use super::StateMachine;
+    use crate::smr::smr_types::{
+        SMREvent, SMRStatus, SMRTrigger, Step, TriggerSource, TriggerType,
+    };
+    use crate::types::{Hash, Height, Round, VoteType};
+    use crate::{DurationConfig, Reporter};
+
+    #[derive(Debug, Default)]
+    struct StepReporter(Mutex<Vec<(Height, Round, Step, Step)>>);
+
+    impl Reporter for StepReporter {
+        fn on_step_change(&self, height: Height, round: Round, old_step: Step, new_step: Step) {
+            self.0.lock().push((height, round, old_step, new_step));
+        }
+    }
+
+    fn trigger(
+        trigger_type: TriggerType,
+        source: TriggerSource,
+        hash: &[u8],
+        round: Round,
+        lock_round: Option<Round>,
+    ) -> SMRTrigger {
+        SMRTrigger {
+            trigger_type,
+            source,
+            hash: Hash::from(hash.to_vec()),
+            lock_round,
+            round,
+            height: Height(1),
+            wal_info: None,
+        }
+    }
+
+    /// The triggers which acquire, keep or release a lock, relative to the current round.
+    fn lock_triggers(round: Round) -> Vec<SMRTrigger> {
+        let mut triggers = Vec::new();
+        for hash in [&b"a"[..], &b"b"[..]] {
+            for lock_round in [None, Some(Round(0)), Some(round), Some(round + 1)] {
+                triggers.push(trigger(
+                    TriggerType::Proposal,
+                    TriggerSource::State,
+                    hash,
+                    round,
+                    lock_round,
+                ));
+            }
+        }
+        for hash in [&b"a"[..], &b"b"[..], &b""[..]] {
+            for qc_round in [round, round + 1] {
+                triggers.push(trigger(
+                    TriggerType::PrevoteQC,
+                    TriggerSource::State,
+                    hash,
+                    qc_round,
+                    None,
+                ));
+            }
+        }
+        triggers.push(trigger(
+            TriggerType::PrecommitQC,
+            TriggerSource::State,
+            b"",
+            round,
+            None,
+        ));
+        for trigger_type in [
+            TriggerType::Proposal,
+            TriggerType::PrevoteQC,
+            TriggerType::PrecommitQC,
+        ] {
+            triggers.push(trigger(
+                trigger_type,
+                TriggerSource::Timer,
+                b"",
+                round,
+                None,
+            ));
+        }
+        for vote_type in [VoteType::Prevote, VoteType::Precommit] {
+            triggers.push(trigger(
+                TriggerType::FullVote(vote_type),
+                TriggerSource::State,
+                b"",
+                round,
+                None,
+            ));
+        }
+        triggers.push(trigger(
+            TriggerType::ContinueRound,
+            TriggerSource::State,
+            b"",
+            round + 1,
+            None,
+        ));
+        triggers
+    }
+
+    /// Drive the state machine with all the sequences of the lock triggers up to the given depth,
+    /// and check the lock and step invariants after each transition.
+    fn exhaust_lock_transitions(smr: &StateMachine, depth: usize, visited: &mut u64) {
+        if depth == 0 {
+            return;
+        }
+        for trigger in lock_triggers(smr.round).into_iter() {
+            let (tx, rx) = unbounded();
+            let (mut next, _state_event, _timer_event) = StateMachine::new(rx);
+            next.height = smr.height;
+            next.round = smr.round;
+            next.step = smr.step.clone();
+            next.block_hash = smr.block_hash.clone();
+            next.lock = smr.lock.clone();
+            drop(tx);
+
+            let _ = next.handle_trigger(trigger.clone());
+            *visited += 1;
+            if let Some((name, msg)) = next
+                .lock_violation(smr.height, smr.lock.as_ref(), &trigger)
+                .or_else(|| next.step_violation(smr.height, smr.round, &smr.step, &trigger))
+            {
+                panic!("invariant {} violated by {}: {}", name, trigger, msg);
+            }
+            exhaust_lock_transitions(&next, depth - 1, visited);
+        }
+    }
+
+    #[test]
+    fn test_lock_transitions() {
+        let (_tx, rx) = unbounded();
+        let (mut smr, _state_event, _timer_event) = StateMachine::new(rx);
+        smr.handle_trigger(SMRTrigger {
+            trigger_type: TriggerType::NewHeight(SMRStatus::new(Height(1))),
+            source: TriggerSource::State,
+            hash: Hash::new(),
+            lock_round: None,
+            round: Round(0),
+            height: Height(1),
+            wal_info: None,
+        })
+        .unwrap()
+        .unwrap();
+
+        let mut visited = 0;
+        exhaust_lock_transitions(&smr, 4, &mut visited);
+        assert!(visited > 100_000);
+    }
+
+    #[test]
+    fn test_step_violation() {
+        let (_tx, rx) = unbounded();
+        let (mut smr, _state_event, _timer_event) = StateMachine::new(rx);
+        smr.height = Height(1);
+        smr.step = Step::Precommit;
+        let name = |smr: &StateMachine, step: Step, trigger: SMRTrigger| {
+            smr.step_violation(Height(1), Round(0), &step, &trigger)
+                .map(|(name, _)| name)
+        };
+
+        let proposal = trigger(
+            TriggerType::Proposal,
+            TriggerSource::State,
+            b"a",
+            Round(0),
+            None,
+        );
+        assert_eq!(
+            name(&smr, Step::Prevote, proposal.clone()),
+            Some("PRECOMMIT-QC")
+        );
+        let prevote_qc = trigger(
+            TriggerType::PrevoteQC,
+            TriggerSource::State,
+            b"a",
+            Round(0),
+            None,
+        );
+        assert_eq!(name(&smr, Step::Prevote, prevote_qc.clone()), None);
+
+        smr.step = Step::Propose;
+        assert_eq!(
+            name(&smr, Step::Prevote, prevote_qc),
+            Some("STEP-MONOTONIC")
+        );
+    }
+
+    #[test]
+    fn test_timer_config() {
+        let (_tx, rx) = unbounded();
+        let (mut smr, _state_event, mut timer_event) = StateMachine::new(rx);
+        let new_config = |event: SMREvent| match event {
+            SMREvent::NewRoundInfo { new_config, .. } => new_config,
+            event => panic!("unexpected event {:?}", event),
+        };
+
+        smr.handle_trigger(trigger(
+            TriggerType::NewHeight(SMRStatus::new(Height(1))),
+            TriggerSource::State,
+            b"",
+            Round(0),
+            None,
+        ))
+        .unwrap()
+        .unwrap();
+        assert!(new_config(timer_event.rx.try_recv().unwrap()).is_none());
+
+        // The pending config is applied at the next round boundary only once.
+        let config = DurationConfig::new(30, 10, 10, 5);
+        smr.handle_trigger(trigger(
+            TriggerType::TimerConfig(config.clone()),
+            TriggerSource::State,
+            b"",
+            Round(0),
+            None,
+        ))
+        .unwrap()
+        .unwrap();
+        assert!(timer_event.rx.try_recv().is_err());
+
+        for (round, expect) in [(1, Some(config)), (2, None)] {
+            smr.handle_trigger(trigger(
+                TriggerType::ContinueRound,
+                TriggerSource::State,
+                b"",
+                Round(round),
+                None,
+            ))
+            .unwrap()
+            .unwrap();
+            assert_eq!(new_config(timer_event.rx.try_recv().unwrap()), expect);
+        }
+    }
+
+    #[test]
+    fn test_full_vote() {
+        let (_tx, rx) = unbounded();
+        let (mut smr, mut state_event, _timer_event) = StateMachine::new(rx);
+        let handle = |smr: &mut StateMachine, trigger_type, source, hash: &[u8]| {
+            smr.handle_trigger(trigger(trigger_type, source, hash, Round(0), None))
+                .unwrap()
+                .unwrap();
+        };
+
+        handle(
+            &mut smr,
+            TriggerType::NewHeight(SMRStatus::new(Height(1))),
+            TriggerSource::State,
+            b"",
+        );
+        handle(&mut smr, TriggerType::Proposal, TriggerSource::State, b"a");
+        while state_event.rx.try_recv().is_ok() {}
+        assert_eq!(smr.step, Step::Prevote);
+
+        // A full vote ends the step as its timeout does, and the later timeout is ignored.
+        handle(
+            &mut smr,
+            TriggerType::FullVote(VoteType::Prevote),
+            TriggerSource::State,
+            b"",
+        );
+        assert_eq!(smr.step, Step::Precommit);
+        match state_event.rx.try_recv().unwrap() {
+            SMREvent::PrecommitVote { block_hash, .. } => assert!(block_hash.is_empty()),
+            event => panic!("unexpected event {:?}", event),
+        }
+        handle(&mut smr, TriggerType::PrevoteQC, TriggerSource::Timer, b"");
+        assert!(state_event.rx.try_recv().is_err());
+
+        handle(
+            &mut smr,
+            TriggerType::FullVote(VoteType::Precommit),
+            TriggerSource::State,
+            b"",
+        );
+        assert_eq!(smr.step, Step::Brake);
+        assert!(matches!(
+            state_event.rx.try_recv().unwrap(),
+            SMREvent::Brake { .. }
+        ));
+        handle(
+            &mut smr,
+            TriggerType::PrecommitQC,
+            TriggerSource::Timer,
+            b"",
+        );
+        assert!(state_event.rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_force_brake() {
+        let (_tx, rx) = unbounded();
+        let (mut smr, mut state_event, _timer_event) = StateMachine::new(rx);
+        let handle = |smr: &mut StateMachine, trigger_type, source, hash: &[u8]| {
+            smr.handle_trigger(trigger(trigger_type, source, hash, Round(0), None))
+                .unwrap()
+                .unwrap();
+        };
+
+        handle(
+            &mut smr,
+            TriggerType::NewHeight(SMRStatus::new(Height(1))),
+            TriggerSource::State,
+            b"",
+        );
+        handle(&mut smr, TriggerType::Proposal, TriggerSource::State, b"a");
+        while state_event.rx.try_recv().is_ok() {}
+
+        // The round is forced to the brake step, and the timeouts of the steps are ignored.
+        handle(&mut smr, TriggerType::ForceBrake, TriggerSource::State, b"");
+        assert_eq!(smr.step, Step::Brake);
+        assert!(matches!(
+            state_event.rx.try_recv().unwrap(),
+            SMREvent::Brake { .. }
+        ));
+        handle(&mut smr, TriggerType::PrevoteQC, TriggerSource::Timer, b"");
+        handle(&mut smr, TriggerType::ForceBrake, TriggerSource::State, b"");
+        assert!(state_event.rx.try_recv().is_err());
+        assert_eq!(smr.step, Step::Brake);
+    }
+
+    #[test]
+    fn test_step_change() {
+        let (_tx, rx) = unbounded();
+        let (mut smr, _state_event, _timer_event) = StateMachine::new(rx);
+        let reporter = Arc::new(StepReporter::default());
+        smr.set_reporter(Arc::clone(&reporter) as Arc<dyn Reporter>);
+        let mut handle = |trigger_type, source, hash: &[u8]| {
+            smr.handle_trigger(trigger(trigger_type, source, hash, Round(0), None))
+                .unwrap()
+                .unwrap();
+        };
+
+        handle(
+            TriggerType::NewHeight(SMRStatus::new(Height(1))),
+            TriggerSource::State,
+            b"",
+        );
+        handle(TriggerType::Proposal, TriggerSource::State, b"a");
+        handle(TriggerType::PrevoteQC, TriggerSource::Timer, b"");
+        assert_eq!(
+            *reporter.0.lock(),
+            vec![
+                (Height(1), Round(0), Step::Commit, Step::Propose),
+                (Height(1), Round(0), Step::Propose, Step::Prevote),
+                (Height(1), Round(0), Step::Prevote, Step::Precommit),
+            ]
+        );
+    }
 
     #[test]
     fn test_xor() {
         let left = Bytes::new();
-        let right: Option<u64> = None;
+        let right: Option<Round> = None;
         assert!(!left.is_empty().bitxor(&right.is_none()));
     }
 }
@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use crate::aux_store::{AuxStore, HeightRecords};
+use crate::error::ConsensusError;
+use crate::types::{Height, HeightArtifacts};
+use crate::{Codec, ConsensusResult};
+
+const ARTIFACTS_PREFIX: &str = "artifacts";
+
+/// A store of the consensus artifacts of the recent committed heights. It keeps no more than
+/// `retention` heights and evicts the lowest height first. The artifacts are persisted in the
+/// auxiliary store, so that the proofs of the recent heights are served after a restart.
+#[derive(Debug)]
+pub(crate) struct ArtifactStore<T: Codec> {
+    retention: usize,
+    heights: BTreeMap<Height, HeightArtifacts<T>>,
+    records: HeightRecords,
+}
+
+impl<T: Codec> ArtifactStore<T> {
+    /// Create a store with the artifacts restored from the auxiliary store.
+    pub(crate) fn new(retention: usize, store: Arc<dyn AuxStore>) -> ConsensusResult<Self> {
+        let (records, loaded) = HeightRecords::open(store, ARTIFACTS_PREFIX)?;
+        let mut heights = BTreeMap::new();
+        for (height, raw) in loaded.into_iter() {
+            let artifacts = bcs::from_bytes(&raw).map_err(|e| ConsensusError::StorageCodecErr {
+                context: "decode artifacts",
+                source: e,
+            })?;
+            heights.insert(height, artifacts);
+        }
+
+        let mut artifact_store = ArtifactStore {
+            retention,
+            heights,
+            records,
+        };
+        let evicted = artifact_store.evict();
+        if !evicted.is_empty() {
+            artifact_store.records.update(Vec::new(), evicted)?;
+        }
+        Ok(artifact_store)
+    }
+
+    pub(crate) fn insert(&mut self, artifacts: HeightArtifacts<T>) {
+        if self.retention == 0 {
+            return;
+        }
+
+        let height = artifacts.height;
+        let encode = bcs::to_bytes(&artifacts);
+        self.heights.insert(height, artifacts);
+        let evicted = self.evict();
+
+        // The artifacts are served from memory, so a failed persistence only loses them on
+        // restart.
+        let res = encode
+            .map_err(|e| ConsensusError::StorageCodecErr {
+                context: "encode artifacts",
+                source: e,
+            })
+            .and_then(|encode| {
+                self.records
+                    .update(vec![(height, Bytes::from(encode))], evicted)
+            });
+        if let Err(e) = res {
+            log::warn!(
+                "Overlord: persist artifacts of height {} error {:?}",
+                height,
+                e
+            );
+        }
+    }
+
+    fn evict(&mut self) -> Vec<Height> {
+        let mut evicted = Vec::new();
+        while self.heights.len() > self.retention {
+            if let Some((height, _)) = self.heights.pop_first() {
+                evicted.push(height);
+            }
+        }
+        evicted
+    }
+
+    pub(crate) fn get(&self, height: Height) -> Option<HeightArtifacts<T>> {
+        self.heights.get(&height).cloned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::ArtifactStore;
+    use crate::aux_store::{AuxStore, MemoryAuxStore};
+    use crate::types::{Height, HeightArtifacts};
+
+    fn gen_artifacts(height: u64) -> HeightArtifacts<Vec<u8>> {
+        HeightArtifacts {
+            height: Height(height),
+            proposals: Vec::new(),
+            qcs: Vec::new(),
+            chokes: Vec::new(),
+            choke_qcs: Vec::new(),
+            proof: None,
+        }
+    }
+
+    #[test]
+    fn test_artifact_store() {
+        let aux_store: Arc<dyn AuxStore> = Arc::new(MemoryAuxStore::new());
+        let mut store = ArtifactStore::new(2, Arc::clone(&aux_store)).unwrap();
+        store.insert(gen_artifacts(1));
+        store.insert(gen_artifacts(2));
+        assert_eq!(store.get(Height(1)), Some(gen_artifacts(1)));
+
+        store.insert(gen_artifacts(3));
+        assert!(store.get(Height(1)).is_none());
+        assert_eq!(store.get(Height(2)), Some(gen_artifacts(2)));
+        assert_eq!(store.get(Height(3)), Some(gen_artifacts(3)));
+
+        // The artifacts are restored, and trimmed to a lower retention.
+        let store = ArtifactStore::<Vec<u8>>::new(1, aux_store).unwrap();
+        assert!(store.get(Height(2)).is_none());
+        assert_eq!(store.get(Height(3)), Some(gen_artifacts(3)));
+
+        let mut store = ArtifactStore::new(0, Arc::new(MemoryAuxStore::new())).unwrap();
+        store.insert(gen_artifacts(1));
+        assert!(store.get(Height(1)).is_none());
+    }
+}
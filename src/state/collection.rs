@@ -1,208 +1,284 @@
 use std::collections::{BTreeMap, HashMap, HashSet};
 
+use bytes::{Bytes, BytesMut};
 use creep::Context;
 use hummer::coding::hex_encode;
 
+use crate::error::{ConsensusError, Missing};
 use crate::types::{
-    Address, AggregatedChoke, AggregatedVote, Hash, SignedChoke, SignedProposal, SignedVote,
-    VoteType,
+    Address, AggregatedChoke, AggregatedVote, BlockPart, CompactProposal, Hash, Height,
+    PartSetHeader, Round, SignedChoke, SignedCompactProposal, SignedProposal, SignedVote, VoteType,
 };
-use crate::{error::ConsensusError, Codec, ConsensusResult};
-
-/// A struct to collect signed proposals in each height. It stores each height and the corresponding
-/// signed proposals in a `BTreeMap`.
+use crate::utils::auth_manage::AuthorityManage;
+use crate::utils::lru::LruCache;
+use crate::utils::merkle::verify_merkle_proof;
+use crate::{Codec, ConsensusResult, Crypto};
+
+/// A collected signed proposal with the compact form that its signature covers, which is `None`
+/// if the proposal is signed in the full form, and the context it is received with.
+pub type ProposalItem<T> = (SignedProposal<T>, Option<CompactProposal>, Context);
+
+/// A struct to collect signed proposals. It stores the signed proposal of each height and round
+/// in a least recently used cache, so that the proposals of spam rounds are evicted. A proposal
+/// restored from a signed compact proposal keeps the compact form, so that it is verified and
+/// served in the form it is signed in.
 #[derive(Clone, Debug)]
-pub struct ProposalCollector<T: Codec>(BTreeMap<u64, ProposalRoundCollector<T>>);
+pub struct ProposalCollector<T: Codec>(LruCache<(Height, Round), ProposalItem<T>>);
 
 impl<T> ProposalCollector<T>
 where
     T: Codec,
 {
-    /// Create a new proposal collector.
-    pub fn new() -> Self {
-        ProposalCollector(BTreeMap::new())
+    /// Create a new proposal collector with the given capacity.
+    pub fn new(capacity: usize) -> Self {
+        ProposalCollector(LruCache::new(capacity))
     }
 
-    /// Insert a signed proposal into the proposal collector. Return `Err()` while the proposal of
-    /// the given height and round exists.
+    /// Insert a signed proposal with the compact form it is signed in, if any, into the proposal
+    /// collector. Return `Err()` while the proposal of the given height and round exists.
     pub fn insert(
         &mut self,
         ctx: Context,
-        height: u64,
-        round: u64,
+        height: Height,
+        round: Round,
         proposal: SignedProposal<T>,
+        compact: Option<CompactProposal>,
     ) -> ConsensusResult<()> {
-        self.0
-            .entry(height)
-            .or_insert_with(ProposalRoundCollector::new)
-            .insert(ctx, round, proposal)
-            .map_err(|_| ConsensusError::MultiProposal(height, round))
+        if let Some((sp, _, _)) = self.0.peek(&(height, round)) {
+            if sp == &proposal {
+                return Ok(());
+            }
+            return Err(ConsensusError::MultiProposal(height, round));
+        }
+
+        if let Some(((height, round), _)) = self.0.insert((height, round), (proposal, compact, ctx))
+        {
+            log::warn!(
+                "Overlord: proposal collector evicts the proposal of height {}, round {}",
+                height,
+                round
+            );
+        }
+        Ok(())
     }
 
     /// Get the signed proposal of the given height and round. Return `Err` when there is no
     /// signed proposal. Return `Err` when can not get it.
-    pub fn get(&self, height: u64, round: u64) -> ConsensusResult<(SignedProposal<T>, Context)> {
-        if let Some(round_collector) = self.0.get(&height) {
-            return Ok(round_collector
-                .get(round)
-                .map_err(|_| {
-                    ConsensusError::StorageErr(format!(
-                        "No proposal height {}, round {}",
-                        height, round
-                    ))
-                })?
-                .to_owned());
-        }
-
-        Err(ConsensusError::StorageErr(format!(
-            "No proposal height {}, round {}",
-            height, round
-        )))
+    pub fn get(&mut self, height: Height, round: Round) -> ConsensusResult<ProposalItem<T>> {
+        self.0
+            .get(&(height, round))
+            .cloned()
+            .ok_or_else(|| ConsensusError::MissingErr {
+                item: Missing::Proposal,
+                height,
+                round,
+            })
     }
 
     /// Get all proposals of the given height.
-    pub fn get_height_proposals(
-        &mut self,
-        height: u64,
-    ) -> Option<Vec<(SignedProposal<T>, Context)>> {
-        self.0.remove(&height).map_or_else(
-            || None,
-            |map| Some(map.0.values().cloned().collect::<Vec<_>>()),
-        )
+    pub fn get_height_proposals(&mut self, height: Height) -> Option<Vec<ProposalItem<T>>> {
+        let proposals = self.0.remove_by(|(h, _)| *h == height);
+        if proposals.is_empty() {
+            return None;
+        }
+        Some(proposals.into_iter().map(|(_, item)| item).collect())
     }
 
-    /// Remove items that height is less than `till`.
-    pub fn flush(&mut self, till: u64) {
-        self.0 = self.0.split_off(&till);
+    /// Get the signed proposals of the given height without removing them, ordered by round.
+    pub fn height_proposals(&self, height: Height) -> Vec<SignedProposal<T>> {
+        let mut proposals = self
+            .0
+            .iter()
+            .filter(|((h, _), _)| *h == height)
+            .map(|((_, round), (sp, _, _))| (*round, sp.clone()))
+            .collect::<Vec<_>>();
+        proposals.sort_by_key(|(round, _)| *round);
+        proposals.into_iter().map(|(_, sp)| sp).collect()
     }
-}
-
-/// A struct to collect signed proposals in each round. It stores each round and the corresponding
-/// signed proposals in a `HashMap`.
-#[derive(Clone, Debug)]
-struct ProposalRoundCollector<T: Codec>(HashMap<u64, (SignedProposal<T>, Context)>);
 
-impl<T> ProposalRoundCollector<T>
-where
-    T: Codec,
-{
-    fn new() -> Self {
-        ProposalRoundCollector(HashMap::new())
+    /// Remove items that height is less than `till`.
+    pub fn flush(&mut self, till: Height) {
+        self.0.remove_by(|(height, _)| *height < till);
     }
 
-    fn insert(
-        &mut self,
-        ctx: Context,
-        round: u64,
-        proposal: SignedProposal<T>,
-    ) -> ConsensusResult<()> {
-        if let Some((sp, _)) = self.0.get(&round) {
-            if sp == &proposal {
-                return Ok(());
-            }
-            return Err(ConsensusError::Other("_".to_string()));
-        }
-        self.0.insert(round, (proposal, ctx));
-        Ok(())
+    /// Get the block hash of the proposal of the given height and round without using it.
+    pub fn block_hash(&self, height: Height, round: Round) -> Option<Hash> {
+        self.0
+            .peek(&(height, round))
+            .map(|(sp, _, _)| sp.proposal.block_hash.clone())
     }
 
-    fn get(&self, round: u64) -> ConsensusResult<&(SignedProposal<T>, Context)> {
-        self.0
-            .get(&round)
-            .ok_or_else(|| ConsensusError::StorageErr("_".to_string()))
+    /// The count of the evicted proposals.
+    pub fn evictions(&self) -> u64 {
+        self.0.evictions()
     }
 }
 
 /// A struct to collect votes in each height. It stores each height and the corresponding votes in a
-/// `BTreeMap`. The votes includes aggregated vote and signed vote.
+/// `BTreeMap`. The votes includes aggregated vote and signed vote. The rounds that have votes are
+/// tracked in a least recently used cache, and the votes of the evicted round are removed, so that
+/// spam rounds do not grow without bound.
 #[derive(Clone, Debug)]
-pub struct VoteCollector(BTreeMap<u64, VoteRoundCollector>);
+pub struct VoteCollector {
+    heights: BTreeMap<Height, VoteRoundCollector>,
+    rounds: LruCache<(Height, Round), ()>,
+}
 
 impl VoteCollector {
-    /// Create a new vote collector.
-    pub fn new() -> Self {
-        VoteCollector(BTreeMap::new())
+    /// Create a new vote collector which keeps the votes of at most `round_capacity` rounds.
+    pub fn new(round_capacity: usize) -> Self {
+        VoteCollector {
+            heights: BTreeMap::new(),
+            rounds: LruCache::new(round_capacity),
+        }
     }
 
-    /// Insert a vote to the collector.
-    pub fn insert_vote(&mut self, ctx: Context, hash: Hash, vote: SignedVote, addr: Address) {
-        self.0
+    /// Insert a vote of the given vote weight to the collector. The weight is added to the running
+    /// total of the hash, unless the voter has voted in the round.
+    pub fn insert_vote(
+        &mut self,
+        ctx: Context,
+        hash: Hash,
+        vote: SignedVote,
+        addr: Address,
+        weight: u64,
+    ) {
+        self.use_round(vote.get_height(), vote.get_round());
+        self.heights
             .entry(vote.get_height())
             .or_insert_with(VoteRoundCollector::new)
-            .insert_vote(ctx, hash, vote, addr);
+            .insert_vote(ctx, hash, vote, addr, weight);
     }
 
-    /// Set a given quorum certificate to the collector.
-    pub fn set_qc(&mut self, qc: AggregatedVote) {
-        self.0
+    /// Recount the running totals of the votes of the given height with the vote weights of
+    /// `weight_of`, which is the authority list of the height. The weights of the votes cached
+    /// before self reaches the height are of the list then, so they are recounted once the list of
+    /// the height is known. The votes of which the voter is not in the list are removed.
+    pub fn reweigh(&mut self, height: Height, weight_of: impl Fn(&Address) -> Option<u64>) {
+        if let Some(vrc) = self.heights.get_mut(&height) {
+            for rc in vrc.general.values_mut() {
+                rc.prevote.reweigh(&weight_of);
+                rc.precommit.reweigh(&weight_of);
+            }
+        }
+    }
+
+    /// Get the first hash of which the running total of the vote weight is above the threshold
+    /// of the authority list, with the given height, round and type.
+    pub fn first_hash_above_threshold(
+        &self,
+        height: Height,
+        round: Round,
+        vote_type: VoteType,
+        authority: &AuthorityManage,
+    ) -> Option<Hash> {
+        self.heights
+            .get(&height)
+            .and_then(|vrc| vrc.general.get(&round))
+            .and_then(|rc| {
+                rc.get_weights(vote_type)
+                    .iter()
+                    .find(|(_, weight)| authority.is_above_weight(**weight))
+                    .map(|(hash, _)| hash.clone())
+            })
+    }
+
+    /// Set a given quorum certificate to the collector. Return the kept QC of another block hash
+    /// with the same height, round and type if any, which is replaced by the given one. Two such
+    /// QCs are the evidence of a fork.
+    pub fn set_qc(&mut self, qc: AggregatedVote) -> Option<AggregatedVote> {
+        self.use_round(qc.get_height(), qc.get_round());
+        self.heights
             .entry(qc.get_height())
             .or_insert_with(VoteRoundCollector::new)
-            .set_qc(qc);
+            .set_qc(qc)
+    }
+
+    /// The count of the evicted rounds.
+    pub fn evictions(&self) -> u64 {
+        self.rounds.evictions()
+    }
+
+    /// Pin the given rounds of the height in place of the ones pinned before, so that the votes
+    /// and QCs of them are never evicted, such as the current round and the lock round.
+    pub fn pin_rounds(&mut self, height: Height, rounds: impl IntoIterator<Item = Round>) {
+        self.rounds
+            .pin(rounds.into_iter().map(|round| (height, round)));
+    }
+
+    fn use_round(&mut self, height: Height, round: Round) {
+        if let Some(((height, round), _)) = self.rounds.insert((height, round), ()) {
+            log::warn!(
+                "Overlord: vote collector evicts the votes of height {}, round {}",
+                height,
+                round
+            );
+            if let Some(vrc) = self.heights.get_mut(&height) {
+                vrc.general.remove(&round);
+            }
+        }
     }
 
     /// Get an index of a `HashMap` that the key is vote hash and the value is address list, with
     /// the given height, round and type.
     pub fn get_vote_map(
         &mut self,
-        height: u64,
-        round: u64,
+        height: Height,
+        round: Round,
         vote_type: VoteType,
     ) -> ConsensusResult<&HashMap<Hash, HashSet<Address>>> {
-        self.0
+        self.heights
             .get_mut(&height)
             .and_then(|vrc| vrc.get_vote_map(round, vote_type.clone()))
-            .ok_or_else(|| {
-                ConsensusError::StorageErr(format!(
-                    "Can not get {:?} vote map height {}, round {}",
-                    vote_type, height, round
-                ))
+            .ok_or_else(|| ConsensusError::MissingErr {
+                item: Missing::VoteMap(vote_type.clone()),
+                height,
+                round,
             })
     }
 
     /// Get a vote list with the given height, round, type and hash.
     pub fn get_votes(
         &mut self,
-        height: u64,
-        round: u64,
+        height: Height,
+        round: Round,
         vote_type: VoteType,
         hash: &Hash,
     ) -> ConsensusResult<Vec<(SignedVote, Context)>> {
-        self.0
+        self.heights
             .get_mut(&height)
             .and_then(|vrc| vrc.get_votes(round, vote_type.clone(), hash))
-            .ok_or_else(|| {
-                ConsensusError::StorageErr(format!(
-                    "Can not get {:?} votes height {}, round {}",
-                    vote_type, height, round
-                ))
+            .ok_or_else(|| ConsensusError::MissingErr {
+                item: Missing::Votes(vote_type.clone()),
+                height,
+                round,
             })
     }
 
     /// Get a quorum certificate with the given height, round and type.
     pub fn get_qc_by_id(
         &mut self,
-        height: u64,
-        round: u64,
+        height: Height,
+        round: Round,
         qc_type: VoteType,
     ) -> ConsensusResult<AggregatedVote> {
-        self.0
+        self.heights
             .get_mut(&height)
             .and_then(|vrc| vrc.get_qc_by_id(round, qc_type.clone()))
-            .ok_or_else(|| {
-                ConsensusError::StorageErr(format!(
-                    "Can not get {:?} qc height {}, round {}",
-                    qc_type, height, round
-                ))
+            .ok_or_else(|| ConsensusError::MissingErr {
+                item: Missing::QC(qc_type.clone()),
+                height,
+                round,
             })
     }
 
     pub fn get_qc_by_hash(
         &mut self,
-        height: u64,
+        height: Height,
         hash: Hash,
         qc_type: VoteType,
     ) -> Option<AggregatedVote> {
-        self.0
+        self.heights
             .get_mut(&height)
             .and_then(|vrc| vrc.get_qc_by_hash(hash, qc_type))
     }
@@ -211,9 +287,10 @@ impl VoteCollector {
     #[allow(clippy::type_complexity)]
     pub fn get_height_votes(
         &mut self,
-        height: u64,
+        height: Height,
     ) -> Option<(Vec<(SignedVote, Context)>, Vec<AggregatedVote>)> {
-        self.0.remove(&height).map_or_else(
+        self.rounds.remove_by(|(h, _)| *h == height);
+        self.heights.remove(&height).map_or_else(
             || None,
             |mut vrc| {
                 let mut votes = Vec::new();
@@ -229,16 +306,45 @@ impl VoteCollector {
         )
     }
 
-    pub fn vote_count(&self, height: u64, round: u64, vote_type: VoteType) -> usize {
-        if let Some(vrc) = self.0.get(&height) {
+    /// Get the signed votes of both types of the given height and round without removing them.
+    pub fn round_votes(&self, height: Height, round: Round) -> Vec<SignedVote> {
+        self.heights
+            .get(&height)
+            .and_then(|vrc| vrc.general.get(&round))
+            .map_or_else(Vec::new, |rc| {
+                rc.prevote
+                    .by_address
+                    .values()
+                    .chain(rc.precommit.by_address.values())
+                    .map(|(vote, _)| vote.clone())
+                    .collect()
+            })
+    }
+
+    /// Get the quorum certificates of the given height without removing them, ordered by round
+    /// and the prevote QC first.
+    pub fn height_qcs(&self, height: Height) -> Vec<AggregatedVote> {
+        let mut qcs = self.heights.get(&height).map_or_else(Vec::new, |vrc| {
+            vrc.general
+                .values()
+                .flat_map(|rc| rc.qc.get_all_qcs())
+                .collect::<Vec<_>>()
+        });
+        qcs.sort_by_key(|qc| (qc.round, !qc.is_prevote_qc()));
+        qcs
+    }
+
+    pub fn vote_count(&self, height: Height, round: Round, vote_type: VoteType) -> usize {
+        if let Some(vrc) = self.heights.get(&height) {
             return vrc.vote_count(round, vote_type);
         }
         0
     }
 
     /// Remove items that height is less than `till`.
-    pub fn flush(&mut self, till: u64) {
-        self.0 = self.0.split_off(&till);
+    pub fn flush(&mut self, till: Height) {
+        self.rounds.remove_by(|(height, _)| *height < till);
+        self.heights = self.heights.split_off(&till);
     }
 }
 
@@ -246,7 +352,7 @@ impl VoteCollector {
 /// in a `HashMap`.
 #[derive(Clone, Debug)]
 struct VoteRoundCollector {
-    general: HashMap<u64, RoundCollector>,
+    general: HashMap<Round, RoundCollector>,
     qc_by_hash: HashMap<Hash, QuorumCertificate>,
 }
 
@@ -258,28 +364,40 @@ impl VoteRoundCollector {
         }
     }
 
-    fn insert_vote(&mut self, ctx: Context, hash: Hash, vote: SignedVote, addr: Address) {
+    fn insert_vote(
+        &mut self,
+        ctx: Context,
+        hash: Hash,
+        vote: SignedVote,
+        addr: Address,
+        weight: u64,
+    ) {
         self.general
             .entry(vote.get_round())
             .or_insert_with(RoundCollector::new)
-            .insert_vote(ctx, hash, vote, addr);
+            .insert_vote(ctx, hash, vote, addr, weight);
     }
 
-    fn set_qc(&mut self, qc: AggregatedVote) {
+    fn set_qc(&mut self, qc: AggregatedVote) -> Option<AggregatedVote> {
         self.qc_by_hash
             .entry(qc.block_hash.clone())
             .or_insert_with(QuorumCertificate::new)
             .set_quorum_certificate(qc.clone());
 
-        self.general
+        let rc = self
+            .general
             .entry(qc.get_round())
-            .or_insert_with(RoundCollector::new)
-            .set_qc(qc);
+            .or_insert_with(RoundCollector::new);
+        let conflict = rc
+            .get_qc(qc.vote_type.clone())
+            .filter(|kept| kept.block_hash != qc.block_hash);
+        rc.set_qc(qc);
+        conflict
     }
 
     fn get_vote_map(
         &mut self,
-        round: u64,
+        round: Round,
         vote_type: VoteType,
     ) -> Option<&HashMap<Hash, HashSet<Address>>> {
         self.general.get_mut(&round).and_then(|rc| {
@@ -293,7 +411,7 @@ impl VoteRoundCollector {
 
     fn get_votes(
         &mut self,
-        round: u64,
+        round: Round,
         vote_type: VoteType,
         hash: &Hash,
     ) -> Option<Vec<(SignedVote, Context)>> {
@@ -302,7 +420,7 @@ impl VoteRoundCollector {
             .and_then(|rc| rc.get_votes(vote_type, hash))
     }
 
-    fn get_qc_by_id(&mut self, round: u64, qc_type: VoteType) -> Option<AggregatedVote> {
+    fn get_qc_by_id(&mut self, round: Round, qc_type: VoteType) -> Option<AggregatedVote> {
         self.general
             .get_mut(&round)
             .and_then(|rc| rc.get_qc(qc_type))
@@ -315,7 +433,7 @@ impl VoteRoundCollector {
         None
     }
 
-    fn vote_count(&self, round: u64, vote_type: VoteType) -> usize {
+    fn vote_count(&self, round: Round, vote_type: VoteType) -> usize {
         if let Some(rc) = self.general.get(&round) {
             return rc.vote_count(vote_type);
         }
@@ -340,11 +458,18 @@ impl RoundCollector {
         }
     }
 
-    fn insert_vote(&mut self, ctx: Context, hash: Hash, vote: SignedVote, addr: Address) {
+    fn insert_vote(
+        &mut self,
+        ctx: Context,
+        hash: Hash,
+        vote: SignedVote,
+        addr: Address,
+        weight: u64,
+    ) {
         if vote.is_prevote() {
-            self.prevote.insert(ctx, hash, addr, vote);
+            self.prevote.insert(ctx, hash, addr, vote, weight);
         } else {
-            self.precommit.insert(ctx, hash, addr, vote);
+            self.precommit.insert(ctx, hash, addr, vote, weight);
         }
     }
 
@@ -359,6 +484,13 @@ impl RoundCollector {
         }
     }
 
+    fn get_weights(&self, vote_type: VoteType) -> &HashMap<Hash, u64> {
+        match vote_type {
+            VoteType::Prevote => &self.prevote.weights,
+            VoteType::Precommit => &self.precommit.weights,
+        }
+    }
+
     fn get_votes(
         &mut self,
         vote_type: VoteType,
@@ -412,7 +544,7 @@ impl QuorumCertificate {
         }
     }
 
-    fn get_all_qcs(&mut self) -> Vec<AggregatedVote> {
+    fn get_all_qcs(&self) -> Vec<AggregatedVote> {
         let mut res = Vec::new();
 
         if let Some(tmp) = self.prevote.clone() {
@@ -426,11 +558,12 @@ impl QuorumCertificate {
     }
 }
 
-///
+/// The votes of a type in a round, with the running total of the vote weight of each hash.
 #[derive(Clone, Debug)]
 struct Votes {
     by_hash: HashMap<Hash, HashSet<Address>>,
     by_address: HashMap<Address, (SignedVote, Context)>,
+    weights: HashMap<Hash, u64>,
 }
 
 impl Votes {
@@ -438,10 +571,11 @@ impl Votes {
         Votes {
             by_hash: HashMap::new(),
             by_address: HashMap::new(),
+            weights: HashMap::new(),
         }
     }
 
-    fn insert(&mut self, ctx: Context, hash: Hash, addr: Address, vote: SignedVote) {
+    fn insert(&mut self, ctx: Context, hash: Hash, addr: Address, vote: SignedVote, weight: u64) {
         if self.by_address.contains_key(&addr) {
             // the addr somehow has already inserted a Vote we ignore the incoming SignedVote no
             // matter it duplicates or differs(byzantine), reject the current request!
@@ -454,10 +588,32 @@ impl Votes {
             return;
         }
 
+        let total = self.weights.entry(hash.clone()).or_default();
+        match total.checked_add(weight) {
+            Some(sum) => *total = sum,
+            None => {
+                log::error!(
+                    "Overlord: VoteCollector drops the vote of {:?} as the weight sum overflows",
+                    hex_encode(addr)
+                );
+                return;
+            }
+        }
         self.by_hash.entry(hash).or_default().insert(addr.clone());
         self.by_address.entry(addr).or_insert((vote, ctx));
     }
 
+    fn reweigh(&mut self, weight_of: &impl Fn(&Address) -> Option<u64>) {
+        let votes = std::mem::take(&mut self.by_address);
+        self.by_hash.clear();
+        self.weights.clear();
+        for (addr, (vote, ctx)) in votes.into_iter() {
+            if let Some(weight) = weight_of(&addr) {
+                self.insert(ctx, vote.get_hash(), addr, vote, weight);
+            }
+        }
+    }
+
     fn get_vote_map(&self) -> &HashMap<Hash, HashSet<Address>> {
         &self.by_hash
     }
@@ -482,8 +638,8 @@ impl Votes {
 
 #[derive(Clone, Debug)]
 pub struct ChokeCollector {
-    chokes: BTreeMap<u64, HashMap<Address, SignedChoke>>,
-    qcs: HashMap<u64, AggregatedChoke>,
+    chokes: BTreeMap<Round, HashMap<Address, SignedChoke>>,
+    qcs: HashMap<Round, AggregatedChoke>,
 }
 
 impl ChokeCollector {
@@ -494,28 +650,43 @@ impl ChokeCollector {
         }
     }
 
-    pub fn insert(&mut self, round: u64, signed_choke: SignedChoke) {
+    pub fn insert(&mut self, round: Round, signed_choke: SignedChoke) {
         self.chokes
             .entry(round)
             .or_default()
             .insert(signed_choke.address.clone(), signed_choke);
     }
 
-    pub fn set_qc(&mut self, round: u64, qc: AggregatedChoke) {
+    pub fn set_qc(&mut self, round: Round, qc: AggregatedChoke) {
         self.qcs.insert(round, qc);
     }
 
-    pub fn get_chokes(&self, round: u64) -> Option<Vec<SignedChoke>> {
+    pub fn get_chokes(&self, round: Round) -> Option<Vec<SignedChoke>> {
         self.chokes
             .get(&round)
             .map(|map| map.values().cloned().collect::<Vec<_>>())
     }
 
-    pub fn get_qc(&self, round: u64) -> Option<AggregatedChoke> {
+    pub fn get_qc(&self, round: Round) -> Option<AggregatedChoke> {
         self.qcs.get(&round).cloned()
     }
 
-    pub fn max_round_above_threshold(&self, nodes_num: usize) -> Option<u64> {
+    /// Get all signed chokes, ordered by round.
+    pub fn all_chokes(&self) -> Vec<SignedChoke> {
+        self.chokes
+            .values()
+            .flat_map(|map| map.values().cloned())
+            .collect()
+    }
+
+    /// Get all aggregated chokes, ordered by round.
+    pub fn all_qcs(&self) -> Vec<AggregatedChoke> {
+        let mut qcs = self.qcs.values().cloned().collect::<Vec<_>>();
+        qcs.sort_by_key(|qc| qc.round);
+        qcs
+    }
+
+    pub fn max_round_above_threshold(&self, nodes_num: usize) -> Option<Round> {
         for (round, set) in self.chokes.iter().rev() {
             if set.len() * 3 > nodes_num * 2 {
                 return Some(*round);
@@ -524,7 +695,7 @@ impl ChokeCollector {
         None
     }
 
-    pub fn print_round_choke_log(&self, round: u64) {
+    pub fn print_round_choke_log(&self, round: Round) {
         if let Some(map) = self.chokes.get(&round) {
             let voters = map.keys().map(hex_encode).collect::<Vec<_>>();
             log::info!(
@@ -544,6 +715,126 @@ impl ChokeCollector {
     }
 }
 
+/// The maximum count of the block parts that arrive before the header of their round, over all
+/// rounds.
+const PENDING_PART_CAPACITY: usize = 1024;
+
+/// A struct to collect the block parts of the compact proposals of the current height. The parts
+/// are verified by the merkle root in the header once the header is received, and the block is
+/// reassembled as soon as every part is received.
+#[derive(Clone, Debug, Default)]
+pub struct PartCollector {
+    rounds: HashMap<Round, PartSet>,
+    pending: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+struct PartSet {
+    header: Option<(SignedCompactProposal, PartSetHeader)>,
+    parts: BTreeMap<u32, BlockPart>,
+    assembled: bool,
+}
+
+impl PartCollector {
+    pub fn new() -> Self {
+        PartCollector::default()
+    }
+
+    /// Set the header of a round, which is the signed compact proposal with the part set header.
+    /// Return the header and the reassembled block bytes if every part is received.
+    pub fn insert_header<C: Crypto + ?Sized>(
+        &mut self,
+        crypto: &C,
+        sp: SignedCompactProposal,
+    ) -> ConsensusResult<Option<(SignedCompactProposal, Bytes)>> {
+        let round = sp.proposal.round;
+        let set = self.rounds.entry(round).or_default();
+        if let Some((header, _)) = &set.header {
+            if header == &sp {
+                return Ok(None);
+            }
+            return Err(ConsensusError::MultiProposal(sp.proposal.height, round));
+        }
+
+        let part_set = sp.proposal.parts.clone().ok_or_else(|| {
+            ConsensusError::ProposalErr("compact proposal without part set header".to_string())
+        })?;
+        self.pending -= set.parts.len();
+        set.parts
+            .retain(|_, part| verify_part(crypto, &part_set, part));
+        set.header = Some((sp, part_set));
+        Ok(set.try_assemble())
+    }
+
+    /// Insert a block part. Return the header and the reassembled block bytes if every part is
+    /// received. A part that arrives before its header is kept while the pending parts are less
+    /// than the capacity.
+    pub fn insert_part<C: Crypto + ?Sized>(
+        &mut self,
+        crypto: &C,
+        part: BlockPart,
+    ) -> ConsensusResult<Option<(SignedCompactProposal, Bytes)>> {
+        let set = self.rounds.entry(part.round).or_default();
+        if set.assembled {
+            return Ok(None);
+        }
+
+        let part_set = match set.header.as_ref() {
+            Some((_, part_set)) => part_set,
+            None => {
+                if self.pending < PENDING_PART_CAPACITY && !set.parts.contains_key(&part.index) {
+                    self.pending += 1;
+                    set.parts.insert(part.index, part);
+                }
+                return Ok(None);
+            }
+        };
+
+        if !verify_part(crypto, part_set, &part) {
+            return Err(ConsensusError::ProposalErr(format!(
+                "invalid block part height {}, round {}, index {}",
+                part.height, part.round, part.index
+            )));
+        }
+        set.parts.insert(part.index, part);
+        Ok(set.try_assemble())
+    }
+
+    pub fn clear(&mut self) {
+        self.rounds.clear();
+        self.pending = 0;
+    }
+}
+
+impl PartSet {
+    fn try_assemble(&mut self) -> Option<(SignedCompactProposal, Bytes)> {
+        let (header, part_set) = self.header.as_ref()?;
+        if self.assembled || self.parts.len() != part_set.total as usize {
+            return None;
+        }
+        let header = header.clone();
+
+        let mut bytes = BytesMut::new();
+        for part in self.parts.values() {
+            bytes.extend_from_slice(&part.data);
+        }
+        self.parts.clear();
+        self.assembled = true;
+        Some((header, bytes.freeze()))
+    }
+}
+
+fn verify_part<C: Crypto + ?Sized>(crypto: &C, part_set: &PartSetHeader, part: &BlockPart) -> bool {
+    verify_merkle_proof(
+        crypto,
+        &part_set.root,
+        &part.data,
+        part.index as usize,
+        part_set.total as usize,
+        &part.proof,
+    )
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::{HashMap, HashSet};
@@ -553,11 +844,15 @@ mod test {
     use rand::random;
     use serde::{Deserialize, Serialize};
 
-    use crate::state::collection::{ProposalCollector, VoteCollector};
+    use crate::state::collection::{PartCollector, ProposalCollector, VoteCollector};
+    use crate::test_utils::MockCrypto;
     use crate::types::{
-        Address, AggregatedSignature, AggregatedVote, Hash, Proposal, Signature, SignedProposal,
-        SignedVote, Vote, VoteType,
+        Address, AggregatedSignature, AggregatedVote, BlockPart, Hash, Height, Node, PartSetHeader,
+        Proposal, QcKind, Round, Signature, SignedCompactProposal, SignedProposal, SignedVote,
+        Vote, VoteType,
     };
+    use crate::utils::auth_manage::AuthorityManage;
+    use crate::utils::merkle::{merkle_proof, merkle_root};
 
     #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
     struct Pill {
@@ -589,18 +884,20 @@ mod test {
         AggregatedSignature {
             signature: gen_signature(),
             address_bitmap: Bytes::from((0..8).map(|_| random::<u8>()).collect::<Vec<_>>()),
+            kind: QcKind::Aggregated,
         }
     }
 
     fn gen_signed_proposal(height: u64, round: u64) -> SignedProposal<Pill> {
         let signature = gen_signature();
         let proposal = Proposal {
-            height,
-            round,
+            height: Height(height),
+            round: Round(round),
             content: Pill::new(),
             block_hash: gen_hash(),
             lock: None,
             proposer: gen_address(),
+            vrf: None,
         };
 
         SignedProposal {
@@ -617,8 +914,8 @@ mod test {
         addr: Address,
     ) -> SignedVote {
         let vote = Vote {
-            height,
-            round,
+            height: Height(height),
+            round: Round(round),
             vote_type,
             block_hash: hash,
         };
@@ -627,65 +924,182 @@ mod test {
             signature: gen_signature(),
             voter: addr,
             vote,
+            extension: None,
         }
     }
 
-    fn _gen_aggregated_vote(height: u64, round: u64, vote_type: VoteType) -> AggregatedVote {
+    fn gen_aggregated_vote(height: u64, round: u64, vote_type: VoteType) -> AggregatedVote {
         let signature = _gen_aggr_signature();
 
         AggregatedVote {
             signature,
-            height,
-            round,
+            height: Height(height),
+            round: Round(round),
             vote_type,
             block_hash: gen_hash(),
             leader: gen_address(),
+            extensions: None,
         }
     }
 
     #[test]
     fn test_proposal_collector() {
-        let mut proposals = ProposalCollector::<Pill>::new();
+        let mut proposals = ProposalCollector::<Pill>::new(16);
         let proposal_01 = gen_signed_proposal(1, 0);
         let proposal_02 = gen_signed_proposal(1, 0);
 
         assert!(proposals
-            .insert(Context::new(), 1, 0, proposal_01.clone())
+            .insert(
+                Context::new(),
+                Height(1),
+                Round(0),
+                proposal_01.clone(),
+                None
+            )
             .is_ok());
-        assert!(proposals.insert(Context::new(), 1, 0, proposal_02).is_err());
-        assert_eq!(proposals.get(1, 0).unwrap().0, proposal_01);
+        assert!(proposals
+            .insert(Context::new(), Height(1), Round(0), proposal_02, None)
+            .is_err());
+        assert_eq!(proposals.get(Height(1), Round(0)).unwrap().0, proposal_01);
 
         let proposal_03 = gen_signed_proposal(2, 0);
         let proposal_04 = gen_signed_proposal(3, 0);
 
         assert!(proposals
-            .insert(Context::new(), 2, 0, proposal_03.clone())
+            .insert(
+                Context::new(),
+                Height(2),
+                Round(0),
+                proposal_03.clone(),
+                None
+            )
             .is_ok());
         assert!(proposals
-            .insert(Context::new(), 3, 0, proposal_04.clone())
+            .insert(
+                Context::new(),
+                Height(3),
+                Round(0),
+                proposal_04.clone(),
+                None
+            )
             .is_ok());
 
-        proposals.flush(2);
-        assert!(proposals.get(1, 0).is_err());
-        assert_eq!(proposals.get(2, 0).unwrap().0, proposal_03);
-        assert_eq!(proposals.get(3, 0).unwrap().0, proposal_04);
+        let proposal_05 = gen_signed_proposal(3, 1);
+        assert!(proposals
+            .insert(
+                Context::new(),
+                Height(3),
+                Round(1),
+                proposal_05.clone(),
+                None
+            )
+            .is_ok());
+        assert_eq!(
+            proposals.height_proposals(Height(3)),
+            vec![proposal_04.clone(), proposal_05.clone()]
+        );
+
+        proposals.flush(Height(2));
+        assert!(proposals.get(Height(1), Round(0)).is_err());
+        assert_eq!(proposals.get(Height(2), Round(0)).unwrap().0, proposal_03);
+        assert_eq!(proposals.get(Height(3), Round(0)).unwrap().0, proposal_04);
 
-        assert!(proposals.get_height_proposals(1).is_none());
+        assert!(proposals.get_height_proposals(Height(1)).is_none());
         assert_eq!(
             proposals
-                .get_height_proposals(2)
+                .get_height_proposals(Height(2))
                 .unwrap()
                 .into_iter()
                 .map(|item| item.0)
                 .collect::<Vec<_>>(),
             vec![proposal_03]
         );
-        assert!(proposals.get(2, 0).is_err());
+        assert!(proposals.get(Height(2), Round(0)).is_err());
+
+        // The least recently used proposal is evicted beyond the capacity.
+        let mut proposals = ProposalCollector::<Pill>::new(1);
+        proposals
+            .insert(
+                Context::new(),
+                Height(1),
+                Round(0),
+                proposal_01.clone(),
+                None,
+            )
+            .unwrap();
+        proposals
+            .insert(Context::new(), Height(1), Round(1), proposal_05, None)
+            .unwrap();
+        assert!(proposals.get(Height(1), Round(0)).is_err());
+        assert_eq!(proposals.evictions(), 1);
+    }
+
+    #[test]
+    fn test_vote_weight_tally() {
+        let mut votes = VoteCollector::new(16);
+        let addrs = (0..4).map(|_| gen_address()).collect::<Vec<_>>();
+        let weights = [1u64, 1, 1, 3];
+        let mut nodes = addrs
+            .iter()
+            .zip(weights)
+            .map(|(addr, weight)| {
+                let mut node = Node::new(addr.clone());
+                node.set_vote_weight(weight);
+                node
+            })
+            .collect::<Vec<_>>();
+        let mut authority = AuthorityManage::new();
+        authority.update(&mut nodes).unwrap();
+
+        let (hash_01, hash_02) = (gen_hash(), gen_hash());
+        let insert = |votes: &mut VoteCollector, hash: &Hash, index: usize| {
+            let vote = gen_signed_vote(1, 0, VoteType::Prevote, hash.clone(), addrs[index].clone());
+            votes.insert_vote(
+                Context::new(),
+                hash.clone(),
+                vote,
+                addrs[index].clone(),
+                weights[index],
+            );
+        };
+        insert(&mut votes, &hash_01, 0);
+        insert(&mut votes, &hash_01, 1);
+        insert(&mut votes, &hash_02, 2);
+        // A second vote of a voter adds no weight.
+        insert(&mut votes, &hash_01, 2);
+        insert(&mut votes, &hash_01, 0);
+        assert_eq!(
+            votes.first_hash_above_threshold(Height(1), Round(0), VoteType::Prevote, &authority),
+            None
+        );
+
+        insert(&mut votes, &hash_01, 3);
+        assert_eq!(
+            votes.first_hash_above_threshold(Height(1), Round(0), VoteType::Prevote, &authority),
+            Some(hash_01)
+        );
+        assert_eq!(
+            votes.first_hash_above_threshold(Height(1), Round(0), VoteType::Precommit, &authority),
+            None
+        );
+
+        // The votes are recounted with the weights of another list, which drops the last voter.
+        votes.reweigh(Height(1), |addr| {
+            addrs[..3]
+                .iter()
+                .position(|a| a == addr)
+                .map(|index| weights[index])
+        });
+        assert_eq!(
+            votes.first_hash_above_threshold(Height(1), Round(0), VoteType::Prevote, &authority),
+            None
+        );
+        assert_eq!(votes.vote_count(Height(1), Round(0), VoteType::Prevote), 3);
     }
 
     #[test]
     fn test_vote_collector() {
-        let mut votes = VoteCollector::new();
+        let mut votes = VoteCollector::new(16);
 
         let mut map = HashMap::new();
         let mut vec = Vec::new();
@@ -705,16 +1119,20 @@ mod test {
             hash_01.clone(),
             signed_vote_01.clone(),
             addr_01.clone(),
+            1,
         );
 
         set.insert(addr_01);
         map.insert(hash_01.clone(), set);
         vec.push(signed_vote_01);
 
-        assert_eq!(votes.get_vote_map(1, 0, VoteType::Prevote), Ok(&map));
+        assert_eq!(
+            votes.get_vote_map(Height(1), Round(0), VoteType::Prevote),
+            Ok(&map)
+        );
         assert_eq!(
             votes
-                .get_votes(1, 0, VoteType::Prevote, &hash_01)
+                .get_votes(Height(1), Round(0), VoteType::Prevote, &hash_01)
                 .unwrap()
                 .iter()
                 .map(|item| item.0.clone())
@@ -722,30 +1140,130 @@ mod test {
                 .collect::<Vec<_>>(),
             vec
         );
-        assert!(votes.get_vote_map(1, 0, VoteType::Precommit).is_err());
         assert!(votes
-            .get_votes(1, 0, VoteType::Precommit, &hash_01)
+            .get_vote_map(Height(1), Round(0), VoteType::Precommit)
+            .is_err());
+        assert!(votes
+            .get_votes(Height(1), Round(0), VoteType::Precommit, &hash_01)
+            .is_err());
+        assert!(votes
+            .get_vote_map(Height(1), Round(1), VoteType::Prevote)
+            .is_err());
+        assert!(votes
+            .get_votes(Height(1), Round(1), VoteType::Prevote, &hash_01)
+            .is_err());
+        assert!(votes
+            .get_votes(Height(1), Round(0), VoteType::Prevote, &hash_02)
             .is_err());
-        assert!(votes.get_vote_map(1, 1, VoteType::Prevote).is_err());
-        assert!(votes.get_votes(1, 1, VoteType::Prevote, &hash_01).is_err());
-        assert!(votes.get_votes(1, 0, VoteType::Prevote, &hash_02).is_err());
 
         votes.insert_vote(
             Context::new(),
             hash_01.clone(),
             signed_vote_02.clone(),
             addr_02.clone(),
+            1,
         );
         map.get_mut(&hash_01).unwrap().insert(addr_02);
         vec.push(signed_vote_02);
 
-        assert_eq!(votes.get_vote_map(1, 0, VoteType::Prevote), Ok(&map));
+        assert_eq!(
+            votes.get_vote_map(Height(1), Round(0), VoteType::Prevote),
+            Ok(&map)
+        );
         let res = votes
-            .get_votes(1, 0, VoteType::Prevote, &hash_01)
+            .get_votes(Height(1), Round(0), VoteType::Prevote, &hash_01)
             .unwrap()
             .iter()
             .map(|item| item.0.clone())
             .collect::<HashSet<_>>();
         assert_eq!(res, vec.iter().cloned().collect::<HashSet<_>>());
     }
+
+    #[test]
+    fn test_conflicting_qc() {
+        let mut votes = VoteCollector::new(16);
+        let qc = gen_aggregated_vote(1, 0, VoteType::Precommit);
+        assert_eq!(votes.set_qc(qc.clone()), None);
+        assert_eq!(votes.set_qc(qc.clone()), None);
+        assert_eq!(
+            votes.set_qc(gen_aggregated_vote(1, 0, VoteType::Prevote)),
+            None
+        );
+        assert_eq!(
+            votes.set_qc(gen_aggregated_vote(1, 1, VoteType::Precommit)),
+            None
+        );
+
+        let conflict = gen_aggregated_vote(1, 0, VoteType::Precommit);
+        assert_eq!(votes.set_qc(conflict.clone()), Some(qc));
+        assert_eq!(
+            votes.get_qc_by_id(Height(1), Round(0), VoteType::Precommit),
+            Ok(conflict)
+        );
+    }
+
+    #[test]
+    fn test_vote_collector_pin_lock() {
+        let mut votes = VoteCollector::new(4);
+        let lock = gen_aggregated_vote(1, 1, VoteType::Prevote);
+        votes.set_qc(lock.clone());
+        votes.pin_rounds(Height(1), [Round(1), Round(3)]);
+
+        // The spam rounds evict each other around the lock round and the current round.
+        for round in 2..100 {
+            votes.set_qc(gen_aggregated_vote(1, round, VoteType::Precommit));
+        }
+        assert_eq!(
+            votes.get_qc_by_id(Height(1), Round(1), VoteType::Prevote),
+            Ok(lock)
+        );
+        assert!(votes
+            .get_qc_by_id(Height(1), Round(3), VoteType::Precommit)
+            .is_ok());
+        assert!(votes
+            .get_qc_by_id(Height(1), Round(2), VoteType::Precommit)
+            .is_err());
+        assert_eq!(votes.evictions(), 93);
+    }
+
+    #[test]
+    fn test_part_collector() {
+        let crypto = MockCrypto::new(Address::new());
+        let data = (0..3u8)
+            .map(|i| Bytes::from(vec![i; 4]))
+            .collect::<Vec<_>>();
+        let gen_part = |index: usize| BlockPart {
+            height: Height(1),
+            round: Round(0),
+            index: index as u32,
+            data: data[index].clone(),
+            proof: merkle_proof(&crypto, &data, index),
+        };
+        let mut proposal = gen_signed_proposal(1, 0).proposal.to_compact();
+        proposal.parts = Some(PartSetHeader {
+            total: 3,
+            root: merkle_root(&crypto, &data),
+        });
+        let header = SignedCompactProposal {
+            signature: gen_signature(),
+            proposal,
+        };
+
+        // The part before the header is kept, and a forged part is refused after the header.
+        let mut parts = PartCollector::new();
+        assert!(parts.insert_part(&crypto, gen_part(2)).unwrap().is_none());
+        assert!(parts
+            .insert_header(&crypto, header.clone())
+            .unwrap()
+            .is_none());
+        let mut forged = gen_part(0);
+        forged.data = Bytes::from(vec![9u8; 4]);
+        assert!(parts.insert_part(&crypto, forged).is_err());
+        assert!(parts.insert_part(&crypto, gen_part(0)).unwrap().is_none());
+
+        let (res, bytes) = parts.insert_part(&crypto, gen_part(1)).unwrap().unwrap();
+        assert_eq!(res, header);
+        assert_eq!(bytes, data.concat());
+        assert!(parts.insert_part(&crypto, gen_part(1)).unwrap().is_none());
+    }
 }
@@ -0,0 +1,76 @@
+use std::time::{Duration, Instant};
+
+use crate::types::{Address, DebugTarget, Height};
+
+/// The debug targets that are set at runtime. A target expires after its time to live, so that
+/// the debug output stops by itself.
+#[derive(Debug, Default)]
+pub(crate) struct DebugTargets {
+    targets: Vec<(DebugTarget, Instant)>,
+    sampled: u64,
+}
+
+impl DebugTargets {
+    pub(crate) fn new() -> Self {
+        DebugTargets::default()
+    }
+
+    /// Set a target which expires after the given time to live. Setting a target again renews it.
+    pub(crate) fn set(&mut self, target: DebugTarget, ttl: Duration) {
+        let expiry = Instant::now() + ttl;
+        self.targets.retain(|(item, _)| item != &target);
+        self.targets.push((target, expiry));
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.targets.clear();
+    }
+
+    /// Whether a message or an event of the height, from the sender if any, is targeted. The
+    /// expired targets are removed.
+    pub(crate) fn matches(&mut self, height: Height, sender: Option<&Address>) -> bool {
+        if self.targets.is_empty() {
+            return false;
+        }
+        let now = Instant::now();
+        self.targets.retain(|(_, expiry)| *expiry > now);
+
+        self.sampled += 1;
+        let sampled = self.sampled;
+        self.targets.iter().any(|(target, _)| match target {
+            DebugTarget::Height(h) => *h == height,
+            DebugTarget::Address(addr) => sender == Some(addr),
+            DebugTarget::Sample(every) => sampled.is_multiple_of((*every).max(1)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::DebugTargets;
+    use crate::types::{Address, DebugTarget, Height};
+
+    #[test]
+    fn test_debug_targets() {
+        let mut targets = DebugTargets::new();
+        let addr = Address::from(vec![1u8; 20]);
+        assert!(!targets.matches(Height(1), Some(&addr)));
+
+        targets.set(DebugTarget::Height(Height(2)), Duration::from_secs(60));
+        targets.set(DebugTarget::Address(addr.clone()), Duration::from_secs(60));
+        assert!(targets.matches(Height(2), None));
+        assert!(targets.matches(Height(1), Some(&addr)));
+        assert!(!targets.matches(Height(1), Some(&Address::from(vec![2u8; 20]))));
+
+        // The expired target is removed.
+        targets.set(DebugTarget::Height(Height(2)), Duration::ZERO);
+        assert!(!targets.matches(Height(2), None));
+
+        targets.clear();
+        targets.set(DebugTarget::Sample(2), Duration::from_secs(60));
+        let hits = (0..10).filter(|_| targets.matches(Height(1), None)).count();
+        assert_eq!(hits, 5);
+    }
+}
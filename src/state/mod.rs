@@ -1,6 +1,14 @@
 ///
+mod artifacts;
+///
 mod collection;
+/// Debug targets which are set at runtime.
+mod debug;
 ///
 mod parallel;
 ///
 pub mod process;
+///
+mod sign_guard;
+/// The stuck height watchdog.
+mod watchdog;
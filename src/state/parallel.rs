@@ -1,130 +1,607 @@
-use std::sync::Arc;
+use std::collections::HashSet;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc};
+use std::thread;
 
 use bytes::Bytes;
 use creep::Context;
-use futures::channel::mpsc::UnboundedSender;
+use futures::channel::mpsc::{Receiver, Sender};
+use futures::{executor, SinkExt};
+use hummer::coding::hex_encode;
+#[cfg(feature = "apm")]
 use muta_apm::derive::tracing_span;
+use parking_lot::Mutex;
 
-use crate::types::{Address, AggregatedVote, OverlordMsg};
+use crate::codec::domain_payload;
+use crate::error::ConsensusError;
+use crate::types::{
+    verify_vote_extensions, Address, AggregatedChoke, AggregatedVote, OverlordMsg, RoundSummary,
+    SignedChoke, SignedVote, UpdateFrom, VoteExtension,
+};
 use crate::utils::auth_manage::AuthorityManage;
 use crate::{Codec, ConsensusResult, Crypto};
 
-#[tracing_span(kind = "overlord.vreify_sig_pool")]
+/// A pool of the threads that verify the signatures of the messages, so that the CPU heavy
+/// verification does not hold the workers of the async runtime, see
+/// `OverlordConfig::verify_workers`. The queue of the pool is bounded, and a message is dropped
+/// when it is full, as the state must not block on the workers. The threads exit once the pool is
+/// dropped.
+#[derive(Debug)]
+pub(crate) struct VerifyPool {
+    tx: mpsc::SyncSender<Job>,
+}
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+impl VerifyPool {
+    pub(crate) fn new(workers: usize, capacity: usize) -> ConsensusResult<Self> {
+        let (tx, rx) = mpsc::sync_channel::<Job>(capacity.max(1));
+        let rx = Arc::new(Mutex::new(rx));
+        for i in 0..workers.max(1) {
+            let rx = Arc::clone(&rx);
+            thread::Builder::new()
+                .name(format!("overlord-verify-{}", i))
+                .spawn(move || loop {
+                    let job = match rx.lock().recv() {
+                        Ok(job) => job,
+                        Err(_) => return,
+                    };
+                    if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                        log::error!("Overlord: verify worker {} panicked on a message", i);
+                    }
+                })
+                .map_err(|e| {
+                    ConsensusError::Other(format!("spawn verify worker {} error {}", i, e))
+                })?;
+        }
+        Ok(VerifyPool { tx })
+    }
+
+    fn execute(&self, job: Job) {
+        match self.tx.try_send(job) {
+            Ok(()) => (),
+            Err(mpsc::TrySendError::Full(_)) => {
+                log::warn!("Overlord: verify queue is full, drop a message")
+            }
+            Err(mpsc::TrySendError::Disconnected(_)) => {
+                log::error!("Overlord: verify workers are gone, drop a message")
+            }
+        }
+    }
+}
+
+/// Verify the message and send it to the state over the verified message channel, on the verify
+/// pool if any, or on a task of the async runtime otherwise.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "overlord.verify_sig_pool", skip_all, fields(kind = "overlord"))
+)]
+#[cfg_attr(feature = "apm", tracing_span(kind = "overlord.vreify_sig_pool"))]
 pub async fn parallel_verify<T: Codec + 'static, C: Crypto + Sync + 'static>(
     ctx: Context,
     msg: OverlordMsg<T>,
     crypto: Arc<C>,
+    chain_id: Bytes,
     authority: AuthorityManage,
-    tx: UnboundedSender<(Context, OverlordMsg<T>)>,
+    mut tx: Sender<(Context, OverlordMsg<T>)>,
+    pool: Option<&VerifyPool>,
 ) {
-    let msg_clone = msg.clone();
-    tokio::spawn(async move {
-        match msg {
-            OverlordMsg::SignedProposal(sp) => {
-                let hash = crypto.hash(alloy_rlp::encode(&sp.proposal).into());
-                if let Err(err) = crypto.verify_signature(
-                    sp.signature.clone(),
-                    hash,
-                    sp.proposal.proposer.clone(),
-                ) {
-                    log::error!(
-                        "Overlord: verify {:?} proposal signature failed {:?}",
-                        sp,
-                        err
-                    );
-                    return;
+    match pool {
+        // The sending blocks the worker when the verified message channel is full, which holds
+        // the further verification back.
+        Some(pool) => pool.execute(Box::new(move || {
+            if let Some(msg) = verify_msg(msg, crypto.as_ref(), &chain_id, &authority) {
+                let _ = executor::block_on(tx.send((ctx, msg)));
+            }
+        })),
+        None => {
+            tokio::spawn(async move {
+                if let Some(msg) = verify_msg(msg, crypto.as_ref(), &chain_id, &authority) {
+                    let _ = tx.send((ctx, msg)).await;
                 }
+            });
+        }
+    }
+}
 
-                if let Some(polc) = sp.proposal.lock {
-                    verify_qc(
-                        ctx.clone(),
-                        crypto,
-                        polc.lock_votes,
-                        authority,
-                        tx.clone(),
-                        msg_clone.clone(),
-                    );
-                } else {
-                    let _ = tx.unbounded_send((ctx, msg_clone));
-                }
+/// Drain the verified votes queued right behind a verified vote into a batch of at most
+/// `max_batch` votes, so that the votes are counted once for the batch instead of once for each,
+/// see `OverlordConfig::vote_batch_size`. Return the message to handle and the first drained
+/// message which is not a vote, to handle after it.
+pub(crate) fn drain_verified_votes<T: Codec>(
+    msg: OverlordMsg<T>,
+    rx: &mut Receiver<(Context, OverlordMsg<T>)>,
+    max_batch: usize,
+) -> (OverlordMsg<T>, Option<(Context, OverlordMsg<T>)>) {
+    let mut votes = match msg {
+        OverlordMsg::SignedVote(sv) if max_batch > 1 => vec![sv],
+        msg => return (msg, None),
+    };
+
+    let mut rest = None;
+    while votes.len() < max_batch {
+        match rx.try_recv() {
+            Ok((_, OverlordMsg::SignedVote(sv))) => votes.push(sv),
+            Ok(msg) => {
+                rest = Some(msg);
+                break;
             }
+            _ => break,
+        }
+    }
+
+    if votes.len() == 1 {
+        return (OverlordMsg::SignedVote(votes.remove(0)), rest);
+    }
+    (OverlordMsg::VoteBatch(votes), rest)
+}
 
-            OverlordMsg::SignedVote(sv) => {
-                let hash = crypto.hash(alloy_rlp::encode(&sv.vote).into());
-                crypto
-                    .verify_signature(sv.signature.clone(), hash, sv.voter.clone())
-                    .map_or_else(
-                        |err| {
-                            log::error!(
-                                "Overlord: verify {:?} vote signature failed {:?}",
-                                sv,
-                                err
-                            );
-                        },
-                        |_| {
-                            let _ = tx.unbounded_send((ctx, msg_clone));
-                        },
-                    );
+/// Verify the signatures of the message, return the message to send to the state if it passes.
+fn verify_msg<T: Codec, C: Crypto>(
+    msg: OverlordMsg<T>,
+    crypto: &C,
+    chain_id: &[u8],
+    authority: &AuthorityManage,
+) -> Option<OverlordMsg<T>> {
+    // The votes of a batch are verified in one job, the invalid ones are dropped and the rest are
+    // sent to the state together.
+    if let OverlordMsg::VoteBatch(votes) = msg {
+        let votes = votes
+            .into_iter()
+            .filter(|sv| verify_signed_vote(crypto, chain_id, sv))
+            .collect::<Vec<_>>();
+        if votes.is_empty() {
+            return None;
+        }
+        return Some(OverlordMsg::VoteBatch(votes));
+    }
+
+    match &msg {
+        OverlordMsg::SignedProposal(sp) => {
+            // A signed proposal is verified in the full form only. A proposal signed in the compact
+            // form is verified as a signed compact proposal, which never carries the content.
+            let hash = crypto.hash(domain_payload(
+                chain_id,
+                alloy_rlp::encode(&sp.proposal).into(),
+            ));
+            if let Err(err) = crypto.verify_signature_at(
+                sp.proposal.height,
+                sp.signature.clone(),
+                hash,
+                sp.proposal.proposer.clone(),
+            ) {
+                log::error!(
+                    "Overlord: verify {:?} proposal signature failed {:?}",
+                    sp,
+                    err
+                );
+                return None;
             }
 
-            OverlordMsg::AggregatedVote(qc) => {
-                verify_qc(ctx, crypto, qc, authority, tx, msg_clone);
+            if let Some(polc) = sp.proposal.lock.as_ref() {
+                verify_qc(crypto, chain_id, &polc.lock_votes, authority)?;
             }
+            Some(msg)
+        }
 
-            OverlordMsg::SignedChoke(sc) => {
-                let hash = crypto.hash(alloy_rlp::encode(&sc.choke.to_hash()).into());
-                crypto
-                    .verify_signature(sc.signature.clone(), hash, sc.address.clone())
-                    .map_or_else(
-                        |err| {
-                            log::error!(
-                                "Overlord: verify {:?} choke signature failed {:?}",
-                                sc,
-                                err
-                            );
-                        },
-                        |_| {
-                            let _ = tx.unbounded_send((ctx, msg_clone));
-                        },
-                    )
+        OverlordMsg::SignedCompactProposal(sp) => {
+            let hash = crypto.hash(domain_payload(
+                chain_id,
+                alloy_rlp::encode(&sp.proposal).into(),
+            ));
+            if let Err(err) = crypto.verify_signature_at(
+                sp.proposal.height,
+                sp.signature.clone(),
+                hash,
+                sp.proposal.proposer.clone(),
+            ) {
+                log::error!(
+                    "Overlord: verify {:?} compact proposal signature failed {:?}",
+                    sp,
+                    err
+                );
+                return None;
             }
 
-            _ => (),
+            if let Some(polc) = sp.proposal.lock.as_ref() {
+                verify_qc(crypto, chain_id, &polc.lock_votes, authority)?;
+            }
+            Some(msg)
+        }
+
+        OverlordMsg::SignedVote(sv) if verify_signed_vote(crypto, chain_id, sv) => Some(msg),
+
+        OverlordMsg::AggregatedVote(qc) => {
+            verify_qc(crypto, chain_id, qc, authority)?;
+            Some(msg)
+        }
+
+        OverlordMsg::RoundSummary(summary) => {
+            if let Err(err) = verify_choke_qc(crypto, chain_id, summary, authority) {
+                log::error!("Overlord: verify {:?} round summary error {}", summary, err);
+                return None;
+            }
+            Some(msg)
+        }
+
+        OverlordMsg::SignedChoke(sc) => {
+            if let Err(err) = check_signed_choke(crypto, chain_id, sc, authority) {
+                log::error!("Overlord: verify {:?} choke error {}", sc, err);
+                return None;
+            }
+            Some(msg)
+        }
+
+        _ => None,
+    }
+}
+
+/// Verify that the signer of the choke is in the authority list and that its signature passes.
+/// The QC by which the signer went to the round is handled as a received QC, so it is verified as
+/// one if it is of the choke height. A QC of another height is ignored by the state.
+pub(crate) fn check_signed_choke<C: Crypto>(
+    crypto: &C,
+    chain_id: &[u8],
+    sc: &SignedChoke,
+    authority: &AuthorityManage,
+) -> ConsensusResult<()> {
+    if !authority.contains(&sc.address) {
+        return Err(ConsensusError::InvalidAddress(sc.address.clone()));
+    }
+
+    let hash = crypto.hash(domain_payload(
+        chain_id,
+        alloy_rlp::encode(sc.choke.to_hash()).into(),
+    ));
+    crypto
+        .verify_signature_at(
+            sc.choke.height,
+            sc.signature.clone(),
+            hash,
+            sc.address.clone(),
+        )
+        .map_err(|err| ConsensusError::InvalidSignatureErr {
+            height: sc.choke.height,
+            round: sc.choke.round,
+            source: err.into(),
+        })?;
+
+    match &sc.choke.from {
+        UpdateFrom::PrevoteQC(qc) | UpdateFrom::PrecommitQC(qc) if qc.height == sc.choke.height => {
+            check_qc(crypto, chain_id, qc, authority)
+        }
+        UpdateFrom::ChokeQC(qc) if qc.height == sc.choke.height => {
+            check_choke_qc(crypto, chain_id, qc, authority)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Verify the signature of the signed vote and of its extension if any.
+fn verify_signed_vote<C: Crypto>(crypto: &C, chain_id: &[u8], sv: &SignedVote) -> bool {
+    let hash = crypto.hash(domain_payload(chain_id, alloy_rlp::encode(&sv.vote).into()));
+    let height = sv.vote.height;
+    if let Err(err) =
+        crypto.verify_signature_at(height, sv.signature.clone(), hash, sv.voter.clone())
+    {
+        log::error!("Overlord: verify {:?} vote signature failed {:?}", sv, err);
+        return false;
+    }
+
+    if let Some(extension) = sv.extension.as_ref() {
+        let hash = crypto.hash(domain_payload(
+            chain_id,
+            VoteExtension::sign_payload(&sv.vote, &extension.data),
+        ));
+        if let Err(err) =
+            crypto.verify_signature_at(height, extension.signature.clone(), hash, sv.voter.clone())
+        {
+            log::error!(
+                "Overlord: verify {:?} vote extension signature failed {:?}",
+                sv,
+                err
+            );
+            return false;
         }
-    });
+    }
+    true
+}
+
+/// Verify that the choke QC of the round summary leads to the new round, and that it passes
+/// `check_choke_qc`.
+fn verify_choke_qc<C: Crypto>(
+    crypto: &C,
+    chain_id: &[u8],
+    summary: &RoundSummary,
+    authority: &AuthorityManage,
+) -> ConsensusResult<()> {
+    let qc = &summary.choke_qc;
+    if qc.height != summary.height || qc.round + 1 != summary.new_round {
+        return Err(ConsensusError::BrakeErr(format!(
+            "choke qc of height {}, round {} mismatches the new round {}",
+            qc.height, qc.round, summary.new_round
+        )));
+    }
+    check_choke_qc(crypto, chain_id, qc, authority)
+}
+
+/// Verify that the voters of the choke QC are above the threshold and that its aggregated
+/// signature passes.
+pub(crate) fn check_choke_qc<C: Crypto>(
+    crypto: &C,
+    chain_id: &[u8],
+    qc: &AggregatedChoke,
+    authority: &AuthorityManage,
+) -> ConsensusResult<()> {
+    // A voter listed twice would count its weight twice, and the aggregated signature schemes do
+    // not tell it either.
+    let mut voters = HashSet::with_capacity(qc.voters.len());
+    if let Some(voter) = qc.voters.iter().find(|voter| !voters.insert(*voter)) {
+        return Err(ConsensusError::BrakeErr(format!(
+            "choke qc of height {}, round {} has the duplicate voter {}",
+            qc.height,
+            qc.round,
+            hex_encode(voter.clone())
+        )));
+    }
+
+    let weight = authority.sum_vote_weight(qc.voters.iter())?;
+    if !authority.is_above_weight(weight) {
+        return Err(ConsensusError::BrakeErr(
+            "choke qc is not above threshold".to_string(),
+        ));
+    }
+
+    let hash = crypto.hash(domain_payload(
+        chain_id,
+        alloy_rlp::encode(qc.to_hash()).into(),
+    ));
+    crypto
+        .verify_aggregated_signature_at(qc.height, qc.signature.clone(), hash, qc.voters.clone())
+        .map_err(|err| ConsensusError::InvalidSignatureErr {
+            height: qc.height,
+            round: qc.round,
+            source: err.into(),
+        })
 }
 
 fn get_voters(
     addr_bitmap: &Bytes,
     authority_manage: AuthorityManage,
 ) -> ConsensusResult<Vec<Address>> {
-    authority_manage.is_above_threshold(addr_bitmap)?;
+    if !authority_manage.is_above_threshold(addr_bitmap)? {
+        return Err(ConsensusError::AggregatedSignatureErr(
+            "QC is not above threshold".to_string(),
+        ));
+    }
     authority_manage.get_voters(addr_bitmap)
 }
 
-fn verify_qc<T: Codec, C: Crypto>(
-    ctx: Context,
-    crypto: Arc<C>,
-    qc: AggregatedVote,
-    authority: AuthorityManage,
-    tx: UnboundedSender<(Context, OverlordMsg<T>)>,
-    msg_clone: OverlordMsg<T>,
-) {
-    let hash = crypto.hash(alloy_rlp::encode(&qc.to_vote()).into());
-    if let Ok(voters) = get_voters(&qc.signature.address_bitmap, authority) {
-        crypto
-            .verify_aggregated_signature(qc.signature.signature.clone(), hash, voters)
-            .map_or_else(
-                |err| {
-                    log::error!(
-                        "Overlord: verify {:?} aggregated signature error {:?}",
-                        qc,
-                        err
-                    );
-                },
-                |_| {
-                    let _ = tx.unbounded_send((ctx, msg_clone));
-                },
-            );
+/// Verify the QC, log and return `None` if it fails.
+fn verify_qc<C: Crypto>(
+    crypto: &C,
+    chain_id: &[u8],
+    qc: &AggregatedVote,
+    authority: &AuthorityManage,
+) -> Option<()> {
+    if let Err(err) = check_qc(crypto, chain_id, qc, authority) {
+        log::error!("Overlord: verify {:?} qc error {}", qc, err);
+        return None;
+    }
+    Some(())
+}
+
+/// Verify that the voters of the QC are above the threshold, and that its vote extensions and its
+/// aggregated signature pass, or that the threshold signature of a threshold QC passes.
+pub(crate) fn check_qc<C: Crypto>(
+    crypto: &C,
+    chain_id: &[u8],
+    qc: &AggregatedVote,
+    authority: &AuthorityManage,
+) -> ConsensusResult<()> {
+    let hash = crypto.hash(domain_payload(
+        chain_id,
+        alloy_rlp::encode(qc.to_vote()).into(),
+    ));
+    if qc.signature.is_threshold() {
+        // The threshold is enforced by the scheme, and the signature is of the group key of the
+        // authority list. There are no voters to check the vote extensions against but the
+        // authority list.
+        verify_vote_extensions(
+            crypto,
+            chain_id,
+            &qc.to_vote(),
+            qc.extensions.as_deref(),
+            authority.get_address_ref(),
+        )?;
+        return crypto
+            .verify_threshold_signature(
+                qc.height,
+                qc.signature.signature.clone(),
+                hash,
+                authority.get_address_ref().clone(),
+            )
+            .map_err(|err| ConsensusError::InvalidSignatureErr {
+                height: qc.height,
+                round: qc.round,
+                source: err.into(),
+            });
+    }
+
+    let voters = get_voters(&qc.signature.address_bitmap, authority.clone())?;
+    verify_vote_extensions(
+        crypto,
+        chain_id,
+        &qc.to_vote(),
+        qc.extensions.as_deref(),
+        &voters,
+    )?;
+
+    crypto
+        .verify_aggregated_signature_at(qc.height, qc.signature.signature.clone(), hash, voters)
+        .map_err(|err| ConsensusError::InvalidSignatureErr {
+            height: qc.height,
+            round: qc.round,
+            source: err.into(),
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+
+    use bytes::Bytes;
+    use creep::Context;
+    use futures::channel::mpsc::channel;
+
+    use super::{drain_verified_votes, verify_choke_qc, verify_msg, VerifyPool};
+    use crate::codec::domain_payload;
+    use crate::test_utils::EchoCrypto;
+    use crate::types::{
+        Address, AggregatedChoke, Hash, Height, Node, OverlordMsg, Proposal, Round, RoundSummary,
+        Signature, SignedCompactProposal, SignedProposal, SignedVote, Vote, VoteType,
+    };
+    use crate::utils::auth_manage::AuthorityManage;
+    use crate::Crypto;
+
+    #[test]
+    fn test_verify_pool() {
+        let pool = VerifyPool::new(1, 4).unwrap();
+        let (tx, rx) = mpsc::channel();
+
+        // A panicking job does not take its worker down.
+        pool.execute(Box::new(|| panic!("invalid message")));
+        for i in 0..3 {
+            let tx = tx.clone();
+            pool.execute(Box::new(move || tx.send(i).unwrap()));
+        }
+        assert_eq!(rx.iter().take(3).collect::<Vec<_>>(), vec![0, 1, 2]);
+
+        // The jobs beyond the capacity are dropped while the worker is busy.
+        let (started_tx, started_rx) = mpsc::channel();
+        let (resume_tx, resume_rx) = mpsc::channel::<()>();
+        pool.execute(Box::new(move || {
+            started_tx.send(()).unwrap();
+            resume_rx.recv().unwrap();
+        }));
+        started_rx.recv().unwrap();
+        for i in 0..6 {
+            let tx = tx.clone();
+            pool.execute(Box::new(move || tx.send(i).unwrap()));
+        }
+        drop(tx);
+        resume_tx.send(()).unwrap();
+        assert_eq!(rx.iter().collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+    }
+
+    fn signed_vote(voter: u8) -> OverlordMsg<Bytes> {
+        OverlordMsg::SignedVote(SignedVote {
+            signature: Signature::new(),
+            vote: Vote {
+                height: Height(1),
+                round: Round(0),
+                vote_type: VoteType::Prevote,
+                block_hash: Hash::new(),
+            },
+            voter: Address::from(vec![voter]),
+            extension: None,
+        })
+    }
+
+    #[test]
+    fn test_drain_verified_votes() {
+        let (mut tx, mut rx) = channel(16);
+        for voter in 1..4 {
+            tx.try_send((Context::new(), signed_vote(voter))).unwrap();
+        }
+        let stop = OverlordMsg::Stop;
+        tx.try_send((Context::new(), stop.clone())).unwrap();
+        tx.try_send((Context::new(), signed_vote(4))).unwrap();
+
+        // The batch stops at the size, then at the first message which is not a vote.
+        let (msg, rest) = drain_verified_votes(signed_vote(0), &mut rx, 3);
+        assert!(matches!(msg, OverlordMsg::VoteBatch(ref votes) if votes.len() == 3));
+        assert!(rest.is_none());
+        let (msg, rest) = drain_verified_votes(signed_vote(0), &mut rx, 3);
+        assert!(matches!(msg, OverlordMsg::VoteBatch(ref votes) if votes.len() == 2));
+        assert_eq!(rest.unwrap().1, stop);
+
+        let (msg, rest) = drain_verified_votes(signed_vote(0), &mut rx, 3);
+        assert!(matches!(msg, OverlordMsg::VoteBatch(ref votes) if votes.len() == 2));
+        assert!(rest.is_none());
+
+        // A single vote is handled as it is.
+        let (msg, rest) = drain_verified_votes(signed_vote(0), &mut rx, 3);
+        assert!(matches!(msg, OverlordMsg::SignedVote(_)));
+        assert!(rest.is_none());
+        let (msg, _) = drain_verified_votes(stop.clone(), &mut rx, 3);
+        assert_eq!(msg, stop);
+    }
+
+    #[test]
+    fn test_verify_proposal_form() {
+        let crypto = EchoCrypto;
+        let authority = AuthorityManage::new();
+        let proposal = Proposal {
+            height: Height(1),
+            round: Round(0),
+            content: Bytes::from_static(b"block"),
+            block_hash: Hash::from(vec![1u8]),
+            lock: None,
+            proposer: Address::from(vec![0u8]),
+            vrf: None,
+        };
+        let compact = proposal.to_compact();
+        let signature = crypto
+            .sign(crypto.hash(domain_payload(b"", alloy_rlp::encode(&compact).into())))
+            .unwrap();
+
+        // The signature of the compact form never covers a full proposal, whatever its content.
+        let mut forged = proposal.clone();
+        forged.content = Bytes::from_static(b"forged");
+        let msg = OverlordMsg::SignedProposal(SignedProposal {
+            signature: signature.clone(),
+            proposal: forged,
+        });
+        assert!(verify_msg(msg, &crypto, b"", &authority).is_none());
+
+        let msg = OverlordMsg::<Bytes>::SignedCompactProposal(SignedCompactProposal {
+            signature,
+            proposal: compact,
+        });
+        assert!(verify_msg(msg, &crypto, b"", &authority).is_some());
+    }
+
+    #[test]
+    fn test_verify_choke_qc_duplicate_voters() {
+        let crypto = EchoCrypto;
+        let mut nodes = (0..4u8)
+            .map(|i| Node::new(Address::from(vec![i])))
+            .collect::<Vec<_>>();
+        let mut authority = AuthorityManage::new();
+        authority.update(&mut nodes).unwrap();
+
+        let mut choke_qc = AggregatedChoke {
+            height: Height(1),
+            round: Round(0),
+            signature: Signature::new(),
+            voters: vec![nodes[0].address.clone(); 3],
+        };
+        choke_qc.signature = crypto
+            .sign(crypto.hash(domain_payload(
+                b"",
+                alloy_rlp::encode(choke_qc.to_hash()).into(),
+            )))
+            .unwrap();
+        let mut summary = RoundSummary {
+            height: Height(1),
+            new_round: Round(1),
+            choke_qc,
+        };
+
+        // A voter listed three times does not make up the threshold alone.
+        assert!(verify_choke_qc(&crypto, b"", &summary, &authority).is_err());
+        summary.choke_qc.voters = nodes[..3].iter().map(|node| node.address.clone()).collect();
+        assert!(verify_choke_qc(&crypto, b"", &summary, &authority).is_ok());
     }
 }
@@ -1,35 +1,65 @@
 use std::cmp::{Ord, Ordering};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::error::Error;
 use std::string::ToString;
-use std::time::{Duration, Instant};
-use std::{ops::BitXor, sync::Arc};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{iter, ops::BitXor, sync::Arc};
 
 use alloy_rlp::Decodable;
 use bit_vec::BitVec;
 use bytes::Bytes;
 use creep::Context;
-use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
-use futures::{select, StreamExt};
+use futures::channel::mpsc::{
+    channel, unbounded, Receiver, Sender, UnboundedReceiver, UnboundedSender,
+};
+use futures::channel::oneshot;
+use futures::{future, select, FutureExt, SinkExt, StreamExt};
 use hummer::coding::hex_encode;
+#[cfg(feature = "apm")]
 use muta_apm::derive::tracing_span;
-use tokio::time::sleep;
-
-use crate::error::ConsensusError;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, sleep_until};
+
+use crate::aux_store::AuxStore;
+use crate::codec::domain_payload;
+use crate::crash::CrashRecorder;
+use crate::error::{ConsensusError, ErrorReport, Missing};
+use crate::metrics::{
+    CacheEvictions, CheckTasks, ClockSkew, DropCount, LatencyRecorder, StateBranch, StatusCounts,
+};
 use crate::smr::smr_types::{FromWhere, SMREvent, SMRTrigger, Step, TriggerSource, TriggerType};
 use crate::smr::{Event, SMRHandler};
-use crate::state::collection::{ChokeCollector, ProposalCollector, VoteCollector};
-use crate::state::parallel::parallel_verify;
+use crate::state::artifacts::ArtifactStore;
+use crate::state::collection::{
+    ChokeCollector, PartCollector, ProposalCollector, ProposalItem, VoteCollector,
+};
+use crate::state::debug::DebugTargets;
+use crate::state::parallel::{
+    check_choke_qc, check_qc, check_signed_choke, drain_verified_votes, parallel_verify, VerifyPool,
+};
+use crate::state::sign_guard::{SignGuard, SignKind};
+use crate::state::watchdog::StallTracker;
+use crate::transport::PeerId;
 use crate::types::{
-    Address, AggregatedChoke, AggregatedSignature, AggregatedVote, Choke, Commit, Hash, Node,
-    OverlordMsg, PoLC, Proof, Proposal, Signature, SignedChoke, SignedProposal, SignedVote, Status,
-    UpdateFrom, VerifyResp, ViewChangeReason, Vote, VoteType,
+    split_vote_batch, AbandonedWork, Address, AggregateResp, AggregatedChoke, AggregatedSignature,
+    AggregatedVote, BlockPart, Choke, Commit, CompactProposal, ConsensusStatus, ControlMsg,
+    Decision, DropReason, FetchResp, Hash, Height, HeightArtifacts, HeightCancel, MessageDrop,
+    Node, OverlordMsg, PartSetHeader, PoLC, Proof, Proposal, PullProposal, PullQC, PullVotes,
+    QcKind, Round, RoundCancel, RoundSummary, Signature, SignedChoke, SignedCompactProposal,
+    SignedProposal, SignedVote, StallDiagnostics, StateSnapshot, Status, UpdateFrom, VerifyResp,
+    ViewChangeReason, Vote, VoteExtension, VoteParticipation, VoteTally, VoteType, VoterExtension,
+    Vrf, HEIGHT_CANCEL_KEY, ROUND_CANCEL_KEY,
+};
+use crate::utils::auth_manage::{
+    check_authority_size, check_authority_weights, check_threshold_weights, AuthorityManage,
+};
+use crate::utils::lru::LruCache;
+use crate::utils::merkle::{merkle_proof, merkle_root};
+use crate::wal::{decode_wal_record, encode_wal_record, SMRBase, WalInfo, WalLock, WalWriter};
+use crate::{
+    ArtifactSink, BlockProvider, Codec, Consensus, ConsensusResult, Crypto, DurationConfig,
+    Network, NoopReporter, OverlordConfig, ProofStore, Reporter, Wal, INIT_HEIGHT, INIT_ROUND,
 };
-use crate::utils::auth_manage::AuthorityManage;
-use crate::wal::{SMRBase, WalInfo, WalLock};
-use crate::{Codec, Consensus, ConsensusResult, Crypto, Wal, INIT_HEIGHT, INIT_ROUND};
-
-const FUTURE_HEIGHT_GAP: u64 = 5;
-const FUTURE_ROUND_GAP: u64 = 10;
 
 /// Overlord state struct. It maintains the local state of the node, and monitor the SMR event. The
 /// `proposals` is used to cache the signed proposals that are with higher height or round. The
@@ -38,28 +68,86 @@ const FUTURE_ROUND_GAP: u64 = 10;
 /// than `current_height - 1`.
 #[derive(Debug)]
 pub struct State<T: Codec, F: Consensus<T>, C: Crypto, W: Wal> {
-    height: u64,
-    round: u64,
+    height: Height,
+    round: Round,
+    step: Step,
+    lock_round: Option<Round>,
+    previous_round: Round,
     state_machine: SMRHandler,
     address: Address,
     proposals: ProposalCollector<T>,
     votes: VoteCollector,
     chokes: ChokeCollector,
     authority: AuthorityManage,
-    hash_with_block: HashMap<Hash, T>,
-    is_full_transaction: HashMap<Hash, bool>,
+    hash_with_block: LruCache<Hash, Arc<T>>,
+    is_full_transaction: LruCache<Hash, bool>,
     is_leader: bool,
     leader_address: Address,
     update_from_where: UpdateFrom,
     height_start: Instant,
+    round_start: Instant,
     block_interval: u64,
     consensus_power: bool,
     stopped: bool,
-
-    verify_sig_tx: UnboundedSender<(Context, OverlordMsg<T>)>,
-    resp_tx: UnboundedSender<VerifyResp>,
+    aggregating: HashSet<(Height, Round, VoteType)>,
+    latency: LatencyRecorder,
+    sign_guard: SignGuard,
+    halt_on_double_sign: bool,
+    halted: bool,
+    paused: bool,
+    vrf_proposer: bool,
+    vrf_seed: Hash,
+    /// The verified VRF outputs of the proposers of the current height. The output of a proposer
+    /// is unique for a height, whichever round it proposes in.
+    vrf_outputs: HashMap<Address, Vrf>,
+    artifacts: ArtifactStore<T>,
+    check_tasks: Vec<(Height, Round, Hash, JoinHandle<()>)>,
+    check_queue: VecDeque<(Context, Height, Round, Hash, Arc<T>)>,
+    fetch_tasks: Vec<(Height, JoinHandle<()>)>,
+    height_cancel: HeightCancel,
+    round_cancel: RoundCancel,
+    forced_round: Option<Round>,
+    commit_subscribers: Vec<Sender<Commit<T>>>,
+    max_check_tasks: usize,
+    max_clock_skew: Option<Duration>,
+    clock_skews: BTreeMap<Address, ClockSkew>,
+    check_block_timeout: Option<Duration>,
+    compact_proposal: bool,
+    threshold_qc: bool,
+    inline_aggregation: bool,
+    optimistic_step: bool,
+    block_part_size: usize,
+    parts: PartCollector,
+    last_status: Option<Status>,
+    drops: BTreeMap<(String, DropReason), u64>,
+    debug: DebugTargets,
+    duplicate_statuses: u64,
+    stale_statuses: u64,
+    check_spawned: u64,
+    check_cancelled: u64,
+    max_authority_size: usize,
+    future_height_gap: u64,
+    future_round_gap: u64,
+    crash_recorder: Option<Arc<CrashRecorder>>,
+    reporter: Arc<dyn Reporter>,
+    stall: StallTracker,
+    chain_id: Bytes,
+
+    verify_sig_tx: Sender<(Context, OverlordMsg<T>)>,
+    verify_pool: Option<VerifyPool>,
+    vote_batch_size: usize,
+    wal_votes: bool,
+    verify_height_jump: bool,
+    qc_relay: bool,
+    resp_tx: Sender<VerifyResp>,
+    aggregate_tx: UnboundedSender<(Context, AggregateResp)>,
+    fetch_tx: UnboundedSender<(Context, FetchResp<T>)>,
     function: Arc<F>,
-    wal: Arc<W>,
+    network: Arc<dyn Network<T>>,
+    block_provider: Arc<dyn BlockProvider<T>>,
+    artifact_sink: Option<Arc<dyn ArtifactSink<T>>>,
+    proof_store: Option<Arc<dyn ProofStore>>,
+    wal: WalWriter<W>,
     util: Arc<C>,
 }
 
@@ -68,90 +156,226 @@ where
     T: Codec + 'static,
     F: Consensus<T> + 'static,
     C: Crypto + Sync + 'static,
-    W: Wal,
+    W: Wal + Sync,
 {
     /// Create a new state struct.
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
     pub(crate) fn new(
         smr: SMRHandler,
         addr: Address,
-        init_height: u64,
+        init_height: Height,
         interval: u64,
         mut authority_list: Vec<Node>,
-        verify_tx: UnboundedSender<(Context, OverlordMsg<T>)>,
+        verify_tx: Sender<(Context, OverlordMsg<T>)>,
         consensus: Arc<F>,
         crypto: Arc<C>,
         wal_engine: Arc<W>,
-    ) -> (Self, UnboundedReceiver<VerifyResp>) {
-        let (tx, rx) = unbounded();
-        let mut auth = AuthorityManage::new();
-        auth.update(&mut authority_list);
+        aux_store: Arc<dyn AuxStore>,
+        config: &OverlordConfig,
+    ) -> ConsensusResult<(
+        Self,
+        Receiver<VerifyResp>,
+        UnboundedReceiver<(Context, AggregateResp)>,
+        UnboundedReceiver<(Context, FetchResp<T>)>,
+    )> {
+        if !aux_store.is_durable() {
+            log::warn!(
+                "Overlord: the auxiliary store is not durable, the votes signed by self are lost \
+                 on restart and do not guard against double signing"
+            );
+        }
+
+        let (tx, rx) = channel(config.verify_channel_capacity);
+        // The aggregation responses are no more than the QCs in building, so it is unbounded.
+        let (aggregate_tx, aggregate_rx) = unbounded();
+        // The fetched blocks are no more than the fetch tasks of the current height.
+        let (fetch_tx, fetch_rx) = unbounded();
+        let mut auth = AuthorityManage::with_retention(config.authority_retention);
+        auth.transit(init_height, &mut authority_list)?;
 
         let state = State {
             height: init_height,
             round: INIT_ROUND,
+            step: Step::default(),
+            lock_round: None,
+            previous_round: INIT_ROUND,
             state_machine: smr,
             consensus_power: auth.contains(&addr),
             address: addr,
-            proposals: ProposalCollector::new(),
-            votes: VoteCollector::new(),
+            proposals: ProposalCollector::new(config.proposal_cache_capacity),
+            votes: VoteCollector::new(config.vote_round_capacity),
             chokes: ChokeCollector::new(),
             authority: auth,
-            hash_with_block: HashMap::new(),
-            is_full_transaction: HashMap::new(),
+            hash_with_block: LruCache::new(config.block_cache_capacity),
+            is_full_transaction: LruCache::new(config.block_cache_capacity),
             is_leader: false,
             leader_address: Address::default(),
             update_from_where: UpdateFrom::PrecommitQC(mock_init_qc()),
             height_start: Instant::now(),
+            round_start: Instant::now(),
             block_interval: interval,
             stopped: false,
+            aggregating: HashSet::new(),
+            latency: LatencyRecorder::new(),
+            sign_guard: SignGuard::new(Arc::clone(&aux_store))?,
+            reporter: Arc::new(NoopReporter),
+            crash_recorder: config
+                .panic_snapshot
+                .then(|| Arc::new(CrashRecorder::new(Arc::clone(&aux_store)))),
+            halt_on_double_sign: config.halt_on_double_sign,
+            halted: false,
+            paused: false,
+            vrf_proposer: config.vrf_proposer,
+            vrf_seed: Hash::new(),
+            vrf_outputs: HashMap::new(),
+            artifacts: ArtifactStore::new(config.artifact_retention, aux_store)?,
+            check_tasks: Vec::new(),
+            fetch_tasks: Vec::new(),
+            height_cancel: HeightCancel::default(),
+            round_cancel: RoundCancel::default(),
+            forced_round: None,
+            commit_subscribers: Vec::new(),
+            check_queue: VecDeque::new(),
+            max_check_tasks: config.max_check_tasks.max(1),
+            max_clock_skew: Some(config.max_clock_skew)
+                .filter(|skew| *skew > 0)
+                .map(Duration::from_millis),
+            clock_skews: BTreeMap::new(),
+            check_block_timeout: Some(config.check_block_timeout)
+                .filter(|timeout| *timeout > 0)
+                .map(Duration::from_millis),
+            compact_proposal: config.compact_proposal,
+            threshold_qc: config.threshold_qc,
+            inline_aggregation: config.inline_aggregation,
+            optimistic_step: config.optimistic_step,
+            block_part_size: config.block_part_size,
+            parts: PartCollector::new(),
+            last_status: None,
+            drops: BTreeMap::new(),
+            debug: DebugTargets::new(),
+            duplicate_statuses: 0,
+            stale_statuses: 0,
+            check_spawned: 0,
+            check_cancelled: 0,
+            max_authority_size: config.max_authority_size,
+            future_height_gap: config.future_height_gap,
+            future_round_gap: config.future_round_gap,
+            stall: StallTracker::new(config.stall_watchdog.clone()),
+            chain_id: config.chain_id.clone(),
 
             verify_sig_tx: verify_tx,
+            verify_pool: match config.verify_workers {
+                0 => None,
+                workers => Some(VerifyPool::new(workers, config.verify_channel_capacity)?),
+            },
+            vote_batch_size: config.vote_batch_size,
+            wal_votes: config.wal_votes,
+            verify_height_jump: config.verify_height_jump,
+            qc_relay: config.qc_relay,
             resp_tx: tx,
+            aggregate_tx,
+            fetch_tx,
+            network: Arc::clone(&consensus) as Arc<dyn Network<T>>,
+            block_provider: Arc::clone(&consensus) as Arc<dyn BlockProvider<T>>,
+            artifact_sink: None,
+            proof_store: None,
             function: consensus,
             util: crypto,
-            wal: wal_engine,
+            wal: WalWriter::new(wal_engine, config.wal_policy.clone()),
         };
 
-        (state, rx)
+        state.rotate_keys(&authority_list);
+        Ok((state, rx, aggregate_rx, fetch_rx))
+    }
+
+    pub(crate) fn set_reporter(&mut self, reporter: Arc<dyn Reporter>) {
+        self.reporter = reporter;
+    }
+
+    pub(crate) fn set_network(&mut self, network: Arc<dyn Network<T>>) {
+        self.network = network;
+    }
+
+    pub(crate) fn set_block_provider(&mut self, block_provider: Arc<dyn BlockProvider<T>>) {
+        self.block_provider = block_provider;
+    }
+
+    pub(crate) fn set_artifact_sink(&mut self, sink: Arc<dyn ArtifactSink<T>>) {
+        self.artifact_sink = Some(sink);
+    }
+
+    pub(crate) fn set_proof_store(&mut self, proof_store: Arc<dyn ProofStore>) {
+        self.proof_store = Some(proof_store);
+    }
+
+    /// The recorder of the crash snapshot if the panic hook is enabled.
+    pub(crate) fn crash_recorder(&self) -> Option<Arc<CrashRecorder>> {
+        self.crash_recorder.clone()
     }
 
     /// Run state module.
     pub(crate) async fn run(
         &mut self,
-        mut raw_rx: UnboundedReceiver<(Context, OverlordMsg<T>)>,
+        mut raw_rx: Receiver<(Context, OverlordMsg<T>)>,
+        mut priority_rx: UnboundedReceiver<(Context, OverlordMsg<T>)>,
+        mut ctrl_rx: UnboundedReceiver<ControlMsg<T>>,
+        mut trusted_rx: Receiver<(Context, OverlordMsg<T>)>,
         mut event: Event,
-        mut verify_resp: UnboundedReceiver<VerifyResp>,
-        mut verify_sig: UnboundedReceiver<(Context, OverlordMsg<T>)>,
+        mut verify_resp: Receiver<VerifyResp>,
+        mut verify_sig: Receiver<(Context, OverlordMsg<T>)>,
+        mut aggregate_resp: UnboundedReceiver<(Context, AggregateResp)>,
+        mut fetch_resp: UnboundedReceiver<(Context, FetchResp<T>)>,
     ) {
         log::debug!("Overlord: state start running");
         if let Err(e) = self.start_with_wal().await {
             log::error!("Overlord: start with wal error {:?}", e);
         }
 
+        let mut exited = None;
+        let mut watchdog = self.stall.check_period().map(tokio::time::interval);
         loop {
             select! {
                 raw = raw_rx.next() => {
+                    let start = Instant::now();
                     let (ctx, msg) = raw.expect("Overlord message handler dropped");
+                    self.handle_raw_msg(ctx, msg).await;
+                    self.latency.record(StateBranch::RawMsg, start.elapsed());
+                }
+
+                priority = priority_rx.next() => {
+                    let start = Instant::now();
+                    let (ctx, msg) = priority.expect("Overlord message handler dropped");
+                    self.handle_raw_msg(ctx, msg).await;
+                    self.latency.record(StateBranch::PriorityMsg, start.elapsed());
+                }
+
+                trusted = trusted_rx.next() => {
+                    if let Some((ctx, msg)) = trusted {
+                        let start = Instant::now();
+                        self.handle_trusted_msg(ctx, msg).await;
+                        self.latency.record(StateBranch::TrustedMsg, start.elapsed());
+                    }
+                }
 
-                    if msg.is_rich_status() {
-                        let _ = self.verify_sig_tx.unbounded_send((ctx, msg));
-                    } else {
-                        match self.height.cmp(&msg.get_height()) {
-                            Ordering::Less => {
-                                let _ = self.verify_sig_tx.unbounded_send((ctx, msg));
-                            }
-                            Ordering::Equal => {
-                                parallel_verify(
-                                    ctx,
-                                    msg,
-                                    Arc::clone(&self.util),
-                                    self.authority.clone(),
-                                    self.verify_sig_tx.clone()
-                                )
-                                .await;
-                            }
-                            Ordering::Greater => (),
-                        };
+                ctrl = ctrl_rx.next() => {
+                    let start = Instant::now();
+                    let shutdown = match ctrl {
+                        Some(msg) => self.handle_control(msg).await,
+                        None => None,
+                    };
+                    self.latency.record(StateBranch::Control, start.elapsed());
+                    if let Some(tx) = shutdown {
+                        // Close the channels from the handler before the internal ones.
+                        raw_rx.close();
+                        priority_rx.close();
+                        trusted_rx.close();
+                        ctrl_rx.close();
+                        verify_sig.close();
+                        verify_resp.close();
+                        aggregate_resp.close();
+                        fetch_resp.close();
+                        exited = Some(tx);
+                        break;
                     }
                 }
 
@@ -164,9 +388,11 @@ where
                         continue;
                     }
 
+                    let start = Instant::now();
                     if let Err(e) = self.handle_event(evt).await{
                         log::error!("Overlord: state {:?} error", e);
                     }
+                    self.latency.record(StateBranch::Event, start.elapsed());
                 }
 
                 res = verify_resp.next() => {
@@ -174,24 +400,306 @@ where
                         continue;
                     }
 
+                    let start = Instant::now();
                     if let Err(e) = self.handle_resp(res) {
                         log::error!("Overlord: state {:?} error", e);
                     }
+                    self.latency.record(StateBranch::VerifyResp, start.elapsed());
+                }
+
+                aggregated = aggregate_resp.next() => {
+                    let start = Instant::now();
+                    let (ctx, resp) = aggregated.expect("Overlord aggregation sender dropped");
+                    if let Err(e) = self.handle_aggregate_resp(ctx, resp).await {
+                        log::error!("Overlord: state {:?} error", e);
+                    }
+                    self.latency.record(StateBranch::AggregateResp, start.elapsed());
+                }
+
+                fetched = fetch_resp.next() => {
+                    let start = Instant::now();
+                    let (ctx, resp) = fetched.expect("Overlord fetch sender dropped");
+                    if let Err(e) = self.handle_fetch_resp(ctx, resp).await {
+                        log::error!("Overlord: state handle fetch response error {:?}", e);
+                    }
+                    self.latency.record(StateBranch::FetchResp, start.elapsed());
                 }
 
                 verified_msg = verify_sig.next() => {
+                    let start = Instant::now();
                     let (ctx, msg) = verified_msg.expect("Overlord message handler dropped");
-                    if let Err(e) = self.handle_msg(ctx.clone(), msg).await {
-                        self.report_error(ctx, e.clone());
-                        log::error!("Overlord: state {:?} error", e);
+                    let (msg, rest) =
+                        drain_verified_votes(msg, &mut verify_sig, self.vote_batch_size);
+                    for (ctx, msg) in iter::once((ctx, msg)).chain(rest) {
+                        let sender = msg.sender().cloned();
+                        if let Err(e) = self.handle_msg(ctx.clone(), msg).await {
+                            self.report_error(ctx, e.clone(), sender);
+                            log::error!("Overlord: state {:?} error", e);
+                        }
+                    }
+                    self.latency.record(StateBranch::VerifiedMsg, start.elapsed());
+                }
+
+                _ = Box::pin(async {
+                    match watchdog.as_mut() {
+                        Some(ticker) => {
+                            ticker.tick().await;
+                        }
+                        None => future::pending::<()>().await,
+                    }
+                }).fuse() => self.check_stalled(),
+            }
+        }
+
+        log::info!("Overlord: state exit");
+        if let Some(tx) = exited {
+            let _ = tx.send(());
+        }
+    }
+
+    /// Drain the state before it exits. Save a final wal entry of the current step and lock,
+    /// cancel the outstanding check block tasks and stop the SMR and the timer.
+    async fn shutdown(&mut self) {
+        log::info!(
+            "Overlord: state shutdown height {}, round {}",
+            self.height,
+            self.round
+        );
+
+        if self.consensus_power {
+            if let Err(e) = self
+                .save_wal_with_lock_round(self.step.clone(), self.lock_round)
+                .await
+            {
+                log::error!("Overlord: state save final wal error {:?}", e);
+            }
+        }
+        if let Err(e) = self.wal.flush().await {
+            log::error!("Overlord: state flush wal error {:?}", e);
+        }
+
+        for (_, _, _, task) in self.check_tasks.drain(..) {
+            task.abort();
+        }
+        self.check_queue.clear();
+
+        if let Err(e) = self.state_machine.trigger(SMRTrigger {
+            trigger_type: TriggerType::Stop,
+            source: TriggerSource::State,
+            hash: Hash::new(),
+            lock_round: None,
+            round: self.round,
+            height: self.height,
+            wal_info: None,
+        }) {
+            log::error!("Overlord: state stop SMR error {:?}", e);
+        }
+        self.stopped = true;
+    }
+
+    /// Dispatch a message from the overlord handler. Rich status and stop messages are handled
+    /// directly. Messages of the current height are sent to verify signatures, and messages of
+    /// future heights are cached without verification. A future height message is dropped if the
+    /// verified message channel is full, since it will be broadcast again by the peers.
+    async fn handle_raw_msg(&mut self, ctx: Context, msg: OverlordMsg<T>) {
+        if msg.is_priority() {
+            let sender = msg.sender().cloned();
+            if let Err(e) = self.handle_msg(ctx.clone(), msg).await {
+                self.report_error(ctx, e.clone(), sender);
+                log::error!("Overlord: state {:?} error", e);
+            }
+            return;
+        }
+
+        // The votes of a batch may be of different heights, each height is dispatched apart.
+        if let OverlordMsg::VoteBatch(votes) = msg {
+            for votes in split_vote_batch(votes).into_iter() {
+                self.dispatch_msg(ctx.clone(), OverlordMsg::VoteBatch(votes))
+                    .await;
+            }
+            return;
+        }
+        self.dispatch_msg(ctx, msg).await;
+    }
+
+    async fn dispatch_msg(&mut self, ctx: Context, msg: OverlordMsg<T>) {
+        if self.debug.matches(msg.get_height(), msg.sender()) {
+            log::info!(
+                target: "overlord::debug",
+                "Overlord: debug receive {} height {}, from {:?}, self height {}, round {}",
+                msg,
+                msg.get_height(),
+                msg.sender().map(hex_encode),
+                self.height,
+                self.round
+            );
+        }
+
+        // A QC request carries no signature, it is served directly.
+        if let OverlordMsg::PullQC(pull) = msg {
+            let requester = pull.requester.clone();
+            if let Err(e) = self.handle_pull_qc(ctx.clone(), pull).await {
+                self.report_error(ctx, e.clone(), Some(requester));
+                log::error!("Overlord: state handle pull QC error {:?}", e);
+            }
+            return;
+        }
+
+        // A proposal request carries no signature either, the pulled proposal verifies itself.
+        if let OverlordMsg::PullProposal(pull) = msg {
+            let requester = pull.requester.clone();
+            if let Err(e) = self.handle_pull_proposal(ctx.clone(), pull).await {
+                self.report_error(ctx, e.clone(), Some(requester));
+                log::error!("Overlord: state handle pull proposal error {:?}", e);
+            }
+            return;
+        }
+
+        // A votes request carries no signature either, the pulled votes verify themselves.
+        if let OverlordMsg::PullVotes(pull) = msg {
+            let requester = pull.requester.clone();
+            if let Err(e) = self.handle_pull_votes(ctx.clone(), pull).await {
+                self.report_error(ctx, e.clone(), Some(requester));
+                log::error!("Overlord: state handle pull votes error {:?}", e);
+            }
+            return;
+        }
+
+        // A block part carries no signature, it is verified by the header of its proposal.
+        if let OverlordMsg::BlockPart(part) = msg {
+            self.handle_block_part(ctx, part).await;
+            return;
+        }
+
+        match self.height.cmp(&msg.get_height()) {
+            Ordering::Less => {
+                // The messages of the next height are verified ahead if its authority list is
+                // announced, otherwise they are verified after self reaches the height.
+                if let Some(authority) = self.authority.announced(msg.get_height()) {
+                    parallel_verify(
+                        ctx,
+                        msg,
+                        Arc::clone(&self.util),
+                        self.chain_id.clone(),
+                        authority,
+                        self.verify_sig_tx.clone(),
+                        self.verify_pool.as_ref(),
+                    )
+                    .await;
+                    return;
+                }
+                self.cache_future_msg(ctx, msg).await;
+            }
+            Ordering::Equal => {
+                parallel_verify(
+                    ctx,
+                    msg,
+                    Arc::clone(&self.util),
+                    self.chain_id.clone(),
+                    self.authority.snapshot(),
+                    self.verify_sig_tx.clone(),
+                    self.verify_pool.as_ref(),
+                )
+                .await;
+            }
+            Ordering::Greater => {
+                // The votes and QCs of the previous height are verified with the authority list of
+                // that height, which differs from the current one across a validator set change.
+                // They are kept to serve the peers which pull them.
+                let height = msg.get_height();
+                let is_vote = matches!(
+                    msg,
+                    OverlordMsg::SignedVote(_)
+                        | OverlordMsg::VoteBatch(_)
+                        | OverlordMsg::AggregatedVote(_)
+                );
+                if is_vote && height.saturating_add(1) == self.height {
+                    if let Some(authority) = self.authority.at_height(height) {
+                        parallel_verify(
+                            ctx,
+                            msg,
+                            Arc::clone(&self.util),
+                            self.chain_id.clone(),
+                            authority,
+                            self.verify_sig_tx.clone(),
+                            self.verify_pool.as_ref(),
+                        )
+                        .await;
+                        return;
                     }
                 }
+                self.record_drop(&msg.to_string(), height, None, DropReason::OutdatedHeight);
+            }
+        };
+    }
+
+    /// Cache a message of a future height of which the authority list is unknown yet. It is cached
+    /// unverified on the state task rather than sent over the verified message channel, so that it
+    /// is never taken as a verified message of the current height once self reaches its height.
+    /// The cached messages of a height are verified when self reaches it, see `goto_new_height`,
+    /// and nothing is derived from them before, so a cached QC is not checked for a fork.
+    async fn cache_future_msg(&mut self, ctx: Context, msg: OverlordMsg<T>) {
+        if !self.consensus_power {
+            return;
+        }
+
+        match msg {
+            OverlordMsg::AggregatedVote(qc) => {
+                if !self.filter_message("Aggregated Vote", qc.height, qc.round) {
+                    log::debug!(
+                        "Overlord: state cache a future QC, height {}, round {}",
+                        qc.height,
+                        qc.round,
+                    );
+                    self.votes.set_qc(qc);
+                }
+            }
+            OverlordMsg::SignedVote(sv) => self.cache_future_vote(ctx, sv),
+            OverlordMsg::VoteBatch(votes) => {
+                for sv in votes.into_iter() {
+                    self.cache_future_vote(ctx.clone(), sv);
+                }
             }
+            msg => {
+                let sender = msg.sender().cloned();
+                if let Err(e) = self.handle_msg(ctx.clone(), msg).await {
+                    self.report_error(ctx, e.clone(), sender);
+                    log::error!("Overlord: state cache future message error {:?}", e);
+                }
+            }
+        }
+    }
+
+    /// Cache an unverified vote of a future height, see `cache_future_msg`. Unlike a verified vote,
+    /// a vote of self is not recorded in the sign guard, nor is its extension checked.
+    fn cache_future_vote(&mut self, ctx: Context, sv: SignedVote) {
+        if self.filter_message("Signed Vote", sv.get_height(), sv.get_round()) {
+            return;
+        }
+        // The weight is recounted with the authority list of the height, see `goto_new_height`.
+        let weight = match self.authority.get_vote_weight(&sv.voter) {
+            Ok(weight) => *weight,
+            Err(_) => return,
+        };
+        let (hash, voter) = (sv.get_hash(), sv.voter.clone());
+        self.votes.insert_vote(ctx, hash, sv, voter, weight);
+    }
+
+    /// Handle a message from the trusted intake as a verified one.
+    async fn handle_trusted_msg(&mut self, ctx: Context, msg: OverlordMsg<T>) {
+        let sender = msg.sender().cloned();
+        if let Err(e) = self.handle_msg(ctx.clone(), msg).await {
+            self.report_error(ctx, e.clone(), sender);
+            log::error!("Overlord: state {:?} error", e);
         }
     }
 
     /// A function to handle message from the network. Public this in the crate to do unit tests.
-    #[tracing_span(kind = "overlord")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "overlord.handle_msg", skip_all, fields(kind = "overlord"))
+    )]
+    #[cfg_attr(feature = "apm", tracing_span(kind = "overlord"))]
     pub(crate) async fn handle_msg(
         &mut self,
         ctx: Context,
@@ -203,7 +711,7 @@ where
 
         match raw {
             OverlordMsg::SignedProposal(sp) => {
-                if let Err(e) = self.handle_signed_proposal(ctx.clone(), sp).await {
+                if let Err(e) = self.handle_signed_proposal(ctx.clone(), sp, None).await {
                     log::error!("Overlord: state handle signed proposal error {:?}", e);
                 }
                 Ok(())
@@ -223,6 +731,13 @@ where
                 Ok(())
             }
 
+            OverlordMsg::VoteBatch(votes) => {
+                if let Err(e) = self.handle_vote_batch(ctx.clone(), votes).await {
+                    log::error!("Overlord: state handle vote batch error {:?}", e);
+                }
+                Ok(())
+            }
+
             OverlordMsg::SignedChoke(sc) => {
                 if let Err(e) = self.handle_signed_choke(ctx.clone(), sc).await {
                     log::error!("Overlord: state handle signed choke error {:?}", e);
@@ -230,6 +745,33 @@ where
                 Ok(())
             }
 
+            OverlordMsg::SignedCompactProposal(sp) => {
+                if sp.proposal.parts.is_some() {
+                    self.handle_part_header(ctx, sp).await;
+                } else {
+                    self.fetch_proposal_block(ctx, sp).await;
+                }
+                Ok(())
+            }
+
+            OverlordMsg::BlockPart(part) => {
+                self.handle_block_part(ctx, part).await;
+                Ok(())
+            }
+
+            OverlordMsg::PullQC(pull) => self.handle_pull_qc(ctx, pull).await,
+
+            OverlordMsg::PullProposal(pull) => self.handle_pull_proposal(ctx, pull).await,
+
+            OverlordMsg::PullVotes(pull) => self.handle_pull_votes(ctx, pull).await,
+
+            OverlordMsg::RoundSummary(summary) => {
+                if let Err(e) = self.handle_round_summary(summary) {
+                    log::error!("Overlord: state handle round summary error {:?}", e);
+                }
+                Ok(())
+            }
+
             OverlordMsg::RichStatus(rs) => {
                 if let Err(e) = self.goto_new_height(ctx.clone(), rs).await {
                     log::error!("Overlord: state handle rich status error {:?}", e);
@@ -257,99 +799,548 @@ where
         }
     }
 
-    /// A function to handle event from the SMR. Public this function in the crate to do unit tests.
-    pub(crate) async fn handle_event(&mut self, event: Option<SMREvent>) -> ConsensusResult<()> {
-        match event.ok_or_else(|| ConsensusError::Other("Event sender dropped".to_string()))? {
-            SMREvent::NewRoundInfo {
-                round,
-                lock_round,
-                lock_proposal,
-                from_where,
-                ..
-            } => {
-                if let Err(e) = self
-                    .handle_new_round(round, lock_round, lock_proposal, from_where)
-                    .await
-                {
-                    log::error!("Overlord: state handle new round error {:?}", e);
-                }
-                Ok(())
+    /// Handle a control message. Return the responder of a shutdown once the state is drained, on
+    /// which the state loop exits.
+    async fn handle_control(&mut self, msg: ControlMsg<T>) -> Option<oneshot::Sender<()>> {
+        match msg {
+            ControlMsg::QueryStatus(tx) => {
+                let _ = tx.send(self.consensus_status());
             }
-
-            SMREvent::PrevoteVote {
-                block_hash,
-                lock_round,
-                ..
-            } => {
-                if let Err(e) = self
-                    .handle_vote_event(block_hash, VoteType::Prevote, lock_round)
-                    .await
-                {
-                    log::error!("Overlord: state handle prevote vote error {:?}", e);
-                }
-                Ok(())
+            ControlMsg::QueryMetrics(tx) => {
+                let mut metrics = self.latency.metrics();
+                metrics.evictions = CacheEvictions {
+                    blocks: self.hash_with_block.evictions(),
+                    full_transactions: self.is_full_transaction.evictions(),
+                    proposals: self.proposals.evictions(),
+                    vote_rounds: self.votes.evictions(),
+                };
+                self.check_tasks
+                    .retain(|(_, _, _, task)| !task.is_finished());
+                metrics.check_tasks = CheckTasks {
+                    running: self.check_tasks.len() as u64,
+                    queued: self.check_queue.len() as u64,
+                    spawned: self.check_spawned,
+                    cancelled: self.check_cancelled,
+                };
+                metrics.statuses = StatusCounts {
+                    duplicate: self.duplicate_statuses,
+                    stale: self.stale_statuses,
+                };
+                metrics.clock_skews = self.clock_skews.values().cloned().collect();
+                metrics.wal_coalesced = self.wal.coalesced();
+                metrics.drops = self
+                    .drops
+                    .iter()
+                    .map(|((msg_type, reason), count)| DropCount {
+                        msg_type: msg_type.clone(),
+                        reason: *reason,
+                        count: *count,
+                    })
+                    .collect();
+                let _ = tx.send(metrics);
             }
-
-            SMREvent::PrecommitVote {
-                block_hash,
-                lock_round,
-                ..
-            } => {
-                if let Err(e) = self
-                    .handle_vote_event(block_hash, VoteType::Precommit, lock_round)
-                    .await
-                {
-                    log::error!("Overlord: state handle precommit vote error {:?}", e);
-                }
-                Ok(())
+            ControlMsg::ExportArtifacts(height, tx) => {
+                let _ = tx.send(self.export_artifacts(height));
             }
-
-            SMREvent::Commit(hash) => {
-                if let Err(e) = self.handle_commit(hash).await {
-                    log::error!("Overlord: state handle commit error {:?}", e);
-                }
-                Ok(())
+            ControlMsg::ExportSnapshot(tx) => {
+                let _ = tx.send(self.export_snapshot());
+            }
+            ControlMsg::ImportSnapshot(snapshot, tx) => {
+                let _ = tx.send(self.import_snapshot(*snapshot).await);
+            }
+            ControlMsg::InjectQC(qc, tx) => {
+                let _ = tx.send(self.inject_qc(qc).await);
+            }
+            ControlMsg::Shutdown(tx) => {
+                self.shutdown().await;
+                return Some(tx);
+            }
+            ControlMsg::SetPaused(paused, tx) => {
+                log::info!("Overlord: state set paused {}", paused);
+                self.paused = paused;
+                let _ = tx.send(());
+            }
+            ControlMsg::SetDebugTarget(target, ttl, tx) => {
+                log::info!("Overlord: state debug {} for {:?}", target, ttl);
+                self.debug.set(target, ttl);
+                let _ = tx.send(());
+            }
+            ControlMsg::ClearDebugTargets(tx) => {
+                self.debug.clear();
+                let _ = tx.send(());
+            }
+            ControlMsg::UpdateTimerConfig(config, tx) => {
+                let _ = tx.send(self.update_timer_config(config));
+            }
+            ControlMsg::QueryProof(height, tx) => {
+                let _ = tx.send(self.query_proof(height));
+            }
+            ControlMsg::QueryAuthority(height, tx) => {
+                let authority = self.authority.at_height(height);
+                let _ = tx.send(authority.map(|authority| authority.get_authority_list()));
+            }
+            ControlMsg::SubscribeCommits(tx) => self.commit_subscribers.push(tx),
+            ControlMsg::ForceViewChange(tx) => {
+                let _ = tx.send(self.force_view_change());
+            }
+            ControlMsg::QueryProposer(height, round, tx) => {
+                let proposer = self
+                    .schedule_authority(height)
+                    .and_then(|authority| self.proposer_of(&authority, height, round).ok());
+                let _ = tx.send(proposer);
+            }
+            ControlMsg::QueryRotation(height, tx) => {
+                let rotation = self.schedule_authority(height).and_then(|authority| {
+                    (0..authority.rotation_len() as u64)
+                        .map(|round| self.proposer_of(&authority, height, Round(round)).ok())
+                        .collect::<Option<Vec<_>>>()
+                });
+                let _ = tx.send(rotation);
             }
+        }
+        None
+    }
 
-            SMREvent::Brake {
-                height,
-                round,
-                lock_round,
-            } => {
-                if height != self.height {
-                    return Ok(());
-                }
+    /// End the current round as on its timeout, which chokes and moves on once the chokes of the
+    /// round are above the threshold.
+    fn force_view_change(&mut self) -> ConsensusResult<()> {
+        if !self.consensus_power {
+            return Err(ConsensusError::StateErr(
+                "force view change without the consensus power".to_string(),
+            ));
+        }
 
-                if let Err(e) = self.handle_brake(round, lock_round).await {
-                    log::error!("Overlord: state handle brake error {:?}", e);
-                }
-                Ok(())
-            }
+        log::warn!(
+            "Overlord: state force view change, height {}, round {}",
+            self.height,
+            self.round
+        );
+        self.forced_round = Some(self.round);
+        self.state_machine.force_brake(self.height, self.round)
+    }
 
-            _ => unreachable!(),
+    /// The authority list to verify the messages and QCs of the height, which is one of the kept
+    /// previous authority lists for a previous height, and the announced one if any for a future
+    /// height.
+    fn verify_authority(&self, height: Height) -> ConsensusResult<AuthorityManage> {
+        self.authority
+            .at_height(height)
+            .ok_or_else(|| ConsensusError::AuthorityNotKeptErr(height))
+    }
+
+    /// The authority list which schedules the proposers of the height, or `None` if it is not
+    /// kept, or if the VRF seed of the height is unknown in the VRF proposer mode.
+    fn schedule_authority(&self, height: Height) -> Option<AuthorityManage> {
+        if self.vrf_proposer && height != self.height {
+            return None;
         }
+        self.authority.at_height(height)
     }
 
-    fn handle_resp(&mut self, msg: Option<VerifyResp>) -> ConsensusResult<()> {
-        let resp = msg.ok_or_else(|| ConsensusError::Other("Event sender dropped".to_string()))?;
-        if resp.height != self.height {
-            return Ok(());
+    fn proposer_of(
+        &self,
+        authority: &AuthorityManage,
+        height: Height,
+        round: Round,
+    ) -> ConsensusResult<Address> {
+        if self.vrf_proposer {
+            return authority.get_vrf_proposer(&self.vrf_seed, height, round);
         }
+        authority.get_proposer(height, round)
+    }
 
-        let block_hash = resp.block_hash.clone();
-        log::debug!(
-            "Overlord: state receive a verify response true, height {}, round {}, hash {:?}",
-            resp.height,
-            resp.round,
-            hex_encode(block_hash.clone())
+    /// Hand a valid timer configuration to SMR, which applies it at the next round boundary, so
+    /// that the timers of the current step are not changed.
+    fn update_timer_config(&mut self, config: DurationConfig) -> ConsensusResult<()> {
+        if config.propose_ratio == 0
+            || config.prevote_ratio == 0
+            || config.precommit_ratio == 0
+            || config.brake_ratio == 0
+        {
+            return Err(ConsensusError::Other(format!(
+                "invalid timer config {:?}, the ratios must be positive",
+                config
+            )));
+        }
+
+        log::info!(
+            "Overlord: state update timer config {:?} from the next round",
+            config
         );
+        self.state_machine
+            .update_timer_config(self.height, self.round, config)
+    }
 
-        self.is_full_transaction
-            .insert(block_hash.clone(), resp.is_pass);
+    /// Export the artifacts of the current height which is in progress, or of a retained
+    /// committed height.
+    fn export_artifacts(&self, height: Height) -> Option<HeightArtifacts<T>> {
+        if height == self.height {
+            return Some(self.height_artifacts(None));
+        }
+        self.artifacts.get(height)
+    }
 
-        if let Some(qc) =
-            self.votes
-                .get_qc_by_hash(self.height, block_hash.clone(), VoteType::Precommit)
+    /// Get the commit proof of the height, or the latest one, from the proof store if any,
+    /// otherwise from the artifacts of the recent heights.
+    fn query_proof(&self, height: Option<Height>) -> ConsensusResult<Option<Proof>> {
+        if let Some(proof_store) = self.proof_store.as_ref() {
+            let res = match height {
+                Some(height) => proof_store.get(height),
+                None => proof_store.latest(),
+            };
+            return res.map_err(|e| ConsensusError::ProofStoreErr {
+                context: "query proof",
+                source: e.into(),
+            });
+        }
+
+        let height = match height {
+            Some(height) => height,
+            None if self.height > INIT_HEIGHT => self.height - 1,
+            None => return Ok(None),
+        };
+        Ok(self
+            .artifacts
+            .get(height)
+            .and_then(|artifacts| artifacts.proof))
+    }
+
+    /// Export the state of the current height, from which a node of the same address continues
+    /// the height by `import_snapshot`.
+    fn export_snapshot(&mut self) -> ConsensusResult<StateSnapshot<T>> {
+        let lock = self.wal_lock(self.lock_round)?;
+        Ok(StateSnapshot {
+            wal_info: self.wal_info(self.step.clone(), lock),
+            qcs: self.votes.height_qcs(self.height),
+            chokes: self.chokes.all_chokes(),
+            choke_qcs: self.chokes.all_qcs(),
+        })
+    }
+
+    /// Handle a precommit QC of the current height from the sync of the node, which is verified
+    /// as a QC from the network, so that the height is committed once the block is checked.
+    async fn inject_qc(&mut self, qc: AggregatedVote) -> ConsensusResult<()> {
+        if qc.vote_type != VoteType::Precommit || qc.height != self.height {
+            return Err(ConsensusError::InjectHeightErr {
+                vote_type: qc.vote_type.clone(),
+                height: qc.height,
+                current: self.height,
+            });
+        }
+        if !self.is_qc_above_threshold(&qc.signature) {
+            return Err(ConsensusError::AggregatedSignatureErr(
+                "inject a QC below the threshold".to_string(),
+            ));
+        }
+        check_qc(self.util.as_ref(), &self.chain_id, &qc, &self.authority)?;
+
+        log::info!(
+            "Overlord: state inject a precommit QC height {}, round {}",
+            qc.height,
+            qc.round
+        );
+        self.handle_msg(Context::new(), OverlordMsg::AggregatedVote(qc))
+            .await
+    }
+
+    /// Continue the height of a snapshot exported by a node of the same address. The QCs, the
+    /// chokes and the choke QCs are verified against the authority list of the snapshot height,
+    /// and the snapshot is saved to the wal before the state recovers from it, the same as from
+    /// the wal on restart.
+    async fn import_snapshot(&mut self, snapshot: StateSnapshot<T>) -> ConsensusResult<()> {
+        let info = snapshot.wal_info;
+        if (info.height, info.round) < (self.height, self.round) {
+            return Err(ConsensusError::StaleSnapshotErr {
+                height: info.height,
+                round: info.round,
+                current_height: self.height,
+                current_round: self.round,
+            });
+        }
+
+        // The snapshot may be of a later height, of which the validator set is announced.
+        let authority = self.verify_authority(info.height)?;
+        let lock_qc = info.lock.as_ref().map(|lock| &lock.lock_votes);
+        for qc in snapshot.qcs.iter().chain(lock_qc) {
+            if qc.height != info.height {
+                return Err(ConsensusError::StateErr(format!(
+                    "snapshot of height {} with a qc of height {}",
+                    info.height, qc.height
+                )));
+            }
+            check_qc(self.util.as_ref(), &self.chain_id, qc, &authority)?;
+        }
+        // A choke QC moves the round forward, and the chokes are aggregated into one, so both are
+        // verified as those received from the network.
+        for sc in snapshot.chokes.iter() {
+            if sc.choke.height == info.height {
+                check_signed_choke(self.util.as_ref(), &self.chain_id, sc, &authority)?;
+            }
+        }
+        for qc in snapshot.choke_qcs.iter() {
+            if qc.height == info.height {
+                check_choke_qc(self.util.as_ref(), &self.chain_id, qc, &authority)?;
+            }
+        }
+
+        log::info!("Overlord: state import snapshot {}", info);
+        self.write_wal(&info, true).await?;
+        if info.height != self.height {
+            self.chokes.clear();
+        }
+        for qc in snapshot.qcs.into_iter() {
+            self.guard_qc(&qc)?;
+            self.keep_qc(qc)?;
+        }
+        for sc in snapshot.chokes.into_iter() {
+            if sc.choke.height == info.height {
+                self.chokes.insert(sc.choke.round, sc);
+            }
+        }
+        for qc in snapshot.choke_qcs.into_iter() {
+            if qc.height == info.height {
+                self.chokes.set_qc(qc.round, qc);
+            }
+        }
+        self.recover(info).await
+    }
+
+    fn height_artifacts(&self, proof: Option<Proof>) -> HeightArtifacts<T> {
+        HeightArtifacts {
+            height: self.height,
+            proposals: self.proposals.height_proposals(self.height),
+            qcs: self.votes.height_qcs(self.height),
+            chokes: self.chokes.all_chokes(),
+            choke_qcs: self.chokes.all_qcs(),
+            proof,
+        }
+    }
+
+    fn consensus_status(&mut self) -> ConsensusStatus {
+        ConsensusStatus {
+            height: self.height,
+            round: self.round,
+            step: self.step.clone(),
+            leader: self.leader_address.clone(),
+            is_leader: self.is_leader,
+            paused: self.paused,
+            lock_round: self.lock_round,
+            authority_list: self.authority.get_authority_list(),
+            prevote_tally: self.vote_tally(VoteType::Prevote),
+            precommit_tally: self.vote_tally(VoteType::Precommit),
+        }
+    }
+
+    fn vote_tally(&mut self, vote_type: VoteType) -> Vec<VoteTally> {
+        let vote_map = match self.votes.get_vote_map(self.height, self.round, vote_type) {
+            Ok(map) => map.clone(),
+            Err(_) => return Vec::new(),
+        };
+
+        vote_map
+            .into_iter()
+            .map(|(hash, set)| VoteTally {
+                block_hash: hash,
+                vote_count: set.len(),
+                vote_weight: set
+                    .iter()
+                    .filter_map(|addr| self.authority.get_vote_weight(addr).ok())
+                    .fold(0u64, |acc, weight| acc.saturating_add(*weight)),
+            })
+            .collect()
+    }
+
+    /// A function to handle event from the SMR. Public this function in the crate to do unit tests.
+    pub(crate) async fn handle_event(&mut self, event: Option<SMREvent>) -> ConsensusResult<()> {
+        // A failed check block task sends no response, so its slot is also released here.
+        self.spawn_check_tasks();
+        let event =
+            event.ok_or_else(|| ConsensusError::Other("Event sender dropped".to_string()))?;
+        if self.debug.matches(self.height, None) {
+            log::info!(
+                target: "overlord::debug",
+                "Overlord: debug height {}, round {}, step {} event {:?}",
+                self.height,
+                self.round,
+                self.step,
+                event
+            );
+        }
+
+        match event {
+            SMREvent::NewRoundInfo {
+                round,
+                lock_round,
+                lock_proposal,
+                from_where,
+                ..
+            } => {
+                if let Err(e) = self
+                    .handle_new_round(round, lock_round, lock_proposal, from_where)
+                    .await
+                {
+                    log::error!("Overlord: state handle new round error {:?}", e);
+                }
+                Ok(())
+            }
+
+            SMREvent::PrevoteVote {
+                block_hash,
+                lock_round,
+                ..
+            } => {
+                if let Err(e) = self
+                    .handle_vote_event(block_hash, VoteType::Prevote, lock_round)
+                    .await
+                {
+                    log::error!("Overlord: state handle prevote vote error {:?}", e);
+                }
+                Ok(())
+            }
+
+            SMREvent::PrecommitVote {
+                block_hash,
+                lock_round,
+                ..
+            } => {
+                if let Err(e) = self
+                    .handle_vote_event(block_hash, VoteType::Precommit, lock_round)
+                    .await
+                {
+                    log::error!("Overlord: state handle precommit vote error {:?}", e);
+                }
+                Ok(())
+            }
+
+            SMREvent::Commit(hash) => {
+                if let Err(e) = self.handle_commit(hash).await {
+                    log::error!("Overlord: state handle commit error {:?}", e);
+                }
+                Ok(())
+            }
+
+            SMREvent::Brake {
+                height,
+                round,
+                lock_round,
+            } => {
+                if height != self.height {
+                    return Ok(());
+                }
+
+                if let Err(e) = self.handle_brake(round, lock_round).await {
+                    log::error!("Overlord: state handle brake error {:?}", e);
+                }
+                Ok(())
+            }
+
+            SMREvent::PullProposal { height, round } => {
+                if height != self.height || round != self.round {
+                    return Ok(());
+                }
+
+                if let Err(e) = self.pull_proposal().await {
+                    log::error!("Overlord: state pull proposal error {:?}", e);
+                }
+                Ok(())
+            }
+
+            SMREvent::PullVotes {
+                height,
+                round,
+                vote_type,
+            } => {
+                if height != self.height || round != self.round {
+                    return Ok(());
+                }
+
+                self.pull_votes(vote_type).await;
+                Ok(())
+            }
+
+            SMREvent::RebroadcastProposal { height, round } => {
+                if height != self.height || round != self.round {
+                    return Ok(());
+                }
+
+                self.rebroadcast_proposal().await;
+                Ok(())
+            }
+
+            _ => unreachable!(),
+        }
+    }
+
+    fn handle_resp(&mut self, msg: Option<VerifyResp>) -> ConsensusResult<()> {
+        let resp = msg.ok_or_else(|| ConsensusError::Other("Event sender dropped".to_string()))?;
+        self.spawn_check_tasks();
+        if resp.height != self.height {
+            return Ok(());
+        }
+
+        let block_hash = resp.block_hash.clone();
+        log::debug!(
+            "Overlord: state receive a verify response {}, height {}, round {}, hash {:?}",
+            resp.is_pass,
+            resp.height,
+            resp.round,
+            hex_encode(block_hash.clone())
+        );
+
+        // A timed out check is not cached, so that the block is checked again once it is
+        // proposed again or a QC of it arrives. If a QC of it has arrived, check it again now.
+        if resp.is_timeout {
+            log::warn!(
+                "Overlord: state check block {:?} timeout, height {}, round {}",
+                hex_encode(block_hash.clone()),
+                resp.height,
+                resp.round
+            );
+            let has_qc = [VoteType::Precommit, VoteType::Prevote]
+                .into_iter()
+                .any(|vote_type| {
+                    self.votes
+                        .get_qc_by_hash(self.height, block_hash.clone(), vote_type)
+                        .is_some()
+                });
+            if let Some(block) = self.hash_with_block.peek(&block_hash).filter(|_| has_qc) {
+                let ctx = Context::new()
+                    .with_value(HEIGHT_CANCEL_KEY, self.height_cancel.clone())
+                    .with_value(ROUND_CANCEL_KEY, self.round_cancel.clone());
+                self.check_queue.push_back((
+                    ctx,
+                    self.height,
+                    self.round,
+                    block_hash.clone(),
+                    Arc::clone(block),
+                ));
+                self.spawn_check_tasks();
+            }
+        } else {
+            self.is_full_transaction
+                .insert(block_hash.clone(), resp.is_pass);
+        }
+
+        // A block which does not pass is never followed. If self has not prevoted in the round of
+        // the block, prevote as on a propose timeout, which is nil unless self is locked.
+        if !resp.is_pass {
+            if resp.round == self.round && self.step == Step::Propose {
+                self.state_machine.trigger(SMRTrigger {
+                    trigger_type: TriggerType::Proposal,
+                    source: TriggerSource::Timer,
+                    hash: Hash::new(),
+                    lock_round: None,
+                    round: self.round,
+                    height: self.height,
+                    wal_info: None,
+                })?;
+            }
+            return Ok(());
+        }
+
+        if let Some(qc) =
+            self.votes
+                .get_qc_by_hash(self.height, block_hash.clone(), VoteType::Precommit)
         {
             self.state_machine.trigger(SMRTrigger {
                 trigger_type: TriggerType::PrecommitQC,
@@ -379,6 +1370,22 @@ where
         Ok(())
     }
 
+    /// Whether the status is the last applied one, which is delivered again by the application,
+    /// such as in a race of the sync and the commit. The VRF seed of the commit is filled by the
+    /// state, so a status without the seed is compared with it.
+    fn is_duplicate_status(&self, status: &Status) -> bool {
+        match &self.last_status {
+            Some(last) if last.height == status.height => {
+                let mut status = status.clone();
+                if status.vrf_seed.is_none() {
+                    status.vrf_seed = last.vrf_seed.clone();
+                }
+                &status == last
+            }
+            _ => false,
+        }
+    }
+
     /// On receiving a rich status will call this method. This status can be either the return value
     /// of the `commit()` interface, or latest status after the synchronization is completed send
     /// by the overlord handler.
@@ -388,8 +1395,18 @@ where
     /// interval. Since it is possible to have received and cached the current height's proposals,
     /// votes and quorum certificates before, these should be re-checked as goto new height.
     /// Finally, trigger SMR to goto new height.
-    async fn goto_new_height(&mut self, _ctx: Context, status: Status) -> ConsensusResult<()> {
+    async fn goto_new_height(&mut self, ctx: Context, status: Status) -> ConsensusResult<()> {
+        if self.is_duplicate_status(&status) {
+            log::debug!(
+                "Overlord: state absorb a duplicate status, height {}",
+                status.height
+            );
+            self.duplicate_statuses += 1;
+            return Ok(());
+        }
+
         if status.height <= self.height {
+            self.stale_statuses += 1;
             log::warn!(
                 "Overlord: state receive an outdated status, height {}, self height {}",
                 status.height,
@@ -398,38 +1415,76 @@ where
             return Ok(());
         }
 
+        self.check_status(&status)?;
+        if self.verify_height_jump && status.height > self.height + 1 {
+            self.check_height_jump(&status)?;
+        }
+
         let new_height = status.height;
+        let last_height = self.height;
+        // The round of the commit of the previous height, which bounds the rounds of the votes of
+        // that height kept, see `filter_previous_round`.
+        self.previous_round = match status.proof.as_ref() {
+            Some(proof) if proof.height + 1 == new_height => proof.round,
+            _ if last_height + 1 == new_height => self.round,
+            _ => INIT_ROUND,
+        };
+        self.last_status = Some(status.clone());
         self.height = new_height;
         self.round = INIT_ROUND;
+        self.vrf_seed = status.vrf_seed.clone().unwrap_or_default();
+        std::mem::take(&mut self.height_cancel).cancel();
+        std::mem::take(&mut self.round_cancel).cancel();
+        self.forced_round = None;
 
         // Check the consensus power.
+        let had_power = self.consensus_power;
         self.consensus_power = status.is_consensus_node(&self.address);
         if !self.consensus_power {
             log::info!(
                 "Overlord: self does not have consensus power height {}",
                 new_height
             );
+            if had_power {
+                self.abandon_height(ctx, last_height);
+            }
             return Ok(());
         }
 
         log::info!("Overlord: state goto new height {}", self.height);
 
+        let rejoin = !had_power;
+        if rejoin {
+            self.rejoin();
+        }
         self.save_wal(Step::Propose, None).await?;
 
         // Update height and authority list.
         self.height_start = Instant::now();
-        let mut auth_list = status.authority_list.clone();
-        self.authority.update(&mut auth_list);
+        self.stall.reset();
+        self.update_authority(&status)?;
 
         if let Some(interval) = status.interval {
             self.block_interval = interval;
         }
 
-        // Clear outdated proposals and votes.
+        // Clear outdated proposals and votes. The votes of the height cached before are counted
+        // with the weights of the list then, so they are recounted with the list of the height.
         self.proposals.flush(new_height - 1);
         self.votes.flush(new_height - 1);
+        let authority = &self.authority;
+        self.votes.reweigh(new_height, |addr| {
+            authority.get_vote_weight(addr).ok().copied()
+        });
         self.hash_with_block.clear();
+        self.vrf_outputs.clear();
+        self.aggregating.clear();
         self.chokes.clear();
+        self.parts.clear();
+        if let Err(e) = self.sign_guard.flush(new_height) {
+            log::warn!("Overlord: state flush sign guard error {:?}", e);
+        }
+        self.cancel_stale_checks();
 
         // Re-check proposals that have been in the proposal collector, of the current height.
         if let Some(proposals) = self.proposals.get_height_proposals(self.height) {
@@ -443,17 +1498,127 @@ where
         }
 
         self.state_machine.new_height_status(status.into())?;
+        if rejoin {
+            self.pull_proposal().await?;
+            self.pull_missing_qcs().await;
+        }
+        Ok(())
+    }
+
+    /// Reset the lock of a node which regains the consensus power, so that nothing of the heights
+    /// before it lost the power is carried into the wal. The collectors are flushed for the new
+    /// height as on any height. Then the proposal and the QCs of the height are pulled from the
+    /// peers, which also send the QC of their current round, so that the node joins the round of
+    /// the peers rather than waiting for the next height.
+    fn rejoin(&mut self) {
+        log::info!(
+            "Overlord: state rejoin as a validator at height {}",
+            self.height
+        );
+        self.lock_round = None;
+        self.update_from_where = UpdateFrom::PrecommitQC(mock_init_qc());
+    }
+
+    /// Check the proof of the height before a status which jumps over heights, against the
+    /// authority list that self knows for that height. Self knows the list of the next height
+    /// only, which is the announced one or the current one, so the proof of a higher height is
+    /// refused rather than verified against a list that may have changed.
+    fn check_height_jump(&self, status: &Status) -> ConsensusResult<()> {
+        let refuse = |reason: String| ConsensusError::HeightJumpErr {
+            height: status.height,
+            current: self.height,
+            reason,
+        };
+        let proof = status
+            .proof
+            .as_ref()
+            .ok_or_else(|| refuse("no proof".to_string()))?;
+        if proof.height + 1 != status.height {
+            return Err(refuse(format!("proof of height {}", proof.height)));
+        }
+        if proof.height > self.height + 1 {
+            return Err(refuse(format!(
+                "unknown authority list of height {}",
+                proof.height
+            )));
+        }
+
+        let authority = self
+            .authority
+            .at_height(proof.height)
+            .ok_or_else(|| refuse("unknown authority list".to_string()))?;
+        proof
+            .verify_with_chain_id(
+                &authority.get_authority_list(),
+                self.util.as_ref(),
+                &self.chain_id,
+            )
+            .map_err(|e| refuse(e.to_string()))
+    }
+
+    /// Check the authority lists of a status before anything is changed by it. Return `Err()` if
+    /// any of them exceeds the maximum authority size, or if its weight sums overflow.
+    fn check_status(&self, status: &Status) -> ConsensusResult<()> {
+        // The seed of the first height is empty, the proposers of which follow `get_proposer`.
+        if self.vrf_proposer && status.vrf_seed.is_none() && status.height > INIT_HEIGHT + 1 {
+            return Err(ConsensusError::Other(format!(
+                "status of height {} lacks the VRF seed in the VRF proposer mode",
+                status.height
+            )));
+        }
+        check_authority_size(&status.authority_list, self.max_authority_size)?;
+        check_authority_weights(&status.authority_list)?;
+        if self.threshold_qc {
+            check_threshold_weights(&status.authority_list)?;
+        }
+        if let Some(next_list) = status.next_authority_list.as_ref() {
+            check_authority_size(next_list, self.max_authority_size)?;
+            check_authority_weights(next_list)?;
+            if self.threshold_qc {
+                check_threshold_weights(next_list)?;
+            }
+        }
         Ok(())
     }
 
+    /// Move to the authority list of the new height and keep the announced one of the next height.
+    /// The status is checked by `check_status` before.
+    fn update_authority(&mut self, status: &Status) -> ConsensusResult<()> {
+        let mut auth_list = status.authority_list.clone();
+        self.authority.transit(status.height, &mut auth_list)?;
+        self.rotate_keys(&auth_list);
+        if let Some(mut next_list) = status.next_authority_list.clone() {
+            self.authority.announce(status.height + 1, &mut next_list)?;
+            self.rotate_keys(&next_list);
+        }
+        Ok(())
+    }
+
+    /// Hand the key rotations of the authority list to the crypto. A rotation that the crypto
+    /// refuses is logged, and the signatures of the node are verified by its previous key.
+    fn rotate_keys(&self, authority_list: &[Node]) {
+        for node in authority_list.iter() {
+            if let Some(rotation) = node.key_rotation.as_ref() {
+                if let Err(e) = self.util.rotate_key(&node.address, rotation) {
+                    log::error!(
+                        "Overlord: state rotate key of {:?} from height {} error {:?}",
+                        hex_encode(node.address.clone()),
+                        rotation.height,
+                        e
+                    );
+                }
+            }
+        }
+    }
+
     /// Handle `NewRoundInfo` event from SMR. Firstly, goto new round and check the `XOR`
     /// relationship between the lock round type and the lock proposal type. Secondly, check if self
     /// is a proposer. If is not a proposer, return `Ok(())` and wait for a signed proposal from the
     /// network. Otherwise, make up a proposal, broadcast it and touch off SMR trigger.
     async fn handle_new_round(
         &mut self,
-        new_round: u64,
-        lock_round: Option<u64>,
+        new_round: Round,
+        lock_round: Option<Round>,
         lock_proposal: Option<Hash>,
         from_where: FromWhere,
     ) -> ConsensusResult<()> {
@@ -463,9 +1628,20 @@ where
             let last_round = self.round;
             let reason = self.view_change_reason(last_round, &from_where);
             self.report_view_change(last_round, reason);
+            self.reporter
+                .report_round_latency(self.height, last_round, self.round_start.elapsed());
         }
 
+        let round_changed = self.round != new_round;
+        if round_changed {
+            std::mem::take(&mut self.round_cancel).cancel();
+        }
         self.round = new_round;
+        self.round_start = Instant::now();
+        if round_changed {
+            self.cancel_stale_checks();
+        }
+        self.check_stalled();
         self.is_leader = false;
 
         if lock_round.is_some().bitxor(lock_proposal.is_some()) {
@@ -481,8 +1657,11 @@ where
         // If self is not proposer, check whether it has received current signed proposal before. If
         // has, then handle it.
         if !self.is_proposer()? {
-            if let Ok((signed_proposal, ctx)) = self.proposals.get(self.height, self.round) {
-                return self.handle_signed_proposal(ctx, signed_proposal).await;
+            if let Ok((signed_proposal, compact, ctx)) = self.proposals.get(self.height, self.round)
+            {
+                return self
+                    .handle_signed_proposal(ctx, signed_proposal, compact)
+                    .await;
             }
             return Ok(());
         }
@@ -500,14 +1679,27 @@ where
         // done by doing this. These things constitute a Proposal. Then sign it and broadcast it to
         // other nodes.
         self.is_leader = true;
+        if self.paused {
+            log::info!(
+                "Overlord: state is paused, skip proposing height {}, round {}",
+                self.height,
+                self.round
+            );
+            return Ok(());
+        }
+
         let ctx = Context::new();
         let (block, hash, polc) = if lock_round.is_none() {
             let (new_block, new_hash) = self
-                .function
+                .block_provider
                 .get_block(ctx.clone(), self.height)
                 .await
-                .map_err(|err| ConsensusError::Other(format!("get block error {:?}", err)))?;
-            (new_block, new_hash, None)
+                .map_err(|err| ConsensusError::ConsensusCallErr {
+                    op: "get block",
+                    height: self.height,
+                    source: err.into(),
+                })?;
+            (Arc::new(new_block), new_hash, None)
         } else {
             let round = lock_round.unwrap();
             let hash = lock_proposal.unwrap();
@@ -524,20 +1716,42 @@ where
                 lock_round: round,
                 lock_votes: qc,
             };
-            (block.to_owned(), hash, Some(polc))
+            (Arc::clone(block), hash, Some(polc))
         };
 
-        self.hash_with_block
-            .entry(hash.clone())
-            .or_insert_with(|| block.clone());
+        let decision = self
+            .function
+            .before_propose(ctx.clone(), self.height, self.round, hash.clone())
+            .await;
+        if decision == Decision::Abort {
+            log::warn!(
+                "Overlord: state abort proposing height {}, round {}, hash {:?}",
+                self.height,
+                self.round,
+                hex_encode(hash)
+            );
+            return Ok(());
+        }
+
+        if !self.hash_with_block.contains_key(&hash) {
+            self.hash_with_block
+                .insert(hash.clone(), Arc::clone(&block));
+        }
+        self.pin_lock();
+
+        let vrf = self.prove_vrf()?;
+        if let Some(vrf) = vrf.clone() {
+            self.vrf_outputs.insert(self.address.clone(), vrf);
+        }
 
         let proposal = Proposal {
             height: self.height,
             round: self.round,
-            content: block.clone(),
+            content: block.as_ref().clone(),
             block_hash: hash.clone(),
             lock: polc.clone(),
             proposer: self.address.clone(),
+            vrf,
         };
 
         log::debug!(
@@ -547,11 +1761,51 @@ where
             hex_encode(hash.clone())
         );
 
-        self.broadcast(
-            Context::new(),
-            OverlordMsg::SignedProposal(self.sign_proposal(proposal)?),
-        )
-        .await;
+        // Keep the proposal of self with the received ones, which are exported as the height
+        // artifacts.
+        let parts = self.split_block(block.as_ref());
+        let part_set = parts.as_ref().map(|(part_set, _)| part_set.clone());
+        let signed_proposal = self.sign_proposal(proposal, part_set.clone()).await?;
+        let compact = (self.compact_proposal || part_set.is_some()).then(|| {
+            let mut compact = signed_proposal.proposal.to_compact();
+            compact.parts = part_set;
+            compact
+        });
+        if self
+            .proposals
+            .insert(
+                Context::new(),
+                self.height,
+                self.round,
+                signed_proposal.clone(),
+                compact.clone(),
+            )
+            .is_ok()
+        {
+            self.archive(|sink| sink.archive_proposal(&signed_proposal));
+        }
+        let msg = match compact {
+            Some(compact) => OverlordMsg::SignedCompactProposal(SignedCompactProposal {
+                signature: signed_proposal.signature,
+                proposal: compact,
+            }),
+            None => OverlordMsg::SignedProposal(signed_proposal),
+        };
+        self.broadcast(Context::new(), msg).await;
+
+        if let Some((_, data)) = parts {
+            for (index, chunk) in data.iter().enumerate() {
+                let part = BlockPart {
+                    height: self.height,
+                    round: self.round,
+                    index: index as u32,
+                    data: chunk.clone(),
+                    proof: merkle_proof(self.util.as_ref(), &data, index),
+                };
+                self.broadcast(Context::new(), OverlordMsg::BlockPart(part))
+                    .await;
+            }
+        }
 
         self.state_machine.trigger(SMRTrigger {
             trigger_type: TriggerType::Proposal,
@@ -569,21 +1823,39 @@ where
 
     /// This function only handle signed proposals which height and round are equal to current.
     /// Others will be ignored or stored in the proposal collector.
-    #[tracing_span(
-        kind = "overlord",
-        tags = "{
-            'height': 'signed_proposal.proposal.height', 
-            'round': 'signed_proposal.proposal.round'
-        }",
-        logs = "{
-            'proposal_hash': 'hex_encode(signed_proposal.proposal.block_hash.clone())',
-            'proposer': 'hex_encode(signed_proposal.proposal.proposer.clone())'
-        }"
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "overlord.handle_signed_proposal",
+            skip_all,
+            fields(
+                kind = "overlord",
+                height = %signed_proposal.proposal.height,
+                round = %signed_proposal.proposal.round,
+                proposal_hash = %hex_encode(&signed_proposal.proposal.block_hash),
+                proposer = %hex_encode(&signed_proposal.proposal.proposer),
+            )
+        )
+    )]
+    #[cfg_attr(
+        feature = "apm",
+        tracing_span(
+            kind = "overlord",
+            tags = "{
+                'height': 'signed_proposal.proposal.height', 
+                'round': 'signed_proposal.proposal.round'
+            }",
+            logs = "{
+                'proposal_hash': 'hex_encode(signed_proposal.proposal.block_hash.clone())',
+                'proposer': 'hex_encode(signed_proposal.proposal.proposer.clone())'
+            }"
+        )
     )]
     async fn handle_signed_proposal(
         &mut self,
         ctx: Context,
         signed_proposal: SignedProposal<T>,
+        compact: Option<CompactProposal>,
     ) -> ConsensusResult<()> {
         let proposal_height = signed_proposal.proposal.height;
         let proposal_round = signed_proposal.proposal.round;
@@ -610,12 +1882,13 @@ where
             proposal_height,
             proposal_round,
             &signed_proposal,
+            &compact,
         )? {
             return Ok(());
         }
 
         let proposal = signed_proposal.proposal.clone();
-        let signature = signed_proposal.signature.clone();
+        self.verify_vrf(&proposal)?;
 
         // If the signed proposal is with a lock, check the lock round and the QC then trigger it to
         // SMR. Otherwise, touch off SMR directly.
@@ -626,15 +1899,32 @@ where
             None
         };
 
-        let hash = proposal.block_hash.clone();
-        let block = proposal.content.clone();
-        self.hash_with_block.insert(hash.clone(), proposal.content);
+        // Cache the block only after the proposal is collected, so that a conflicting proposal of
+        // the round never replaces the block of a hash.
         self.proposals.insert(
             ctx.clone(),
             self.height,
             self.round,
             signed_proposal.clone(),
+            compact,
         )?;
+        let hash = proposal.block_hash.clone();
+        let block = Arc::new(proposal.content);
+        if let Some(vrf) = proposal.vrf {
+            self.vrf_outputs
+                .entry(proposal.proposer.clone())
+                .or_insert(vrf);
+        }
+        self.hash_with_block
+            .insert(hash.clone(), Arc::clone(&block));
+        self.pin_lock();
+        self.archive(|sink| sink.archive_proposal(&signed_proposal));
+
+        // A locked block is proposed again with its old timestamp, so only a fresh block is
+        // compared with the local clock, which is prevoted nil as a failed check if skewed.
+        if proposal.lock.is_none() && !self.check_clock_skew(&proposal.proposer, block.as_ref()) {
+            self.is_full_transaction.insert(hash.clone(), false);
+        }
 
         log::debug!(
             "Overlord: state trigger SMR proposal height {}, round {}, hash {:?}",
@@ -662,7 +1952,7 @@ where
         &mut self,
         hash: Hash,
         vote_type: VoteType,
-        lock_round: Option<u64>,
+        lock_round: Option<Round>,
     ) -> ConsensusResult<()> {
         log::debug!(
             "Overlord: state receive {:?} vote event height {}, round {}, hash {:?}",
@@ -672,22 +1962,52 @@ where
             hex_encode(hash.clone())
         );
 
-        let signed_vote = self.sign_vote(Vote {
-            height: self.height,
-            round: self.round,
-            vote_type: vote_type.clone(),
-            block_hash: hash.clone(),
-        })?;
+        // A paused node keeps its step and lock, and follows the QCs from the peers.
+        if self.paused {
+            self.save_wal_with_lock_round(vote_type.clone().into(), lock_round)
+                .await?;
+            return self.vote_process(vote_type).await;
+        }
+
+        // The SMR prevotes a proposal before its block is checked. If the check has failed
+        // already, prevote nil instead, unless self is locked on the block.
+        let hash = if vote_type == VoteType::Prevote
+            && lock_round.is_none()
+            && self.is_full_transaction.peek(&hash) == Some(&false)
+        {
+            log::info!(
+                "Overlord: state prevote nil on a block which does not pass the check, hash {:?}",
+                hex_encode(hash)
+            );
+            Hash::new()
+        } else {
+            hash
+        };
+
+        let mut signed_vote = self
+            .sign_vote(Vote {
+                height: self.height,
+                round: self.round,
+                vote_type: vote_type.clone(),
+                block_hash: hash.clone(),
+            })
+            .await?;
+
+        if vote_type == VoteType::Precommit && !hash.is_empty() {
+            signed_vote.extension = self.extend_vote(&signed_vote.vote).await;
+        }
 
         self.save_wal_with_lock_round(vote_type.clone().into(), lock_round)
             .await?;
 
         if self.is_leader {
+            let weight = *self.authority.get_vote_weight(&self.address)?;
             self.votes.insert_vote(
                 Context::new(),
                 signed_vote.get_hash(),
                 signed_vote,
                 self.address.clone(),
+                weight,
             );
         } else {
             log::debug!(
@@ -705,7 +2025,11 @@ where
         Ok(())
     }
 
-    async fn handle_brake(&mut self, round: u64, lock_round: Option<u64>) -> ConsensusResult<()> {
+    async fn handle_brake(
+        &mut self,
+        round: Round,
+        lock_round: Option<Round>,
+    ) -> ConsensusResult<()> {
         if round != self.round {
             return Err(ConsensusError::CorrectnessErr(format!(
                 "SMR round {}, state round {}",
@@ -713,16 +2037,31 @@ where
             )));
         }
 
+        if self.paused {
+            self.save_wal_with_lock_round(Step::Brake, lock_round)
+                .await?;
+            self.pull_missing_qcs().await;
+            return Ok(());
+        }
+
         let choke = Choke {
             height: self.height,
             round: self.round,
             from: self.update_from_where.clone(),
         };
 
+        self.check_halted()?;
+        let (hash, signed) = (
+            self.sign_hash(alloy_rlp::encode(&choke.to_hash()).into()),
+            choke.clone(),
+        );
         let signature = self
-            .util
-            .sign(self.util.hash(alloy_rlp::encode(&choke.to_hash()).into()))
-            .map_err(|err| ConsensusError::CryptoErr(format!("sign choke error {:?}", err)))?;
+            .sign_by(move |crypto| crypto.sign_choke(&signed, hash))
+            .await
+            .map_err(|err| ConsensusError::CryptoCallErr {
+                op: "sign choke",
+                source: err.into(),
+            })?;
         let signed_choke = SignedChoke {
             signature,
             choke,
@@ -738,9 +2077,494 @@ where
         self.chokes.insert(self.round, signed_choke.clone());
         self.save_wal_with_lock_round(Step::Brake, lock_round)
             .await?;
+        self.pull_missing_qcs().await;
         self.broadcast(Context::new(), OverlordMsg::SignedChoke(signed_choke))
             .await;
-        self.check_choke_above_threshold()?;
+        self.check_choke_above_threshold().await?;
+        Ok(())
+    }
+
+    /// Request the prevote and precommit QCs of the current round that self has not received
+    /// from the peers, since the round is about to be choked for the lack of them.
+    async fn pull_missing_qcs(&mut self) {
+        for vote_type in [VoteType::Prevote, VoteType::Precommit] {
+            if self
+                .votes
+                .get_qc_by_id(self.height, self.round, vote_type.clone())
+                .is_ok()
+            {
+                continue;
+            }
+
+            log::debug!(
+                "Overlord: state pull {:?} QC height {}, round {}",
+                vote_type,
+                self.height,
+                self.round
+            );
+            let pull = PullQC {
+                height: self.height,
+                round: self.round,
+                vote_type,
+                requester: self.address.clone(),
+            };
+            self.broadcast(Context::new(), OverlordMsg::PullQC(pull))
+                .await;
+        }
+    }
+
+    /// Split the encoded block into parts if it is larger than the part size.
+    fn split_block(&self, block: &T) -> Option<(PartSetHeader, Vec<Bytes>)> {
+        if self.block_part_size == 0 {
+            return None;
+        }
+
+        let bytes = Bytes::from(bcs::to_bytes(block).ok()?);
+        if bytes.len() <= self.block_part_size {
+            return None;
+        }
+        let data = bytes
+            .chunks(self.block_part_size)
+            .map(Bytes::copy_from_slice)
+            .collect::<Vec<_>>();
+        let part_set = PartSetHeader {
+            total: data.len() as u32,
+            root: merkle_root(self.util.as_ref(), &data),
+        };
+        Some((part_set, data))
+    }
+
+    /// Handle the header of the block parts of the current height, of which the signature is
+    /// verified. The parts of a future height are dropped, as they can not be verified before
+    /// self reaches the height.
+    async fn handle_part_header(&mut self, ctx: Context, sp: SignedCompactProposal) {
+        if sp.proposal.height != self.height {
+            log::debug!(
+                "Overlord: state drop the part header of height {}, self height {}",
+                sp.proposal.height,
+                self.height
+            );
+            return;
+        }
+
+        match self.parts.insert_header(self.util.as_ref(), sp) {
+            Ok(Some((sp, bytes))) => self.restore_parts(ctx, sp, bytes).await,
+            Ok(None) => (),
+            Err(e) => log::error!("Overlord: state handle part header error {:?}", e),
+        }
+    }
+
+    /// Handle a block part of the current height. The parts are not signed, they are verified by
+    /// the merkle root of the header.
+    async fn handle_block_part(&mut self, ctx: Context, part: BlockPart) {
+        if part.height != self.height {
+            return;
+        }
+
+        match self.parts.insert_part(self.util.as_ref(), part) {
+            Ok(Some((sp, bytes))) => self.restore_parts(ctx, sp, bytes).await,
+            Ok(None) => (),
+            Err(e) => log::warn!("Overlord: state handle block part error {:?}", e),
+        }
+    }
+
+    /// Decode the reassembled block and handle the restored signed proposal.
+    async fn restore_parts(&mut self, ctx: Context, sp: SignedCompactProposal, bytes: Bytes) {
+        let block: T = match bcs::from_bytes(&bytes) {
+            Ok(block) => block,
+            Err(e) => {
+                log::error!(
+                    "Overlord: state decode the block parts of height {}, round {} error {:?}",
+                    sp.proposal.height,
+                    sp.proposal.round,
+                    e
+                );
+                return;
+            }
+        };
+
+        self.restore_proposal(ctx, sp, block).await;
+    }
+
+    /// Handle the signed proposal restored from a verified signed compact proposal and its block,
+    /// which keeps the compact form that the signature covers.
+    async fn restore_proposal(&mut self, ctx: Context, sp: SignedCompactProposal, block: T) {
+        let compact = sp.proposal.clone();
+        let signed_proposal = SignedProposal {
+            signature: sp.signature,
+            proposal: sp.proposal.into_proposal(block),
+        };
+        if let Err(e) = self
+            .handle_signed_proposal(ctx, signed_proposal, Some(compact))
+            .await
+        {
+            log::error!("Overlord: state handle restored proposal error {:?}", e);
+        }
+    }
+
+    /// Restore the signed proposal with a fetched block, which has passed the check against the
+    /// block hash of the proposal. The block of the current height is marked as checked before
+    /// the restoration, so that it is not checked again, and the check response is handled then.
+    async fn handle_fetch_resp(&mut self, ctx: Context, resp: FetchResp<T>) -> ConsensusResult<()> {
+        let height = resp.proposal.proposal.height;
+        let round = resp.proposal.proposal.round;
+        let hash = resp.proposal.proposal.block_hash.clone();
+        if height == self.height {
+            self.is_full_transaction.insert(hash.clone(), true);
+        }
+
+        self.restore_proposal(ctx, resp.proposal, resp.block).await;
+        if height != self.height {
+            return Ok(());
+        }
+        // The restored block fails if its timestamp is skewed.
+        let is_pass = self.is_full_transaction.peek(&hash) != Some(&false);
+        self.handle_resp(Some(VerifyResp {
+            height,
+            round,
+            block_hash: hash,
+            is_pass,
+            is_timeout: false,
+        }))
+    }
+
+    /// Fetch the block of a compact proposal from the application, then restore the signed
+    /// proposal with the block. The signature of the compact proposal of the current height is
+    /// verified, and that of a future height is verified in the compact form when self reaches
+    /// the height, as the signed proposals. A fetched block is checked against the block hash
+    /// before it is restored, as the application may return a block of another hash, and the block
+    /// which does not pass is dropped.
+    async fn fetch_proposal_block(&mut self, ctx: Context, sp: SignedCompactProposal) {
+        let height = sp.proposal.height;
+        if height < self.height {
+            return;
+        }
+
+        let hash = sp.proposal.block_hash.clone();
+        // The locked block is proposed again, which is cached already.
+        if let Some(block) = self.hash_with_block.peek(&hash) {
+            let block = block.as_ref().clone();
+            self.restore_proposal(ctx, sp, block).await;
+            return;
+        }
+
+        let function = Arc::clone(&self.function);
+        let provider = Arc::clone(&self.block_provider);
+        let timeout = self.check_block_timeout;
+        let tx = self.fetch_tx.clone();
+        let ctx = ctx.with_value(HEIGHT_CANCEL_KEY, self.height_cancel.clone());
+        self.fetch_tasks.retain(|(_, task)| !task.is_finished());
+        let task = tokio::spawn(async move {
+            match function
+                .get_block_by_hash(ctx.clone(), height, hash.clone())
+                .await
+            {
+                Ok(block) => {
+                    let round = sp.proposal.round;
+                    let res = check_current_block(
+                        ctx.clone(),
+                        provider,
+                        height,
+                        round,
+                        hash.clone(),
+                        block.clone(),
+                        timeout,
+                    )
+                    .await;
+                    if res != Some(true) {
+                        log::warn!(
+                            "Overlord: state drop the fetched block of hash {:?}, height {}",
+                            hex_encode(hash),
+                            height
+                        );
+                        return;
+                    }
+                    let resp = FetchResp {
+                        proposal: sp,
+                        block,
+                    };
+                    let _ = tx.unbounded_send((ctx, resp));
+                }
+                Err(e) => log::error!(
+                    "Overlord: state get block by hash {:?} of height {} error {:?}",
+                    hex_encode(hash),
+                    height,
+                    e
+                ),
+            }
+        });
+        self.fetch_tasks.push((height, task));
+    }
+
+    /// Send the requested QC back to the requester if self has it. The requests from the nodes
+    /// beyond the authority list are ignored.
+    async fn handle_pull_qc(&mut self, ctx: Context, pull: PullQC) -> ConsensusResult<()> {
+        if pull.requester == self.address {
+            return Ok(());
+        }
+        self.verify_address(&pull.requester)?;
+
+        if let Ok(qc) = self
+            .votes
+            .get_qc_by_id(pull.height, pull.round, pull.vote_type.clone())
+        {
+            log::debug!(
+                "Overlord: state serve {:?} QC height {}, round {} to {:?}",
+                pull.vote_type,
+                pull.height,
+                pull.round,
+                hex_encode(pull.requester.clone())
+            );
+            if let Err(err) = self
+                .network
+                .transmit(
+                    ctx.clone(),
+                    pull.requester.clone(),
+                    OverlordMsg::AggregatedVote(qc),
+                )
+                .await
+            {
+                log::error!("Overlord: state transmit pulled QC failed {:?}", err);
+            }
+        }
+
+        // A requester behind the round of self, such as a rejoining validator, is sent the QC by
+        // which self went to the current round, so that it jumps to the round.
+        if pull.height == self.height && pull.round < self.round {
+            let msg = match self.update_from_where.clone() {
+                UpdateFrom::ChokeQC(qc) => OverlordMsg::RoundSummary(RoundSummary {
+                    height: self.height,
+                    new_round: qc.round + 1,
+                    choke_qc: qc,
+                }),
+                UpdateFrom::PrevoteQC(qc) | UpdateFrom::PrecommitQC(qc) => {
+                    if qc.height != self.height {
+                        return Ok(());
+                    }
+                    OverlordMsg::AggregatedVote(qc)
+                }
+            };
+            if let Err(err) = self.network.transmit(ctx, pull.requester, msg).await {
+                log::error!("Overlord: state transmit round QC failed {:?}", err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Pull the proposal of the current round from its proposer and the backups, which are the
+    /// proposers of the next two rounds, if self has not received it yet.
+    async fn pull_proposal(&mut self) -> ConsensusResult<()> {
+        if self.is_leader || self.proposals.get(self.height, self.round).is_ok() {
+            return Ok(());
+        }
+
+        let mut targets: Vec<Address> = Vec::new();
+        for offset in 0..3 {
+            let proposer = self.get_proposer(self.height, self.round + offset)?;
+            if proposer != self.address && !targets.contains(&proposer) {
+                targets.push(proposer);
+            }
+        }
+
+        log::debug!(
+            "Overlord: state pull proposal height {}, round {} from {} peers",
+            self.height,
+            self.round,
+            targets.len()
+        );
+        let pull = PullProposal {
+            height: self.height,
+            round: self.round,
+            requester: self.address.clone(),
+        };
+        for target in targets.into_iter() {
+            if let Err(err) = self
+                .network
+                .transmit(
+                    Context::new(),
+                    target,
+                    OverlordMsg::PullProposal(pull.clone()),
+                )
+                .await
+            {
+                log::error!("Overlord: state transmit pull proposal failed {:?}", err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Broadcast the signed proposal of the current round once more if self is the leader, for the
+    /// replicas which missed the first broadcast. It is sent in the form it is signed in, as a
+    /// pulled proposal is served.
+    async fn rebroadcast_proposal(&mut self) {
+        if !self.is_leader {
+            return;
+        }
+
+        if let Ok((signed_proposal, compact, _)) = self.proposals.get(self.height, self.round) {
+            log::debug!(
+                "Overlord: state rebroadcast proposal height {}, round {}",
+                self.height,
+                self.round
+            );
+            for msg in self.proposal_msgs(signed_proposal, compact) {
+                self.broadcast(Context::new(), msg).await;
+            }
+        }
+    }
+
+    /// The messages to send a collected proposal in the form it is signed in. A proposal signed in
+    /// the compact form is sent as the signed compact proposal, followed by the block parts if
+    /// its header carries them.
+    fn proposal_msgs(
+        &self,
+        signed_proposal: SignedProposal<T>,
+        compact: Option<CompactProposal>,
+    ) -> Vec<OverlordMsg<T>> {
+        let compact = match compact {
+            Some(compact) => compact,
+            None => return vec![OverlordMsg::SignedProposal(signed_proposal)],
+        };
+
+        let parts = compact.parts.as_ref().map(|header| {
+            match self.split_block(&signed_proposal.proposal.content) {
+                Some((part_set, data)) if &part_set == header => data,
+                // The parts are split by another part size, which can not be rebuilt.
+                _ => Vec::new(),
+            }
+        });
+        let mut msgs = vec![OverlordMsg::SignedCompactProposal(SignedCompactProposal {
+            signature: signed_proposal.signature,
+            proposal: compact.clone(),
+        })];
+        if let Some(data) = parts {
+            msgs.extend((0..data.len()).map(|index| {
+                OverlordMsg::BlockPart(BlockPart {
+                    height: compact.height,
+                    round: compact.round,
+                    index: index as u32,
+                    data: data[index].clone(),
+                    proof: merkle_proof(self.util.as_ref(), &data, index),
+                })
+            }));
+        }
+        msgs
+    }
+
+    /// Send the requested signed proposal back to the requester if self has it. The requests from
+    /// the nodes beyond the authority list are ignored.
+    async fn handle_pull_proposal(
+        &mut self,
+        ctx: Context,
+        pull: PullProposal,
+    ) -> ConsensusResult<()> {
+        if pull.requester == self.address {
+            return Ok(());
+        }
+        self.verify_address(&pull.requester)?;
+
+        if let Ok((signed_proposal, compact, _)) = self.proposals.get(pull.height, pull.round) {
+            log::debug!(
+                "Overlord: state serve proposal height {}, round {} to {:?}",
+                pull.height,
+                pull.round,
+                hex_encode(pull.requester.clone())
+            );
+            for msg in self.proposal_msgs(signed_proposal, compact) {
+                if let Err(err) = self
+                    .network
+                    .transmit(ctx.clone(), pull.requester.clone(), msg)
+                    .await
+                {
+                    log::error!("Overlord: state transmit pulled proposal failed {:?}", err);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Request the votes of the current round that the leader misses from the peers, if the QC of
+    /// the vote type has not been formed yet.
+    async fn pull_votes(&mut self, vote_type: VoteType) {
+        if !self.is_leader
+            || self
+                .votes
+                .get_qc_by_id(self.height, self.round, vote_type.clone())
+                .is_ok()
+        {
+            return;
+        }
+
+        let have = self
+            .votes
+            .get_vote_map(self.height, self.round, vote_type.clone())
+            .map(|map| map.values().flatten().cloned().collect::<HashSet<_>>())
+            .unwrap_or_default();
+        log::debug!(
+            "Overlord: state pull {:?} votes height {}, round {}, have {} votes",
+            vote_type,
+            self.height,
+            self.round,
+            have.len()
+        );
+        let pull = PullVotes {
+            height: self.height,
+            round: self.round,
+            vote_type,
+            have_bitmap: self.voters_bitmap(&have),
+            requester: self.address.clone(),
+        };
+        self.broadcast(Context::new(), OverlordMsg::PullVotes(pull))
+            .await;
+    }
+
+    /// Send the collected votes that the requester misses back in a batch. The requests from the
+    /// nodes beyond the authority list are ignored.
+    async fn handle_pull_votes(&mut self, ctx: Context, pull: PullVotes) -> ConsensusResult<()> {
+        if pull.requester == self.address {
+            return Ok(());
+        }
+        self.verify_address(&pull.requester)?;
+
+        let have = self.authority.get_voters(&pull.have_bitmap)?;
+        let hashes = match self
+            .votes
+            .get_vote_map(pull.height, pull.round, pull.vote_type.clone())
+        {
+            Ok(map) => map.keys().cloned().collect::<Vec<_>>(),
+            Err(_) => return Ok(()),
+        };
+        let mut missing = Vec::new();
+        for hash in hashes.iter() {
+            let votes =
+                self.votes
+                    .get_votes(pull.height, pull.round, pull.vote_type.clone(), hash)?;
+            missing.extend(
+                votes
+                    .into_iter()
+                    .map(|(vote, _)| vote)
+                    .filter(|vote| !have.contains(&vote.voter)),
+            );
+        }
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        log::debug!(
+            "Overlord: state serve {} {:?} votes height {}, round {} to {:?}",
+            missing.len(),
+            pull.vote_type,
+            pull.height,
+            pull.round,
+            hex_encode(pull.requester.clone())
+        );
+        if let Err(err) = self
+            .network
+            .transmit(ctx, pull.requester, OverlordMsg::VoteBatch(missing))
+            .await
+        {
+            log::error!("Overlord: state transmit pulled votes failed {:?}", err);
+        }
         Ok(())
     }
 
@@ -755,7 +2579,7 @@ where
         log::debug!("Overlord: state get origin block");
         let height = self.height;
         let content = if let Some(tmp) = self.hash_with_block.get(&hash) {
-            tmp.to_owned()
+            Arc::clone(tmp)
         } else {
             return Err(ConsensusError::Other(format!(
                 "Lose whole block height {}, round {}",
@@ -771,38 +2595,89 @@ where
         } else {
             return Err(ConsensusError::StorageErr("Lose precommit QC".to_string()));
         };
+        invariant!(
+            "COMMIT-RULE",
+            qc.height == height
+                && qc.block_hash == hash
+                && qc.vote_type == VoteType::Precommit
+                && self.is_qc_above_threshold(&qc.signature),
+            "commit height {}, hash {:?} with a {:?} QC height {}, round {}",
+            height,
+            hex_encode(hash.clone()),
+            qc.vote_type,
+            qc.height,
+            qc.round
+        );
 
         let polc = Some(WalLock {
             lock_round: self.round,
             lock_votes: qc.clone(),
-            content: content.clone(),
+            content: Arc::clone(&content),
         });
         self.save_wal(Step::Commit, polc).await?;
 
         log::debug!("Overlord: state generate proof");
 
+        // The VRF of the commit is of the proposer of the round of the precommit QC, which is
+        // unique for the height however the block is proposed, so it can not be ground.
+        let vrf = if self.vrf_proposer {
+            let proposer = self.get_proposer(height, qc.round)?;
+            self.vrf_outputs.get(&proposer).cloned()
+        } else {
+            None
+        };
+        let next_seed = vrf
+            .as_ref()
+            .map(|vrf| Vrf::next_seed(self.util.as_ref(), height, &self.vrf_seed, &vrf.output));
         let proof = Proof {
             height,
             round: qc.round,
             block_hash: hash.clone(),
             signature: qc.signature.clone(),
+            extensions: qc.extensions.clone(),
+            vrf,
         };
+        self.artifacts
+            .insert(self.height_artifacts(Some(proof.clone())));
+        if let Some(proof_store) = self.proof_store.as_ref() {
+            if let Err(e) = proof_store.save(proof.clone()) {
+                log::warn!(
+                    "Overlord: state save the proof of height {} error {:?}",
+                    height,
+                    e
+                );
+            }
+        }
         let commit = Commit {
             height,
-            content,
+            content: content.as_ref().clone(),
             proof,
         };
 
+        let published = (!self.commit_subscribers.is_empty()).then(|| commit.clone());
+
         let ctx = Context::new();
-        let status = self
+        let mut status = self
             .function
             .commit(ctx.clone(), height, commit)
             .await
-            .map_err(|err| ConsensusError::Other(format!("commit error {:?}", err)))?;
+            .map_err(|err| ConsensusError::ConsensusCallErr {
+                op: "commit",
+                height,
+                source: err.into(),
+            })?;
+        if status.vrf_seed.is_none() {
+            status.vrf_seed = next_seed;
+        }
+        if let Some(commit) = published {
+            self.publish_commit(commit);
+        }
+        self.check_status(&status)?;
 
-        let mut auth_list = status.authority_list.clone();
-        self.authority.update(&mut auth_list);
+        self.update_authority(&status)?;
         let cost = Instant::now() - self.height_start;
+        self.reporter
+            .report_round_latency(self.height, self.round, self.round_start.elapsed());
 
         log::info!(
             "Overlord: achieve consensus in height {}, costs {} round {:?} time",
@@ -811,16 +2686,51 @@ where
             cost
         );
 
-        if self.next_proposer(status.height, INIT_ROUND)?
-            && cost < Duration::from_millis(self.block_interval)
-        {
-            sleep(Duration::from_millis(self.block_interval) - cost).await;
+        if self.next_proposer(&status, INIT_ROUND)? {
+            self.block_provider
+                .prepare_block(ctx.clone(), status.height);
+            if let Some(at) = self.function.next_propose_at(status.height) {
+                sleep_until(at.into()).await;
+            } else if cost < Duration::from_millis(self.block_interval) {
+                sleep(Duration::from_millis(self.block_interval) - cost).await;
+            }
         }
 
         self.goto_new_height(ctx, status).await?;
         Ok(())
     }
 
+    /// Send a commit to the subscribers, and drop the ones whose stream is dropped or whose buffer
+    /// is full, so that a lagging subscriber neither holds the commits without bound nor misses
+    /// one silently.
+    fn publish_commit(&mut self, commit: Commit<T>) {
+        let height = commit.height;
+        self.commit_subscribers
+            .retain_mut(|tx| match tx.try_send(commit.clone()) {
+                Ok(()) => true,
+                Err(e) if e.is_full() => {
+                    log::warn!(
+                        "Overlord: state drop a lagging commit subscriber at height {}",
+                        height
+                    );
+                    false
+                }
+                Err(_) => false,
+            });
+    }
+
+    /// Whether the voters of the QC are above the threshold. The threshold signature of a
+    /// threshold QC is only combined from the shares above the threshold, which is enforced by
+    /// the scheme when the QC is verified.
+    fn is_qc_above_threshold(&self, signature: &AggregatedSignature) -> bool {
+        if self.threshold_qc && signature.is_threshold() {
+            return true;
+        }
+        self.authority
+            .is_above_threshold(&signature.address_bitmap)
+            .unwrap_or(false)
+    }
+
     /// The main process of handle signed vote is that only handle those height and round are both
     /// equal to the current. The lower votes will be ignored directly even if the height is equal
     /// to the `current height - 1` and the round is higher than the current round. The reason is
@@ -828,23 +2738,81 @@ where
     /// will be done by the leader. For the higher votes, check the signature and save them in
     /// the vote collector. Whenever the current vote is received, a statistic is made to check
     /// if the sum of the voting weights corresponding to the hash exceeds the threshold.
-    #[tracing_span(
-        kind = "overlord",
-        tags = "{
-            'height': 'signed_vote.vote.height', 
-            'round': 'signed_vote.vote.round', 
-            'vote_type': 'signed_vote.vote.vote_type'
-        }",
-        logs = "{
-            'vote_hash': 'hex_encode(signed_vote.vote.block_hash.clone())',
-            'voter': 'hex_encode(signed_vote.voter.clone())'
-        }"
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "overlord.handle_signed_vote",
+            skip_all,
+            fields(
+                kind = "overlord",
+                height = %signed_vote.vote.height,
+                round = %signed_vote.vote.round,
+                vote_type = %signed_vote.vote.vote_type,
+                vote_hash = %hex_encode(&signed_vote.vote.block_hash),
+                voter = %hex_encode(&signed_vote.voter),
+            )
+        )
+    )]
+    #[cfg_attr(
+        feature = "apm",
+        tracing_span(
+            kind = "overlord",
+            tags = "{
+                'height': 'signed_vote.vote.height', 
+                'round': 'signed_vote.vote.round', 
+                'vote_type': 'signed_vote.vote.vote_type'
+            }",
+            logs = "{
+                'vote_hash': 'hex_encode(signed_vote.vote.block_hash.clone())',
+                'voter': 'hex_encode(signed_vote.voter.clone())'
+            }"
+        )
     )]
     async fn handle_signed_vote(
         &mut self,
         ctx: Context,
         signed_vote: SignedVote,
     ) -> ConsensusResult<()> {
+        match self.collect_signed_vote(ctx.clone(), signed_vote).await? {
+            Some(vote_type) => self.count_votes(ctx, vote_type),
+            None => Ok(()),
+        }
+    }
+
+    /// Collect the votes of a batch, then count the votes once for each vote type. A vote that
+    /// fails the checks is skipped without failing the rest of the batch.
+    async fn handle_vote_batch(
+        &mut self,
+        ctx: Context,
+        votes: Vec<SignedVote>,
+    ) -> ConsensusResult<()> {
+        log::debug!(
+            "Overlord: state receive a batch of {} signed votes",
+            votes.len()
+        );
+
+        let mut counting = Vec::new();
+        for signed_vote in votes.into_iter() {
+            match self.collect_signed_vote(ctx.clone(), signed_vote).await {
+                Ok(Some(vote_type)) if !counting.contains(&vote_type) => counting.push(vote_type),
+                Ok(_) => (),
+                Err(e) => log::error!("Overlord: state collect a batched vote error {:?}", e),
+            }
+        }
+
+        for vote_type in counting.into_iter() {
+            self.count_votes(ctx.clone(), vote_type)?;
+        }
+        Ok(())
+    }
+
+    /// Check a signed vote and insert it into the vote collector. Return the vote type if the
+    /// vote of the current height is inserted and the votes should be counted.
+    async fn collect_signed_vote(
+        &mut self,
+        ctx: Context,
+        signed_vote: SignedVote,
+    ) -> ConsensusResult<Option<VoteType>> {
         let height = signed_vote.get_height();
         let round = signed_vote.get_round();
         let vote_type = if signed_vote.is_prevote() {
@@ -862,18 +2830,26 @@ where
             hex_encode(signed_vote.vote.block_hash.clone())
         );
 
-        if self.filter_message(height, round) {
-            return Ok(());
+        if height.saturating_add(1) == self.height {
+            if !self.filter_previous_round("Signed Vote", height, round) {
+                self.keep_previous_vote(ctx, signed_vote);
+            }
+            return Ok(None);
+        }
+        if self.filter_message("Signed Vote", height, round) {
+            return Ok(None);
         }
-
-        let tmp_type: String = vote_type.to_string();
 
         // All the votes must pass the verification of signature and address before be saved into
         // vote collector.
-        let signature = signed_vote.signature.clone();
         let voter = signed_vote.voter.clone();
         let vote = signed_vote.vote.clone();
         self.verify_address(&voter)?;
+        if voter == self.address {
+            self.guard_vote(height, round, vote_type.clone(), &vote.block_hash)?;
+        }
+        self.verify_vote_extension(ctx.clone(), &signed_vote)
+            .await?;
 
         // Check if the quorum certificate has generated before check whether there is a hash that
         // vote weight is above the threshold. If no hash achieved this, return directly.
@@ -882,38 +2858,100 @@ where
             .get_qc_by_id(height, round, vote_type.clone())
             .is_ok()
         {
-            return Ok(());
+            return Ok(None);
         }
 
+        let weight = *self.authority.get_vote_weight(&voter)?;
         self.votes.insert_vote(
             ctx.clone(),
             signed_vote.get_hash(),
             signed_vote.clone(),
             voter,
+            weight,
         );
 
         if height > self.height {
+            return Ok(None);
+        }
+        Ok(Some(vote_type))
+    }
+
+    /// Keep a verified vote of the previous height with the vote weight of the authority list of
+    /// that height, to serve the peers which pull the votes. It is not counted, the height has
+    /// been committed.
+    fn keep_previous_vote(&mut self, ctx: Context, signed_vote: SignedVote) {
+        let height = signed_vote.get_height();
+        let weight = match self
+            .authority
+            .at_height(height)
+            .and_then(|authority| authority.get_vote_weight(&signed_vote.voter).ok().copied())
+        {
+            Some(weight) => weight,
+            None => return,
+        };
+        let (hash, voter) = (signed_vote.get_hash(), signed_vote.voter.clone());
+        self.votes
+            .insert_vote(ctx, hash, signed_vote, voter, weight);
+    }
+
+    /// Count the votes of the current round, and aggregate the votes if a hash is above the
+    /// threshold.
+    fn count_votes(&mut self, ctx: Context, vote_type: VoteType) -> ConsensusResult<()> {
+        let block_hash = self.counting_vote(vote_type.clone())?;
+        if block_hash.is_none() {
+            log::debug!("Overlord: state counting of vote and no one above threshold");
+            if self.optimistic_step && self.is_full_vote(vote_type.clone())? {
+                self.state_machine
+                    .full_vote(self.height, self.round, vote_type)?;
+            }
             return Ok(());
         }
 
-        let block_hash = self.counting_vote(vote_type.clone())?;
-        if block_hash.is_none() {
-            log::debug!("Overlord: state counting of vote and no one above threshold");
+        // Build the quorum certificate needs to aggregate signatures into an aggregate
+        // signature besides the address bitmap.
+        self.aggregate_votes(ctx, block_hash.unwrap(), vote_type)
+    }
+
+    /// Handle the quorum certificate built off the state task. If the QC of the same height,
+    /// round and type has been received from the network in the meantime, ignore it. Otherwise,
+    /// save and broadcast the QC, then touch off SMR trigger.
+    async fn handle_aggregate_resp(
+        &mut self,
+        ctx: Context,
+        resp: AggregateResp,
+    ) -> ConsensusResult<()> {
+        self.aggregating
+            .remove(&(resp.height, resp.round, resp.vote_type.clone()));
+        let qc = resp.qc?;
+
+        if qc.height != self.height
+            || self
+                .votes
+                .get_qc_by_id(qc.height, qc.round, qc.vote_type.clone())
+                .is_ok()
+        {
             return Ok(());
         }
 
-        // Build the quorum certificate needs to aggregate signatures into an aggregate
-        // signature besides the address bitmap.
-        let block_hash = block_hash.unwrap();
-        let qc = self.generate_qc(block_hash.clone(), vote_type.clone())?;
-
         log::debug!(
             "Overlord: state set QC height {}, round {}",
-            self.height,
-            self.round
+            qc.height,
+            qc.round
         );
 
-        self.votes.set_qc(qc.clone());
+        invariant!(
+            "QC-THRESHOLD",
+            self.is_qc_above_threshold(&qc.signature),
+            "aggregate a {:?} QC height {}, round {}",
+            qc.vote_type,
+            qc.height,
+            qc.round
+        );
+        let block_hash = qc.block_hash.clone();
+        let vote_type = qc.vote_type.clone();
+        self.report_qc(&qc);
+        self.archive(|sink| sink.archive_qc(&qc));
+        self.keep_qc(qc.clone())?;
 
         log::debug!(
             "Overlord: state broadcast a {:?} QC, height {}, round {}, hash {:?}",
@@ -927,19 +2965,20 @@ where
             .await;
 
         if !self.try_get_full_txs(&block_hash) {
+            self.recheck_block(&block_hash).await;
             return Ok(());
         }
 
         log::debug!(
             "Overlord: state trigger SMR {:?} QC height {}, round {}, hash {:?}",
             vote_type,
-            self.height,
-            self.round,
+            qc.height,
+            qc.round,
             hex_encode(block_hash.clone())
         );
 
         self.state_machine.trigger(SMRTrigger {
-            trigger_type: vote_type.clone().into(),
+            trigger_type: vote_type.into(),
             source: TriggerSource::State,
             hash: block_hash,
             lock_round: None,
@@ -964,18 +3003,37 @@ where
     /// is precommit, ignore it. Otherwise, retransmit precommit QC.
     ///
     /// 4. Other cases, return `Ok(())` directly.
-    #[tracing_span(
-        kind = "overlord",
-        tags = "{
-            'height': 'aggregated_vote.height', 
-            'round': 'aggregated_vote.round', 
-            'qc_type': 'aggregated_vote.vote_type'
-        }",
-        logs = "{
-            'qc_hash': 'hex_encode(aggregated_vote.block_hash.clone())',
-            'leader': 'hex_encode(aggregated_vote.leader.clone())'
-        }"
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "overlord.handle_aggregated_vote",
+            skip_all,
+            fields(
+                kind = "overlord",
+                height = %aggregated_vote.height,
+                round = %aggregated_vote.round,
+                qc_type = %aggregated_vote.vote_type,
+                qc_hash = %hex_encode(&aggregated_vote.block_hash),
+                leader = %hex_encode(&aggregated_vote.leader),
+            )
+        )
     )]
+    #[cfg_attr(
+        feature = "apm",
+        tracing_span(
+            kind = "overlord",
+            tags = "{
+                'height': 'aggregated_vote.height', 
+                'round': 'aggregated_vote.round', 
+                'qc_type': 'aggregated_vote.vote_type'
+            }",
+            logs = "{
+                'qc_hash': 'hex_encode(aggregated_vote.block_hash.clone())',
+                'leader': 'hex_encode(aggregated_vote.leader.clone())'
+            }"
+        )
+    )]
+    #[cfg_attr(not(feature = "apm"), allow(unused_variables))]
     async fn handle_aggregated_vote(
         &mut self,
         ctx: Context,
@@ -998,9 +3056,33 @@ where
             hex_encode(aggregated_vote.block_hash.clone())
         );
 
-        // If the vote height is lower than the current height, ignore it directly. If the vote
-        // height is higher than current height, save it and return Ok;
+        if vote_height >= self.height {
+            self.guard_qc(&aggregated_vote)?;
+        }
+
+        // If the vote height is lower than the current height, keep it if it is of the previous
+        // height, or ignore it. If the vote height is higher than current height, save it and
+        // return Ok;
         match vote_height.cmp(&self.height) {
+            Ordering::Less if vote_height.saturating_add(1) == self.height => {
+                if self.filter_previous_round("Aggregated Vote", vote_height, vote_round) {
+                    return Ok(());
+                }
+                log::debug!(
+                    "Overlord: state receive a QC of the previous height, round {}",
+                    vote_round,
+                );
+                if self
+                    .votes
+                    .get_qc_by_id(vote_height, vote_round, qc_type)
+                    .is_err()
+                {
+                    self.archive(|sink| sink.archive_qc(&aggregated_vote));
+                }
+                self.keep_qc(aggregated_vote)?;
+                return Ok(());
+            }
+
             Ordering::Less => {
                 log::debug!(
                     "Overlord: state receive an outdated QC, height {}, round {}",
@@ -1011,13 +3093,15 @@ where
             }
 
             Ordering::Greater => {
-                if self.height + FUTURE_HEIGHT_GAP > vote_height && vote_round < FUTURE_ROUND_GAP {
+                if self.height.saturating_add(self.future_height_gap) > vote_height
+                    && vote_round.0 < self.future_round_gap
+                {
                     log::debug!(
                         "Overlord: state receive a future QC, height {}, round {}",
                         vote_height,
                         vote_round,
                     );
-                    self.votes.set_qc(aggregated_vote);
+                    self.keep_qc(aggregated_vote)?;
                 } else {
                     log::warn!("Overlord: state receive a much higher aggregated vote");
                 }
@@ -1038,11 +3122,50 @@ where
             return Ok(());
         }
 
+        invariant!(
+            "QC-THRESHOLD",
+            self.is_qc_above_threshold(&aggregated_vote.signature),
+            "receive a {:?} QC height {}, round {}",
+            qc_type,
+            vote_height,
+            vote_round
+        );
+
         // Check if the block hash has been verified.
         let qc_hash = aggregated_vote.block_hash.clone();
-        self.votes.set_qc(aggregated_vote);
+        let mut relay = false;
+        match self
+            .votes
+            .get_qc_by_id(vote_height, vote_round, qc_type.clone())
+        {
+            Ok(existing) if existing == aggregated_vote => (),
+            Ok(_) => self.archive(|sink| sink.archive_qc(&aggregated_vote)),
+            Err(_) => {
+                self.report_qc(&aggregated_vote);
+                self.archive(|sink| sink.archive_qc(&aggregated_vote));
+                relay = self.qc_relay && vote_round == self.round;
+            }
+        }
+        self.keep_qc(aggregated_vote.clone())?;
+
+        // Relay a QC of the current round the first time it is received, so that it reaches the
+        // replicas which missed the broadcast of the leader. A QC already kept is never relayed.
+        if relay {
+            log::debug!(
+                "Overlord: state relay {:?} QC height {}, round {}",
+                qc_type,
+                vote_height,
+                vote_round
+            );
+            self.broadcast(
+                Context::new(),
+                OverlordMsg::AggregatedVote(aggregated_vote.clone()),
+            )
+            .await;
+        }
 
         if !qc_hash.is_empty() && !self.try_get_full_txs(&qc_hash) {
+            self.recheck_block(&qc_hash).await;
             return Ok(());
         }
 
@@ -1080,6 +3203,7 @@ where
             {
                 let block_hash = qc.block_hash.clone();
                 if !self.try_get_full_txs(&block_hash) {
+                    self.recheck_block(&block_hash).await;
                     return Ok(());
                 }
 
@@ -1103,53 +3227,33 @@ where
                 return Ok(());
             }
         } else if let Some(block_hash) = self.counting_vote(vote_type.clone())? {
-            let qc = self.generate_qc(block_hash.clone(), vote_type.clone())?;
-            self.votes.set_qc(qc.clone());
-
-            log::debug!(
-                "Overlord: state broadcast a {:?} QC, height {}, round {}, hash {:?}",
-                vote_type,
-                qc.height,
-                qc.round,
-                hex_encode(block_hash.clone())
-            );
-
-            self.broadcast(Context::new(), OverlordMsg::AggregatedVote(qc))
-                .await;
-
-            if !self.try_get_full_txs(&block_hash) {
-                return Ok(());
-            }
-
-            log::debug!(
-                "Overlord: state trigger SMR {:?} QC height {}, round {}, hash {:?}",
-                vote_type,
-                self.height,
-                self.round,
-                hex_encode(block_hash.clone())
-            );
-
-            self.state_machine.trigger(SMRTrigger {
-                trigger_type: vote_type.clone().into(),
-                source: TriggerSource::State,
-                hash: block_hash,
-                lock_round: None,
-                round: self.round,
-                height: self.height,
-                wal_info: None,
-            })?;
+            self.aggregate_votes(Context::new(), block_hash, vote_type)?;
         }
         Ok(())
     }
 
+    /// Whether the whole vote weight has voted in the current round, for any block.
+    fn is_full_vote(&mut self, vote_type: VoteType) -> ConsensusResult<bool> {
+        let vote_map = self
+            .votes
+            .get_vote_map(self.height, self.round, vote_type)?;
+        let acc = self
+            .authority
+            .sum_vote_weight(vote_map.values().flatten())?;
+        Ok(acc == self.authority.get_vote_weight_sum())
+    }
+
     fn counting_vote(&mut self, vote_type: VoteType) -> ConsensusResult<Option<Hash>> {
         let len = self
             .votes
             .vote_count(self.height, self.round, vote_type.clone());
-        let vote_map = self
-            .votes
-            .get_vote_map(self.height, self.round, vote_type.clone())?;
-        let threshold = self.authority.get_vote_weight_sum() * 2;
+        if len == 0 {
+            return Err(ConsensusError::MissingErr {
+                item: Missing::VoteMap(vote_type),
+                height: self.height,
+                round: self.round,
+            });
+        }
 
         log::debug!(
             "Overlord: state round {}, {:?} vote pool length {}",
@@ -1158,25 +3262,37 @@ where
             len
         );
 
-        for (hash, set) in vote_map.iter() {
-            let mut acc = 0u32;
-            for addr in set.iter() {
-                acc += self.authority.get_vote_weight(addr)?;
-            }
-            if u64::from(acc) * 3 > threshold {
-                return Ok(Some(hash.to_owned()));
-            }
-        }
-        Ok(None)
+        Ok(self.votes.first_hash_above_threshold(
+            self.height,
+            self.round,
+            vote_type,
+            &self.authority,
+        ))
     }
 
-    #[tracing_span(
-        kind = "overlord",
-        tags = "{
-            'height': 'signed_choke.choke.height',
-            'round': 'signed_choke.choke.round'
-        }",
-        logs = "{'choke_from': 'hex_encode(signed_choke.address.clone())'}"
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "overlord.handle_signed_choke",
+            skip_all,
+            fields(
+                kind = "overlord",
+                height = %signed_choke.choke.height,
+                round = %signed_choke.choke.round,
+                choke_from = %hex_encode(&signed_choke.address),
+            )
+        )
+    )]
+    #[cfg_attr(
+        feature = "apm",
+        tracing_span(
+            kind = "overlord",
+            tags = "{
+                'height': 'signed_choke.choke.height',
+                'round': 'signed_choke.choke.round'
+            }",
+            logs = "{'choke_from': 'hex_encode(signed_choke.address.clone())'}"
+        )
     )]
     async fn handle_signed_choke(
         &mut self,
@@ -1203,23 +3319,58 @@ where
             hex_encode(signed_choke.address.clone())
         );
 
+        // The QC of the choke is verified only if it is of the choke height, see `verify_msg`.
         if choke_round > self.round {
             match choke.from {
-                UpdateFrom::PrevoteQC(qc) => {
+                UpdateFrom::PrevoteQC(qc) | UpdateFrom::PrecommitQC(qc)
+                    if qc.height == choke_height =>
+                {
                     return self.handle_aggregated_vote(ctx.clone(), qc).await
                 }
-                UpdateFrom::PrecommitQC(qc) => {
-                    return self.handle_aggregated_vote(ctx.clone(), qc).await
+                UpdateFrom::ChokeQC(qc) if qc.height == choke_height => {
+                    let jump = qc.round > self.round;
+                    self.handle_aggregated_choke(qc.clone())?;
+                    if jump {
+                        self.broadcast_round_summary(qc).await;
+                    }
+                    return Ok(());
                 }
-                UpdateFrom::ChokeQC(qc) => return self.handle_aggregated_choke(qc),
+                _ => return Ok(()),
             }
         }
 
         self.chokes.insert(choke_round, signed_choke);
-        self.check_choke_above_threshold()?;
+        self.check_choke_above_threshold().await?;
         Ok(())
     }
 
+    /// Jump to the new round of a verified round summary. The summaries of other heights or of
+    /// no later rounds are ignored.
+    fn handle_round_summary(&mut self, summary: RoundSummary) -> ConsensusResult<()> {
+        if summary.height != self.height || summary.new_round <= self.round {
+            return Ok(());
+        }
+
+        log::debug!(
+            "Overlord: state receive a round summary of height {}, jump from round {} to {}",
+            summary.height,
+            self.round,
+            summary.new_round
+        );
+        self.handle_aggregated_choke(summary.choke_qc)
+    }
+
+    /// Broadcast a round summary after self jumps over more than one round by the choke QC.
+    async fn broadcast_round_summary(&self, choke_qc: AggregatedChoke) {
+        let summary = RoundSummary {
+            height: self.height,
+            new_round: choke_qc.round + 1,
+            choke_qc,
+        };
+        self.broadcast(Context::new(), OverlordMsg::RoundSummary(summary))
+            .await;
+    }
+
     fn handle_aggregated_choke(
         &mut self,
         aggregated_choke: AggregatedChoke,
@@ -1232,6 +3383,9 @@ where
         }
 
         let choke = aggregated_choke.to_hash();
+        if self.chokes.get_qc(choke.round).is_none() {
+            self.archive(|sink| sink.archive_choke_qc(&aggregated_choke));
+        }
         self.chokes.set_qc(choke.round, aggregated_choke);
 
         self.state_machine.trigger(SMRTrigger {
@@ -1246,11 +3400,20 @@ where
         Ok(())
     }
 
-    fn generate_qc(
+    /// Aggregating signatures takes tens of milliseconds when there are hundreds of voters, so
+    /// it runs on the blocking pool, and the QC is sent back by the aggregation channel. There is
+    /// at most one aggregation for a height, round and vote type at the same time.
+    fn aggregate_votes(
         &mut self,
+        ctx: Context,
         block_hash: Hash,
         vote_type: VoteType,
-    ) -> ConsensusResult<AggregatedVote> {
+    ) -> ConsensusResult<()> {
+        let key = (self.height, self.round, vote_type.clone());
+        if self.aggregating.contains(&key) {
+            return Ok(());
+        }
+
         let mut votes = self
             .votes
             .get_votes(self.height, self.round, vote_type.clone(), &block_hash)?
@@ -1261,6 +3424,16 @@ where
 
         log::debug!("Overlord: state build aggregated signature");
 
+        let extensions = votes
+            .iter()
+            .filter_map(|vote| {
+                vote.extension.clone().map(|extension| VoterExtension {
+                    voter: vote.voter.clone(),
+                    extension,
+                })
+            })
+            .collect::<Vec<_>>();
+
         let len = votes.len();
         let mut signatures = Vec::with_capacity(len);
         let mut voters = Vec::with_capacity(len);
@@ -1269,42 +3442,105 @@ where
             voters.push(vote.voter);
         }
 
-        let set = voters.iter().cloned().collect::<HashSet<_>>();
-        let mut bit_map = BitVec::from_elem(self.authority.len(), false);
-        for (index, addr) in self.authority.get_address_ref().iter().enumerate() {
-            if set.contains(addr) {
-                bit_map.set(index, true);
-            }
-        }
-
-        let aggregated_signature = AggregatedSignature {
-            signature: self.aggregate_signatures(signatures, voters)?,
-            address_bitmap: Bytes::from(bit_map.to_bytes()),
+        let threshold = self.threshold_qc;
+        let address_bitmap = if threshold {
+            Bytes::new()
+        } else {
+            let set = voters.iter().cloned().collect::<HashSet<_>>();
+            self.voters_bitmap(&set)
         };
-        let qc = AggregatedVote {
-            signature: aggregated_signature,
-            vote_type,
+
+        let pretty_voter = voters
+            .iter()
+            .map(|addr| hex_encode(addr.clone()))
+            .collect::<Vec<_>>();
+
+        log::debug!(
+            "Overlord: state aggregate signatures height {}, round {}, voters {:?}",
+            self.height,
+            self.round,
+            pretty_voter
+        );
+
+        let mut qc = AggregatedVote {
+            signature: AggregatedSignature {
+                signature: Signature::default(),
+                address_bitmap,
+                kind: if threshold {
+                    QcKind::Threshold
+                } else {
+                    QcKind::Aggregated
+                },
+            },
+            vote_type: vote_type.clone(),
             height: self.height,
             round: self.round,
             block_hash,
             leader: self.address.clone(),
+            extensions: if extensions.is_empty() {
+                None
+            } else {
+                Some(extensions)
+            },
+        };
+
+        self.aggregating.insert(key);
+        let crypto = Arc::clone(&self.util);
+        let tx = self.aggregate_tx.clone();
+        let (height, round) = (self.height, self.round);
+
+        let aggregate = move || {
+            let signature = if threshold {
+                crypto.combine_threshold_signature(signatures, voters)
+            } else {
+                crypto.aggregate_signatures(signatures, voters)
+            };
+            let qc = signature
+                .map(|signature| {
+                    qc.signature.signature = signature;
+                    qc
+                })
+                .map_err(|err| ConsensusError::CryptoCallErr {
+                    op: "aggregate",
+                    source: err.into(),
+                });
+            let resp = AggregateResp {
+                height,
+                round,
+                vote_type,
+                qc,
+            };
+            let _ = tx.unbounded_send((ctx, resp));
         };
-        Ok(qc)
+        if self.inline_aggregation {
+            tokio::spawn(async move { aggregate() });
+        } else {
+            tokio::task::spawn_blocking(aggregate);
+        }
+        Ok(())
     }
 
-    async fn re_check_proposals(
-        &mut self,
-        proposals_and_ctxs: Vec<(SignedProposal<T>, Context)>,
-    ) -> ConsensusResult<()> {
+    /// Verify the proposals of the current height received ahead again. A proposal restored from a
+    /// signed compact proposal is verified in the compact form, and its block is fetched again.
+    async fn re_check_proposals(&mut self, proposals: Vec<ProposalItem<T>>) -> ConsensusResult<()> {
         log::debug!("Overlord: state re-check future signed proposals");
 
-        for item in proposals_and_ctxs.into_iter() {
+        for (signed_proposal, compact, ctx) in proposals.into_iter() {
+            let msg = match compact {
+                Some(compact) => OverlordMsg::SignedCompactProposal(SignedCompactProposal {
+                    signature: signed_proposal.signature,
+                    proposal: compact,
+                }),
+                None => OverlordMsg::SignedProposal(signed_proposal),
+            };
             parallel_verify(
-                item.1,
-                OverlordMsg::SignedProposal(item.0),
+                ctx,
+                msg,
                 Arc::clone(&self.util),
-                self.authority.clone(),
+                self.chain_id.clone(),
+                self.authority.snapshot(),
                 self.verify_sig_tx.clone(),
+                self.verify_pool.as_ref(),
             )
             .await;
         }
@@ -1318,16 +3554,24 @@ where
     ) -> ConsensusResult<()> {
         log::debug!("Overlord: state re-check future signed votes");
 
-        for item in votes_and_ctxs.into_iter() {
-            parallel_verify(
-                item.1,
-                OverlordMsg::SignedVote(item.0),
-                Arc::clone(&self.util),
-                self.authority.clone(),
-                self.verify_sig_tx.clone(),
-            )
-            .await;
+        // The cached votes are verified as one batch.
+        let votes = votes_and_ctxs
+            .into_iter()
+            .map(|(vote, _)| vote)
+            .collect::<Vec<_>>();
+        if votes.is_empty() {
+            return Ok(());
         }
+        parallel_verify(
+            Context::new(),
+            OverlordMsg::VoteBatch(votes),
+            Arc::clone(&self.util),
+            self.chain_id.clone(),
+            self.authority.snapshot(),
+            self.verify_sig_tx.clone(),
+            self.verify_pool.as_ref(),
+        )
+        .await;
 
         Ok(())
     }
@@ -1340,8 +3584,10 @@ where
                 Context::new(),
                 OverlordMsg::AggregatedVote(item),
                 Arc::clone(&self.util),
-                self.authority.clone(),
+                self.chain_id.clone(),
+                self.authority.snapshot(),
                 self.verify_sig_tx.clone(),
+                self.verify_pool.as_ref(),
             )
             .await;
         }
@@ -1352,7 +3598,7 @@ where
     /// If self is not the proposer of the height and round, set leader address as the proposer
     /// address.
     fn is_proposer(&mut self) -> ConsensusResult<bool> {
-        let proposer = self.authority.get_proposer(self.height, self.round)?;
+        let proposer = self.get_proposer(self.height, self.round)?;
 
         if proposer == self.address {
             log::info!(
@@ -1376,17 +3622,141 @@ where
         Ok(false)
     }
 
-    fn next_proposer(&self, height: u64, round: u64) -> ConsensusResult<bool> {
-        let proposer = self.authority.get_proposer(height, round)?;
+    fn next_proposer(&self, status: &Status, round: Round) -> ConsensusResult<bool> {
+        let proposer = if self.vrf_proposer {
+            self.authority.get_vrf_proposer(
+                &status.vrf_seed.clone().unwrap_or_default(),
+                status.height,
+                round,
+            )?
+        } else {
+            self.authority.get_proposer(status.height, round)?
+        };
         Ok(self.address == proposer)
     }
 
-    fn sign_proposal(&self, proposal: Proposal<T>) -> ConsensusResult<SignedProposal<T>> {
+    /// The bitmap of the given voters in the order of the authority list.
+    fn voters_bitmap(&self, voters: &HashSet<Address>) -> Bytes {
+        let mut bit_map = BitVec::from_elem(self.authority.len(), false);
+        for (index, addr) in self.authority.get_address_ref().iter().enumerate() {
+            if voters.contains(addr) {
+                bit_map.set(index, true);
+            }
+        }
+        Bytes::from(bit_map.to_bytes())
+    }
+
+    /// Get the proposer of the given round. The VRF seed is of the current height, so the height
+    /// must be the current height in the VRF proposer mode.
+    fn get_proposer(&self, height: Height, round: Round) -> ConsensusResult<Address> {
+        if self.vrf_proposer {
+            return self
+                .authority
+                .get_vrf_proposer(&self.vrf_seed, height, round);
+        }
+        self.authority.get_proposer(height, round)
+    }
+
+    fn prove_vrf(&self) -> ConsensusResult<Option<Vrf>> {
+        if !self.vrf_proposer {
+            return Ok(None);
+        }
+
+        let (output, proof) = self
+            .util
+            .vrf_prove(Vrf::input(self.height, &self.vrf_seed))
+            .map_err(|err| ConsensusError::CryptoCallErr {
+                op: "prove VRF",
+                source: err.into(),
+            })?;
+        Ok(Some(Vrf { output, proof }))
+    }
+
+    /// Verify the VRF of a proposal of the current height. A proposal without VRF is refused in
+    /// the VRF proposer mode.
+    fn verify_vrf(&self, proposal: &Proposal<T>) -> ConsensusResult<()> {
+        if !self.vrf_proposer {
+            return Ok(());
+        }
+
+        let vrf = proposal
+            .vrf
+            .as_ref()
+            .ok_or_else(|| ConsensusError::ProposalErr("Lose VRF of the proposal".to_string()))?;
+        let output = self
+            .util
+            .vrf_verify(
+                vrf.proof.clone(),
+                Vrf::input(proposal.height, &self.vrf_seed),
+                proposal.proposer.clone(),
+            )
+            .map_err(|err| ConsensusError::CryptoCallErr {
+                op: "verify VRF",
+                source: err.into(),
+            })?;
+
+        if output != vrf.output {
+            return Err(ConsensusError::ProposalErr(
+                "Invalid VRF output".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The hash to sign of the payload, which is bound to the chain id.
+    fn sign_hash(&self, payload: Bytes) -> Hash {
+        self.util.hash(domain_payload(&self.chain_id, payload))
+    }
+
+    /// Sign by the crypto, on the blocking thread pool if the signing blocks, see
+    /// `Crypto::is_sign_blocking`, so that a remote signer does not hold a worker of the runtime.
+    async fn sign_by<S>(&self, sign: S) -> Result<Signature, Box<dyn Error + Send>>
+    where
+        S: FnOnce(&C) -> Result<Signature, Box<dyn Error + Send>> + Send + 'static,
+    {
+        if !self.util.is_sign_blocking() {
+            return sign(self.util.as_ref());
+        }
+        let crypto = Arc::clone(&self.util);
+        tokio::task::spawn_blocking(move || sign(crypto.as_ref()))
+            .await
+            .map_err(|e| {
+                Box::new(ConsensusError::Other(format!("sign task error {:?}", e)))
+                    as Box<dyn Error + Send>
+            })?
+    }
+
+    /// Sign a proposal. The proposal is signed in the compact form in the compact proposal mode,
+    /// or if the block is split into parts, of which the header is signed with the proposal.
+    async fn sign_proposal(
+        &mut self,
+        proposal: Proposal<T>,
+        part_set: Option<PartSetHeader>,
+    ) -> ConsensusResult<SignedProposal<T>> {
+        self.check_halted()?;
+        self.guard_mark(
+            SignKind::Proposal,
+            proposal.height,
+            proposal.round,
+            &proposal.block_hash,
+        )?;
         log::debug!("Overlord: state sign a proposal");
+        let mut compact = proposal.to_compact();
+        let hash = if part_set.is_some() {
+            compact.parts = part_set;
+            self.sign_hash(alloy_rlp::encode(&compact).into())
+        } else if self.compact_proposal {
+            self.sign_hash(alloy_rlp::encode(&compact).into())
+        } else {
+            self.sign_hash(alloy_rlp::encode(&proposal).into())
+        };
         let signature = self
-            .util
-            .sign(self.util.hash(alloy_rlp::encode(&proposal).into()))
-            .map_err(|err| ConsensusError::CryptoErr(format!("{:?}", err)))?;
+            .sign_by(move |crypto| crypto.sign_proposal(&compact, hash))
+            .await
+            .map_err(|err| ConsensusError::CryptoCallErr {
+                op: "sign proposal",
+                source: err.into(),
+            })?;
 
         Ok(SignedProposal {
             signature,
@@ -1394,18 +3764,108 @@ where
         })
     }
 
-    fn sign_vote(&self, vote: Vote) -> ConsensusResult<SignedVote> {
-        log::debug!("Overlord: state sign a vote");
-        let signature = self
-            .util
-            .sign(self.util.hash(alloy_rlp::encode(&vote).into()))
-            .map_err(|err| ConsensusError::CryptoErr(format!("{:?}", err)))?;
+    async fn sign_vote(&mut self, vote: Vote) -> ConsensusResult<SignedVote> {
+        self.check_halted()?;
+        self.guard_vote(
+            vote.height,
+            vote.round,
+            vote.vote_type.clone(),
+            &vote.block_hash,
+        )?;
+        self.guard_mark(
+            SignKind::Vote(vote.vote_type.clone()),
+            vote.height,
+            vote.round,
+            &vote.block_hash,
+        )?;
+
+        log::debug!("Overlord: state sign a vote");
+        let (hash, signed) = (
+            self.sign_hash(alloy_rlp::encode(&vote).into()),
+            vote.clone(),
+        );
+        let signature = self
+            .sign_by(move |crypto| crypto.sign_vote(&signed, hash))
+            .await
+            .map_err(|err| ConsensusError::CryptoCallErr {
+                op: "sign vote",
+                source: err.into(),
+            })?;
+
+        Ok(SignedVote {
+            voter: self.address.clone(),
+            signature,
+            vote,
+            extension: None,
+        })
+    }
+
+    /// Get the vote extension from the application and sign it. If the application fails to
+    /// extend the vote or extends nothing, vote without an extension.
+    async fn extend_vote(&self, vote: &Vote) -> Option<VoteExtension> {
+        let data = match self
+            .function
+            .extend_vote(
+                Context::new(),
+                vote.height,
+                vote.round,
+                vote.block_hash.clone(),
+            )
+            .await
+        {
+            Ok(data) if !data.is_empty() => data,
+            Ok(_) => return None,
+            Err(err) => {
+                log::error!("Overlord: state extend vote error {:?}", err);
+                return None;
+            }
+        };
+
+        log::debug!("Overlord: state sign a vote extension");
+        let hash = self.sign_hash(VoteExtension::sign_payload(vote, &data));
+        let (signed, extension) = (vote.clone(), data.clone());
+        match self
+            .sign_by(move |crypto| crypto.sign_extension(&signed, &extension, hash))
+            .await
+        {
+            Ok(signature) => Some(VoteExtension { data, signature }),
+            Err(err) => {
+                log::error!("Overlord: state sign vote extension error {:?}", err);
+                None
+            }
+        }
+    }
+
+    /// Only the precommit votes of a non-empty block hash can be extended, and the signature of
+    /// the extension has been verified with the vote.
+    async fn verify_vote_extension(
+        &self,
+        ctx: Context,
+        signed_vote: &SignedVote,
+    ) -> ConsensusResult<()> {
+        let extension = match &signed_vote.extension {
+            Some(extension) => extension,
+            None => return Ok(()),
+        };
+
+        if signed_vote.is_prevote() || signed_vote.vote.block_hash.is_empty() {
+            return Err(ConsensusError::PrecommitErr(
+                "Unexpected vote extension".to_string(),
+            ));
+        }
 
-        Ok(SignedVote {
-            voter: self.address.clone(),
-            signature,
-            vote,
-        })
+        self.function
+            .verify_vote_extension(
+                ctx,
+                signed_vote.get_height(),
+                signed_vote.voter.clone(),
+                signed_vote.vote.block_hash.clone(),
+                extension.data.clone(),
+            )
+            .await
+            .map_err(|err| {
+                ConsensusError::PrecommitErr(format!("Invalid vote extension {:?}", err))
+            })
     }
 
     fn aggregate_signatures(
@@ -1428,14 +3888,27 @@ where
         let signature = self
             .util
             .aggregate_signatures(signatures, voters)
-            .map_err(|err| ConsensusError::CryptoErr(format!("{:?}", err)))?;
+            .map_err(|err| ConsensusError::CryptoCallErr {
+                op: "aggregate",
+                source: err.into(),
+            })?;
         Ok(signature)
     }
 
-    fn verify_proposer(&self, height: u64, round: u64, address: &Address) -> ConsensusResult<()> {
+    fn verify_proposer(
+        &self,
+        height: Height,
+        round: Round,
+        address: &Address,
+    ) -> ConsensusResult<()> {
         log::debug!("Overlord: state verify a proposer");
         self.verify_address(address)?;
-        if address != &self.authority.get_proposer(height, round)? {
+        // The VRF seed of a future height is unknown, the proposer is verified again when the
+        // proposal is re-checked in that height.
+        if self.vrf_proposer && height != self.height {
+            return Ok(());
+        }
+        if address != &self.get_proposer(height, round)? {
             return Err(ConsensusError::ProposalErr("Invalid proposer".to_string()));
         }
         Ok(())
@@ -1444,7 +3917,7 @@ where
     /// Check whether the given address is included in the corresponding authority list.
     fn verify_address(&self, address: &Address) -> ConsensusResult<()> {
         if !self.authority.contains(address) {
-            return Err(ConsensusError::InvalidAddress);
+            return Err(ConsensusError::InvalidAddress(address.clone()));
         }
         Ok(())
     }
@@ -1457,8 +3930,8 @@ where
         );
 
         let _ = self
-            .function
-            .transmit_to_relayer(ctx, self.leader_address.clone(), msg.clone())
+            .network
+            .transmit(ctx, self.leader_address.clone(), msg.clone())
             .await
             .map_err(|err| {
                 log::error!(
@@ -1476,54 +3949,252 @@ where
         );
 
         let _ = self
-            .function
-            .broadcast_to_other(ctx, msg.clone())
+            .network
+            .broadcast(ctx, msg.clone())
             .await
             .map_err(|err| {
                 log::error!("Overlord: state broadcast message failed {:?}", err);
             });
     }
 
-    fn report_error(&self, ctx: Context, err: ConsensusError) {
-        self.function.report_error(ctx, err);
+    /// Record a vote of self. If self has signed a conflicting vote, report the fatal safety error,
+    /// and refuse to sign anything further when the overlord is configured to halt.
+    fn guard_vote(
+        &mut self,
+        height: Height,
+        round: Round,
+        vote_type: VoteType,
+        hash: &Hash,
+    ) -> ConsensusResult<()> {
+        if let Err(e) = self.sign_guard.record(height, round, vote_type, hash) {
+            log::error!("Overlord: state detect a double sign {}", e);
+            self.report_error(Context::new(), e.clone(), None);
+            if self.halt_on_double_sign {
+                log::error!("Overlord: state halt signing");
+                self.halted = true;
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Advance the signing high-water mark to a payload which self is about to sign. A payload
+    /// below the mark or conflicting with it is refused, and the fatal safety error is reported.
+    fn guard_mark(
+        &mut self,
+        kind: SignKind,
+        height: Height,
+        round: Round,
+        hash: &Hash,
+    ) -> ConsensusResult<()> {
+        if let Err(e) = self.sign_guard.advance(kind, height, round, hash) {
+            log::error!("Overlord: state refuse to sign {}", e);
+            self.report_error(Context::new(), e.clone(), None);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Keep a QC in the vote collector. A kept QC of another block hash with the same height,
+    /// round and type means that more than a third of the vote weight has signed conflicting
+    /// votes, so self halts signing, reports both QCs to the application and returns the fatal
+    /// fork error. The QC must have passed `check_qc` against the authority list of its height,
+    /// the unverified QCs of a future height are cached by `cache_future_msg` instead.
+    fn keep_qc(&mut self, qc: AggregatedVote) -> ConsensusResult<()> {
+        let kept = match self.votes.set_qc(qc.clone()) {
+            Some(kept) => kept,
+            None => return Ok(()),
+        };
+
+        let e = ConsensusError::ForkDetected {
+            vote_type: qc.vote_type.clone(),
+            height: qc.height,
+            round: qc.round,
+            kept: kept.block_hash.clone(),
+            received: qc.block_hash.clone(),
+        };
+        log::error!("Overlord: state detect a fork {}, halt signing", e);
+        self.halted = true;
+        self.function.report_fork(Context::new(), kept, qc);
+        self.report_error(Context::new(), e.clone(), None);
+        Err(e)
+    }
+
+    /// Record the vote of self if self is a voter of the QC. The QC must be verified, or be saved
+    /// by self, as a forged one would refuse the real vote of self.
+    fn guard_qc(&mut self, qc: &AggregatedVote) -> ConsensusResult<()> {
+        let voters = match self.authority.get_voters(&qc.signature.address_bitmap) {
+            Ok(voters) => voters,
+            Err(_) => return Ok(()),
+        };
+        if voters.contains(&self.address) {
+            self.guard_vote(qc.height, qc.round, qc.vote_type.clone(), &qc.block_hash)?;
+        }
+        Ok(())
+    }
+
+    fn check_halted(&self) -> ConsensusResult<()> {
+        if self.halted {
+            return Err(ConsensusError::SignHaltedErr);
+        }
+        Ok(())
+    }
+
+    /// Report the error with its severity and the context of the state. The sender is the signer
+    /// of the offending message, if the error is caused by a message.
+    fn report_error(&self, ctx: Context, err: ConsensusError, sender: Option<Address>) {
+        let report = ErrorReport {
+            severity: err.severity(),
+            height: self.height,
+            round: self.round,
+            step: self.step.clone(),
+            peer: PeerId::from_context(&ctx),
+            sender,
+        };
+        self.function.report_error_detail(ctx, err, report);
+    }
+
+    /// Hand an artifact to the artifact sink, if any.
+    fn archive(&self, f: impl FnOnce(&dyn ArtifactSink<T>)) {
+        if let Some(sink) = self.artifact_sink.as_ref() {
+            f(sink.as_ref());
+        }
+    }
+
+    /// Report the formation time of a QC of the current height and the participation in it.
+    fn report_qc(&self, qc: &AggregatedVote) {
+        if qc.round == self.round {
+            self.reporter.report_qc_formation(
+                qc.height,
+                qc.round,
+                qc.vote_type.clone(),
+                self.round_start.elapsed(),
+            );
+        }
+
+        let voted = match self.authority.get_voters(&qc.signature.address_bitmap) {
+            Ok(voted) => voted,
+            Err(_) => return,
+        };
+        let absent = self.absent_voters(&voted);
+        self.reporter.report_vote_participation(VoteParticipation {
+            height: qc.height,
+            round: qc.round,
+            vote_type: qc.vote_type.clone(),
+            voted,
+            absent,
+        });
+    }
+
+    /// The authorities of the current height that are not in the given voters.
+    fn absent_voters(&self, voted: &[Address]) -> Vec<Address> {
+        self.authority
+            .get_authority_list()
+            .into_iter()
+            .map(|node| node.address)
+            .filter(|address| !voted.contains(address))
+            .collect()
+    }
+
+    /// Report the height as stalled if it passes a threshold of the watchdog.
+    fn check_stalled(&mut self) {
+        if !self.consensus_power || self.stopped || !self.stall.check(self.round) {
+            return;
+        }
+
+        let diagnostics = self.stall_diagnostics();
+        log::warn!(
+            "Overlord: state height {} stalled at round {}, step {}, lock round {:?}",
+            self.height,
+            self.round,
+            diagnostics.step,
+            diagnostics.lock_round
+        );
+        self.function
+            .report_stalled(Context::new(), self.height, self.round, diagnostics);
+    }
+
+    fn stall_diagnostics(&mut self) -> StallDiagnostics {
+        let lock_hash = self.lock_round.and_then(|round| {
+            self.votes
+                .get_qc_by_id(self.height, round, VoteType::Prevote)
+                .ok()
+                .map(|qc| qc.block_hash)
+        });
+
+        StallDiagnostics {
+            step: self.step.clone(),
+            elapsed: self.stall.elapsed().as_millis() as u64,
+            leader: self.leader_address.clone(),
+            lock_round: self.lock_round,
+            lock_hash,
+            missing_prevotes: self.missing_voters(VoteType::Prevote),
+            missing_precommits: self.missing_voters(VoteType::Precommit),
+            chokes: self.chokes.all_chokes(),
+        }
+    }
+
+    /// The authorities of which neither a vote nor a QC of the current round is seen.
+    fn missing_voters(&mut self, vote_type: VoteType) -> Vec<Address> {
+        let voted = match self
+            .votes
+            .get_qc_by_id(self.height, self.round, vote_type.clone())
+        {
+            Ok(qc) => self
+                .authority
+                .get_voters(&qc.signature.address_bitmap)
+                .unwrap_or_default(),
+            Err(_) => self
+                .votes
+                .get_vote_map(self.height, self.round, vote_type)
+                .map(|map| map.values().flatten().cloned().collect())
+                .unwrap_or_default(),
+        };
+        self.absent_voters(&voted)
     }
 
-    fn report_view_change(&self, round: u64, reason: ViewChangeReason) {
+    fn report_view_change(&self, round: Round, reason: ViewChangeReason) {
         self.function
             .report_view_change(Context::new(), self.height, round, reason)
     }
 
-    fn view_change_reason(&mut self, round: u64, update_from: &FromWhere) -> ViewChangeReason {
+    fn view_change_reason(&mut self, round: Round, update_from: &FromWhere) -> ViewChangeReason {
         if round != update_from.get_round() {
             return update_from.to_reason(round);
         }
+        if self.forced_round == Some(round) {
+            return ViewChangeReason::Forced;
+        }
 
         let height = self.height;
 
         // Leader condition
         if self.is_leader {
-            if let Ok(qc) = self.votes.get_qc_by_id(height, round, VoteType::Prevote) {
-                if !qc.block_hash.is_empty() {
-                    return ViewChangeReason::LeaderReceivedVoteBelowThreshold(VoteType::Precommit);
-                }
-            }
-            return ViewChangeReason::LeaderReceivedVoteBelowThreshold(VoteType::Prevote);
+            let vote_type = match self.votes.get_qc_by_id(height, round, VoteType::Prevote) {
+                Ok(qc) if !qc.block_hash.is_empty() => VoteType::Precommit,
+                _ => VoteType::Prevote,
+            };
+            return ViewChangeReason::LeaderReceivedVoteBelowThreshold {
+                missing: self.missing_voters(vote_type.clone()),
+                vote_type,
+            };
         }
 
         // Replica condition
         // No proposal case.
         let proposal = self.proposals.get(height, round);
         if proposal.is_err() {
-            return ViewChangeReason::NoProposalFromNetwork;
+            return ViewChangeReason::NoProposalFromNetwork {
+                proposer: self.leader_address.clone(),
+            };
         }
 
         // Check block failed case.
         let proposal = proposal.unwrap().0;
-        if !self
-            .is_full_transaction
-            .contains_key(&proposal.proposal.block_hash)
-        {
-            return ViewChangeReason::CheckBlockNotPass;
+        if !self.try_get_full_txs(&proposal.proposal.block_hash) {
+            return ViewChangeReason::CheckBlockNotPass {
+                block_hash: proposal.proposal.block_hash,
+            };
         }
 
         if self
@@ -1543,7 +4214,7 @@ where
         }
     }
 
-    fn check_choke_above_threshold(&mut self) -> ConsensusResult<()> {
+    async fn check_choke_above_threshold(&mut self) -> ConsensusResult<()> {
         self.chokes.print_round_choke_log(self.round);
         if let Some(round) = self.chokes.max_round_above_threshold(self.authority.len()) {
             if round < self.round {
@@ -1561,15 +4232,17 @@ where
                 voters.push(sc.address.clone());
             }
             let sig = self.aggregate_signatures(sigs, voters.clone())?;
-            self.chokes.set_qc(
+            let choke_qc = AggregatedChoke {
+                height: self.height,
+                signature: sig,
                 round,
-                AggregatedChoke {
-                    height: self.height,
-                    signature: sig,
-                    round,
-                    voters,
-                },
-            );
+                voters,
+            };
+            self.archive(|sink| sink.archive_choke_qc(&choke_qc));
+            self.chokes.set_qc(round, choke_qc.clone());
+            if round > self.round {
+                self.broadcast_round_summary(choke_qc).await;
+            }
 
             log::debug!(
                 "Overlord: state trigger SMR go on {} round of height {}",
@@ -1590,54 +4263,265 @@ where
         Ok(())
     }
 
-    #[tracing_span(
-        kind = "overlord",
-        tags = "{'height': 'self.height', 'round': 'self.round'}"
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "overlord.check_block",
+            skip_all,
+            fields(kind = "overlord", height = %self.height, round = %self.round)
+        )
+    )]
+    #[cfg_attr(
+        feature = "apm",
+        tracing_span(
+            kind = "overlord",
+            tags = "{'height': 'self.height', 'round': 'self.round'}"
+        )
     )]
-    async fn check_block(&mut self, ctx: Context, hash: Hash, block: T) {
+    async fn check_block(&mut self, ctx: Context, hash: Hash, block: Arc<T>) {
         let height = self.height;
         let round = self.round;
-        let function = Arc::clone(&self.function);
-        let resp_tx = self.resp_tx.clone();
 
-        tokio::spawn(async move {
-            if let Err(e) =
-                check_current_block(ctx, function, height, round, hash.clone(), block, resp_tx)
-                    .await
-            {
-                log::error!("Overlord: state check block failed: {:?}", e);
+        // The check of the same block supersedes, as the response is used by hash.
+        if self.is_full_transaction.contains_key(&hash)
+            || self.check_tasks.iter().any(|(h, _, task_hash, task)| {
+                *h == height && task_hash == &hash && !task.is_finished()
+            })
+            || self
+                .check_queue
+                .iter()
+                .any(|(_, h, _, task_hash, _)| *h == height && task_hash == &hash)
+        {
+            log::debug!(
+                "Overlord: state skip checking block {:?} which is checked or in checking",
+                hex_encode(hash)
+            );
+            return;
+        }
+
+        let ctx = ctx
+            .with_value(HEIGHT_CANCEL_KEY, self.height_cancel.clone())
+            .with_value(ROUND_CANCEL_KEY, self.round_cancel.clone());
+        self.check_queue
+            .push_back((ctx, height, round, hash, block));
+        self.spawn_check_tasks();
+    }
+
+    /// Check a block of which a QC arrives again, if the check of the block timed out. The block
+    /// in checking or of which the check has a result is skipped.
+    async fn recheck_block(&mut self, hash: &Hash) {
+        if let Some(block) = self.hash_with_block.peek(hash).cloned() {
+            self.check_block(Context::new(), hash.clone(), block).await;
+        }
+    }
+
+    /// Spawn the queued check block tasks while the count of the running tasks is less than the
+    /// maximum.
+    fn spawn_check_tasks(&mut self) {
+        self.check_tasks
+            .retain(|(_, _, _, task)| !task.is_finished());
+
+        while self.check_tasks.len() < self.max_check_tasks {
+            let (ctx, height, round, hash, block) = match self.check_queue.pop_front() {
+                Some(item) => item,
+                None => break,
+            };
+            let provider = Arc::clone(&self.block_provider);
+            let mut resp_tx = self.resp_tx.clone();
+            let timeout = self.check_block_timeout;
+            // The application owns the block to check, so it is materialized here.
+            let block = Arc::try_unwrap(block).unwrap_or_else(|block| block.as_ref().clone());
+
+            self.check_spawned += 1;
+            self.check_tasks.push((
+                height,
+                round,
+                hash.clone(),
+                tokio::spawn(async move {
+                    let res = check_current_block(
+                        ctx,
+                        provider,
+                        height,
+                        round,
+                        hash.clone(),
+                        block,
+                        timeout,
+                    )
+                    .await;
+                    let resp = VerifyResp {
+                        height,
+                        round,
+                        block_hash: hash,
+                        is_pass: res == Some(true),
+                        is_timeout: res.is_none(),
+                    };
+                    if let Err(e) = resp_tx.send(resp).await {
+                        log::error!("Overlord: state send check block response error {:?}", e);
+                    }
+                }),
+            ));
+        }
+    }
+
+    /// Cancel the running and queued check block tasks of the heights and rounds lower than the
+    /// current, and the block fetches of the heights lower than the current. The responses of the
+    /// lower heights are ignored, and a block of a lower round of the current height is checked
+    /// again once it is proposed again or a QC of it arrives.
+    fn cancel_stale_checks(&mut self) {
+        let (height, round) = (self.height, self.round);
+        let is_stale = |h: Height, r: Round| h < height || (h == height && r < round);
+        let mut cancelled = 0;
+        self.check_tasks.retain(|(h, r, _, task)| {
+            if is_stale(*h, *r) && !task.is_finished() {
+                task.abort();
+                cancelled += 1;
+                return false;
             }
+            !task.is_finished()
         });
+
+        self.fetch_tasks.retain(|(h, task)| {
+            if *h < height {
+                task.abort();
+                return false;
+            }
+            !task.is_finished()
+        });
+
+        let queued = self.check_queue.len();
+        self.check_queue.retain(|(_, h, r, _, _)| !is_stale(*h, *r));
+        cancelled += queued - self.check_queue.len();
+
+        if cancelled > 0 {
+            log::debug!(
+                "Overlord: state cancel {} stale check block tasks at height {}, round {}",
+                cancelled,
+                height,
+                round
+            );
+        }
+        self.check_cancelled += cancelled as u64;
+        self.spawn_check_tasks();
+    }
+
+    /// Cancel all the height scoped work as a status removes self from the authority list, and
+    /// report the abandoned work to the application. The cancellation flag of the abandoned height
+    /// is cancelled already.
+    fn abandon_height(&mut self, ctx: Context, height: Height) {
+        let mut check_tasks = self.check_queue.len() as u64;
+        self.check_queue.clear();
+        for (_, _, _, task) in self.check_tasks.drain(..) {
+            if !task.is_finished() {
+                task.abort();
+                check_tasks += 1;
+            }
+        }
+        self.check_cancelled += check_tasks;
+
+        let mut block_fetches = 0;
+        for (_, task) in self.fetch_tasks.drain(..) {
+            if !task.is_finished() {
+                task.abort();
+                block_fetches += 1;
+            }
+        }
+        self.parts.clear();
+
+        let work = AbandonedWork {
+            height,
+            check_tasks,
+            block_fetches,
+        };
+        log::info!("Overlord: state {}", work);
+        if check_tasks > 0 || block_fetches > 0 {
+            self.function.report_abandoned_height(ctx, work);
+        }
     }
 
     async fn save_wal(&mut self, step: Step, lock: Option<WalLock<T>>) -> ConsensusResult<()> {
-        let wal_info = WalInfo {
+        self.step = step.clone();
+        self.lock_round = lock.as_ref().map(|polc| polc.lock_round);
+        self.pin_lock();
+        // Self signs a vote or a choke after saving the entry of these steps unless it is paused.
+        let critical = lock.is_some() || (!self.paused && step != Step::Propose);
+        let wal_info = self.wal_info(step, lock);
+        self.write_wal(&wal_info, critical).await
+    }
+
+    fn wal_info(&self, step: Step, lock: Option<WalLock<T>>) -> WalInfo<T> {
+        let (votes, qcs) = if self.wal_votes {
+            (
+                self.votes.round_votes(self.height, self.round),
+                self.votes.height_qcs(self.height),
+            )
+        } else {
+            (Vec::new(), Vec::new())
+        };
+        WalInfo {
             height: self.height,
             round: self.round,
-            step: step.clone(),
+            step,
             from: self.update_from_where.clone(),
             lock,
-        };
+            vrf_seed: (self.vrf_proposer && !self.vrf_seed.is_empty())
+                .then(|| self.vrf_seed.clone()),
+            votes,
+            qcs,
+        }
+    }
 
+    async fn write_wal(&mut self, wal_info: &WalInfo<T>, critical: bool) -> ConsensusResult<()> {
+        let (height, round, step) = (wal_info.height, wal_info.round, wal_info.step.clone());
+        let record = encode_wal_record(&alloy_rlp::encode(wal_info));
+        if let Some(recorder) = self.crash_recorder.as_ref() {
+            recorder.record_wal(height, round, step.clone(), record.clone());
+        }
+        let start = Instant::now();
         self.wal
-            .save(alloy_rlp::encode(&wal_info).into())
+            .save(height, round, step.clone(), record, critical)
             .await
             .map_err(|e| {
                 log::error!("Overlord: state save wal error {:?}", e);
                 ConsensusError::SaveWalErr {
-                    height: self.height,
-                    round: self.round,
+                    height,
+                    round,
                     step: step.to_string(),
                 }
             })?;
+        self.reporter
+            .report_wal_duration(height, round, step, start.elapsed());
         Ok(())
     }
 
+    /// Pin the current round and the lock round in the vote collector, and the locked block and
+    /// the block proposed in the current round in the block cache, so that the messages of spam
+    /// rounds never evict what the lock and the commit are made of.
+    fn pin_lock(&mut self) {
+        let lock_hash = self.lock_round.and_then(|round| {
+            self.votes
+                .get_qc_by_id(self.height, round, VoteType::Prevote)
+                .ok()
+                .map(|qc| qc.block_hash)
+        });
+        let round_hash = self.proposals.block_hash(self.height, self.round);
+        self.votes
+            .pin_rounds(self.height, iter::once(self.round).chain(self.lock_round));
+        self.hash_with_block
+            .pin(lock_hash.into_iter().chain(round_hash));
+    }
+
     async fn save_wal_with_lock_round(
         &mut self,
         step: Step,
-        lock_round: Option<u64>,
+        lock_round: Option<Round>,
     ) -> ConsensusResult<()> {
+        let polc = self.wal_lock(lock_round)?;
+        self.save_wal(step, polc).await?;
+        Ok(())
+    }
+
+    /// The lock of the given round with the locked block.
+    fn wal_lock(&mut self, lock_round: Option<Round>) -> ConsensusResult<Option<WalLock<T>>> {
         let polc = if let Some(round) = lock_round {
             if let Ok(qc) = self
                 .votes
@@ -1651,7 +4535,7 @@ where
                 Some(WalLock {
                     lock_round: round,
                     lock_votes: qc,
-                    content: block.clone(),
+                    content: Arc::clone(block),
                 })
             } else {
                 return Err(ConsensusError::Other("no qc".to_string()));
@@ -1659,9 +4543,7 @@ where
         } else {
             None
         };
-
-        self.save_wal(step, polc).await?;
-        Ok(())
+        Ok(polc)
     }
 
     fn wal_lost(&mut self) -> ConsensusResult<()> {
@@ -1699,20 +4581,34 @@ where
 
         let wal_info = wal_info.unwrap();
         log::info!("overlord: start from wal {}", wal_info);
+        self.recover(wal_info).await
+    }
 
+    /// Recover the state from a wal info, which is loaded from the wal or imported from a
+    /// snapshot.
+    async fn recover(&mut self, wal_info: WalInfo<T>) -> ConsensusResult<()> {
         // recover basic state
         self.height = wal_info.height;
         self.round = wal_info.round;
+        self.vrf_seed = wal_info.vrf_seed.clone().unwrap_or_default();
         self.is_leader = self.is_proposer()?;
         self.update_from_where = wal_info.from.clone();
 
+        // Recover the votes of self from the QCs in the wal, so that self never signs a
+        // conflicting vote after restarting.
+        if let UpdateFrom::PrevoteQC(qc) | UpdateFrom::PrecommitQC(qc) = &wal_info.from {
+            self.guard_qc(qc)?;
+        }
+
         // recover lock state
         if wal_info.lock.is_some() {
             let lock = wal_info.lock.clone().unwrap();
             let qc = lock.lock_votes.clone();
-            self.votes.set_qc(qc.clone());
+            self.guard_qc(&qc)?;
+            self.keep_qc(qc.clone())?;
             self.hash_with_block.insert(qc.block_hash, lock.content);
         }
+        let resume = self.recover_votes(&wal_info)?;
 
         if wal_info.step == Step::Commit {
             let qc = wal_info
@@ -1736,12 +4632,69 @@ where
             height: self.height,
             wal_info: Some(wal_info.into_smr_base()),
         })?;
+
+        for vote_type in resume.into_iter() {
+            if let Err(e) = self.count_votes(Context::new(), vote_type) {
+                log::warn!("Overlord: state resume counting votes error {:?}", e);
+            }
+        }
         Ok(())
     }
 
+    /// Recover the QCs and the votes saved in the wal info into the collector. Return the vote
+    /// types of the recovered votes to count once the SMR is recovered, so that the aggregation
+    /// resumes where it left off. The votes of the voters out of the authority list are skipped.
+    fn recover_votes(&mut self, wal_info: &WalInfo<T>) -> ConsensusResult<Vec<VoteType>> {
+        let height = self.height;
+        for qc in wal_info.qcs.iter().filter(|qc| qc.height == height) {
+            self.guard_qc(qc)?;
+            self.keep_qc(qc.clone())?;
+        }
+
+        let mut resume = Vec::new();
+        for signed_vote in wal_info.votes.iter() {
+            let vote = &signed_vote.vote;
+            if vote.height != self.height || vote.round != self.round {
+                continue;
+            }
+            let weight = match self.authority.get_vote_weight(&signed_vote.voter) {
+                Ok(weight) => *weight,
+                Err(_) => continue,
+            };
+            if signed_vote.voter == self.address {
+                self.guard_vote(
+                    vote.height,
+                    vote.round,
+                    vote.vote_type.clone(),
+                    &vote.block_hash,
+                )?;
+            }
+            self.votes.insert_vote(
+                Context::new(),
+                signed_vote.get_hash(),
+                signed_vote.clone(),
+                signed_vote.voter.clone(),
+                weight,
+            );
+            if !resume.contains(&vote.vote_type) {
+                resume.push(vote.vote_type.clone());
+            }
+        }
+
+        if !wal_info.votes.is_empty() {
+            log::info!(
+                "Overlord: state recover {} votes and {} qcs from wal",
+                wal_info.votes.len(),
+                wal_info.qcs.len()
+            );
+        }
+        Ok(resume)
+    }
+
     async fn load_wal(&mut self) -> ConsensusResult<Option<WalInfo<T>>> {
         let tmp = self
             .wal
+            .wal()
             .load()
             .await
             .map_err(|e| ConsensusError::LoadWalErr(e.to_string()))?;
@@ -1750,17 +4703,55 @@ where
             return Ok(None);
         }
 
-        let info: WalInfo<T> = Decodable::decode(&mut tmp.unwrap().as_ref())
+        let record = tmp.unwrap();
+        let (_, payload) = decode_wal_record(&record)?;
+        let info: WalInfo<T> = Decodable::decode(&mut payload.as_ref())
             .map_err(|e| ConsensusError::LoadWalErr(e.to_string()))?;
+        self.wal.resume(info.height);
         Ok(Some(info))
     }
 
+    /// Measure the clock skew of the proposer by the timestamp of a block. Return `false` if the
+    /// skew is beyond the maximum clock skew, and `true` for a block without a timestamp.
+    fn check_clock_skew(&mut self, proposer: &Address, block: &T) -> bool {
+        let timestamp = match self.function.block_timestamp(block) {
+            Some(timestamp) => timestamp,
+            None => return true,
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_millis() as u64)
+            .unwrap_or_default();
+        let skew = if timestamp >= now {
+            i64::try_from(timestamp - now).unwrap_or(i64::MAX)
+        } else {
+            i64::try_from(now - timestamp).map_or(i64::MIN, |skew| -skew)
+        };
+        let is_outlier = self
+            .max_clock_skew
+            .is_some_and(|max| u128::from(skew.unsigned_abs()) > max.as_millis());
+        self.clock_skews
+            .entry(proposer.clone())
+            .or_insert_with(|| ClockSkew::new(proposer.clone()))
+            .record(skew, is_outlier);
+
+        if is_outlier {
+            log::warn!(
+                "Overlord: state refuse the block of {:?} height {} with a clock skew of {} ms",
+                hex_encode(proposer.clone()),
+                self.height,
+                skew
+            );
+        }
+        !is_outlier
+    }
+
     /// When block hash is empty, return true directly.
     fn try_get_full_txs(&self, hash: &Hash) -> bool {
         log::debug!("Overlord: state check if get full transactions");
         if hash.is_empty() {
             return true;
-        } else if let Some(res) = self.is_full_transaction.get(hash) {
+        } else if let Some(res) = self.is_full_transaction.peek(hash) {
             return *res;
         }
         false
@@ -1776,7 +4767,7 @@ where
             }
 
             FromWhere::PrecommitQC(round) => {
-                let qc = if round == u64::MAX {
+                let qc = if round == Round(u64::MAX) {
                     mock_init_qc()
                 } else {
                     self.votes
@@ -1801,16 +4792,17 @@ where
 
     /// Filter the proposals that do not need to be handed.
     /// 1. Outdated proposals
-    /// 2. A much higher height which is larger than the FUTURE_HEIGHT_GAP
-    /// 3. A much higher round which is larger than the FUTURE_ROUND_GAP
+    /// 2. A much higher height which is larger than the future height gap
+    /// 3. A much higher round which is larger than the future round gap
     fn filter_signed_proposal(
         &mut self,
         ctx: Context,
-        height: u64,
-        round: u64,
+        height: Height,
+        round: Round,
         signed_proposal: &SignedProposal<T>,
+        compact: &Option<CompactProposal>,
     ) -> ConsensusResult<bool> {
-        if self.filter_message(height, round) {
+        if self.filter_message("Signed Proposal", height, round) {
             return Ok(true);
         }
 
@@ -1824,75 +4816,152 @@ where
                 round,
             );
             self.proposals
-                .insert(ctx, height, round, signed_proposal.clone())?;
+                .insert(ctx, height, round, signed_proposal.clone(), compact.clone())?;
             return Ok(true);
         }
         Ok(false)
     }
 
-    fn filter_message(&self, height: u64, round: u64) -> bool {
-        if height < self.height || (height == self.height && round < self.round) {
-            log::debug!(
-                "Overlord: state receive an outdated message height {}, self height {}",
-                height,
-                self.height
-            );
-            return true;
-        } else if self.height + FUTURE_HEIGHT_GAP < height {
-            log::debug!(
-                "Overlord: state receive a future message height {}, self height {}",
-                height,
-                self.height
-            );
-            return true;
-        } else if (height == self.height && self.round + FUTURE_ROUND_GAP < round)
-            || (height > self.height && round > FUTURE_ROUND_GAP)
+    fn filter_message(&mut self, msg_type: &str, height: Height, round: Round) -> bool {
+        let reason = if height < self.height {
+            DropReason::OutdatedHeight
+        } else if height == self.height && round < self.round {
+            DropReason::OutdatedRound
+        } else if self.height.saturating_add(self.future_height_gap) < height {
+            DropReason::FutureHeight
+        } else if (height == self.height
+            && self.round.saturating_add(self.future_round_gap) < round)
+            || (height > self.height && round.0 > self.future_round_gap)
         {
-            log::debug!("Overlord: state receive a much higher round message");
-            return true;
+            DropReason::FutureRound
+        } else {
+            return false;
+        };
+
+        self.record_drop(msg_type, height, Some(round), reason);
+        true
+    }
+
+    /// Filter a vote or a QC of the previous height of which the round is above the round of the
+    /// commit of that height by more than the future round gap. No correct node gets so far past
+    /// the commit, and the rounds would otherwise evict the ones of the current height from the
+    /// vote collector.
+    fn filter_previous_round(&mut self, msg_type: &str, height: Height, round: Round) -> bool {
+        if round <= self.previous_round.saturating_add(self.future_round_gap) {
+            return false;
         }
+        self.record_drop(msg_type, height, Some(round), DropReason::FutureRound);
+        true
+    }
 
-        false
+    /// Count a dropped message and report it to the application.
+    fn record_drop(
+        &mut self,
+        msg_type: &str,
+        height: Height,
+        round: Option<Round>,
+        reason: DropReason,
+    ) {
+        log::debug!(
+            "Overlord: state drop a {} message height {}, round {:?} for {}, self height {}, round {}",
+            msg_type,
+            height,
+            round,
+            reason,
+            self.height,
+            self.round
+        );
+        if self.debug.matches(height, None) {
+            log::info!(
+                target: "overlord::debug",
+                "Overlord: debug drop a {} message height {}, round {:?} for {}",
+                msg_type,
+                height,
+                round,
+                reason
+            );
+        }
+        *self
+            .drops
+            .entry((msg_type.to_string(), reason))
+            .or_default() += 1;
+        self.function.report_message_drop(
+            Context::new(),
+            MessageDrop {
+                msg_type: msg_type.to_string(),
+                height,
+                round,
+                reason,
+            },
+        );
     }
 }
 
-#[tracing_span(kind = "overlord", tags = "{'height': 'height', 'round': 'round'}")]
-async fn check_current_block<U: Consensus<T>, T: Codec>(
+/// Check a block with the application, which is `None` if the check times out.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "overlord.check_current_block",
+        skip_all,
+        fields(kind = "overlord", height = %height, round = %round)
+    )
+)]
+#[cfg_attr(
+    feature = "apm",
+    tracing_span(kind = "overlord", tags = "{'height': 'height', 'round': 'round'}")
+)]
+async fn check_current_block<T: Codec>(
     ctx: Context,
-    function: Arc<U>,
-    height: u64,
-    round: u64,
+    provider: Arc<dyn BlockProvider<T>>,
+    height: Height,
+    round: Round,
     hash: Hash,
     block: T,
-    tx: UnboundedSender<VerifyResp>,
-) -> ConsensusResult<()> {
-    function
-        .check_block(ctx, height, hash.clone(), block)
-        .await
-        .map_err(|err| ConsensusError::Other(format!("check {} block error {:?}", height, err)))?;
+    timeout: Option<Duration>,
+) -> Option<bool> {
+    let check = provider.check_block(ctx, height, hash, block);
+    let res = match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, check).await {
+            Ok(res) => res,
+            Err(_) => {
+                log::warn!(
+                    "Overlord: state check block of height {}, round {} timeout",
+                    height,
+                    round
+                );
+                return None;
+            }
+        },
+        None => check.await,
+    };
 
+    if let Err(err) = res {
+        log::error!(
+            "Overlord: state check block of height {}, round {} failed: {:?}",
+            height,
+            round,
+            err
+        );
+        return Some(false);
+    }
     log::debug!("Overlord: state check block {}", true);
-    tx.unbounded_send(VerifyResp {
-        height,
-        round,
-        block_hash: hash,
-        is_pass: true,
-    })
-    .map_err(|e| ConsensusError::ChannelErr(e.to_string()))
+    Some(true)
 }
 
 fn mock_init_qc() -> AggregatedVote {
     let aggregated_signature = AggregatedSignature {
         signature: Signature::default(),
         address_bitmap: Bytes::default(),
+        kind: QcKind::Aggregated,
     };
 
     AggregatedVote {
         signature: aggregated_signature,
         vote_type: VoteType::Precommit,
-        height: 0u64,
-        round: 0u64,
+        height: Height(0),
+        round: Round(0),
         block_hash: Hash::default(),
         leader: Address::default(),
+        extensions: None,
     }
 }
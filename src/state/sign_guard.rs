@@ -0,0 +1,314 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::aux_store::{AuxStore, HeightRecords};
+use crate::error::ConsensusError;
+use crate::types::{Hash, Height, Round, Vote, VoteType};
+use crate::ConsensusResult;
+
+const SIGN_GUARD_PREFIX: &str = "sign_guard";
+const SIGN_MARK_KEY: &str = "overlord/sign_mark";
+
+/// The kind of a payload signed by self, of which the last signed one is the high-water mark.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum SignKind {
+    Proposal,
+    Vote(VoteType),
+}
+
+/// A guard of the votes signed by self. It records the votes that self signs, and the votes of
+/// self that are found in the wal or from the network, which might be signed before the node is
+/// restored from an old backup. Self must never sign two votes with different block hashes for the
+/// same height, round and vote type. The votes are persisted per height in the auxiliary store,
+/// so that they are guarded across restarts.
+///
+/// Apart from them, the guard keeps the high-water mark of each kind of payload, which is the last
+/// proposal or vote of the type that self signs. The marks are never flushed, so a payload below
+/// them is refused even if the records of its height are gone, such as after a crash which lost
+/// the wal entry of a signed payload.
+pub(crate) struct SignGuard {
+    signed: HashMap<(Height, Round, VoteType), Hash>,
+    records: HeightRecords,
+    marks: HashMap<SignKind, (Height, Round, Hash)>,
+    store: Arc<dyn AuxStore>,
+}
+
+impl std::fmt::Debug for SignGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignGuard")
+            .field("signed", &self.signed)
+            .field("records", &self.records)
+            .field("marks", &self.marks)
+            .finish()
+    }
+}
+
+impl SignGuard {
+    /// Create a guard with the votes restored from the auxiliary store.
+    pub(crate) fn new(store: Arc<dyn AuxStore>) -> ConsensusResult<Self> {
+        let (records, loaded) = HeightRecords::open(Arc::clone(&store), SIGN_GUARD_PREFIX)?;
+        let mut signed = HashMap::new();
+        for (height, raw) in loaded.into_iter() {
+            let votes: Vec<(Round, VoteType, Hash)> =
+                bcs::from_bytes(&raw).map_err(|e| ConsensusError::StorageCodecErr {
+                    context: "decode signed votes",
+                    source: e,
+                })?;
+            for (round, vote_type, hash) in votes.into_iter() {
+                signed.insert((height, round, vote_type), hash);
+            }
+        }
+
+        let raw = store
+            .get(SIGN_MARK_KEY.as_bytes())
+            .map_err(|e| ConsensusError::AuxStoreErr {
+                context: "load sign marks",
+                source: e.into(),
+            })?;
+        let mut marks = HashMap::new();
+        if let Some(raw) = raw {
+            let loaded: Vec<(SignKind, Height, Round, Hash)> =
+                bcs::from_bytes(&raw).map_err(|e| ConsensusError::StorageCodecErr {
+                    context: "decode sign marks",
+                    source: e,
+                })?;
+            for (kind, height, round, hash) in loaded.into_iter() {
+                marks.insert(kind, (height, round, hash));
+            }
+        }
+        Ok(SignGuard {
+            signed,
+            records,
+            marks,
+            store,
+        })
+    }
+
+    /// Advance the high-water mark of the kind to a payload which self is about to sign, and
+    /// persist it before the payload is signed. Return `Err()` if the payload is below the mark,
+    /// or of another block hash at the height and round of the mark.
+    pub(crate) fn advance(
+        &mut self,
+        kind: SignKind,
+        height: Height,
+        round: Round,
+        hash: &Hash,
+    ) -> ConsensusResult<()> {
+        if let Some((signed_height, signed_round, signed)) = self.marks.get(&kind) {
+            match (height, round).cmp(&(*signed_height, *signed_round)) {
+                Ordering::Greater => (),
+                Ordering::Equal if signed == hash => return Ok(()),
+                _ => {
+                    return Err(match kind {
+                        SignKind::Proposal => ConsensusError::RefuseProposalErr {
+                            height,
+                            round,
+                            signed_height: *signed_height,
+                            signed_round: *signed_round,
+                        },
+                        SignKind::Vote(vote_type) => ConsensusError::RefuseSignErr {
+                            vote: Vote {
+                                height,
+                                round,
+                                vote_type,
+                                block_hash: hash.clone(),
+                            },
+                            height: *signed_height,
+                            round: *signed_round,
+                        },
+                    })
+                }
+            }
+        }
+
+        let last = self
+            .marks
+            .insert(kind.clone(), (height, round, hash.clone()));
+        if let Err(e) = self.persist_marks() {
+            match last {
+                Some(last) => self.marks.insert(kind, last),
+                None => self.marks.remove(&kind),
+            };
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Record a vote of self. Return `Err()` if self has signed another block hash with the same
+    /// height, round and vote type.
+    pub(crate) fn record(
+        &mut self,
+        height: Height,
+        round: Round,
+        vote_type: VoteType,
+        hash: &Hash,
+    ) -> ConsensusResult<()> {
+        if let Some(signed) = self.signed.get(&(height, round, vote_type.clone())) {
+            if signed != hash {
+                return Err(ConsensusError::DoubleSignErr {
+                    vote_type,
+                    height,
+                    round,
+                    signed: signed.clone(),
+                    hash: hash.clone(),
+                });
+            }
+            return Ok(());
+        }
+
+        // The vote must be persisted before it is signed.
+        self.signed
+            .insert((height, round, vote_type.clone()), hash.clone());
+        if let Err(e) = self.persist(height) {
+            self.signed.remove(&(height, round, vote_type));
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Remove items that height is less than `till`.
+    pub(crate) fn flush(&mut self, till: Height) -> ConsensusResult<()> {
+        self.signed.retain(|(height, _, _), _| *height >= till);
+        let outdated = self.records.heights_below(till);
+        if outdated.is_empty() {
+            return Ok(());
+        }
+        self.records.update(Vec::new(), outdated)
+    }
+
+    fn persist(&mut self, height: Height) -> ConsensusResult<()> {
+        let votes = self
+            .signed
+            .iter()
+            .filter(|((h, _, _), _)| *h == height)
+            .map(|((_, round, vote_type), hash)| (*round, vote_type.clone(), hash.clone()))
+            .collect::<Vec<_>>();
+        let encode = bcs::to_bytes(&votes).map_err(|e| ConsensusError::StorageCodecErr {
+            context: "encode signed votes",
+            source: e,
+        })?;
+        self.records
+            .update(vec![(height, Bytes::from(encode))], Vec::new())
+    }
+
+    fn persist_marks(&self) -> ConsensusResult<()> {
+        let marks = self
+            .marks
+            .iter()
+            .map(|(kind, (height, round, hash))| (kind.clone(), *height, *round, hash.clone()))
+            .collect::<Vec<_>>();
+        let encode = bcs::to_bytes(&marks).map_err(|e| ConsensusError::StorageCodecErr {
+            context: "encode sign marks",
+            source: e,
+        })?;
+        self.store
+            .put(Bytes::from(SIGN_MARK_KEY), Bytes::from(encode))
+            .map_err(|e| ConsensusError::AuxStoreErr {
+                context: "save sign marks",
+                source: e.into(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::{SignGuard, SignKind};
+    use crate::aux_store::{AuxStore, MemoryAuxStore};
+    use crate::error::ConsensusError;
+    use crate::types::{Hash, Height, Round, VoteType};
+
+    #[test]
+    fn test_sign_guard() {
+        let store: Arc<dyn AuxStore> = Arc::new(MemoryAuxStore::new());
+        let mut guard = SignGuard::new(Arc::clone(&store)).unwrap();
+        let hash_a = Hash::from(vec![1u8]);
+        let hash_b = Hash::from(vec![2u8]);
+
+        assert!(guard
+            .record(Height(1), Round(0), VoteType::Prevote, &hash_a)
+            .is_ok());
+        assert!(guard
+            .record(Height(1), Round(0), VoteType::Prevote, &hash_a)
+            .is_ok());
+        assert!(guard
+            .record(Height(1), Round(0), VoteType::Precommit, &hash_b)
+            .is_ok());
+        assert!(guard
+            .record(Height(1), Round(1), VoteType::Prevote, &hash_b)
+            .is_ok());
+        assert!(matches!(
+            guard.record(Height(1), Round(0), VoteType::Prevote, &hash_b),
+            Err(ConsensusError::DoubleSignErr { .. })
+        ));
+
+        guard
+            .record(Height(2), Round(0), VoteType::Prevote, &hash_a)
+            .unwrap();
+        guard.flush(Height(2)).unwrap();
+        assert!(guard
+            .record(Height(1), Round(0), VoteType::Prevote, &hash_b)
+            .is_ok());
+        assert!(guard
+            .record(Height(2), Round(0), VoteType::Prevote, &hash_b)
+            .is_err());
+
+        // The votes of self are guarded after a restart.
+        let mut guard = SignGuard::new(store).unwrap();
+        assert!(guard
+            .record(Height(2), Round(0), VoteType::Prevote, &hash_b)
+            .is_err());
+        assert!(guard
+            .record(Height(2), Round(0), VoteType::Prevote, &hash_a)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_sign_mark() {
+        let store: Arc<dyn AuxStore> = Arc::new(MemoryAuxStore::new());
+        let mut guard = SignGuard::new(Arc::clone(&store)).unwrap();
+        let hash_a = Hash::from(vec![1u8]);
+        let hash_b = Hash::from(vec![2u8]);
+        let prevote = SignKind::Vote(VoteType::Prevote);
+
+        guard
+            .advance(prevote.clone(), Height(2), Round(1), &hash_a)
+            .unwrap();
+        guard
+            .advance(prevote.clone(), Height(2), Round(1), &hash_a)
+            .unwrap();
+        guard
+            .advance(SignKind::Proposal, Height(2), Round(1), &hash_b)
+            .unwrap();
+
+        // The marks survive a restart, even if the records of the height are flushed.
+        guard.flush(Height(3)).unwrap();
+        let mut guard = SignGuard::new(store).unwrap();
+        assert!(matches!(
+            guard.advance(prevote.clone(), Height(2), Round(1), &hash_b),
+            Err(ConsensusError::RefuseSignErr { .. })
+        ));
+        assert!(matches!(
+            guard.advance(prevote.clone(), Height(2), Round(0), &hash_a),
+            Err(ConsensusError::RefuseSignErr { .. })
+        ));
+        assert!(matches!(
+            guard.advance(SignKind::Proposal, Height(2), Round(1), &hash_a),
+            Err(ConsensusError::RefuseProposalErr { .. })
+        ));
+        assert!(guard
+            .advance(
+                SignKind::Vote(VoteType::Precommit),
+                Height(1),
+                Round(0),
+                &hash_a
+            )
+            .is_ok());
+        assert!(guard.advance(prevote, Height(2), Round(2), &hash_b).is_ok());
+    }
+}
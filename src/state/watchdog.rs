@@ -0,0 +1,91 @@
+use std::time::{Duration, Instant};
+
+use crate::types::Round;
+use crate::StallWatchdog;
+
+/// The shortest period to check the duration threshold, as millisecond.
+const MIN_CHECK_PERIOD: u64 = 100;
+
+/// The tracker of the stuck height watchdog. A height is reported as stalled once, when it passes
+/// a threshold of the watchdog without a commit.
+#[derive(Debug)]
+pub(crate) struct StallTracker {
+    config: Option<StallWatchdog>,
+    since: Instant,
+    reported: bool,
+}
+
+impl StallTracker {
+    pub(crate) fn new(config: Option<StallWatchdog>) -> Self {
+        StallTracker {
+            config,
+            since: Instant::now(),
+            reported: false,
+        }
+    }
+
+    /// Start tracking a new height.
+    pub(crate) fn reset(&mut self) {
+        self.since = Instant::now();
+        self.reported = false;
+    }
+
+    /// The duration of the tracked height so far.
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.since.elapsed()
+    }
+
+    /// The period to check the duration threshold, if it is enabled.
+    pub(crate) fn check_period(&self) -> Option<Duration> {
+        self.config
+            .as_ref()
+            .filter(|config| config.max_duration > 0)
+            .map(|config| Duration::from_millis((config.max_duration / 4).max(MIN_CHECK_PERIOD)))
+    }
+
+    /// Whether the height is stalled at the given round and is not reported yet. The height is
+    /// marked as reported once it is stalled.
+    pub(crate) fn check(&mut self, round: Round) -> bool {
+        let config = match self.config.as_ref() {
+            Some(config) if !self.reported => config,
+            _ => return false,
+        };
+
+        let rounds = config.max_rounds > 0 && round.0 >= config.max_rounds;
+        let duration =
+            config.max_duration > 0 && self.elapsed() >= Duration::from_millis(config.max_duration);
+        self.reported = rounds || duration;
+        self.reported
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::StallTracker;
+    use crate::types::Round;
+    use crate::StallWatchdog;
+
+    #[test]
+    fn test_stall_tracker() {
+        let mut tracker = StallTracker::new(None);
+        assert!(!tracker.check(Round(100)));
+        assert_eq!(tracker.check_period(), None);
+
+        let mut tracker = StallTracker::new(Some(StallWatchdog::new(3, 0)));
+        assert_eq!(tracker.check_period(), None);
+        assert!(!tracker.check(Round(2)));
+        assert!(tracker.check(Round(3)));
+        // A stalled height is reported once.
+        assert!(!tracker.check(Round(4)));
+        tracker.reset();
+        assert!(!tracker.check(Round(0)));
+        assert!(tracker.check(Round(3)));
+
+        let mut tracker = StallTracker::new(Some(StallWatchdog::new(0, 1)));
+        assert_eq!(tracker.check_period(), Some(Duration::from_millis(100)));
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(tracker.check(Round(0)));
+    }
+}
@@ -0,0 +1,401 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
+use std::error::Error;
+use std::hash::{Hash as _, Hasher};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use creep::Context;
+use parking_lot::Mutex;
+
+use crate::error::ConsensusError;
+use crate::types::{
+    Address, Commit, Hash, Height, Node, OverlordMsg, Round, Signature, Status, ViewChangeReason,
+};
+use crate::{Codec, Consensus, Crypto, Wal};
+
+/// Hash a message to 32 bytes deterministically. It is not collision resistant against an
+/// adversary, which is not a concern of the tests.
+pub fn mock_hash(msg: &[u8]) -> Hash {
+    let mut out = Vec::with_capacity(32);
+    for i in 0u8..4 {
+        let mut hasher = DefaultHasher::new();
+        i.hash(&mut hasher);
+        msg.hash(&mut hasher);
+        out.extend_from_slice(&hasher.finish().to_be_bytes());
+    }
+    Hash::from(out)
+}
+
+/// A dummy crypto, of which the signature is the address of the signer and every signature
+/// passes the verification. The VRF proof is the address of the prover as well, and the output
+/// is the hash of the address and the message.
+#[derive(Clone, Debug)]
+pub struct MockCrypto {
+    address: Address,
+}
+
+impl MockCrypto {
+    /// Create a crypto which signs as the given address.
+    pub fn new(address: Address) -> Self {
+        MockCrypto { address }
+    }
+}
+
+impl Crypto for MockCrypto {
+    fn hash(&self, msg: Bytes) -> Hash {
+        mock_hash(&msg)
+    }
+
+    fn sign(&self, _hash: Hash) -> Result<Signature, Box<dyn Error + Send>> {
+        Ok(Signature::from(self.address.clone().into_bytes()))
+    }
+
+    fn aggregate_signatures(
+        &self,
+        _signatures: Vec<Signature>,
+        _voters: Vec<Address>,
+    ) -> Result<Signature, Box<dyn Error + Send>> {
+        Ok(Signature::new())
+    }
+
+    fn verify_signature(
+        &self,
+        _signature: Signature,
+        _hash: Hash,
+        _voter: Address,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        Ok(())
+    }
+
+    fn verify_aggregated_signature(
+        &self,
+        _aggregate_signature: Signature,
+        _msg_hash: Hash,
+        _voters: Vec<Address>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        Ok(())
+    }
+
+    fn vrf_prove(&self, msg: Bytes) -> Result<(Hash, Bytes), Box<dyn Error + Send>> {
+        let proof = self.address.clone().into_bytes();
+        Ok((mock_hash(&[proof.as_ref(), msg.as_ref()].concat()), proof))
+    }
+
+    fn vrf_verify(
+        &self,
+        proof: Bytes,
+        msg: Bytes,
+        signer: Address,
+    ) -> Result<Hash, Box<dyn Error + Send>> {
+        if proof.as_ref() != signer.as_bytes() {
+            return Err(Box::new(ConsensusError::CryptoErr(
+                "VRF proof mismatch".to_string(),
+            )));
+        }
+        Ok(mock_hash(&[proof.as_ref(), msg.as_ref()].concat()))
+    }
+}
+
+/// A crypto of which the hash is the message itself and the signature is the signed hash. Unlike
+/// [`MockCrypto`], a signature only verifies against the hash it is made of, so the tests can tell
+/// a signature of another payload apart. An aggregated or a threshold signature is the hash as
+/// well, whoever the voters are.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EchoCrypto;
+
+impl Crypto for EchoCrypto {
+    fn hash(&self, msg: Bytes) -> Hash {
+        Hash::from(msg)
+    }
+
+    fn sign(&self, hash: Hash) -> Result<Signature, Box<dyn Error + Send>> {
+        Ok(Signature::from(hash.into_bytes()))
+    }
+
+    fn aggregate_signatures(
+        &self,
+        _signatures: Vec<Signature>,
+        _voters: Vec<Address>,
+    ) -> Result<Signature, Box<dyn Error + Send>> {
+        Ok(Signature::new())
+    }
+
+    fn verify_signature(
+        &self,
+        signature: Signature,
+        hash: Hash,
+        _voter: Address,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        if signature.as_bytes() != hash.as_bytes() {
+            return Err(Box::new(ConsensusError::CryptoErr(
+                "signature mismatch".to_string(),
+            )));
+        }
+        Ok(())
+    }
+
+    fn verify_aggregated_signature(
+        &self,
+        aggregate_signature: Signature,
+        msg_hash: Hash,
+        _voters: Vec<Address>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.verify_signature(aggregate_signature, msg_hash, Address::new())
+    }
+
+    fn verify_threshold_signature(
+        &self,
+        _height: Height,
+        signature: Signature,
+        msg_hash: Hash,
+        _authority: Vec<Address>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.verify_signature(signature, msg_hash, Address::new())
+    }
+}
+
+/// An in-memory wal which keeps every entry of the current height. Nothing survives the drop of
+/// the wal, so a restart is simulated by passing the same wal to a new overlord.
+#[derive(Debug, Default)]
+pub struct MemoryWal(Mutex<Vec<Bytes>>);
+
+impl MemoryWal {
+    /// Create an empty wal.
+    pub fn new() -> Self {
+        MemoryWal::default()
+    }
+
+    /// The entries of the current height in the order they are appended.
+    pub fn entries(&self) -> Vec<Bytes> {
+        self.0.lock().clone()
+    }
+}
+
+#[async_trait]
+impl Wal for MemoryWal {
+    async fn save(&self, info: Bytes) -> Result<(), Box<dyn Error + Send>> {
+        let mut log = self.0.lock();
+        log.pop();
+        log.push(info);
+        Ok(())
+    }
+
+    async fn load(&self) -> Result<Option<Bytes>, Box<dyn Error + Send>> {
+        Ok(self.0.lock().last().cloned())
+    }
+
+    async fn append(&self, info: Bytes) -> Result<(), Box<dyn Error + Send>> {
+        self.0.lock().push(info);
+        Ok(())
+    }
+
+    async fn load_all(&self) -> Result<Vec<Bytes>, Box<dyn Error + Send>> {
+        Ok(self.0.lock().clone())
+    }
+
+    async fn compact(&self, info: Bytes) -> Result<(), Box<dyn Error + Send>> {
+        *self.0.lock() = vec![info];
+        Ok(())
+    }
+}
+
+/// A scriptable consensus adapter, which proposes the blocks pushed by `push_block` in order,
+/// rejects the blocks of the hashes given by `reject_block`, and records the commits, the sent
+/// messages and the reports for the assertions of a test. `get_block` fails once the pushed
+/// blocks run out, and every commit keeps the authority list with the default interval.
+#[derive(Debug)]
+pub struct MockConsensus<T: Codec> {
+    authority_list: Vec<Node>,
+    blocks: Mutex<VecDeque<(T, Hash)>>,
+    rejected: Mutex<HashSet<Hash>>,
+    commits: Mutex<Vec<Commit<T>>>,
+    broadcasts: Mutex<Vec<OverlordMsg<T>>>,
+    transmits: Mutex<Vec<(Address, OverlordMsg<T>)>>,
+    errors: Mutex<Vec<ConsensusError>>,
+    view_changes: Mutex<Vec<(Height, Round, ViewChangeReason)>>,
+}
+
+impl<T: Codec> MockConsensus<T> {
+    /// Create an adapter of the given authority list, which has no block to propose yet.
+    pub fn new(authority_list: Vec<Node>) -> Self {
+        MockConsensus {
+            authority_list,
+            blocks: Mutex::new(VecDeque::new()),
+            rejected: Mutex::new(HashSet::new()),
+            commits: Mutex::new(Vec::new()),
+            broadcasts: Mutex::new(Vec::new()),
+            transmits: Mutex::new(Vec::new()),
+            errors: Mutex::new(Vec::new()),
+            view_changes: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Push a block with its hash to propose by the next `get_block`.
+    pub fn push_block(&self, block: T, hash: Hash) {
+        self.blocks.lock().push_back((block, hash));
+    }
+
+    /// Fail the `check_block` of the block of the hash.
+    pub fn reject_block(&self, hash: Hash) {
+        self.rejected.lock().insert(hash);
+    }
+
+    /// The commits so far.
+    pub fn commits(&self) -> Vec<Commit<T>> {
+        self.commits.lock().clone()
+    }
+
+    /// The messages broadcast so far.
+    pub fn broadcasts(&self) -> Vec<OverlordMsg<T>> {
+        self.broadcasts.lock().clone()
+    }
+
+    /// The messages transmitted so far with their receivers.
+    pub fn transmits(&self) -> Vec<(Address, OverlordMsg<T>)> {
+        self.transmits.lock().clone()
+    }
+
+    /// The errors reported so far.
+    pub fn errors(&self) -> Vec<ConsensusError> {
+        self.errors.lock().clone()
+    }
+
+    /// The view changes reported so far.
+    pub fn view_changes(&self) -> Vec<(Height, Round, ViewChangeReason)> {
+        self.view_changes.lock().clone()
+    }
+}
+
+#[async_trait]
+impl<T: Codec + Sync + 'static> Consensus<T> for MockConsensus<T> {
+    async fn get_block(
+        &self,
+        _ctx: Context,
+        height: Height,
+    ) -> Result<(T, Hash), Box<dyn Error + Send>> {
+        self.blocks.lock().pop_front().ok_or_else(|| {
+            Box::new(ConsensusError::Other(format!(
+                "no scripted block of height {}",
+                height
+            ))) as Box<dyn Error + Send>
+        })
+    }
+
+    async fn check_block(
+        &self,
+        _ctx: Context,
+        height: Height,
+        hash: Hash,
+        _block: T,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        if self.rejected.lock().contains(&hash) {
+            return Err(Box::new(ConsensusError::Other(format!(
+                "rejected block of height {}",
+                height
+            ))));
+        }
+        Ok(())
+    }
+
+    async fn commit(
+        &self,
+        _ctx: Context,
+        height: Height,
+        commit: Commit<T>,
+    ) -> Result<Status, Box<dyn Error + Send>> {
+        self.commits.lock().push(commit);
+        Ok(Status {
+            height: height + 1,
+            interval: None,
+            timer_config: None,
+            vrf_seed: None,
+            next_authority_list: None,
+            proof: None,
+            authority_list: self.authority_list.clone(),
+        })
+    }
+
+    async fn get_authority_list(
+        &self,
+        _ctx: Context,
+        _height: Height,
+    ) -> Result<Vec<Node>, Box<dyn Error + Send>> {
+        Ok(self.authority_list.clone())
+    }
+
+    async fn broadcast_to_other(
+        &self,
+        _ctx: Context,
+        msg: OverlordMsg<T>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.broadcasts.lock().push(msg);
+        Ok(())
+    }
+
+    async fn transmit_to_relayer(
+        &self,
+        _ctx: Context,
+        addr: Address,
+        msg: OverlordMsg<T>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.transmits.lock().push((addr, msg));
+        Ok(())
+    }
+
+    fn report_error(&self, _ctx: Context, error: ConsensusError) {
+        self.errors.lock().push(error);
+    }
+
+    fn report_view_change(
+        &self,
+        _ctx: Context,
+        height: Height,
+        round: Round,
+        reason: ViewChangeReason,
+    ) {
+        self.view_changes.lock().push((height, round, reason));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::{mock_hash, MemoryWal, MockConsensus, MockCrypto};
+    use crate::types::{Address, Height, Node};
+    use crate::Overlord;
+
+    #[tokio::test]
+    async fn test_single_node_with_mocks() {
+        let address = Address::from(mock_hash(b"node").into_bytes());
+        let authority_list = vec![Node::new(address.clone())];
+        let consensus = Arc::new(MockConsensus::new(authority_list.clone()));
+        for height in 1u64..4 {
+            let block = height.to_be_bytes().to_vec();
+            consensus.push_block(block.clone(), mock_hash(&block));
+        }
+
+        let overlord = Overlord::new(
+            address.clone(),
+            Arc::clone(&consensus),
+            Arc::new(MockCrypto::new(address)),
+            Arc::new(MemoryWal::new()),
+        );
+        tokio::spawn(async move {
+            let _ = overlord.run(Height(1), 50, authority_list, None).await;
+        });
+
+        for _ in 0..100 {
+            if consensus.commits().len() >= 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let commits = consensus.commits();
+        assert!(commits.len() >= 3);
+        assert_eq!(commits[2].height, Height(3));
+        assert_eq!(commits[2].content, 3u64.to_be_bytes().to_vec());
+    }
+}
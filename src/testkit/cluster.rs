@@ -0,0 +1,321 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use creep::Context;
+use futures::channel::mpsc::unbounded;
+use parking_lot::Mutex;
+use tokio::time::{sleep, Instant};
+
+use crate::test_utils::{mock_hash, MemoryWal, MockCrypto};
+use crate::testkit::network::{Links, SimNetwork};
+use crate::testkit::node::{Ledger, SimAdapter, SimBlock};
+use crate::testkit::sim::SafetyViolation;
+use crate::types::{Address, Hash, Height, Node, OverlordMsg, Round, Status};
+use crate::{Overlord, OverlordConfig, OverlordHandler};
+
+/// An in-process cluster of overlord instances wired through in-memory channels, which deliver
+/// every message without a fault. Unlike a `Simulation`, it runs on the runtime of the caller, so
+/// a test drives it in real time, or in virtual time on a paused runtime, and asserts on it as
+/// the nodes go. The cluster is stopped as it is dropped.
+pub struct Cluster {
+    handlers: Vec<OverlordHandler<SimBlock>>,
+    authority_list: Vec<Node>,
+    ledger: Arc<Mutex<Ledger>>,
+}
+
+impl Cluster {
+    /// Start a cluster of the given count of nodes, which are all in the authority list, with the
+    /// height interval as millisecond and the configuration of each overlord instance. It spawns
+    /// the instances and the network, so it must be called in a tokio runtime.
+    pub fn start(nodes: usize, interval: u64, config: OverlordConfig) -> Self {
+        let authority_list = (0..nodes)
+            .map(|index| {
+                Node::new(Address::from(
+                    mock_hash(&(index as u64).to_be_bytes()).into_bytes(),
+                ))
+            })
+            .collect::<Vec<_>>();
+        let ledger = Arc::new(Mutex::new(Ledger::new(nodes)));
+        let (network_tx, network_rx) = unbounded();
+
+        let mut handlers = Vec::with_capacity(nodes);
+        for (index, node) in authority_list.iter().enumerate() {
+            let adapter = SimAdapter::new(
+                index,
+                authority_list.clone(),
+                interval,
+                None,
+                network_tx.clone(),
+                Arc::clone(&ledger),
+            );
+            let overlord = Overlord::new_with_config(
+                node.address.clone(),
+                Arc::new(adapter),
+                Arc::new(MockCrypto::new(node.address.clone())),
+                Arc::new(MemoryWal::new()),
+                config.clone(),
+            );
+            handlers.push(overlord.get_handler());
+
+            let list = authority_list.clone();
+            tokio::spawn(async move {
+                if let Err(e) = overlord.run(Height(1), interval, list, None).await {
+                    log::error!("Overlord: cluster node {} error {:?}", index, e);
+                }
+            });
+        }
+        drop(network_tx);
+
+        // Start the first height together, as a simulation does.
+        for handler in handlers.iter() {
+            let _ = handler.send_msg(
+                Context::new(),
+                OverlordMsg::RichStatus(Status {
+                    height: Height(1),
+                    interval: Some(interval),
+                    timer_config: None,
+                    vrf_seed: None,
+                    next_authority_list: None,
+                    proof: None,
+                    authority_list: authority_list.clone(),
+                }),
+            );
+        }
+
+        let links = Arc::new(Mutex::new(Links::new(nodes)));
+        let network = SimNetwork::new(0, (0, 0), links, handlers.clone());
+        tokio::spawn(network.route(network_rx));
+
+        Cluster {
+            handlers,
+            authority_list,
+            ledger,
+        }
+    }
+
+    /// Wait until every node commits the target height. Return `false` if the timeout elapses
+    /// first.
+    pub async fn run_until(&self, target: Height, timeout: Duration) -> bool {
+        let start = Instant::now();
+        loop {
+            if self.heights().iter().all(|height| *height >= target) {
+                return true;
+            }
+            if start.elapsed() >= timeout {
+                return false;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    /// The addresses of the nodes in order.
+    pub fn addresses(&self) -> Vec<Address> {
+        self.authority_list
+            .iter()
+            .map(|node| node.address.clone())
+            .collect()
+    }
+
+    /// The latest committed height of each node.
+    pub fn heights(&self) -> Vec<Height> {
+        self.ledger.lock().heights.clone()
+    }
+
+    /// The block hash first committed at the height.
+    pub fn committed(&self, height: Height) -> Option<Hash> {
+        self.ledger.lock().blocks.get(&height).cloned()
+    }
+
+    /// The highest round of the commits.
+    pub fn max_round(&self) -> Round {
+        self.ledger.lock().max_round
+    }
+
+    /// The commits of a block hash other than the first one committed at the height.
+    pub fn violations(&self) -> Vec<SafetyViolation> {
+        self.ledger.lock().violations.clone()
+    }
+
+    /// Assert that all the nodes committed the same block hash at each height.
+    pub fn assert_same_commits(&self) {
+        let violations = self.violations();
+        assert!(
+            violations.is_empty(),
+            "conflicting commits {:?}",
+            violations
+        );
+    }
+
+    /// Assert that no commit took a round above the given one.
+    pub fn assert_max_round(&self, round: Round) {
+        let max_round = self.max_round();
+        assert!(
+            max_round <= round,
+            "a commit took round {}, above {}",
+            max_round,
+            round
+        );
+    }
+
+    /// The handler of the node of the index.
+    pub fn handler(&self, index: usize) -> &OverlordHandler<SimBlock> {
+        &self.handlers[index]
+    }
+
+    /// Send a message to the node of the index, as if it is received from a peer.
+    pub fn send_msg(&self, index: usize, msg: OverlordMsg<SimBlock>) {
+        let _ = self.handlers[index].send_msg(Context::new(), msg);
+    }
+
+    /// Stop all the nodes.
+    pub fn stop(&self) {
+        for handler in self.handlers.iter() {
+            let _ = handler.send_msg(Context::new(), OverlordMsg::Stop);
+        }
+    }
+}
+
+impl Drop for Cluster {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+
+    use super::Cluster;
+    use crate::types::{
+        Address, AggregatedChoke, AggregatedSignature, AggregatedVote, Choke, Hash, Height,
+        OverlordMsg, QcKind, Round, Signature, SignedChoke, SignedVote, UpdateFrom, Vote, VoteType,
+    };
+    use crate::OverlordConfig;
+
+    /// A QC of the voters of the bitmap, which is below the threshold of four nodes with at most
+    /// two voters, so it never passes the verification.
+    fn forged_qc(height: Height, hash: u8, bitmap: u8) -> AggregatedVote {
+        AggregatedVote {
+            signature: AggregatedSignature {
+                signature: Signature::new(),
+                address_bitmap: Bytes::from(vec![bitmap]),
+                kind: QcKind::Aggregated,
+            },
+            vote_type: VoteType::Prevote,
+            height,
+            round: Round(0),
+            block_hash: Hash::from(vec![hash; 32]),
+            leader: Address::from(vec![0u8; 20]),
+            extensions: None,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cluster() {
+        let cluster = Cluster::start(4, 100, OverlordConfig::default());
+        assert!(cluster.run_until(Height(5), Duration::from_secs(60)).await);
+        cluster.assert_same_commits();
+        // As in a simulation, the first height has no proposal in round 0.
+        cluster.assert_max_round(Round(1));
+        assert!(cluster.committed(Height(5)).is_some());
+        assert_eq!(cluster.addresses().len(), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cluster_with_forged_future_qcs() {
+        let cluster = Cluster::start(4, 100, OverlordConfig::default());
+        assert!(cluster.run_until(Height(2), Duration::from_secs(60)).await);
+
+        // Two conflicting QCs of a future height are no evidence of a fork before they are
+        // verified, and they never are.
+        let height = cluster.heights().into_iter().max().unwrap() + 3;
+        for index in 0..4 {
+            cluster.send_msg(
+                index,
+                OverlordMsg::AggregatedVote(forged_qc(height, 1, 0x80)),
+            );
+            cluster.send_msg(
+                index,
+                OverlordMsg::AggregatedVote(forged_qc(height, 2, 0x40)),
+            );
+        }
+        assert!(cluster.run_until(height + 2, Duration::from_secs(60)).await);
+        cluster.assert_same_commits();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cluster_with_forged_choke_qcs() {
+        let config = OverlordConfig {
+            halt_on_double_sign: true,
+            ..Default::default()
+        };
+        let cluster = Cluster::start(4, 100, config);
+        assert!(cluster.run_until(Height(2), Duration::from_secs(60)).await);
+
+        // The QCs of the chokes have every node as a voter, but they are below the threshold, so
+        // no node takes them as its votes.
+        let height = cluster.heights().into_iter().max().unwrap() + 1;
+        for index in 0..4 {
+            for bitmap in [0xc0, 0x30] {
+                let choke = SignedChoke {
+                    signature: Signature::new(),
+                    choke: Choke {
+                        height,
+                        round: Round(1),
+                        from: UpdateFrom::PrevoteQC(forged_qc(height, 1, bitmap)),
+                    },
+                    address: cluster.addresses()[index].clone(),
+                };
+                cluster.send_msg(index, OverlordMsg::SignedChoke(choke));
+            }
+        }
+        assert!(cluster.run_until(height + 2, Duration::from_secs(60)).await);
+        cluster.assert_same_commits();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cluster_with_previous_height_spam_rounds() {
+        let cluster = Cluster::start(4, 100, OverlordConfig::default());
+        assert!(cluster.run_until(Height(3), Duration::from_secs(60)).await);
+
+        // A validator signs the votes of many rounds of a committed height, which are no double
+        // signing, and none of them takes a round of the vote collector.
+        let height = cluster.heights()[0];
+        for round in 100..400 {
+            let vote = SignedVote {
+                signature: Signature::new(),
+                vote: Vote {
+                    height,
+                    round: Round(round),
+                    vote_type: VoteType::Prevote,
+                    block_hash: Hash::from(vec![1u8; 32]),
+                },
+                voter: cluster.addresses()[1].clone(),
+                extension: None,
+            };
+            cluster.send_msg(0, OverlordMsg::SignedVote(vote));
+        }
+        assert!(cluster.run_until(height + 2, Duration::from_secs(60)).await);
+        let metrics = cluster.handler(0).get_state_metrics().await.unwrap();
+        assert_eq!(metrics.evictions.vote_rounds, 0);
+        cluster.assert_same_commits();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cluster_import_forged_choke_qc() {
+        let cluster = Cluster::start(4, 100, OverlordConfig::default());
+        assert!(cluster.run_until(Height(2), Duration::from_secs(60)).await);
+
+        let mut snapshot = cluster.handler(0).export_snapshot().await.unwrap();
+        let height = snapshot.wal_info.height;
+        snapshot.choke_qcs.push(AggregatedChoke {
+            height,
+            round: Round(0),
+            signature: Signature::new(),
+            voters: cluster.addresses()[..2].to_vec(),
+        });
+        assert!(cluster.handler(0).import_snapshot(snapshot).await.is_err());
+    }
+}
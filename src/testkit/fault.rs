@@ -0,0 +1,363 @@
+use std::error::Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use creep::Context;
+use parking_lot::Mutex;
+use rand_core::{RngCore, SeedableRng};
+use rand_pcg::Pcg64Mcg as Pcg;
+use tokio::time::sleep;
+
+use crate::error::{ConsensusError, ErrorReport};
+use crate::types::{
+    AbandonedWork, Address, AggregatedVote, Commit, Decision, Hash, Height, MessageDrop, Node,
+    OverlordMsg, Round, Status, ViewChangeReason,
+};
+use crate::{Codec, Consensus};
+
+const PER_MILLE: u64 = 1000;
+
+/// The policy of the faults injected into the outgoing messages. The rates are per mille of the
+/// messages. A duplicated message may also be corrupted or delayed on its own.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FaultPolicy {
+    /// The seed of the random decisions.
+    pub seed: u64,
+    /// The rate of the dropped messages.
+    pub drop_rate: u64,
+    /// The rate of the messages sent twice.
+    pub duplicate_rate: u64,
+    /// The rate of the corrupted messages, of which the signature or the block part data is
+    /// flipped.
+    pub corrupt_rate: u64,
+    /// The minimum and the maximum extra delay of a message as millisecond.
+    pub delay: (u64, u64),
+}
+
+/// The count of the injected faults.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FaultStats {
+    /// The count of the dropped messages.
+    pub dropped: u64,
+    /// The count of the duplicated messages.
+    pub duplicated: u64,
+    /// The count of the corrupted messages.
+    pub corrupted: u64,
+    /// The count of the delayed messages.
+    pub delayed: u64,
+}
+
+impl FaultStats {
+    /// Add the counts of another one.
+    pub fn merge(&mut self, other: &FaultStats) {
+        self.dropped += other.dropped;
+        self.duplicated += other.duplicated;
+        self.corrupted += other.corrupted;
+        self.delayed += other.delayed;
+    }
+}
+
+/// A network adapter which injects faults into the messages of `broadcast_to_other` and
+/// `transmit_to_relayer` according to the policy, and delegates all the other methods to the
+/// wrapped application. It is used for the chaos tests of the state machine without external
+/// tooling.
+pub struct FaultyNetwork<F> {
+    inner: Arc<F>,
+    policy: FaultPolicy,
+    rng: Mutex<Pcg>,
+    stats: Mutex<FaultStats>,
+}
+
+impl<F> FaultyNetwork<F> {
+    /// Wrap the application with the fault policy.
+    pub fn new(inner: Arc<F>, policy: FaultPolicy) -> Self {
+        FaultyNetwork {
+            inner,
+            rng: Mutex::new(Pcg::seed_from_u64(policy.seed)),
+            policy,
+            stats: Mutex::new(FaultStats::default()),
+        }
+    }
+
+    /// Get the count of the injected faults.
+    pub fn stats(&self) -> FaultStats {
+        self.stats.lock().clone()
+    }
+
+    /// Decide the copies of a message to send, as the delay and whether to corrupt of each.
+    fn decide(&self) -> Vec<(Duration, bool)> {
+        let mut rng = self.rng.lock();
+        let mut stats = self.stats.lock();
+        if roll(&mut rng, self.policy.drop_rate) {
+            stats.dropped += 1;
+            return Vec::new();
+        }
+        let copies = if roll(&mut rng, self.policy.duplicate_rate) {
+            stats.duplicated += 1;
+            2
+        } else {
+            1
+        };
+
+        (0..copies)
+            .map(|_| {
+                let corrupt = roll(&mut rng, self.policy.corrupt_rate);
+                let (min, max) = (
+                    self.policy.delay.0.min(self.policy.delay.1),
+                    self.policy.delay.0.max(self.policy.delay.1),
+                );
+                let delay = min + rng.next_u64() % (max - min + 1);
+                if corrupt {
+                    stats.corrupted += 1;
+                }
+                if delay > 0 {
+                    stats.delayed += 1;
+                }
+                (Duration::from_millis(delay), corrupt)
+            })
+            .collect()
+    }
+}
+
+impl<F> FaultyNetwork<F> {
+    async fn send<T>(&self, ctx: Context, route: Route, msg: OverlordMsg<T>) -> SendResult
+    where
+        T: Codec + 'static,
+        F: Consensus<T> + 'static,
+    {
+        let mut res = Ok(());
+        for (delay, corrupt) in self.decide().into_iter() {
+            let msg = if corrupt {
+                corrupt_msg(msg.clone())
+            } else {
+                msg.clone()
+            };
+            let (inner, ctx, route) = (Arc::clone(&self.inner), ctx.clone(), route.clone());
+            if delay.is_zero() {
+                if let Err(e) = route.deliver(inner.as_ref(), ctx, msg).await {
+                    res = Err(e);
+                }
+            } else {
+                tokio::spawn(async move {
+                    sleep(delay).await;
+                    let _ = route.deliver(inner.as_ref(), ctx, msg).await;
+                });
+            }
+        }
+        res
+    }
+}
+
+fn roll(rng: &mut Pcg, rate: u64) -> bool {
+    rate > 0 && rng.next_u64() % PER_MILLE < rate
+}
+
+type SendResult = Result<(), Box<dyn Error + Send>>;
+
+/// The way a message is sent.
+#[derive(Clone)]
+enum Route {
+    Broadcast,
+    Relay(Address),
+}
+
+impl Route {
+    async fn deliver<T: Codec, F: Consensus<T>>(
+        self,
+        inner: &F,
+        ctx: Context,
+        msg: OverlordMsg<T>,
+    ) -> SendResult {
+        match self {
+            Route::Broadcast => inner.broadcast_to_other(ctx, msg).await,
+            Route::Relay(addr) => inner.transmit_to_relayer(ctx, addr, msg).await,
+        }
+    }
+}
+
+/// Flip the first byte of the signature of a signed message, or of the data of a block part.
+fn corrupt_msg<T: Codec>(msg: OverlordMsg<T>) -> OverlordMsg<T> {
+    match msg {
+        OverlordMsg::SignedProposal(mut sp) => {
+            sp.signature = flip(&sp.signature);
+            OverlordMsg::SignedProposal(sp)
+        }
+        OverlordMsg::SignedCompactProposal(mut sp) => {
+            sp.signature = flip(&sp.signature);
+            OverlordMsg::SignedCompactProposal(sp)
+        }
+        OverlordMsg::SignedVote(mut sv) => {
+            sv.signature = flip(&sv.signature);
+            OverlordMsg::SignedVote(sv)
+        }
+        OverlordMsg::VoteBatch(mut votes) => {
+            if let Some(sv) = votes.first_mut() {
+                sv.signature = flip(&sv.signature);
+            }
+            OverlordMsg::VoteBatch(votes)
+        }
+        OverlordMsg::AggregatedVote(mut av) => {
+            av.signature.signature = flip(&av.signature.signature);
+            OverlordMsg::AggregatedVote(av)
+        }
+        OverlordMsg::SignedChoke(mut sc) => {
+            sc.signature = flip(&sc.signature);
+            OverlordMsg::SignedChoke(sc)
+        }
+        OverlordMsg::BlockPart(mut part) => {
+            part.data = flip(&part.data);
+            OverlordMsg::BlockPart(part)
+        }
+        msg => msg,
+    }
+}
+
+fn flip<B: AsRef<[u8]> + From<Vec<u8>>>(bytes: &B) -> B {
+    let mut bytes = bytes.as_ref().to_vec();
+    match bytes.first_mut() {
+        Some(byte) => *byte ^= 0xff,
+        None => bytes.push(0xff),
+    }
+    B::from(bytes)
+}
+
+#[async_trait]
+impl<T, F> Consensus<T> for FaultyNetwork<F>
+where
+    T: Codec + 'static,
+    F: Consensus<T> + 'static,
+{
+    async fn get_block(
+        &self,
+        ctx: Context,
+        height: Height,
+    ) -> Result<(T, Hash), Box<dyn Error + Send>> {
+        self.inner.get_block(ctx, height).await
+    }
+
+    async fn check_block(
+        &self,
+        ctx: Context,
+        height: Height,
+        hash: Hash,
+        block: T,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.inner.check_block(ctx, height, hash, block).await
+    }
+
+    async fn commit(
+        &self,
+        ctx: Context,
+        height: Height,
+        commit: Commit<T>,
+    ) -> Result<Status, Box<dyn Error + Send>> {
+        self.inner.commit(ctx, height, commit).await
+    }
+
+    async fn get_authority_list(
+        &self,
+        ctx: Context,
+        height: Height,
+    ) -> Result<Vec<Node>, Box<dyn Error + Send>> {
+        self.inner.get_authority_list(ctx, height).await
+    }
+
+    async fn broadcast_to_other(
+        &self,
+        ctx: Context,
+        msg: OverlordMsg<T>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.send(ctx, Route::Broadcast, msg).await
+    }
+
+    async fn transmit_to_relayer(
+        &self,
+        ctx: Context,
+        addr: Address,
+        msg: OverlordMsg<T>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.send(ctx, Route::Relay(addr), msg).await
+    }
+
+    async fn get_block_by_hash(
+        &self,
+        ctx: Context,
+        height: Height,
+        hash: Hash,
+    ) -> Result<T, Box<dyn Error + Send>> {
+        self.inner.get_block_by_hash(ctx, height, hash).await
+    }
+
+    fn prepare_block(&self, ctx: Context, height: Height) {
+        self.inner.prepare_block(ctx, height)
+    }
+
+    async fn before_propose(
+        &self,
+        ctx: Context,
+        height: Height,
+        round: Round,
+        hash: Hash,
+    ) -> Decision {
+        self.inner.before_propose(ctx, height, round, hash).await
+    }
+
+    async fn extend_vote(
+        &self,
+        ctx: Context,
+        height: Height,
+        round: Round,
+        block_hash: Hash,
+    ) -> Result<Bytes, Box<dyn Error + Send>> {
+        self.inner.extend_vote(ctx, height, round, block_hash).await
+    }
+
+    async fn verify_vote_extension(
+        &self,
+        ctx: Context,
+        height: Height,
+        voter: Address,
+        block_hash: Hash,
+        extension: Bytes,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.inner
+            .verify_vote_extension(ctx, height, voter, block_hash, extension)
+            .await
+    }
+
+    fn next_propose_at(&self, height: Height) -> Option<Instant> {
+        self.inner.next_propose_at(height)
+    }
+
+    fn report_message_drop(&self, ctx: Context, drop: MessageDrop) {
+        self.inner.report_message_drop(ctx, drop)
+    }
+
+    fn report_abandoned_height(&self, ctx: Context, work: AbandonedWork) {
+        self.inner.report_abandoned_height(ctx, work)
+    }
+
+    fn report_fork(&self, ctx: Context, kept: AggregatedVote, received: AggregatedVote) {
+        self.inner.report_fork(ctx, kept, received)
+    }
+
+    fn report_error(&self, ctx: Context, error: ConsensusError) {
+        self.inner.report_error(ctx, error)
+    }
+
+    fn report_error_detail(&self, ctx: Context, error: ConsensusError, report: ErrorReport) {
+        self.inner.report_error_detail(ctx, error, report)
+    }
+
+    fn report_view_change(
+        &self,
+        ctx: Context,
+        height: Height,
+        round: Round,
+        reason: ViewChangeReason,
+    ) {
+        self.inner.report_view_change(ctx, height, round, reason)
+    }
+}
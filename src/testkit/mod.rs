@@ -0,0 +1,16 @@
+/// The in-process cluster which runs the overlord instances on the runtime of the caller.
+mod cluster;
+/// The network adapter which injects faults into the outgoing messages.
+mod fault;
+/// The simulated network which delivers the messages between the simulated nodes.
+mod network;
+/// The application, crypto and wal adapters of a simulated node.
+mod node;
+/// The simulation which runs the overlord instances in virtual time.
+mod sim;
+
+pub use self::cluster::Cluster;
+pub use self::fault::{FaultPolicy, FaultStats, FaultyNetwork};
+pub use self::network::Fault;
+pub use self::node::SimBlock;
+pub use self::sim::{SafetyViolation, SimConfig, SimReport, Simulation};
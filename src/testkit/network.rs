@@ -0,0 +1,151 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use creep::Context;
+use futures::channel::mpsc::UnboundedReceiver;
+use futures::StreamExt;
+use parking_lot::Mutex;
+use rand_core::{RngCore, SeedableRng};
+use rand_pcg::Pcg64Mcg as Pcg;
+use tokio::time::sleep;
+
+use crate::testkit::node::SimBlock;
+use crate::types::OverlordMsg;
+use crate::OverlordHandler;
+
+/// A fault of the simulated network, which is applied at a scheduled virtual time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// The node of the index stops sending and receiving messages.
+    Crash(usize),
+    /// The crashed node of the index is connected again, and synchronized to the highest
+    /// committed height.
+    Recover(usize),
+    /// Split the nodes into the groups of the indexes. The nodes of different groups are
+    /// disconnected, and the nodes of no group are disconnected from all.
+    Partition(Vec<Vec<usize>>),
+    /// Remove the partition, and synchronize the lagging nodes to the highest committed height.
+    Heal,
+    /// Remove the node of the index from the authority list of the statuses committed after. The
+    /// node stays connected without the consensus power.
+    Leave(usize),
+    /// Add the removed node of the index back to the authority list, and synchronize it to the
+    /// height in consensus, so that it rejoins as a validator.
+    Rejoin(usize),
+}
+
+/// A message sent by a simulated node. A message to no node is broadcast to the others.
+pub(crate) struct Envelope {
+    pub(crate) from: usize,
+    pub(crate) to: Option<usize>,
+    pub(crate) msg: OverlordMsg<SimBlock>,
+}
+
+/// The connectivity of the simulated nodes.
+#[derive(Debug)]
+pub(crate) struct Links {
+    crashed: Vec<bool>,
+    groups: Option<Vec<Option<usize>>>,
+}
+
+impl Links {
+    pub(crate) fn new(nodes: usize) -> Self {
+        Links {
+            crashed: vec![false; nodes],
+            groups: None,
+        }
+    }
+
+    pub(crate) fn apply(&mut self, fault: &Fault) {
+        match fault {
+            Fault::Crash(index) => self.crashed[*index] = true,
+            Fault::Recover(index) => self.crashed[*index] = false,
+            Fault::Partition(groups) => {
+                let mut group_of = vec![None; self.crashed.len()];
+                for (group, indexes) in groups.iter().enumerate() {
+                    for index in indexes.iter() {
+                        group_of[*index] = Some(group);
+                    }
+                }
+                self.groups = Some(group_of);
+            }
+            Fault::Heal => self.groups = None,
+            Fault::Leave(_) | Fault::Rejoin(_) => (),
+        }
+    }
+
+    pub(crate) fn is_crashed(&self, index: usize) -> bool {
+        self.crashed[index]
+    }
+
+    pub(crate) fn connected(&self, from: usize, to: usize) -> bool {
+        if self.crashed[from] || self.crashed[to] {
+            return false;
+        }
+        match self.groups.as_ref() {
+            Some(groups) => groups[from].is_some() && groups[from] == groups[to],
+            None => true,
+        }
+    }
+}
+
+/// The simulated network. Each delivered message is delayed by a latency drawn from the seeded
+/// random generator, so that the messages are reordered reproducibly.
+pub(crate) struct SimNetwork {
+    rng: Pcg,
+    min_latency: u64,
+    max_latency: u64,
+    links: Arc<Mutex<Links>>,
+    handlers: Vec<OverlordHandler<SimBlock>>,
+}
+
+impl SimNetwork {
+    pub(crate) fn new(
+        seed: u64,
+        latency: (u64, u64),
+        links: Arc<Mutex<Links>>,
+        handlers: Vec<OverlordHandler<SimBlock>>,
+    ) -> Self {
+        SimNetwork {
+            rng: Pcg::seed_from_u64(seed),
+            min_latency: latency.0.min(latency.1),
+            max_latency: latency.0.max(latency.1),
+            links,
+            handlers,
+        }
+    }
+
+    /// Route the messages until all the senders are dropped.
+    pub(crate) async fn route(mut self, mut rx: UnboundedReceiver<Envelope>) {
+        while let Some(envelope) = rx.next().await {
+            let targets = match envelope.to {
+                Some(to) => vec![to],
+                None => (0..self.handlers.len())
+                    .filter(|to| *to != envelope.from)
+                    .collect(),
+            };
+
+            for to in targets.into_iter() {
+                if !self.links.lock().connected(envelope.from, to) {
+                    continue;
+                }
+                let delay = self.latency();
+                let links = Arc::clone(&self.links);
+                let handler = self.handlers[to].clone();
+                let msg = envelope.msg.clone();
+                tokio::spawn(async move {
+                    sleep(delay).await;
+                    // The receiver may crash while the message is in flight.
+                    if !links.lock().is_crashed(to) {
+                        let _ = handler.send_msg(Context::new(), msg);
+                    }
+                });
+            }
+        }
+    }
+
+    fn latency(&mut self) -> Duration {
+        let range = self.max_latency - self.min_latency + 1;
+        Duration::from_millis(self.min_latency + self.rng.next_u64() % range)
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use creep::Context;
+use futures::channel::mpsc::UnboundedSender;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ConsensusError;
+use crate::test_utils::{mock_hash, MockCrypto};
+use crate::testkit::network::Envelope;
+use crate::testkit::sim::SafetyViolation;
+use crate::types::{
+    Address, Commit, Hash, Height, Node, OverlordMsg, Proof, Round, Status, ViewChangeReason, Vrf,
+};
+use crate::{Consensus, DurationConfig};
+
+/// The block of the simulation, which is unique per proposer, height and proposing count.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SimBlock {
+    /// The height of the block.
+    pub height: Height,
+    /// The proposer of the block.
+    #[serde(with = "crate::serde_hex")]
+    pub proposer: Address,
+    /// The count of the blocks the proposer produced before.
+    pub nonce: u64,
+}
+
+/// The commits of all the simulated nodes.
+#[derive(Debug, Default)]
+pub(crate) struct Ledger {
+    /// The first committed block hash of each height.
+    pub(crate) blocks: BTreeMap<Height, Hash>,
+    /// The proof of the first commit of each height, which the synchronization carries.
+    pub(crate) proofs: BTreeMap<Height, Proof>,
+    /// The VRF seed of each height in the VRF proposer mode, which the synchronization carries.
+    /// It is derived from the committed VRF of the previous height as a chain does.
+    pub(crate) seeds: BTreeMap<Height, Hash>,
+    /// The latest committed height of each node.
+    pub(crate) heights: Vec<Height>,
+    pub(crate) max_round: Round,
+    pub(crate) prepared_blocks: u64,
+    pub(crate) violations: Vec<SafetyViolation>,
+    /// The indexes of the nodes removed from the authority list.
+    pub(crate) left: BTreeSet<usize>,
+    /// The removed nodes of each height, fixed once a status of the height is made.
+    left_at: BTreeMap<Height, BTreeSet<usize>>,
+}
+
+impl Ledger {
+    pub(crate) fn new(nodes: usize) -> Self {
+        Ledger {
+            heights: vec![Height::default(); nodes],
+            seeds: BTreeMap::from([(Height(1), Hash::new())]),
+            ..Default::default()
+        }
+    }
+
+    fn commit(&mut self, index: usize, address: &Address, commit: &Commit<SimBlock>) {
+        let hash = &commit.proof.block_hash;
+        match self.blocks.get(&commit.height) {
+            Some(first) if first != hash => self.violations.push(SafetyViolation {
+                height: commit.height,
+                node: address.clone(),
+                first: first.clone(),
+                conflict: hash.clone(),
+            }),
+            Some(_) => (),
+            None => {
+                self.blocks.insert(commit.height, hash.clone());
+                self.proofs.insert(commit.height, commit.proof.clone());
+            }
+        }
+        self.heights[index] = self.heights[index].max(commit.height);
+        self.max_round = self.max_round.max(commit.proof.round);
+
+        let next = commit.height + 1;
+        if let (Some(vrf), Some(seed), false) = (
+            commit.proof.vrf.as_ref(),
+            self.seeds.get(&commit.height),
+            self.seeds.contains_key(&next),
+        ) {
+            let seed = Vrf::next_seed(
+                &MockCrypto::new(address.clone()),
+                commit.height,
+                seed,
+                &vrf.output,
+            );
+            self.seeds.insert(next, seed);
+        }
+    }
+
+    /// The authority list of the status of the height, without the removed nodes. The list of a
+    /// height is fixed by the first status of it, as a chain derives it from its blocks, so the
+    /// nodes that commit the previous height before and after a node leaves or rejoins agree.
+    pub(crate) fn authority(&mut self, height: Height, authority_list: &[Node]) -> Vec<Node> {
+        let left = match self.left_at.get(&height) {
+            Some(left) => left,
+            None => self.left_at.entry(height).or_insert(self.left.clone()),
+        };
+        authority_list
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !left.contains(index))
+            .map(|(_, node)| node.clone())
+            .collect()
+    }
+}
+
+/// The application adapter of a simulated node, which sends the messages through the simulated
+/// network and records the commits in the ledger.
+pub(crate) struct SimAdapter {
+    index: usize,
+    address: Address,
+    authority_list: Vec<Node>,
+    interval: u64,
+    timer_config: Option<DurationConfig>,
+    nonce: AtomicU64,
+    network: UnboundedSender<Envelope>,
+    ledger: Arc<Mutex<Ledger>>,
+}
+
+impl SimAdapter {
+    pub(crate) fn new(
+        index: usize,
+        authority_list: Vec<Node>,
+        interval: u64,
+        timer_config: Option<DurationConfig>,
+        network: UnboundedSender<Envelope>,
+        ledger: Arc<Mutex<Ledger>>,
+    ) -> Self {
+        SimAdapter {
+            index,
+            address: authority_list[index].address.clone(),
+            authority_list,
+            interval,
+            timer_config,
+            nonce: AtomicU64::new(0),
+            network,
+            ledger,
+        }
+    }
+
+    fn send(&self, to: Option<usize>, msg: OverlordMsg<SimBlock>) {
+        let _ = self.network.unbounded_send(Envelope {
+            from: self.index,
+            to,
+            msg,
+        });
+    }
+}
+
+#[async_trait]
+impl Consensus<SimBlock> for SimAdapter {
+    async fn get_block(
+        &self,
+        _ctx: Context,
+        height: Height,
+    ) -> Result<(SimBlock, Hash), Box<dyn Error + Send>> {
+        let block = SimBlock {
+            height,
+            proposer: self.address.clone(),
+            nonce: self.nonce.fetch_add(1, Ordering::Relaxed),
+        };
+        let encode = bcs::to_bytes(&block)
+            .map_err(|e| Box::new(ConsensusError::Other(e.to_string())) as Box<dyn Error + Send>)?;
+        Ok((block, mock_hash(&encode)))
+    }
+
+    fn prepare_block(&self, _ctx: Context, _height: Height) {
+        self.ledger.lock().prepared_blocks += 1;
+    }
+
+    async fn check_block(
+        &self,
+        _ctx: Context,
+        _height: Height,
+        _hash: Hash,
+        _block: SimBlock,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        Ok(())
+    }
+
+    async fn commit(
+        &self,
+        _ctx: Context,
+        height: Height,
+        commit: Commit<SimBlock>,
+    ) -> Result<Status, Box<dyn Error + Send>> {
+        let authority_list = {
+            let mut ledger = self.ledger.lock();
+            ledger.commit(self.index, &self.address, &commit);
+            ledger.authority(height + 1, &self.authority_list)
+        };
+        Ok(Status {
+            height: height + 1,
+            interval: Some(self.interval),
+            timer_config: self.timer_config.clone(),
+            vrf_seed: None,
+            next_authority_list: None,
+            proof: None,
+            authority_list,
+        })
+    }
+
+    async fn get_authority_list(
+        &self,
+        _ctx: Context,
+        _height: Height,
+    ) -> Result<Vec<Node>, Box<dyn Error + Send>> {
+        Ok(self.authority_list.clone())
+    }
+
+    async fn broadcast_to_other(
+        &self,
+        _ctx: Context,
+        msg: OverlordMsg<SimBlock>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        self.send(None, msg);
+        Ok(())
+    }
+
+    async fn transmit_to_relayer(
+        &self,
+        _ctx: Context,
+        addr: Address,
+        msg: OverlordMsg<SimBlock>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        if let Some(to) = self
+            .authority_list
+            .iter()
+            .position(|node| node.address == addr)
+        {
+            self.send(Some(to), msg);
+        }
+        Ok(())
+    }
+
+    fn report_error(&self, _ctx: Context, _error: ConsensusError) {}
+
+    fn report_view_change(
+        &self,
+        _ctx: Context,
+        _height: Height,
+        _round: Round,
+        _reason: ViewChangeReason,
+    ) {
+    }
+}
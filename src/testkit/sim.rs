@@ -0,0 +1,604 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use creep::Context;
+use futures::channel::mpsc::unbounded;
+use parking_lot::Mutex;
+use tokio::runtime::Builder;
+use tokio::time::{sleep, Instant};
+
+use crate::test_utils::{mock_hash, MemoryWal, MockCrypto};
+use crate::testkit::fault::{FaultPolicy, FaultStats, FaultyNetwork};
+use crate::testkit::network::{Fault, Links, SimNetwork};
+use crate::testkit::node::{Ledger, SimAdapter, SimBlock};
+use crate::types::{Address, Hash, Height, Node, OverlordMsg, Round, Status};
+use crate::{ArtifactSink, DurationConfig, Overlord, OverlordConfig, OverlordHandler};
+
+/// The setting of a simulation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SimConfig {
+    /// The count of the simulated nodes, which are all in the authority list.
+    pub nodes: usize,
+    /// The seed of the node addresses and the network latencies.
+    pub seed: u64,
+    /// The height interval as millisecond.
+    pub interval: u64,
+    /// The consensus timeout configuration.
+    pub timer_config: Option<DurationConfig>,
+    /// The minimum and the maximum latency of a message as millisecond.
+    pub latency: (u64, u64),
+    /// Synchronize the nodes which lag behind over one height per interval, as millisecond.
+    pub sync_interval: u64,
+    /// The faults injected into the outgoing messages of each node, of which the seed is added by
+    /// the node index.
+    pub fault_policy: FaultPolicy,
+    /// The configuration of each overlord instance. The simulation verifies and aggregates the
+    /// signatures on its own thread, whatever `verify_workers` and `inline_aggregation` are.
+    pub overlord: OverlordConfig,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            nodes: 4,
+            seed: 0,
+            interval: 100,
+            timer_config: None,
+            latency: (1, 20),
+            sync_interval: 1000,
+            fault_policy: FaultPolicy::default(),
+            overlord: OverlordConfig::default(),
+        }
+    }
+}
+
+/// Two different blocks committed at the same height.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SafetyViolation {
+    /// The height of the conflicting commits.
+    pub height: Height,
+    /// The node which committed the conflicting block.
+    pub node: Address,
+    /// The block hash committed first at the height.
+    pub first: Hash,
+    /// The conflicting block hash.
+    pub conflict: Hash,
+}
+
+/// The result of a simulation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SimReport {
+    /// The latest committed height of each node.
+    pub heights: Vec<Height>,
+    /// The block hash committed at each height.
+    pub blocks: BTreeMap<Height, Hash>,
+    /// The highest round of the commits.
+    pub max_round: Round,
+    /// The count of the `prepare_block` hints to the proposers of the next heights.
+    pub prepared_blocks: u64,
+    /// The conflicting commits.
+    pub violations: Vec<SafetyViolation>,
+    /// The count of the faults injected into the outgoing messages of all the nodes.
+    pub fault_stats: FaultStats,
+    /// Whether all the nodes which are not crashed committed the target height.
+    pub reached: bool,
+    /// The virtual time elapsed.
+    pub elapsed: Duration,
+}
+
+impl SimReport {
+    /// Return whether no conflicting blocks are committed.
+    pub fn is_safe(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// A seeded multi-node simulation. It runs the overlord instances in process over a simulated
+/// network with seeded latencies and faults, on a single thread runtime with the virtual time, so
+/// the timeouts elapse at once when all the nodes are idle. The signatures are verified and
+/// aggregated on the tasks of that runtime rather than on other threads. It is not
+/// deterministic, the `select!`s poll their branches in a random order and the hash maps are
+/// randomly seeded, so two runs of the same setting may interleave the messages differently. The faults such as a crashed leader or
+/// a partition are scheduled at the virtual time before running.
+pub struct Simulation {
+    config: SimConfig,
+    faults: Vec<(Duration, Fault)>,
+    sinks: Vec<(usize, Arc<dyn ArtifactSink<SimBlock>>)>,
+}
+
+impl Simulation {
+    /// Create a simulation of the given setting.
+    pub fn new(config: SimConfig) -> Self {
+        Simulation {
+            config,
+            faults: Vec::new(),
+            sinks: Vec::new(),
+        }
+    }
+
+    /// Schedule a fault at the given virtual time since the simulation starts.
+    pub fn fault(mut self, at: Duration, fault: Fault) -> Self {
+        self.faults.push((at, fault));
+        self
+    }
+
+    /// Archive the consensus artifacts of the node of the index to the given sink.
+    pub fn artifact_sink(mut self, index: usize, sink: Arc<dyn ArtifactSink<SimBlock>>) -> Self {
+        self.sinks.push((index, sink));
+        self
+    }
+
+    /// Run the simulation until all the nodes which are not crashed commit the target height, or
+    /// the virtual deadline expires. It creates its own runtime, so it must not be called in an
+    /// async context.
+    pub fn run(self, target: Height, deadline: Duration) -> SimReport {
+        let runtime = Builder::new_current_thread()
+            .enable_time()
+            .start_paused(true)
+            .build()
+            .expect("build the simulation runtime");
+        runtime.block_on(self.simulate(target, deadline))
+    }
+
+    async fn simulate(self, target: Height, deadline: Duration) -> SimReport {
+        let config = self.config;
+        let authority_list = (0..config.nodes)
+            .map(|index| {
+                let mut seed = config.seed.to_be_bytes().to_vec();
+                seed.extend_from_slice(&(index as u64).to_be_bytes());
+                Node::new(Address::from(mock_hash(&seed).into_bytes()))
+            })
+            .collect::<Vec<_>>();
+        let ledger = Arc::new(Mutex::new(Ledger::new(config.nodes)));
+        let links = Arc::new(Mutex::new(Links::new(config.nodes)));
+        let (network_tx, network_rx) = unbounded();
+
+        let mut handlers = Vec::with_capacity(config.nodes);
+        let mut adapters = Vec::with_capacity(config.nodes);
+        for (index, node) in authority_list.iter().enumerate() {
+            let policy = FaultPolicy {
+                seed: config.fault_policy.seed.wrapping_add(index as u64),
+                ..config.fault_policy.clone()
+            };
+            let adapter = SimAdapter::new(
+                index,
+                authority_list.clone(),
+                config.interval,
+                config.timer_config.clone(),
+                network_tx.clone(),
+                Arc::clone(&ledger),
+            );
+            let adapter = Arc::new(FaultyNetwork::new(Arc::new(adapter), policy));
+            adapters.push(Arc::clone(&adapter));
+            let mut overlord = Overlord::new_with_config(
+                node.address.clone(),
+                adapter,
+                Arc::new(MockCrypto::new(node.address.clone())),
+                Arc::new(MemoryWal::new()),
+                OverlordConfig {
+                    verify_workers: 0,
+                    inline_aggregation: true,
+                    ..config.overlord.clone()
+                },
+            );
+            for (_, sink) in self.sinks.iter().filter(|(i, _)| *i == index) {
+                overlord = overlord.with_artifact_sink(Arc::clone(sink));
+            }
+            handlers.push(overlord.get_handler());
+
+            let (list, interval, timer_config) = (
+                authority_list.clone(),
+                config.interval,
+                config.timer_config.clone(),
+            );
+            tokio::spawn(async move {
+                if let Err(e) = overlord.run(Height(1), interval, list, timer_config).await {
+                    log::error!("Overlord: simulated node {} error {:?}", index, e);
+                }
+            });
+        }
+        drop(network_tx);
+
+        let nodes = Arc::new(SimNodes {
+            handlers: handlers.clone(),
+            authority_list,
+            interval: config.interval,
+            timer_config: config.timer_config.clone(),
+            ledger: Arc::clone(&ledger),
+            links: Arc::clone(&links),
+        });
+        nodes.sync(Height(1), 0);
+
+        let network = SimNetwork::new(config.seed, config.latency, Arc::clone(&links), handlers);
+        tokio::spawn(network.route(network_rx));
+
+        for (at, fault) in self.faults.into_iter() {
+            let nodes = Arc::clone(&nodes);
+            tokio::spawn(async move {
+                sleep(at).await;
+                log::info!("Overlord: simulation apply fault {:?}", fault);
+                nodes.links.lock().apply(&fault);
+                match fault {
+                    Fault::Leave(index) => {
+                        nodes.ledger.lock().left.insert(index);
+                    }
+                    Fault::Rejoin(index) => {
+                        nodes.ledger.lock().left.remove(&index);
+                        nodes.sync_lagging(1);
+                    }
+                    Fault::Recover(_) | Fault::Heal => nodes.sync_lagging(1),
+                    _ => (),
+                }
+            });
+        }
+
+        let start = Instant::now();
+        let mut last_sync = start;
+        let sync_interval = Duration::from_millis(config.sync_interval);
+        let reached = loop {
+            if nodes.reached(target) {
+                break true;
+            }
+            if start.elapsed() >= deadline {
+                break false;
+            }
+            sleep(Duration::from_millis(config.interval)).await;
+            if last_sync.elapsed() >= sync_interval {
+                nodes.sync_lagging(2);
+                last_sync = Instant::now();
+            }
+        };
+
+        for handler in nodes.handlers.iter() {
+            let _ = handler.send_msg(Context::new(), OverlordMsg::Stop);
+        }
+
+        let mut fault_stats = FaultStats::default();
+        for adapter in adapters.iter() {
+            fault_stats.merge(&adapter.stats());
+        }
+        let ledger = ledger.lock();
+        SimReport {
+            heights: ledger.heights.clone(),
+            blocks: ledger.blocks.clone(),
+            max_round: ledger.max_round,
+            prepared_blocks: ledger.prepared_blocks,
+            violations: ledger.violations.clone(),
+            fault_stats,
+            reached,
+            elapsed: start.elapsed(),
+        }
+    }
+}
+
+/// The simulated nodes shared with the scheduled faults.
+struct SimNodes {
+    handlers: Vec<OverlordHandler<SimBlock>>,
+    authority_list: Vec<Node>,
+    interval: u64,
+    timer_config: Option<DurationConfig>,
+    ledger: Arc<Mutex<Ledger>>,
+    links: Arc<Mutex<Links>>,
+}
+
+impl SimNodes {
+    fn reached(&self, target: Height) -> bool {
+        let ledger = self.ledger.lock();
+        let links = self.links.lock();
+        ledger
+            .heights
+            .iter()
+            .enumerate()
+            .all(|(index, height)| links.is_crashed(index) || *height >= target)
+    }
+
+    /// Synchronize the nodes which are not crashed and lag behind the highest committed height
+    /// over the given count of heights, like a block synchronization of the application.
+    fn sync_lagging(&self, min_lag: u64) {
+        let highest = self.ledger.lock().blocks.keys().last().copied();
+        if let Some(highest) = highest {
+            self.sync(highest + 1, min_lag);
+        }
+    }
+
+    /// The statuses are delivered height by height from a jump over one height, as the proof of
+    /// a jump is verified against the authority list that the node knows.
+    fn sync(&self, height: Height, min_lag: u64) {
+        let mut ledger = self.ledger.lock();
+        let links = self.links.lock();
+        for (index, handler) in self.handlers.iter().enumerate() {
+            if links.is_crashed(index) || ledger.heights[index].0 + min_lag >= height.0 {
+                continue;
+            }
+            let first = (ledger.heights[index] + 3).min(height);
+            for status_height in first.0..=height.0 {
+                let _ = handler.send_msg(
+                    Context::new(),
+                    OverlordMsg::RichStatus(Status {
+                        height: Height(status_height),
+                        interval: Some(self.interval),
+                        timer_config: self.timer_config.clone(),
+                        vrf_seed: ledger.seeds.get(&Height(status_height)).cloned(),
+                        next_authority_list: None,
+                        proof: ledger.proofs.get(&Height(status_height - 1)).cloned(),
+                        authority_list: ledger
+                            .authority(Height(status_height), &self.authority_list),
+                    }),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use parking_lot::Mutex;
+
+    use super::{SimConfig, Simulation};
+    use crate::testkit::{Fault, FaultPolicy, SimBlock};
+    use crate::types::{AggregatedChoke, AggregatedVote, Height, SignedProposal, VoteType};
+    use crate::{ArtifactSink, OverlordConfig, WalPolicy};
+
+    #[derive(Default)]
+    struct Archive {
+        proposals: Mutex<Vec<SignedProposal<SimBlock>>>,
+        qcs: Mutex<Vec<AggregatedVote>>,
+        choke_qcs: Mutex<Vec<AggregatedChoke>>,
+    }
+
+    impl ArtifactSink<SimBlock> for Archive {
+        fn archive_proposal(&self, proposal: &SignedProposal<SimBlock>) {
+            self.proposals.lock().push(proposal.clone());
+        }
+
+        fn archive_qc(&self, qc: &AggregatedVote) {
+            self.qcs.lock().push(qc.clone());
+        }
+
+        fn archive_choke_qc(&self, qc: &AggregatedChoke) {
+            self.choke_qcs.lock().push(qc.clone());
+        }
+    }
+
+    #[test]
+    fn test_simulation() {
+        let report = Simulation::new(SimConfig::default()).run(Height(5), Duration::from_secs(60));
+        assert!(report.reached);
+        assert!(report.is_safe());
+        assert!(report.heights.iter().all(|height| *height >= Height(5)));
+        assert!(report.prepared_blocks > 0);
+    }
+
+    #[test]
+    fn test_simulation_with_verify_workers() {
+        // The verify workers are ignored, the signatures are verified on the simulation thread.
+        let config = SimConfig {
+            seed: 11,
+            overlord: OverlordConfig {
+                verify_workers: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let report = Simulation::new(config)
+            .fault(Duration::from_secs(1), Fault::Crash(2))
+            .fault(Duration::from_secs(3), Fault::Recover(2))
+            .run(Height(6), Duration::from_secs(120));
+        assert!(report.reached);
+        assert!(report.is_safe());
+    }
+
+    #[test]
+    fn test_simulation_with_vrf_proposer() {
+        let config = SimConfig {
+            seed: 13,
+            overlord: OverlordConfig {
+                vrf_proposer: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        // The crashed node catches up by the statuses which carry the VRF seeds.
+        let report = Simulation::new(config)
+            .fault(Duration::from_millis(0), Fault::Crash(3))
+            .fault(Duration::from_secs(2), Fault::Recover(3))
+            .run(Height(12), Duration::from_secs(120));
+        assert!(report.reached);
+        assert!(report.is_safe());
+    }
+
+    #[test]
+    fn test_simulation_with_faults() {
+        let config = SimConfig {
+            seed: 7,
+            ..Default::default()
+        };
+        let report = Simulation::new(config.clone())
+            .fault(Duration::from_millis(0), Fault::Crash(0))
+            .run(Height(5), Duration::from_secs(120));
+        assert!(report.reached);
+        assert!(report.is_safe());
+        assert_eq!(report.heights[0], Height(0));
+
+        let report = Simulation::new(config)
+            .fault(
+                Duration::from_millis(0),
+                Fault::Partition(vec![vec![0, 1], vec![2, 3]]),
+            )
+            .fault(Duration::from_secs(5), Fault::Heal)
+            .run(Height(3), Duration::from_secs(120));
+        assert!(report.reached);
+        assert!(report.is_safe());
+        assert!(report.elapsed >= Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_simulation_with_batched_wal() {
+        let config = SimConfig {
+            seed: 5,
+            overlord: OverlordConfig {
+                wal_policy: WalPolicy::Batched,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let report = Simulation::new(config)
+            .fault(Duration::from_secs(1), Fault::Crash(1))
+            .fault(Duration::from_secs(3), Fault::Recover(1))
+            .run(Height(6), Duration::from_secs(120));
+        assert!(report.reached);
+        assert!(report.is_safe());
+    }
+
+    #[test]
+    fn test_simulation_with_verified_height_jump() {
+        let config = SimConfig {
+            seed: 9,
+            overlord: OverlordConfig {
+                verify_height_jump: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let report = Simulation::new(config)
+            .fault(Duration::from_millis(0), Fault::Crash(3))
+            .fault(Duration::from_secs(2), Fault::Recover(3))
+            .run(Height(40), Duration::from_secs(120));
+        assert!(report.reached);
+        assert!(report.is_safe());
+    }
+
+    #[test]
+    fn test_simulation_with_rejoin() {
+        let config = SimConfig {
+            seed: 13,
+            ..Default::default()
+        };
+        let report = Simulation::new(config)
+            .fault(Duration::from_millis(500), Fault::Leave(3))
+            .fault(Duration::from_secs(2), Fault::Rejoin(3))
+            .run(Height(40), Duration::from_secs(120));
+        assert!(report.reached);
+        assert!(report.is_safe());
+    }
+
+    #[test]
+    fn test_simulation_with_artifact_sink() {
+        let archive = Arc::new(Archive::default());
+        let report = Simulation::new(SimConfig::default())
+            .artifact_sink(0, Arc::clone(&archive) as Arc<dyn ArtifactSink<SimBlock>>)
+            .fault(Duration::from_millis(0), Fault::Crash(1))
+            .run(Height(5), Duration::from_secs(120));
+        assert!(report.reached);
+
+        // Every committed height has its proposal and QCs archived, and a QC is archived once.
+        let qcs = archive.qcs.lock();
+        for (height, hash) in report.blocks.iter() {
+            assert!(archive
+                .proposals
+                .lock()
+                .iter()
+                .any(|sp| sp.proposal.height == *height && sp.proposal.block_hash == *hash));
+            assert!(qcs.iter().any(|qc| qc.height == *height
+                && qc.vote_type == VoteType::Precommit
+                && qc.block_hash == *hash));
+        }
+        for (i, qc) in qcs.iter().enumerate() {
+            assert!(!qcs[..i].contains(qc));
+        }
+        // The crashed leader makes some round fail by the choke QC.
+        assert!(!archive.choke_qcs.lock().is_empty());
+    }
+
+    #[test]
+    fn test_simulation_with_faulty_network() {
+        let config = SimConfig {
+            seed: 3,
+            fault_policy: FaultPolicy {
+                seed: 3,
+                drop_rate: 100,
+                duplicate_rate: 100,
+                corrupt_rate: 0,
+                delay: (0, 50),
+            },
+            ..Default::default()
+        };
+        let report = Simulation::new(config).run(Height(5), Duration::from_secs(120));
+        assert!(report.reached);
+        assert!(report.is_safe());
+        assert!(report.fault_stats.dropped > 0);
+        assert!(report.fault_stats.duplicated > 0);
+        assert!(report.fault_stats.delayed > 0);
+    }
+
+    #[test]
+    fn test_simulation_with_pulls() {
+        let config = SimConfig {
+            seed: 11,
+            overlord: OverlordConfig {
+                proposal_pull: 50,
+                vote_pull: 50,
+                ..Default::default()
+            },
+            fault_policy: FaultPolicy {
+                seed: 11,
+                drop_rate: 150,
+                duplicate_rate: 0,
+                corrupt_rate: 0,
+                delay: (0, 20),
+            },
+            ..Default::default()
+        };
+        let report = Simulation::new(config).run(Height(5), Duration::from_secs(120));
+        assert!(report.reached);
+        assert!(report.is_safe());
+    }
+
+    #[test]
+    fn test_simulation_with_qc_relay() {
+        let config = SimConfig {
+            seed: 17,
+            overlord: OverlordConfig {
+                qc_relay: true,
+                ..Default::default()
+            },
+            fault_policy: FaultPolicy {
+                seed: 17,
+                drop_rate: 150,
+                duplicate_rate: 0,
+                corrupt_rate: 0,
+                delay: (0, 20),
+            },
+            ..Default::default()
+        };
+        let report = Simulation::new(config).run(Height(5), Duration::from_secs(120));
+        assert!(report.reached);
+        assert!(report.is_safe());
+    }
+
+    #[test]
+    fn test_simulation_with_proposal_rebroadcast() {
+        let config = SimConfig {
+            seed: 13,
+            overlord: OverlordConfig {
+                proposal_rebroadcast: 50,
+                ..Default::default()
+            },
+            fault_policy: FaultPolicy {
+                seed: 13,
+                drop_rate: 150,
+                duplicate_rate: 0,
+                corrupt_rate: 0,
+                delay: (0, 20),
+            },
+            ..Default::default()
+        };
+        let report = Simulation::new(config).run(Height(5), Duration::from_secs(120));
+        assert!(report.reached);
+        assert!(report.is_safe());
+    }
+}
@@ -6,27 +6,34 @@ use derive_more::Display;
 use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
 use futures::stream::{Stream, StreamExt};
 use futures::SinkExt;
+use rand_core::{RngCore, SeedableRng};
+use rand_pcg::Pcg64Mcg as Pcg;
 use tokio::time::{sleep, Sleep};
 
 use crate::smr::smr_types::{SMREvent, SMRTrigger, TriggerSource, TriggerType};
 use crate::smr::{Event, SMRHandler};
-use crate::DurationConfig;
+use crate::types::{Hash, Height, Round, VoteType};
+use crate::utils::timer_config::TimerConfig;
 use crate::{error::ConsensusError, ConsensusResult, INIT_HEIGHT, INIT_ROUND};
-use crate::{types::Hash, utils::timer_config::TimerConfig};
-
-const MAX_TIMEOUT_COEF: u32 = 5;
+use crate::{DurationConfig, TimeoutBackoff};
 
 /// Overlord timer used futures timer which is powered by a timer heap. When monitor a SMR event,
 /// timer will get timeout interval from timer config, then set a delay. When the timeout expires,
 #[derive(Debug)]
 pub struct Timer {
     config: TimerConfig,
+    backoff: TimeoutBackoff,
     event: Event,
     sender: UnboundedSender<SMREvent>,
     notify: UnboundedReceiver<SMREvent>,
     state_machine: SMRHandler,
-    height: u64,
-    round: u64,
+    proposal_pull: u64,
+    vote_pull: u64,
+    proposal_rebroadcast: u64,
+    jitter: u64,
+    rng: Pcg,
+    height: Height,
+    round: Round,
 }
 
 ///
@@ -86,6 +93,7 @@ impl Timer {
         state_machine: SMRHandler,
         interval: u64,
         config: Option<DurationConfig>,
+        backoff: TimeoutBackoff,
     ) -> Self {
         let (tx, rx) = unbounded();
         let mut timer_config = TimerConfig::new(interval);
@@ -95,6 +103,12 @@ impl Timer {
 
         Timer {
             config: timer_config,
+            backoff,
+            proposal_pull: 0,
+            vote_pull: 0,
+            proposal_rebroadcast: 0,
+            jitter: 0,
+            rng: Pcg::seed_from_u64(0),
             height: INIT_HEIGHT,
             round: INIT_ROUND,
             sender: tx,
@@ -104,6 +118,35 @@ impl Timer {
         }
     }
 
+    /// Notify the SMR to pull the proposal at the given percent of the propose timeout. Zero never
+    /// pulls.
+    pub fn with_proposal_pull(mut self, percent: u64) -> Self {
+        self.proposal_pull = percent;
+        self
+    }
+
+    /// Notify the SMR to pull the missing votes at the given percent of the prevote and precommit
+    /// timeouts. Zero never pulls.
+    pub fn with_vote_pull(mut self, percent: u64) -> Self {
+        self.vote_pull = percent;
+        self
+    }
+
+    /// Notify the SMR to rebroadcast the proposal of the leader at the given percent of the
+    /// prevote timeout. Zero never rebroadcasts.
+    pub fn with_proposal_rebroadcast(mut self, percent: u64) -> Self {
+        self.proposal_rebroadcast = percent;
+        self
+    }
+
+    /// Delay the propose, prevote and precommit timeouts by a random time up to the given percent
+    /// of the timeout, of which the random source is seeded by the given seed. Zero adds no delay.
+    pub fn with_jitter(mut self, percent: u64, seed: u64) -> Self {
+        self.jitter = percent;
+        self.rng = Pcg::seed_from_u64(seed);
+        self
+    }
+
     pub fn run(mut self) {
         tokio::spawn(async move {
             while let Some(err) = self.next().await {
@@ -114,6 +157,7 @@ impl Timer {
 
     fn set_timer(&mut self, event: SMREvent) -> ConsensusResult<()> {
         let mut is_brake_timer = false;
+        let mut pulls = Vec::new();
         match event.clone() {
             SMREvent::NewRoundInfo {
                 height,
@@ -133,6 +177,31 @@ impl Timer {
                 if let Some(config) = new_config {
                     self.config.update(config);
                 }
+                pulls.push((SMREvent::PullProposal { height, round }, self.proposal_pull));
+            }
+            SMREvent::PrevoteVote { height, round, .. } => {
+                pulls.push((
+                    SMREvent::PullVotes {
+                        height,
+                        round,
+                        vote_type: VoteType::Prevote,
+                    },
+                    self.vote_pull,
+                ));
+                pulls.push((
+                    SMREvent::RebroadcastProposal { height, round },
+                    self.proposal_rebroadcast,
+                ));
+            }
+            SMREvent::PrecommitVote { height, round, .. } => {
+                pulls.push((
+                    SMREvent::PullVotes {
+                        height,
+                        round,
+                        vote_type: VoteType::Precommit,
+                    },
+                    self.vote_pull,
+                ));
             }
             SMREvent::Brake { .. } => is_brake_timer = true,
             SMREvent::Commit(_) => return Ok(()),
@@ -141,11 +210,18 @@ impl Timer {
 
         let mut interval = self.config.get_timeout(event.clone())?;
         if !is_brake_timer {
-            let mut coef = self.round as u32;
-            if coef > MAX_TIMEOUT_COEF {
-                coef = MAX_TIMEOUT_COEF;
+            interval = self.backoff.timeout(interval, self.round);
+            interval = jitter(interval, self.jitter, &mut self.rng);
+        }
+
+        for (pull, percent) in pulls.into_iter() {
+            if percent > 0 && percent < 100 {
+                let pull_timer =
+                    TimeoutInfo::new(interval * percent as u32 / 100, pull, self.sender.clone());
+                tokio::spawn(async move {
+                    pull_timer.await;
+                });
             }
-            interval *= 2u32.pow(coef);
         }
 
         log::debug!("Overlord: timer set {} timer", event);
@@ -192,6 +268,27 @@ impl Timer {
                 (TriggerType::BrakeTimeout, round, height)
             }
 
+            SMREvent::PullProposal { height, round } => {
+                if height < self.height || round < self.round {
+                    return Ok(());
+                }
+                (TriggerType::PullProposal, round, height)
+            }
+
+            SMREvent::PullVotes { height, round, ref vote_type } => {
+                if height < self.height || round < self.round {
+                    return Ok(());
+                }
+                (TriggerType::PullVotes(vote_type.clone()), round, height)
+            }
+
+            SMREvent::RebroadcastProposal { height, round } => {
+                if height < self.height || round < self.round {
+                    return Ok(());
+                }
+                (TriggerType::RebroadcastProposal, round, height)
+            }
+
             _ => return Err(ConsensusError::TimerErr("No commit timer".to_string())),
         };
 
@@ -254,6 +351,15 @@ impl TimeoutInfo {
     }
 }
 
+/// Add a random delay up to the percent of the timeout.
+fn jitter(timeout: Duration, percent: u64, rng: &mut Pcg) -> Duration {
+    if percent == 0 {
+        return timeout;
+    }
+    let bound = (timeout.as_millis() as u64).saturating_mul(percent) / 100;
+    timeout + Duration::from_millis(rng.next_u64() % (bound + 1))
+}
+
 #[cfg(test)]
 mod test {
     use futures::channel::mpsc::unbounded;
@@ -261,7 +367,33 @@ mod test {
 
     use crate::smr::smr_types::{FromWhere, SMREvent, SMRTrigger, TriggerSource, TriggerType};
     use crate::smr::{Event, SMRHandler};
-    use crate::{timer::Timer, types::Hash};
+    use std::time::Duration;
+
+    use rand_core::SeedableRng;
+    use rand_pcg::Pcg64Mcg as Pcg;
+
+    use crate::timer::{jitter, Timer};
+    use crate::types::{Hash, Height, Round};
+    use crate::TimeoutBackoff;
+
+    #[test]
+    fn test_jitter() {
+        let timeout = Duration::from_millis(1000);
+        let mut rng = Pcg::seed_from_u64(7);
+        assert_eq!(jitter(timeout, 0, &mut rng), timeout);
+
+        let delays = (0..64)
+            .map(|_| jitter(timeout, 20, &mut rng))
+            .collect::<Vec<_>>();
+        assert!(delays
+            .iter()
+            .all(|delay| *delay >= timeout && *delay <= Duration::from_millis(1200)));
+        assert!(delays.iter().any(|delay| *delay != delays[0]));
+
+        // The same seed draws the same delays.
+        let mut rng = Pcg::seed_from_u64(7);
+        assert_eq!(jitter(timeout, 20, &mut rng), delays[0]);
+    }
 
     async fn test_timer_trigger(input: SMREvent, output: SMRTrigger) {
         let (trigger_tx, mut trigger_rx) = unbounded();
@@ -271,6 +403,7 @@ mod test {
             SMRHandler::new(trigger_tx),
             3000,
             None,
+            TimeoutBackoff::default(),
         );
         event_tx.unbounded_send(input).unwrap();
 
@@ -294,9 +427,9 @@ mod test {
             source: TriggerSource::Timer,
             hash: Hash::new(),
             trigger_type,
-            round,
+            round: Round(round),
             lock_round: None,
-            height,
+            height: Height(height),
             wal_info: None,
         }
     }
@@ -306,13 +439,13 @@ mod test {
         // Test propose step timer.
         test_timer_trigger(
             SMREvent::NewRoundInfo {
-                height: 0,
-                round: 0,
+                height: Height(0),
+                round: Round(0),
                 lock_round: None,
                 lock_proposal: None,
                 new_interval: None,
                 new_config: None,
-                from_where: FromWhere::PrecommitQC(0),
+                from_where: FromWhere::PrecommitQC(Round(0)),
             },
             gen_output(TriggerType::Proposal, 0, 0),
         )
@@ -321,8 +454,8 @@ mod test {
         // Test prevote step timer.
         test_timer_trigger(
             SMREvent::PrevoteVote {
-                height: 0u64,
-                round: 0u64,
+                height: Height(0),
+                round: Round(0),
                 block_hash: Hash::new(),
                 lock_round: None,
             },
@@ -333,8 +466,8 @@ mod test {
         // Test precommit step timer.
         test_timer_trigger(
             SMREvent::PrecommitVote {
-                height: 0u64,
-                round: 0u64,
+                height: Height(0),
+                round: Round(0),
                 block_hash: Hash::new(),
                 lock_round: None,
             },
@@ -352,28 +485,29 @@ mod test {
             SMRHandler::new(trigger_tx),
             3000,
             None,
+            TimeoutBackoff::default(),
         );
 
         let new_round_event = SMREvent::NewRoundInfo {
-            height: 0,
-            round: 0,
+            height: Height(0),
+            round: Round(0),
             lock_round: None,
             lock_proposal: None,
             new_interval: None,
             new_config: None,
-            from_where: FromWhere::PrecommitQC(0),
+            from_where: FromWhere::PrecommitQC(Round(0)),
         };
 
         let prevote_event = SMREvent::PrevoteVote {
-            height: 0u64,
-            round: 0u64,
+            height: Height(0),
+            round: Round(0),
             block_hash: Hash::new(),
             lock_round: None,
         };
 
         let precommit_event = SMREvent::PrecommitVote {
-            height: 0u64,
-            round: 0u64,
+            height: Height(0),
+            round: Round(0),
             block_hash: Hash::new(),
             lock_round: None,
         };
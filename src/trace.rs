@@ -0,0 +1,288 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use hummer::coding::hex_encode;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ConsensusError;
+use crate::smr::replay;
+use crate::smr::smr_types::{SMREvent, SMRTrigger, TriggerType};
+use crate::ConsensusResult;
+
+/// A trigger handled by the SMR or an event thrown by it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum TraceEntry {
+    /// A trigger handled by the SMR.
+    Trigger(SMRTrigger),
+    /// An event thrown to both the state and the timer.
+    Event(SMREvent),
+    /// An event thrown to the timer only.
+    TimerEvent(SMREvent),
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceEntry::Trigger(trigger) => write!(
+                f,
+                "{} trigger from {:?}, height {}, round {}, lock round {:?}, hash {}",
+                trigger.trigger_type,
+                trigger.source,
+                trigger.height,
+                trigger.round,
+                trigger.lock_round,
+                hex_encode(&trigger.hash)
+            ),
+            TraceEntry::Event(event) => write!(f, "{}", event),
+            TraceEntry::TimerEvent(event) => write!(f, "{} to timer", event),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+struct TraceRecord {
+    /// The unix timestamp in milliseconds.
+    timestamp: u64,
+    entry: TraceEntry,
+}
+
+/// The triggers handled by the SMR and the events thrown by it in order, with the timestamps. It
+/// is encoded to be kept as a file, and a replay drives a fresh SMR by the triggers to check that
+/// it throws the same events, so that a liveness bug seen in an incident can be reproduced.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SmrTrace {
+    records: Vec<TraceRecord>,
+}
+
+/// The result of a replay of a trace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayReport {
+    /// The count of the replayed triggers.
+    pub triggers: usize,
+    /// The count of the events thrown by the replay.
+    pub events: usize,
+    /// The first entry where the replay differs from the trace, if any.
+    pub divergence: Option<String>,
+}
+
+impl SmrTrace {
+    /// Encode the trace.
+    pub fn encode(&self) -> ConsensusResult<Bytes> {
+        bcs::to_bytes(self)
+            .map(Bytes::from)
+            .map_err(|e| ConsensusError::Other(format!("encode smr trace {:?}", e)))
+    }
+
+    /// Decode a trace from the encoded bytes.
+    pub fn decode(raw: &[u8]) -> ConsensusResult<Self> {
+        bcs::from_bytes(raw)
+            .map_err(|e| ConsensusError::DecodeErr(format!("decode smr trace {:?}", e)))
+    }
+
+    /// The count of the records.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether the trace has no record.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// The readable lines of the records, each prefixed by its timestamp.
+    pub fn lines(&self) -> Vec<String> {
+        self.records
+            .iter()
+            .map(|record| format!("{} {}", record.timestamp, record.entry))
+            .collect()
+    }
+
+    /// Drive a fresh SMR by the triggers of the trace, and compare the entries it records with the
+    /// ones of the trace. A trace which starts in the middle of a height diverges at its first
+    /// event, since the fresh SMR has not reached the height.
+    pub fn replay(&self) -> ReplayReport {
+        let recorder = Arc::new(TraceRecorder::new(usize::MAX));
+        let triggers = self
+            .records
+            .iter()
+            .filter_map(|record| match &record.entry {
+                TraceEntry::Trigger(trigger) => Some(trigger.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        replay(triggers, Arc::clone(&recorder));
+
+        let replayed = recorder.trace().records;
+        let mut report = ReplayReport {
+            triggers: 0,
+            events: 0,
+            divergence: None,
+        };
+        for (i, record) in replayed.iter().enumerate() {
+            match self.records.get(i) {
+                Some(expect) if expect.entry == record.entry => (),
+                expect => {
+                    report.divergence = Some(format!(
+                        "record {}: traced {}, replayed {}",
+                        i,
+                        expect.map_or("nothing".to_string(), |r| r.entry.to_string()),
+                        record.entry
+                    ));
+                    break;
+                }
+            }
+            match record.entry {
+                TraceEntry::Trigger(_) => report.triggers += 1,
+                _ => report.events += 1,
+            }
+        }
+        if report.divergence.is_none() && replayed.len() < self.records.len() {
+            report.divergence = Some(format!(
+                "record {}: traced {}, replayed nothing",
+                replayed.len(),
+                self.records[replayed.len()].entry
+            ));
+        }
+        report
+    }
+}
+
+/// The recorder of the SMR trace of an overlord instance, see `Overlord::with_trace_recorder`. It
+/// keeps the records up to the capacity, and drops the oldest heights as a whole beyond it, so
+/// that the kept trace starts with a new height and replays from it. The capacity should cover
+/// the records of a height, otherwise the trace is cut in the middle of a height.
+#[derive(Debug)]
+pub struct TraceRecorder {
+    capacity: usize,
+    records: Mutex<VecDeque<TraceRecord>>,
+}
+
+impl TraceRecorder {
+    /// Create a recorder of the given capacity.
+    pub fn new(capacity: usize) -> Self {
+        TraceRecorder {
+            capacity,
+            records: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// The trace recorded so far.
+    pub fn trace(&self) -> SmrTrace {
+        SmrTrace {
+            records: self.records.lock().iter().cloned().collect(),
+        }
+    }
+
+    pub(crate) fn record(&self, entry: TraceEntry) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let mut records = self.records.lock();
+        records.push_back(TraceRecord { timestamp, entry });
+        if records.len() <= self.capacity {
+            return;
+        }
+
+        let next_height = records
+            .iter()
+            .skip(1)
+            .position(|record| is_new_height(&record.entry))
+            .map_or(1, |pos| pos + 1);
+        records.drain(..next_height);
+    }
+}
+
+fn is_new_height(entry: &TraceEntry) -> bool {
+    matches!(
+        entry,
+        TraceEntry::Trigger(SMRTrigger {
+            trigger_type: TriggerType::NewHeight(_),
+            ..
+        })
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::{SmrTrace, TraceEntry, TraceRecorder};
+    use crate::smr::replay;
+    use crate::smr::smr_types::{SMRStatus, SMRTrigger, TriggerSource, TriggerType};
+    use crate::types::{Hash, Height, Round};
+
+    fn trigger(trigger_type: TriggerType, height: u64, hash: &[u8]) -> SMRTrigger {
+        SMRTrigger {
+            trigger_type,
+            source: TriggerSource::State,
+            hash: Hash::from(hash.to_vec()),
+            lock_round: None,
+            round: Round(0),
+            height: Height(height),
+            wal_info: None,
+        }
+    }
+
+    fn height_triggers(height: u64) -> Vec<SMRTrigger> {
+        vec![
+            trigger(
+                TriggerType::NewHeight(SMRStatus::new(Height(height))),
+                height,
+                b"",
+            ),
+            trigger(TriggerType::Proposal, height, b"a"),
+            trigger(TriggerType::PrevoteQC, height, b"a"),
+            trigger(TriggerType::PrecommitQC, height, b"a"),
+        ]
+    }
+
+    #[test]
+    fn test_trace_replay() {
+        let recorder = Arc::new(TraceRecorder::new(usize::MAX));
+        replay(
+            (1..4).flat_map(height_triggers).collect(),
+            Arc::clone(&recorder),
+        );
+        let trace = SmrTrace::decode(&recorder.trace().encode().unwrap()).unwrap();
+        assert_eq!(trace, recorder.trace());
+        assert!(trace.lines()[0].contains("New height trigger from State, height 1"));
+
+        let report = trace.replay();
+        assert_eq!(report.triggers, 12);
+        assert_eq!(report.events, trace.len() - 12);
+        assert_eq!(report.divergence, None);
+
+        // A trace with a dropped event diverges at it.
+        let mut broken = trace.clone();
+        let pos = broken
+            .records
+            .iter()
+            .position(|record| matches!(record.entry, TraceEntry::Event(_)))
+            .unwrap();
+        broken.records.remove(pos);
+        assert!(broken
+            .replay()
+            .divergence
+            .unwrap()
+            .starts_with(&format!("record {}", pos)));
+    }
+
+    #[test]
+    fn test_trace_capacity() {
+        let recorder = Arc::new(TraceRecorder::new(12));
+        replay(
+            (1..4).flat_map(height_triggers).collect(),
+            Arc::clone(&recorder),
+        );
+
+        // The oldest heights are dropped as a whole, so the kept trace replays.
+        let trace = recorder.trace();
+        assert!(trace.len() <= 12);
+        assert!(trace.lines()[0].contains("New height trigger from State, height 3"));
+        assert_eq!(trace.replay().divergence, None);
+    }
+}
@@ -0,0 +1,201 @@
+use alloy_rlp::{encode_list, Decodable, Encodable, Header};
+use bytes::Bytes;
+use creep::Context;
+
+use crate::codec::{compress_frame, decompress_frame, CodecConfig};
+use crate::error::ConsensusError;
+use crate::types::OverlordMsg;
+use crate::{Codec, ConsensusResult};
+
+const SIGNED_PROPOSAL: u8 = 0;
+const SIGNED_VOTE: u8 = 1;
+const VOTE_BATCH: u8 = 2;
+const AGGREGATED_VOTE: u8 = 3;
+const SIGNED_CHOKE: u8 = 4;
+const SIGNED_COMPACT_PROPOSAL: u8 = 5;
+const BLOCK_PART: u8 = 6;
+const PULL_QC: u8 = 7;
+const PULL_PROPOSAL: u8 = 8;
+const PULL_VOTES: u8 = 9;
+const ROUND_SUMMARY: u8 = 10;
+
+/// Encode a message to send to the peers, as the RLP list of its kind and its payload, which is
+/// compressed under the `compression` feature if it is large. The transport between the nodes is
+/// up to the application, which sends the encoded bytes as they are over its own connections, be
+/// it gRPC, a gossip network or anything else. The rich status and the stop messages come from the
+/// application only, and are not encoded.
+pub fn encode_msg<T: Codec>(msg: &OverlordMsg<T>) -> ConsensusResult<Bytes> {
+    let (kind, payload): (u8, &dyn Encodable) = match msg {
+        OverlordMsg::SignedProposal(sp) => (SIGNED_PROPOSAL, sp),
+        OverlordMsg::SignedVote(sv) => (SIGNED_VOTE, sv),
+        OverlordMsg::VoteBatch(votes) => (VOTE_BATCH, votes),
+        OverlordMsg::AggregatedVote(av) => (AGGREGATED_VOTE, av),
+        OverlordMsg::SignedChoke(sc) => (SIGNED_CHOKE, sc),
+        OverlordMsg::SignedCompactProposal(sp) => (SIGNED_COMPACT_PROPOSAL, sp),
+        OverlordMsg::BlockPart(part) => (BLOCK_PART, part),
+        OverlordMsg::PullQC(pull) => (PULL_QC, pull),
+        OverlordMsg::PullProposal(pull) => (PULL_PROPOSAL, pull),
+        OverlordMsg::PullVotes(pull) => (PULL_VOTES, pull),
+        OverlordMsg::RoundSummary(summary) => (ROUND_SUMMARY, summary),
+        _ => {
+            return Err(ConsensusError::Other(format!(
+                "{} is not a network message",
+                msg
+            )))
+        }
+    };
+
+    let mut out = Vec::new();
+    let enc: [&dyn Encodable; 2] = [&kind, payload];
+    encode_list::<_, dyn Encodable>(&enc, &mut out);
+    Ok(Bytes::from(compress_frame(out)))
+}
+
+/// Decode a message received from a peer with the limits of the config enforced, see
+/// `encode_msg`. A compressed message is decompressed up to `max_content_bytes` first.
+pub fn decode_msg<T: Codec>(config: &CodecConfig, buf: &[u8]) -> ConsensusResult<OverlordMsg<T>> {
+    let buf = decompress_frame(buf, Some(config.max_content_bytes))?;
+    config
+        .decode::<NetworkMsg<T>>(&buf)
+        .map(|network_msg| network_msg.0)
+}
+
+/// The context key of the [`PeerId`] of a received message.
+pub const PEER_ID_KEY: &str = "overlord_peer_id";
+
+/// The transport identity of the peer that a message is received from. A transport attaches it
+/// to the context passed to `OverlordHandler::send_msg`, and the context is handed back to
+/// `Consensus::report_error` when the message is invalid, so that the application can punish the
+/// peer rather than the signer, which a relaying peer may not be. With a gossip network, the
+/// peer is the one that forwarded the message, not the one that published it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PeerId(pub Bytes);
+
+impl PeerId {
+    /// Get the peer id carried by the context, if any.
+    pub fn from_context(ctx: &Context) -> Option<PeerId> {
+        ctx.get::<PeerId>(PEER_ID_KEY).cloned()
+    }
+
+    /// Attach the peer id to the context.
+    pub fn attach(&self, ctx: &Context) -> Context {
+        ctx.with_value(PEER_ID_KEY, self.clone())
+    }
+}
+
+/// A message of the peers on the wire.
+struct NetworkMsg<T: Codec>(OverlordMsg<T>);
+
+impl<T: Codec> Decodable for NetworkMsg<T> {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let mut payload = Header::decode_bytes(buf, true)?;
+        let msg = match u8::decode(&mut payload)? {
+            SIGNED_PROPOSAL => OverlordMsg::SignedProposal(Decodable::decode(&mut payload)?),
+            SIGNED_VOTE => OverlordMsg::SignedVote(Decodable::decode(&mut payload)?),
+            VOTE_BATCH => OverlordMsg::VoteBatch(Decodable::decode(&mut payload)?),
+            AGGREGATED_VOTE => OverlordMsg::AggregatedVote(Decodable::decode(&mut payload)?),
+            SIGNED_CHOKE => OverlordMsg::SignedChoke(Decodable::decode(&mut payload)?),
+            SIGNED_COMPACT_PROPOSAL => {
+                OverlordMsg::SignedCompactProposal(Decodable::decode(&mut payload)?)
+            }
+            BLOCK_PART => OverlordMsg::BlockPart(Decodable::decode(&mut payload)?),
+            PULL_QC => OverlordMsg::PullQC(Decodable::decode(&mut payload)?),
+            PULL_PROPOSAL => OverlordMsg::PullProposal(Decodable::decode(&mut payload)?),
+            PULL_VOTES => OverlordMsg::PullVotes(Decodable::decode(&mut payload)?),
+            ROUND_SUMMARY => OverlordMsg::RoundSummary(Decodable::decode(&mut payload)?),
+            _ => return Err(alloy_rlp::Error::Custom("unknown message kind")),
+        };
+        Ok(NetworkMsg(msg))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use creep::Context;
+
+    use super::{decode_msg, encode_msg, PeerId};
+    use crate::codec::CodecConfig;
+    use crate::types::{
+        Address, Hash, Height, OverlordMsg, PullQC, Round, Signature, SignedVote, Vote, VoteType,
+    };
+    #[cfg(feature = "compression")]
+    use crate::types::{Proposal, SignedProposal};
+
+    fn signed_vote(voter: u8) -> SignedVote {
+        SignedVote {
+            signature: Signature::from(vec![voter; 4]),
+            vote: Vote {
+                height: Height(3),
+                round: Round(1),
+                vote_type: VoteType::Prevote,
+                block_hash: Hash::from(vec![7u8; 32]),
+            },
+            voter: Address::from(vec![voter; 20]),
+            extension: None,
+        }
+    }
+
+    #[test]
+    fn test_network_msg() {
+        let config = CodecConfig::default();
+        let msgs: Vec<OverlordMsg<Vec<u8>>> = vec![
+            OverlordMsg::SignedVote(signed_vote(1)),
+            OverlordMsg::VoteBatch(vec![signed_vote(1), signed_vote(2)]),
+            OverlordMsg::PullQC(PullQC {
+                height: Height(3),
+                round: Round(1),
+                vote_type: VoteType::Precommit,
+                requester: Address::from(vec![3u8; 20]),
+            }),
+        ];
+        for msg in msgs.into_iter() {
+            let encode = encode_msg(&msg).unwrap();
+            assert_eq!(decode_msg::<Vec<u8>>(&config, &encode).unwrap(), msg);
+        }
+
+        // The messages of the application are never on the wire.
+        assert!(encode_msg::<Vec<u8>>(&OverlordMsg::Stop).is_err());
+        let unknown = Bytes::from(alloy_rlp::encode(vec![0xffu8]));
+        assert!(decode_msg::<Vec<u8>>(&config, &unknown).is_err());
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_compressed_msg() {
+        let proposal = Proposal {
+            height: Height(3),
+            round: Round(1),
+            content: vec![7u8; 4096],
+            block_hash: Hash::from(vec![7u8; 32]),
+            lock: None,
+            proposer: Address::from(vec![1u8; 20]),
+            vrf: None,
+        };
+        let msg = OverlordMsg::SignedProposal(SignedProposal {
+            signature: Signature::from(vec![1u8; 4]),
+            proposal,
+        });
+        let encode = encode_msg(&msg).unwrap();
+        assert!(encode.len() < 1024);
+        assert_eq!(
+            decode_msg::<Vec<u8>>(&CodecConfig::default(), &encode).unwrap(),
+            msg
+        );
+
+        // The decompressed message is limited as the content.
+        let config = CodecConfig {
+            max_content_bytes: 1024,
+            ..Default::default()
+        };
+        assert!(decode_msg::<Vec<u8>>(&config, &encode).is_err());
+    }
+
+    #[test]
+    fn test_peer_id() {
+        let peer = PeerId(Bytes::from_static(b"peer"));
+        let ctx = peer.attach(&Context::new());
+        assert_eq!(PeerId::from_context(&ctx), Some(peer));
+        assert_eq!(PeerId::from_context(&Context::new()), None);
+    }
+}
@@ -1,21 +1,205 @@
 use std::cmp::{Ord, Ordering, PartialOrd};
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
-
-use alloy_rlp::{RlpDecodable, RlpEncodable};
+use std::fmt;
+use std::ops::{Add, AddAssign, Deref, Sub};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloy_rlp::{RlpDecodable, RlpDecodableWrapper, RlpEncodable, RlpEncodableWrapper};
+use bit_vec::BitVec;
 use bytes::Bytes;
+use creep::Context;
 use derive_more::Display;
+use futures::channel::{mpsc::Sender, oneshot};
+use hummer::coding::hex_encode;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
+use crate::codec::domain_payload;
 use crate::error::ConsensusError;
+use crate::metrics::StateMetrics;
 use crate::smr::smr_types::{SMRStatus, Step, TriggerType};
-use crate::{Codec, DurationConfig};
+use crate::utils::auth_manage::AuthorityManage;
+use crate::wal::WalInfo;
+use crate::{Codec, ConsensusResult, Crypto, DurationConfig};
+
+macro_rules! bytes_type {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[derive(
+            Serialize,
+            Deserialize,
+            RlpEncodableWrapper,
+            RlpDecodableWrapper,
+            Clone,
+            Default,
+            PartialEq,
+            Eq,
+            PartialOrd,
+            Ord,
+            Hash,
+        )]
+        #[serde(transparent)]
+        pub struct $name(Bytes);
+
+        impl $name {
+            /// Create an empty one.
+            pub fn new() -> Self {
+                $name(Bytes::new())
+            }
+
+            /// Create one of the given bytes, which must be of the given length.
+            pub fn with_len(bytes: Bytes, len: usize) -> ConsensusResult<Self> {
+                if bytes.len() != len {
+                    return Err(ConsensusError::Other(format!(
+                        "{} of {} bytes, expect {} bytes",
+                        stringify!($name),
+                        bytes.len(),
+                        len
+                    )));
+                }
+                Ok($name(bytes))
+            }
+
+            /// Get the inner bytes.
+            pub fn as_bytes(&self) -> &Bytes {
+                &self.0
+            }
+
+            /// Convert into the inner bytes.
+            pub fn into_bytes(self) -> Bytes {
+                self.0
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(&self.0, f)
+            }
+        }
+
+        impl Deref for $name {
+            type Target = [u8];
+
+            fn deref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl From<Bytes> for $name {
+            fn from(bytes: Bytes) -> Self {
+                $name(bytes)
+            }
+        }
+
+        impl From<Vec<u8>> for $name {
+            fn from(bytes: Vec<u8>) -> Self {
+                $name(Bytes::from(bytes))
+            }
+        }
+
+        impl From<$name> for Bytes {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+    };
+}
+
+bytes_type!(
+    /// Address type. It is encoded as the inner bytes by both serde and rlp.
+    Address
+);
+bytes_type!(
+    /// Hash type. It is encoded as the inner bytes by both serde and rlp.
+    Hash
+);
+bytes_type!(
+    /// Signature type. It is encoded as the inner bytes by both serde and rlp.
+    Signature
+);
+
+macro_rules! number_type {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[derive(
+            Serialize,
+            Deserialize,
+            RlpEncodableWrapper,
+            RlpDecodableWrapper,
+            Clone,
+            Copy,
+            Debug,
+            Default,
+            Display,
+            PartialEq,
+            Eq,
+            PartialOrd,
+            Ord,
+            Hash,
+        )]
+        #[serde(transparent)]
+        #[display("{}", _0)]
+        pub struct $name(pub u64);
+
+        impl $name {
+            /// Add the given count, which saturates at the maximum rather than overflows.
+            pub fn saturating_add(self, rhs: u64) -> Self {
+                $name(self.0.saturating_add(rhs))
+            }
+        }
+
+        impl From<u64> for $name {
+            fn from(value: u64) -> Self {
+                $name(value)
+            }
+        }
 
-/// Address type.
-pub type Address = Bytes;
-/// Hash type.
-pub type Hash = Bytes;
-/// Signature type.
-pub type Signature = Bytes;
+        impl From<$name> for u64 {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl Add<u64> for $name {
+            type Output = $name;
+
+            fn add(self, rhs: u64) -> Self::Output {
+                $name(self.0 + rhs)
+            }
+        }
+
+        impl AddAssign<u64> for $name {
+            fn add_assign(&mut self, rhs: u64) {
+                self.0 += rhs;
+            }
+        }
+
+        impl Sub<u64> for $name {
+            type Output = $name;
+
+            fn sub(self, rhs: u64) -> Self::Output {
+                $name(self.0 - rhs)
+            }
+        }
+    };
+}
+
+number_type!(
+    /// Height type. It is encoded as the inner `u64` by both serde and rlp.
+    Height
+);
+number_type!(
+    /// Round type. It is encoded as the inner `u64` by both serde and rlp.
+    Round
+);
 
 /// Vote or QC types. Prevote and precommit QC will promise the rightness and the final consistency
 /// of overlord consensus protocol.
@@ -78,6 +262,9 @@ pub enum OverlordMsg<T: Codec> {
     /// Signed vote message.
     #[display("Signed Vote")]
     SignedVote(SignedVote),
+    /// A batch of signed votes, which are verified and collected together.
+    #[display("Vote Batch")]
+    VoteBatch(Vec<SignedVote>),
     /// Aggregated vote message.
     #[display("Aggregated Vote")]
     AggregatedVote(AggregatedVote),
@@ -87,6 +274,24 @@ pub enum OverlordMsg<T: Codec> {
     /// Signed choke message
     #[display("Choke Message")]
     SignedChoke(SignedChoke),
+    /// Signed proposal message without the block content.
+    #[display("Signed Compact Proposal")]
+    SignedCompactProposal(SignedCompactProposal),
+    /// A part of the block of a compact proposal.
+    #[display("Block Part")]
+    BlockPart(BlockPart),
+    /// Request a missing QC from the peers.
+    #[display("Pull QC")]
+    PullQC(PullQC),
+    /// Request a missing proposal from the proposer and the backups.
+    #[display("Pull Proposal")]
+    PullProposal(PullProposal),
+    /// Request the votes that the leader misses from the peers.
+    #[display("Pull Votes")]
+    PullVotes(PullVotes),
+    /// Summary of a round jump by a choke QC.
+    #[display("Round Summary")]
+    RoundSummary(RoundSummary),
     /// Stop consensus process.
     #[display("Stop Overlord")]
     Stop,
@@ -101,18 +306,57 @@ impl<T: Codec> OverlordMsg<T> {
         matches!(self, OverlordMsg::RichStatus(_))
     }
 
-    pub(crate) fn get_height(&self) -> u64 {
+    /// Rich status and stop messages come from the application rather than the network, they
+    /// bypass the bounded message channel and are never dropped.
+    pub(crate) fn is_priority(&self) -> bool {
+        matches!(self, OverlordMsg::RichStatus(_) | OverlordMsg::Stop)
+    }
+
+    /// The address that signs or sends the message, if any.
+    pub(crate) fn sender(&self) -> Option<&Address> {
+        match self {
+            OverlordMsg::SignedProposal(sp) => Some(&sp.proposal.proposer),
+            OverlordMsg::SignedCompactProposal(sp) => Some(&sp.proposal.proposer),
+            OverlordMsg::SignedVote(sv) => Some(&sv.voter),
+            OverlordMsg::AggregatedVote(av) => Some(&av.leader),
+            OverlordMsg::SignedChoke(sc) => Some(&sc.address),
+            OverlordMsg::PullQC(pull) => Some(&pull.requester),
+            OverlordMsg::PullProposal(pull) => Some(&pull.requester),
+            OverlordMsg::PullVotes(pull) => Some(&pull.requester),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn get_height(&self) -> Height {
         match self {
             OverlordMsg::SignedProposal(sp) => sp.proposal.height,
             OverlordMsg::SignedVote(sv) => sv.get_height(),
+            OverlordMsg::VoteBatch(votes) => {
+                votes.first().map(|sv| sv.get_height()).unwrap_or_default()
+            }
             OverlordMsg::AggregatedVote(av) => av.get_height(),
             OverlordMsg::RichStatus(s) => s.height,
             OverlordMsg::SignedChoke(sc) => sc.choke.height,
+            OverlordMsg::SignedCompactProposal(sp) => sp.proposal.height,
+            OverlordMsg::BlockPart(part) => part.height,
+            OverlordMsg::PullQC(pull) => pull.height,
+            OverlordMsg::PullProposal(pull) => pull.height,
+            OverlordMsg::PullVotes(pull) => pull.height,
+            OverlordMsg::RoundSummary(summary) => summary.height,
             _ => unreachable!(),
         }
     }
 }
 
+/// Split the votes of a batch by height, in the ascending order of height.
+pub(crate) fn split_vote_batch(votes: Vec<SignedVote>) -> Vec<Vec<SignedVote>> {
+    let mut batches: BTreeMap<Height, Vec<SignedVote>> = BTreeMap::new();
+    for vote in votes.into_iter() {
+        batches.entry(vote.get_height()).or_default().push(vote);
+    }
+    batches.into_values().collect()
+}
+
 /// How does state goto the current round.
 #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
 pub enum UpdateFrom {
@@ -127,9 +371,16 @@ pub enum UpdateFrom {
 /// The reason of overlord view change.
 #[derive(Serialize, Deserialize, Clone, Debug, Display)]
 pub enum ViewChangeReason {
-    ///
-    #[display("Do not receive proposal from network")]
-    NoProposalFromNetwork,
+    /// No proposal of the round is received from the expected proposer.
+    #[display(
+        "Do not receive proposal from network, proposer {}",
+        hex_encode(proposer)
+    )]
+    NoProposalFromNetwork {
+        /// The expected proposer of the round.
+        #[serde(with = "super::serde_hex")]
+        proposer: Address,
+    },
 
     ///
     #[display("Do not receive Prevote QC from network")]
@@ -139,37 +390,173 @@ pub enum ViewChangeReason {
     #[display("Do not receive precommit QC from network")]
     NoPrecommitQCFromNetwork,
 
-    ///
-    #[display("Check the block not pass")]
-    CheckBlockNotPass,
+    /// The proposed block does not pass `check_block` in time.
+    #[display("Check the block {} not pass", hex_encode(block_hash))]
+    CheckBlockNotPass {
+        /// The hash of the proposed block.
+        #[serde(with = "super::serde_hex")]
+        block_hash: Hash,
+    },
 
     ///
     #[display("Update from a higher round prevote QC from {} to {}", _0, _1)]
-    UpdateFromHigherPrevoteQC(u64, u64),
+    UpdateFromHigherPrevoteQC(Round, Round),
 
     ///
     #[display("Update from a higher round precommit QC from {} to {}", _0, _1)]
-    UpdateFromHigherPrecommitQC(u64, u64),
+    UpdateFromHigherPrecommitQC(Round, Round),
 
     ///
     #[display("Update from a higher round choke QC from {} to {}", _0, _1)]
-    UpdateFromHigherChokeQC(u64, u64),
+    UpdateFromHigherChokeQC(Round, Round),
+
+    /// The leader does not collect enough votes of the vote type to form the QC.
+    #[display(
+        "{:?} votes count is below threshold, {} authorities missing",
+        vote_type,
+        missing.len()
+    )]
+    LeaderReceivedVoteBelowThreshold {
+        /// The type of the votes.
+        vote_type: VoteType,
+        /// The authorities of which no vote is received.
+        #[serde(with = "super::serde_multi_hex")]
+        missing: Vec<Address>,
+    },
 
     ///
-    #[display("{:?} votes count is below threshold", _0)]
-    LeaderReceivedVoteBelowThreshold(VoteType),
+    #[display("Forced by the handler")]
+    Forced,
 
     ///
     #[display("other reasons")]
     Others,
 }
 
+/// The reason that the state drops a message.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Display, PartialEq, Eq, Hash, PartialOrd, Ord,
+)]
+pub enum DropReason {
+    /// The height of the message is lower than the current height.
+    #[display("outdated height")]
+    OutdatedHeight,
+    /// The round of the message is lower than the current round of the current height.
+    #[display("outdated round")]
+    OutdatedRound,
+    /// The height of the message is beyond the future height gap.
+    #[display("far future height")]
+    FutureHeight,
+    /// The round of the message is beyond the future round gap.
+    #[display("far future round")]
+    FutureRound,
+}
+
+/// A message dropped by the state, which is reported by `Consensus::report_message_drop`.
+#[derive(Serialize, Deserialize, Clone, Debug, Display, PartialEq, Eq)]
+#[display(
+    "Drop {} height {}, round {:?} for {}",
+    msg_type,
+    height,
+    round,
+    reason
+)]
+pub struct MessageDrop {
+    /// The type of the message, as the display of `OverlordMsg`.
+    pub msg_type: String,
+    /// The height of the message.
+    pub height: Height,
+    /// The round of the message if it has one.
+    pub round: Option<Round>,
+    /// The reason of the drop.
+    pub reason: DropReason,
+}
+
+/// The context key of the [`HeightCancel`] carried by the contexts of `check_block` and
+/// `get_block_by_hash`.
+pub const HEIGHT_CANCEL_KEY: &str = "overlord_height_cancel";
+
+/// The cancellation flag of the work scoped to a height. The overlord cancels it when the state
+/// leaves the height, so that a long running `check_block` or `get_block_by_hash` can stop early.
+#[derive(Clone, Debug, Default)]
+pub struct HeightCancel(Arc<AtomicBool>);
+
+impl HeightCancel {
+    /// Get the cancellation flag carried by the context, if any.
+    pub fn from_context(ctx: &Context) -> Option<HeightCancel> {
+        ctx.get::<HeightCancel>(HEIGHT_CANCEL_KEY).cloned()
+    }
+
+    /// Return whether the height of the work is abandoned.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(AtomicOrdering::Acquire)
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.0.store(true, AtomicOrdering::Release);
+    }
+}
+
+/// The context key of the [`RoundCancel`] carried by the contexts of `check_block`.
+pub const ROUND_CANCEL_KEY: &str = "overlord_round_cancel";
+
+/// The cancellation flag of a check block task scoped to the round it is started in. The overlord
+/// cancels it when the state leaves the round, so that a long running `check_block` can stop early.
+/// The check of a round which is left is still used if it finishes, as the same block may be
+/// proposed again, while the check of a height which is left is aborted, see [`HeightCancel`].
+#[derive(Clone, Debug, Default)]
+pub struct RoundCancel(Arc<AtomicBool>);
+
+impl RoundCancel {
+    /// Get the cancellation flag carried by the context, if any.
+    pub fn from_context(ctx: &Context) -> Option<RoundCancel> {
+        ctx.get::<RoundCancel>(ROUND_CANCEL_KEY).cloned()
+    }
+
+    /// Return whether the round of the work is left.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(AtomicOrdering::Acquire)
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.0.store(true, AtomicOrdering::Release);
+    }
+}
+
+/// The work of a height which is abandoned as self loses the consensus power.
+#[derive(Serialize, Deserialize, Clone, Debug, Display, PartialEq, Eq)]
+#[display(
+    "Abandon height {}, check block tasks {}, block fetches {}",
+    height,
+    check_tasks,
+    block_fetches
+)]
+pub struct AbandonedWork {
+    /// The abandoned height.
+    pub height: Height,
+    /// The count of the cancelled check block tasks, including the queued ones.
+    pub check_tasks: u64,
+    /// The count of the cancelled block fetches of compact proposals.
+    pub block_fetches: u64,
+}
+
+/// The decision of the application on whether to propose the block of self.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Display, PartialEq, Eq)]
+pub enum Decision {
+    /// Sign and broadcast the proposal.
+    #[display("Propose")]
+    Propose,
+    /// Do not propose in this round, the round goes on with a timeout of the proposal.
+    #[display("Abort")]
+    Abort,
+}
+
 /// A signed proposal.
 #[derive(Serialize, Deserialize, Clone, Debug, Display, PartialEq, Eq)]
 #[display("Signed Proposal {:?}", proposal)]
 pub struct SignedProposal<T: Codec> {
     /// Signature of the proposal.
-    pub signature: Bytes,
+    pub signature: Signature,
     /// A proposal.
     #[serde(bound = "T: Serialize + DeserializeOwned")]
     pub proposal: Proposal<T>,
@@ -180,9 +567,9 @@ pub struct SignedProposal<T: Codec> {
 #[display("Proposal height {}, round {}", height, round)]
 pub struct Proposal<T: Codec> {
     /// Height of the proposal.
-    pub height: u64,
+    pub height: Height,
     /// Round of the proposal.
-    pub round: u64,
+    pub round: Round,
     /// Proposal content.
     #[serde(bound = "T: Serialize + DeserializeOwned")]
     pub content: T,
@@ -192,13 +579,169 @@ pub struct Proposal<T: Codec> {
     pub lock: Option<PoLC>,
     /// Proposer address.
     pub proposer: Address,
+    /// The VRF output of the proposer, which is only set in the VRF proposer mode.
+    pub vrf: Option<Vrf>,
+}
+
+impl<T: Codec> Proposal<T> {
+    /// Get the compact form of the proposal, which carries the block hash instead of the content.
+    pub fn to_compact(&self) -> CompactProposal {
+        CompactProposal {
+            height: self.height,
+            round: self.round,
+            block_hash: self.block_hash.clone(),
+            proposer: self.proposer.clone(),
+            lock: self.lock.clone(),
+            vrf: self.vrf.clone(),
+            parts: None,
+        }
+    }
+}
+
+/// A signed proposal in the compact form. The receivers fetch the block of the hash by
+/// `Consensus::get_block_by_hash`.
+#[derive(
+    Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, Display, PartialEq, Eq,
+)]
+#[display("Signed Compact Proposal {}", proposal)]
+pub struct SignedCompactProposal {
+    /// Signature of the compact proposal.
+    pub signature: Signature,
+    /// A compact proposal.
+    pub proposal: CompactProposal,
+}
+
+/// A proposal without its content, which is used when the blocks are disseminated apart from the
+/// consensus messages.
+#[derive(
+    Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, Display, PartialEq, Eq,
+)]
+#[rlp(trailing)]
+#[display("Compact Proposal height {}, round {}", height, round)]
+pub struct CompactProposal {
+    /// Height of the proposal.
+    pub height: Height,
+    /// Round of the proposal.
+    pub round: Round,
+    /// Proposal block hash.
+    pub block_hash: Hash,
+    /// Proposer address.
+    pub proposer: Address,
+    /// Optional field. If the proposal has a PoLC, this contains the lock round and lock votes.
+    pub lock: Option<PoLC>,
+    /// The VRF output of the proposer, which is only set in the VRF proposer mode.
+    pub vrf: Option<Vrf>,
+    /// The header of the block parts, if the block is disseminated in parts rather than fetched
+    /// by `Consensus::get_block_by_hash`.
+    pub parts: Option<PartSetHeader>,
+}
+
+impl CompactProposal {
+    /// Restore the full proposal with the fetched content.
+    pub fn into_proposal<T: Codec>(self, content: T) -> Proposal<T> {
+        Proposal {
+            height: self.height,
+            round: self.round,
+            content,
+            block_hash: self.block_hash,
+            lock: self.lock,
+            proposer: self.proposer,
+            vrf: self.vrf,
+        }
+    }
+}
+
+/// The header of the parts that a block is split into.
+#[derive(
+    Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, Display, PartialEq, Eq,
+)]
+#[display("Part set header total {}", total)]
+pub struct PartSetHeader {
+    /// The count of the parts.
+    pub total: u32,
+    /// The merkle root of the parts.
+    #[serde(with = "super::serde_hex")]
+    pub root: Hash,
+}
+
+/// A part of the encoded block of a proposal. It is authenticated by the merkle root in the
+/// header of the signed compact proposal, so it is not signed itself.
+#[derive(
+    Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, Display, PartialEq, Eq,
+)]
+#[display("Block part height {}, round {}, index {}", height, round, index)]
+pub struct BlockPart {
+    /// Height of the proposal.
+    pub height: Height,
+    /// Round of the proposal.
+    pub round: Round,
+    /// The index of the part.
+    pub index: u32,
+    /// The bytes of the part.
+    #[serde(with = "super::serde_hex")]
+    pub data: Bytes,
+    /// The merkle proof of the part.
+    #[serde(with = "super::serde_multi_hex")]
+    pub proof: Vec<Hash>,
+}
+
+/// A verifiable random output of a proposer. The proposer proves it over the VRF input of its
+/// height, and the output seeds the proposer election of the next height.
+#[derive(Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Vrf {
+    /// The VRF output.
+    #[serde(with = "super::serde_hex")]
+    pub output: Hash,
+    /// The VRF proof of the output.
+    #[serde(with = "super::serde_hex")]
+    pub proof: Bytes,
+}
+
+impl Vrf {
+    /// Get the VRF input of the given height, which is anchored in the VRF output of the previous
+    /// committed proposal.
+    pub fn input(height: Height, seed: &Hash) -> Bytes {
+        alloy_rlp::encode(VrfInput {
+            height,
+            seed: seed.clone(),
+        })
+        .into()
+    }
+
+    /// Get the VRF seed of the height after the given one, which is the hash of the seed of the
+    /// height and the VRF output of the committed proposal by `Crypto::hash`. The committed VRF is
+    /// of the proposer of the round of the commit, see `Proof::vrf`. The output of a proposer is
+    /// unique for the input of the height, so unlike the block hash the proposer can not grind it.
+    pub fn next_seed(crypto: &impl Crypto, height: Height, seed: &Hash, output: &Hash) -> Hash {
+        crypto.hash(
+            alloy_rlp::encode(VrfSeed {
+                height,
+                seed: seed.clone(),
+                output: output.clone(),
+            })
+            .into(),
+        )
+    }
+}
+
+#[derive(RlpEncodable)]
+struct VrfInput {
+    height: Height,
+    seed: Hash,
+}
+
+#[derive(RlpEncodable)]
+struct VrfSeed {
+    height: Height,
+    seed: Hash,
+    output: Hash,
 }
 
 /// A PoLC.
 #[derive(Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, PartialEq, Eq)]
 pub struct PoLC {
     /// Lock round of the proposal.
-    pub lock_round: u64,
+    pub lock_round: Round,
     /// Lock votes of the proposal.
     pub lock_votes: AggregatedVote,
 }
@@ -208,13 +751,17 @@ pub struct PoLC {
     Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, Display, PartialEq, Eq, Hash,
 )]
 #[display("Signed vote {:?}", vote)]
+#[rlp(trailing)]
 pub struct SignedVote {
     /// Signature of the vote.
-    pub signature: Bytes,
+    pub signature: Signature,
     /// A vote.
     pub vote: Vote,
     /// Voter address.
     pub voter: Address,
+    /// The vote extension of a precommit vote.
+    #[serde(default)]
+    pub extension: Option<VoteExtension>,
 }
 
 impl PartialOrd for SignedVote {
@@ -231,12 +778,12 @@ impl Ord for SignedVote {
 
 impl SignedVote {
     /// Get the height of the signed vote.
-    pub fn get_height(&self) -> u64 {
+    pub fn get_height(&self) -> Height {
         self.vote.height
     }
 
     /// Get the round of the signed vote.
-    pub fn get_round(&self) -> u64 {
+    pub fn get_round(&self) -> Round {
         self.vote.round
     }
 
@@ -251,8 +798,21 @@ impl SignedVote {
     }
 }
 
+/// The kind of the signature of a QC.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Display, PartialEq, Eq, Hash)]
+pub enum QcKind {
+    /// An aggregated signature of the voters in the address bitmap.
+    #[default]
+    #[display("Aggregated")]
+    Aggregated,
+    /// A threshold signature verified by the group public key of the authority list, without the
+    /// voters, see `OverlordConfig::threshold_qc`.
+    #[display("Threshold")]
+    Threshold,
+}
+
 /// An aggregate signature.
-#[derive(Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
 pub struct AggregatedSignature {
     /// Aggregated signature.
     #[serde(with = "super::serde_hex")]
@@ -260,37 +820,102 @@ pub struct AggregatedSignature {
     /// Voter address bit map.
     #[serde(with = "super::serde_hex")]
     pub address_bitmap: Bytes,
+    /// The kind of the signature, which is carried on the wire for a threshold QC only, so that
+    /// the aggregated signatures keep the same encoding.
+    #[serde(default)]
+    pub kind: QcKind,
+}
+
+impl AggregatedSignature {
+    /// Create the signature of a threshold QC, see `OverlordConfig::threshold_qc`.
+    pub fn threshold(signature: Signature) -> Self {
+        AggregatedSignature {
+            signature,
+            address_bitmap: Bytes::new(),
+            kind: QcKind::Threshold,
+        }
+    }
+
+    /// Whether it is the threshold signature of a threshold QC, by its kind. An aggregated
+    /// signature of an empty bitmap is not one, it is below the threshold.
+    pub fn is_threshold(&self) -> bool {
+        self.kind == QcKind::Threshold
+    }
+
+    /// Resolve the address bitmap into the voters by the authority list of the height. The
+    /// bitmap follows the order of the sorted authority list, the given list needs not be sorted.
+    pub fn voters(&self, authority: &[Node]) -> Vec<Address> {
+        self.voter_nodes(authority)
+            .into_iter()
+            .map(|node| node.address)
+            .collect()
+    }
+
+    /// Get the sum of the vote weights of the voters in the address bitmap. Return `Err()` if the
+    /// sum overflows.
+    pub fn vote_weight(&self, authority: &[Node]) -> ConsensusResult<u64> {
+        self.voter_nodes(authority)
+            .iter()
+            .try_fold(0u64, |acc, node| acc.checked_add(node.vote_weight))
+            .ok_or_else(|| ConsensusError::Other("vote weight sum overflows".to_string()))
+    }
+
+    fn voter_nodes(&self, authority: &[Node]) -> Vec<Node> {
+        let mut sorted = authority.to_vec();
+        sorted.sort();
+        BitVec::from_bytes(&self.address_bitmap)
+            .iter()
+            .zip(sorted)
+            .filter(|(hit, _)| *hit)
+            .map(|(_, node)| node)
+            .collect()
+    }
 }
 
 /// An aggregated vote.
 #[derive(Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, Display, PartialEq, Eq, Hash)]
 #[rustfmt::skip]
 #[display("{:?} aggregated vote height {}, round {}", vote_type, height, round)]
+#[rlp(trailing)]
 pub struct AggregatedVote {
     /// Aggregated signature of the vote.
     pub signature: AggregatedSignature,
     /// Type of the vote.
     pub vote_type: VoteType,
     /// Height of the vote.
-    pub height: u64,
+    pub height: Height,
     /// Round of the vote.
-    pub round: u64,
+    pub round: Round,
     /// Proposal hash of the vote.
     #[serde(with = "super::serde_hex")]
     pub block_hash: Hash,
     /// The leader that aggregate the signed votes.
     #[serde(with = "super::serde_hex")]
     pub leader: Address,
+    /// The vote extensions of the voters.
+    #[serde(default)]
+    pub extensions: Option<Vec<VoterExtension>>,
 }
 
 impl AggregatedVote {
+    /// Resolve the address bitmap into the voters by the authority list of the height, see
+    /// `AggregatedSignature::voters`.
+    pub fn voters(&self, authority: &[Node]) -> Vec<Address> {
+        self.signature.voters(authority)
+    }
+
+    /// Get the sum of the vote weights of the voters. Return `Err()` if the sum overflows.
+    pub fn vote_weight(&self, authority: &[Node]) -> ConsensusResult<u64> {
+        self.signature.vote_weight(authority)
+    }
+
     /// Get the height of the aggregate vote.
-    pub fn get_height(&self) -> u64 {
+    pub fn get_height(&self) -> Height {
         self.height
     }
 
     /// Get the round of the aggregate vote.
-    pub fn get_round(&self) -> u64 {
+    pub fn get_round(&self) -> Round {
         self.round
     }
 
@@ -317,21 +942,100 @@ impl AggregatedVote {
 #[display("{:?} vote height {}, round {}", vote_type, height, round)]
 pub struct Vote {
     /// Height of the vote.
-    pub height: u64,
+    pub height: Height,
     /// Round of the vote.
-    pub round: u64,
+    pub round: Round,
     /// Type of the vote.
     pub vote_type: VoteType,
     /// Block hash of the vote.
     pub block_hash: Hash,
 }
 
+/// An application defined payload attached to a precommit vote. It is signed apart from the vote,
+/// so that the votes of a block still aggregate into one signature.
+#[derive(Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VoteExtension {
+    /// The application defined payload.
+    #[serde(with = "super::serde_hex")]
+    pub data: Bytes,
+    /// Signature of the extension payload.
+    #[serde(with = "super::serde_hex")]
+    pub signature: Signature,
+}
+
+impl VoteExtension {
+    /// Get the payload to sign of the extension data, which binds the data to the height, round
+    /// and block hash of the vote.
+    pub fn sign_payload(vote: &Vote, data: &Bytes) -> Bytes {
+        alloy_rlp::encode(ExtensionPayload {
+            height: vote.height,
+            round: vote.round,
+            block_hash: vote.block_hash.clone(),
+            data: data.clone(),
+        })
+        .into()
+    }
+}
+
+#[derive(RlpEncodable)]
+struct ExtensionPayload {
+    height: Height,
+    round: Round,
+    block_hash: Hash,
+    data: Bytes,
+}
+
+/// A vote extension of a voter in a quorum certificate.
+#[derive(Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VoterExtension {
+    /// Voter address.
+    #[serde(with = "super::serde_hex")]
+    pub voter: Address,
+    /// The vote extension of the voter.
+    pub extension: VoteExtension,
+}
+
+/// Every vote extension of a QC must come from a voter of the QC, and be signed by the voter.
+pub(crate) fn verify_vote_extensions(
+    crypto: &impl Crypto,
+    chain_id: &[u8],
+    vote: &Vote,
+    extensions: Option<&[VoterExtension]>,
+    voters: &[Address],
+) -> ConsensusResult<()> {
+    for item in extensions.unwrap_or_default().iter() {
+        if !voters.contains(&item.voter) {
+            return Err(ConsensusError::AggregatedSignatureErr(
+                "Vote extension from a non-voter".to_string(),
+            ));
+        }
+
+        let hash = crypto.hash(domain_payload(
+            chain_id,
+            VoteExtension::sign_payload(vote, &item.extension.data),
+        ));
+        crypto
+            .verify_signature_at(
+                vote.height,
+                item.extension.signature.clone(),
+                hash,
+                item.voter.clone(),
+            )
+            .map_err(|err| ConsensusError::InvalidSignatureErr {
+                height: vote.height,
+                round: vote.round,
+                source: err.into(),
+            })?;
+    }
+    Ok(())
+}
+
 /// A commit.
 #[derive(Serialize, Deserialize, Clone, Debug, Display, PartialEq, Eq)]
 #[display("Commit height {}", height)]
 pub struct Commit<T: Codec> {
     /// Height of the commit.
-    pub height: u64,
+    pub height: Height,
     /// Commit content.
     #[serde(bound = "T: Serialize + DeserializeOwned")]
     pub content: T,
@@ -341,15 +1045,117 @@ pub struct Commit<T: Codec> {
 
 /// A Proof.
 #[derive(Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, PartialEq, Eq)]
+#[rlp(trailing)]
 pub struct Proof {
     /// Height of the proof.
-    pub height: u64,
+    pub height: Height,
     /// Round of the proof.
-    pub round: u64,
+    pub round: Round,
     /// Block hash of the proof.
     pub block_hash: Hash,
     /// Aggregated signature of the proof.
     pub signature: AggregatedSignature,
+    /// The vote extensions of the precommit voters.
+    #[serde(default)]
+    pub extensions: Option<Vec<VoterExtension>>,
+    /// The VRF of the proposer of the round of the commit if self has received it, which is only
+    /// set in the VRF proposer mode. The seed of the next height is derived from its output, see
+    /// `Vrf::next_seed`.
+    #[serde(default)]
+    pub vrf: Option<Vrf>,
+}
+
+impl Proof {
+    /// Verify the proof against the authority list of its height by the same rules as overlord
+    /// verifies a precommit QC. The voters in the address bitmap must hold more than 2/3 of the
+    /// vote weights, the aggregated signature and the vote extensions must be signed by the
+    /// voters. The VRF is not verified, for the proof does not carry the proposer.
+    pub fn verify(&self, authority: &[Node], crypto: &impl Crypto) -> ConsensusResult<()> {
+        self.verify_with_chain_id(authority, crypto, &[])
+    }
+
+    /// Verify the proof of a chain whose nodes sign with the given chain id, see
+    /// `OverlordConfig::chain_id`.
+    pub fn verify_with_chain_id(
+        &self,
+        authority: &[Node],
+        crypto: &impl Crypto,
+        chain_id: &[u8],
+    ) -> ConsensusResult<()> {
+        let mut authority_manage = AuthorityManage::new();
+        authority_manage.update(&mut authority.to_vec())?;
+        if self.signature.is_threshold() {
+            return self.verify_threshold(&authority_manage, crypto, chain_id);
+        }
+
+        let bitmap = &self.signature.address_bitmap;
+        if !authority_manage.is_above_threshold(bitmap)? {
+            return Err(ConsensusError::AggregatedSignatureErr(
+                "Proof is not above threshold".to_string(),
+            ));
+        }
+        let voters = authority_manage.get_voters(bitmap)?;
+
+        let vote = Vote {
+            height: self.height,
+            round: self.round,
+            vote_type: VoteType::Precommit,
+            block_hash: self.block_hash.clone(),
+        };
+        verify_vote_extensions(crypto, chain_id, &vote, self.extensions.as_deref(), &voters)?;
+
+        let hash = crypto.hash(domain_payload(chain_id, alloy_rlp::encode(&vote).into()));
+        crypto
+            .verify_aggregated_signature_at(
+                self.height,
+                self.signature.signature.clone(),
+                hash,
+                voters,
+            )
+            .map_err(|err| ConsensusError::InvalidSignatureErr {
+                height: self.height,
+                round: self.round,
+                source: err.into(),
+            })
+    }
+
+    /// Verify the proof of a threshold QC by the group public key of the authority list, of which
+    /// the threshold is enforced by the scheme. It carries no voters, so the vote extensions need
+    /// only come from the authority list.
+    fn verify_threshold(
+        &self,
+        authority: &AuthorityManage,
+        crypto: &impl Crypto,
+        chain_id: &[u8],
+    ) -> ConsensusResult<()> {
+        let vote = Vote {
+            height: self.height,
+            round: self.round,
+            vote_type: VoteType::Precommit,
+            block_hash: self.block_hash.clone(),
+        };
+        verify_vote_extensions(
+            crypto,
+            chain_id,
+            &vote,
+            self.extensions.as_deref(),
+            authority.get_address_ref(),
+        )?;
+
+        let hash = crypto.hash(domain_payload(chain_id, alloy_rlp::encode(&vote).into()));
+        crypto
+            .verify_threshold_signature(
+                self.height,
+                self.signature.signature.clone(),
+                hash,
+                authority.get_address_ref().clone(),
+            )
+            .map_err(|err| ConsensusError::InvalidSignatureErr {
+                height: self.height,
+                round: self.round,
+                source: err.into(),
+            })
+    }
 }
 
 /// A rich status.
@@ -360,13 +1166,29 @@ pub struct Proof {
 #[rlp(trailing)]
 pub struct Status {
     /// New height.
-    pub height: u64,
+    pub height: Height,
     /// New authority list.
     pub authority_list: Vec<Node>,
     /// New block interval.
     pub interval: Option<u64>,
     /// New timeout configuration.
     pub timer_config: Option<DurationConfig>,
+    /// The VRF seed of the new height, which is derived from the seed and the committed VRF of the
+    /// previous height by `Vrf::next_seed`, and is filled by overlord on a commit if absent and
+    /// self has received the committed VRF. Only the VRF proposer mode uses it, where a status
+    /// above the first height without the seed is refused, so the application must fill it on a
+    /// synchronization, from the VRF of the proof of the previous height.
+    #[serde(default)]
+    pub vrf_seed: Option<Hash>,
+    /// The authority list of the next height, announced one height ahead when the validator set
+    /// changes. The messages of the next height are verified against it before self reaches the
+    /// next height.
+    #[serde(default)]
+    pub next_authority_list: Option<Vec<Node>>,
+    /// The commit proof of the previous height. A status which jumps over a height must carry it
+    /// when `OverlordConfig::verify_height_jump` is set.
+    #[serde(default)]
+    pub proof: Option<Proof>,
 }
 
 impl From<Status> for SMRStatus {
@@ -383,21 +1205,42 @@ impl Status {
     pub(crate) fn is_consensus_node(&self, address: &Address) -> bool {
         self.authority_list
             .iter()
-            .any(|node| node.address == address)
+            .any(|node| node.address == *address)
     }
 }
 
 /// A node info.
 #[derive(Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, PartialEq, Eq)]
+#[rlp(trailing)]
 pub struct Node {
     /// Node address.
     #[serde(with = "super::serde_hex")]
     pub address: Address,
-    /// The propose weight of the node. The field is only effective in `features =
-    /// "random_leader"`.
-    pub propose_weight: u32,
+    /// The propose weight of the node. The nodes propose in proportion to their weights, by a
+    /// weighted round-robin, or by a weighted random election in `features = "random_leader"`.
+    pub propose_weight: u64,
     /// The vote weight of the node.
-    pub vote_weight: u32,
+    pub vote_weight: u64,
+    /// The new public key of the node effective from a future height, so that the node rotates
+    /// its key without leaving the authority list. It is handed to `Crypto::rotate_key` when the
+    /// authority list is updated.
+    #[serde(default)]
+    pub key_rotation: Option<KeyRotation>,
+}
+
+/// A new public key of a node effective from a height. The signatures of the node are verified
+/// by the new key from the height on, and by the previous key below it.
+#[derive(Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyRotation {
+    /// The first height signed by the new key.
+    pub height: Height,
+    /// The new public key, in the encoding of the `Crypto`.
+    #[serde(with = "super::serde_hex")]
+    pub public_key: Bytes,
+    /// The proof of the new key required by the `Crypto`, such as the proof of possession of a
+    /// BLS key, or empty.
+    #[serde(with = "super::serde_hex")]
+    pub proof: Bytes,
 }
 
 impl PartialOrd for Node {
@@ -417,19 +1260,28 @@ impl Node {
     pub fn new(addr: Address) -> Self {
         Node {
             address: addr,
-            propose_weight: 1u32,
-            vote_weight: 1u32,
+            propose_weight: 1u64,
+            vote_weight: 1u64,
+            key_rotation: None,
         }
     }
 
-    /// Set a new propose weight of the node. Propose weight is only effective in `features =
-    /// "random_leader"`.
-    pub fn set_propose_weight(&mut self, propose_weight: u32) {
+    /// Rotate the public key of the node to a new one from the given height.
+    pub fn set_key_rotation(&mut self, height: Height, public_key: Bytes, proof: Bytes) {
+        self.key_rotation = Some(KeyRotation {
+            height,
+            public_key,
+            proof,
+        });
+    }
+
+    /// Set a new propose weight of the node.
+    pub fn set_propose_weight(&mut self, propose_weight: u64) {
         self.propose_weight = propose_weight;
     }
 
     /// Set a new vote weight of the node.
-    pub fn set_vote_weight(&mut self, vote_weight: u32) {
+    pub fn set_vote_weight(&mut self, vote_weight: u64) {
         self.vote_weight = vote_weight;
     }
 }
@@ -438,22 +1290,47 @@ impl Node {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub(crate) struct VerifyResp {
     /// The height of the verified block.
-    pub(crate) height: u64,
+    pub(crate) height: Height,
     /// The round of the verified block.
-    pub(crate) round: u64,
+    pub(crate) round: Round,
     /// Verified proposal hash.
     pub(crate) block_hash: Hash,
     /// The block is pass or not.
     pub(crate) is_pass: bool,
+    /// The check timed out, which is not a verdict on the block, so the block is checked again.
+    pub(crate) is_timeout: bool,
+}
+
+/// An aggregation response, which carries the quorum certificate built off the state task.
+#[derive(Clone, Debug)]
+pub(crate) struct AggregateResp {
+    /// The height of the quorum certificate.
+    pub(crate) height: Height,
+    /// The round of the quorum certificate.
+    pub(crate) round: Round,
+    /// The type of the quorum certificate.
+    pub(crate) vote_type: VoteType,
+    /// The quorum certificate, or the error of aggregating signatures.
+    pub(crate) qc: Result<AggregatedVote, ConsensusError>,
+}
+
+/// A block fetched by the hash of a signed compact proposal off the state task. The signed
+/// proposal is restored with the block, and keeps the compact form that its signature covers.
+#[derive(Clone, Debug)]
+pub(crate) struct FetchResp<T: Codec> {
+    /// The signed compact proposal of the block.
+    pub(crate) proposal: SignedCompactProposal,
+    /// The fetched block.
+    pub(crate) block: T,
 }
 
 /// An aggregated choke.
-#[derive(Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, RlpEncodable, Clone, Debug, Hash, PartialEq, Eq)]
 pub struct AggregatedChoke {
     /// The height of the aggregated choke.
-    pub height: u64,
+    pub height: Height,
     /// The round of the aggregated choke.
-    pub round: u64,
+    pub round: Round,
     /// The aggregated signature of the aggregated choke.
     #[serde(with = "super::serde_hex")]
     pub signature: Signature,
@@ -491,9 +1368,9 @@ pub struct SignedChoke {
 #[derive(Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, Hash, PartialEq, Eq)]
 pub struct Choke {
     /// The height of the choke.
-    pub height: u64,
+    pub height: Height,
     /// The round of the choke.
-    pub round: u64,
+    pub round: Round,
     /// How does state goto the current round.
     pub from: UpdateFrom,
 }
@@ -507,15 +1384,269 @@ impl Choke {
     }
 }
 
+/// A summary that self jumps to a new round by a choke QC of the previous round. It is broadcast
+/// when the round advances by more than one step, so that the late peers verify the choke QC once
+/// and jump to the new round directly, rather than going through the rounds one by one.
+#[derive(
+    Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, Display, PartialEq, Eq,
+)]
+#[display("Round summary height {}, new round {}", height, new_round)]
+pub struct RoundSummary {
+    /// The height of the round jump.
+    pub height: Height,
+    /// The round that self jumps to.
+    pub new_round: Round,
+    /// The choke QC of the round before the new round.
+    pub choke_qc: AggregatedChoke,
+}
+
+/// A request of a missing QC. The peer that has the QC of the height, round and vote type sends
+/// it back to the requester directly, and a peer in a later round of the height also sends the QC
+/// by which it went to its round. The QC verifies itself, so the request is not signed.
+#[derive(
+    Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, Display, PartialEq, Eq,
+)]
+#[display("Pull {:?} QC height {}, round {}", vote_type, height, round)]
+pub struct PullQC {
+    /// The height of the QC.
+    pub height: Height,
+    /// The round of the QC.
+    pub round: Round,
+    /// The vote type of the QC.
+    pub vote_type: VoteType,
+    /// The address of the requester.
+    #[serde(with = "super::serde_hex")]
+    pub requester: Address,
+}
+
+/// A request of a missing proposal, which is sent to the proposer of the round and the backups
+/// shortly before the propose timeout. The peer that has the signed proposal of the height and
+/// round sends it back to the requester directly. The proposal verifies itself, so the request is
+/// not signed.
+#[derive(
+    Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, Display, PartialEq, Eq,
+)]
+#[display("Pull proposal height {}, round {}", height, round)]
+pub struct PullProposal {
+    /// The height of the proposal.
+    pub height: Height,
+    /// The round of the proposal.
+    pub round: Round,
+    /// The address of the requester.
+    #[serde(with = "super::serde_hex")]
+    pub requester: Address,
+}
+
+/// A request of the votes that a leader misses when it is stuck below the threshold, since the
+/// votes might have been relayed to a wrong leader. The peers send back the signed votes of the
+/// height, round and vote type they have collected, except the ones of the voters in the bitmap.
+/// The votes verify themselves, so the request is not signed.
+#[derive(
+    Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug, Display, PartialEq, Eq,
+)]
+#[display("Pull {:?} votes height {}, round {}", vote_type, height, round)]
+pub struct PullVotes {
+    /// The height of the votes.
+    pub height: Height,
+    /// The round of the votes.
+    pub round: Round,
+    /// The vote type of the votes.
+    pub vote_type: VoteType,
+    /// The bitmap of the voters whose votes the requester has, in the order of the authority
+    /// list.
+    #[serde(with = "super::serde_hex")]
+    pub have_bitmap: Bytes,
+    /// The address of the requester.
+    #[serde(with = "super::serde_hex")]
+    pub requester: Address,
+}
+
 #[derive(Serialize, Deserialize, RlpEncodable, RlpDecodable, Clone, Debug)]
 pub(crate) struct HashChoke {
-    pub(crate) height: u64,
-    pub(crate) round: u64,
+    pub(crate) height: Height,
+    pub(crate) round: Round,
+}
+
+/// A snapshot of the live consensus status of an overlord instance.
+#[derive(Serialize, Deserialize, Clone, Debug, Display, PartialEq, Eq)]
+#[display("Consensus status height {}, round {}, step {}", height, round, step)]
+pub struct ConsensusStatus {
+    /// Current height.
+    pub height: Height,
+    /// Current round.
+    pub round: Round,
+    /// Current step.
+    pub step: Step,
+    /// Leader address of the current round.
+    #[serde(with = "super::serde_hex")]
+    pub leader: Address,
+    /// If self is the leader of the current round.
+    pub is_leader: bool,
+    /// If self is paused, see `OverlordHandler::pause`.
+    pub paused: bool,
+    /// Lock round, if self has a PoLC.
+    pub lock_round: Option<Round>,
+    /// Authority list of the current height.
+    pub authority_list: Vec<Node>,
+    /// Prevote tallies of the current round. Only the leader collects signed votes.
+    pub prevote_tally: Vec<VoteTally>,
+    /// Precommit tallies of the current round. Only the leader collects signed votes.
+    pub precommit_tally: Vec<VoteTally>,
+}
+
+/// The collected votes for a block hash.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct VoteTally {
+    /// Voted block hash.
+    #[serde(with = "super::serde_hex")]
+    pub block_hash: Hash,
+    /// Count of the votes.
+    pub vote_count: usize,
+    /// Sum of the vote weights.
+    pub vote_weight: u64,
+}
+
+/// The diagnostics of a stalled height, see `Consensus::report_stalled`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StallDiagnostics {
+    /// Current step.
+    pub step: Step,
+    /// The duration of the height so far, as millisecond.
+    pub elapsed: u64,
+    /// Leader address of the current round.
+    #[serde(with = "super::serde_hex")]
+    pub leader: Address,
+    /// Lock round, if self has a PoLC.
+    pub lock_round: Option<Round>,
+    /// The block hash of the PoLC, if self has one.
+    pub lock_hash: Option<Hash>,
+    /// The authorities of which no prevote of the current round is seen. Only the leader collects
+    /// signed votes, so it is every authority for the others until the QC arrives.
+    #[serde(with = "super::serde_multi_hex")]
+    pub missing_prevotes: Vec<Address>,
+    /// The authorities of which no precommit of the current round is seen.
+    #[serde(with = "super::serde_multi_hex")]
+    pub missing_precommits: Vec<Address>,
+    /// The chokes received at the current height.
+    pub chokes: Vec<SignedChoke>,
+}
+
+/// The participation of the authorities in a QC.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct VoteParticipation {
+    /// Height of the QC.
+    pub height: Height,
+    /// Round of the QC.
+    pub round: Round,
+    /// Type of the QC.
+    pub vote_type: VoteType,
+    /// The authorities of which the votes are aggregated in the QC.
+    #[serde(with = "super::serde_multi_hex")]
+    pub voted: Vec<Address>,
+    /// The authorities of which the votes are not in the QC.
+    #[serde(with = "super::serde_multi_hex")]
+    pub absent: Vec<Address>,
+}
+
+/// The consensus artifacts that self saw in a height, which let an auditor reconstruct the
+/// consensus process of a disputed height.
+#[derive(Serialize, Deserialize, Clone, Debug, Display, PartialEq, Eq)]
+#[display("Height artifacts height {}", height)]
+pub struct HeightArtifacts<T: Codec> {
+    /// Height of the artifacts.
+    pub height: Height,
+    /// The signed proposals of each round, ordered by round.
+    #[serde(bound = "T: Serialize + DeserializeOwned")]
+    pub proposals: Vec<SignedProposal<T>>,
+    /// The prevote and precommit QCs of each round, ordered by round.
+    pub qcs: Vec<AggregatedVote>,
+    /// The signed chokes, ordered by round.
+    pub chokes: Vec<SignedChoke>,
+    /// The aggregated chokes, ordered by round.
+    pub choke_qcs: Vec<AggregatedChoke>,
+    /// The proof of the committed block. It is `None` if the height is not committed yet.
+    pub proof: Option<Proof>,
+}
+
+/// The consensus state of a height in progress, which is exported from a node and imported into a
+/// node of the same address, such as when the validator is migrated to new hardware mid-height.
+/// It carries the lock with the locked block, so that the PoLC is not lost.
+#[derive(Serialize, Deserialize, Clone, Debug, Display, PartialEq, Eq)]
+#[display("State snapshot {}", wal_info)]
+pub struct StateSnapshot<T: Codec> {
+    /// The height, round, step and lock of the state, the same as its wal entry.
+    #[serde(bound = "T: Serialize + DeserializeOwned")]
+    pub wal_info: WalInfo<T>,
+    /// The prevote and precommit QCs of the height, ordered by round.
+    pub qcs: Vec<AggregatedVote>,
+    /// The signed chokes of the height, ordered by round.
+    pub chokes: Vec<SignedChoke>,
+    /// The aggregated chokes of the height, ordered by round.
+    pub choke_qcs: Vec<AggregatedChoke>,
+}
+
+/// Control messages from the overlord handler which are not consensus messages.
+#[derive(Debug)]
+pub(crate) enum ControlMsg<T: Codec> {
+    /// Query the live consensus status.
+    QueryStatus(oneshot::Sender<ConsensusStatus>),
+    /// Query the latency metrics of the state loop.
+    QueryMetrics(oneshot::Sender<StateMetrics>),
+    /// Export the consensus artifacts of a height.
+    ExportArtifacts(Height, oneshot::Sender<Option<HeightArtifacts<T>>>),
+    /// Export the state snapshot of the current height.
+    ExportSnapshot(oneshot::Sender<ConsensusResult<StateSnapshot<T>>>),
+    /// Continue the height of a state snapshot.
+    ImportSnapshot(Box<StateSnapshot<T>>, oneshot::Sender<ConsensusResult<()>>),
+    /// Query the commit proof of a height, or the latest one if no height is given.
+    QueryProof(
+        Option<Height>,
+        oneshot::Sender<ConsensusResult<Option<Proof>>>,
+    ),
+    /// Query the authority list that applies to a height.
+    QueryAuthority(Height, oneshot::Sender<Option<Vec<Node>>>),
+    /// Handle a precommit QC of the current height obtained by the sync.
+    InjectQC(AggregatedVote, oneshot::Sender<ConsensusResult<()>>),
+    /// Force the current round to end as on its timeout.
+    ForceViewChange(oneshot::Sender<ConsensusResult<()>>),
+    /// Query the proposer of a height and a round.
+    QueryProposer(Height, Round, oneshot::Sender<Option<Address>>),
+    /// Query the proposers of the rotation of a height.
+    QueryRotation(Height, oneshot::Sender<Option<Vec<Address>>>),
+    /// Send the commits to the subscriber from the next one.
+    SubscribeCommits(Sender<Commit<T>>),
+    /// Pause or resume proposing and voting.
+    SetPaused(bool, oneshot::Sender<()>),
+    /// Drain and stop the state, respond when the state loop exits.
+    Shutdown(oneshot::Sender<()>),
+    /// Set a debug target which expires after the duration.
+    SetDebugTarget(DebugTarget, Duration, oneshot::Sender<()>),
+    /// Clear the debug targets.
+    ClearDebugTargets(oneshot::Sender<()>),
+    /// Apply the timer configuration from the next round.
+    UpdateTimerConfig(DurationConfig, oneshot::Sender<ConsensusResult<()>>),
+}
+
+/// A target of the debug output at runtime. The targeted messages and events are logged at the
+/// info level with the `overlord::debug` log target, so that they are output without the global
+/// debug logging.
+#[derive(Serialize, Deserialize, Clone, Debug, Display, PartialEq, Eq)]
+pub enum DebugTarget {
+    /// Trace the messages and the events of the height.
+    #[display("height {}", _0)]
+    Height(Height),
+    /// Trace the messages from the address.
+    #[display("address {:?}", _0)]
+    Address(#[serde(with = "super::serde_hex")] Address),
+    /// Trace one of every given count of messages and events.
+    #[display("sample one of {}", _0)]
+    Sample(u64),
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::test_utils::EchoCrypto;
     use rand::random;
 
     fn gen_address() -> Address {
@@ -528,9 +1659,12 @@ mod test {
 
     fn mock_status() -> Status {
         Status {
-            height: random::<u64>(),
+            height: Height(random::<u64>()),
             interval: None,
             timer_config: None,
+            vrf_seed: None,
+            next_authority_list: None,
+            proof: None,
             authority_list: vec![mock_node(), mock_node()],
         }
     }
@@ -544,4 +1678,299 @@ mod test {
         assert!(status.is_consensus_node(&consensus_node));
         assert!(!status.is_consensus_node(&sync_node));
     }
+
+    #[test]
+    fn test_height_cancel() {
+        let cancel = HeightCancel::default();
+        let ctx = Context::new().with_value(HEIGHT_CANCEL_KEY, cancel.clone());
+        let carried = HeightCancel::from_context(&ctx).unwrap();
+        assert!(!carried.is_cancelled());
+
+        cancel.cancel();
+        assert!(carried.is_cancelled());
+        assert!(HeightCancel::from_context(&Context::new()).is_none());
+
+        let cancel = RoundCancel::default();
+        let ctx = ctx.with_value(ROUND_CANCEL_KEY, cancel.clone());
+        assert!(!RoundCancel::from_context(&ctx).unwrap().is_cancelled());
+        cancel.cancel();
+        assert!(RoundCancel::from_context(&ctx).unwrap().is_cancelled());
+    }
+
+    #[test]
+    fn test_split_vote_batch() {
+        let gen_vote = |height: u64| SignedVote {
+            signature: Signature::new(),
+            vote: Vote {
+                height: Height(height),
+                round: Round(0),
+                vote_type: VoteType::Prevote,
+                block_hash: Hash::new(),
+            },
+            voter: gen_address(),
+            extension: None,
+        };
+
+        let batch = vec![gen_vote(2), gen_vote(1), gen_vote(2)];
+        let msg = OverlordMsg::<Vec<u8>>::VoteBatch(batch.clone());
+        assert_eq!(msg.get_height(), Height(2));
+
+        let batches = split_vote_batch(batch);
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].len(), 1);
+        assert_eq!(batches[0][0].get_height(), Height(1));
+        assert!(batches[1].iter().all(|vote| vote.get_height() == Height(2)));
+        assert!(split_vote_batch(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_vrf_next_seed() {
+        let seed = Hash::from(vec![1u8; 32]);
+        let output = Hash::from(vec![2u8; 32]);
+        let next = Vrf::next_seed(&EchoCrypto, Height(1), &seed, &output);
+        assert_eq!(next, Vrf::next_seed(&EchoCrypto, Height(1), &seed, &output));
+        assert_ne!(
+            next,
+            Vrf::next_seed(&EchoCrypto, Height(1), &seed, &Hash::from(vec![3u8; 32]))
+        );
+        assert_ne!(next, Vrf::next_seed(&EchoCrypto, Height(2), &seed, &output));
+    }
+
+    #[test]
+    fn test_verify_threshold_proof() {
+        let authority = (0..4).map(|_| mock_node()).collect::<Vec<_>>();
+        let vote = Vote {
+            height: Height(1),
+            round: Round(0),
+            vote_type: VoteType::Precommit,
+            block_hash: Hash::from(vec![1u8]),
+        };
+        let mut proof = Proof {
+            height: Height(1),
+            round: Round(0),
+            block_hash: vote.block_hash.clone(),
+            signature: AggregatedSignature::threshold(alloy_rlp::encode(&vote).into()),
+            extensions: None,
+            vrf: None,
+        };
+        assert!(proof.signature.is_threshold());
+        assert!(proof.verify(&authority, &EchoCrypto).is_ok());
+
+        // The threshold signature is checked against the group key of the given authority list.
+        let group = GroupCrypto(authority.iter().map(|node| node.address.clone()).collect());
+        assert!(proof.verify(&authority, &group).is_ok());
+        assert!(proof.verify(&authority[..3], &group).is_err());
+
+        // An empty bitmap does not make an aggregated signature a threshold one.
+        let mut aggregated = proof.clone();
+        aggregated.signature.kind = QcKind::Aggregated;
+        assert!(!aggregated.signature.is_threshold());
+        assert!(aggregated.verify(&authority, &EchoCrypto).is_err());
+
+        // The threshold signature is of another vote.
+        proof.round = Round(1);
+        assert!(proof.verify(&authority, &EchoCrypto).is_err());
+    }
+
+    /// A crypto of which the threshold signatures are made by the group of an authority list.
+    struct GroupCrypto(Vec<Address>);
+
+    impl Crypto for GroupCrypto {
+        fn hash(&self, msg: Bytes) -> Hash {
+            EchoCrypto.hash(msg)
+        }
+
+        fn sign(&self, hash: Hash) -> Result<Signature, Box<dyn std::error::Error + Send>> {
+            EchoCrypto.sign(hash)
+        }
+
+        fn aggregate_signatures(
+            &self,
+            signatures: Vec<Signature>,
+            voters: Vec<Address>,
+        ) -> Result<Signature, Box<dyn std::error::Error + Send>> {
+            EchoCrypto.aggregate_signatures(signatures, voters)
+        }
+
+        fn verify_signature(
+            &self,
+            signature: Signature,
+            hash: Hash,
+            voter: Address,
+        ) -> Result<(), Box<dyn std::error::Error + Send>> {
+            EchoCrypto.verify_signature(signature, hash, voter)
+        }
+
+        fn verify_aggregated_signature(
+            &self,
+            aggregate_signature: Signature,
+            msg_hash: Hash,
+            voters: Vec<Address>,
+        ) -> Result<(), Box<dyn std::error::Error + Send>> {
+            EchoCrypto.verify_aggregated_signature(aggregate_signature, msg_hash, voters)
+        }
+
+        fn verify_threshold_signature(
+            &self,
+            height: Height,
+            signature: Signature,
+            msg_hash: Hash,
+            mut authority: Vec<Address>,
+        ) -> Result<(), Box<dyn std::error::Error + Send>> {
+            let mut group = self.0.clone();
+            group.sort();
+            authority.sort();
+            if group != authority {
+                return Err(Box::new(ConsensusError::CryptoErr(
+                    "not the group key".to_string(),
+                )));
+            }
+            EchoCrypto.verify_threshold_signature(height, signature, msg_hash, authority)
+        }
+    }
+
+    #[test]
+    fn test_bytes_type() {
+        let hash = Hash::with_len(Bytes::from(vec![1u8; 32]), 32).unwrap();
+        assert_eq!(hash.len(), 32);
+        assert!(Hash::with_len(Bytes::from(vec![1u8; 31]), 32).is_err());
+
+        // The newtypes are encoded as the inner bytes.
+        let bytes = Bytes::from(vec![2u8; 20]);
+        let address = Address::from(bytes.clone());
+        assert_eq!(alloy_rlp::encode(&address), alloy_rlp::encode(&bytes));
+        assert_eq!(
+            bcs::to_bytes(&address).unwrap(),
+            bcs::to_bytes(&bytes).unwrap()
+        );
+        assert_eq!(Bytes::from(address), bytes);
+    }
+
+    #[test]
+    fn test_verify_proof() {
+        let mut authority = (0..4).map(|_| mock_node()).collect::<Vec<_>>();
+        authority.sort();
+        let vote = Vote {
+            height: Height(1),
+            round: Round(0),
+            vote_type: VoteType::Precommit,
+            block_hash: Hash::from(vec![1u8]),
+        };
+        let mut proof = Proof {
+            height: Height(1),
+            round: Round(0),
+            block_hash: vote.block_hash.clone(),
+            signature: AggregatedSignature {
+                signature: alloy_rlp::encode(&vote).into(),
+                address_bitmap: Bytes::from(vec![0b1110_0000]),
+                kind: QcKind::Aggregated,
+            },
+            extensions: None,
+            vrf: None,
+        };
+        assert!(proof.verify(&authority, &EchoCrypto).is_ok());
+
+        // The extension must be signed by a voter.
+        let data = Bytes::from(vec![2u8]);
+        let mut extension = VoterExtension {
+            voter: authority[3].address.clone(),
+            extension: VoteExtension {
+                signature: VoteExtension::sign_payload(&vote, &data).into(),
+                data,
+            },
+        };
+        proof.extensions = Some(vec![extension.clone()]);
+        assert!(proof.verify(&authority, &EchoCrypto).is_err());
+        extension.voter = authority[0].address.clone();
+        proof.extensions = Some(vec![extension]);
+        assert!(proof.verify(&authority, &EchoCrypto).is_ok());
+
+        // Two of four voters are not above the threshold.
+        proof.signature.address_bitmap = Bytes::from(vec![0b1100_0000]);
+        assert!(proof.verify(&authority, &EchoCrypto).is_err());
+
+        proof.signature.address_bitmap = Bytes::from(vec![0b1111_0000]);
+        proof.round = Round(1);
+        assert!(proof.verify(&authority, &EchoCrypto).is_err());
+
+        // A proof signed with a chain id only verifies with the same chain id.
+        let chain_id = b"chain-a";
+        proof.round = Round(0);
+        proof.extensions = None;
+        proof.signature.signature =
+            domain_payload(chain_id, alloy_rlp::encode(&vote).into()).into();
+        assert!(proof.verify(&authority, &EchoCrypto).is_err());
+        assert!(proof
+            .verify_with_chain_id(&authority, &EchoCrypto, chain_id)
+            .is_ok());
+        assert!(proof
+            .verify_with_chain_id(&authority, &EchoCrypto, b"chain-b")
+            .is_err());
+    }
+
+    #[test]
+    fn test_bitmap_voters() {
+        let mut authority = (0..10).map(|_| mock_node()).collect::<Vec<_>>();
+        authority
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, node)| node.set_vote_weight(i as u64 + 1));
+        let mut sorted = authority.clone();
+        sorted.sort();
+
+        let signature = AggregatedSignature {
+            signature: Signature::new(),
+            address_bitmap: Bytes::from(vec![0b1000_0001, 0b0100_0000]),
+            kind: QcKind::Aggregated,
+        };
+        assert_eq!(
+            signature.voters(&authority),
+            vec![
+                sorted[0].address.clone(),
+                sorted[7].address.clone(),
+                sorted[9].address.clone()
+            ]
+        );
+        assert_eq!(
+            signature.vote_weight(&authority).unwrap(),
+            sorted[0].vote_weight + sorted[7].vote_weight + sorted[9].vote_weight
+        );
+
+        let mut authority = authority;
+        assert_eq!(
+            signature.voters(&authority),
+            crate::extract_voters(&mut authority, &signature.address_bitmap).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_view_change_reason() {
+        let reason = ViewChangeReason::NoProposalFromNetwork {
+            proposer: Address::from(vec![0xab, 0xcd]),
+        };
+        assert_eq!(
+            reason.to_string(),
+            "Do not receive proposal from network, proposer abcd"
+        );
+        assert_eq!(
+            serde_json::to_string(&reason).unwrap(),
+            r#"{"NoProposalFromNetwork":{"proposer":"abcd"}}"#
+        );
+
+        let reason = ViewChangeReason::LeaderReceivedVoteBelowThreshold {
+            vote_type: VoteType::Precommit,
+            missing: vec![gen_address(), gen_address()],
+        };
+        assert_eq!(
+            reason.to_string(),
+            "Precommit votes count is below threshold, 2 authorities missing"
+        );
+        let json = serde_json::to_string(&reason).unwrap();
+        match serde_json::from_str::<ViewChangeReason>(&json).unwrap() {
+            ViewChangeReason::LeaderReceivedVoteBelowThreshold { missing, .. } => {
+                assert_eq!(missing.len(), 2)
+            }
+            _ => unreachable!(),
+        }
+    }
 }
@@ -1,26 +1,48 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
 
 use bit_vec::BitVec;
 use derive_more::Display;
 use prime_tools::get_primes_less_than_x;
 
 use crate::error::ConsensusError;
-use crate::types::{Address, Node};
+use crate::types::{Address, Hash, Height, KeyRotation, Node, Round};
 use crate::utils::rand_proposer::get_random_proposer_index;
 use crate::ConsensusResult;
 
+/// The maximum count of the slots of the weighted proposer schedule. The propose weights are
+/// scaled down to fit in it.
+const MAX_SCHEDULE_LEN: u64 = 4096;
+
 /// Authority manage is an extensional data structure of authority list which means
 /// `Vec<Node>`. It transforms the information in `Node` struct into a more suitable data structure
 /// according to its usage scene. The vote weight need look up by address frequently, therefore,
 /// address with vote weight saved in a `HashMap`.
+///
+/// Besides the authority list of the current height, it keeps the previous authority lists and
+/// the one announced for the next height, so that messages signed under another validator set can
+/// still be verified around a transition.
 #[derive(Clone, Debug, Display, PartialEq, Eq)]
 #[display("Authority List {:?}", address)]
 pub struct AuthorityManage {
     address: Vec<Address>,
     propose_weights: Vec<u64>,
-    vote_weight_map: HashMap<Address, u32>,
+    vote_weight_map: HashMap<Address, u64>,
+    key_rotations: HashMap<Address, KeyRotation>,
     propose_weight_sum: u64,
     vote_weight_sum: u64,
+    /// The weighted round-robin of the proposer indices, see `weighted_schedule`.
+    schedule: Arc<[usize]>,
+    /// The stride of the schedule slot per height, which is coprime to the schedule length.
+    stride: u64,
+    /// The first height that the current authority list applies to.
+    start: Height,
+    /// The previous authority lists, the latest one is at the back.
+    previous: VecDeque<AuthorityManage>,
+    /// The authority list announced for a future height.
+    next: Option<Box<AuthorityManage>>,
+    /// The count of the previous authority lists to keep.
+    retention: usize,
 }
 
 impl AuthorityManage {
@@ -30,46 +52,165 @@ impl AuthorityManage {
             address: Vec::new(),
             propose_weights: Vec::new(),
             vote_weight_map: HashMap::new(),
+            key_rotations: HashMap::new(),
             propose_weight_sum: 0u64,
             vote_weight_sum: 0u64,
+            schedule: Arc::from(Vec::new()),
+            stride: 1,
+            start: Height(0),
+            previous: VecDeque::new(),
+            next: None,
+            retention: 0,
+        }
+    }
+
+    /// Create a new height authority manage which keeps the given count of previous authority
+    /// lists.
+    pub fn with_retention(retention: usize) -> Self {
+        AuthorityManage {
+            retention,
+            ..AuthorityManage::new()
+        }
+    }
+
+    /// Move to the authority list of the given height. If the list differs from the current one,
+    /// the current one is kept as a previous authority list. An announced authority list is
+    /// dropped once its height is reached. Return `Err()` and keep the current authority list if
+    /// the weight sums of the new one overflow.
+    pub fn transit(&mut self, height: Height, authority_list: &mut [Node]) -> ConsensusResult<()> {
+        authority_list.sort();
+        if self.next.as_ref().is_some_and(|next| next.start <= height) {
+            self.next = None;
+        }
+
+        if self.get_authority_list() == authority_list {
+            return Ok(());
+        }
+
+        let previous = self.snapshot();
+        self.update(authority_list)?;
+        if !previous.address.is_empty() && height > previous.start {
+            self.previous.push_back(previous);
+            while self.previous.len() > self.retention {
+                self.previous.pop_front();
+            }
+        }
+        self.start = height;
+        Ok(())
+    }
+
+    /// Announce the authority list of a future height ahead. Return `Err()` if its weight sums
+    /// overflow.
+    pub fn announce(&mut self, height: Height, authority_list: &mut [Node]) -> ConsensusResult<()> {
+        let mut next = AuthorityManage::new();
+        next.update(authority_list)?;
+        next.start = height;
+        self.next = Some(Box::new(next));
+        Ok(())
+    }
+
+    /// Get the authority list that applies to the given height. A future height uses the
+    /// announced authority list if any, otherwise the current one. Return `None` if the height is
+    /// older than all the kept authority lists.
+    pub fn at_height(&self, height: Height) -> Option<AuthorityManage> {
+        if let Some(next) = self.next.as_ref().filter(|next| next.start <= height) {
+            return Some(next.snapshot());
+        }
+        if height >= self.start {
+            return Some(self.snapshot());
+        }
+        self.previous
+            .iter()
+            .rev()
+            .find(|authority| authority.start <= height)
+            .map(AuthorityManage::snapshot)
+    }
+
+    /// Get the authority list announced exactly for the given height.
+    pub fn announced(&self, height: Height) -> Option<AuthorityManage> {
+        self.next
+            .as_ref()
+            .filter(|next| next.start == height)
+            .map(|next| next.snapshot())
+    }
+
+    /// Copy the current authority list without the previous and the announced ones.
+    pub fn snapshot(&self) -> AuthorityManage {
+        AuthorityManage {
+            address: self.address.clone(),
+            propose_weights: self.propose_weights.clone(),
+            vote_weight_map: self.vote_weight_map.clone(),
+            key_rotations: self.key_rotations.clone(),
+            propose_weight_sum: self.propose_weight_sum,
+            vote_weight_sum: self.vote_weight_sum,
+            schedule: Arc::clone(&self.schedule),
+            stride: self.stride,
+            start: self.start,
+            ..AuthorityManage::new()
         }
     }
 
-    /// Update the height authority manage by a new authority list.
-    pub fn update(&mut self, authority_list: &mut [Node]) {
+    /// Update the height authority manage by a new authority list. Return `Err()` and keep the
+    /// current authority list if the weight sums of the new one overflow, see
+    /// `check_authority_weights`.
+    pub fn update(&mut self, authority_list: &mut [Node]) -> ConsensusResult<()> {
+        let (propose_weight_sum, vote_weight_sum) = weight_sums(authority_list)?;
         self.flush();
         authority_list.sort();
 
         for node in authority_list.iter_mut() {
-            let propose_weight = u64::from(node.propose_weight);
+            let propose_weight = node.propose_weight;
             let vote_weight = node.vote_weight;
 
             self.address.push(node.address.clone());
             self.propose_weights.push(propose_weight);
             self.vote_weight_map
                 .insert(node.address.clone(), vote_weight);
-            self.propose_weight_sum += propose_weight;
-            self.vote_weight_sum += u64::from(vote_weight);
+            if let Some(rotation) = node.key_rotation.as_ref() {
+                self.key_rotations
+                    .insert(node.address.clone(), rotation.clone());
+            }
         }
+        self.propose_weight_sum = propose_weight_sum;
+        self.vote_weight_sum = vote_weight_sum;
+
+        let schedule = weighted_schedule(&self.propose_weights);
+        // The sieve of `get_primes_less_than_x` panics on zero, so an empty schedule falls back
+        // to the unit stride.
+        self.stride = if schedule.is_empty() {
+            1
+        } else {
+            *get_primes_less_than_x(schedule.len() as u32)
+                .last()
+                .unwrap_or(&1) as u64
+        };
+        self.schedule = Arc::from(schedule);
+        Ok(())
     }
 
     /// Get a vote weight of the node.
-    pub fn get_vote_weight(&self, addr: &Address) -> ConsensusResult<&u32> {
+    pub fn get_vote_weight(&self, addr: &Address) -> ConsensusResult<&u64> {
         self.vote_weight_map
             .get(addr)
-            .ok_or(ConsensusError::InvalidAddress)
+            .ok_or_else(|| ConsensusError::InvalidAddress(addr.clone()))
     }
 
-    /// Get the proposer address by a given seed.
-    pub fn get_proposer(&self, height: u64, round: u64) -> ConsensusResult<Address> {
+    /// Get the proposer address of the height and the round. The proposers follow the weighted
+    /// round-robin schedule of the propose weights, of which each height starts at a slot apart by
+    /// the stride and each round moves to the next slot. With the equal weights it is the rotation
+    /// of the authority list.
+    pub fn get_proposer(&self, height: Height, round: Round) -> ConsensusResult<Address> {
         let index = if cfg!(feature = "random_leader") {
             get_random_proposer_index(
-                height + round,
+                height.0 + round.0,
                 &self.propose_weights,
                 self.propose_weight_sum,
             )
+        } else if self.schedule.is_empty() {
+            0
         } else {
-            rotation_leader_index(height, round, self.address.len())
+            let slot = height.0.wrapping_mul(self.stride).wrapping_add(round.0);
+            self.schedule[(slot % self.schedule.len() as u64) as usize]
         };
 
         if let Some(addr) = self.address.get(index) {
@@ -80,15 +221,79 @@ impl AuthorityManage {
         ))
     }
 
+    /// Get the count of the rounds of a height after which the proposers repeat, which is the
+    /// length of the weighted round-robin schedule. The random elections never repeat, of which it
+    /// is the size of the authority list.
+    pub fn rotation_len(&self) -> usize {
+        if cfg!(feature = "random_leader") {
+            self.address.len()
+        } else {
+            self.schedule.len()
+        }
+    }
+
+    /// Get the proposer address by the VRF seed of the height. The seed is empty before the first
+    /// commit in the VRF proposer mode, then fall back to `get_proposer`.
+    pub fn get_vrf_proposer(
+        &self,
+        seed: &Hash,
+        height: Height,
+        round: Round,
+    ) -> ConsensusResult<Address> {
+        if seed.is_empty() {
+            return self.get_proposer(height, round);
+        }
+
+        let mut prefix = [0u8; 8];
+        let len = seed.len().min(prefix.len());
+        prefix[..len].copy_from_slice(&seed[..len]);
+        let index = get_random_proposer_index(
+            u64::from_be_bytes(prefix).wrapping_add(round.0),
+            &self.propose_weights,
+            self.propose_weight_sum,
+        );
+
+        if let Some(addr) = self.address.get(index) {
+            return Ok(addr.to_owned());
+        }
+        Err(ConsensusError::Other(
+            "The address list mismatch propose weight list".to_string(),
+        ))
+    }
+
     /// Calculate whether the sum of vote weights from bitmap is above 2/3.
     pub fn is_above_threshold(&self, bitmap: &[u8]) -> ConsensusResult<bool> {
+        Ok(self.is_above_weight(self.bitmap_weight(bitmap)?))
+    }
+
+    /// Calculate whether the vote weight is above 2/3 of the whole vote weight.
+    pub fn is_above_weight(&self, weight: u64) -> bool {
+        u128::from(weight) * 3 > u128::from(self.vote_weight_sum) * 2
+    }
+
+    /// Get the sum of the vote weights of the voters. Return `Err()` if a voter is not in the
+    /// authority list.
+    pub fn sum_vote_weight<'a>(
+        &self,
+        voters: impl IntoIterator<Item = &'a Address>,
+    ) -> ConsensusResult<u64> {
+        let mut acc = 0u64;
+        for voter in voters {
+            acc = acc
+                .checked_add(*self.get_vote_weight(voter)?)
+                .ok_or_else(weight_overflow)?;
+        }
+        Ok(acc)
+    }
+
+    fn bitmap_weight(&self, bitmap: &[u8]) -> ConsensusResult<u64> {
         let bitmap = BitVec::from_bytes(bitmap);
         let mut acc = 0u64;
 
         for node in bitmap.iter().zip(self.address.iter()) {
             if node.0 {
                 if let Some(weight) = self.vote_weight_map.get(node.1) {
-                    acc += u64::from(*weight);
+                    acc = acc.checked_add(*weight).ok_or_else(weight_overflow)?;
                 } else {
                     return Err(ConsensusError::Other(format!(
                         "Lose {:?} vote weight",
@@ -98,7 +303,7 @@ impl AuthorityManage {
             }
         }
 
-        Ok(acc * 3 > self.vote_weight_sum * 2)
+        Ok(acc)
     }
 
     pub fn get_voters(&self, bitmap: &[u8]) -> ConsensusResult<Vec<Address>> {
@@ -127,8 +332,11 @@ impl AuthorityManage {
         self.address.clear();
         self.propose_weights.clear();
         self.vote_weight_map.clear();
+        self.key_rotations.clear();
         self.propose_weight_sum = 0;
         self.vote_weight_sum = 0;
+        self.schedule = Arc::from(Vec::new());
+        self.stride = 1;
     }
 
     /// Get the length of the current authority list.
@@ -139,6 +347,76 @@ impl AuthorityManage {
     pub fn get_address_ref(&self) -> &Vec<Address> {
         &self.address
     }
+
+    /// Get the sorted authority list of the current height.
+    pub fn get_authority_list(&self) -> Vec<Node> {
+        self.address
+            .iter()
+            .zip(self.propose_weights.iter())
+            .map(|(addr, propose_weight)| Node {
+                address: addr.clone(),
+                propose_weight: *propose_weight,
+                vote_weight: self.vote_weight_map.get(addr).copied().unwrap_or_default(),
+                key_rotation: self.key_rotations.get(addr).cloned(),
+            })
+            .collect()
+    }
+}
+
+/// Check that the authority list does not exceed the maximum size.
+pub(crate) fn check_authority_size(
+    authority_list: &[Node],
+    max_size: usize,
+) -> ConsensusResult<()> {
+    if authority_list.len() > max_size {
+        return Err(ConsensusError::Other(format!(
+            "authority list size {} exceeds the maximum {}",
+            authority_list.len(),
+            max_size
+        )));
+    }
+    Ok(())
+}
+
+/// Check that the sums of the propose weights and of the vote weights of the authority list do
+/// not overflow.
+pub(crate) fn check_authority_weights(authority_list: &[Node]) -> ConsensusResult<()> {
+    weight_sums(authority_list).map(|_| ())
+}
+
+/// Get the sums of the propose weights and of the vote weights of the authority list. Return
+/// `Err()` if any of them overflows.
+fn weight_sums(authority_list: &[Node]) -> ConsensusResult<(u64, u64)> {
+    let sum = |weight: fn(&Node) -> u64| {
+        authority_list
+            .iter()
+            .try_fold(0u64, |acc, node| acc.checked_add(weight(node)))
+            .ok_or_else(weight_overflow)
+    };
+    Ok((
+        sum(|node| node.propose_weight)?,
+        sum(|node| node.vote_weight)?,
+    ))
+}
+
+fn weight_overflow() -> ConsensusError {
+    ConsensusError::Other("authority list weight sum overflows".to_string())
+}
+
+/// Check that every node of the authority list has the same vote weight, which the threshold QC
+/// mode requires, see `OverlordConfig::threshold_qc`.
+pub(crate) fn check_threshold_weights(authority_list: &[Node]) -> ConsensusResult<()> {
+    if let Some(first) = authority_list.first() {
+        if authority_list
+            .iter()
+            .any(|node| node.vote_weight != first.vote_weight)
+        {
+            return Err(ConsensusError::Other(
+                "threshold QC requires the same vote weight of every node".to_string(),
+            ));
+        }
+    }
+    Ok(())
 }
 
 /// Give the validators list and bitmap, returns the activated validators, the authority list MUST
@@ -159,41 +437,116 @@ pub fn extract_voters(
 }
 
 /// Get the leader address of the height and the round, the authority list MUST be sorted.
-pub fn get_leader(height: u64, round: u64, mut authority_list: Vec<Node>) -> Address {
-    authority_list.sort();
-    let mut weight_sum = 0;
-    let mut propose_weights = Vec::new();
-    for node in authority_list.iter() {
-        weight_sum += node.propose_weight;
-        propose_weights.push(node.propose_weight as u64);
+/// Return `Err()` if the authority list is empty or its weight sums overflow.
+pub fn get_leader(
+    height: Height,
+    round: Round,
+    authority_list: Vec<Node>,
+) -> ConsensusResult<Address> {
+    select_proposer(&authority_list, height, round, &ProposerConfig::default())
+}
+
+/// The inputs of the proposer election besides the authority list, height and round.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProposerConfig {
+    /// Whether the proposers are elected by VRF, as `OverlordConfig::vrf_proposer`.
+    pub vrf_proposer: bool,
+    /// The VRF seed of the height, which is the `vrf_seed` of its status, see `Vrf::next_seed`.
+    /// It only takes part in the VRF proposer mode.
+    pub vrf_seed: Option<Hash>,
+}
+
+/// Select the proposer of the height and the round as the overlord engine does, without a
+/// running engine. The authority list need not be sorted. Return `Err()` if the authority list is
+/// empty or its weight sums overflow.
+pub fn select_proposer(
+    authority_list: &[Node],
+    height: Height,
+    round: Round,
+    config: &ProposerConfig,
+) -> ConsensusResult<Address> {
+    if authority_list.is_empty() {
+        return Err(ConsensusError::Other("empty authority list".to_string()));
     }
+    let mut authority = AuthorityManage::new();
+    authority.update(&mut authority_list.to_vec())?;
 
-    let index = if cfg!(feature = "random_leader") {
-        get_random_proposer_index(height + round, &propose_weights, weight_sum as u64)
+    if config.vrf_proposer {
+        authority.get_vrf_proposer(&config.vrf_seed.clone().unwrap_or_default(), height, round)
     } else {
-        rotation_leader_index(height, round, authority_list.len())
-    };
+        authority.get_proposer(height, round)
+    }
+}
+
+/// Build the proposer schedule of the propose weights by the smooth weighted round-robin of
+/// Tendermint. In every slot each node accumulates its weight, then the node of the highest
+/// priority proposes and is charged the weight sum, the first one on ties. Over the schedule each
+/// node proposes as many slots as its weight, spread evenly, and the priorities are back to zero.
+/// The weights are reduced by their greatest common divisor and scaled to `MAX_SCHEDULE_LEN`
+/// slots at most. The nodes of zero weight never propose unless all the weights are zero.
+fn weighted_schedule(weights: &[u64]) -> Vec<usize> {
+    let mut weights = weights.to_vec();
+    if weights.iter().all(|weight| *weight == 0) {
+        weights.iter_mut().for_each(|weight| *weight = 1);
+    }
+    let divisor = weights.iter().fold(0, |acc, weight| gcd(acc, *weight));
+    weights
+        .iter_mut()
+        .for_each(|weight| *weight /= divisor.max(1));
 
-    authority_list[index].address.clone()
+    let sum = weights
+        .iter()
+        .map(|weight| u128::from(*weight))
+        .sum::<u128>();
+    if sum > u128::from(MAX_SCHEDULE_LEN) {
+        for weight in weights.iter_mut().filter(|weight| **weight > 0) {
+            let scaled = u128::from(*weight) * u128::from(MAX_SCHEDULE_LEN) / sum;
+            *weight = (scaled as u64).max(1);
+        }
+    }
+
+    let sum = weights
+        .iter()
+        .map(|weight| i128::from(*weight))
+        .sum::<i128>();
+    let mut priorities = vec![0i128; weights.len()];
+    (0..sum)
+        .map(|_| {
+            let mut index = 0;
+            for (i, weight) in weights.iter().enumerate() {
+                priorities[i] += i128::from(*weight);
+                if priorities[i] > priorities[index] {
+                    index = i;
+                }
+            }
+            priorities[index] -= sum;
+            index
+        })
+        .collect()
 }
 
-fn rotation_leader_index(height: u64, round: u64, authority_len: usize) -> usize {
-    let len = authority_len as u32;
-    let prime_num = *get_primes_less_than_x(len).last().unwrap_or(&1) as u64;
-    let res = (height * prime_num + round) % (len as u64);
-    res as usize
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use alloy_rlp::{Decodable, RlpEncodable};
     use bit_vec::BitVec;
     use bytes::Bytes;
     use rand::random;
 
     use crate::error::ConsensusError;
     use crate::extract_voters;
-    use crate::types::{Address, Node};
-    use crate::utils::auth_manage::AuthorityManage;
+    use crate::types::{Address, Hash, Height, Node, Round};
+    use crate::utils::auth_manage::{
+        check_authority_size, check_authority_weights, get_leader, select_proposer,
+        weighted_schedule, AuthorityManage, ProposerConfig,
+    };
 
     fn gen_address() -> Address {
         Address::from((0..32).map(|_| random::<u8>()).collect::<Vec<_>>())
@@ -206,12 +559,16 @@ mod test {
 
         let mut authority_list = Vec::new();
         for _ in 0..len {
-            authority_list.push(gen_node(gen_address(), random::<u32>(), random::<u32>()));
+            authority_list.push(gen_node(
+                gen_address(),
+                u64::from(random::<u32>()),
+                u64::from(random::<u32>()),
+            ));
         }
         authority_list
     }
 
-    fn gen_node(addr: Address, propose_weight: u32, vote_weight: u32) -> Node {
+    fn gen_node(addr: Address, propose_weight: u64, vote_weight: u64) -> Node {
         let mut node = Node::new(addr);
         node.set_propose_weight(propose_weight);
         node.set_vote_weight(vote_weight);
@@ -230,12 +587,12 @@ mod test {
     fn test_vote_weight() {
         let mut authority_list = gen_auth_list(0);
         let mut authority_manage = AuthorityManage::new();
-        authority_manage.update(&mut authority_list);
+        authority_manage.update(&mut authority_list).unwrap();
 
         for node in authority_list.iter() {
             assert_eq!(
                 authority_manage.get_vote_weight(&node.address),
-                Err(ConsensusError::InvalidAddress)
+                Err(ConsensusError::InvalidAddress(node.address.clone()))
             );
         }
 
@@ -243,7 +600,9 @@ mod test {
         while auth_len == 0 {
             auth_len = random::<u8>();
         }
-        authority_manage.update(&mut gen_auth_list(auth_len as usize));
+        authority_manage
+            .update(&mut gen_auth_list(auth_len as usize))
+            .unwrap();
 
         for node in authority_list.iter() {
             assert_eq!(
@@ -257,7 +616,7 @@ mod test {
     fn test_update() {
         let mut authority_list = gen_auth_list(random::<u8>() as usize);
         let mut auth_manage = AuthorityManage::new();
-        auth_manage.update(&mut authority_list);
+        auth_manage.update(&mut authority_list).unwrap();
         assert_eq!(
             auth_manage.address,
             authority_list
@@ -270,14 +629,14 @@ mod test {
     #[test]
     fn test_vote_threshold() {
         let mut authority_list = vec![
-            gen_node(gen_address(), 1u32, 1u32),
-            gen_node(gen_address(), 1u32, 1u32),
-            gen_node(gen_address(), 1u32, 1u32),
-            gen_node(gen_address(), 1u32, 1u32),
+            gen_node(gen_address(), 1u64, 1u64),
+            gen_node(gen_address(), 1u64, 1u64),
+            gen_node(gen_address(), 1u64, 1u64),
+            gen_node(gen_address(), 1u64, 1u64),
         ];
         authority_list.sort();
         let mut authority = AuthorityManage::new();
-        authority.update(&mut authority_list);
+        authority.update(&mut authority_list).unwrap();
 
         for i in 0..4 {
             let bit_map = gen_bitmap(4, vec![i]);
@@ -342,41 +701,76 @@ mod test {
     #[test]
     fn test_poll_leader() {
         let mut authority_list = vec![
-            gen_node(gen_address(), 1u32, 1u32),
-            gen_node(gen_address(), 1u32, 1u32),
-            gen_node(gen_address(), 1u32, 1u32),
-            gen_node(gen_address(), 1u32, 1u32),
+            gen_node(gen_address(), 1u64, 1u64),
+            gen_node(gen_address(), 1u64, 1u64),
+            gen_node(gen_address(), 1u64, 1u64),
+            gen_node(gen_address(), 1u64, 1u64),
         ];
         authority_list.sort();
         let mut authority = AuthorityManage::new();
-        authority.update(&mut authority_list);
+        authority.update(&mut authority_list).unwrap();
 
         assert_eq!(
-            authority.get_proposer(1, 0).unwrap(),
+            authority.get_proposer(Height(1), Round(0)).unwrap(),
             authority_list[3].address
         );
         assert_eq!(
-            authority.get_proposer(1, 1).unwrap(),
+            authority.get_proposer(Height(1), Round(1)).unwrap(),
             authority_list[0].address
         );
         assert_eq!(
-            authority.get_proposer(2, 0).unwrap(),
+            authority.get_proposer(Height(2), Round(0)).unwrap(),
             authority_list[2].address
         );
         assert_eq!(
-            authority.get_proposer(2, 2).unwrap(),
+            authority.get_proposer(Height(2), Round(2)).unwrap(),
             authority_list[0].address
         );
         assert_eq!(
-            authority.get_proposer(3, 0).unwrap(),
+            authority.get_proposer(Height(3), Round(0)).unwrap(),
             authority_list[1].address
         );
         assert_eq!(
-            authority.get_proposer(3, 1).unwrap(),
+            authority.get_proposer(Height(3), Round(1)).unwrap(),
             authority_list[2].address
         );
     }
 
+    #[test]
+    fn test_get_authority_list() {
+        let mut authority_list = gen_auth_list(10);
+        authority_list[3].set_key_rotation(Height(7), Bytes::from(vec![1u8; 32]), Bytes::new());
+        let mut authority = AuthorityManage::new();
+        authority.update(&mut authority_list).unwrap();
+        assert_eq!(authority.get_authority_list(), authority_list);
+
+        // A node with a key rotation is encoded with a trailing field, and a node without one
+        // keeps the encoding of the releases without key rotations.
+        for node in authority_list.iter() {
+            let encode = alloy_rlp::encode(node);
+            assert_eq!(Node::decode(&mut encode.as_slice()).unwrap(), *node);
+        }
+        #[derive(RlpEncodable)]
+        struct LegacyNode {
+            address: Address,
+            propose_weight: u64,
+            vote_weight: u64,
+        }
+        let node = authority_list
+            .iter()
+            .find(|node| node.key_rotation.is_none())
+            .cloned()
+            .unwrap();
+        assert_eq!(
+            alloy_rlp::encode(&node),
+            alloy_rlp::encode(LegacyNode {
+                address: node.address.clone(),
+                propose_weight: node.propose_weight,
+                vote_weight: node.vote_weight,
+            })
+        );
+    }
+
     #[test]
     fn test_extract_voters() {
         let mut auth_list = gen_auth_list(10);
@@ -394,4 +788,196 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn test_weighted_proposer() {
+        assert_eq!(weighted_schedule(&[3, 2, 1]), vec![0, 1, 0, 2, 1, 0]);
+        assert_eq!(weighted_schedule(&[5, 5, 5]), vec![0, 1, 2]);
+        assert_eq!(weighted_schedule(&[0, 2, 0, 1]), vec![1, 3, 1]);
+        assert_eq!(weighted_schedule(&[0, 0]), vec![0, 1]);
+        assert!(weighted_schedule(&[]).is_empty());
+
+        // The large weights are scaled down, and a light node still proposes.
+        let schedule = weighted_schedule(&[u64::from(u32::MAX), u64::from(u32::MAX / 2), 1]);
+        assert!(schedule.len() <= 4096);
+        let count = |index| schedule.iter().filter(|i| **i == index).count();
+        assert_eq!((count(0) + 1) / count(1), 2);
+        assert_eq!(count(2), 1);
+        if cfg!(feature = "random_leader") {
+            return;
+        }
+
+        let mut authority_list = vec![
+            gen_node(gen_address(), 3u64, 1u64),
+            gen_node(gen_address(), 2u64, 1u64),
+            gen_node(gen_address(), 1u64, 1u64),
+            gen_node(gen_address(), 0u64, 1u64),
+        ];
+        let mut authority = AuthorityManage::new();
+        authority.update(&mut authority_list).unwrap();
+        assert_eq!(authority.rotation_len(), 6);
+        for height in 1..10u64 {
+            let mut proposers = (0..6u64)
+                .map(|round| {
+                    authority
+                        .get_proposer(Height(height), Round(round))
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+            proposers.sort();
+            let mut expect = Vec::new();
+            for node in authority_list.iter() {
+                for _ in 0..node.propose_weight {
+                    expect.push(node.address.clone());
+                }
+            }
+            expect.sort();
+            assert_eq!(proposers, expect);
+        }
+    }
+
+    #[test]
+    fn test_vrf_proposer() {
+        let mut authority_list = vec![
+            gen_node(gen_address(), 1u64, 1u64),
+            gen_node(gen_address(), 1u64, 1u64),
+            gen_node(gen_address(), 1u64, 1u64),
+            gen_node(gen_address(), 1u64, 1u64),
+        ];
+        let mut authority = AuthorityManage::new();
+        authority.update(&mut authority_list).unwrap();
+
+        // An empty seed falls back to the default proposer.
+        for round in 0..4 {
+            assert_eq!(
+                authority.get_vrf_proposer(&Hash::new(), Height(5), Round(round)),
+                authority.get_proposer(Height(5), Round(round))
+            );
+        }
+
+        let seed = Hash::from(vec![0u8, 0, 0, 0, 0, 0, 0, 1, 0xff]);
+        let ans = [3, 2, 0, 0];
+        for round in 0..4u64 {
+            let proposer = authority
+                .get_vrf_proposer(&seed, Height(5), Round(round))
+                .unwrap();
+            assert_eq!(proposer, authority.address[ans[round as usize]]);
+            // The height does not take part once the seed is given.
+            assert_eq!(
+                proposer,
+                authority
+                    .get_vrf_proposer(&seed, Height(6), Round(round))
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_select_proposer() {
+        let authority_list = gen_auth_list(7);
+        let mut authority = AuthorityManage::new();
+        authority.update(&mut authority_list.clone()).unwrap();
+        let seed = Hash::from(vec![7u8; 32]);
+
+        for height in 1..10u64 {
+            for round in 0..4u64 {
+                let (height, round) = (Height(height), Round(round));
+                assert_eq!(
+                    select_proposer(&authority_list, height, round, &ProposerConfig::default())
+                        .unwrap(),
+                    authority.get_proposer(height, round).unwrap()
+                );
+
+                let config = ProposerConfig {
+                    vrf_proposer: true,
+                    vrf_seed: Some(seed.clone()),
+                };
+                assert_eq!(
+                    select_proposer(&authority_list, height, round, &config).unwrap(),
+                    authority.get_vrf_proposer(&seed, height, round).unwrap()
+                );
+            }
+        }
+        assert!(select_proposer(&[], Height(1), Round(0), &ProposerConfig::default()).is_err());
+        assert!(get_leader(Height(1), Round(0), Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_authority_transit() {
+        let mut list_a = gen_auth_list(4);
+        let mut list_b = gen_auth_list(4);
+        let mut list_c = gen_auth_list(4);
+        let mut authority = AuthorityManage::with_retention(1);
+        authority.transit(Height(1), &mut list_a).unwrap();
+        authority.transit(Height(2), &mut list_a.clone()).unwrap();
+        assert!(authority.previous.is_empty());
+
+        authority.announce(Height(3), &mut list_b).unwrap();
+        assert_eq!(
+            authority.announced(Height(3)).unwrap().get_authority_list(),
+            list_b
+        );
+        assert!(authority.announced(Height(4)).is_none());
+        assert_eq!(
+            authority.at_height(Height(2)).unwrap().get_authority_list(),
+            list_a
+        );
+
+        authority.transit(Height(3), &mut list_b).unwrap();
+        assert!(authority.next.is_none());
+        authority.transit(Height(5), &mut list_c).unwrap();
+        assert_eq!(authority.get_authority_list(), list_c);
+        assert_eq!(
+            authority.at_height(Height(4)).unwrap().get_authority_list(),
+            list_b
+        );
+        // Only one previous authority list is kept.
+        assert!(authority.at_height(Height(2)).is_none());
+        assert_eq!(
+            authority.at_height(Height(6)).unwrap().get_authority_list(),
+            list_c
+        );
+    }
+
+    #[test]
+    fn test_check_authority_size() {
+        let authority_list = gen_auth_list(4);
+        assert!(check_authority_size(&authority_list, 4).is_ok());
+        assert!(check_authority_size(&authority_list, 3).is_err());
+    }
+
+    #[test]
+    fn test_large_weights() {
+        // The weights of a third each sum up to the maximum.
+        let third = u64::MAX / 3;
+        let mut authority_list = vec![
+            gen_node(gen_address(), third, third),
+            gen_node(gen_address(), third, third),
+            gen_node(gen_address(), third, third),
+        ];
+        assert!(check_authority_weights(&authority_list).is_ok());
+        let mut authority = AuthorityManage::new();
+        authority.update(&mut authority_list).unwrap();
+        assert_eq!(authority.get_vote_weight_sum(), u64::MAX);
+
+        let two = Bytes::from(gen_bitmap(3, vec![0, 1]).to_bytes());
+        let all = Bytes::from(gen_bitmap(3, vec![0, 1, 2]).to_bytes());
+        assert!(!authority.is_above_threshold(&two).unwrap());
+        assert!(authority.is_above_threshold(&all).unwrap());
+        assert!(authority.is_above_weight(third * 2 + 1));
+        assert!(!authority.is_above_weight(third * 2));
+        let voters = authority.get_voters(&all).unwrap();
+        assert_eq!(authority.sum_vote_weight(voters.iter()).unwrap(), u64::MAX);
+        let twice = voters.iter().chain(voters.iter().take(1));
+        assert!(authority.sum_vote_weight(twice).is_err());
+
+        // One more weight overflows the sums, and the authority list is kept.
+        authority_list[0].set_vote_weight(third + 1);
+        assert!(check_authority_weights(&authority_list).is_err());
+        assert!(authority.update(&mut authority_list.clone()).is_err());
+        assert_eq!(authority.get_vote_weight_sum(), u64::MAX);
+        authority_list[0].set_vote_weight(third);
+        authority_list[0].set_propose_weight(u64::MAX);
+        assert!(check_authority_weights(&authority_list).is_err());
+    }
 }
@@ -0,0 +1,164 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hash;
+
+/// A map with a capacity, which evicts the least recently used item when an insertion exceeds the
+/// capacity. Inserting and `get` use an item, while `peek` does not. The pinned items are never
+/// evicted and do not count toward the capacity. It counts the evicted items for the metrics.
+#[derive(Clone, Debug)]
+pub(crate) struct LruCache<K, V> {
+    capacity: usize,
+    tick: u64,
+    items: HashMap<K, (V, u64)>,
+    order: BTreeMap<u64, K>,
+    pinned: HashSet<K>,
+    evictions: u64,
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    /// Create a cache of the given capacity, which is at least one.
+    pub(crate) fn new(capacity: usize) -> Self {
+        LruCache {
+            capacity: capacity.max(1),
+            tick: 0,
+            items: HashMap::new(),
+            order: BTreeMap::new(),
+            pinned: HashSet::new(),
+            evictions: 0,
+        }
+    }
+
+    /// Insert an item, return the evicted one if the capacity is exceeded.
+    pub(crate) fn insert(&mut self, key: K, value: V) -> Option<(K, V)> {
+        let tick = self.next_tick();
+        if let Some((_, last)) = self.items.insert(key.clone(), (value, tick)) {
+            self.order.remove(&last);
+        }
+        self.order.insert(tick, key);
+
+        let pinned = self
+            .pinned
+            .iter()
+            .filter(|key| self.items.contains_key(key))
+            .count();
+        if self.items.len() - pinned <= self.capacity {
+            return None;
+        }
+        let oldest = self
+            .order
+            .iter()
+            .find(|(_, key)| !self.pinned.contains(key))
+            .map(|(tick, _)| *tick)?;
+        let oldest = self.order.remove(&oldest)?;
+        self.evictions += 1;
+        self.items.remove(&oldest).map(|(value, _)| (oldest, value))
+    }
+
+    /// Pin the keys in place of the ones pinned before. A key is pinned whether its item is in
+    /// the cache or inserted later.
+    pub(crate) fn pin(&mut self, keys: impl IntoIterator<Item = K>) {
+        self.pinned = keys.into_iter().collect();
+    }
+
+    /// Get an item and mark it as the most recently used.
+    pub(crate) fn get(&mut self, key: &K) -> Option<&V> {
+        let tick = self.next_tick();
+        let (value, last) = self.items.get_mut(key)?;
+        self.order.remove(last);
+        self.order.insert(tick, key.clone());
+        *last = tick;
+        Some(value)
+    }
+
+    /// Get an item without marking it as used.
+    pub(crate) fn peek(&self, key: &K) -> Option<&V> {
+        self.items.get(key).map(|(value, _)| value)
+    }
+
+    pub(crate) fn contains_key(&self, key: &K) -> bool {
+        self.items.contains_key(key)
+    }
+
+    /// Remove the items of which the key matches, and return them.
+    pub(crate) fn remove_by<F: Fn(&K) -> bool>(&mut self, f: F) -> Vec<(K, V)> {
+        let keys = self
+            .items
+            .keys()
+            .filter(|key| f(key))
+            .cloned()
+            .collect::<Vec<_>>();
+        keys.into_iter()
+            .filter_map(|key| {
+                let (value, tick) = self.items.remove(&key)?;
+                self.order.remove(&tick);
+                Some((key, value))
+            })
+            .collect()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.items.iter().map(|(key, (value, _))| (key, value))
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.items.clear();
+        self.order.clear();
+        self.pinned.clear();
+    }
+
+    /// The count of the evicted items since the cache is created.
+    pub(crate) fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LruCache;
+
+    #[test]
+    fn test_lru_cache() {
+        let mut cache = LruCache::new(2);
+        assert!(cache.insert(1, "a").is_none());
+        assert!(cache.insert(2, "b").is_none());
+        assert_eq!(cache.get(&1), Some(&"a"));
+
+        // The item 2 is the least recently used one.
+        assert_eq!(cache.insert(3, "c"), Some((2, "b")));
+        assert!(!cache.contains_key(&2));
+        assert_eq!(cache.evictions(), 1);
+
+        // Peeking does not use the item.
+        assert_eq!(cache.peek(&1), Some(&"a"));
+        assert_eq!(cache.insert(4, "d"), Some((1, "a")));
+
+        assert_eq!(cache.remove_by(|key| *key == 3), vec![(3, "c")]);
+        assert_eq!(cache.iter().count(), 1);
+        cache.clear();
+        assert!(cache.peek(&4).is_none());
+        assert_eq!(cache.evictions(), 2);
+    }
+
+    #[test]
+    fn test_lru_cache_pin() {
+        let mut cache = LruCache::new(2);
+        cache.pin([1, 5]);
+        assert!(cache.insert(1, "a").is_none());
+        assert!(cache.insert(2, "b").is_none());
+        assert!(cache.insert(3, "c").is_none());
+
+        // The pinned item 1 is skipped, and so is the pinned item 5 once inserted.
+        assert_eq!(cache.insert(4, "d"), Some((2, "b")));
+        assert!(cache.insert(5, "e").is_none());
+        assert_eq!(cache.insert(6, "f"), Some((3, "c")));
+        assert_eq!(cache.iter().count(), 4);
+
+        // An unpinned item counts toward the capacity again.
+        cache.pin([5]);
+        assert_eq!(cache.insert(7, "g"), Some((1, "a")));
+    }
+}
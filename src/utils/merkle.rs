@@ -0,0 +1,197 @@
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::types::Hash;
+use crate::Crypto;
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+/// Calculate the merkle root of the given leaves. The leaves and the nodes are hashed with
+/// different prefixes, and the last node of an odd level is promoted to the next level.
+pub(crate) fn merkle_root<C: Crypto + ?Sized>(crypto: &C, leaves: &[Bytes]) -> Hash {
+    let mut level = leaves
+        .iter()
+        .map(|leaf| hash_leaf(crypto, leaf))
+        .collect::<Vec<_>>();
+    if level.is_empty() {
+        return Hash::new();
+    }
+
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => hash_node(crypto, left, right),
+                [single] => single.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    level.remove(0)
+}
+
+/// Get the merkle proof of the leaf of the given index, which is the sibling hashes from the leaf
+/// level to the root. A promoted node has no sibling in its level.
+pub(crate) fn merkle_proof<C: Crypto + ?Sized>(
+    crypto: &C,
+    leaves: &[Bytes],
+    index: usize,
+) -> Vec<Hash> {
+    let mut level = leaves
+        .iter()
+        .map(|leaf| hash_leaf(crypto, leaf))
+        .collect::<Vec<_>>();
+    let mut index = index;
+    let mut proof = Vec::new();
+
+    while level.len() > 1 {
+        let sibling = index ^ 1;
+        if sibling < level.len() {
+            proof.push(level[sibling].clone());
+        }
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => hash_node(crypto, left, right),
+                [single] => single.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+        index /= 2;
+    }
+    proof
+}
+
+/// Verify the merkle proof of a leaf of the given index, among the leaves of the given count.
+pub(crate) fn verify_merkle_proof<C: Crypto + ?Sized>(
+    crypto: &C,
+    root: &Hash,
+    leaf: &Bytes,
+    index: usize,
+    total: usize,
+    proof: &[Hash],
+) -> bool {
+    if index >= total {
+        return false;
+    }
+
+    let mut hash = hash_leaf(crypto, leaf);
+    let mut proof = proof.iter();
+    let (mut index, mut len) = (index, total);
+
+    while len > 1 {
+        let sibling = index ^ 1;
+        if sibling < len {
+            let sibling_hash = match proof.next() {
+                Some(hash) => hash,
+                None => return false,
+            };
+            hash = if index % 2 == 0 {
+                hash_node(crypto, &hash, sibling_hash)
+            } else {
+                hash_node(crypto, sibling_hash, &hash)
+            };
+        }
+        index /= 2;
+        len = len.div_ceil(2);
+    }
+    proof.next().is_none() && hash == *root
+}
+
+fn hash_leaf<C: Crypto + ?Sized>(crypto: &C, leaf: &Bytes) -> Hash {
+    let mut buf = BytesMut::with_capacity(leaf.len() + 1);
+    buf.put_u8(LEAF_PREFIX);
+    buf.put_slice(leaf);
+    crypto.hash(buf.freeze())
+}
+
+fn hash_node<C: Crypto + ?Sized>(crypto: &C, left: &Hash, right: &Hash) -> Hash {
+    let mut buf = BytesMut::with_capacity(left.len() + right.len() + 1);
+    buf.put_u8(NODE_PREFIX);
+    buf.put_slice(left);
+    buf.put_slice(right);
+    crypto.hash(buf.freeze())
+}
+
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+
+    use blake2b_simd::blake2b;
+    use bytes::Bytes;
+
+    use super::{merkle_proof, merkle_root, verify_merkle_proof};
+    use crate::types::{Address, Hash, Signature};
+    use crate::Crypto;
+
+    struct Blake2bCrypto;
+
+    impl Crypto for Blake2bCrypto {
+        fn hash(&self, msg: Bytes) -> Hash {
+            Hash::from(blake2b(&msg).as_bytes().to_vec())
+        }
+
+        fn sign(&self, hash: Hash) -> Result<Signature, Box<dyn Error + Send>> {
+            Ok(Signature::from(hash.into_bytes()))
+        }
+
+        fn aggregate_signatures(
+            &self,
+            _signatures: Vec<Signature>,
+            _voters: Vec<Address>,
+        ) -> Result<Signature, Box<dyn Error + Send>> {
+            Ok(Signature::new())
+        }
+
+        fn verify_signature(
+            &self,
+            _signature: Signature,
+            _hash: Hash,
+            _voter: Address,
+        ) -> Result<(), Box<dyn Error + Send>> {
+            Ok(())
+        }
+
+        fn verify_aggregated_signature(
+            &self,
+            _aggregate_signature: Signature,
+            _msg_hash: Hash,
+            _voters: Vec<Address>,
+        ) -> Result<(), Box<dyn Error + Send>> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_merkle_proof() {
+        let crypto = Blake2bCrypto;
+        for total in 1..=9usize {
+            let leaves = (0..total)
+                .map(|i| Bytes::from(vec![i as u8; 3]))
+                .collect::<Vec<_>>();
+            let root = merkle_root(&crypto, &leaves);
+
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = merkle_proof(&crypto, &leaves, index);
+                assert!(verify_merkle_proof(
+                    &crypto, &root, leaf, index, total, &proof
+                ));
+                // A leaf does not pass with the proof of another index.
+                let other = (index + 1) % total;
+                if other != index {
+                    assert!(!verify_merkle_proof(
+                        &crypto, &root, leaf, other, total, &proof
+                    ));
+                }
+            }
+            assert!(!verify_merkle_proof(
+                &crypto,
+                &root,
+                &Bytes::from(vec![0xff]),
+                0,
+                total,
+                &merkle_proof(&crypto, &leaves, 0)
+            ));
+        }
+    }
+}
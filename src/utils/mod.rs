@@ -1,5 +1,9 @@
 ///
 pub mod auth_manage;
+/// A least recently used cache.
+pub(crate) mod lru;
+/// Merkle root and proofs of the block parts.
+pub(crate) mod merkle;
 ///
 mod rand_proposer;
 ///
@@ -0,0 +1,242 @@
+use alloy_rlp::{Decodable, Header};
+use hummer::coding::hex_encode;
+use serde::Serialize;
+
+use crate::error::ConsensusError;
+use crate::smr::smr_types::Step;
+use crate::types::{AggregatedVote, Hash, Height, Round, SignedVote, UpdateFrom};
+use crate::wal::wal_type::{decode_wal_record, WAL_HEADER_LEN, WAL_MAGIC};
+use crate::ConsensusResult;
+
+/// The summary of a wal record, which is decoded without the type of the block, so that an
+/// operator can read the wal of any chain.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct WalReport {
+    /// The version of the record.
+    pub version: u8,
+    /// The length of the record in bytes.
+    pub len: usize,
+    /// The height of the entry.
+    pub height: Height,
+    /// The round of the entry.
+    pub round: Round,
+    /// The step of the entry.
+    pub step: Step,
+    /// The round of the lock, if locked.
+    pub lock_round: Option<Round>,
+    /// The hex encoded block hash of the lock, if locked.
+    pub lock_hash: Option<String>,
+    /// How the state went to the round.
+    pub from: String,
+    /// The hex encoded VRF seed.
+    pub vrf_seed: Option<String>,
+    /// The count of the saved votes.
+    pub votes: usize,
+    /// The count of the saved QCs.
+    pub qcs: usize,
+}
+
+/// The records read from the bytes of a wal log, of which the records are concatenated, see
+/// `repair`.
+#[derive(Clone, Debug)]
+pub struct WalRepair {
+    /// The reports of the valid records in order.
+    pub reports: Vec<WalReport>,
+    /// The length of the valid records. The bytes beyond it are the corrupted tail.
+    pub valid_len: usize,
+    /// The error of the first corrupted record, if any.
+    pub error: Option<ConsensusError>,
+}
+
+/// Decode a wal record into its report. Return `CorruptedWalErr` if the record is torn, fails the
+/// checksum or can not be decoded.
+pub fn inspect(record: &[u8]) -> ConsensusResult<WalReport> {
+    let (version, payload) = decode_wal_record(record)?;
+    let mut payload = payload.as_ref();
+    let corrupted = |e: alloy_rlp::Error| ConsensusError::CorruptedWalErr(e.to_string());
+
+    let mut info = Header::decode_bytes(&mut payload, true).map_err(corrupted)?;
+    let has_locked = bool::decode(&mut info).map_err(corrupted)?;
+    let height = Height::decode(&mut info).map_err(corrupted)?;
+    let round = Round::decode(&mut info).map_err(corrupted)?;
+    let step = Step::decode(&mut info).map_err(corrupted)?;
+    let lock = if has_locked {
+        // The locked block is skipped, which is encoded by the type of the block.
+        let mut lock = Header::decode_bytes(&mut info, true).map_err(corrupted)?;
+        let lock_round = Round::decode(&mut lock).map_err(corrupted)?;
+        let qc = AggregatedVote::decode(&mut lock).map_err(corrupted)?;
+        Some((lock_round, qc.block_hash))
+    } else {
+        None
+    };
+    let from = UpdateFrom::decode(&mut info).map_err(corrupted)?;
+    let vrf_seed = trailing::<Hash>(&mut info)
+        .map_err(corrupted)?
+        .filter(|seed| !seed.is_empty());
+    let votes = trailing::<Vec<SignedVote>>(&mut info).map_err(corrupted)?;
+    let qcs = trailing::<Vec<AggregatedVote>>(&mut info).map_err(corrupted)?;
+
+    Ok(WalReport {
+        version,
+        len: record.len(),
+        height,
+        round,
+        step,
+        lock_round: lock.as_ref().map(|(round, _)| *round),
+        lock_hash: lock.map(|(_, hash)| hex_encode(hash)),
+        from: match from {
+            UpdateFrom::PrevoteQC(qc) => format!("prevote qc of round {}", qc.round),
+            UpdateFrom::PrecommitQC(qc) => format!("precommit qc of round {}", qc.round),
+            UpdateFrom::ChokeQC(qc) => format!("choke qc of round {}", qc.round),
+        },
+        vrf_seed: vrf_seed.map(hex_encode),
+        votes: votes.map_or(0, |votes| votes.len()),
+        qcs: qcs.map_or(0, |qcs| qcs.len()),
+    })
+}
+
+/// Read the concatenated records of a wal log up to the first corrupted record, such as a record
+/// torn by a crash. Truncating the log to `WalRepair::valid_len` strips the corrupted tail.
+pub fn repair(data: &[u8]) -> WalRepair {
+    let mut reports = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let rest = &data[offset..];
+        let report = record_len(rest).and_then(|len| {
+            rest.get(..len)
+                .ok_or_else(|| {
+                    ConsensusError::CorruptedWalErr(format!(
+                        "torn record of {} bytes at offset {}",
+                        rest.len(),
+                        offset
+                    ))
+                })
+                .and_then(inspect)
+        });
+        match report {
+            Ok(report) => {
+                offset += report.len;
+                reports.push(report);
+            }
+            Err(e) => {
+                return WalRepair {
+                    reports,
+                    valid_len: offset,
+                    error: Some(e),
+                }
+            }
+        }
+    }
+
+    WalRepair {
+        reports,
+        valid_len: offset,
+        error: None,
+    }
+}
+
+/// The length of the record at the start of the bytes, by its frame header, or by its RLP header
+/// if it is a record of version 0.
+fn record_len(data: &[u8]) -> ConsensusResult<usize> {
+    if data.starts_with(&WAL_MAGIC) {
+        let len = data
+            .get(WAL_MAGIC.len() + 1..WAL_MAGIC.len() + 5)
+            .ok_or_else(|| ConsensusError::CorruptedWalErr("torn record header".to_string()))?;
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(len);
+        return Ok(WAL_HEADER_LEN + u32::from_be_bytes(buf) as usize);
+    }
+
+    let mut buf = data;
+    let header =
+        Header::decode(&mut buf).map_err(|e| ConsensusError::CorruptedWalErr(e.to_string()))?;
+    Ok(data.len() - buf.len() + header.payload_length)
+}
+
+fn trailing<D: Decodable>(payload: &mut &[u8]) -> alloy_rlp::Result<Option<D>> {
+    if payload.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(D::decode(payload)?))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+    use serde::{Deserialize, Serialize};
+
+    use super::{inspect, repair};
+    use crate::smr::smr_types::Step;
+    use crate::types::{
+        Address, AggregatedSignature, AggregatedVote, Hash, Height, QcKind, Round, Signature,
+        UpdateFrom, VoteType,
+    };
+    use crate::wal::{encode_wal_record, WalInfo, WalLock};
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+    struct Pill(Vec<u8>);
+
+    fn qc(round: u64) -> AggregatedVote {
+        AggregatedVote {
+            signature: AggregatedSignature {
+                signature: Signature::default(),
+                address_bitmap: Bytes::default(),
+                kind: QcKind::Aggregated,
+            },
+            vote_type: VoteType::Prevote,
+            height: Height(7),
+            round: Round(round),
+            block_hash: Hash::from(vec![0xab; 4]),
+            leader: Address::default(),
+            extensions: None,
+        }
+    }
+
+    fn record(round: u64) -> Bytes {
+        let info = WalInfo {
+            height: Height(7),
+            round: Round(round),
+            step: Step::Precommit,
+            lock: Some(WalLock {
+                lock_round: Round(round),
+                lock_votes: qc(round),
+                content: Arc::new(Pill(vec![1, 2, 3])),
+            }),
+            from: UpdateFrom::PrevoteQC(qc(round)),
+            vrf_seed: None,
+            votes: Vec::new(),
+            qcs: vec![qc(round)],
+        };
+        encode_wal_record(&alloy_rlp::encode(&info))
+    }
+
+    #[test]
+    fn test_inspect_and_repair() {
+        let first = record(1);
+        let report = inspect(&first).unwrap();
+        assert_eq!(report.len, first.len());
+        assert_eq!((report.height, report.round), (Height(7), Round(1)));
+        assert_eq!(report.step, Step::Precommit);
+        assert_eq!(report.lock_round, Some(Round(1)));
+        assert_eq!(report.lock_hash.as_deref(), Some("abababab"));
+        assert_eq!(report.from, "prevote qc of round 1");
+        assert_eq!((report.votes, report.qcs), (0, 1));
+
+        // A torn trailing record is stripped, and the records before it are kept.
+        let second = record(2);
+        let mut log = [first.as_ref(), second.as_ref()].concat();
+        let repaired = repair(&log);
+        assert_eq!(repaired.reports.len(), 2);
+        assert_eq!(repaired.valid_len, log.len());
+        assert!(repaired.error.is_none());
+
+        log.truncate(log.len() - 3);
+        let repaired = repair(&log);
+        assert_eq!(repaired.reports, vec![report]);
+        assert_eq!(repaired.valid_len, first.len());
+        assert!(repaired.error.is_some());
+        assert!(inspect(&log[first.len()..]).is_err());
+    }
+}
@@ -1,3 +1,11 @@
+/// The inspection and the repair of the wal records for the operators.
+mod inspect;
 mod wal_type;
+/// The writer of the wal entries under a durability policy.
+mod writer;
 
-pub use self::wal_type::{SMRBase, WalInfo, WalLock};
+pub use self::inspect::{inspect, repair, WalRepair, WalReport};
+pub(crate) use self::wal_type::SMRBase;
+pub(crate) use self::wal_type::{decode_wal_record, encode_wal_record, WAL_VERSION_0};
+pub use self::wal_type::{WalInfo, WalLock};
+pub(crate) use self::writer::WalWriter;
@@ -1,9 +1,118 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use bytes::{BufMut, Bytes, BytesMut};
 use derive_more::Display;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
+use crate::codec::{compress_frame, decompress_frame};
+use crate::error::ConsensusError;
 use crate::smr::smr_types::{Lock, Step};
-use crate::types::{AggregatedVote, UpdateFrom};
-use crate::Codec;
+use crate::types::{AggregatedVote, Hash, Height, Round, SignedVote, UpdateFrom};
+use crate::{Codec, ConsensusResult};
+
+/// The magic bytes of a framed wal record. An encoded `WalInfo` is an RLP list which starts with a
+/// byte of at least `0xc0`, so a record of version 0 never starts with the magic bytes.
+pub(crate) const WAL_MAGIC: [u8; 4] = *b"OWAL";
+/// The version of the bare encoded `WalInfo`, which is saved before the records are framed.
+pub(crate) const WAL_VERSION_0: u8 = 0;
+/// The version of the framed records before they carry the votes and the QCs.
+pub(crate) const WAL_VERSION_1: u8 = 1;
+/// The current version of the wal record, of which the `WalInfo` may carry the votes of the round
+/// and the QCs of the height.
+pub(crate) const WAL_VERSION: u8 = 2;
+/// The magic bytes, the version, the payload length and the CRC-32 of the payload.
+pub(crate) const WAL_HEADER_LEN: usize = WAL_MAGIC.len() + 1 + 4 + 4;
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+/// Frame an encoded `WalInfo` into a wal record of the current version. The payload is compressed
+/// under the `compression` feature if it is large, see `compress_frame`.
+pub(crate) fn encode_wal_record(payload: &[u8]) -> Bytes {
+    let payload = compress_frame(payload.to_vec());
+    let payload = payload.as_slice();
+    let mut buf = BytesMut::with_capacity(WAL_HEADER_LEN + payload.len());
+    buf.put_slice(&WAL_MAGIC);
+    buf.put_u8(WAL_VERSION);
+    buf.put_u32(payload.len() as u32);
+    buf.put_u32(crc32(payload));
+    buf.put_slice(payload);
+    buf.freeze()
+}
+
+/// Unframe a wal record into its version and the encoded `WalInfo`. A record without the magic
+/// bytes is read as a record of version 0. Return `Err()` if the record is torn, fails the
+/// checksum, or is of an unknown version.
+pub(crate) fn decode_wal_record(record: &[u8]) -> ConsensusResult<(u8, Cow<'_, [u8]>)> {
+    if !record.starts_with(&WAL_MAGIC) {
+        return Ok((WAL_VERSION_0, Cow::Borrowed(record)));
+    }
+    if record.len() < WAL_HEADER_LEN {
+        return Err(ConsensusError::CorruptedWalErr(format!(
+            "torn record header of {} bytes",
+            record.len()
+        )));
+    }
+
+    let version = record[WAL_MAGIC.len()];
+    if !(WAL_VERSION_1..=WAL_VERSION).contains(&version) {
+        return Err(ConsensusError::CorruptedWalErr(format!(
+            "unknown record version {}",
+            version
+        )));
+    }
+
+    let read_u32 = |offset: usize| {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(&record[offset..offset + 4]);
+        u32::from_be_bytes(buf)
+    };
+    let len = read_u32(WAL_MAGIC.len() + 1) as usize;
+    let checksum = read_u32(WAL_MAGIC.len() + 5);
+    let payload = &record[WAL_HEADER_LEN..];
+    if payload.len() != len {
+        return Err(ConsensusError::CorruptedWalErr(format!(
+            "record length {} mismatches the payload of {} bytes",
+            len,
+            payload.len()
+        )));
+    }
+    if crc32(payload) != checksum {
+        return Err(ConsensusError::CorruptedWalErr(
+            "record checksum mismatch".to_string(),
+        ));
+    }
+    let payload = decompress_frame(payload, None)
+        .map_err(|e| ConsensusError::CorruptedWalErr(e.to_string()))?;
+    Ok((version, payload))
+}
+
+/// The CRC-32 (IEEE) of the given bytes.
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, byte| {
+        CRC32_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, Display, Eq, PartialEq)]
 #[rustfmt::skip]
@@ -11,9 +120,9 @@ use crate::Codec;
 /// Structure of Wal Info
 pub struct WalInfo<T: Codec> {
     /// height
-    pub height: u64,
+    pub height: Height,
     /// round
-    pub round:  u64,
+    pub round:  Round,
     /// step
     pub step:   Step,
     /// lock
@@ -21,11 +130,21 @@ pub struct WalInfo<T: Codec> {
     pub lock:   Option<WalLock<T>>,
     /// from
     pub from:   UpdateFrom,
+    /// vrf seed
+    #[serde(default)]
+    pub vrf_seed: Option<Hash>,
+    /// The signed votes of the round collected when the entry is saved, see
+    /// `OverlordConfig::wal_votes`.
+    #[serde(default)]
+    pub votes:  Vec<SignedVote>,
+    /// The QCs of the height known when the entry is saved.
+    #[serde(default)]
+    pub qcs:    Vec<AggregatedVote>,
 }
 
 impl<T: Codec> WalInfo<T> {
     /// transfer WalInfo to SMRBase
-    pub fn into_smr_base(self) -> SMRBase {
+    pub(crate) fn into_smr_base(self) -> SMRBase {
         SMRBase {
             height: self.height,
             round: self.round,
@@ -37,15 +156,19 @@ impl<T: Codec> WalInfo<T> {
 
 #[derive(Serialize, Deserialize, Clone, Debug, Display, PartialEq, Eq)]
 #[display("wal lock round {}, qc {:?}", lock_round, lock_votes)]
+/// The lock of the state with the locked block.
 pub struct WalLock<T: Codec> {
-    pub lock_round: u64,
+    /// The round of the lock.
+    pub lock_round: Round,
+    /// The prevote QC of the lock.
     pub lock_votes: AggregatedVote,
+    /// The locked block, which is shared with the block cache of the state.
     #[serde(bound = "T: Serialize + DeserializeOwned")]
-    pub content: T,
+    pub content: Arc<T>,
 }
 
 impl<T: Codec> WalLock<T> {
-    pub fn to_lock(&self) -> Lock {
+    pub(crate) fn to_lock(&self) -> Lock {
         Lock {
             round: self.lock_round,
             hash: self.lock_votes.block_hash.clone(),
@@ -53,10 +176,10 @@ impl<T: Codec> WalLock<T> {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct SMRBase {
-    pub height: u64,
-    pub round: u64,
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SMRBase {
+    pub height: Height,
+    pub round: Round,
     pub step: Step,
     pub polc: Option<Lock>,
 }
@@ -67,7 +190,9 @@ mod test {
     use rand::random;
 
     use super::*;
-    use crate::types::{AggregatedSignature, VoteType};
+    use crate::types::{
+        Address, AggregatedSignature, Hash, Height, QcKind, Round, Signature, VoteType,
+    };
 
     #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
     struct Pill {
@@ -84,35 +209,96 @@ mod test {
 
     fn mock_qc() -> AggregatedVote {
         let aggregated_signature = AggregatedSignature {
-            signature: Bytes::default(),
+            signature: Signature::default(),
             address_bitmap: Bytes::default(),
+            kind: QcKind::Aggregated,
         };
 
         AggregatedVote {
             signature: aggregated_signature,
             vote_type: VoteType::Precommit,
-            height: 0u64,
-            round: 0u64,
-            block_hash: Bytes::default(),
-            leader: Bytes::default(),
+            height: Height(0),
+            round: Round(0),
+            block_hash: Hash::default(),
+            leader: Address::default(),
+            extensions: None,
+        }
+    }
+
+    #[test]
+    fn test_wal_record() {
+        // The check value of CRC-32 (IEEE).
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+
+        let info = WalInfo::<Pill> {
+            height: Height(3),
+            round: Round(1),
+            step: Step::Prevote,
+            lock: None,
+            from: UpdateFrom::PrecommitQC(mock_qc()),
+            vrf_seed: None,
+            votes: Vec::new(),
+            qcs: Vec::new(),
+        };
+        let payload = alloy_rlp::encode(&info);
+        let record = encode_wal_record(&payload);
+        assert_eq!(
+            decode_wal_record(&record).unwrap(),
+            (WAL_VERSION, Cow::Borrowed(payload.as_slice()))
+        );
+
+        // A bare encoded wal info is read as version 0.
+        assert_eq!(
+            decode_wal_record(&payload).unwrap(),
+            (WAL_VERSION_0, Cow::Borrowed(payload.as_slice()))
+        );
+
+        let mut flipped = record.to_vec();
+        *flipped.last_mut().unwrap() ^= 1;
+        assert!(decode_wal_record(&flipped).is_err());
+        assert!(decode_wal_record(&record[..record.len() - 1]).is_err());
+        assert!(decode_wal_record(&record[..WAL_HEADER_LEN - 1]).is_err());
+
+        // A record of version 1 is still read.
+        let mut legacy = record.to_vec();
+        legacy[WAL_MAGIC.len()] = WAL_VERSION_1;
+        assert_eq!(
+            decode_wal_record(&legacy).unwrap(),
+            (WAL_VERSION_1, Cow::Borrowed(payload.as_slice()))
+        );
+
+        let mut unknown = record.to_vec();
+        unknown[WAL_MAGIC.len()] = WAL_VERSION + 1;
+        assert!(decode_wal_record(&unknown).is_err());
+
+        // A large record is compressed, and is decoded to the plain payload.
+        #[cfg(feature = "compression")]
+        {
+            let payload = alloy_rlp::encode(vec![vec![7u8; 4096]]);
+            let record = encode_wal_record(&payload);
+            assert!(record.len() < 1024);
+            assert_eq!(decode_wal_record(&record).unwrap().1.as_ref(), payload);
         }
     }
 
     #[test]
     fn test_display() {
         let wal_lock = WalLock {
-            lock_round: 0,
+            lock_round: Round(0),
             lock_votes: mock_qc(),
-            content: Pill::new(),
+            content: Arc::new(Pill::new()),
         };
         println!("{}", wal_lock);
 
         let wal_info = WalInfo {
-            height: 0,
-            round: 0,
+            height: Height(0),
+            round: Round(0),
             step: Step::Propose,
             lock: Some(wal_lock),
             from: UpdateFrom::PrecommitQC(mock_qc()),
+            vrf_seed: None,
+            votes: Vec::new(),
+            qcs: Vec::new(),
         };
 
         assert_eq!(
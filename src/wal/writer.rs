@@ -0,0 +1,232 @@
+use std::error::Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+
+use crate::smr::smr_types::Step;
+use crate::types::{Height, Round};
+use crate::{Wal, WalPolicy};
+
+type WalResult = Result<(), Box<dyn Error + Send>>;
+
+/// A writer of the wal entries under a `WalPolicy`. The entries of a height are appended to the
+/// log, and the first entry of a new height compacts the log. The safety critical entries are
+/// always saved and synced before the writer returns. The other entries are saved without
+/// syncing, and are coalesced per step under `WalPolicy::Batched`.
+#[derive(Debug)]
+pub(crate) struct WalWriter<W: Wal> {
+    wal: Arc<W>,
+    policy: WalPolicy,
+    log_height: Option<Height>,
+    last_sync: Instant,
+    last_saved: Option<(Height, Round, Step)>,
+    pending: Option<(Height, Bytes)>,
+    coalesced: u64,
+}
+
+impl<W: Wal + Sync> WalWriter<W> {
+    pub(crate) fn new(wal: Arc<W>, policy: WalPolicy) -> Self {
+        WalWriter {
+            wal,
+            policy,
+            log_height: None,
+            last_sync: Instant::now(),
+            last_saved: None,
+            pending: None,
+            coalesced: 0,
+        }
+    }
+
+    pub(crate) fn wal(&self) -> &Arc<W> {
+        &self.wal
+    }
+
+    /// Continue the log of the given height, which is loaded from the wal on restart, so that the
+    /// entries before the restart are kept.
+    pub(crate) fn resume(&mut self, height: Height) {
+        self.log_height = Some(height);
+    }
+
+    /// Save a wal entry of the given height, round and step.
+    pub(crate) async fn save(
+        &mut self,
+        height: Height,
+        round: Round,
+        step: Step,
+        info: Bytes,
+        critical: bool,
+    ) -> WalResult {
+        let key = (height, round, step);
+        match self.policy {
+            WalPolicy::Always => return self.write(height, info).await,
+            WalPolicy::Batched if !critical && self.last_saved.as_ref() == Some(&key) => {
+                // The latest entry supersedes the pending one.
+                self.pending = Some((height, info));
+                self.coalesced += 1;
+                return Ok(());
+            }
+            _ => (),
+        }
+
+        self.pending = None;
+        self.write(height, info).await?;
+        self.last_saved = Some(key);
+        if critical || self.sync_due() {
+            self.wal.sync().await?;
+            self.last_sync = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Save and sync the pending entry if any.
+    pub(crate) async fn flush(&mut self) -> WalResult {
+        if let Some((height, info)) = self.pending.take() {
+            self.write(height, info).await?;
+        }
+        if self.policy != WalPolicy::Always {
+            self.wal.sync().await?;
+            self.last_sync = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// The count of the entries superseded before they are saved.
+    pub(crate) fn coalesced(&self) -> u64 {
+        self.coalesced
+    }
+
+    async fn write(&mut self, height: Height, info: Bytes) -> WalResult {
+        if self.log_height == Some(height) {
+            return self.wal.append(info).await;
+        }
+        self.wal.compact(info).await?;
+        self.log_height = Some(height);
+        Ok(())
+    }
+
+    fn sync_due(&self) -> bool {
+        match self.policy {
+            WalPolicy::Interval(interval) => {
+                self.last_sync.elapsed() >= Duration::from_millis(interval)
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use bytes::Bytes;
+    use parking_lot::Mutex;
+
+    use super::WalWriter;
+    use crate::smr::smr_types::Step;
+    use crate::types::{Height, Round};
+    use crate::{Wal, WalPolicy};
+
+    #[derive(Default)]
+    struct CountingWal {
+        saved: Mutex<Vec<Bytes>>,
+        syncs: Mutex<u64>,
+    }
+
+    #[async_trait]
+    impl Wal for CountingWal {
+        async fn save(&self, info: Bytes) -> Result<(), Box<dyn Error + Send>> {
+            self.saved.lock().push(info);
+            Ok(())
+        }
+
+        async fn load(&self) -> Result<Option<Bytes>, Box<dyn Error + Send>> {
+            Ok(self.saved.lock().last().cloned())
+        }
+
+        async fn load_all(&self) -> Result<Vec<Bytes>, Box<dyn Error + Send>> {
+            Ok(self.saved.lock().clone())
+        }
+
+        async fn compact(&self, info: Bytes) -> Result<(), Box<dyn Error + Send>> {
+            *self.saved.lock() = vec![info];
+            Ok(())
+        }
+
+        async fn sync(&self) -> Result<(), Box<dyn Error + Send>> {
+            *self.syncs.lock() += 1;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wal_writer() {
+        let entry = |i: u8| Bytes::from(vec![i]);
+
+        let wal = Arc::new(CountingWal::default());
+        let mut writer = WalWriter::new(Arc::clone(&wal), WalPolicy::Always);
+        for i in 0..3 {
+            writer
+                .save(Height(1), Round(0), Step::Propose, entry(i), false)
+                .await
+                .unwrap();
+        }
+        assert_eq!(wal.saved.lock().len(), 3);
+        assert_eq!(*wal.syncs.lock(), 0);
+
+        let wal = Arc::new(CountingWal::default());
+        let mut writer = WalWriter::new(Arc::clone(&wal), WalPolicy::Batched);
+        for i in 0..3 {
+            writer
+                .save(Height(1), Round(0), Step::Propose, entry(i), false)
+                .await
+                .unwrap();
+        }
+        // The entries of the same step are coalesced into the latest one.
+        assert_eq!(wal.saved.lock().len(), 1);
+        assert_eq!(writer.coalesced(), 2);
+        writer.flush().await.unwrap();
+        assert_eq!(wal.load().await.unwrap(), Some(entry(2)));
+
+        // A critical entry is saved and synced at once.
+        writer
+            .save(Height(1), Round(0), Step::Prevote, entry(3), true)
+            .await
+            .unwrap();
+        writer
+            .save(Height(1), Round(0), Step::Prevote, entry(4), true)
+            .await
+            .unwrap();
+        assert_eq!(wal.load().await.unwrap(), Some(entry(4)));
+        assert_eq!(*wal.syncs.lock(), 3);
+
+        let wal = Arc::new(CountingWal::default());
+        let mut writer = WalWriter::new(Arc::clone(&wal), WalPolicy::Interval(60_000));
+        for i in 0..3 {
+            writer
+                .save(Height(1), Round(i as u64), Step::Propose, entry(i), false)
+                .await
+                .unwrap();
+        }
+        assert_eq!(wal.saved.lock().len(), 3);
+        assert_eq!(*wal.syncs.lock(), 0);
+
+        // The first entry of a new height compacts the log.
+        writer
+            .save(Height(2), Round(0), Step::Propose, entry(3), false)
+            .await
+            .unwrap();
+        assert_eq!(wal.load_all().await.unwrap(), vec![entry(3)]);
+
+        // A resumed log keeps the entries of its height.
+        let mut writer = WalWriter::new(Arc::clone(&wal), WalPolicy::Always);
+        writer.resume(Height(2));
+        writer
+            .save(Height(2), Round(0), Step::Prevote, entry(4), true)
+            .await
+            .unwrap();
+        assert_eq!(wal.load_all().await.unwrap(), vec![entry(3), entry(4)]);
+    }
+}
@@ -1,49 +1,50 @@
 use super::utils::hash;
 use bytes::Bytes;
+use overlord::types::{Address, Hash, Signature};
 use overlord::Crypto;
 use std::error::Error;
 
 pub struct MockCrypto {
-    name: Bytes,
+    name: Address,
 }
 
 impl MockCrypto {
-    pub fn new(name: Bytes) -> Self {
+    pub fn new(name: Address) -> Self {
         MockCrypto { name }
     }
 }
 
 impl Crypto for MockCrypto {
-    fn hash(&self, speech: Bytes) -> Bytes {
+    fn hash(&self, speech: Bytes) -> Hash {
         hash(&speech)
     }
 
-    fn sign(&self, _hash: Bytes) -> Result<Bytes, Box<dyn Error + Send>> {
-        Ok(self.name.clone())
+    fn sign(&self, _hash: Hash) -> Result<Signature, Box<dyn Error + Send>> {
+        Ok(Signature::from(self.name.clone().into_bytes()))
     }
 
     fn aggregate_signatures(
         &self,
-        _signatures: Vec<Bytes>,
-        _speaker: Vec<Bytes>,
-    ) -> Result<Bytes, Box<dyn Error + Send>> {
-        Ok(Bytes::new())
+        _signatures: Vec<Signature>,
+        _speaker: Vec<Address>,
+    ) -> Result<Signature, Box<dyn Error + Send>> {
+        Ok(Signature::new())
     }
 
     fn verify_signature(
         &self,
-        _signature: Bytes,
-        _hash: Bytes,
-        _voter: Bytes,
+        _signature: Signature,
+        _hash: Hash,
+        _voter: Address,
     ) -> Result<(), Box<dyn Error + Send>> {
         Ok(())
     }
 
     fn verify_aggregated_signature(
         &self,
-        _aggregated_signature: Bytes,
-        _hash: Bytes,
-        _voters: Vec<Bytes>,
+        _aggregated_signature: Signature,
+        _hash: Hash,
+        _voters: Vec<Address>,
     ) -> Result<(), Box<dyn Error + Send>> {
         Ok(())
     }
@@ -10,7 +10,9 @@ use crossbeam_channel::{Receiver, Sender};
 use serde::{Deserialize, Serialize};
 
 use overlord::error::ConsensusError;
-use overlord::types::{Commit, Hash, Node, OverlordMsg, Status, ViewChangeReason};
+use overlord::types::{
+    Address, Commit, Hash, Height, Node, OverlordMsg, Round, Status, ViewChangeReason,
+};
 use overlord::{Consensus, DurationConfig, Overlord, OverlordHandler};
 
 use super::crypto::MockCrypto;
@@ -33,16 +35,16 @@ impl Block {
 }
 
 pub struct Adapter {
-    pub address: Bytes, // address
-    pub talk_to: HashMap<Bytes, Sender<OverlordMsg<Block>>>,
+    pub address: Address,
+    pub talk_to: HashMap<Address, Sender<OverlordMsg<Block>>>,
     pub hearing: Receiver<OverlordMsg<Block>>,
     pub records: RecordInternal,
 }
 
 impl Adapter {
     fn new(
-        address: Bytes,
-        talk_to: HashMap<Bytes, Sender<OverlordMsg<Block>>>,
+        address: Address,
+        talk_to: HashMap<Address, Sender<OverlordMsg<Block>>>,
         hearing: Receiver<OverlordMsg<Block>>,
         records: RecordInternal,
     ) -> Adapter {
@@ -60,7 +62,7 @@ impl Consensus<Block> for Adapter {
     async fn get_block(
         &self,
         _ctx: Context,
-        _height: u64,
+        _height: Height,
     ) -> Result<(Block, Hash), Box<dyn Error + Send>> {
         let content = gen_random_bytes();
         Ok((Block::from(content.clone()), hash(&content)))
@@ -69,7 +71,7 @@ impl Consensus<Block> for Adapter {
     async fn check_block(
         &self,
         _ctx: Context,
-        _height: u64,
+        _height: Height,
         _hash: Hash,
         _block: Block,
     ) -> Result<(), Box<dyn Error + Send>> {
@@ -79,13 +81,16 @@ impl Consensus<Block> for Adapter {
     async fn commit(
         &self,
         _ctx: Context,
-        height: u64,
+        height: Height,
         commit: Commit<Block>,
     ) -> Result<Status, Box<dyn Error + Send>> {
         let status = Status {
             height: height + 1,
             interval: Some(self.records.interval),
             timer_config: None,
+            vrf_seed: None,
+            next_authority_list: None,
+            proof: None,
             authority_list: self.records.node_record.clone(),
         };
 
@@ -103,7 +108,7 @@ impl Consensus<Block> for Adapter {
         let mut consistency_break = false;
         {
             let mut commit_record = self.records.commit_record.lock().unwrap();
-            if let Some(block_hash) = commit_record.get_mut(&commit.height) {
+            if let Some(block_hash) = commit_record.get_mut(&commit.height.0) {
                 // Consistency check
                 if block_hash != &commit_block_hash {
                     consistency_break = true;
@@ -127,9 +132,9 @@ impl Consensus<Block> for Adapter {
                 commit.height,
             );
             let mut commit_record = self.records.commit_record.lock().unwrap();
-            commit_record.insert(commit.height, commit_block_hash);
+            commit_record.insert(commit.height.0, commit_block_hash);
             let mut height_record = self.records.height_record.lock().unwrap();
-            height_record.insert(self.address.clone(), commit.height);
+            height_record.insert(self.address.clone(), commit.height.0);
         }
 
         Ok(status)
@@ -138,7 +143,7 @@ impl Consensus<Block> for Adapter {
     async fn get_authority_list(
         &self,
         _ctx: Context,
-        _height: u64,
+        _height: Height,
     ) -> Result<Vec<Node>, Box<dyn Error + Send>> {
         Ok(self.records.node_record.clone())
     }
@@ -157,7 +162,7 @@ impl Consensus<Block> for Adapter {
     async fn transmit_to_relayer(
         &self,
         _ctx: Context,
-        address: Bytes,
+        address: Address,
         words: OverlordMsg<Block>,
     ) -> Result<(), Box<dyn Error + Send>> {
         if let Some(sender) = self.talk_to.get(&address) {
@@ -171,8 +176,8 @@ impl Consensus<Block> for Adapter {
     fn report_view_change(
         &self,
         _ctx: Context,
-        _height: u64,
-        _round: u64,
+        _height: Height,
+        _round: Round,
         _reason: ViewChangeReason,
     ) {
     }
@@ -186,8 +191,8 @@ pub struct Participant {
 
 impl Participant {
     pub fn new(
-        address: &Bytes,
-        talk_to: HashMap<Bytes, Sender<OverlordMsg<Block>>>,
+        address: &Address,
+        talk_to: HashMap<Address, Sender<OverlordMsg<Block>>>,
         hearing: Receiver<OverlordMsg<Block>>,
         records: RecordInternal,
     ) -> Self {
@@ -210,9 +215,12 @@ impl Participant {
             .send_msg(
                 Context::new(),
                 OverlordMsg::RichStatus(Status {
-                    height: 1,
+                    height: Height(1),
                     interval: Some(records.interval),
                     timer_config: timer_config(),
+                    vrf_seed: None,
+                    next_authority_list: None,
+                    proof: None,
                     authority_list: records.node_record,
                 }),
             )
@@ -251,6 +259,20 @@ impl Participant {
                     OverlordMsg::SignedChoke(choke) => {
                         let _ = handler.send_msg(Context::new(), OverlordMsg::SignedChoke(choke));
                     }
+                    OverlordMsg::SignedCompactProposal(proposal) => {
+                        let _ = handler
+                            .send_msg(Context::new(), OverlordMsg::SignedCompactProposal(proposal));
+                    }
+                    OverlordMsg::BlockPart(part) => {
+                        let _ = handler.send_msg(Context::new(), OverlordMsg::BlockPart(part));
+                    }
+                    OverlordMsg::PullQC(pull) => {
+                        let _ = handler.send_msg(Context::new(), OverlordMsg::PullQC(pull));
+                    }
+                    OverlordMsg::RoundSummary(summary) => {
+                        let _ =
+                            handler.send_msg(Context::new(), OverlordMsg::RoundSummary(summary));
+                    }
                     OverlordMsg::Stop => {
                         break;
                     }
@@ -260,7 +282,7 @@ impl Participant {
         });
 
         self.overlord
-            .run(1, interval, node_list, timer_config)
+            .run(Height(1), interval, node_list, timer_config)
             .await
             .unwrap();
 
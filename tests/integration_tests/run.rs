@@ -3,11 +3,10 @@ use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
-use bytes::Bytes;
 use creep::Context;
 use crossbeam_channel::{unbounded, Receiver, Sender};
 
-use overlord::types::{Node, OverlordMsg, Status};
+use overlord::types::{Address, Height, Node, OverlordMsg, Status};
 
 use super::primitive::{Block, Channel, Participant};
 use super::utils::{get_max_alive_height, timer_config, to_hex, to_hex_strings};
@@ -83,7 +82,7 @@ fn run_alive_nodes(
     let alive_num = alive_nodes.len();
 
     let channels: Vec<Channel> = (0..alive_num).map(|_| unbounded()).collect();
-    let hearings: HashMap<Bytes, Receiver<OverlordMsg<Block>>> = alive_nodes
+    let hearings: HashMap<Address, Receiver<OverlordMsg<Block>>> = alive_nodes
         .iter()
         .map(|node| node.address.clone())
         .zip(channels.iter().map(|(_, receiver)| receiver.clone()))
@@ -92,7 +91,7 @@ fn run_alive_nodes(
     let mut alive_handlers = Vec::new();
     for node in alive_nodes.iter() {
         let address = node.address.clone();
-        let mut talk_to: HashMap<Bytes, Sender<OverlordMsg<Block>>> = alive_nodes
+        let mut talk_to: HashMap<Address, Sender<OverlordMsg<Block>>> = alive_nodes
             .iter()
             .map(|node| node.address.clone())
             .zip(channels.iter().map(|(sender, _)| sender.clone()))
@@ -126,7 +125,7 @@ fn synchronize_height(
     test_id: u64,
 ) {
     let interval = records.interval;
-    let height_record = Arc::<Mutex<HashMap<Bytes, u64>>>::clone(&records.height_record);
+    let height_record = Arc::<Mutex<HashMap<Address, u64>>>::clone(&records.height_record);
     let node_record = records.node_record.clone();
 
     tokio::spawn(async move {
@@ -137,7 +136,7 @@ fn synchronize_height(
             if *height < max_height {
                 alive_handlers
                     .iter()
-                    .filter(|node| node.adapter.address == address)
+                    .filter(|node| node.adapter.address == *address)
                     .for_each(|node| {
                         println!(
                             "Cycle {:?}, synchronize {:?} to node {:?} of height {:?}",
@@ -149,9 +148,12 @@ fn synchronize_height(
                         let _ = node.handler.send_msg(
                             Context::new(),
                             OverlordMsg::RichStatus(Status {
-                                height: max_height + 1,
+                                height: Height(max_height + 1),
                                 interval: Some(interval),
                                 timer_config: timer_config(),
+                                vrf_seed: None,
+                                next_authority_list: None,
+                                proof: None,
                                 authority_list: node_record.clone(),
                             }),
                         );
@@ -7,7 +7,7 @@ use hummer::coding::hex_encode;
 use lazy_static::lazy_static;
 use rand::{random, rng, seq::SliceRandom};
 
-use overlord::types::Node;
+use overlord::types::{Address, Hash, Node};
 use overlord::DurationConfig;
 
 lazy_static! {
@@ -19,10 +19,10 @@ pub fn gen_random_bytes() -> Bytes {
     Bytes::from(vec)
 }
 
-pub fn hash(bytes: &Bytes) -> Bytes {
+pub fn hash(bytes: &Bytes) -> Hash {
     let mut out = [0u8; 32];
     out.copy_from_slice(&HASHER_INST.digest(bytes));
-    BytesMut::from(&out[..]).freeze()
+    Hash::from(BytesMut::from(&out[..]).freeze())
 }
 
 pub fn timer_config() -> Option<DurationConfig> {
@@ -43,15 +43,15 @@ pub fn create_alive_nodes(nodes: Vec<Node>) -> Vec<Node> {
 }
 
 pub fn get_max_alive_height(
-    height_record: &Arc<Mutex<HashMap<Bytes, u64>>>,
+    height_record: &Arc<Mutex<HashMap<Address, u64>>>,
     alives: &[Node],
 ) -> u64 {
     let height_record = height_record.lock().unwrap();
     if let Some(max_height) = height_record
         .clone()
         .into_iter()
-        .filter(|(address, _)| alives.iter().any(|node| node.address == address))
-        .collect::<HashMap<Bytes, u64>>()
+        .filter(|(address, _)| alives.iter().any(|node| node.address == *address))
+        .collect::<HashMap<Address, u64>>()
         .values()
         .max()
     {
@@ -65,7 +65,7 @@ pub fn to_hex_strings(nodes: &[Node]) -> Vec<String> {
     nodes.iter().map(|node| hex_encode(&node.address)).collect()
 }
 
-pub fn to_hex(address: &Bytes) -> String {
+pub fn to_hex(address: &Address) -> String {
     hex_encode(address)
 }
 
@@ -78,10 +78,10 @@ pub fn get_index_array(nodes: &[Node], alives: &[Node]) -> Vec<usize> {
         .collect()
 }
 
-pub fn get_index(nodes: &[Node], address: &Bytes) -> usize {
+pub fn get_index(nodes: &[Node], address: &Address) -> usize {
     let mut index = usize::MAX;
     nodes.iter().enumerate().for_each(|(i, node)| {
-        if node.address == address {
+        if node.address == *address {
             index = i;
         }
     });
@@ -4,13 +4,13 @@ use std::fs::File;
 use std::io::BufReader;
 use std::sync::{Arc, Mutex};
 
-use alloy_rlp::Decodable;
 use async_trait::async_trait;
 use bytes::Bytes;
 use lru_cache::LruCache;
 use serde::{Deserialize, Serialize};
 
-use overlord::types::Node;
+use overlord::compat::decode_wal_info;
+use overlord::types::{Address, Hash, Node};
 use overlord::{Wal, WalInfo};
 
 use super::primitive::Block;
@@ -23,14 +23,14 @@ pub const RECORD_TMP_FILE: &str = "./tests/integration_tests/test.json";
 pub struct MockWal {
     test_id: u64,
     test_id_updated: Arc<Mutex<u64>>,
-    address: Bytes,
+    address: Address,
     content: Arc<Mutex<Option<Bytes>>>,
 }
 
 impl MockWal {
     pub fn new(
         test_id_updated: &Arc<Mutex<u64>>,
-        addr: Bytes,
+        addr: Address,
         content: &Arc<Mutex<Option<Bytes>>>,
     ) -> MockWal {
         MockWal {
@@ -60,7 +60,7 @@ impl Wal for MockWal {
     async fn load(&self) -> Result<Option<Bytes>, Box<dyn Error + Send>> {
         let info = self.content.lock().unwrap().as_ref().cloned();
         if let Some(info) = info.clone() {
-            let content: WalInfo<Block> = Decodable::decode(&mut info.as_ref()).unwrap();
+            let content = decode_wal_info::<Block>(&info).unwrap();
             println!("{:?} load {:?}", to_hex(&self.address), content);
         }
         Ok(info)
@@ -71,18 +71,20 @@ pub struct Record {
     pub test_id: Arc<Mutex<u64>>,
     pub node_record: Vec<Node>,
     pub alive_record: Mutex<Vec<Node>>,
-    pub wal_record: HashMap<Bytes, MockWal>,
-    pub commit_record: Arc<Mutex<LruCache<u64, Bytes>>>,
-    pub height_record: Arc<Mutex<HashMap<Bytes, u64>>>,
+    pub wal_record: HashMap<Address, MockWal>,
+    pub commit_record: Arc<Mutex<LruCache<u64, Hash>>>,
+    pub height_record: Arc<Mutex<HashMap<Address, u64>>>,
     pub interval: u64,
 }
 
 impl Record {
     pub fn new(num: usize, interval: u64) -> Record {
         let test_id = Arc::new(Mutex::new(0));
-        let node_record: Vec<Node> = (0..num).map(|_| Node::new(gen_random_bytes())).collect();
+        let node_record: Vec<Node> = (0..num)
+            .map(|_| Node::new(gen_random_bytes().into()))
+            .collect();
         let alive_record = Mutex::new(create_alive_nodes(node_record.clone()));
-        let wal_record: HashMap<Bytes, MockWal> = (0..num)
+        let wal_record: HashMap<Address, MockWal> = (0..num)
             .map(|i| {
                 let address = node_record.get(i).unwrap().address.clone();
                 (
@@ -95,10 +97,13 @@ impl Record {
                 )
             })
             .collect();
-        let commit_record: Arc<Mutex<LruCache<u64, Bytes>>> =
+        let commit_record: Arc<Mutex<LruCache<u64, Hash>>> =
             Arc::new(Mutex::new(LruCache::new(10)));
-        commit_record.lock().unwrap().insert(0, gen_random_bytes());
-        let height_record: Arc<Mutex<HashMap<Bytes, u64>>> = Arc::new(Mutex::new(
+        commit_record
+            .lock()
+            .unwrap()
+            .insert(0, gen_random_bytes().into());
+        let height_record: Arc<Mutex<HashMap<Address, u64>>> = Arc::new(Mutex::new(
             (0..num)
                 .map(|i| (node_record.get(i).unwrap().address.clone(), 0))
                 .collect(),
@@ -129,7 +134,7 @@ impl Record {
                         .lock()
                         .unwrap()
                         .as_ref()
-                        .map(|wal| bcs::from_bytes(wal).unwrap()),
+                        .map(|wal| decode_wal_info(wal).unwrap()),
                 )
             })
             .collect();
@@ -179,7 +184,7 @@ impl Record {
         let test_id_updated = Arc::<Mutex<u64>>::clone(&self.test_id);
         let node_record = self.node_record.clone();
         let alive_record = self.alive_record.lock().unwrap().clone();
-        let wal_record: HashMap<Bytes, MockWal> = self
+        let wal_record: HashMap<Address, MockWal> = self
             .wal_record
             .iter()
             .map(|(address, wal)| {
@@ -189,8 +194,8 @@ impl Record {
                 )
             })
             .collect();
-        let commit_record = Arc::<Mutex<LruCache<u64, Bytes>>>::clone(&self.commit_record);
-        let height_record = Arc::<Mutex<HashMap<Bytes, u64>>>::clone(&self.height_record);
+        let commit_record = Arc::<Mutex<LruCache<u64, Hash>>>::clone(&self.commit_record);
+        let height_record = Arc::<Mutex<HashMap<Address, u64>>>::clone(&self.height_record);
         let interval = self.interval;
 
         RecordInternal {
@@ -225,9 +230,9 @@ pub struct RecordInternal {
     pub test_id_updated: Arc<Mutex<u64>>,
     pub node_record: Vec<Node>,
     pub alive_record: Vec<Node>,
-    pub wal_record: HashMap<Bytes, MockWal>,
-    pub commit_record: Arc<Mutex<LruCache<u64, Bytes>>>,
-    pub height_record: Arc<Mutex<HashMap<Bytes, u64>>>,
+    pub wal_record: HashMap<Address, MockWal>,
+    pub commit_record: Arc<Mutex<LruCache<u64, Hash>>>,
+    pub height_record: Arc<Mutex<HashMap<Address, u64>>>,
     pub interval: u64,
 }
 
@@ -246,7 +251,7 @@ impl RecordInternal {
                         .lock()
                         .unwrap()
                         .as_ref()
-                        .map(|wal| bcs::from_bytes(wal).unwrap()),
+                        .map(|wal| decode_wal_info(wal).unwrap()),
                 )
             })
             .collect();
@@ -285,15 +290,15 @@ impl RecordInternal {
 
 #[derive(Serialize, Deserialize)]
 struct TupleWalRecord(
-    #[serde(with = "overlord::serde_hex")] Bytes,
+    #[serde(with = "overlord::serde_hex")] Address,
     Option<WalInfo<Block>>,
 );
 
 #[derive(Serialize, Deserialize, Clone)]
-struct TupleCommitRecord(u64, #[serde(with = "overlord::serde_hex")] Bytes);
+struct TupleCommitRecord(u64, #[serde(with = "overlord::serde_hex")] Hash);
 
 #[derive(Serialize, Deserialize, Clone)]
-struct TupleHeightRecord(#[serde(with = "overlord::serde_hex")] Bytes, u64);
+struct TupleHeightRecord(#[serde(with = "overlord::serde_hex")] Address, u64);
 
 #[derive(Serialize, Deserialize)]
 struct RecordForWal {
@@ -311,7 +316,7 @@ impl RecordForWal {
         let test_id = Arc::new(Mutex::new(self.test_id));
         let node_record = self.node_record.clone();
         let alive_record = Mutex::new(self.alive_record.clone());
-        let wal_record: HashMap<Bytes, MockWal> = self
+        let wal_record: HashMap<Address, MockWal> = self
             .wal_record
             .iter()
             .map(|TupleWalRecord(address, wal)| {
@@ -322,18 +327,18 @@ impl RecordForWal {
                         test_id_updated: Arc::clone(&test_id),
                         address: address.clone(),
                         content: Arc::new(Mutex::new(
-                            wal.as_ref()
-                                .map(|wal| Bytes::from(bcs::to_bytes(wal).unwrap())),
+                            // A record of version 0, which is still loadable.
+                            wal.as_ref().map(|wal| Bytes::from(alloy_rlp::encode(wal))),
                         )),
                     },
                 )
             })
             .collect();
-        let mut commit_record: LruCache<u64, Bytes> = LruCache::new(10);
+        let mut commit_record: LruCache<u64, Hash> = LruCache::new(10);
         for TupleCommitRecord(height, commit_hash) in self.commit_record.clone() {
             commit_record.insert(height, commit_hash);
         }
-        let height_record: HashMap<Bytes, u64> = self
+        let height_record: HashMap<Address, u64> = self
             .height_record
             .iter()
             .map(|TupleHeightRecord(address, height)| (address.clone(), *height))
@@ -7,7 +7,7 @@ use creep::Context;
 use crossbeam_channel::Sender;
 use overlord::error::ConsensusError;
 use overlord::types::{
-    Address, Commit, Hash, Node, OverlordMsg, Signature, Status, ViewChangeReason,
+    Address, Commit, Hash, Height, Node, OverlordMsg, Round, Signature, Status, ViewChangeReason,
 };
 use overlord::{Codec, Consensus, Crypto};
 use rand::random;
@@ -20,12 +20,12 @@ pub enum Approach {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 struct Pill {
-    height: u64,
+    height: Height,
     epoch: Vec<u64>,
 }
 
 impl Pill {
-    fn new(height: u64) -> Self {
+    fn new(height: Height) -> Self {
         let epoch = (0..128).map(|_| random::<u64>()).collect::<Vec<_>>();
         Pill { height, epoch }
     }
@@ -42,18 +42,18 @@ impl Consensus<Pill> for ConsensusHelper<Pill> {
     async fn get_block(
         &self,
         _ctx: Context,
-        height: u64,
+        height: Height,
     ) -> Result<(Pill, Hash), Box<dyn Error + Send>> {
         let epoch = Pill::new(height);
         let hash =
             BytesMut::from(blake2b(bcs::to_bytes(&epoch).unwrap().as_ref()).as_bytes()).freeze();
-        Ok((epoch, hash))
+        Ok((epoch, hash.into()))
     }
 
     async fn check_block(
         &self,
         _ctx: Context,
-        _height: u64,
+        _height: Height,
         _hash: Hash,
         _epoch: Pill,
     ) -> Result<(), Box<dyn Error + Send>> {
@@ -63,7 +63,7 @@ impl Consensus<Pill> for ConsensusHelper<Pill> {
     async fn commit(
         &self,
         _ctx: Context,
-        height: u64,
+        height: Height,
         commit: Commit<Pill>,
     ) -> Result<Status, Box<dyn Error + Send>> {
         self.commit_tx.send(commit).unwrap();
@@ -71,6 +71,9 @@ impl Consensus<Pill> for ConsensusHelper<Pill> {
             height: height + 1,
             interval: None,
             timer_config: None,
+            vrf_seed: None,
+            next_authority_list: None,
+            proof: None,
             authority_list: self.auth_list.clone(),
         };
         Ok(status)
@@ -79,7 +82,7 @@ impl Consensus<Pill> for ConsensusHelper<Pill> {
     async fn get_authority_list(
         &self,
         _ctx: Context,
-        _height: u64,
+        _height: Height,
     ) -> Result<Vec<Node>, Box<dyn Error + Send>> {
         Ok(self.auth_list.clone())
     }
@@ -118,8 +121,8 @@ impl Consensus<Pill> for ConsensusHelper<Pill> {
     fn report_view_change(
         &self,
         _ctx: Context,
-        _height: u64,
-        _round: u64,
+        _height: Height,
+        _round: Round,
         _reason: ViewChangeReason,
     ) {
     }
@@ -130,11 +133,11 @@ struct BlsCrypto(Address);
 
 impl Crypto for BlsCrypto {
     fn hash(&self, _msg: Bytes) -> Hash {
-        self.0.clone()
+        self.0.clone().into_bytes().into()
     }
 
     fn sign(&self, hash: Hash) -> Result<Signature, Box<dyn Error + Send>> {
-        Ok(hash)
+        Ok(hash.into_bytes().into())
     }
 
     fn verify_signature(
@@ -151,7 +154,7 @@ impl Crypto for BlsCrypto {
         _signatures: Vec<Signature>,
         _voters: Vec<Address>,
     ) -> Result<Signature, Box<dyn Error + Send>> {
-        Ok(gen_hash())
+        Ok(gen_hash().into_bytes().into())
     }
 
     fn verify_aggregated_signature(